@@ -109,7 +109,7 @@ pub trait ImageApi: Send + Sync {
     fn image_inspect(
         &self,
         name: &str,
-    ) -> Box<dyn Future<Item = crate::models::Image, Error = Error<serde_json::Value>>>;
+    ) -> Box<dyn Future<Item = crate::models::Image, Error = Error<serde_json::Value>> + Send>;
     fn image_list(
         &self,
         all: bool,
@@ -683,7 +683,7 @@ where
     fn image_inspect(
         &self,
         name: &str,
-    ) -> Box<dyn Future<Item = crate::models::Image, Error = Error<serde_json::Value>>> {
+    ) -> Box<dyn Future<Item = crate::models::Image, Error = Error<serde_json::Value>> + Send> {
         let configuration: &configuration::Configuration<C> = self.configuration.borrow();
 
         let method = hyper::Method::GET;
@@ -36,7 +36,7 @@ pub trait NetworkApi: Send + Sync {
         &self,
         id: &str,
         container: crate::models::Container,
-    ) -> Box<dyn Future<Item = (), Error = Error<serde_json::Value>>>;
+    ) -> Box<dyn Future<Item = (), Error = Error<serde_json::Value>> + Send>;
     fn network_create(
         &self,
         network_config: crate::models::NetworkConfig,
@@ -47,18 +47,18 @@ pub trait NetworkApi: Send + Sync {
     fn network_delete(
         &self,
         id: &str,
-    ) -> Box<dyn Future<Item = (), Error = Error<serde_json::Value>>>;
+    ) -> Box<dyn Future<Item = (), Error = Error<serde_json::Value>> + Send>;
     fn network_disconnect(
         &self,
         id: &str,
         container: crate::models::Container1,
-    ) -> Box<dyn Future<Item = (), Error = Error<serde_json::Value>>>;
+    ) -> Box<dyn Future<Item = (), Error = Error<serde_json::Value>> + Send>;
     fn network_inspect(
         &self,
         id: &str,
         verbose: bool,
         scope: &str,
-    ) -> Box<dyn Future<Item = crate::models::Network, Error = Error<serde_json::Value>>>;
+    ) -> Box<dyn Future<Item = crate::models::Network, Error = Error<serde_json::Value>> + Send>;
     fn network_list(
         &self,
         filters: &str,
@@ -79,7 +79,7 @@ where
         &self,
         id: &str,
         container: crate::models::Container,
-    ) -> Box<dyn Future<Item = (), Error = Error<serde_json::Value>>> {
+    ) -> Box<dyn Future<Item = (), Error = Error<serde_json::Value>> + Send> {
         let configuration: &configuration::Configuration<C> = self.configuration.borrow();
 
         let method = hyper::Method::POST;
@@ -195,7 +195,7 @@ where
     fn network_delete(
         &self,
         id: &str,
-    ) -> Box<dyn Future<Item = (), Error = Error<serde_json::Value>>> {
+    ) -> Box<dyn Future<Item = (), Error = Error<serde_json::Value>> + Send> {
         let configuration: &configuration::Configuration<C> = self.configuration.borrow();
 
         let method = hyper::Method::DELETE;
@@ -243,7 +243,7 @@ where
         &self,
         id: &str,
         container: crate::models::Container1,
-    ) -> Box<dyn Future<Item = (), Error = Error<serde_json::Value>>> {
+    ) -> Box<dyn Future<Item = (), Error = Error<serde_json::Value>> + Send> {
         let configuration: &configuration::Configuration<C> = self.configuration.borrow();
 
         let method = hyper::Method::POST;
@@ -299,7 +299,8 @@ where
         id: &str,
         verbose: bool,
         scope: &str,
-    ) -> Box<dyn Future<Item = crate::models::Network, Error = Error<serde_json::Value>>> {
+    ) -> Box<dyn Future<Item = crate::models::Network, Error = Error<serde_json::Value>> + Send>
+    {
         let configuration: &configuration::Configuration<C> = self.configuration.borrow();
 
         let method = hyper::Method::GET;
@@ -35,7 +35,7 @@ pub trait VolumeApi: Send + Sync {
     fn volume_create(
         &self,
         volume_config: crate::models::VolumeConfig,
-    ) -> Box<dyn Future<Item = crate::models::Volume, Error = Error<serde_json::Value>>>;
+    ) -> Box<dyn Future<Item = crate::models::Volume, Error = Error<serde_json::Value>> + Send>;
     fn volume_delete(
         &self,
         name: &str,
@@ -48,7 +48,10 @@ pub trait VolumeApi: Send + Sync {
     fn volume_list(
         &self,
         filters: &str,
-    ) -> Box<dyn Future<Item = crate::models::InlineResponse20015, Error = Error<serde_json::Value>>>;
+    ) -> Box<
+        dyn Future<Item = crate::models::InlineResponse20015, Error = Error<serde_json::Value>>
+            + Send,
+    >;
     fn volume_prune(
         &self,
         filters: &str,
@@ -64,7 +67,8 @@ where
     fn volume_create(
         &self,
         volume_config: crate::models::VolumeConfig,
-    ) -> Box<dyn Future<Item = crate::models::Volume, Error = Error<serde_json::Value>>> {
+    ) -> Box<dyn Future<Item = crate::models::Volume, Error = Error<serde_json::Value>> + Send>
+    {
         let configuration: &configuration::Configuration<C> = self.configuration.borrow();
 
         let method = hyper::Method::POST;
@@ -222,8 +226,10 @@ where
     fn volume_list(
         &self,
         filters: &str,
-    ) -> Box<dyn Future<Item = crate::models::InlineResponse20015, Error = Error<serde_json::Value>>>
-    {
+    ) -> Box<
+        dyn Future<Item = crate::models::InlineResponse20015, Error = Error<serde_json::Value>>
+            + Send,
+    > {
         let configuration: &configuration::Configuration<C> = self.configuration.borrow();
 
         let method = hyper::Method::GET;
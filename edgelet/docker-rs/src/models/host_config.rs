@@ -90,12 +90,12 @@ pub struct HostConfig {
     // /// Memory nodes (MEMs) in which to allow execution (0-3, 0,1). Only effective on NUMA systems.
     // #[serde(rename = "CpusetMems", skip_serializing_if = "Option::is_none")]
     // cpuset_mems: Option<String>,
-    // /// A list of devices to add to the container.
-    // #[serde(rename = "Devices", skip_serializing_if = "Option::is_none")]
-    // devices: Option<Vec<crate::models::DeviceMapping>>,
-    // /// a list of cgroup rules to apply to the container
-    // #[serde(rename = "DeviceCgroupRules", skip_serializing_if = "Option::is_none")]
-    // device_cgroup_rules: Option<Vec<String>>,
+    /// A list of devices to add to the container.
+    #[serde(rename = "Devices", skip_serializing_if = "Option::is_none")]
+    devices: Option<Vec<crate::models::DeviceMapping>>,
+    /// a list of cgroup rules to apply to the container
+    #[serde(rename = "DeviceCgroupRules", skip_serializing_if = "Option::is_none")]
+    device_cgroup_rules: Option<Vec<String>>,
     // /// Disk limit (in bytes).
     // #[serde(rename = "DiskQuota", skip_serializing_if = "Option::is_none")]
     // disk_quota: Option<i64>,
@@ -143,9 +143,9 @@ pub struct HostConfig {
     // container_id_file: Option<String>,
     // #[serde(rename = "LogConfig", skip_serializing_if = "Option::is_none")]
     // log_config: Option<crate::models::HostConfigLogConfig>,
-    // /// Network mode to use for this container. Supported standard values are: `bridge`, `host`, `none`, and `container:<name|id>`. Any other value is taken as a custom network's name to which this container should connect to.
-    // #[serde(rename = "NetworkMode", skip_serializing_if = "Option::is_none")]
-    // network_mode: Option<String>,
+    /// Network mode to use for this container. Supported standard values are: `bridge`, `host`, `none`, and `container:<name|id>`. Any other value is taken as a custom network's name to which this container should connect to.
+    #[serde(rename = "NetworkMode", skip_serializing_if = "Option::is_none")]
+    network_mode: Option<String>,
     /// A map of exposed container ports and the host port they should map to.
     #[serde(rename = "PortBindings", skip_serializing_if = "Option::is_none")]
     port_bindings:
@@ -158,15 +158,15 @@ pub struct HostConfig {
     // /// Driver that this container uses to mount volumes.
     // #[serde(rename = "VolumeDriver", skip_serializing_if = "Option::is_none")]
     // volume_driver: Option<String>,
-    // /// A list of volumes to inherit from another container, specified in the form `<container name>[:<ro|rw>]`.
-    // #[serde(rename = "VolumesFrom", skip_serializing_if = "Option::is_none")]
-    // volumes_from: Option<Vec<String>>,
+    /// A list of volumes to inherit from another container, specified in the form `<container name>[:<ro|rw>]`.
+    #[serde(rename = "VolumesFrom", skip_serializing_if = "Option::is_none")]
+    volumes_from: Option<Vec<String>>,
     /// Specification for mounts to be added to the container.
     #[serde(rename = "Mounts", skip_serializing_if = "Option::is_none")]
     mounts: Option<Vec<crate::models::Mount>>,
-    // /// A list of kernel capabilities to add to the container.
-    // #[serde(rename = "CapAdd", skip_serializing_if = "Option::is_none")]
-    // cap_add: Option<Vec<String>>,
+    /// A list of kernel capabilities to add to the container.
+    #[serde(rename = "CapAdd", skip_serializing_if = "Option::is_none")]
+    cap_add: Option<Vec<String>>,
     // /// A list of kernel capabilities to drop from the container.
     // #[serde(rename = "CapDrop", skip_serializing_if = "Option::is_none")]
     // cap_drop: Option<Vec<String>>,
@@ -185,9 +185,9 @@ pub struct HostConfig {
     // /// A list of additional groups that the container process will run as.
     // #[serde(rename = "GroupAdd", skip_serializing_if = "Option::is_none")]
     // group_add: Option<Vec<String>>,
-    // /// IPC sharing mode for the container. Possible values are:  - `\"none\"`: own private IPC namespace, with /dev/shm not mounted - `\"private\"`: own private IPC namespace - `\"shareable\"`: own private IPC namespace, with a possibility to share it with other containers - `\"container:<name|id>\"`: join another (shareable) container's IPC namespace - `\"host\"`: use the host system's IPC namespace  If not specified, daemon default is used, which can either be `\"private\"` or `\"shareable\"`, depending on daemon version and configuration.
-    // #[serde(rename = "IpcMode", skip_serializing_if = "Option::is_none")]
-    // ipc_mode: Option<String>,
+    /// IPC sharing mode for the container. Possible values are:  - `\"none\"`: own private IPC namespace, with /dev/shm not mounted - `\"private\"`: own private IPC namespace - `\"shareable\"`: own private IPC namespace, with a possibility to share it with other containers - `\"container:<name|id>\"`: join another (shareable) container's IPC namespace - `\"host\"`: use the host system's IPC namespace  If not specified, daemon default is used, which can either be `\"private\"` or `\"shareable\"`, depending on daemon version and configuration.
+    #[serde(rename = "IpcMode", skip_serializing_if = "Option::is_none")]
+    ipc_mode: Option<String>,
     // /// Cgroup to use for the container.
     // #[serde(rename = "Cgroup", skip_serializing_if = "Option::is_none")]
     // cgroup: Option<String>,
@@ -197,9 +197,9 @@ pub struct HostConfig {
     // /// An integer value containing the score given to the container in order to tune OOM killer preferences.
     // #[serde(rename = "OomScoreAdj", skip_serializing_if = "Option::is_none")]
     // oom_score_adj: Option<i32>,
-    // /// Set the PID (Process) Namespace mode for the container. It can be either:  - `\"container:<name|id>\"`: joins another container's PID namespace - `\"host\"`: use the host's PID namespace inside the container
-    // #[serde(rename = "PidMode", skip_serializing_if = "Option::is_none")]
-    // pid_mode: Option<String>,
+    /// Set the PID (Process) Namespace mode for the container. It can be either:  - `\"container:<name|id>\"`: joins another container's PID namespace - `\"host\"`: use the host's PID namespace inside the container
+    #[serde(rename = "PidMode", skip_serializing_if = "Option::is_none")]
+    pid_mode: Option<String>,
     /// Gives the container full access to the host.
     #[serde(rename = "Privileged", skip_serializing_if = "Option::is_none")]
     privileged: Option<bool>,
@@ -209,9 +209,9 @@ pub struct HostConfig {
     // /// Mount the container's root filesystem as read only.
     // #[serde(rename = "ReadonlyRootfs", skip_serializing_if = "Option::is_none")]
     // readonly_rootfs: Option<bool>,
-    // /// A list of string values to customize labels for MLS systems, such as SELinux.
-    // #[serde(rename = "SecurityOpt", skip_serializing_if = "Option::is_none")]
-    // security_opt: Option<Vec<String>>,
+    /// A list of string values to customize labels for MLS systems, such as SELinux.
+    #[serde(rename = "SecurityOpt", skip_serializing_if = "Option::is_none")]
+    security_opt: Option<Vec<String>>,
     // /// Storage driver options for this container, in the form `{\"size\": \"120G\"}`.
     // #[serde(rename = "StorageOpt", skip_serializing_if = "Option::is_none")]
     // storage_opt: Option<::std::collections::HashMap<String, String>>,
@@ -221,9 +221,9 @@ pub struct HostConfig {
     // /// UTS namespace to use for the container.
     // #[serde(rename = "UTSMode", skip_serializing_if = "Option::is_none")]
     // uts_mode: Option<String>,
-    // /// Sets the usernamespace mode for the container when usernamespace remapping option is enabled.
-    // #[serde(rename = "UsernsMode", skip_serializing_if = "Option::is_none")]
-    // userns_mode: Option<String>,
+    /// Sets the usernamespace mode for the container when usernamespace remapping option is enabled.
+    #[serde(rename = "UsernsMode", skip_serializing_if = "Option::is_none")]
+    userns_mode: Option<String>,
     // /// Size of `/dev/shm` in bytes. If omitted, the system uses 64MB.
     // #[serde(rename = "ShmSize", skip_serializing_if = "Option::is_none")]
     // shm_size: Option<i64>,
@@ -262,8 +262,8 @@ impl HostConfig {
             // cpu_realtime_runtime: None,
             // cpuset_cpus: None,
             // cpuset_mems: None,
-            // devices: None,
-            // device_cgroup_rules: None,
+            devices: None,
+            device_cgroup_rules: None,
             // disk_quota: None,
             // kernel_memory: None,
             // memory_reservation: None,
@@ -280,33 +280,33 @@ impl HostConfig {
             binds: None,
             // container_id_file: None,
             // log_config: None,
-            // network_mode: None,
+            network_mode: None,
             port_bindings: None,
             // restart_policy: None,
             // auto_remove: None,
             // volume_driver: None,
-            // volumes_from: None,
+            volumes_from: None,
             mounts: None,
-            // cap_add: None,
+            cap_add: None,
             // cap_drop: None,
             // dns: None,
             // dns_options: None,
             // dns_search: None,
             // extra_hosts: None,
             // group_add: None,
-            // ipc_mode: None,
+            ipc_mode: None,
             // cgroup: None,
             // links: None,
             // oom_score_adj: None,
-            // pid_mode: None,
+            pid_mode: None,
             privileged: None,
             // publish_all_ports: None,
             // readonly_rootfs: None,
-            // security_opt: None,
+            security_opt: None,
             // storage_opt: None,
             // tmpfs: None,
             // uts_mode: None,
-            // userns_mode: None,
+            userns_mode: None,
             // shm_size: None,
             // sysctls: None,
             // runtime: None,
@@ -601,39 +601,39 @@ impl HostConfig {
     //     self.cpuset_mems = None;
     // }
 
-    // pub fn set_devices(&mut self, devices: Vec<crate::models::DeviceMapping>) {
-    //     self.devices = Some(devices);
-    // }
+    pub fn set_devices(&mut self, devices: Vec<crate::models::DeviceMapping>) {
+        self.devices = Some(devices);
+    }
 
-    // pub fn with_devices(mut self, devices: Vec<crate::models::DeviceMapping>) -> Self {
-    //     self.devices = Some(devices);
-    //     self
-    // }
+    pub fn with_devices(mut self, devices: Vec<crate::models::DeviceMapping>) -> Self {
+        self.devices = Some(devices);
+        self
+    }
 
-    // pub fn devices(&self) -> Option<&[crate::models::DeviceMapping]> {
-    //     self.devices.as_ref().map(AsRef::as_ref)
-    // }
+    pub fn devices(&self) -> Option<&[crate::models::DeviceMapping]> {
+        self.devices.as_ref().map(AsRef::as_ref)
+    }
 
-    // pub fn reset_devices(&mut self) {
-    //     self.devices = None;
-    // }
+    pub fn reset_devices(&mut self) {
+        self.devices = None;
+    }
 
-    // pub fn set_device_cgroup_rules(&mut self, device_cgroup_rules: Vec<String>) {
-    //     self.device_cgroup_rules = Some(device_cgroup_rules);
-    // }
+    pub fn set_device_cgroup_rules(&mut self, device_cgroup_rules: Vec<String>) {
+        self.device_cgroup_rules = Some(device_cgroup_rules);
+    }
 
-    // pub fn with_device_cgroup_rules(mut self, device_cgroup_rules: Vec<String>) -> Self {
-    //     self.device_cgroup_rules = Some(device_cgroup_rules);
-    //     self
-    // }
+    pub fn with_device_cgroup_rules(mut self, device_cgroup_rules: Vec<String>) -> Self {
+        self.device_cgroup_rules = Some(device_cgroup_rules);
+        self
+    }
 
-    // pub fn device_cgroup_rules(&self) -> Option<&[String]> {
-    //     self.device_cgroup_rules.as_ref().map(AsRef::as_ref)
-    // }
+    pub fn device_cgroup_rules(&self) -> Option<&[String]> {
+        self.device_cgroup_rules.as_ref().map(AsRef::as_ref)
+    }
 
-    // pub fn reset_device_cgroup_rules(&mut self) {
-    //     self.device_cgroup_rules = None;
-    // }
+    pub fn reset_device_cgroup_rules(&mut self) {
+        self.device_cgroup_rules = None;
+    }
 
     // pub fn set_disk_quota(&mut self, disk_quota: i64) {
     //     self.disk_quota = Some(disk_quota);
@@ -907,22 +907,22 @@ impl HostConfig {
     //     self.log_config = None;
     // }
 
-    // pub fn set_network_mode(&mut self, network_mode: String) {
-    //     self.network_mode = Some(network_mode);
-    // }
+    pub fn set_network_mode(&mut self, network_mode: String) {
+        self.network_mode = Some(network_mode);
+    }
 
-    // pub fn with_network_mode(mut self, network_mode: String) -> Self {
-    //     self.network_mode = Some(network_mode);
-    //     self
-    // }
+    pub fn with_network_mode(mut self, network_mode: String) -> Self {
+        self.network_mode = Some(network_mode);
+        self
+    }
 
-    // pub fn network_mode(&self) -> Option<&str> {
-    //     self.network_mode.as_ref().map(AsRef::as_ref)
-    // }
+    pub fn network_mode(&self) -> Option<&str> {
+        self.network_mode.as_ref().map(AsRef::as_ref)
+    }
 
-    // pub fn reset_network_mode(&mut self) {
-    //     self.network_mode = None;
-    // }
+    pub fn reset_network_mode(&mut self) {
+        self.network_mode = None;
+    }
 
     pub fn set_port_bindings(
         &mut self,
@@ -1007,22 +1007,22 @@ impl HostConfig {
     //     self.volume_driver = None;
     // }
 
-    // pub fn set_volumes_from(&mut self, volumes_from: Vec<String>) {
-    //     self.volumes_from = Some(volumes_from);
-    // }
+    pub fn set_volumes_from(&mut self, volumes_from: Vec<String>) {
+        self.volumes_from = Some(volumes_from);
+    }
 
-    // pub fn with_volumes_from(mut self, volumes_from: Vec<String>) -> Self {
-    //     self.volumes_from = Some(volumes_from);
-    //     self
-    // }
+    pub fn with_volumes_from(mut self, volumes_from: Vec<String>) -> Self {
+        self.volumes_from = Some(volumes_from);
+        self
+    }
 
-    // pub fn volumes_from(&self) -> Option<&[String]> {
-    //     self.volumes_from.as_ref().map(AsRef::as_ref)
-    // }
+    pub fn volumes_from(&self) -> Option<&[String]> {
+        self.volumes_from.as_ref().map(AsRef::as_ref)
+    }
 
-    // pub fn reset_volumes_from(&mut self) {
-    //     self.volumes_from = None;
-    // }
+    pub fn reset_volumes_from(&mut self) {
+        self.volumes_from = None;
+    }
 
     pub fn set_mounts(&mut self, mounts: Vec<crate::models::Mount>) {
         self.mounts = Some(mounts);
@@ -1041,22 +1041,22 @@ impl HostConfig {
         self.mounts = None;
     }
 
-    // pub fn set_cap_add(&mut self, cap_add: Vec<String>) {
-    //     self.cap_add = Some(cap_add);
-    // }
+    pub fn set_cap_add(&mut self, cap_add: Vec<String>) {
+        self.cap_add = Some(cap_add);
+    }
 
-    // pub fn with_cap_add(mut self, cap_add: Vec<String>) -> Self {
-    //     self.cap_add = Some(cap_add);
-    //     self
-    // }
+    pub fn with_cap_add(mut self, cap_add: Vec<String>) -> Self {
+        self.cap_add = Some(cap_add);
+        self
+    }
 
-    // pub fn cap_add(&self) -> Option<&[String]> {
-    //     self.cap_add.as_ref().map(AsRef::as_ref)
-    // }
+    pub fn cap_add(&self) -> Option<&[String]> {
+        self.cap_add.as_ref().map(AsRef::as_ref)
+    }
 
-    // pub fn reset_cap_add(&mut self) {
-    //     self.cap_add = None;
-    // }
+    pub fn reset_cap_add(&mut self) {
+        self.cap_add = None;
+    }
 
     // pub fn set_cap_drop(&mut self, cap_drop: Vec<String>) {
     //     self.cap_drop = Some(cap_drop);
@@ -1160,22 +1160,22 @@ impl HostConfig {
     //     self.group_add = None;
     // }
 
-    // pub fn set_ipc_mode(&mut self, ipc_mode: String) {
-    //     self.ipc_mode = Some(ipc_mode);
-    // }
+    pub fn set_ipc_mode(&mut self, ipc_mode: String) {
+        self.ipc_mode = Some(ipc_mode);
+    }
 
-    // pub fn with_ipc_mode(mut self, ipc_mode: String) -> Self {
-    //     self.ipc_mode = Some(ipc_mode);
-    //     self
-    // }
+    pub fn with_ipc_mode(mut self, ipc_mode: String) -> Self {
+        self.ipc_mode = Some(ipc_mode);
+        self
+    }
 
-    // pub fn ipc_mode(&self) -> Option<&str> {
-    //     self.ipc_mode.as_ref().map(AsRef::as_ref)
-    // }
+    pub fn ipc_mode(&self) -> Option<&str> {
+        self.ipc_mode.as_ref().map(AsRef::as_ref)
+    }
 
-    // pub fn reset_ipc_mode(&mut self) {
-    //     self.ipc_mode = None;
-    // }
+    pub fn reset_ipc_mode(&mut self) {
+        self.ipc_mode = None;
+    }
 
     // pub fn set_cgroup(&mut self, cgroup: String) {
     //     self.cgroup = Some(cgroup);
@@ -1228,22 +1228,22 @@ impl HostConfig {
     //     self.oom_score_adj = None;
     // }
 
-    // pub fn set_pid_mode(&mut self, pid_mode: String) {
-    //     self.pid_mode = Some(pid_mode);
-    // }
+    pub fn set_pid_mode(&mut self, pid_mode: String) {
+        self.pid_mode = Some(pid_mode);
+    }
 
-    // pub fn with_pid_mode(mut self, pid_mode: String) -> Self {
-    //     self.pid_mode = Some(pid_mode);
-    //     self
-    // }
+    pub fn with_pid_mode(mut self, pid_mode: String) -> Self {
+        self.pid_mode = Some(pid_mode);
+        self
+    }
 
-    // pub fn pid_mode(&self) -> Option<&str> {
-    //     self.pid_mode.as_ref().map(AsRef::as_ref)
-    // }
+    pub fn pid_mode(&self) -> Option<&str> {
+        self.pid_mode.as_ref().map(AsRef::as_ref)
+    }
 
-    // pub fn reset_pid_mode(&mut self) {
-    //     self.pid_mode = None;
-    // }
+    pub fn reset_pid_mode(&mut self) {
+        self.pid_mode = None;
+    }
 
     pub fn set_privileged(&mut self, privileged: bool) {
         self.privileged = Some(privileged);
@@ -1296,22 +1296,22 @@ impl HostConfig {
     //     self.readonly_rootfs = None;
     // }
 
-    // pub fn set_security_opt(&mut self, security_opt: Vec<String>) {
-    //     self.security_opt = Some(security_opt);
-    // }
+    pub fn set_security_opt(&mut self, security_opt: Vec<String>) {
+        self.security_opt = Some(security_opt);
+    }
 
-    // pub fn with_security_opt(mut self, security_opt: Vec<String>) -> Self {
-    //     self.security_opt = Some(security_opt);
-    //     self
-    // }
+    pub fn with_security_opt(mut self, security_opt: Vec<String>) -> Self {
+        self.security_opt = Some(security_opt);
+        self
+    }
 
-    // pub fn security_opt(&self) -> Option<&[String]> {
-    //     self.security_opt.as_ref().map(AsRef::as_ref)
-    // }
+    pub fn security_opt(&self) -> Option<&[String]> {
+        self.security_opt.as_ref().map(AsRef::as_ref)
+    }
 
-    // pub fn reset_security_opt(&mut self) {
-    //     self.security_opt = None;
-    // }
+    pub fn reset_security_opt(&mut self) {
+        self.security_opt = None;
+    }
 
     // pub fn set_storage_opt(&mut self, storage_opt: ::std::collections::HashMap<String, String>) {
     //     self.storage_opt = Some(storage_opt);
@@ -1367,22 +1367,22 @@ impl HostConfig {
     //     self.uts_mode = None;
     // }
 
-    // pub fn set_userns_mode(&mut self, userns_mode: String) {
-    //     self.userns_mode = Some(userns_mode);
-    // }
+    pub fn set_userns_mode(&mut self, userns_mode: String) {
+        self.userns_mode = Some(userns_mode);
+    }
 
-    // pub fn with_userns_mode(mut self, userns_mode: String) -> Self {
-    //     self.userns_mode = Some(userns_mode);
-    //     self
-    // }
+    pub fn with_userns_mode(mut self, userns_mode: String) -> Self {
+        self.userns_mode = Some(userns_mode);
+        self
+    }
 
-    // pub fn userns_mode(&self) -> Option<&str> {
-    //     self.userns_mode.as_ref().map(AsRef::as_ref)
-    // }
+    pub fn userns_mode(&self) -> Option<&str> {
+        self.userns_mode.as_ref().map(AsRef::as_ref)
+    }
 
-    // pub fn reset_userns_mode(&mut self) {
-    //     self.userns_mode = None;
-    // }
+    pub fn reset_userns_mode(&mut self) {
+        self.userns_mode = None;
+    }
 
     // pub fn set_shm_size(&mut self, shm_size: i64) {
     //     self.shm_size = Some(shm_size);
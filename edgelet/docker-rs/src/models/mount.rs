@@ -51,8 +51,8 @@ pub struct Mount {
     // consistency: Option<String>,
     // #[serde(rename = "BindOptions", skip_serializing_if = "Option::is_none")]
     // bind_options: Option<crate::models::MountBindOptions>,
-    // #[serde(rename = "VolumeOptions", skip_serializing_if = "Option::is_none")]
-    // volume_options: Option<crate::models::MountVolumeOptions>,
+    #[serde(rename = "VolumeOptions", skip_serializing_if = "Option::is_none")]
+    volume_options: Option<crate::models::MountVolumeOptions>,
     // #[serde(rename = "TmpfsOptions", skip_serializing_if = "Option::is_none")]
     // tmpfs_options: Option<crate::models::MountTmpfsOptions>,
     #[serde(flatten)]
@@ -68,7 +68,7 @@ impl Mount {
             read_only: None,
             // consistency: None,
             // bind_options: None,
-            // volume_options: None,
+            volume_options: None,
             // tmpfs_options: None,
             other_properties: Default::default(),
         }
@@ -176,25 +176,25 @@ impl Mount {
     //     self.bind_options = None;
     // }
 
-    // pub fn set_volume_options(&mut self, volume_options: crate::models::MountVolumeOptions) {
-    //     self.volume_options = Some(volume_options);
-    // }
+    pub fn set_volume_options(&mut self, volume_options: crate::models::MountVolumeOptions) {
+        self.volume_options = Some(volume_options);
+    }
 
-    // pub fn with_volume_options(
-    //     mut self,
-    //     volume_options: crate::models::MountVolumeOptions,
-    // ) -> Self {
-    //     self.volume_options = Some(volume_options);
-    //     self
-    // }
+    pub fn with_volume_options(
+        mut self,
+        volume_options: crate::models::MountVolumeOptions,
+    ) -> Self {
+        self.volume_options = Some(volume_options);
+        self
+    }
 
-    // pub fn volume_options(&self) -> Option<&crate::models::MountVolumeOptions> {
-    //     self.volume_options.as_ref()
-    // }
+    pub fn volume_options(&self) -> Option<&crate::models::MountVolumeOptions> {
+        self.volume_options.as_ref()
+    }
 
-    // pub fn reset_volume_options(&mut self) {
-    //     self.volume_options = None;
-    // }
+    pub fn reset_volume_options(&mut self) {
+        self.volume_options = None;
+    }
 
     // pub fn set_tmpfs_options(&mut self, tmpfs_options: crate::models::MountTmpfsOptions) {
     //     self.tmpfs_options = Some(tmpfs_options);
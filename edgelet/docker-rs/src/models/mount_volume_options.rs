@@ -13,61 +13,85 @@
 #[allow(unused_imports)]
 use serde_json::Value;
 
+// DEVNOTE: Why is most of this type commented out?
+//
+// We do not want to restrict the properties that the user can set in their create options, because future versions of Docker can add new properties
+// that we don't define here.
+//
+// So this type has a `#[serde(flatten)] HashMap` field to collect all the extra properties that we don't have a struct field for.
+//
+// But if an existing field references another type under `crate::models::`, then that would still be parsed lossily, so we would have to also add
+// a `#[serde(flatten)] HashMap` field there. And if that type has fields that reference types under `crate::models::` ...
+//
+// To avoid having to do this for effectively the whole crate, instead we've just commented out the fields we don't use in our code.
+//
+// ---
+//
+// If you need to access a commented out field, uncomment it.
+//
+// - If it's a simple built-in type, then that is all you need to do.
+//
+// - Otherwise if it references another type under `crate::models::`, then ensure that that type also has a `#[serde(flatten)] HashMap` property
+//   and is commented out as much as possible. Also copy this devnote there for future readers.
+
 #[derive(Debug, serde_derive::Serialize, serde_derive::Deserialize, Clone)]
 pub struct MountVolumeOptions {
-    /// Populate volume with data from the target.
-    #[serde(rename = "NoCopy", skip_serializing_if = "Option::is_none")]
-    no_copy: Option<bool>,
-    /// User-defined key/value metadata.
-    #[serde(rename = "Labels", skip_serializing_if = "Option::is_none")]
-    labels: Option<::std::collections::HashMap<String, String>>,
+    // /// Populate volume with data from the target.
+    // #[serde(rename = "NoCopy", skip_serializing_if = "Option::is_none")]
+    // no_copy: Option<bool>,
+    // /// User-defined key/value metadata.
+    // #[serde(rename = "Labels", skip_serializing_if = "Option::is_none")]
+    // labels: Option<::std::collections::HashMap<String, String>>,
     #[serde(rename = "DriverConfig", skip_serializing_if = "Option::is_none")]
     driver_config: Option<crate::models::MountVolumeOptionsDriverConfig>,
+    #[serde(flatten)]
+    other_properties: std::collections::HashMap<String, serde_json::Value>,
 }
 
 impl MountVolumeOptions {
     /// Optional configuration for the `volume` type.
     pub fn new() -> Self {
         MountVolumeOptions {
-            no_copy: None,
-            labels: None,
+            // no_copy: None,
+            // labels: None,
             driver_config: None,
+            other_properties: Default::default(),
         }
     }
 
-    pub fn set_no_copy(&mut self, no_copy: bool) {
-        self.no_copy = Some(no_copy);
-    }
+    // pub fn set_no_copy(&mut self, no_copy: bool) {
+    //     self.no_copy = Some(no_copy);
+    // }
 
-    pub fn with_no_copy(mut self, no_copy: bool) -> Self {
-        self.no_copy = Some(no_copy);
-        self
-    }
+    // pub fn with_no_copy(mut self, no_copy: bool) -> Self {
+    //     self.no_copy = Some(no_copy);
+    //     self
+    // }
 
-    pub fn no_copy(&self) -> Option<&bool> {
-        self.no_copy.as_ref()
-    }
+    // pub fn no_copy(&self) -> Option<&bool> {
+    //     self.no_copy.as_ref()
+    // }
 
-    pub fn reset_no_copy(&mut self) {
-        self.no_copy = None;
-    }
+    // pub fn reset_no_copy(&mut self) {
+    //     self.no_copy = None;
+    // }
 
-    pub fn set_labels(&mut self, labels: ::std::collections::HashMap<String, String>) {
-        self.labels = Some(labels);
-    }
+    // pub fn set_labels(&mut self, labels: ::std::collections::HashMap<String, String>) {
+    //     self.labels = Some(labels);
+    // }
 
-    pub fn with_labels(mut self, labels: ::std::collections::HashMap<String, String>) -> Self {
-        self.labels = Some(labels);
-        self
-    }
+    // pub fn with_labels(mut self, labels: ::std::collections::HashMap<String, String>) -> Self {
+    //     self.labels = Some(labels);
+    //     self
+    // }
 
-    pub fn labels(&self) -> Option<&::std::collections::HashMap<String, String>> {
-        self.labels.as_ref()
-    }
+    // pub fn labels(&self) -> Option<&::std::collections::HashMap<String, String>> {
+    //     self.labels.as_ref()
+    // }
 
-    pub fn reset_labels(&mut self) {
-        self.labels = None;
-    }
+    // pub fn reset_labels(&mut self) {
+    //     self.labels = None;
+    // }
 
     pub fn set_driver_config(
         &mut self,
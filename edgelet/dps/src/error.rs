@@ -7,12 +7,14 @@ use failure::{Backtrace, Context, Fail};
 
 use edgelet_http::{Error as HttpError, ErrorKind as HttpErrorKind};
 
+use crate::state::RegistrationFailure;
+
 #[derive(Debug)]
 pub struct Error {
     inner: Context<ErrorKind>,
 }
 
-#[derive(Clone, Copy, Debug, Fail)]
+#[derive(Clone, Debug, Fail)]
 pub enum ErrorKind {
     #[fail(display = "Could not get device registration result")]
     GetDeviceRegistrationResult,
@@ -38,6 +40,9 @@ pub enum ErrorKind {
     #[fail(display = "Could not get TPM challenge key because the TPM token is invalid")]
     InvalidTpmToken,
 
+    #[fail(display = "Could not load the DPS registration state")]
+    LoadRegistrationState,
+
     #[fail(display = "DPS registration failed")]
     RegisterWithAuthUnexpectedlyFailed,
 
@@ -52,6 +57,15 @@ pub enum ErrorKind {
 
     #[fail(display = "X509 certificate based registration failed")]
     RegisterWithX509IdentityCertificate,
+
+    #[fail(display = "DPS registration failed: {}", _0)]
+    RegistrationFailed(RegistrationFailure),
+
+    #[fail(display = "Could not wait before retrying the DPS request")]
+    RetryTimer,
+
+    #[fail(display = "Could not save the DPS registration state")]
+    SaveRegistrationState,
 }
 
 impl Fail for Error {
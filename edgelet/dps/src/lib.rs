@@ -12,6 +12,7 @@ pub mod dps;
 pub mod error;
 mod model;
 pub mod registration;
+mod state;
 
 pub use error::{Error, ErrorKind};
 pub use model::{
@@ -19,5 +20,9 @@ pub use model::{
     TpmRegistrationResult, X509CertificateInfo, X509RegistrationResult,
 };
 pub use registration::{DpsClient, DpsTokenSource};
+pub use state::{AttestationKind, RegistrationFailure, RegistrationState};
 
 pub const DPS_API_VERSION: &str = "2018-11-01";
+
+/// The name of the file that keeps the state of the DPS registration
+pub const DPS_REGISTRATION_STATE_FILENAME: &str = "dps_registration.json";
@@ -23,6 +23,8 @@ pub struct DeviceRegistration {
     registration_id: Option<String>,
     #[serde(rename = "tpm", skip_serializing_if = "Option::is_none")]
     tpm: Option<TpmAttestation>,
+    #[serde(rename = "payload", skip_serializing_if = "Option::is_none")]
+    payload: Option<Value>,
 }
 
 impl DeviceRegistration {
@@ -31,6 +33,7 @@ impl DeviceRegistration {
         DeviceRegistration {
             registration_id: None,
             tpm: None,
+            payload: None,
         }
     }
 
@@ -67,6 +70,23 @@ impl DeviceRegistration {
     pub fn reset_tpm(&mut self) {
         self.tpm = None;
     }
+
+    pub fn set_payload(&mut self, payload: Value) {
+        self.payload = Some(payload);
+    }
+
+    pub fn with_payload(mut self, payload: Value) -> Self {
+        self.payload = Some(payload);
+        self
+    }
+
+    pub fn payload(&self) -> Option<&Value> {
+        self.payload.as_ref()
+    }
+
+    pub fn reset_payload(&mut self) {
+        self.payload = None;
+    }
 }
 
 impl Default for DeviceRegistration {
@@ -567,6 +587,9 @@ pub struct DeviceRegistrationResult {
     /// The entity tag associated with the resource.
     #[serde(rename = "etag", skip_serializing_if = "Option::is_none")]
     etag: Option<String>,
+    /// Custom allocation payload returned from the webhook to the device.
+    #[serde(rename = "payload", skip_serializing_if = "Option::is_none")]
+    payload: Option<Value>,
 }
 
 impl DeviceRegistrationResult {
@@ -586,6 +609,7 @@ impl DeviceRegistrationResult {
             error_message: None,
             last_updated_date_time_utc: None,
             etag: None,
+            payload: None,
         }
     }
 
@@ -792,6 +816,23 @@ impl DeviceRegistrationResult {
     pub fn reset_etag(&mut self) {
         self.etag = None;
     }
+
+    pub fn set_payload(&mut self, payload: Value) {
+        self.payload = Some(payload);
+    }
+
+    pub fn with_payload(mut self, payload: Value) -> Self {
+        self.payload = Some(payload);
+        self
+    }
+
+    pub fn payload(&self) -> Option<&Value> {
+        self.payload.as_ref()
+    }
+
+    pub fn reset_payload(&mut self) {
+        self.payload = None;
+    }
 }
 
 impl Default for DeviceRegistrationResult {
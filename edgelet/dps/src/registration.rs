@@ -1,5 +1,7 @@
 // Copyright (c) Microsoft. All rights reserved.
 
+use std::cmp;
+use std::path::{Path, PathBuf};
 use std::sync::{Arc, RwLock};
 use std::time::{Duration, Instant};
 
@@ -7,25 +9,26 @@ use base64;
 use bytes::Bytes;
 use chrono::{DateTime, Utc};
 use failure::{Fail, ResultExt};
-use futures::future::Either;
+use futures::future::{Either, Loop};
 use futures::{future, Future};
 use hyper::{Method, StatusCode};
-use log::{debug, info};
+use log::{debug, info, warn};
 use percent_encoding::{define_encode_set, percent_encode, PATH_SEGMENT_ENCODE_SET};
-use serde_json;
+use serde_json::{self, Value};
 use tokio::prelude::*;
-use tokio::timer::Interval;
+use tokio::timer::Delay;
 use url::form_urlencoded::Serializer as UrlSerializer;
 
 use edgelet_core::crypto::{Activate, KeyIdentity, KeyStore, Sign, Signature, SignatureAlgorithm};
 use edgelet_http::client::{Client, ClientImpl, TokenSource};
-use edgelet_http::ErrorKind as HttpErrorKind;
+use edgelet_http::{Error as HttpError, ErrorKind as HttpErrorKind};
 
 use crate::error::{Error, ErrorKind};
 use crate::model::{
     DeviceRegistration, DeviceRegistrationResult, RegistrationOperationStatus, TpmAttestation,
     TpmRegistrationResult,
 };
+use crate::state::{AttestationKind, RegistrationFailure, RegistrationState};
 
 /// This is the interval at which to poll DPS for registration assignment status
 const DPS_ASSIGNMENT_RETRY_INTERVAL_SECS: u64 = 10;
@@ -33,6 +36,16 @@ const DPS_ASSIGNMENT_RETRY_INTERVAL_SECS: u64 = 10;
 /// This is the number of seconds to wait for DPS to complete assignment to a hub
 const DPS_ASSIGNMENT_TIMEOUT_SECS: u64 = 120;
 
+/// This is the delay before retrying a request that DPS throttled or failed with a server error,
+/// if DPS didn't say how long to wait with a `Retry-After` header. It doubles with every retry.
+const DPS_RETRY_BASE_DELAY_SECS: u64 = 2;
+
+/// This is the longest delay between retries of a request, when DPS doesn't say how long to wait
+const DPS_RETRY_MAX_DELAY_SECS: u64 = 60;
+
+/// This is the number of times a throttled or failed request is retried
+const DPS_MAX_RETRIES: u32 = 6;
+
 define_encode_set! {
     pub IOTHUB_ENCODE_SET = [PATH_SEGMENT_ENCODE_SET] | { '=' }
 }
@@ -89,12 +102,23 @@ where
     }
 }
 
+#[derive(Clone)]
 pub enum DpsAuthKind {
     Tpm { ek: Bytes, srk: Bytes },
     SymmetricKey,
     X509,
 }
 
+impl DpsAuthKind {
+    fn attestation_kind(&self) -> AttestationKind {
+        match self {
+            DpsAuthKind::Tpm { .. } => AttestationKind::Tpm,
+            DpsAuthKind::SymmetricKey => AttestationKind::SymmetricKey,
+            DpsAuthKind::X509 => AttestationKind::X509,
+        }
+    }
+}
+
 pub struct DpsClient<C, K, A>
 where
     C: ClientImpl,
@@ -106,6 +130,8 @@ where
     registration_id: String,
     auth: DpsAuthKind,
     key_store: A,
+    payload: Option<Value>,
+    state_file: Option<PathBuf>,
 }
 
 impl<C, K, A> DpsClient<C, K, A>
@@ -127,9 +153,25 @@ where
             registration_id,
             auth,
             key_store,
+            payload: None,
+            state_file: None,
         })
     }
 
+    /// Sets the custom allocation payload that is sent to DPS with the registration request.
+    pub fn with_payload(mut self, payload: Option<Value>) -> Self {
+        self.payload = payload;
+        self
+    }
+
+    /// Sets the file that keeps the state of the registration. With it, a registration that was
+    /// interrupted while DPS was assigning the device is resumed by polling the same operation,
+    /// instead of registering the device again.
+    pub fn with_state_file(mut self, state_file: Option<PathBuf>) -> Self {
+        self.state_file = state_file;
+        self
+    }
+
     fn get_tpm_challenge_key(body: &str, key_store: &mut A) -> Result<K, Error> {
         let tpm_challenge: TpmRegistrationResult =
             serde_json::from_str(body).context(ErrorKind::GetTpmChallengeKey)?;
@@ -251,13 +293,11 @@ where
     }
 
     // The purpose of this function is to poll DPS till it sends either an error or the device
-    // credentials back. This function calls get_operation_status on a timer which in turns calls
-    // in to DPS. The way polling is implemented is by generating a stream of timer events and
-    // calling get_operation_status on each timer event. Stream processing is aborted if either the
-    // timer generates an error or if get_operation_status returns an error. All results from
-    // get_operation_status are discarded, but for the one that returns the desired result. The
-    // skip_while and take(1) implement discarding all but the desired result. Finally fold is
-    // called on the desired result to format and return it from the function.
+    // credentials back. DPS is asked for the status of the operation right away, and then every
+    // DPS_ASSIGNMENT_RETRY_INTERVAL_SECS seconds while it's still assigning the device, at most
+    // retry_count times. If DPS never finishes, the result is None. Requests that DPS throttles
+    // or fails with a server error are retried with backoff, and a registration that DPS finished
+    // without assigning the device is returned as a RegistrationFailed error.
     fn get_device_registration_result(
         client: Arc<RwLock<Client<C, DpsTokenSource<K>>>>,
         scope_id: String,
@@ -270,44 +310,60 @@ where
             "DPS registration result will retry {} times every {} seconds",
             retry_count, DPS_ASSIGNMENT_RETRY_INTERVAL_SECS
         );
-        let chain = Interval::new(
-            Instant::now(),
-            Duration::from_secs(DPS_ASSIGNMENT_RETRY_INTERVAL_SECS),
-        )
-        .take(retry_count)
-        .map_err(|err| Error::from(err.context(ErrorKind::GetDeviceRegistrationResult)))
-        .and_then(move |_instant: Instant| {
-            debug!("Ask DPS for registration status");
-            Self::get_operation_status(
-                &client.clone(),
-                &scope_id,
-                &registration_id,
-                &operation_id,
-                token_source.clone(),
-            )
-        })
-        .skip_while(Self::is_skippable_result)
-        .take(1)
-        .fold(
-            None,
-            |_final_result: Option<DeviceRegistrationResult>,
-             result_from_service: Option<DeviceRegistrationResult>| {
-                debug!("{:?}", result_from_service);
-                future::ok::<Option<DeviceRegistrationResult>, Error>(result_from_service)
-            },
-        );
-        Box::new(chain)
+        let poll = future::loop_fn((0_u64, Duration::from_secs(0)), move |(attempt, delay)| {
+            let client = client.clone();
+            let scope_id = scope_id.clone();
+            let registration_id = registration_id.clone();
+            let operation_id = operation_id.clone();
+            let token_source = token_source.clone();
+            Delay::new(Instant::now() + delay)
+                .map_err(|err| Error::from(err.context(ErrorKind::GetDeviceRegistrationResult)))
+                .and_then(move |()| {
+                    debug!("Ask DPS for registration status");
+                    retry_transient(move || {
+                        Self::get_operation_status(
+                            &client,
+                            &scope_id,
+                            &registration_id,
+                            &operation_id,
+                            token_source.clone(),
+                        )
+                    })
+                })
+                .and_then(move |result| {
+                    if Self::is_skippable_result(&result)? {
+                        return if attempt + 1 < retry_count {
+                            Ok(Loop::Continue((
+                                attempt + 1,
+                                Duration::from_secs(DPS_ASSIGNMENT_RETRY_INTERVAL_SECS),
+                            )))
+                        } else {
+                            Ok(Loop::Break(None))
+                        };
+                    }
+
+                    debug!("{:?}", result);
+                    if let Some(failure) =
+                        result.as_ref().and_then(RegistrationFailure::from_result)
+                    {
+                        return Err(Error::from(ErrorKind::RegistrationFailed(failure)));
+                    }
+                    Ok(Loop::Break(result))
+                })
+        });
+        Box::new(poll)
     }
 
     fn register_with_x509_auth(
         client: &Arc<RwLock<Client<C, DpsTokenSource<K>>>>,
         scope_id: &str,
         registration_id: String,
+        payload: Option<Value>,
         _key_store: &A,
     ) -> Box<dyn Future<Item = Option<RegistrationOperationStatus>, Error = Error> + Send> {
         let cli = client.clone();
         let uri_path = format!("{}/registrations/{}/register", scope_id, registration_id);
-        let registration = DeviceRegistration::new().with_registration_id(registration_id);
+        let registration = device_registration(registration_id, payload);
         let cli = cli.read().expect("RwLock read failure").clone();
         let f = cli
             .request::<DeviceRegistration, RegistrationOperationStatus>(
@@ -332,10 +388,11 @@ where
         client: &Arc<RwLock<Client<C, DpsTokenSource<K>>>>,
         scope_id: String,
         registration_id: String,
+        payload: Option<Value>,
         key_store: &A,
     ) -> Box<dyn Future<Item = Option<RegistrationOperationStatus>, Error = Error> + Send> {
         let cli = client.clone();
-        let registration = DeviceRegistration::new().with_registration_id(registration_id.clone());
+        let registration = device_registration(registration_id.clone(), payload);
         let f = Self::get_symmetric_challenge_key(key_store)
             .map_err(|err| Error::from(err.context(ErrorKind::GetOperationStatusForSymmetricKey)))
             .into_future()
@@ -373,13 +430,13 @@ where
         registration_id: String,
         tpm_ek: &Bytes,
         tpm_srk: &Bytes,
+        payload: Option<Value>,
         key_store: &A,
     ) -> Box<dyn Future<Item = Option<RegistrationOperationStatus>, Error = Error> + Send> {
         let tpm_attestation = TpmAttestation::new(base64::encode(&tpm_ek))
             .with_storage_root_key(base64::encode(&tpm_srk));
-        let registration = DeviceRegistration::new()
-            .with_registration_id(registration_id.clone())
-            .with_tpm(tpm_attestation);
+        let registration =
+            device_registration(registration_id.clone(), payload).with_tpm(tpm_attestation);
         let client_inner = client.clone();
         let mut key_store_inner = key_store.clone();
         let r = client
@@ -452,125 +509,291 @@ where
         Box::new(r)
     }
 
+    /// Returns the ID of the operation of an earlier run of this registration, if DPS was still
+    /// assigning the device when it stopped.
+    fn pending_operation_id(&self, state: &RegistrationState) -> Option<String> {
+        let state_file = self.state_file.as_ref()?;
+        match RegistrationState::load(state_file) {
+            Ok(Some(saved)) if saved.is_same_registration(state) => {
+                saved.operation_id().map(ToString::to_string)
+            }
+            Ok(_) => None,
+            Err(err) => {
+                warn!(
+                    "Could not read the DPS registration state from {}: {}",
+                    state_file.display(),
+                    err
+                );
+                None
+            }
+        }
+    }
+
+    fn start_registration(
+        &self,
+    ) -> impl Future<Item = Option<RegistrationOperationStatus>, Error = Error> + Send {
+        let client = self.client.clone();
+        let scope_id = self.scope_id.clone();
+        let registration_id = self.registration_id.clone();
+        let auth = self.auth.clone();
+        let payload = self.payload.clone();
+        let key_store = self.key_store.clone();
+        retry_transient(move || match &auth {
+            DpsAuthKind::Tpm { ek, srk } => Self::register_with_tpm_auth(
+                &client,
+                scope_id.clone(),
+                registration_id.clone(),
+                ek,
+                srk,
+                payload.clone(),
+                &key_store,
+            ),
+            DpsAuthKind::SymmetricKey => Self::register_with_symmetric_key_auth(
+                &client,
+                scope_id.clone(),
+                registration_id.clone(),
+                payload.clone(),
+                &key_store,
+            ),
+            DpsAuthKind::X509 => Self::register_with_x509_auth(
+                &client,
+                &scope_id,
+                registration_id.clone(),
+                payload.clone(),
+                &key_store,
+            ),
+        })
+    }
+
     pub fn register(
         &self,
-    ) -> Box<dyn Future<Item = (String, String, Option<String>), Error = Error> + Send> {
+    ) -> Box<dyn Future<Item = (String, String, Option<String>, Option<Value>), Error = Error> + Send>
+    {
         let key_store = self.key_store.clone();
         let mut key_store_status = self.key_store.clone();
         let client_with_token_status = self.client.clone();
         let scope_id = self.scope_id.clone();
-        let scope_id_status = self.scope_id.clone();
         let registration_id = self.registration_id.clone();
-        let registration_id_status = self.registration_id.clone();
-        info!(
-            "Starting DPS registration with scope_id \"{}\", registration_id \"{}\"",
-            scope_id, registration_id,
+        let state = RegistrationState::new(
+            scope_id.clone(),
+            registration_id.clone(),
+            self.auth.attestation_kind(),
         );
-
-        let mut use_tpm_auth = false;
-        let mut use_x509_auth = false;
-        let r = match &self.auth {
-            DpsAuthKind::Tpm { ek, srk } => {
-                use_tpm_auth = true;
-                Self::register_with_tpm_auth(
-                    &self.client,
-                    scope_id.clone(),
-                    registration_id.clone(),
-                    &ek,
-                    &srk,
-                    &self.key_store,
-                )
-            }
-            DpsAuthKind::SymmetricKey => Self::register_with_symmetric_key_auth(
-                &self.client,
-                scope_id.clone(),
-                registration_id.clone(),
-                &self.key_store,
-            ),
-            DpsAuthKind::X509 => {
-                use_x509_auth = true;
-                Self::register_with_x509_auth(
-                    &self.client,
-                    &scope_id,
-                    registration_id.clone(),
-                    &self.key_store,
-                )
+        let state_status = state.clone();
+        let state_file = self.state_file.clone();
+        let state_file_status = self.state_file.clone();
+        let use_tpm_auth = self.auth.attestation_kind() == AttestationKind::Tpm;
+        let use_x509_auth = self.auth.attestation_kind() == AttestationKind::X509;
+
+        let operation_id = match self.pending_operation_id(&state) {
+            Some(operation_id) => {
+                info!(
+                    "Resuming DPS registration with scope_id \"{}\", registration_id \"{}\", operation \"{}\"",
+                    scope_id, registration_id, operation_id,
+                );
+                Either::A(future::ok(operation_id))
             }
-        }
-        .and_then(
-            move |operation_status: Option<RegistrationOperationStatus>| {
-                operation_status.map_or_else(
-                    || {
-                        Either::B(future::err(Error::from(
-                            ErrorKind::RegisterWithAuthUnexpectedlyFailedOperationNotAssigned,
-                        )))
-                    },
-                    move |s| {
-                        let retry_count =
-                            (DPS_ASSIGNMENT_TIMEOUT_SECS / DPS_ASSIGNMENT_RETRY_INTERVAL_SECS) + 1;
-                        let token_key: Result<Option<K>, ()> = if use_x509_auth {
-                            Ok(None)
-                        } else {
-                            match key_store.get(&KeyIdentity::Device, "primary") {
-                                Ok(id_key) => Ok(Some(id_key.clone())),
-                                Err(_err) => Err(()),
-                            }
-                        };
-                        match token_key {
-                            Ok(tk) => {
-                                let ts = if let Some(k) = tk {
-                                    Some(DpsTokenSource::new(
-                                        scope_id.to_string(),
-                                        registration_id.clone().to_string(),
-                                        k,
-                                    ))
-                                } else {
-                                    None
-                                };
-                                Either::A(Self::get_device_registration_result(
-                                    client_with_token_status,
-                                    scope_id_status,
-                                    registration_id_status,
-                                    s.operation_id().clone(),
-                                    ts,
-                                    retry_count,
-                                ))
+            None => {
+                info!(
+                    "Starting DPS registration with scope_id \"{}\", registration_id \"{}\"",
+                    scope_id, registration_id,
+                );
+                Either::B(self.start_registration().and_then(
+                    move |operation_status: Option<RegistrationOperationStatus>| {
+                        let operation_id = operation_status
+                            .map(|s| s.operation_id().clone())
+                            .ok_or_else(|| {
+                                Error::from(
+                                    ErrorKind::RegisterWithAuthUnexpectedlyFailedOperationNotAssigned,
+                                )
+                            })?;
+                        if let Some(state_file) = state_file {
+                            let state = state.with_operation_id(operation_id.clone());
+                            if let Err(err) = state.save(&state_file) {
+                                warn!(
+                                    "Could not save the DPS registration state to {}: {}",
+                                    state_file.display(),
+                                    err
+                                );
                             }
-                            Err(_err) => Either::B(future::err(Error::from(
-                                ErrorKind::RegisterWithAuthUnexpectedlyFailedOperationNotAssigned,
-                            ))),
                         }
+                        Ok(operation_id)
                     },
-                )
-            },
-        )
-        .and_then(move |operation_status: Option<DeviceRegistrationResult>| {
-            let s = operation_status.ok_or_else(|| {
-                Error::from(ErrorKind::RegisterWithAuthUnexpectedlyFailedOperationNotAssigned)
-            })?;
-            if use_tpm_auth {
-                let tpm_result = s.tpm();
-                let r = tpm_result.ok_or_else(|| {
-                    Error::from(ErrorKind::RegisterWithAuthUnexpectedlyFailedOperationNotAssigned)
-                })?;
-                let ks = r.authentication_key().ok_or_else(|| {
+                ))
+            }
+        };
+
+        let r = operation_id
+            .and_then(move |operation_id| {
+                let retry_count =
+                    (DPS_ASSIGNMENT_TIMEOUT_SECS / DPS_ASSIGNMENT_RETRY_INTERVAL_SECS) + 1;
+                let token_source = if use_x509_auth {
+                    Ok(None)
+                } else {
+                    key_store
+                        .get(&KeyIdentity::Device, "primary")
+                        .map(|id_key| {
+                            Some(DpsTokenSource::new(
+                                scope_id.clone(),
+                                registration_id.clone(),
+                                id_key,
+                            ))
+                        })
+                };
+                match token_source {
+                    Ok(token_source) => Either::A(Self::get_device_registration_result(
+                        client_with_token_status,
+                        scope_id,
+                        registration_id,
+                        operation_id,
+                        token_source,
+                        retry_count,
+                    )),
+                    Err(_err) => Either::B(future::err(Error::from(
+                        ErrorKind::RegisterWithAuthUnexpectedlyFailedOperationNotAssigned,
+                    ))),
+                }
+            })
+            .then(move |result| {
+                if let Some(state_file) = state_file_status {
+                    update_state(&state_file, state_status, &result);
+                }
+                result
+            })
+            .and_then(move |operation_status: Option<DeviceRegistrationResult>| {
+                let s = operation_status.ok_or_else(|| {
                     Error::from(ErrorKind::RegisterWithAuthUnexpectedlyFailedOperationNotAssigned)
                 })?;
-                let kb =
-                    base64::decode(ks).context(ErrorKind::RegisterWithAuthUnexpectedlyFailed)?;
-                key_store_status
-                    .activate_identity_key(KeyIdentity::Device, "primary".to_string(), kb)
-                    .context(ErrorKind::RegisterWithAuthUnexpectedlyFailed)?;
-            }
-            get_device_info(&s)
-        });
+                if use_tpm_auth {
+                    let tpm_result = s.tpm();
+                    let r = tpm_result.ok_or_else(|| {
+                        Error::from(
+                            ErrorKind::RegisterWithAuthUnexpectedlyFailedOperationNotAssigned,
+                        )
+                    })?;
+                    let ks = r.authentication_key().ok_or_else(|| {
+                        Error::from(
+                            ErrorKind::RegisterWithAuthUnexpectedlyFailedOperationNotAssigned,
+                        )
+                    })?;
+                    let kb = base64::decode(ks)
+                        .context(ErrorKind::RegisterWithAuthUnexpectedlyFailed)?;
+                    key_store_status
+                        .activate_identity_key(KeyIdentity::Device, "primary".to_string(), kb)
+                        .context(ErrorKind::RegisterWithAuthUnexpectedlyFailed)?;
+                }
+                get_device_info(&s)
+            });
         Box::new(r)
     }
 }
 
+/// Sends the request made by `request` again while DPS throttles it or fails it with a server
+/// error, at most `DPS_MAX_RETRIES` times.
+fn retry_transient<F, R, T>(mut request: F) -> impl Future<Item = T, Error = Error> + Send
+where
+    F: FnMut() -> R + Send,
+    R: Future<Item = T, Error = Error> + Send,
+    T: Send,
+{
+    future::loop_fn(0_u32, move |retries| {
+        request().then(move |result| match result {
+            Ok(value) => Either::A(future::ok(Loop::Break(value))),
+            Err(err) => match retry_delay(&err, retries) {
+                Some(delay) if retries < DPS_MAX_RETRIES => {
+                    info!(
+                        "DPS request failed, retrying in {} seconds: {}",
+                        delay.as_secs(),
+                        err
+                    );
+                    Either::B(
+                        Delay::new(Instant::now() + delay)
+                            .map_err(|err| Error::from(err.context(ErrorKind::RetryTimer)))
+                            .map(move |()| Loop::Continue(retries + 1)),
+                    )
+                }
+                _ => Either::A(future::err(err)),
+            },
+        })
+    })
+}
+
+/// Returns how long to wait before retrying a request that failed with `err`, or `None` if DPS
+/// rejected the request and retrying it won't help. The delay that DPS asks for with
+/// `Retry-After` is used if there is one.
+fn retry_delay(err: &Error, retries: u32) -> Option<Duration> {
+    dps_response_error(err).and_then(|err| match err.kind() {
+        HttpErrorKind::HttpWithErrorResponse(status, _) if is_transient(*status) => {
+            Some(err.retry_after().unwrap_or_else(|| backoff(retries)))
+        }
+        _ => None,
+    })
+}
+
+fn backoff(retries: u32) -> Duration {
+    let delay = DPS_RETRY_BASE_DELAY_SECS
+        .checked_shl(retries)
+        .unwrap_or(DPS_RETRY_MAX_DELAY_SECS);
+    Duration::from_secs(cmp::min(delay, DPS_RETRY_MAX_DELAY_SECS))
+}
+
+fn is_transient(status: StatusCode) -> bool {
+    status == StatusCode::TOO_MANY_REQUESTS || status.is_server_error()
+}
+
+/// The error response that DPS sent, if the request got that far
+fn dps_response_error(err: &Error) -> Option<&HttpError> {
+    Fail::iter_chain(err)
+        .filter_map(|cause| cause.downcast_ref::<HttpError>())
+        .find(|err| match err.kind() {
+            HttpErrorKind::HttpWithErrorResponse(_, _) => true,
+            _ => false,
+        })
+}
+
+/// Records how polling the registration ended. A registration that DPS is still assigning, or
+/// couldn't be asked about, is kept so that it's resumed. A registration that DPS rejected is kept
+/// with the reason, so that it can be shown. Anything else starts a new registration next time.
+fn update_state(
+    path: &Path,
+    state: RegistrationState,
+    result: &Result<Option<DeviceRegistrationResult>, Error>,
+) {
+    let updated = match result {
+        Ok(Some(_)) => RegistrationState::remove(path),
+        Ok(None) => return,
+        Err(err) => match err.kind() {
+            ErrorKind::RegistrationFailed(failure) => {
+                state.with_failure(failure.clone()).save(path)
+            }
+            _ => match dps_response_error(err).map(HttpError::kind) {
+                Some(HttpErrorKind::HttpWithErrorResponse(status, _)) if !is_transient(*status) => {
+                    RegistrationState::remove(path)
+                }
+                _ => return,
+            },
+        },
+    };
+    if let Err(err) = updated {
+        warn!(
+            "Could not update the DPS registration state in {}: {}",
+            path.display(),
+            err
+        );
+    }
+}
+
+fn device_registration(registration_id: String, payload: Option<Value>) -> DeviceRegistration {
+    let registration = DeviceRegistration::new().with_registration_id(registration_id);
+    match payload {
+        Some(payload) => registration.with_payload(payload),
+        None => registration,
+    }
+}
+
 fn get_device_info(
     registration_result: &DeviceRegistrationResult,
-) -> Result<(String, String, Option<String>), Error> {
+) -> Result<(String, String, Option<String>, Option<Value>), Error> {
     Ok((
         registration_result
             .device_id()
@@ -585,17 +808,20 @@ fn get_device_info(
             })?
             .to_string(),
         registration_result.substatus().map(ToString::to_string),
+        registration_result.payload().cloned(),
     ))
 }
 
 #[cfg(test)]
 mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
     use std::sync::Mutex;
 
     use edgelet_core::crypto::{MemoryKey, MemoryKeyStore};
     use http;
     use hyper::{self, Body, Request, Response, StatusCode};
-    use serde_json;
+    use serde_json::{self, json};
+    use tempdir::TempDir;
     use tokio;
     use url::Url;
 
@@ -654,6 +880,7 @@ mod tests {
             "reg".to_string(),
             &Bytes::from("ek".to_string().into_bytes()),
             &Bytes::from("srk".to_string().into_bytes()),
+            None,
             &MemoryKeyStore::new(),
         )
         .map(|result| match result {
@@ -718,6 +945,7 @@ mod tests {
             &client,
             "scope".to_string(),
             "reg".to_string(),
+            None,
             &key_store,
         )
         .map(|result| match result {
@@ -778,6 +1006,7 @@ mod tests {
             &client,
             "scope",
             "reg".to_string(),
+            None,
             &empty_key_store,
         )
         .map(|result| match result {
@@ -1308,8 +1537,250 @@ mod tests {
             (
                 "device".to_string(),
                 "hub".to_string(),
-                Some("initialAssignment".to_string())
+                Some("initialAssignment".to_string()),
+                None
+            )
+        )
+    }
+
+    #[test]
+    fn get_device_info_returns_payload() {
+        let (_, _, _, payload) = get_device_info(
+            &DeviceRegistrationResult::new()
+                .with_device_id("device".to_string())
+                .with_assigned_hub("hub".to_string())
+                .with_payload(json!({ "siteId": "site 7" })),
+        )
+        .unwrap();
+        assert_eq!(Some(json!({ "siteId": "site 7" })), payload);
+    }
+
+    #[test]
+    fn device_registration_includes_payload() {
+        let registration = device_registration("reg".to_string(), None);
+        assert_eq!(
+            json!({ "registrationId": "reg" }),
+            serde_json::to_value(&registration).unwrap()
+        );
+
+        let registration =
+            device_registration("reg".to_string(), Some(json!({ "skus": ["a", "b"] })));
+        assert_eq!(
+            json!({ "registrationId": "reg", "payload": { "skus": ["a", "b"] } }),
+            serde_json::to_value(&registration).unwrap()
+        );
+    }
+
+    fn sym_key_dps_client<H>(
+        handler: H,
+        state_file: &Path,
+    ) -> DpsClient<H, MemoryKey, MemoryKeyStore>
+    where
+        H: 'static + ClientImpl,
+    {
+        let client = Client::new(
+            handler,
+            None,
+            DPS_API_VERSION.to_string(),
+            Url::parse("https://global.azure-devices-provisioning.net/").unwrap(),
+        )
+        .unwrap();
+        let mut key_store = MemoryKeyStore::new();
+        key_store
+            .activate_identity_key(KeyIdentity::Device, "primary".to_string(), "some key")
+            .unwrap();
+        DpsClient::new(
+            client,
+            "scope".to_string(),
+            "reg".to_string(),
+            DpsAuthKind::SymmetricKey,
+            key_store,
+        )
+        .unwrap()
+        .with_state_file(Some(state_file.to_path_buf()))
+    }
+
+    fn operation_status_response(registration_state: DeviceRegistrationResult) -> Response<Body> {
+        Response::new(
+            serde_json::to_string(
+                &RegistrationOperationStatus::new("operation".to_string())
+                    .with_status("assigned".to_string())
+                    .with_registration_state(registration_state),
             )
+            .unwrap()
+            .into(),
         )
     }
+
+    #[test]
+    fn register_retries_throttled_requests_and_forgets_finished_registration() {
+        let dir = TempDir::new("dps").unwrap();
+        let state_file = dir.path().join("dps_registration.json");
+        let puts = Arc::new(AtomicUsize::new(0));
+        let puts_handler = puts.clone();
+        let handler = move |req: Request<Body>| {
+            if req.method() == Method::PUT {
+                if puts_handler.fetch_add(1, Ordering::SeqCst) == 0 {
+                    return future::ok(
+                        Response::builder()
+                            .status(StatusCode::TOO_MANY_REQUESTS)
+                            .header(hyper::header::RETRY_AFTER, "0")
+                            .body(Body::empty())
+                            .unwrap(),
+                    );
+                }
+                let result = RegistrationOperationStatus::new("operation".to_string())
+                    .with_status("assigning".to_string());
+                future::ok(Response::new(
+                    serde_json::to_string(&result).unwrap().into(),
+                ))
+            } else {
+                future::ok(operation_status_response(
+                    DeviceRegistrationResult::new()
+                        .with_status("assigned".to_string())
+                        .with_device_id("device".to_string())
+                        .with_assigned_hub("hub".to_string()),
+                ))
+            }
+        };
+
+        let dps = sym_key_dps_client(handler, &state_file);
+        let (device_id, hub_name, _, _) = tokio::runtime::current_thread::Runtime::new()
+            .unwrap()
+            .block_on(dps.register())
+            .unwrap();
+        assert_eq!("device", device_id);
+        assert_eq!("hub", hub_name);
+        assert_eq!(2, puts.load(Ordering::SeqCst));
+        assert_eq!(None, RegistrationState::load(&state_file).unwrap());
+    }
+
+    #[test]
+    fn register_resumes_pending_operation() {
+        let dir = TempDir::new("dps").unwrap();
+        let state_file = dir.path().join("dps_registration.json");
+        RegistrationState::new(
+            "scope".to_string(),
+            "reg".to_string(),
+            AttestationKind::SymmetricKey,
+        )
+        .with_operation_id("pending".to_string())
+        .save(&state_file)
+        .unwrap();
+
+        let expected_uri = format!("https://global.azure-devices-provisioning.net/scope/registrations/reg/operations/pending?api-version={}", DPS_API_VERSION);
+        let handler = move |req: Request<Body>| {
+            assert_eq!(Method::GET, req.method());
+            assert_eq!(expected_uri, req.uri().to_string());
+            future::ok(operation_status_response(
+                DeviceRegistrationResult::new()
+                    .with_status("assigned".to_string())
+                    .with_device_id("device".to_string())
+                    .with_assigned_hub("hub".to_string()),
+            ))
+        };
+
+        let dps = sym_key_dps_client(handler, &state_file);
+        let (device_id, _, _, _) = tokio::runtime::current_thread::Runtime::new()
+            .unwrap()
+            .block_on(dps.register())
+            .unwrap();
+        assert_eq!("device", device_id);
+        assert_eq!(None, RegistrationState::load(&state_file).unwrap());
+    }
+
+    #[test]
+    fn register_ignores_pending_operation_of_other_registration() {
+        let dir = TempDir::new("dps").unwrap();
+        let state_file = dir.path().join("dps_registration.json");
+        RegistrationState::new(
+            "scope".to_string(),
+            "other".to_string(),
+            AttestationKind::SymmetricKey,
+        )
+        .with_operation_id("pending".to_string())
+        .save(&state_file)
+        .unwrap();
+
+        let handler = |req: Request<Body>| {
+            assert_ne!(Method::GET, req.method());
+            future::ok(
+                Response::builder()
+                    .status(StatusCode::NOT_FOUND)
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+        };
+
+        let dps = sym_key_dps_client(handler, &state_file);
+        let err = tokio::runtime::current_thread::Runtime::new()
+            .unwrap()
+            .block_on(dps.register())
+            .unwrap_err();
+        match err.kind() {
+            ErrorKind::RegisterWithSymmetricChallengeKey => (),
+            _ => panic!("unexpected error {:?}", err),
+        }
+    }
+
+    #[test]
+    fn register_returns_rejected_registration_as_failure() {
+        let dir = TempDir::new("dps").unwrap();
+        let state_file = dir.path().join("dps_registration.json");
+        let handler = |req: Request<Body>| {
+            if req.method() == Method::PUT {
+                let result = RegistrationOperationStatus::new("operation".to_string())
+                    .with_status("assigning".to_string());
+                future::ok(Response::new(
+                    serde_json::to_string(&result).unwrap().into(),
+                ))
+            } else {
+                future::ok(operation_status_response(
+                    DeviceRegistrationResult::new()
+                        .with_status("disabled".to_string())
+                        .with_error_code(401_002)
+                        .with_error_message("The enrollment is disabled".to_string()),
+                ))
+            }
+        };
+
+        let dps = sym_key_dps_client(handler, &state_file);
+        let err = tokio::runtime::current_thread::Runtime::new()
+            .unwrap()
+            .block_on(dps.register())
+            .unwrap_err();
+        let failure = match err.kind() {
+            ErrorKind::RegistrationFailed(failure) => failure.clone(),
+            _ => panic!("unexpected error {:?}", err),
+        };
+        assert!(failure.is_disabled());
+        assert_eq!(Some(401_002), failure.error_code());
+
+        let state = RegistrationState::load(&state_file).unwrap().unwrap();
+        assert_eq!(None, state.operation_id());
+        assert_eq!(Some(&failure), state.failure());
+    }
+
+    #[test]
+    fn retry_delay_prefers_retry_after_and_backs_off() {
+        let throttled = Error::from(
+            HttpError::http_with_error_response(StatusCode::TOO_MANY_REQUESTS, b"")
+                .context(ErrorKind::GetOperationStatus),
+        );
+        assert_eq!(Some(Duration::from_secs(2)), retry_delay(&throttled, 0));
+        assert_eq!(Some(Duration::from_secs(16)), retry_delay(&throttled, 3));
+        assert_eq!(Some(Duration::from_secs(60)), retry_delay(&throttled, 10));
+
+        let unavailable = Error::from(
+            HttpError::http_with_error_response(StatusCode::SERVICE_UNAVAILABLE, b"")
+                .context(ErrorKind::GetOperationStatus),
+        );
+        assert_eq!(Some(Duration::from_secs(2)), retry_delay(&unavailable, 0));
+
+        let rejected = Error::from(
+            HttpError::http_with_error_response(StatusCode::UNAUTHORIZED, b"")
+                .context(ErrorKind::GetOperationStatus),
+        );
+        assert_eq!(None, retry_delay(&rejected, 0));
+    }
 }
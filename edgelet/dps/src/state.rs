@@ -0,0 +1,235 @@
+// Copyright (c) Microsoft. All rights reserved.
+
+//! The state of a DPS registration, kept on disk so that a registration that is still being
+//! assigned when iotedged restarts is resumed instead of started again, and so that the reason a
+//! registration failed can be shown by `iotedge check`.
+
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use failure::{Fail, ResultExt};
+use serde_derive::{Deserialize, Serialize};
+
+use crate::error::{Error, ErrorKind};
+use crate::model::DeviceRegistrationResult;
+
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum AttestationKind {
+    Tpm,
+    SymmetricKey,
+    X509,
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct RegistrationState {
+    #[serde(rename = "scopeId")]
+    scope_id: String,
+    #[serde(rename = "registrationId")]
+    registration_id: String,
+    #[serde(rename = "attestation")]
+    attestation: AttestationKind,
+    #[serde(rename = "operationId", skip_serializing_if = "Option::is_none")]
+    operation_id: Option<String>,
+    #[serde(rename = "failure", skip_serializing_if = "Option::is_none")]
+    failure: Option<RegistrationFailure>,
+}
+
+impl RegistrationState {
+    pub fn new(scope_id: String, registration_id: String, attestation: AttestationKind) -> Self {
+        RegistrationState {
+            scope_id,
+            registration_id,
+            attestation,
+            operation_id: None,
+            failure: None,
+        }
+    }
+
+    pub fn with_operation_id(mut self, operation_id: String) -> Self {
+        self.operation_id = Some(operation_id);
+        self
+    }
+
+    pub fn with_failure(mut self, failure: RegistrationFailure) -> Self {
+        self.failure = Some(failure);
+        self
+    }
+
+    pub fn scope_id(&self) -> &str {
+        &self.scope_id
+    }
+
+    pub fn registration_id(&self) -> &str {
+        &self.registration_id
+    }
+
+    pub fn attestation(&self) -> AttestationKind {
+        self.attestation
+    }
+
+    /// The ID of the registration operation that DPS was still assigning
+    pub fn operation_id(&self) -> Option<&str> {
+        self.operation_id.as_ref().map(AsRef::as_ref)
+    }
+
+    /// Why DPS rejected the last registration
+    pub fn failure(&self) -> Option<&RegistrationFailure> {
+        self.failure.as_ref()
+    }
+
+    /// Whether this state belongs to the same registration as `other`. A state that was left
+    /// behind by a different scope, registration ID or attestation method must not be resumed.
+    pub fn is_same_registration(&self, other: &RegistrationState) -> bool {
+        self.scope_id == other.scope_id
+            && self.registration_id == other.registration_id
+            && self.attestation == other.attestation
+    }
+
+    /// Returns `None` if there is no state file.
+    pub fn load(path: &Path) -> Result<Option<Self>, Error> {
+        let buffer = match fs::read(path) {
+            Ok(buffer) => buffer,
+            Err(ref err) if err.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(err) => return Err(Error::from(err.context(ErrorKind::LoadRegistrationState))),
+        };
+        let state = serde_json::from_slice(&buffer).context(ErrorKind::LoadRegistrationState)?;
+        Ok(Some(state))
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), Error> {
+        let buffer = serde_json::to_vec(self).context(ErrorKind::SaveRegistrationState)?;
+        fs::write(path, buffer).context(ErrorKind::SaveRegistrationState)?;
+        Ok(())
+    }
+
+    pub fn remove(path: &Path) -> Result<(), Error> {
+        match fs::remove_file(path) {
+            Ok(()) => Ok(()),
+            Err(ref err) if err.kind() == io::ErrorKind::NotFound => Ok(()),
+            Err(err) => Err(Error::from(err.context(ErrorKind::SaveRegistrationState))),
+        }
+    }
+}
+
+/// A registration that DPS finished without assigning the device to an IoT hub
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct RegistrationFailure {
+    #[serde(rename = "status")]
+    status: String,
+    #[serde(rename = "errorCode", skip_serializing_if = "Option::is_none")]
+    error_code: Option<i32>,
+    #[serde(rename = "errorMessage", skip_serializing_if = "Option::is_none")]
+    error_message: Option<String>,
+}
+
+impl RegistrationFailure {
+    /// Returns `None` unless DPS reported the registration as `failed` or `disabled`.
+    pub fn from_result(result: &DeviceRegistrationResult) -> Option<Self> {
+        let status = result.status()?;
+        if status.eq_ignore_ascii_case("failed") || status.eq_ignore_ascii_case("disabled") {
+            Some(RegistrationFailure {
+                status: status.to_string(),
+                error_code: result.error_code(),
+                error_message: result.error_message().map(ToString::to_string),
+            })
+        } else {
+            None
+        }
+    }
+
+    pub fn status(&self) -> &str {
+        &self.status
+    }
+
+    pub fn error_code(&self) -> Option<i32> {
+        self.error_code
+    }
+
+    pub fn error_message(&self) -> Option<&str> {
+        self.error_message.as_ref().map(AsRef::as_ref)
+    }
+
+    /// Whether the enrollment of the device is disabled in DPS
+    pub fn is_disabled(&self) -> bool {
+        self.status.eq_ignore_ascii_case("disabled")
+    }
+}
+
+impl fmt::Display for RegistrationFailure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "status {}", self.status)?;
+        if let Some(error_code) = self.error_code {
+            write!(f, ", error code {}", error_code)?;
+        }
+        if let Some(error_message) = &self.error_message {
+            write!(f, ": {}", error_message)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tempdir::TempDir;
+
+    use super::*;
+
+    #[test]
+    fn state_round_trips_through_file() {
+        let dir = TempDir::new("dps").unwrap();
+        let path = dir.path().join("dps_registration.json");
+        assert_eq!(None, RegistrationState::load(&path).unwrap());
+
+        let state = RegistrationState::new(
+            "scope".to_string(),
+            "reg".to_string(),
+            AttestationKind::SymmetricKey,
+        )
+        .with_operation_id("operation".to_string());
+        state.save(&path).unwrap();
+        assert_eq!(Some(state), RegistrationState::load(&path).unwrap());
+
+        RegistrationState::remove(&path).unwrap();
+        RegistrationState::remove(&path).unwrap();
+        assert_eq!(None, RegistrationState::load(&path).unwrap());
+    }
+
+    #[test]
+    fn only_failed_and_disabled_registrations_are_failures() {
+        for status in &["assigned", "assigning", "unassigned"] {
+            let result = DeviceRegistrationResult::new().with_status(status.to_string());
+            assert_eq!(None, RegistrationFailure::from_result(&result));
+        }
+
+        let result = DeviceRegistrationResult::new()
+            .with_status("disabled".to_string())
+            .with_error_code(401_002)
+            .with_error_message("The enrollment is disabled".to_string());
+        let failure = RegistrationFailure::from_result(&result).unwrap();
+        assert!(failure.is_disabled());
+        assert_eq!(
+            "status disabled, error code 401002: The enrollment is disabled",
+            failure.to_string()
+        );
+    }
+
+    #[test]
+    fn state_of_other_registration_is_not_the_same() {
+        let state =
+            RegistrationState::new("scope".to_string(), "reg".to_string(), AttestationKind::Tpm);
+        assert!(state.is_same_registration(&state.clone().with_operation_id("op".to_string())));
+        assert!(!state.is_same_registration(&RegistrationState::new(
+            "scope".to_string(),
+            "other".to_string(),
+            AttestationKind::Tpm,
+        )));
+        assert!(!state.is_same_registration(&RegistrationState::new(
+            "scope".to_string(),
+            "reg".to_string(),
+            AttestationKind::X509,
+        )));
+    }
+}
@@ -116,9 +116,18 @@ pub enum ErrorKind {
     #[fail(display = "An error occured when generating a random number.")]
     MakeRandom,
 
+    #[fail(display = "Could not encode metrics.")]
+    Metrics,
+
     #[fail(display = "A module runtime error occurred.")]
     ModuleRuntime,
 
+    #[fail(display = "The module runtime does not support this operation.")]
+    ModuleRuntimeNotSupported,
+
+    #[fail(display = "Could not read or write the encrypted file {}", _0)]
+    SealedFile(String),
+
     #[fail(display = "Signing error occurred.")]
     Sign,
 
@@ -30,6 +30,7 @@ pub trait Identity {
     fn auth_type(&self) -> AuthType;
 }
 
+#[derive(Clone, Debug)]
 pub struct IdentitySpec {
     module_id: String,
     generation_id: Option<String>,
@@ -84,6 +85,17 @@ pub trait IdentityManager {
     fn delete(&mut self, id: IdentitySpec) -> Self::DeleteFuture;
 }
 
+/// Replaces the symmetric key a device authenticates with, along with the module keys that are
+/// derived from it.
+pub trait DeviceKeyRotation {
+    type Error: Fail;
+    type RotateFuture: Future<Item = (), Error = Self::Error> + Send;
+
+    /// IoT Hub must already accept `primary_key` for the device. If the rotation fails, the
+    /// device is left using its current key.
+    fn rotate_device_key(&self, primary_key: Vec<u8>) -> Self::RotateFuture;
+}
+
 // Useful for error contexts
 #[derive(Clone, Debug)]
 pub enum IdentityOperation {
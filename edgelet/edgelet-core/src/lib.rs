@@ -21,8 +21,10 @@ pub mod crypto;
 mod error;
 mod identity;
 mod logs;
+pub mod metrics;
 mod module;
 mod network;
+mod sealed_file;
 mod settings;
 pub mod watchdog;
 pub mod workload;
@@ -36,20 +38,25 @@ pub use crypto::{
     MasterEncryptionKey, PrivateKey, Signature, IOTEDGED_CA_ALIAS,
 };
 pub use error::{Error, ErrorKind};
-pub use identity::{AuthType, Identity, IdentityManager, IdentityOperation, IdentitySpec};
+pub use identity::{
+    AuthType, DeviceKeyRotation, Identity, IdentityManager, IdentityOperation, IdentitySpec,
+};
 pub use logs::{Chunked, LogChunk, LogDecode};
 pub use module::{
-    DiskInfo, ImagePullPolicy, LogOptions, LogTail, MakeModuleRuntime, Module, ModuleOperation,
-    ModuleRegistry, ModuleRuntime, ModuleRuntimeErrorReason, ModuleRuntimeState, ModuleSpec,
-    ModuleStatus, ModuleTop, ProvisioningResult, RegistryOperation, RuntimeOperation, SystemInfo,
+    DiskInfo, ExecOptions, ImagePullPolicy, LogOptions, LogTail, MakeModuleRuntime, Module,
+    ModuleEvent, ModuleEventKind, ModuleOperation, ModuleRegistry, ModuleResourceUsage,
+    ModuleRuntime, ModuleRuntimeErrorReason, ModuleRuntimeState, ModuleSpec, ModuleStatus,
+    ModuleTop, ProvisioningResult, RegistryOperation, RuntimeOperation, SystemInfo,
     SystemResources,
 };
 pub use network::{Ipam, IpamConfig, MobyNetwork, Network};
+pub use sealed_file::SealedFile;
 pub use settings::{
     AttestationMethod, Certificates, Connect, Dps, External, Listen, Manual, ManualAuthMethod,
     ManualDeviceConnectionString, ManualX509Auth, Protocol, Provisioning, ProvisioningType,
-    RetryLimit, RuntimeSettings, Settings, SymmetricKeyAttestationInfo, TpmAttestationInfo,
-    WatchdogSettings, X509AttestationInfo,
+    RateLimit, RetryLimit, RuntimeSettings, Settings, SymmetricKeyAttestationInfo,
+    TpmAttestationInfo, WatchdogSettings, WorkloadRateLimits, X509AttestationInfo,
+    PKCS11_URI_SCHEME,
 };
 pub use workload::WorkloadConfig;
 
@@ -0,0 +1,186 @@
+// Copyright (c) Microsoft. All rights reserved.
+
+//! Metrics about the daemon. They are always recorded, and served in the Prometheus text format
+//! when a metrics listener is configured.
+
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use failure::ResultExt;
+use lazy_static::lazy_static;
+use prometheus::{
+    register_gauge_vec, register_histogram_vec, register_int_counter, register_int_counter_vec,
+    Encoder, GaugeVec, HistogramVec, IntCounter, IntCounterVec, TextEncoder,
+};
+
+use crate::error::{Error, ErrorKind};
+use crate::module::ModuleRuntimeErrorReason;
+
+lazy_static! {
+    static ref HTTP_REQUESTS: IntCounterVec = register_int_counter_vec!(
+        "edgelet_http_requests_total",
+        "Number of requests handled by the management and workload APIs",
+        &["api", "route", "method", "status", "module"]
+    )
+    .expect("metric is valid");
+    static ref HTTP_REQUEST_DURATION: HistogramVec = register_histogram_vec!(
+        "edgelet_http_request_duration_seconds",
+        "Time taken to handle requests to the management and workload APIs",
+        &["api", "route", "method", "status", "module"]
+    )
+    .expect("metric is valid");
+    static ref CERTIFICATE_EXPIRATION: GaugeVec = register_gauge_vec!(
+        "edgelet_certificate_expiration_timestamp_seconds",
+        "Time at which a certificate managed by the daemon expires, in seconds since the epoch",
+        &["certificate"]
+    )
+    .expect("metric is valid");
+    static ref WATCHDOG_RESTARTS: IntCounterVec = register_int_counter_vec!(
+        "edgelet_watchdog_restarts_total",
+        "Number of times the watchdog started a module that was not running",
+        &["module"]
+    )
+    .expect("metric is valid");
+    static ref WATCHDOG_CREATIONS: IntCounterVec = register_int_counter_vec!(
+        "edgelet_watchdog_creations_total",
+        "Number of times the watchdog created a module that did not exist",
+        &["module"]
+    )
+    .expect("metric is valid");
+    static ref PROVISIONING_FAILURES: IntCounter = register_int_counter!(
+        "edgelet_provisioning_failures_total",
+        "Number of times the device could not be provisioned"
+    )
+    .expect("metric is valid");
+    static ref MODULE_OPERATIONS: IntCounterVec = register_int_counter_vec!(
+        "edgelet_module_operations_total",
+        "Number of operations performed by the module runtime, by outcome",
+        &["operation", "module", "outcome"]
+    )
+    .expect("metric is valid");
+}
+
+/// The content type of the output of `encode`.
+pub const CONTENT_TYPE: &str = "text/plain; version=0.0.4";
+
+pub fn observe_http_request(
+    api: &str,
+    route: &str,
+    method: &str,
+    status: u16,
+    module: &str,
+    duration: Duration,
+) {
+    let status = status.to_string();
+    let labels = [api, route, method, &status, module];
+    HTTP_REQUESTS.with_label_values(&labels).inc();
+    HTTP_REQUEST_DURATION
+        .with_label_values(&labels)
+        .observe(duration.as_secs_f64());
+}
+
+#[allow(clippy::cast_precision_loss)]
+pub fn set_certificate_expiration(certificate: &str, valid_to: DateTime<Utc>) {
+    CERTIFICATE_EXPIRATION
+        .with_label_values(&[certificate])
+        .set(valid_to.timestamp() as f64);
+}
+
+pub fn observe_watchdog_restart(module: &str) {
+    WATCHDOG_RESTARTS.with_label_values(&[module]).inc();
+}
+
+pub fn observe_watchdog_creation(module: &str) {
+    WATCHDOG_CREATIONS.with_label_values(&[module]).inc();
+}
+
+pub fn observe_provisioning_failure() {
+    PROVISIONING_FAILURES.inc();
+}
+
+/// Records the outcome of a module runtime operation, like `create` or `start`, on `module`.
+pub fn observe_module_operation(
+    operation: &str,
+    module: &str,
+    outcome: Result<(), ModuleRuntimeErrorReason>,
+) {
+    let outcome = match outcome {
+        Ok(()) => "success",
+        Err(ModuleRuntimeErrorReason::NotFound) => "not_found",
+        Err(ModuleRuntimeErrorReason::PolicyViolation) => "policy_violation",
+        Err(ModuleRuntimeErrorReason::Other) => "error",
+    };
+    MODULE_OPERATIONS
+        .with_label_values(&[operation, module, outcome])
+        .inc();
+}
+
+/// Encodes all metrics in the Prometheus text format.
+pub fn encode() -> Result<Vec<u8>, Error> {
+    let mut buffer = vec![];
+    TextEncoder::new()
+        .encode(&prometheus::gather(), &mut buffer)
+        .context(ErrorKind::Metrics)?;
+    Ok(buffer)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn encoded() -> String {
+        String::from_utf8(encode().unwrap()).unwrap()
+    }
+
+    #[test]
+    fn http_requests_are_labeled_by_route_and_module() {
+        observe_http_request(
+            "work",
+            "/modules/{name}/genid/{genid}/sign",
+            "POST",
+            200,
+            "tempSensor",
+            Duration::from_millis(5),
+        );
+
+        let metrics = encoded();
+        assert!(metrics.contains(
+            r#"edgelet_http_requests_total{api="work",method="POST",module="tempSensor",route="/modules/{name}/genid/{genid}/sign",status="200"}"#
+        ));
+        assert!(metrics.contains("edgelet_http_request_duration_seconds_bucket{"));
+    }
+
+    #[test]
+    fn module_operations_are_labeled_by_outcome() {
+        observe_module_operation(
+            "create",
+            "blocked",
+            Err(ModuleRuntimeErrorReason::PolicyViolation),
+        );
+
+        assert!(encoded().contains(
+            r#"edgelet_module_operations_total{module="blocked",operation="create",outcome="policy_violation"} 1"#
+        ));
+    }
+
+    #[test]
+    fn watchdog_creations_are_not_restarts() {
+        observe_watchdog_creation("createdAgent");
+
+        let metrics = encoded();
+        assert!(metrics.contains(r#"edgelet_watchdog_creations_total{module="createdAgent"} 1"#));
+        assert!(!metrics.contains(r#"edgelet_watchdog_restarts_total{module="createdAgent"}"#));
+    }
+
+    #[test]
+    fn certificate_expiration_is_a_timestamp() {
+        let valid_to = DateTime::parse_from_rfc3339("2030-01-01T00:00:00Z")
+            .unwrap()
+            .with_timezone(&Utc);
+        set_certificate_expiration("test-ca", valid_to);
+
+        assert!(encoded().contains(
+            r#"edgelet_certificate_expiration_timestamp_seconds{certificate="test-ca"} 1893456000"#
+        ));
+    }
+}
@@ -12,10 +12,13 @@ use chrono::prelude::*;
 use failure::{Fail, ResultExt};
 use futures::{Future, Stream};
 use serde_json;
+use tokio::io::{AsyncRead, AsyncWrite};
 
 use edgelet_utils::{ensure_not_empty_with_context, serialize_ordered};
 
+use crate::crypto::{Decrypt, Encrypt, MakeRandom};
 use crate::error::{Error, ErrorKind, Result};
+use crate::network::Network;
 use crate::settings::RuntimeSettings;
 use crate::GetTrustBundle;
 
@@ -57,6 +60,7 @@ pub struct ModuleRuntimeState {
     finished_at: Option<DateTime<Utc>>,
     image_id: Option<String>,
     pid: Option<i32>,
+    restart_count: Option<u32>,
 }
 
 impl Default for ModuleRuntimeState {
@@ -69,6 +73,7 @@ impl Default for ModuleRuntimeState {
             finished_at: None,
             image_id: None,
             pid: None,
+            restart_count: None,
         }
     }
 }
@@ -136,6 +141,16 @@ impl ModuleRuntimeState {
         self.pid = pid;
         self
     }
+
+    /// The number of times the runtime has restarted the module since it was created.
+    pub fn restart_count(&self) -> Option<u32> {
+        self.restart_count
+    }
+
+    pub fn with_restart_count(mut self, restart_count: Option<u32>) -> Self {
+        self.restart_count = restart_count;
+        self
+    }
 }
 
 #[derive(serde_derive::Deserialize, Debug, serde_derive::Serialize)]
@@ -326,6 +341,47 @@ impl LogOptions {
     }
 }
 
+/// A command run in a module by [`ModuleRuntime::exec`].
+#[derive(Clone, Debug, Default)]
+pub struct ExecOptions {
+    command: Vec<String>,
+    tty: bool,
+    tty_size: Option<(u16, u16)>,
+}
+
+impl ExecOptions {
+    pub fn new(command: Vec<String>) -> Self {
+        ExecOptions {
+            command,
+            tty: false,
+            tty_size: None,
+        }
+    }
+
+    pub fn with_tty(mut self, tty: bool) -> Self {
+        self.tty = tty;
+        self
+    }
+
+    /// The initial height and width of the TTY, in characters.
+    pub fn with_tty_size(mut self, height: u16, width: u16) -> Self {
+        self.tty_size = Some((height, width));
+        self
+    }
+
+    pub fn command(&self) -> &[String] {
+        &self.command
+    }
+
+    pub fn tty(&self) -> bool {
+        self.tty
+    }
+
+    pub fn tty_size(&self) -> Option<(u16, u16)> {
+        self.tty_size
+    }
+}
+
 pub trait Module {
     type Config;
     type Error: Fail;
@@ -341,10 +397,17 @@ pub trait ModuleRegistry {
     type Error: Fail;
     type PullFuture: Future<Item = (), Error = Self::Error> + Send;
     type RemoveFuture: Future<Item = (), Error = Self::Error>;
+    type LoginFuture: Future<Item = (), Error = Self::Error> + Send;
+    type LogoutFuture: Future<Item = (), Error = Self::Error> + Send;
     type Config;
 
     fn pull(&self, config: &Self::Config) -> Self::PullFuture;
     fn remove(&self, name: &str) -> Self::RemoveFuture;
+    /// Stores the credentials that images from `registry` are pulled with when their config
+    /// doesn't have any. `registry` is the host of the registry, with its port if it has one.
+    fn login(&self, registry: &str, username: &str, password: &str) -> Self::LoginFuture;
+    /// Forgets the credentials that were stored for `registry` by `login`.
+    fn logout(&self, registry: &str) -> Self::LogoutFuture;
 }
 
 #[derive(Debug)]
@@ -387,7 +450,9 @@ pub struct SystemResources {
     used_ram: u64,
     total_ram: u64,
     disks: Vec<DiskInfo>,
-    docker_stats: String,
+    modules: Vec<ModuleResourceUsage>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    docker_stats: Option<String>,
 }
 
 impl SystemResources {
@@ -398,7 +463,7 @@ impl SystemResources {
         used_ram: u64,
         total_ram: u64,
         disks: Vec<DiskInfo>,
-        docker_stats: String,
+        modules: Vec<ModuleResourceUsage>,
     ) -> Self {
         SystemResources {
             host_uptime,
@@ -407,9 +472,51 @@ impl SystemResources {
             used_ram,
             total_ram,
             disks,
-            docker_stats,
+            modules,
+            docker_stats: None,
         }
     }
+
+    pub fn host_uptime(&self) -> u64 {
+        self.host_uptime
+    }
+
+    pub fn process_uptime(&self) -> u64 {
+        self.process_uptime
+    }
+
+    pub fn used_cpu(&self) -> f64 {
+        self.used_cpu
+    }
+
+    pub fn used_ram(&self) -> u64 {
+        self.used_ram
+    }
+
+    pub fn total_ram(&self) -> u64 {
+        self.total_ram
+    }
+
+    pub fn disks(&self) -> &[DiskInfo] {
+        &self.disks
+    }
+
+    pub fn modules(&self) -> &[ModuleResourceUsage] {
+        &self.modules
+    }
+
+    /// The raw container stats of every module, as returned by the Docker engine.
+    ///
+    /// This is only served on management API versions older than 2020-07-07, which predate `modules`.
+    /// Runtimes other than Docker don't set it.
+    pub fn docker_stats(&self) -> Option<&str> {
+        self.docker_stats.as_ref().map(AsRef::as_ref)
+    }
+
+    pub fn with_docker_stats(mut self, docker_stats: Option<String>) -> Self {
+        self.docker_stats = docker_stats;
+        self
+    }
 }
 
 #[derive(Debug, serde_derive::Serialize)]
@@ -437,6 +544,120 @@ impl DiskInfo {
             file_type,
         }
     }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn available_space(&self) -> u64 {
+        self.available_space
+    }
+
+    pub fn total_space(&self) -> u64 {
+        self.total_space
+    }
+
+    pub fn file_system(&self) -> &str {
+        &self.file_system
+    }
+
+    pub fn file_type(&self) -> &str {
+        &self.file_type
+    }
+}
+
+/// The resources used by a single module, in a form that every module runtime can report.
+///
+/// Values that the runtime could not measure are `None`.
+#[derive(Clone, Debug, Default, PartialEq, serde_derive::Serialize)]
+pub struct ModuleResourceUsage {
+    name: String,
+    cpu_percent: Option<f64>,
+    memory_usage: Option<u64>,
+    memory_limit: Option<u64>,
+    network_rx_bytes: u64,
+    network_tx_bytes: u64,
+    block_read_bytes: u64,
+    block_write_bytes: u64,
+    pids: Option<u64>,
+}
+
+impl ModuleResourceUsage {
+    pub fn new(name: String) -> Self {
+        ModuleResourceUsage {
+            name,
+            ..ModuleResourceUsage::default()
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// The CPU usage of the module as a percentage of a single CPU, so a module fully using two CPUs is at 200%.
+    pub fn cpu_percent(&self) -> Option<f64> {
+        self.cpu_percent
+    }
+
+    pub fn with_cpu_percent(mut self, cpu_percent: Option<f64>) -> Self {
+        self.cpu_percent = cpu_percent;
+        self
+    }
+
+    pub fn memory_usage(&self) -> Option<u64> {
+        self.memory_usage
+    }
+
+    pub fn with_memory_usage(mut self, memory_usage: Option<u64>) -> Self {
+        self.memory_usage = memory_usage;
+        self
+    }
+
+    pub fn memory_limit(&self) -> Option<u64> {
+        self.memory_limit
+    }
+
+    pub fn with_memory_limit(mut self, memory_limit: Option<u64>) -> Self {
+        self.memory_limit = memory_limit;
+        self
+    }
+
+    pub fn network_rx_bytes(&self) -> u64 {
+        self.network_rx_bytes
+    }
+
+    pub fn network_tx_bytes(&self) -> u64 {
+        self.network_tx_bytes
+    }
+
+    pub fn with_network_io(mut self, rx_bytes: u64, tx_bytes: u64) -> Self {
+        self.network_rx_bytes = rx_bytes;
+        self.network_tx_bytes = tx_bytes;
+        self
+    }
+
+    pub fn block_read_bytes(&self) -> u64 {
+        self.block_read_bytes
+    }
+
+    pub fn block_write_bytes(&self) -> u64 {
+        self.block_write_bytes
+    }
+
+    pub fn with_block_io(mut self, read_bytes: u64, write_bytes: u64) -> Self {
+        self.block_read_bytes = read_bytes;
+        self.block_write_bytes = write_bytes;
+        self
+    }
+
+    pub fn pids(&self) -> Option<u64> {
+        self.pids
+    }
+
+    pub fn with_pids(mut self, pids: Option<u64>) -> Self {
+        self.pids = pids;
+        self
+    }
 }
 
 #[derive(Debug)]
@@ -461,6 +682,43 @@ impl ModuleTop {
     }
 }
 
+/// A change in the lifecycle of a module, as reported by the module runtime.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ModuleEvent {
+    name: String,
+    kind: ModuleEventKind,
+    time: DateTime<Utc>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ModuleEventKind {
+    Created,
+    Started,
+    /// The module exited with this exit code.
+    Died(i64),
+    OomKilled,
+    Restarted,
+    Removed,
+}
+
+impl ModuleEvent {
+    pub fn new(name: String, kind: ModuleEventKind, time: DateTime<Utc>) -> Self {
+        ModuleEvent { name, kind, time }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn kind(&self) -> ModuleEventKind {
+        self.kind
+    }
+
+    pub fn time(&self) -> &DateTime<Utc> {
+        &self.time
+    }
+}
+
 pub trait ProvisioningResult {
     fn device_id(&self) -> &str;
     fn hub_name(&self) -> &str;
@@ -477,7 +735,7 @@ pub trait MakeModuleRuntime {
     fn make_runtime(
         settings: Self::Settings,
         provisioning_result: Self::ProvisioningResult,
-        crypto: impl GetTrustBundle + Send + 'static,
+        crypto: impl GetTrustBundle + Encrypt + Decrypt + MakeRandom + Send + Sync + 'static,
     ) -> Self::Future;
 }
 
@@ -489,6 +747,8 @@ pub trait ModuleRuntime: Sized {
     type ModuleRegistry: ModuleRegistry<Config = Self::Config, Error = Self::Error>;
     type Chunk: AsRef<[u8]>;
     type Logs: Stream<Item = Self::Chunk, Error = Self::Error> + Send;
+    type Events: Stream<Item = ModuleEvent, Error = Self::Error> + Send;
+    type ExecStream: AsyncRead + AsyncWrite + Send + 'static;
 
     type CreateFuture: Future<Item = (), Error = Self::Error> + Send;
     type GetFuture: Future<Item = (Self::Module, ModuleRuntimeState), Error = Self::Error> + Send;
@@ -496,6 +756,10 @@ pub trait ModuleRuntime: Sized {
     type ListWithDetailsStream: Stream<Item = (Self::Module, ModuleRuntimeState), Error = Self::Error>
         + Send;
     type LogsFuture: Future<Item = Self::Logs, Error = Self::Error> + Send;
+    type EventsFuture: Future<Item = Self::Events, Error = Self::Error> + Send;
+    type ExecFuture: Future<Item = (String, Self::ExecStream), Error = Self::Error> + Send;
+    type ExecResizeFuture: Future<Item = (), Error = Self::Error> + Send;
+    type ExecExitCodeFuture: Future<Item = Option<i64>, Error = Self::Error> + Send;
     type RemoveFuture: Future<Item = (), Error = Self::Error> + Send;
     type RestartFuture: Future<Item = (), Error = Self::Error> + Send;
     type StartFuture: Future<Item = (), Error = Self::Error> + Send;
@@ -503,6 +767,9 @@ pub trait ModuleRuntime: Sized {
     type SystemInfoFuture: Future<Item = SystemInfo, Error = Self::Error> + Send;
     type SystemResourcesFuture: Future<Item = SystemResources, Error = Self::Error> + Send;
     type RemoveAllFuture: Future<Item = (), Error = Self::Error> + Send;
+    type ListNetworksFuture: Future<Item = Vec<Network>, Error = Self::Error> + Send;
+    type AttachNetworkFuture: Future<Item = (), Error = Self::Error> + Send;
+    type DetachNetworkFuture: Future<Item = (), Error = Self::Error> + Send;
 
     fn create(&self, module: ModuleSpec<Self::Config>) -> Self::CreateFuture;
     fn get(&self, id: &str) -> Self::GetFuture;
@@ -515,13 +782,39 @@ pub trait ModuleRuntime: Sized {
     fn list(&self) -> Self::ListFuture;
     fn list_with_details(&self) -> Self::ListWithDetailsStream;
     fn logs(&self, id: &str, options: &LogOptions) -> Self::LogsFuture;
+    /// Streams the lifecycle events of the modules, starting with the events since `since`
+    /// that the runtime still remembers, if set.
+    fn events(&self, since: Option<DateTime<Utc>>) -> Self::EventsFuture;
+    /// Runs a command in a running module, and resolves to the id of the command and its stream.
+    /// The input of the command is written to the stream and its output is read from it. Without
+    /// a TTY, stdout and stderr are multiplexed the same way as logs, so they can be read with
+    /// `LogDecode`.
+    fn exec(&self, id: &str, options: &ExecOptions) -> Self::ExecFuture;
+    /// Resizes the TTY of a command started by [`ModuleRuntime::exec`] in the module.
+    fn exec_resize(
+        &self,
+        id: &str,
+        exec_id: &str,
+        height: u16,
+        width: u16,
+    ) -> Self::ExecResizeFuture;
+    /// Gets the exit code of a command started by [`ModuleRuntime::exec`] in the module, or
+    /// `None` while it's still running.
+    fn exec_exit_code(&self, id: &str, exec_id: &str) -> Self::ExecExitCodeFuture;
     fn registry(&self) -> &Self::ModuleRegistry;
     fn remove_all(&self) -> Self::RemoveAllFuture;
+    /// Lists the networks that the runtime manages, with the modules that are attached to them.
+    fn list_networks(&self) -> Self::ListNetworksFuture;
+    /// Attaches a module to one of the networks that the runtime manages.
+    fn attach_network(&self, id: &str, network: &str) -> Self::AttachNetworkFuture;
+    /// Detaches a module from one of the networks that the runtime manages.
+    fn detach_network(&self, id: &str, network: &str) -> Self::DetachNetworkFuture;
 }
 
 #[derive(Clone, Copy, Debug)]
 pub enum ModuleRuntimeErrorReason {
     NotFound,
+    PolicyViolation,
     Other,
 }
 
@@ -542,6 +835,8 @@ impl fmt::Display for ModuleOperation {
 // Useful for error contexts
 #[derive(Clone, Debug)]
 pub enum RegistryOperation {
+    Login(String),
+    Logout(String),
     PullImage(String),
     RemoveImage(String),
 }
@@ -549,6 +844,12 @@ pub enum RegistryOperation {
 impl fmt::Display for RegistryOperation {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
+            RegistryOperation::Login(registry) => {
+                write!(f, "Could not log in to registry {}", registry)
+            }
+            RegistryOperation::Logout(registry) => {
+                write!(f, "Could not log out of registry {}", registry)
+            }
             RegistryOperation::PullImage(name) => write!(f, "Could not pull image {}", name),
             RegistryOperation::RemoveImage(name) => write!(f, "Could not remove image {}", name),
         }
@@ -558,11 +859,18 @@ impl fmt::Display for RegistryOperation {
 // Useful for error contexts
 #[derive(Clone, Debug, PartialEq)]
 pub enum RuntimeOperation {
+    AttachNetwork(String, String),
     CreateModule(String),
+    DetachNetwork(String, String),
     GetModule(String),
+    ExecModule(String),
+    ExecModuleExitCode(String),
+    ExecModuleResize(String),
+    GetModuleEvents,
     GetModuleLogs(String),
     Init,
     ListModules,
+    ListNetworks,
     RemoveModule(String),
     RestartModule(String),
     StartModule(String),
@@ -575,13 +883,36 @@ pub enum RuntimeOperation {
 impl fmt::Display for RuntimeOperation {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
+            RuntimeOperation::AttachNetwork(name, network) => {
+                write!(f, "Could not attach module {} to network {}", name, network)
+            }
             RuntimeOperation::CreateModule(name) => write!(f, "Could not create module {}", name),
+            RuntimeOperation::DetachNetwork(name, network) => write!(
+                f,
+                "Could not detach module {} from network {}",
+                name, network
+            ),
             RuntimeOperation::GetModule(name) => write!(f, "Could not get module {}", name),
+            RuntimeOperation::ExecModule(name) => {
+                write!(f, "Could not run command in module {}", name)
+            }
+            RuntimeOperation::ExecModuleExitCode(name) => write!(
+                f,
+                "Could not get the exit code of the command in module {}",
+                name
+            ),
+            RuntimeOperation::ExecModuleResize(name) => write!(
+                f,
+                "Could not resize the terminal of the command in module {}",
+                name
+            ),
+            RuntimeOperation::GetModuleEvents => write!(f, "Could not get module events"),
             RuntimeOperation::GetModuleLogs(name) => {
                 write!(f, "Could not get logs for module {}", name)
             }
             RuntimeOperation::Init => write!(f, "Could not initialize module runtime"),
             RuntimeOperation::ListModules => write!(f, "Could not list modules"),
+            RuntimeOperation::ListNetworks => write!(f, "Could not list networks"),
             RuntimeOperation::RemoveModule(name) => write!(f, "Could not remove module {}", name),
             RuntimeOperation::RestartModule(name) => write!(f, "Could not restart module {}", name),
             RuntimeOperation::StartModule(name) => write!(f, "Could not start module {}", name),
@@ -761,4 +1092,31 @@ mod tests {
             current_value_architecture_type
         );
     }
+
+    #[test]
+    fn system_resources_modules() {
+        let system_resources = SystemResources::new(
+            0,
+            0,
+            0.0,
+            0,
+            0,
+            vec![],
+            vec![
+                ModuleResourceUsage::new("edgeAgent".to_string()).with_pids(Some(3)),
+                ModuleResourceUsage::new("edgeHub".to_string()).with_network_io(10, 20),
+            ],
+        );
+
+        assert_eq!(None, system_resources.docker_stats());
+
+        let modules = system_resources.modules();
+        assert_eq!(2, modules.len());
+        assert_eq!("edgeAgent", modules[0].name());
+        assert_eq!(Some(3), modules[0].pids());
+        assert_eq!(None, modules[0].cpu_percent());
+        assert_eq!("edgeHub", modules[1].name());
+        assert_eq!(10, modules[1].network_rx_bytes());
+        assert_eq!(20, modules[1].network_tx_bytes());
+    }
 }
@@ -1,24 +1,46 @@
 // Copyright (c) Microsoft. All rights reserved.
 
+use std::collections::BTreeMap;
+
 use crate::DEFAULT_NETWORKID;
 
 #[derive(Clone, Debug, serde_derive::Deserialize, serde_derive::Serialize)]
 pub struct Network {
     name: String,
 
+    #[serde(rename = "driver", skip_serializing_if = "Option::is_none")]
+    driver: Option<String>,
+
+    #[serde(rename = "internal", skip_serializing_if = "Option::is_none")]
+    internal: Option<bool>,
+
     #[serde(rename = "ipv6", skip_serializing_if = "Option::is_none")]
     ipv6: Option<bool>,
 
     #[serde(rename = "ipam", skip_serializing_if = "Option::is_none")]
     ipam: Option<Ipam>,
+
+    #[serde(
+        rename = "options",
+        default,
+        skip_serializing_if = "BTreeMap::is_empty"
+    )]
+    options: BTreeMap<String, String>,
+
+    #[serde(rename = "modules", default, skip_serializing_if = "Vec::is_empty")]
+    modules: Vec<String>,
 }
 
 impl Network {
     pub fn new(name: String) -> Self {
         Network {
             name,
+            driver: None,
+            internal: None,
             ipv6: None,
             ipam: None,
+            options: BTreeMap::new(),
+            modules: vec![],
         }
     }
 
@@ -31,6 +53,28 @@ impl Network {
         self
     }
 
+    /// The driver of the network, such as `bridge` or `macvlan`. The container engine's default
+    /// driver is used if this isn't set.
+    pub fn driver(&self) -> Option<&str> {
+        self.driver.as_ref().map(AsRef::as_ref)
+    }
+
+    pub fn with_driver(mut self, driver: Option<String>) -> Self {
+        self.driver = driver;
+        self
+    }
+
+    /// Whether the network is isolated from the host's networks, so that its modules can only
+    /// reach each other.
+    pub fn internal(&self) -> Option<bool> {
+        self.internal
+    }
+
+    pub fn with_internal(mut self, internal: Option<bool>) -> Self {
+        self.internal = internal;
+        self
+    }
+
     pub fn ipv6(&self) -> Option<bool> {
         self.ipv6
     }
@@ -48,6 +92,28 @@ impl Network {
         self.ipam = Some(ipam);
         self
     }
+
+    /// Driver-specific options of the network, such as the `parent` interface of a `macvlan`
+    /// network.
+    pub fn options(&self) -> &BTreeMap<String, String> {
+        &self.options
+    }
+
+    pub fn with_options(mut self, options: BTreeMap<String, String>) -> Self {
+        self.options = options;
+        self
+    }
+
+    /// The modules on the network. In the settings, these are the modules to attach to the
+    /// network when they're created.
+    pub fn modules(&self) -> &[String] {
+        &self.modules
+    }
+
+    pub fn with_modules(mut self, modules: Vec<String>) -> Self {
+        self.modules = modules;
+        self
+    }
 }
 
 #[derive(Clone, Debug, Default, serde_derive::Deserialize, PartialEq, serde_derive::Serialize)]
@@ -128,6 +194,14 @@ impl MobyNetwork {
             MobyNetwork::Network(network) => &network.name,
         }
     }
+
+    /// The settings of the network, which are just its name if only the name was configured.
+    pub fn network(&self) -> Network {
+        match self {
+            MobyNetwork::Name(_) => Network::new(self.name().to_string()),
+            MobyNetwork::Network(network) => network.clone(),
+        }
+    }
 }
 
 #[cfg(test)]
@@ -160,13 +234,23 @@ mod tests {
         let ipam = Ipam::default().with_config(vec![ipam_config.clone()]);
         let network_name = "my-network";
         let ipv6 = true;
+        let mut options = BTreeMap::new();
+        options.insert("parent".to_string(), "eth1".to_string());
         let network = Network::new(network_name.to_string())
+            .with_driver(Some("macvlan".to_string()))
+            .with_internal(Some(true))
             .with_ipv6(Some(ipv6))
-            .with_ipam(ipam.clone());
+            .with_ipam(ipam.clone())
+            .with_options(options.clone())
+            .with_modules(vec!["opcpublisher".to_string()]);
 
         assert_eq!(network_name, network.name());
+        assert_eq!("macvlan", network.driver().unwrap());
+        assert_eq!(true, network.internal().unwrap());
         assert_eq!(ipv6, network.ipv6().unwrap());
         assert_eq!(ipam, network.ipam().unwrap().clone());
+        assert_eq!(&options, network.options());
+        assert_eq!(&["opcpublisher".to_string()], network.modules());
     }
 
     #[test]
@@ -182,5 +266,12 @@ mod tests {
         assert_eq!(DEFAULT_NETWORKID, moby_network_with_no_name.name());
         assert_eq!(moby_1, moby_network_with_name.name());
         assert_eq!(moby_2, moby_network_config.name());
+
+        assert_eq!(
+            DEFAULT_NETWORKID,
+            moby_network_with_no_name.network().name()
+        );
+        assert_eq!(moby_1, moby_network_with_name.network().name());
+        assert_eq!(moby_2, moby_network_config.network().name());
     }
 }
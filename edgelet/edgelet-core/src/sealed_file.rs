@@ -0,0 +1,218 @@
+// Copyright (c) Microsoft. All rights reserved.
+
+use std::fs::{self, OpenOptions};
+use std::io::{self, Write};
+#[cfg(unix)]
+use std::os::unix::fs::OpenOptionsExt;
+use std::path::{Path, PathBuf};
+
+use failure::{Fail, ResultExt};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+use crate::crypto::{Decrypt, Encrypt, MakeRandom};
+use crate::error::{Error, ErrorKind};
+
+const IV_LEN_BYTES: usize = 16;
+
+/// A file holding a value serialized as JSON and encrypted with the master encryption key.
+///
+/// The file is a random IV followed by the ciphertext, and only the current user can read it.
+/// It is written to a temporary file that is renamed over it, so that a power cut never leaves
+/// a truncated file behind.
+pub struct SealedFile {
+    path: PathBuf,
+    client_id: &'static str,
+    cipher: Box<dyn Cipher + Send + Sync>,
+}
+
+impl SealedFile {
+    /// `client_id` is the client ID the contents are encrypted under, and must be unique to what
+    /// the file holds.
+    pub fn new<C>(path: PathBuf, client_id: &'static str, crypto: C) -> Self
+    where
+        C: 'static + Encrypt + Decrypt + MakeRandom + Send + Sync,
+    {
+        SealedFile {
+            path,
+            client_id,
+            cipher: Box::new(crypto),
+        }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Reads and decrypts the file, returning `None` if it doesn't exist.
+    pub fn load<T>(&self) -> Result<Option<T>, Error>
+    where
+        T: DeserializeOwned,
+    {
+        let sealed = match fs::read(&self.path) {
+            Ok(sealed) => sealed,
+            Err(ref err) if err.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(err) => return Err(Error::from(err.context(self.error()))),
+        };
+        if sealed.len() < IV_LEN_BYTES {
+            return Err(Error::from(self.error()));
+        }
+
+        let (iv, ciphertext) = sealed.split_at(IV_LEN_BYTES);
+        let plaintext = self
+            .cipher
+            .open(self.client_id.as_bytes(), iv, ciphertext)
+            .context(self.error())?;
+        let value = serde_json::from_slice(&plaintext).context(self.error())?;
+        Ok(Some(value))
+    }
+
+    /// Encrypts `value` with a new IV and replaces the file with it.
+    pub fn save<T>(&self, value: &T) -> Result<(), Error>
+    where
+        T: Serialize,
+    {
+        let plaintext = serde_json::to_vec(value).context(self.error())?;
+        let sealed = self
+            .cipher
+            .seal(self.client_id.as_bytes(), &plaintext)
+            .context(self.error())?;
+
+        let mut temp_path = self.path.as_os_str().to_owned();
+        temp_path.push(".tmp");
+        let temp_path = PathBuf::from(temp_path);
+
+        let mut options = OpenOptions::new();
+        options.write(true).create(true).truncate(true);
+        #[cfg(unix)]
+        options.mode(0o600);
+
+        let mut file = options.open(&temp_path).context(self.error())?;
+        file.write_all(&sealed).context(self.error())?;
+        file.sync_all().context(self.error())?;
+        fs::rename(&temp_path, &self.path).context(self.error())?;
+        Ok(())
+    }
+
+    fn error(&self) -> ErrorKind {
+        ErrorKind::SealedFile(self.path.display().to_string())
+    }
+}
+
+// Erases the crypto type, so that the owners of a sealed file don't need to be generic over it.
+trait Cipher {
+    fn seal(&self, client_id: &[u8], plaintext: &[u8]) -> Result<Vec<u8>, Error>;
+    fn open(&self, client_id: &[u8], iv: &[u8], ciphertext: &[u8]) -> Result<Vec<u8>, Error>;
+}
+
+impl<C> Cipher for C
+where
+    C: Encrypt + Decrypt + MakeRandom,
+{
+    fn seal(&self, client_id: &[u8], plaintext: &[u8]) -> Result<Vec<u8>, Error> {
+        let mut sealed = vec![0; IV_LEN_BYTES];
+        self.get_random_bytes(&mut sealed)?;
+        let ciphertext = self.encrypt(client_id, plaintext, &sealed)?;
+        sealed.extend_from_slice(ciphertext.as_ref());
+        Ok(sealed)
+    }
+
+    fn open(&self, client_id: &[u8], iv: &[u8], ciphertext: &[u8]) -> Result<Vec<u8>, Error> {
+        let plaintext = self.decrypt(client_id, ciphertext, iv)?;
+        Ok(plaintext.as_ref().to_vec())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use tempdir::TempDir;
+
+    use super::*;
+
+    /// Encrypts by XORing with the IV, which is enough to tell plaintext and ciphertext apart.
+    struct TestCrypto;
+
+    impl MakeRandom for TestCrypto {
+        fn get_random_bytes(&self, buffer: &mut [u8]) -> Result<(), Error> {
+            for b in buffer.iter_mut() {
+                *b = 0x5a;
+            }
+            Ok(())
+        }
+    }
+
+    impl Encrypt for TestCrypto {
+        type Buffer = Vec<u8>;
+
+        fn encrypt(
+            &self,
+            _client_id: &[u8],
+            plaintext: &[u8],
+            initialization_vector: &[u8],
+        ) -> Result<Self::Buffer, Error> {
+            Ok(xor(plaintext, initialization_vector))
+        }
+    }
+
+    impl Decrypt for TestCrypto {
+        type Buffer = Vec<u8>;
+
+        fn decrypt(
+            &self,
+            _client_id: &[u8],
+            ciphertext: &[u8],
+            initialization_vector: &[u8],
+        ) -> Result<Self::Buffer, Error> {
+            Ok(xor(ciphertext, initialization_vector))
+        }
+    }
+
+    fn xor(data: &[u8], iv: &[u8]) -> Vec<u8> {
+        data.iter()
+            .zip(iv.iter().cycle())
+            .map(|(d, i)| d ^ i)
+            .collect()
+    }
+
+    #[test]
+    fn save_then_load() {
+        let dir = TempDir::new("sealed-file").unwrap();
+        let file = SealedFile::new(dir.path().join("sealed"), "$test", TestCrypto);
+
+        assert_eq!(None, file.load::<BTreeMap<String, String>>().unwrap());
+
+        let value: BTreeMap<String, String> = vec![("key".to_string(), "secret".to_string())]
+            .into_iter()
+            .collect();
+        file.save(&value).unwrap();
+
+        let contents = fs::read(file.path()).unwrap();
+        assert!(!String::from_utf8_lossy(&contents).contains("secret"));
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+
+            let mode = fs::metadata(file.path()).unwrap().permissions().mode();
+            assert_eq!(0o600, mode & 0o777);
+        }
+
+        assert_eq!(Some(value), file.load().unwrap());
+    }
+
+    #[test]
+    fn load_fails_for_truncated_file() {
+        let dir = TempDir::new("sealed-file").unwrap();
+        let path = dir.path().join("sealed");
+        fs::write(&path, b"short").unwrap();
+
+        let file = SealedFile::new(path.clone(), "$test", TestCrypto);
+        let err = file.load::<BTreeMap<String, String>>().unwrap_err();
+        if let ErrorKind::SealedFile(err_path) = err.kind() {
+            assert_eq!(&path.display().to_string(), err_path);
+        } else {
+            panic!("Expected a SealedFile error, but got {:?}", err);
+        }
+    }
+}
@@ -19,6 +19,9 @@ const DEVICEID_KEY: &str = "DeviceId";
 const HOSTNAME_KEY: &str = "HostName";
 const SHAREDACCESSKEY_KEY: &str = "SharedAccessKey";
 
+/// URI scheme of private keys kept on a PKCS#11 token
+pub const PKCS11_URI_SCHEME: &str = "pkcs11";
+
 const DEVICEID_REGEX: &str = r"^[A-Za-z0-9\-:.+%_#*?!(),=@;$']{1,128}$";
 const HOSTNAME_REGEX: &str = r"^[a-zA-Z0-9_\-\.]+$";
 
@@ -57,7 +60,7 @@ impl ManualX509Auth {
     }
 
     pub fn identity_pk_uri(&self) -> Result<&Url, Error> {
-        if is_supported_uri(&self.identity_pk) {
+        if is_supported_key_uri(&self.identity_pk) {
             Ok(&self.identity_pk)
         } else {
             Err(Error::from(ErrorKind::UnsupportedSettingsUri(
@@ -96,6 +99,26 @@ impl ManualDeviceConnectionString {
         &self.device_connection_string
     }
 
+    /// Returns a copy of this connection string that uses `key` as its shared access key.
+    pub fn with_shared_access_key(&self, key: &[u8]) -> Self {
+        let key = base64::encode(key);
+        let device_connection_string = self
+            .device_connection_string
+            .split(';')
+            .map(|p| {
+                if p.split('=').next() == Some(SHAREDACCESSKEY_KEY) {
+                    format!("{}={}", SHAREDACCESSKEY_KEY, key)
+                } else {
+                    p.to_string()
+                }
+            })
+            .collect::<Vec<_>>()
+            .join(";");
+        ManualDeviceConnectionString {
+            device_connection_string,
+        }
+    }
+
     pub fn parse_device_connection_string(&self) -> Result<(MemoryKey, String, String), Error> {
         if self.device_connection_string.is_empty() {
             return Err(Error::from(ErrorKind::ConnectionStringEmpty));
@@ -177,6 +200,8 @@ pub enum ManualAuthMethod {
 #[serde(rename_all = "lowercase")]
 pub struct Manual {
     authentication: ManualAuthMethod,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    fallback_authentication: Vec<ManualAuthMethod>,
 }
 
 impl<'de> serde::Deserialize<'de> for Manual {
@@ -190,6 +215,8 @@ impl<'de> serde::Deserialize<'de> for Manual {
             device_connection_string: Option<String>,
             #[serde(skip_serializing_if = "Option::is_none")]
             authentication: Option<ManualAuthMethod>,
+            #[serde(default)]
+            fallback_authentication: Vec<ManualAuthMethod>,
         }
 
         let value: Inner = serde::Deserialize::deserialize(deserializer)?;
@@ -211,18 +238,53 @@ impl<'de> serde::Deserialize<'de> for Manual {
             }
         };
 
-        Ok(Manual { authentication })
+        for fallback in &value.fallback_authentication {
+            match (&authentication, fallback) {
+                (
+                    ManualAuthMethod::DeviceConnectionString(_),
+                    ManualAuthMethod::DeviceConnectionString(_),
+                ) => (),
+                (ManualAuthMethod::X509(primary), ManualAuthMethod::X509(fallback))
+                    if primary.identity_cert == fallback.identity_cert
+                        && primary.identity_pk == fallback.identity_pk => {}
+                _ => {
+                    return Err(serde::de::Error::custom(
+                        "Every entry of provisioning.fallback_authentication must use the same method, and for x509 the same identity certificate and private key, as provisioning.authentication in the config.yaml.",
+                    ));
+                }
+            }
+        }
+
+        Ok(Manual {
+            authentication,
+            fallback_authentication: value.fallback_authentication,
+        })
     }
 }
 
 impl Manual {
     pub fn new(authentication: ManualAuthMethod) -> Self {
-        Manual { authentication }
+        Manual {
+            authentication,
+            fallback_authentication: Vec::new(),
+        }
     }
 
     pub fn authentication_method(&self) -> &ManualAuthMethod {
         &self.authentication
     }
+
+    /// The IoT hubs, in order, that the device fails over to when the hub of
+    /// `authentication_method` can't be reached
+    pub fn fallback_authentication_methods(&self) -> &[ManualAuthMethod] {
+        &self.fallback_authentication
+    }
+
+    /// The authentication method of every IoT hub the device is registered in, starting
+    /// with the primary hub
+    pub fn authentication_methods(&self) -> impl Iterator<Item = &ManualAuthMethod> {
+        std::iter::once(&self.authentication).chain(&self.fallback_authentication)
+    }
 }
 
 #[derive(Clone, Debug, serde_derive::Deserialize, serde_derive::Serialize)]
@@ -239,16 +301,25 @@ pub enum AttestationMethod {
 #[serde(rename_all = "lowercase")]
 pub struct TpmAttestationInfo {
     registration_id: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    payload: Option<serde_json::Value>,
 }
 
 impl TpmAttestationInfo {
     pub fn new(registration_id: String) -> Self {
-        TpmAttestationInfo { registration_id }
+        TpmAttestationInfo {
+            registration_id,
+            payload: None,
+        }
     }
 
     pub fn registration_id(&self) -> &str {
         &self.registration_id
     }
+
+    pub fn payload(&self) -> Option<&serde_json::Value> {
+        self.payload.as_ref()
+    }
 }
 
 #[derive(Clone, Debug, serde_derive::Deserialize, serde_derive::Serialize)]
@@ -256,6 +327,8 @@ impl TpmAttestationInfo {
 pub struct SymmetricKeyAttestationInfo {
     registration_id: String,
     symmetric_key: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    payload: Option<serde_json::Value>,
 }
 
 impl SymmetricKeyAttestationInfo {
@@ -266,6 +339,10 @@ impl SymmetricKeyAttestationInfo {
     pub fn symmetric_key(&self) -> &str {
         &self.symmetric_key
     }
+
+    pub fn payload(&self) -> Option<&serde_json::Value> {
+        self.payload.as_ref()
+    }
 }
 
 #[derive(Clone, Debug, serde_derive::Deserialize, serde_derive::Serialize)]
@@ -277,6 +354,8 @@ pub struct X509AttestationInfo {
     identity_cert: Url,
     #[serde(with = "url_serde")]
     identity_pk: Url,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    payload: Option<serde_json::Value>,
 }
 
 impl X509AttestationInfo {
@@ -292,7 +371,7 @@ impl X509AttestationInfo {
     }
 
     pub fn identity_pk_uri(&self) -> Result<&Url, Error> {
-        if is_supported_uri(&self.identity_pk) {
+        if is_supported_key_uri(&self.identity_pk) {
             Ok(&self.identity_pk)
         } else {
             Err(Error::from(ErrorKind::UnsupportedSettingsUri(
@@ -316,6 +395,10 @@ impl X509AttestationInfo {
     pub fn registration_id(&self) -> Option<&str> {
         self.registration_id.as_ref().map(AsRef::as_ref)
     }
+
+    pub fn payload(&self) -> Option<&serde_json::Value> {
+        self.payload.as_ref()
+    }
 }
 
 #[derive(Clone, Debug, serde_derive::Serialize)]
@@ -454,6 +537,12 @@ pub struct Listen {
     management_uri: Url,
     #[serde(default = "Protocol::default")]
     min_tls_version: Protocol,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    management_client_ca: Option<PathBuf>,
+    #[serde(default, with = "url_serde", skip_serializing_if = "Option::is_none")]
+    metrics_uri: Option<Url>,
+    #[serde(default)]
+    workload_rate_limits: WorkloadRateLimits,
 }
 
 impl Listen {
@@ -468,6 +557,134 @@ impl Listen {
     pub fn min_tls_version(&self) -> Protocol {
         self.min_tls_version
     }
+
+    /// CA certificates that issue client certificates for the management API. When set, an
+    /// `https` management endpoint only accepts clients presenting a certificate issued by them.
+    pub fn management_client_ca(&self) -> Option<&Path> {
+        self.management_client_ca.as_ref().map(PathBuf::as_path)
+    }
+
+    /// Where metrics are served in the Prometheus text format. No metrics are served when unset.
+    pub fn metrics_uri(&self) -> Option<&Url> {
+        self.metrics_uri.as_ref()
+    }
+
+    pub fn workload_rate_limits(&self) -> &WorkloadRateLimits {
+        &self.workload_rate_limits
+    }
+}
+
+/// Limits on how often, and how many at once, each module may call a group of workload API
+/// routes.
+#[derive(Clone, Debug, PartialEq, serde_derive::Serialize)]
+pub struct RateLimit {
+    requests_per_second: f64,
+    burst: u32,
+    max_concurrent_requests: u32,
+}
+
+impl<'de> serde::Deserialize<'de> for RateLimit {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        #[derive(Debug, serde_derive::Deserialize)]
+        struct Inner {
+            requests_per_second: f64,
+            burst: u32,
+            max_concurrent_requests: u32,
+        }
+
+        let value: Inner = serde::Deserialize::deserialize(deserializer)?;
+
+        // A module without any allowance could never call the routes, and the time it is asked to
+        // wait is computed from requests_per_second.
+        if !(value.requests_per_second.is_finite() && value.requests_per_second > 0.0) {
+            return Err(serde::de::Error::custom(format!(
+                "Rate limit requests_per_second must be a number greater than 0, but is {}",
+                value.requests_per_second
+            )));
+        }
+        if value.burst < 1 {
+            return Err(serde::de::Error::custom(
+                "Rate limit burst must be at least 1",
+            ));
+        }
+        if value.max_concurrent_requests < 1 {
+            return Err(serde::de::Error::custom(
+                "Rate limit max_concurrent_requests must be at least 1",
+            ));
+        }
+
+        Ok(RateLimit::new(
+            value.requests_per_second,
+            value.burst,
+            value.max_concurrent_requests,
+        ))
+    }
+}
+
+impl RateLimit {
+    pub fn new(requests_per_second: f64, burst: u32, max_concurrent_requests: u32) -> Self {
+        RateLimit {
+            requests_per_second,
+            burst,
+            max_concurrent_requests,
+        }
+    }
+
+    /// The rate at which a module's allowance of requests is replenished.
+    pub fn requests_per_second(&self) -> f64 {
+        self.requests_per_second
+    }
+
+    /// The number of requests a module may make in quick succession before being limited to
+    /// `requests_per_second`.
+    pub fn burst(&self) -> u32 {
+        self.burst
+    }
+
+    /// The number of requests from a module that may be in progress at the same time.
+    pub fn max_concurrent_requests(&self) -> u32 {
+        self.max_concurrent_requests
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, serde_derive::Deserialize, serde_derive::Serialize)]
+pub struct WorkloadRateLimits {
+    /// Limits for the sign, encrypt and decrypt routes.
+    #[serde(default = "WorkloadRateLimits::default_crypto")]
+    crypto: RateLimit,
+    /// Limits for the identity and server certificate routes.
+    #[serde(default = "WorkloadRateLimits::default_certificates")]
+    certificates: RateLimit,
+}
+
+impl WorkloadRateLimits {
+    pub fn crypto(&self) -> &RateLimit {
+        &self.crypto
+    }
+
+    pub fn certificates(&self) -> &RateLimit {
+        &self.certificates
+    }
+
+    fn default_crypto() -> RateLimit {
+        RateLimit::new(20.0, 40, 8)
+    }
+
+    fn default_certificates() -> RateLimit {
+        RateLimit::new(1.0, 10, 2)
+    }
+}
+
+impl Default for WorkloadRateLimits {
+    fn default() -> Self {
+        WorkloadRateLimits {
+            crypto: WorkloadRateLimits::default_crypto(),
+            certificates: WorkloadRateLimits::default_certificates(),
+        }
+    }
 }
 
 #[derive(Clone, Copy, Debug, PartialEq)]
@@ -549,6 +766,12 @@ fn is_supported_uri(uri: &Url) -> bool {
     false
 }
 
+/// Private keys can also be kept on a PKCS#11 token, in which case they are referred to by a
+/// `pkcs11:` URI (RFC 7512) instead of a file.
+fn is_supported_key_uri(uri: &Url) -> bool {
+    is_supported_uri(uri) || uri.scheme() == PKCS11_URI_SCHEME
+}
+
 fn get_path_from_uri(uri: &Url, setting_name: &'static str) -> Result<PathBuf, Error> {
     if is_supported_uri(&uri) {
         let path = uri
@@ -605,6 +828,13 @@ fn convert_to_uri(maybe_uri: &str, setting_name: &'static str) -> Result<Url, Er
     }
 }
 
+fn convert_to_key_uri(maybe_uri: &str, setting_name: &'static str) -> Result<Url, Error> {
+    match Url::parse(maybe_uri) {
+        Ok(ref uri) if uri.scheme() == PKCS11_URI_SCHEME => Ok(uri.clone()),
+        _ => convert_to_uri(maybe_uri, setting_name),
+    }
+}
+
 impl DeviceCertificate {
     pub fn device_ca_cert(&self) -> Result<PathBuf, Error> {
         convert_to_path(&self.device_ca_cert, "certificates.device_ca_cert")
@@ -623,7 +853,7 @@ impl DeviceCertificate {
     }
 
     pub fn device_ca_pk_uri(&self) -> Result<Url, Error> {
-        convert_to_uri(&self.device_ca_pk, "certificates.device_ca_pk")
+        convert_to_key_uri(&self.device_ca_pk, "certificates.device_ca_pk")
     }
 
     pub fn trusted_ca_certs_uri(&self) -> Result<Url, Error> {
@@ -683,6 +913,7 @@ pub trait RuntimeSettings {
     fn agent(&self) -> &ModuleSpec<Self::Config>;
     fn agent_mut(&mut self) -> &mut ModuleSpec<Self::Config>;
     fn hostname(&self) -> &str;
+    fn parent_hostname(&self) -> Option<&str>;
     fn connect(&self) -> &Connect;
     fn listen(&self) -> &Listen;
     fn homedir(&self) -> &Path;
@@ -695,6 +926,9 @@ pub struct Settings<T> {
     provisioning: Provisioning,
     agent: ModuleSpec<T>,
     hostname: String,
+    /// The hostname of the IoT Edge device this device reaches IoT Hub through,
+    /// for devices in a nested (layered) network
+    parent_hostname: Option<String>,
     connect: Connect,
     listen: Listen,
     homedir: PathBuf,
@@ -725,6 +959,10 @@ where
         &self.hostname
     }
 
+    fn parent_hostname(&self) -> Option<&str> {
+        self.parent_hostname.as_ref().map(AsRef::as_ref)
+    }
+
     fn connect(&self) -> &Connect {
         &self.connect
     }
@@ -759,6 +997,50 @@ mod tests {
 
     use super::*;
 
+    #[test]
+    fn workload_rate_limits_default_per_group() {
+        let limits: WorkloadRateLimits = serde_json::from_str(
+            r#"{ "certificates": { "requests_per_second": 0.5, "burst": 2, "max_concurrent_requests": 1 } }"#,
+        )
+        .unwrap();
+        assert_eq!(&WorkloadRateLimits::default_crypto(), limits.crypto());
+        assert_eq!(&RateLimit::new(0.5, 2, 1), limits.certificates());
+    }
+
+    #[test_case(-1.0, 2, 1; "when requests_per_second is negative")]
+    #[test_case(0.0, 2, 1; "when requests_per_second is zero")]
+    #[test_case(0.5, 0, 1; "when burst is zero")]
+    #[test_case(0.5, 2, 0; "when max_concurrent_requests is zero")]
+    fn invalid_rate_limit_is_rejected(
+        requests_per_second: f64,
+        burst: u32,
+        max_concurrent_requests: u32,
+    ) {
+        let value = serde_json::json!({
+            "requests_per_second": requests_per_second,
+            "burst": burst,
+            "max_concurrent_requests": max_concurrent_requests,
+        });
+        assert!(serde_json::from_value::<RateLimit>(value).is_err());
+    }
+
+    #[test]
+    fn with_shared_access_key_replaces_only_the_key() {
+        let connection_string = ManualDeviceConnectionString::new(
+            "HostName=hub.azure-devices.net;DeviceId=d1;SharedAccessKey=b2xkIGtleSEh".to_string(),
+        );
+        let rotated = connection_string.with_shared_access_key(b"new key!!");
+        assert_eq!(
+            "HostName=hub.azure-devices.net;DeviceId=d1;SharedAccessKey=bmV3IGtleSEh",
+            rotated.device_connection_string()
+        );
+
+        let (key, device_id, hub) = rotated.parse_device_connection_string().unwrap();
+        assert_eq!(b"new key!!".as_ref(), key.as_ref());
+        assert_eq!("d1", device_id);
+        assert_eq!("hub.azure-devices.net", hub);
+    }
+
     #[test]
     fn test_convert_to_path() {
         if cfg!(windows) {
@@ -914,6 +1196,26 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_convert_to_key_uri() {
+        let uri = "pkcs11:token=edge;object=device-ca?module-path=/usr/lib/softhsm/libsofthsm2.so";
+        assert_eq!(
+            Url::parse(uri).unwrap(),
+            convert_to_key_uri(uri, "test").unwrap()
+        );
+        convert_to_path(uri, "test").expect_err("PKCS#11 URI is not a file path");
+        convert_to_uri(uri, "test").expect_err("PKCS#11 URI is not a file URI");
+        convert_to_key_uri("https://localhost/tmp/sample.txt", "test")
+            .expect_err("Non file or pkcs11 scheme specified");
+
+        if cfg!(not(windows)) {
+            assert_eq!(
+                Url::parse("file:///tmp/sample.txt").unwrap(),
+                convert_to_key_uri("/tmp/sample.txt", "test").unwrap()
+            );
+        }
+    }
+
     #[test_case("tls", Protocol::Tls10; "when tls provided")]
     #[test_case("TLS", Protocol::Tls10; "when uppercase TLS provided")]
     #[test_case("tls1", Protocol::Tls10; "when tls1 provided")]
@@ -14,6 +14,7 @@ use edgelet_utils::log_failure;
 
 use crate::error::{Error, ErrorKind};
 use crate::identity::{Identity, IdentityManager, IdentitySpec};
+use crate::metrics;
 use crate::module::{
     ImagePullPolicy, Module, ModuleRegistry, ModuleRuntime, ModuleRuntimeErrorReason, ModuleSpec,
     ModuleStatus,
@@ -182,6 +183,7 @@ where
                         "Edge runtime status is {}, starting module now...",
                         *state.status(),
                     );
+                    metrics::observe_watchdog_restart(&module);
                     future::Either::B(
                         runtime
                             .start(&module)
@@ -255,6 +257,7 @@ where
 {
     let module_name = spec.name().to_string();
     info!("Creating and starting edge runtime module {}", module_name);
+    metrics::observe_watchdog_creation(&module_name);
     let runtime_copy = runtime.clone();
 
     let mut id_mgr = id_mgr.clone();
@@ -0,0 +1,379 @@
+// Copyright (c) Microsoft. All rights reserved.
+
+//! Typed representation of the container stats returned by the Docker engine's `GET /containers/{id}/stats` API.
+//!
+//! The fields of Linux and Windows containers differ, so every field is optional or defaulted.
+//! The methods on [`ContainerStats`] compute the same values as the `docker stats` CLI.
+
+use std::collections::BTreeMap;
+
+use chrono::{DateTime, Utc};
+use serde_derive::{Deserialize, Serialize};
+
+use edgelet_core::ModuleResourceUsage;
+
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+pub struct ContainerStats {
+    #[serde(default)]
+    name: String,
+    #[serde(default)]
+    id: String,
+    #[serde(default)]
+    read: Option<DateTime<Utc>>,
+    #[serde(default)]
+    preread: Option<DateTime<Utc>>,
+    #[serde(default)]
+    num_procs: u32,
+    #[serde(default)]
+    cpu_stats: CpuStats,
+    #[serde(default)]
+    precpu_stats: CpuStats,
+    #[serde(default)]
+    memory_stats: MemoryStats,
+    #[serde(default)]
+    networks: Option<BTreeMap<String, NetworkStats>>,
+    #[serde(default)]
+    blkio_stats: BlkioStats,
+    #[serde(default)]
+    storage_stats: StorageStats,
+    #[serde(default)]
+    pids_stats: PidsStats,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+pub struct CpuStats {
+    #[serde(default)]
+    cpu_usage: CpuUsage,
+    #[serde(default)]
+    system_cpu_usage: Option<u64>,
+    #[serde(default)]
+    online_cpus: Option<u32>,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+pub struct CpuUsage {
+    #[serde(default)]
+    total_usage: u64,
+    #[serde(default)]
+    percpu_usage: Option<Vec<u64>>,
+    #[serde(default)]
+    usage_in_kernelmode: u64,
+    #[serde(default)]
+    usage_in_usermode: u64,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+pub struct MemoryStats {
+    #[serde(default)]
+    usage: Option<u64>,
+    #[serde(default)]
+    max_usage: Option<u64>,
+    #[serde(default)]
+    limit: Option<u64>,
+    #[serde(default)]
+    stats: Option<BTreeMap<String, u64>>,
+
+    // Windows only
+    #[serde(default)]
+    privateworkingset: Option<u64>,
+    #[serde(default)]
+    commitbytes: Option<u64>,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+pub struct NetworkStats {
+    #[serde(default)]
+    rx_bytes: u64,
+    #[serde(default)]
+    rx_packets: u64,
+    #[serde(default)]
+    rx_errors: u64,
+    #[serde(default)]
+    rx_dropped: u64,
+    #[serde(default)]
+    tx_bytes: u64,
+    #[serde(default)]
+    tx_packets: u64,
+    #[serde(default)]
+    tx_errors: u64,
+    #[serde(default)]
+    tx_dropped: u64,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+pub struct BlkioStats {
+    #[serde(default)]
+    io_service_bytes_recursive: Option<Vec<BlkioStatEntry>>,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+pub struct BlkioStatEntry {
+    #[serde(default)]
+    major: u64,
+    #[serde(default)]
+    minor: u64,
+    #[serde(default)]
+    op: String,
+    #[serde(default)]
+    value: u64,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+pub struct StorageStats {
+    #[serde(default)]
+    read_size_bytes: Option<u64>,
+    #[serde(default)]
+    write_size_bytes: Option<u64>,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+pub struct PidsStats {
+    #[serde(default)]
+    current: Option<u64>,
+    #[serde(default)]
+    limit: Option<u64>,
+}
+
+impl ContainerStats {
+    /// The name of the container, without the leading `/` that Docker adds on Linux.
+    pub fn name(&self) -> &str {
+        self.name.trim_start_matches('/')
+    }
+
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    pub fn read(&self) -> Option<&DateTime<Utc>> {
+        self.read.as_ref()
+    }
+
+    /// The CPU usage of the container between `precpu_stats` and `cpu_stats`, as a percentage of a single CPU.
+    /// So a container fully using two CPUs has a usage of 200%.
+    ///
+    /// Returns `None` if the stats do not contain a previous sample to compare against.
+    #[allow(clippy::cast_possible_truncation, clippy::cast_precision_loss)]
+    pub fn cpu_percent(&self) -> Option<f64> {
+        let cpu_delta = self
+            .cpu_stats
+            .cpu_usage
+            .total_usage
+            .checked_sub(self.precpu_stats.cpu_usage.total_usage)?;
+
+        if self.num_procs > 0 {
+            // Windows reports usage in 100ns intervals, so compare it against the wall clock time between samples.
+            let read = self.read?;
+            let preread = self.preread?;
+            let interval_100ns = (read - preread).num_nanoseconds()? / 100;
+            if interval_100ns <= 0 {
+                return None;
+            }
+
+            let possible_intervals = interval_100ns as f64 * f64::from(self.num_procs);
+            Some(cpu_delta as f64 / possible_intervals * 100.0)
+        } else {
+            let system_delta = self
+                .cpu_stats
+                .system_cpu_usage?
+                .checked_sub(self.precpu_stats.system_cpu_usage?)?;
+            if system_delta == 0 {
+                return None;
+            }
+
+            let online_cpus = self.cpu_stats.online_cpus.unwrap_or_else(|| {
+                self.cpu_stats
+                    .cpu_usage
+                    .percpu_usage
+                    .as_ref()
+                    .map_or(1, |percpu_usage| percpu_usage.len() as u32)
+            });
+
+            Some(cpu_delta as f64 / system_delta as f64 * f64::from(online_cpus) * 100.0)
+        }
+    }
+
+    /// The memory used by the container in bytes, excluding the page cache on Linux.
+    pub fn memory_usage(&self) -> Option<u64> {
+        if let Some(privateworkingset) = self.memory_stats.privateworkingset {
+            return Some(privateworkingset);
+        }
+
+        let usage = self.memory_stats.usage?;
+        let cache = self.memory_stats.stats.as_ref().and_then(|stats| {
+            // cgroup v1 reports "cache", cgroup v2 reports "inactive_file"
+            stats
+                .get("cache")
+                .or_else(|| stats.get("inactive_file"))
+                .copied()
+        });
+        Some(usage.saturating_sub(cache.unwrap_or_default()))
+    }
+
+    /// The memory limit of the container in bytes.
+    pub fn memory_limit(&self) -> Option<u64> {
+        self.memory_stats.limit
+    }
+
+    /// The total bytes received and transmitted over all of the container's network interfaces.
+    pub fn network_io(&self) -> (u64, u64) {
+        self.networks
+            .iter()
+            .flat_map(BTreeMap::values)
+            .fold((0, 0), |(rx, tx), network| {
+                (rx + network.rx_bytes, tx + network.tx_bytes)
+            })
+    }
+
+    /// The total bytes read from and written to block devices by the container.
+    pub fn block_io(&self) -> (u64, u64) {
+        if let (Some(read), Some(write)) = (
+            self.storage_stats.read_size_bytes,
+            self.storage_stats.write_size_bytes,
+        ) {
+            return (read, write);
+        }
+
+        self.blkio_stats
+            .io_service_bytes_recursive
+            .iter()
+            .flatten()
+            .fold((0, 0), |(read, write), entry| {
+                if entry.op.eq_ignore_ascii_case("read") {
+                    (read + entry.value, write)
+                } else if entry.op.eq_ignore_ascii_case("write") {
+                    (read, write + entry.value)
+                } else {
+                    (read, write)
+                }
+            })
+    }
+
+    /// The number of processes or threads in the container.
+    pub fn pids(&self) -> Option<u64> {
+        self.pids_stats.current
+    }
+}
+
+impl<'a> From<&'a ContainerStats> for ModuleResourceUsage {
+    fn from(stats: &'a ContainerStats) -> Self {
+        let (network_rx_bytes, network_tx_bytes) = stats.network_io();
+        let (block_read_bytes, block_write_bytes) = stats.block_io();
+
+        ModuleResourceUsage::new(stats.name().to_string())
+            .with_cpu_percent(stats.cpu_percent())
+            .with_memory_usage(stats.memory_usage())
+            .with_memory_limit(stats.memory_limit())
+            .with_network_io(network_rx_bytes, network_tx_bytes)
+            .with_block_io(block_read_bytes, block_write_bytes)
+            .with_pids(stats.pids())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LINUX_STATS: &str = r#"{
+        "read": "2019-12-05T20:40:02.535112Z",
+        "preread": "2019-12-05T20:40:01.530484Z",
+        "pids_stats": { "current": 7 },
+        "blkio_stats": {
+            "io_service_bytes_recursive": [
+                { "major": 8, "minor": 0, "op": "Read", "value": 4096 },
+                { "major": 8, "minor": 0, "op": "Write", "value": 1024 },
+                { "major": 8, "minor": 0, "op": "Sync", "value": 5120 },
+                { "major": 8, "minor": 0, "op": "Total", "value": 5120 }
+            ]
+        },
+        "num_procs": 0,
+        "storage_stats": {},
+        "cpu_stats": {
+            "cpu_usage": { "total_usage": 2000000000, "usage_in_kernelmode": 0, "usage_in_usermode": 0 },
+            "system_cpu_usage": 20000000000,
+            "online_cpus": 4
+        },
+        "precpu_stats": {
+            "cpu_usage": { "total_usage": 1000000000, "usage_in_kernelmode": 0, "usage_in_usermode": 0 },
+            "system_cpu_usage": 10000000000,
+            "online_cpus": 4
+        },
+        "memory_stats": {
+            "usage": 10485760,
+            "max_usage": 20971520,
+            "stats": { "cache": 2097152 },
+            "limit": 1073741824
+        },
+        "name": "/tempSensor",
+        "id": "3c9d2e5b",
+        "networks": {
+            "eth0": { "rx_bytes": 100, "rx_packets": 1, "rx_errors": 0, "rx_dropped": 0, "tx_bytes": 200, "tx_packets": 2, "tx_errors": 0, "tx_dropped": 0 },
+            "eth1": { "rx_bytes": 10, "rx_packets": 1, "rx_errors": 0, "rx_dropped": 0, "tx_bytes": 20, "tx_packets": 2, "tx_errors": 0, "tx_dropped": 0 }
+        }
+    }"#;
+
+    const WINDOWS_STATS: &str = r#"{
+        "read": "2019-12-05T20:40:02.0000000Z",
+        "preread": "2019-12-05T20:40:01.0000000Z",
+        "pids_stats": {},
+        "blkio_stats": { "io_service_bytes_recursive": null },
+        "num_procs": 2,
+        "storage_stats": { "read_size_bytes": 300, "write_size_bytes": 400 },
+        "cpu_stats": { "cpu_usage": { "total_usage": 15000000 } },
+        "precpu_stats": { "cpu_usage": { "total_usage": 5000000 } },
+        "memory_stats": { "commitbytes": 5000, "privateworkingset": 4000 },
+        "name": "edgeHub",
+        "id": "8a7f",
+        "networks": {}
+    }"#;
+
+    #[test]
+    fn parse_linux_stats() {
+        let stats: ContainerStats = serde_json::from_str(LINUX_STATS).unwrap();
+
+        assert_eq!("tempSensor", stats.name());
+        assert_eq!(Some(40.0), stats.cpu_percent());
+        assert_eq!(Some(8_388_608), stats.memory_usage());
+        assert_eq!(Some(1_073_741_824), stats.memory_limit());
+        assert_eq!((110, 220), stats.network_io());
+        assert_eq!((4096, 1024), stats.block_io());
+        assert_eq!(Some(7), stats.pids());
+    }
+
+    #[test]
+    fn into_module_resource_usage() {
+        let stats: ContainerStats = serde_json::from_str(LINUX_STATS).unwrap();
+        let usage = ModuleResourceUsage::from(&stats);
+
+        assert_eq!("tempSensor", usage.name());
+        assert_eq!(Some(40.0), usage.cpu_percent());
+        assert_eq!(Some(8_388_608), usage.memory_usage());
+        assert_eq!(Some(1_073_741_824), usage.memory_limit());
+        assert_eq!(110, usage.network_rx_bytes());
+        assert_eq!(220, usage.network_tx_bytes());
+        assert_eq!(4096, usage.block_read_bytes());
+        assert_eq!(1024, usage.block_write_bytes());
+        assert_eq!(Some(7), usage.pids());
+    }
+
+    #[test]
+    fn parse_windows_stats() {
+        let stats: ContainerStats = serde_json::from_str(WINDOWS_STATS).unwrap();
+
+        assert_eq!("edgeHub", stats.name());
+        assert_eq!(Some(50.0), stats.cpu_percent());
+        assert_eq!(Some(4000), stats.memory_usage());
+        assert_eq!(None, stats.memory_limit());
+        assert_eq!((0, 0), stats.network_io());
+        assert_eq!((300, 400), stats.block_io());
+        assert_eq!(None, stats.pids());
+    }
+
+    #[test]
+    fn cpu_percent_without_previous_sample() {
+        let stats: ContainerStats =
+            serde_json::from_str(r#"{ "cpu_stats": { "cpu_usage": { "total_usage": 5 } } }"#)
+                .unwrap();
+
+        assert_eq!(None, stats.cpu_percent());
+    }
+}
@@ -0,0 +1,361 @@
+// Copyright (c) Microsoft. All rights reserved.
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::io::Write;
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+use std::sync::Mutex;
+use std::thread;
+
+use failure::{Fail, ResultExt};
+use futures::future::{self, Either};
+use futures::sync::oneshot;
+use futures::Future;
+use log::{debug, warn, Level};
+
+use containrs::Reference;
+use docker::models::AuthConfig;
+use edgelet_core::crypto::{Decrypt, Encrypt, MakeRandom};
+use edgelet_core::SealedFile;
+use edgelet_utils::log_failure;
+
+use crate::error::{Error, ErrorKind, Result};
+
+/// Client ID the stored credentials are encrypted under with the master encryption key
+const REGISTRY_CREDENTIALS_CRYPTO_ID: &str = "$iotedge-registry-credentials";
+
+/// Credential helpers are binaries named `docker-credential-<helper>`, as with the Docker CLI.
+const CREDENTIAL_HELPER_PREFIX: &str = "docker-credential-";
+
+/// What credential helpers print on stdout when they have no credentials for a registry.
+const CREDENTIALS_NOT_FOUND: &str = "credentials not found in native keychain";
+
+/// The hosts that Docker Hub images are pulled from, all stored as the first one.
+const DOCKER_HUB_HOSTS: &[&str] = &["docker.io", "index.docker.io", "registry-1.docker.io"];
+
+/// A username and password for a registry.
+#[derive(Clone, PartialEq, serde_derive::Deserialize, serde_derive::Serialize)]
+pub struct RegistryCredentials {
+    username: String,
+    password: String,
+}
+
+impl RegistryCredentials {
+    pub fn new(username: String, password: String) -> Self {
+        RegistryCredentials { username, password }
+    }
+
+    pub fn username(&self) -> &str {
+        &self.username
+    }
+
+    pub fn password(&self) -> &str {
+        &self.password
+    }
+
+    fn to_auth(&self, registry: &str) -> AuthConfig {
+        AuthConfig::new()
+            .with_username(self.username.clone())
+            .with_password(self.password.clone())
+            .with_serveraddress(registry.to_string())
+    }
+}
+
+// Settings are logged, so the password is left out.
+impl fmt::Debug for RegistryCredentials {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RegistryCredentials")
+            .field("username", &self.username)
+            .finish()
+    }
+}
+
+/// The output of `docker-credential-<helper> get`.
+#[derive(serde_derive::Deserialize)]
+struct HelperCredentials {
+    #[serde(rename = "Username")]
+    username: String,
+    #[serde(rename = "Secret")]
+    secret: String,
+}
+
+/// The credentials that images are pulled with when the config of their module has no `auth`,
+/// keyed by registry host.
+///
+/// Credentials are looked up in this order:
+/// - the ones stored by `iotedge registry login`, which are kept in a file encrypted with the
+///   device's master encryption key,
+/// - the ones in the `registry_credentials` setting,
+/// - the credential helper for the registry in the `credential_helpers` setting.
+pub(crate) struct RegistryCredentialStore {
+    file: SealedFile,
+    configured: BTreeMap<String, RegistryCredentials>,
+    helpers: BTreeMap<String, String>,
+    logins: Mutex<BTreeMap<String, RegistryCredentials>>,
+}
+
+impl RegistryCredentialStore {
+    /// Opens the store at `path`. A store that can't be read or decrypted (for example because
+    /// the master encryption key was regenerated) is discarded rather than treated as an error,
+    /// so the registries have to be logged in to again.
+    pub fn new<C>(
+        path: PathBuf,
+        configured: &BTreeMap<String, RegistryCredentials>,
+        helpers: &BTreeMap<String, String>,
+        crypto: C,
+    ) -> Self
+    where
+        C: 'static + Encrypt + Decrypt + MakeRandom + Send + Sync,
+    {
+        let file = SealedFile::new(path, REGISTRY_CREDENTIALS_CRYPTO_ID, crypto);
+        let logins = match file.load().context(ErrorKind::RegistryCredentialStore) {
+            Ok(logins) => logins.unwrap_or_default(),
+            Err(err) => {
+                warn!(
+                    "Discarding the registry credential store at {}",
+                    file.path().display()
+                );
+                log_failure(Level::Warn, &err);
+                BTreeMap::new()
+            }
+        };
+
+        RegistryCredentialStore {
+            file,
+            configured: configured
+                .iter()
+                .map(|(registry, credentials)| (normalize_registry(registry), credentials.clone()))
+                .collect(),
+            helpers: helpers
+                .iter()
+                .map(|(registry, helper)| (normalize_registry(registry), helper.clone()))
+                .collect(),
+            logins: Mutex::new(logins),
+        }
+    }
+
+    pub fn login(&self, registry: &str, credentials: RegistryCredentials) -> Result<()> {
+        self.update(|logins| {
+            logins.insert(normalize_registry(registry), credentials);
+        })
+    }
+
+    pub fn logout(&self, registry: &str) -> Result<()> {
+        self.update(|logins| {
+            logins.remove(&normalize_registry(registry));
+        })
+    }
+
+    /// Gets the credentials to pull `image` with. Images whose registry has no credentials,
+    /// or whose name can't be parsed, are pulled anonymously.
+    pub fn auth(
+        &self,
+        image: &str,
+    ) -> impl Future<Item = Option<AuthConfig>, Error = Error> + Send {
+        let registry = match image.parse::<Reference>() {
+            Ok(reference) => normalize_registry(reference.registry()),
+            Err(_) => return Either::A(future::ok(None)),
+        };
+
+        let stored = self
+            .logins
+            .lock()
+            .expect("registry credential store lock poisoned")
+            .get(&registry)
+            .or_else(|| self.configured.get(&registry))
+            .map(|credentials| credentials.to_auth(&registry));
+        if stored.is_some() {
+            return Either::A(future::ok(stored));
+        }
+
+        match self.helpers.get(&registry) {
+            Some(helper) => Either::B(run_helper(helper.clone(), registry)),
+            None => Either::A(future::ok(None)),
+        }
+    }
+
+    fn update<F>(&self, f: F) -> Result<()>
+    where
+        F: FnOnce(&mut BTreeMap<String, RegistryCredentials>),
+    {
+        let mut logins = self
+            .logins
+            .lock()
+            .expect("registry credential store lock poisoned");
+        let mut updated = logins.clone();
+        f(&mut updated);
+        self.file
+            .save(&updated)
+            .context(ErrorKind::RegistryCredentialStore)?;
+        *logins = updated;
+        Ok(())
+    }
+}
+
+/// Registries are keyed by lowercase host and port, without a scheme or path, so that
+/// `https://MyRegistry.azurecr.io/` and `myregistry.azurecr.io` are the same registry.
+fn normalize_registry(registry: &str) -> String {
+    let registry = registry.trim();
+    let registry = registry
+        .find("://")
+        .map_or(registry, |index| &registry[index + 3..]);
+    let registry = registry
+        .find('/')
+        .map_or(registry, |index| &registry[..index])
+        .to_lowercase();
+
+    if DOCKER_HUB_HOSTS.contains(&registry.as_str()) {
+        DOCKER_HUB_HOSTS[0].to_string()
+    } else {
+        registry
+    }
+}
+
+/// Runs `docker-credential-<helper> get` with the registry on stdin. The helper runs on its own
+/// thread so that it doesn't block the runtime while it waits for a keychain or a token service.
+fn run_helper(
+    helper: String,
+    registry: String,
+) -> impl Future<Item = Option<AuthConfig>, Error = Error> + Send {
+    let context = ErrorKind::CredentialHelper(helper.clone());
+
+    let (sender, receiver) = oneshot::channel();
+    thread::spawn(move || {
+        let _ = sender.send(get_from_helper(&helper, &registry));
+    });
+
+    receiver.then(move |auth| match auth {
+        Ok(Ok(auth)) => Ok(auth),
+        Ok(Err(err)) => Err(Error::from(err.context(context))),
+        Err(_) => Err(Error::from(context)),
+    })
+}
+
+fn get_from_helper(
+    helper: &str,
+    registry: &str,
+) -> std::result::Result<Option<AuthConfig>, failure::Error> {
+    debug!(
+        "Getting credentials for registry {} from credential helper {}",
+        registry, helper
+    );
+
+    let mut child = Command::new(format!("{}{}", CREDENTIAL_HELPER_PREFIX, helper))
+        .arg("get")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()?;
+    child
+        .stdin
+        .take()
+        .expect("stdin is piped")
+        .write_all(registry.as_bytes())?;
+    let output = child.wait_with_output()?;
+
+    if !output.status.success() {
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        if stdout.trim() == CREDENTIALS_NOT_FOUND {
+            return Ok(None);
+        }
+        return Err(failure::err_msg(format!(
+            "{} {}",
+            output.status,
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+
+    let credentials: HelperCredentials = serde_json::from_slice(&output.stdout)?;
+    Ok(Some(
+        RegistryCredentials::new(credentials.username, credentials.secret).to_auth(registry),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::fs;
+
+    use tempdir::TempDir;
+
+    use edgelet_test_utils::crypto::TestHsm;
+
+    fn store(path: PathBuf) -> RegistryCredentialStore {
+        let configured = vec![(
+            "Configured.azurecr.io".to_string(),
+            RegistryCredentials::new("config-user".to_string(), "config-pass".to_string()),
+        )]
+        .into_iter()
+        .collect();
+        RegistryCredentialStore::new(path, &configured, &BTreeMap::new(), TestHsm::default())
+    }
+
+    fn username(store: &RegistryCredentialStore, image: &str) -> Option<String> {
+        store
+            .auth(image)
+            .wait()
+            .unwrap()
+            .and_then(|auth| auth.username().map(ToString::to_string))
+    }
+
+    #[test]
+    fn registries_are_normalized() {
+        assert_eq!(
+            "myregistry.azurecr.io",
+            normalize_registry("https://MyRegistry.azurecr.io/")
+        );
+        assert_eq!("localhost:5000", normalize_registry("localhost:5000"));
+        assert_eq!("docker.io", normalize_registry("index.docker.io"));
+        assert_eq!("docker.io", normalize_registry("registry-1.docker.io"));
+    }
+
+    #[test]
+    fn logins_survive_restart_and_take_precedence() {
+        let dir = TempDir::new("registry-credentials").unwrap();
+        let path = dir.path().join("registry_credentials");
+
+        let first = store(path.clone());
+        assert_eq!(
+            Some("config-user".to_string()),
+            username(&first, "configured.azurecr.io/module:1.0")
+        );
+        first
+            .login(
+                "configured.azurecr.io",
+                RegistryCredentials::new("login-user".to_string(), "login-pass".to_string()),
+            )
+            .unwrap();
+        first
+            .login(
+                "docker.io",
+                RegistryCredentials::new("hub-user".to_string(), "hub-pass".to_string()),
+            )
+            .unwrap();
+
+        let second = store(path);
+        assert_eq!(
+            Some("login-user".to_string()),
+            username(&second, "configured.azurecr.io/module:1.0")
+        );
+        assert_eq!(Some("hub-user".to_string()), username(&second, "alpine"));
+        assert_eq!(None, username(&second, "other.azurecr.io/module:1.0"));
+
+        second.logout("configured.azurecr.io").unwrap();
+        assert_eq!(
+            Some("config-user".to_string()),
+            username(&second, "configured.azurecr.io/module:1.0")
+        );
+    }
+
+    #[test]
+    fn unreadable_store_is_discarded() {
+        let dir = TempDir::new("registry-credentials").unwrap();
+        let path = dir.path().join("registry_credentials");
+        fs::write(&path, b"short").unwrap();
+
+        let store = store(path);
+        assert_eq!(None, username(&store, "docker.io/library/alpine"));
+    }
+}
@@ -12,6 +12,8 @@ use edgelet_core::{
     ModuleOperation, ModuleRuntimeErrorReason, RegistryOperation, RuntimeOperation,
 };
 
+use crate::verification::ImageVerificationReason;
+
 pub type Result<T> = ::std::result::Result<T, Error>;
 
 #[derive(Debug)]
@@ -47,6 +49,15 @@ pub enum ErrorKind {
     #[fail(display = "Conflict with current operation")]
     Conflict,
 
+    #[fail(display = "Could not create volume {}", _0)]
+    CreateVolume(String),
+
+    #[fail(
+        display = "Could not get registry credentials from credential helper {}",
+        _0
+    )]
+    CredentialHelper(String),
+
     #[fail(display = "Container runtime error")]
     Docker,
 
@@ -71,20 +82,38 @@ pub enum ErrorKind {
     #[fail(display = "Invalid socket URI: {:?}", _0)]
     InvalidSocketUri(String),
 
+    #[fail(display = "Could not load trusted key {}", _0)]
+    LoadTrustedKey(String),
+
+    #[fail(display = "Could not list module volumes")]
+    ListVolumes,
+
     #[fail(display = "{}", _0)]
     ModuleOperation(ModuleOperation),
 
     #[fail(display = "{}", _0)]
     NotFound(String),
 
+    #[fail(display = "Create options are not allowed by the local policy: {}", _0)]
+    PolicyViolation(String),
+
     #[fail(display = "Target of operation already in this state")]
     NotModified,
 
+    #[fail(display = "Could not reconcile network {}", _0)]
+    ReconcileNetwork(String),
+
     #[fail(display = "{}", _0)]
     RegistryOperation(RegistryOperation),
 
+    #[fail(display = "Could not access the registry credential store")]
+    RegistryCredentialStore,
+
     #[fail(display = "{}", _0)]
     RuntimeOperation(RuntimeOperation),
+
+    #[fail(display = "Image {} could not be verified: {}", _0, _1)]
+    UnverifiedImage(String, ImageVerificationReason),
 }
 
 impl Fail for Error {
@@ -148,6 +177,9 @@ impl<'a> From<&'a Error> for ModuleRuntimeErrorReason {
     fn from(err: &'a Error) -> Self {
         match Fail::find_root_cause(err).downcast_ref::<ErrorKind>() {
             Some(ErrorKind::NotFound(_)) => ModuleRuntimeErrorReason::NotFound,
+            Some(ErrorKind::PolicyViolation(_)) | Some(ErrorKind::UnverifiedImage(..)) => {
+                ModuleRuntimeErrorReason::PolicyViolation
+            }
             _ => ModuleRuntimeErrorReason::Other,
         }
     }
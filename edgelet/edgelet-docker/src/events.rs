@@ -0,0 +1,151 @@
+// Copyright (c) Microsoft. All rights reserved.
+
+use std::convert::TryInto;
+
+use chrono::{TimeZone, Utc};
+use futures::{try_ready, Async, Poll, Stream};
+use log::debug;
+
+use docker::models::InlineResponse20012;
+use edgelet_core::{ModuleEvent, ModuleEventKind};
+
+const NANOS_PER_SECOND: i64 = 1_000_000_000;
+
+/// The Docker container actions that are reported as module events.
+pub(crate) const ACTIONS: &[&str] = &["create", "start", "die", "oom", "restart", "destroy"];
+
+/// Splits the body of a Docker events response, which has one JSON event per line, into lines.
+pub(crate) struct Lines<S> {
+    body: S,
+    buffer: Vec<u8>,
+    done: bool,
+}
+
+impl<S> Lines<S> {
+    pub(crate) fn new(body: S) -> Self {
+        Lines {
+            body,
+            buffer: vec![],
+            done: false,
+        }
+    }
+}
+
+impl<S> Stream for Lines<S>
+where
+    S: Stream,
+    S::Item: AsRef<[u8]>,
+{
+    type Item = Vec<u8>;
+    type Error = S::Error;
+
+    fn poll(&mut self) -> Poll<Option<Self::Item>, Self::Error> {
+        loop {
+            if let Some(end) = self.buffer.iter().position(|b| *b == b'\n') {
+                let line = self.buffer.drain(..=end).collect();
+                return Ok(Async::Ready(Some(line)));
+            }
+
+            if self.done {
+                let rest = if self.buffer.is_empty() {
+                    None
+                } else {
+                    Some(self.buffer.split_off(0))
+                };
+                return Ok(Async::Ready(rest));
+            }
+
+            match try_ready!(self.body.poll()) {
+                Some(chunk) => self.buffer.extend_from_slice(chunk.as_ref()),
+                None => self.done = true,
+            }
+        }
+    }
+}
+
+/// Converts a line of a Docker events response to a module event. Lines that are blank, or
+/// that are not about a module lifecycle change, convert to `None`.
+pub(crate) fn module_event(line: &[u8]) -> Option<ModuleEvent> {
+    if line.iter().all(u8::is_ascii_whitespace) {
+        return None;
+    }
+
+    let event: InlineResponse20012 = serde_json::from_slice(line)
+        .map_err(|err| debug!("Ignoring malformed Docker event: {}", err))
+        .ok()?;
+
+    let attributes = event.actor()?.attributes()?;
+    let name = attributes.get("name")?;
+    let kind = match event.action()? {
+        "create" => ModuleEventKind::Created,
+        "start" => ModuleEventKind::Started,
+        "die" => ModuleEventKind::Died(
+            attributes
+                .get("exitCode")
+                .and_then(|code| code.parse().ok())
+                .unwrap_or_default(),
+        ),
+        "oom" => ModuleEventKind::OomKilled,
+        "restart" => ModuleEventKind::Restarted,
+        "destroy" => ModuleEventKind::Removed,
+        _ => return None,
+    };
+    let time = match event.time_nano() {
+        Some(time_nano) => Utc.timestamp(
+            time_nano.div_euclid(NANOS_PER_SECOND),
+            time_nano.rem_euclid(NANOS_PER_SECOND).try_into().ok()?,
+        ),
+        None => Utc.timestamp(event.time()?.into(), 0),
+    };
+
+    Some(ModuleEvent::new(name.clone(), kind, time))
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::{stream, Future};
+
+    use super::*;
+
+    #[test]
+    fn lines_are_split_across_chunks() {
+        let chunks: Vec<&[u8]> = vec![b"{\"a\":1}\n{\"b\"", b":2}\n\n{\"c\":3}"];
+        let lines = Lines::new(stream::iter_ok::<_, ()>(chunks))
+            .collect()
+            .wait()
+            .unwrap();
+
+        assert_eq!(
+            vec![
+                b"{\"a\":1}\n".to_vec(),
+                b"{\"b\":2}\n".to_vec(),
+                b"\n".to_vec(),
+                b"{\"c\":3}".to_vec(),
+            ],
+            lines
+        );
+    }
+
+    #[test]
+    fn die_event_has_exit_code() {
+        let line = br#"{"Type":"container","Action":"die","Actor":{"ID":"abc","Attributes":{"exitCode":"137","name":"tempSensor"}},"time":1551885923,"timeNano":1551885923123456789}"#;
+
+        let event = module_event(line).unwrap();
+        assert_eq!("tempSensor", event.name());
+        assert_eq!(ModuleEventKind::Died(137), event.kind());
+        assert_eq!(1_551_885_923, event.time().timestamp());
+        assert_eq!(123_456_789, event.time().timestamp_subsec_nanos());
+    }
+
+    #[test]
+    fn other_events_are_ignored() {
+        assert_eq!(None, module_event(b"\n"));
+        assert_eq!(None, module_event(b"not json\n"));
+        assert_eq!(
+            None,
+            module_event(
+                br#"{"Type":"container","Action":"exec_start: sh","Actor":{"ID":"abc","Attributes":{"name":"tempSensor"}},"time":1551885923}"#
+            )
+        );
+    }
+}
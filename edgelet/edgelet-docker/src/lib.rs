@@ -10,13 +10,25 @@
 
 mod client;
 mod config;
+mod container_stats;
+mod credentials;
 mod error;
+mod events;
 mod module;
+mod network;
+mod policy;
 mod runtime;
 mod settings;
+mod verification;
+mod volumes;
 
 pub use crate::config::DockerConfig;
+pub use container_stats::ContainerStats;
+pub use credentials::RegistryCredentials;
 pub use error::{Error, ErrorKind};
 pub use module::{DockerModule, MODULE_TYPE};
+pub use policy::{AllowDeny, CreateOptionsPolicy};
 pub use runtime::DockerModuleRuntime;
 pub use settings::{LoadSettingsError, Settings, DEFAULTS};
+pub use verification::{ImageVerificationReason, ImageVerificationSettings};
+pub use volumes::{create_module_volume, list_module_volumes, ModuleVolume};
@@ -1,5 +1,6 @@
 // Copyright (c) Microsoft. All rights reserved.
 
+use std::convert::TryInto;
 use std::str::FromStr;
 
 use chrono::prelude::*;
@@ -137,6 +138,7 @@ fn status_from_exit_code(exit_code: Option<i64>) -> Option<ModuleStatus> {
 
 pub fn runtime_state(
     id: Option<&str>,
+    restart_count: Option<i32>,
     response_state: Option<&InlineResponse200State>,
 ) -> ModuleRuntimeState {
     response_state.map_or_else(ModuleRuntimeState::default, |state| {
@@ -167,6 +169,7 @@ pub fn runtime_state(
             )
             .with_image_id(id.map(ToOwned::to_owned))
             .with_pid(state.pid())
+            .with_restart_count(restart_count.and_then(|count| count.try_into().ok()))
     })
 }
 
@@ -193,7 +196,7 @@ impl<C: 'static + Connect> Module for DockerModule<C> {
             self.client
                 .container_api()
                 .container_inspect(&self.name, false)
-                .map(|resp| runtime_state(resp.id(), resp.state()))
+                .map(|resp| runtime_state(resp.id(), resp.restart_count(), resp.state()))
                 .map_err(|err| {
                     Error::from_docker_error(
                         err,
@@ -327,6 +330,7 @@ mod tests {
                             .with_pid(1234),
                     )
                     .with_id("mod1".to_string())
+                    .with_restart_count(3)
                     .with_exec_i_ds(vec!["id1".to_string(), "id2".to_string()]),
             ),
             "mod1".to_string(),
@@ -348,6 +352,7 @@ mod tests {
             runtime_state.finished_at().unwrap().to_rfc3339()
         );
         assert_eq!(Some(1234), runtime_state.pid());
+        assert_eq!(Some(3), runtime_state.restart_count());
     }
 
     #[test]
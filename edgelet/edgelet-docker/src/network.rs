@@ -0,0 +1,628 @@
+// Copyright (c) Microsoft. All rights reserved.
+
+use std::collections::{BTreeSet, HashMap};
+use std::ops::Deref;
+
+use failure::Fail;
+use futures::future::{self, Either};
+use futures::{stream, Future, Stream};
+use log::{info, warn, Level};
+
+use docker::models::{
+    Container, Container1, Ipam, Network as DockerNetwork, NetworkConfig, NetworkContainer,
+    NetworkSettings,
+};
+use edgelet_core::{Ipam as CoreIpam, IpamConfig, Network, RuntimeOperation};
+use edgelet_http::UrlConnector;
+use edgelet_utils::log_failure;
+
+use crate::client::DockerClient;
+use crate::error::{Error, ErrorKind};
+use crate::runtime::{LABELS, LABEL_KEY, LABEL_VALUE};
+
+/// Brings the container engine's networks in line with the settings.
+///
+/// The configured networks that are missing are created. The ones that iotedged created but
+/// that don't match their settings anymore are recreated if no modules are attached to them,
+/// and the ones whose settings were removed are deleted. Then the existing modules are attached
+/// to the networks that they're configured for.
+///
+/// `networks` starts with the network that the edge agent attaches the modules to, which is
+/// only ever created, like before there were other networks. Only failing to create it is an
+/// error. Failures with the other networks and with attaching modules to them are logged.
+pub(crate) fn reconcile_networks(
+    client: DockerClient<UrlConnector>,
+    networks: Vec<Network>,
+) -> impl Future<Item = (), Error = Error> + Send {
+    let reconcile_client = client.clone();
+    let list_client = client.clone();
+    let attach_networks = networks.clone();
+
+    client
+        .network_api()
+        .network_list("")
+        .map_err(|err| {
+            Error::from_docker_error(
+                err,
+                ErrorKind::RuntimeOperation(RuntimeOperation::ListNetworks),
+            )
+        })
+        .and_then(move |existing| {
+            let mut existing: HashMap<String, DockerNetwork> = existing
+                .into_iter()
+                .filter_map(|network| {
+                    network
+                        .name()
+                        .map(ToOwned::to_owned)
+                        .map(|name| (name, network))
+                })
+                .collect();
+
+            let stale: Vec<String> = existing
+                .values()
+                .filter(|network| is_managed(network))
+                .filter_map(DockerNetwork::name)
+                .filter(|name| !networks.iter().any(|network| network.name() == *name))
+                .map(ToOwned::to_owned)
+                .collect();
+
+            let client = reconcile_client;
+            let remove_client = client.clone();
+            let mut networks = networks.into_iter();
+            let default = match networks.next() {
+                Some(ref network) if !existing.contains_key(network.name()) => {
+                    Either::A(create_network(&client, network, false))
+                }
+                _ => Either::B(future::ok(())),
+            };
+
+            // The other networks only matter to the modules that are configured for them, so
+            // failing to reconcile one of them doesn't stop the runtime from starting.
+            default
+                .and_then(move |()| {
+                    stream::iter_ok(networks).for_each(move |network| {
+                        let existing = existing.remove(network.name());
+                        reconcile_network(client.clone(), network, existing).then(log_warning)
+                    })
+                })
+                .and_then(move |()| {
+                    stream::iter_ok(stale).for_each(move |name| {
+                        remove_network(&remove_client, name).then(log_warning)
+                    })
+                })
+        })
+        .and_then(move |()| {
+            // There's no need to look for the modules if none are configured for any network.
+            if attach_networks
+                .iter()
+                .all(|network| network.modules().is_empty())
+            {
+                return Either::A(future::ok(()));
+            }
+
+            let mut filters = HashMap::new();
+            filters.insert("label", LABELS.deref());
+            let filters = match serde_json::to_string(&filters) {
+                Ok(filters) => filters,
+                Err(err) => {
+                    return Either::A(future::result(log_warning(Err(Error::from(
+                        err.context(ErrorKind::RuntimeOperation(RuntimeOperation::ListModules)),
+                    )))))
+                }
+            };
+
+            let attach_client = list_client.clone();
+            let attached = list_client
+                .container_api()
+                .container_list(true, 0, false, &filters)
+                .map_err(|err| {
+                    Error::from_docker_error(
+                        err,
+                        ErrorKind::RuntimeOperation(RuntimeOperation::ListModules),
+                    )
+                })
+                .and_then(move |containers| {
+                    let modules = containers
+                        .iter()
+                        .filter_map(|container| container.names().first())
+                        .map(|name| name.trim_start_matches('/').to_string())
+                        .collect::<Vec<_>>();
+
+                    stream::iter_ok(modules).for_each(move |module| {
+                        let client = attach_client.clone();
+                        let networks = configured_networks(&attach_networks, &module);
+                        stream::iter_ok(networks).for_each(move |network| {
+                            attach_module(client.clone(), module.clone(), vec![network])
+                                .then(log_warning)
+                        })
+                    })
+                })
+                .then(log_warning);
+
+            Either::B(attached)
+        })
+}
+
+fn log_warning(result: Result<(), Error>) -> Result<(), Error> {
+    if let Err(err) = result {
+        log_failure(Level::Warn, &err);
+    }
+    Ok(())
+}
+
+/// The names of the networks that `module` is configured to be attached to.
+pub(crate) fn configured_networks(networks: &[Network], module: &str) -> Vec<String> {
+    networks
+        .iter()
+        .filter(|network| network.modules().iter().any(|name| name == module))
+        .map(|network| network.name().to_string())
+        .collect()
+}
+
+/// Attaches a module to those of `networks` that it isn't attached to yet.
+pub(crate) fn attach_module(
+    client: DockerClient<UrlConnector>,
+    module: String,
+    networks: Vec<String>,
+) -> impl Future<Item = (), Error = Error> + Send {
+    if networks.is_empty() {
+        return Either::A(future::ok(()));
+    }
+
+    let connect_client = client.clone();
+    let inspected = client
+        .container_api()
+        .container_inspect(&module, false)
+        .map_err({
+            let module = module.clone();
+            |err| {
+                Error::from_docker_error(
+                    err,
+                    ErrorKind::RuntimeOperation(RuntimeOperation::GetModule(module)),
+                )
+            }
+        })
+        .and_then(move |container| {
+            let attached: BTreeSet<String> = container
+                .network_settings()
+                .and_then(NetworkSettings::networks)
+                .map(|networks| networks.keys().cloned().collect())
+                .unwrap_or_default();
+
+            stream::iter_ok(
+                networks
+                    .into_iter()
+                    .filter(move |network| !attached.contains(network)),
+            )
+            .for_each(move |network| connect(&connect_client, &module, &network))
+        });
+
+    Either::B(inspected)
+}
+
+/// Gets the networks in `networks` from the container engine, with the modules that are
+/// attached to them.
+pub(crate) fn list_networks(
+    client: &DockerClient<UrlConnector>,
+    networks: &[Network],
+) -> impl Future<Item = Vec<Network>, Error = Error> + Send {
+    let inspected: Vec<_> = networks
+        .iter()
+        .map(|network| {
+            client
+                .network_api()
+                .network_inspect(network.name(), false, "")
+                .map(|network| core_network(&network))
+                .map_err(|err| {
+                    Error::from_docker_error(
+                        err,
+                        ErrorKind::RuntimeOperation(RuntimeOperation::ListNetworks),
+                    )
+                })
+        })
+        .collect();
+
+    future::join_all(inspected)
+}
+
+pub(crate) fn connect(
+    client: &DockerClient<UrlConnector>,
+    module: &str,
+    network: &str,
+) -> impl Future<Item = (), Error = Error> + Send {
+    info!("Attaching module {} to network {}...", module, network);
+    let module = module.to_string();
+    let network = network.to_string();
+
+    client
+        .network_api()
+        .network_connect(&network, Container::new().with_container(module.clone()))
+        .then(|result| match result {
+            Ok(()) => {
+                info!(
+                    "Successfully attached module {} to network {}",
+                    module, network
+                );
+                Ok(())
+            }
+            Err(err) => Err(Error::from_docker_error(
+                err,
+                ErrorKind::RuntimeOperation(RuntimeOperation::AttachNetwork(module, network)),
+            )),
+        })
+}
+
+pub(crate) fn disconnect(
+    client: &DockerClient<UrlConnector>,
+    module: &str,
+    network: &str,
+) -> impl Future<Item = (), Error = Error> + Send {
+    info!("Detaching module {} from network {}...", module, network);
+    let module = module.to_string();
+    let network = network.to_string();
+
+    client
+        .network_api()
+        .network_disconnect(&network, Container1::new().with_container(module.clone()))
+        .then(|result| match result {
+            Ok(()) => {
+                info!(
+                    "Successfully detached module {} from network {}",
+                    module, network
+                );
+                Ok(())
+            }
+            Err(err) => Err(Error::from_docker_error(
+                err,
+                ErrorKind::RuntimeOperation(RuntimeOperation::DetachNetwork(module, network)),
+            )),
+        })
+}
+
+fn reconcile_network(
+    client: DockerClient<UrlConnector>,
+    network: Network,
+    existing: Option<DockerNetwork>,
+) -> Box<dyn Future<Item = (), Error = Error> + Send> {
+    let existing = match existing {
+        Some(existing) => existing,
+        None => return Box::new(create_network(&client, &network, true)),
+    };
+
+    if !differs(&existing, &network) {
+        return Box::new(future::ok(()));
+    }
+
+    if !is_managed(&existing) {
+        warn!(
+            "Network {} does not match its settings, but was not created by iotedged, so it is left as is",
+            network.name()
+        );
+        return Box::new(future::ok(()));
+    }
+
+    let name = network.name().to_string();
+    let recreated = client
+        .network_api()
+        .network_inspect(&name, false, "")
+        .map_err({
+            let name = name.clone();
+            |err| Error::from_docker_error(err, ErrorKind::ReconcileNetwork(name))
+        })
+        .and_then(move |existing| {
+            if existing.containers().map_or(true, HashMap::is_empty) {
+                info!("Network {} does not match its settings, recreating it", name);
+                Either::A(
+                    remove_network(&client, name)
+                        .and_then(move |()| create_network(&client, &network, true)),
+                )
+            } else {
+                warn!(
+                    "Network {} does not match its settings, but modules are attached to it, so it is left as is",
+                    name
+                );
+                Either::B(future::ok(()))
+            }
+        });
+
+    Box::new(recreated)
+}
+
+fn create_network(
+    client: &DockerClient<UrlConnector>,
+    network: &Network,
+    managed: bool,
+) -> impl Future<Item = (), Error = Error> + Send {
+    info!("Creating network {}...", network.name());
+    let name = network.name().to_string();
+
+    let mut config = network_config(network);
+    if managed {
+        let mut labels = HashMap::new();
+        labels.insert(LABEL_KEY.to_string(), LABEL_VALUE.to_string());
+        config.set_labels(labels);
+    }
+
+    client
+        .network_api()
+        .network_create(config)
+        .then(|result| match result {
+            Ok(_) => {
+                info!("Successfully created network {}", name);
+                Ok(())
+            }
+            Err(err) => Err(Error::from_docker_error(
+                err,
+                ErrorKind::ReconcileNetwork(name),
+            )),
+        })
+}
+
+fn remove_network(
+    client: &DockerClient<UrlConnector>,
+    name: String,
+) -> impl Future<Item = (), Error = Error> + Send {
+    info!("Removing network {}...", name);
+
+    client
+        .network_api()
+        .network_delete(&name)
+        .then(|result| match result {
+            Ok(()) => {
+                info!("Successfully removed network {}", name);
+                Ok(())
+            }
+            Err(err) => Err(Error::from_docker_error(
+                err,
+                ErrorKind::ReconcileNetwork(name),
+            )),
+        })
+}
+
+/// Whether iotedged created the network for one of the configured networks.
+fn is_managed(network: &DockerNetwork) -> bool {
+    network
+        .labels()
+        .and_then(|labels| labels.get(LABEL_KEY))
+        .map_or(false, |value| value == LABEL_VALUE)
+}
+
+/// Whether `existing` doesn't match the settings of `network` anymore. The settings that
+/// aren't set are left to the container engine, so they aren't compared.
+fn differs(existing: &DockerNetwork, network: &Network) -> bool {
+    let driver = network
+        .driver()
+        .map_or(false, |driver| existing.driver() != Some(driver));
+    let internal =
+        network.internal().unwrap_or_default() != existing.internal().cloned().unwrap_or_default();
+    let ipv6 =
+        network.ipv6().unwrap_or_default() != existing.enable_i_pv6().cloned().unwrap_or_default();
+    let options = network
+        .options()
+        .iter()
+        .any(|(key, value)| existing.options().and_then(|options| options.get(key)) != Some(value));
+
+    let subnets: BTreeSet<&str> = network
+        .ipam()
+        .and_then(CoreIpam::config)
+        .unwrap_or_default()
+        .iter()
+        .filter_map(IpamConfig::subnet)
+        .collect();
+    let existing_subnets: BTreeSet<&str> = existing
+        .IPAM()
+        .and_then(Ipam::config)
+        .unwrap_or_default()
+        .iter()
+        .filter_map(|config| config.get("Subnet").map(AsRef::as_ref))
+        .collect();
+    let subnets = !subnets.is_empty() && subnets != existing_subnets;
+
+    driver || internal || ipv6 || options || subnets
+}
+
+/// The request to create `network` with.
+fn network_config(network: &Network) -> NetworkConfig {
+    let mut config = NetworkConfig::new(network.name().to_string())
+        .with_enable_i_pv6(network.ipv6().unwrap_or_default());
+
+    if let Some(driver) = network.driver() {
+        config.set_driver(driver.to_string());
+    }
+
+    if let Some(internal) = network.internal() {
+        config.set_internal(internal);
+    }
+
+    if !network.options().is_empty() {
+        config.set_options(
+            network
+                .options()
+                .iter()
+                .map(|(key, value)| (key.clone(), value.clone()))
+                .collect(),
+        );
+    }
+
+    if let Some(ipam_config) = network.ipam().and_then(CoreIpam::config) {
+        let config_maps = ipam_config
+            .iter()
+            .map(|ipam_config| {
+                let mut config_map = HashMap::new();
+                if let Some(gateway_config) = ipam_config.gateway() {
+                    config_map.insert("Gateway".to_string(), gateway_config.to_string());
+                };
+
+                if let Some(subnet_config) = ipam_config.subnet() {
+                    config_map.insert("Subnet".to_string(), subnet_config.to_string());
+                };
+
+                if let Some(ip_range_config) = ipam_config.ip_range() {
+                    config_map.insert("IPRange".to_string(), ip_range_config.to_string());
+                };
+
+                config_map
+            })
+            .collect();
+
+        config.set_IPAM(Ipam::new().with_config(config_maps));
+    }
+
+    config
+}
+
+fn core_network(network: &DockerNetwork) -> Network {
+    let ipam_config: Vec<IpamConfig> = network
+        .IPAM()
+        .and_then(Ipam::config)
+        .unwrap_or_default()
+        .iter()
+        .map(|config_map| {
+            let mut ipam_config = IpamConfig::default();
+            if let Some(gateway) = config_map.get("Gateway") {
+                ipam_config = ipam_config.with_gateway(gateway.clone());
+            }
+            if let Some(subnet) = config_map.get("Subnet") {
+                ipam_config = ipam_config.with_subnet(subnet.clone());
+            }
+            if let Some(ip_range) = config_map.get("IPRange") {
+                ipam_config = ipam_config.with_ip_range(ip_range.clone());
+            }
+            ipam_config
+        })
+        .collect();
+
+    let mut modules: Vec<String> = network
+        .containers()
+        .map(|containers| {
+            containers
+                .values()
+                .filter_map(NetworkContainer::name)
+                .map(ToOwned::to_owned)
+                .collect()
+        })
+        .unwrap_or_default();
+    modules.sort();
+
+    let network = Network::new(network.name().unwrap_or_default().to_string())
+        .with_driver(network.driver().map(ToOwned::to_owned))
+        .with_internal(network.internal().cloned())
+        .with_ipv6(network.enable_i_pv6().cloned())
+        .with_options(
+            network
+                .options()
+                .map(|options| {
+                    options
+                        .iter()
+                        .map(|(key, value)| (key.clone(), value.clone()))
+                        .collect()
+                })
+                .unwrap_or_default(),
+        )
+        .with_modules(modules);
+
+    if ipam_config.is_empty() {
+        network
+    } else {
+        network.with_ipam(CoreIpam::default().with_config(ipam_config))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use super::*;
+
+    fn fieldbus() -> Network {
+        let mut options = BTreeMap::new();
+        options.insert("parent".to_string(), "eth1".to_string());
+
+        Network::new("fieldbus".to_string())
+            .with_driver(Some("macvlan".to_string()))
+            .with_options(options)
+            .with_ipam(CoreIpam::default().with_config(vec![IpamConfig::default()
+                .with_gateway("192.168.50.1".to_string())
+                .with_subnet("192.168.50.0/24".to_string())]))
+            .with_modules(vec!["opcpublisher".to_string()])
+    }
+
+    fn created(config: &NetworkConfig) -> DockerNetwork {
+        serde_json::from_value(serde_json::to_value(config).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn network_config_has_settings() {
+        let config = serde_json::to_value(network_config(&fieldbus())).unwrap();
+
+        assert_eq!(
+            serde_json::json!({
+                "Name": "fieldbus",
+                "Driver": "macvlan",
+                "EnableIPv6": false,
+                "IPAM": {
+                    "Config": [{ "Gateway": "192.168.50.1", "Subnet": "192.168.50.0/24" }],
+                },
+                "Options": { "parent": "eth1" },
+            }),
+            config
+        );
+    }
+
+    #[test]
+    fn created_network_does_not_differ() {
+        let network = fieldbus();
+        let existing = created(&network_config(&network));
+
+        assert!(!differs(&existing, &network));
+        assert!(!differs(
+            &existing,
+            &Network::new("fieldbus".to_string()).with_driver(Some("macvlan".to_string()))
+        ));
+    }
+
+    #[test]
+    fn changed_settings_differ() {
+        let network = fieldbus();
+        let existing = created(&network_config(&network));
+
+        assert!(differs(
+            &existing,
+            &network.clone().with_driver(Some("bridge".to_string()))
+        ));
+        assert!(differs(
+            &existing,
+            &network.clone().with_internal(Some(true))
+        ));
+        assert!(differs(&existing, &network.clone().with_ipv6(Some(true))));
+        let mut options = BTreeMap::new();
+        options.insert("parent".to_string(), "eth2".to_string());
+        assert!(differs(&existing, &network.clone().with_options(options)));
+        assert!(differs(
+            &existing,
+            &network.with_ipam(CoreIpam::default().with_config(vec![
+                IpamConfig::default().with_subnet("192.168.60.0/24".to_string())
+            ]))
+        ));
+    }
+
+    #[test]
+    fn configured_networks_of_module() {
+        let networks = vec![
+            Network::new("azure-iot-edge".to_string()),
+            fieldbus(),
+            Network::new("analytics".to_string())
+                .with_modules(vec!["filter".to_string(), "opcpublisher".to_string()]),
+        ];
+
+        assert_eq!(
+            vec!["fieldbus".to_string(), "analytics".to_string()],
+            configured_networks(&networks, "opcpublisher")
+        );
+        assert_eq!(
+            vec!["analytics".to_string()],
+            configured_networks(&networks, "filter")
+        );
+        assert!(configured_networks(&networks, "edgeHub").is_empty());
+    }
+}
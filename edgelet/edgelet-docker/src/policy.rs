@@ -0,0 +1,738 @@
+// Copyright (c) Microsoft. All rights reserved.
+
+use std::path::{Component, Path};
+
+use docker::models::{
+    ContainerCreateBody, HostConfig, Mount, MountVolumeOptions, MountVolumeOptionsDriverConfig,
+};
+
+use crate::error::ErrorKind;
+
+/// The registry of images that don't name one, like `alpine` or `library/alpine`.
+const DEFAULT_REGISTRY: &str = "docker.io";
+
+/// The volume driver of volumes that don't name one.
+const LOCAL_VOLUME_DRIVER: &str = "local";
+
+/// The PID, IPC and user namespace mode that shares the namespace of the host.
+const HOST_NAMESPACE_MODE: &str = "host";
+
+/// A local admission policy for the create options of modules.
+///
+/// Deployments come from the cloud, so without a policy any deployment can ask for
+/// privileged containers, host networking or binds of the whole host file system.
+#[derive(Clone, Debug, Default, serde_derive::Deserialize, serde_derive::Serialize)]
+pub struct CreateOptionsPolicy {
+    #[serde(default)]
+    allow_privileged: bool,
+    #[serde(default)]
+    binds: AllowDeny,
+    #[serde(default)]
+    devices: AllowDeny,
+    #[serde(default)]
+    capabilities: AllowDeny,
+    #[serde(default)]
+    network_modes: AllowDeny,
+    #[serde(default)]
+    registries: AllowDeny,
+}
+
+/// A value is allowed unless it matches a denied entry. If there is an allowed list,
+/// the value must also match one of its entries.
+#[derive(Clone, Debug, Default, serde_derive::Deserialize, serde_derive::Serialize)]
+pub struct AllowDeny {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    allowed: Option<Vec<String>>,
+    #[serde(default)]
+    denied: Vec<String>,
+}
+
+impl AllowDeny {
+    pub fn new(allowed: Option<Vec<String>>, denied: Vec<String>) -> Self {
+        AllowDeny { allowed, denied }
+    }
+
+    pub fn allowed(&self) -> Option<&[String]> {
+        self.allowed.as_ref().map(AsRef::as_ref)
+    }
+
+    pub fn denied(&self) -> &[String] {
+        &self.denied
+    }
+
+    fn is_restricted(&self) -> bool {
+        self.allowed.is_some() || !self.denied.is_empty()
+    }
+
+    fn permits(&self, value: &str, matches: impl Fn(&str, &str) -> bool) -> bool {
+        !self.denied.iter().any(|entry| matches(value, entry))
+            && self.allowed.as_ref().map_or(true, |allowed| {
+                allowed.iter().any(|entry| matches(value, entry))
+            })
+    }
+
+    /// A path matches an entry if it is the entry itself or is under it. Paths that go up with
+    /// `..` could get out of the entries, so they are only allowed when nothing is restricted.
+    fn permits_path(&self, path: &str) -> bool {
+        let goes_up = Path::new(path)
+            .components()
+            .any(|component| component == Component::ParentDir);
+
+        !(self.is_restricted() && goes_up)
+            && self.permits(path, |value, entry| Path::new(value).starts_with(entry))
+    }
+}
+
+impl CreateOptionsPolicy {
+    pub fn allow_privileged(&self) -> bool {
+        self.allow_privileged
+    }
+
+    pub fn with_allow_privileged(mut self, allow_privileged: bool) -> Self {
+        self.allow_privileged = allow_privileged;
+        self
+    }
+
+    pub fn binds(&self) -> &AllowDeny {
+        &self.binds
+    }
+
+    pub fn with_binds(mut self, binds: AllowDeny) -> Self {
+        self.binds = binds;
+        self
+    }
+
+    pub fn devices(&self) -> &AllowDeny {
+        &self.devices
+    }
+
+    pub fn with_devices(mut self, devices: AllowDeny) -> Self {
+        self.devices = devices;
+        self
+    }
+
+    pub fn capabilities(&self) -> &AllowDeny {
+        &self.capabilities
+    }
+
+    pub fn with_capabilities(mut self, capabilities: AllowDeny) -> Self {
+        self.capabilities = capabilities;
+        self
+    }
+
+    pub fn network_modes(&self) -> &AllowDeny {
+        &self.network_modes
+    }
+
+    pub fn with_network_modes(mut self, network_modes: AllowDeny) -> Self {
+        self.network_modes = network_modes;
+        self
+    }
+
+    pub fn registries(&self) -> &AllowDeny {
+        &self.registries
+    }
+
+    pub fn with_registries(mut self, registries: AllowDeny) -> Self {
+        self.registries = registries;
+        self
+    }
+
+    /// Checks the image and create options of a module before it is created. Violations are
+    /// returned as an `ErrorKind::PolicyViolation`.
+    pub fn check(
+        &self,
+        image: &str,
+        create_options: &ContainerCreateBody,
+    ) -> Result<(), ErrorKind> {
+        let registry = registry(image);
+        if !self
+            .registries
+            .permits(&registry, |value, entry| value.eq_ignore_ascii_case(entry))
+        {
+            return Err(violation(format!("images from registry {}", registry)));
+        }
+
+        if let Some(host_config) = create_options.host_config() {
+            self.check_host_config(host_config)?;
+        }
+
+        Ok(())
+    }
+
+    fn check_host_config(&self, host_config: &HostConfig) -> Result<(), ErrorKind> {
+        if !self.allow_privileged {
+            if host_config.privileged() == Some(&true) {
+                return Err(violation("privileged mode".to_string()));
+            }
+
+            // Sharing the namespaces of the host or turning off confinement is most of what
+            // privileged mode does.
+            let namespace_modes = [
+                ("PID mode", host_config.pid_mode()),
+                ("IPC mode", host_config.ipc_mode()),
+                ("user namespace mode", host_config.userns_mode()),
+            ];
+            for (what, mode) in &namespace_modes {
+                if *mode == Some(HOST_NAMESPACE_MODE) {
+                    return Err(violation(format!("{} {}", what, HOST_NAMESPACE_MODE)));
+                }
+            }
+
+            for option in host_config.security_opt().unwrap_or_default() {
+                if !is_no_new_privileges(option) {
+                    return Err(violation(format!("security option {}", option)));
+                }
+            }
+        }
+
+        let mounts = host_config.mounts().unwrap_or_default();
+        let bind_sources = host_config
+            .binds()
+            .unwrap_or_default()
+            .iter()
+            .map(|bind| bind_source(bind))
+            .chain(
+                mounts
+                    .iter()
+                    .filter(|mount| mount._type() == Some("bind") || mount._type() == Some("npipe"))
+                    .filter_map(|mount| mount.source()),
+            )
+            .chain(mounts.iter().filter_map(local_volume_device))
+            // Named volumes and file systems like `tmpfs` are not host paths.
+            .filter(|source| source.contains('/') || source.contains('\\'));
+        for source in bind_sources {
+            if !self.binds.permits_path(source) {
+                return Err(violation(format!("binds of host path {}", source)));
+            }
+        }
+
+        // Other volume drivers can take host paths in options we don't know about.
+        if self.binds.is_restricted() {
+            for driver_config in mounts.iter().filter_map(volume_driver_config) {
+                let driver = driver_config.name().unwrap_or(LOCAL_VOLUME_DRIVER);
+                let has_options = driver_config
+                    .options()
+                    .map_or(false, |options| !options.is_empty());
+                if driver != LOCAL_VOLUME_DRIVER && has_options {
+                    return Err(violation(format!("options of volume driver {}", driver)));
+                }
+            }
+
+            // The other container can have any binds.
+            if let Some(container) = host_config.volumes_from().unwrap_or_default().first() {
+                return Err(violation(format!("volumes from container {}", container)));
+            }
+        }
+
+        let devices = host_config
+            .devices()
+            .unwrap_or_default()
+            .iter()
+            .filter_map(|device| device.path_on_host());
+        for device in devices {
+            if !self.devices.permits_path(device) {
+                return Err(violation(format!("device {}", device)));
+            }
+        }
+
+        // Cgroup rules give access to devices by number, which can't be matched with paths.
+        if self.devices.is_restricted() {
+            if let Some(rule) = host_config
+                .device_cgroup_rules()
+                .unwrap_or_default()
+                .first()
+            {
+                return Err(violation(format!("device cgroup rule {}", rule)));
+            }
+        }
+
+        for capability in host_config.cap_add().unwrap_or_default() {
+            // `ALL` grants the denied capabilities too.
+            let grants_denied =
+                normalize_capability(capability) == "ALL" && !self.capabilities.denied.is_empty();
+            if grants_denied || !self.capabilities.permits(capability, capability_matches) {
+                return Err(violation(format!("capability {}", capability)));
+            }
+        }
+
+        if let Some(network_mode) = host_config.network_mode() {
+            if !self
+                .network_modes
+                .permits(network_mode, network_mode_matches)
+            {
+                return Err(violation(format!("network mode {}", network_mode)));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+fn violation(what: String) -> ErrorKind {
+    ErrorKind::PolicyViolation(what)
+}
+
+/// Gets the registry of an image, the same way docker does: the first component of the name
+/// is the registry if it looks like a host name.
+fn registry(image: &str) -> String {
+    match image.find('/') {
+        Some(index) => {
+            let first = &image[..index];
+            if first.contains('.') || first.contains(':') || first == "localhost" {
+                first.to_lowercase()
+            } else {
+                DEFAULT_REGISTRY.to_string()
+            }
+        }
+        None => DEFAULT_REGISTRY.to_string(),
+    }
+}
+
+/// Gets the host path or volume name of a bind like `/src:/dst:ro` or `C:\src:C:\dst`.
+fn bind_source(bind: &str) -> &str {
+    let bytes = bind.as_bytes();
+    let skip = if bytes.len() > 2 && bytes[0].is_ascii_alphabetic() && bytes[1] == b':' {
+        2
+    } else {
+        0
+    };
+    bind[skip..]
+        .find(':')
+        .map_or(bind, |index| &bind[..skip + index])
+}
+
+fn volume_driver_config(mount: &Mount) -> Option<&MountVolumeOptionsDriverConfig> {
+    if mount._type() == Some("volume") {
+        mount
+            .volume_options()
+            .and_then(MountVolumeOptions::driver_config)
+    } else {
+        None
+    }
+}
+
+/// Gets the `device` option of a volume of the local driver. With `o: bind` it is a host path
+/// that gets bound like a bind mount, otherwise it can be a block device or a file system like
+/// `tmpfs`.
+fn local_volume_device(mount: &Mount) -> Option<&str> {
+    volume_driver_config(mount)
+        .filter(|driver_config| {
+            driver_config.name().unwrap_or(LOCAL_VOLUME_DRIVER) == LOCAL_VOLUME_DRIVER
+        })
+        .and_then(MountVolumeOptionsDriverConfig::options)
+        .and_then(|options| options.get("device"))
+        .map(AsRef::as_ref)
+}
+
+/// `no-new-privileges` is the only security option that doesn't loosen the confinement of the
+/// container. The others can turn off seccomp, AppArmor or SELinux, or replace their profiles.
+fn is_no_new_privileges(option: &str) -> bool {
+    match option {
+        "no-new-privileges" | "no-new-privileges:true" | "no-new-privileges=true" => true,
+        _ => false,
+    }
+}
+
+/// Capabilities can be named with or without the `CAP_` prefix.
+fn capability_matches(value: &str, entry: &str) -> bool {
+    normalize_capability(value) == normalize_capability(entry)
+}
+
+fn normalize_capability(capability: &str) -> String {
+    let capability = capability.to_uppercase();
+    match capability.get(..4) {
+        Some("CAP_") => capability[4..].to_string(),
+        _ => capability,
+    }
+}
+
+/// `container` matches the `container:<name|id>` network modes.
+fn network_mode_matches(value: &str, entry: &str) -> bool {
+    value == entry || value.starts_with(&format!("{}:", entry))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use serde_json::json;
+
+    fn create_options(host_config: serde_json::Value) -> ContainerCreateBody {
+        serde_json::from_value(json!({ "HostConfig": host_config })).unwrap()
+    }
+
+    fn list(entries: &[&str]) -> Vec<String> {
+        entries.iter().map(ToString::to_string).collect()
+    }
+
+    fn message(result: Result<(), ErrorKind>) -> String {
+        result.unwrap_err().to_string()
+    }
+
+    #[test]
+    fn default_policy_allows_unprivileged_modules() {
+        let policy = CreateOptionsPolicy::default();
+        let create_options = create_options(json!({
+            "Binds": ["/data:/data"],
+            "CapAdd": ["NET_ADMIN"],
+            "NetworkMode": "host",
+        }));
+
+        policy.check("alpine", &create_options).unwrap();
+    }
+
+    #[test]
+    fn privileged_is_denied_unless_allowed() {
+        let create_options = create_options(json!({ "Privileged": true }));
+
+        let policy = CreateOptionsPolicy::default();
+        assert_eq!(
+            "Create options are not allowed by the local policy: privileged mode",
+            message(policy.check("alpine", &create_options))
+        );
+
+        let policy = policy.with_allow_privileged(true);
+        policy.check("alpine", &create_options).unwrap();
+    }
+
+    #[test]
+    fn binds_match_host_paths() {
+        let policy = CreateOptionsPolicy::default()
+            .with_binds(AllowDeny::new(Some(list(&["/var/lib/data"])), vec![]));
+
+        policy
+            .check(
+                "alpine",
+                &create_options(json!({ "Binds": ["/var/lib/data/x:/x:ro", "volume:/volume"] })),
+            )
+            .unwrap();
+        assert!(policy
+            .check("alpine", &create_options(json!({ "Binds": ["/:/host"] })))
+            .is_err());
+        assert!(policy
+            .check(
+                "alpine",
+                &create_options(json!({ "Binds": ["/var/lib/database:/db"] }))
+            )
+            .is_err());
+        assert!(policy
+            .check(
+                "alpine",
+                &create_options(json!({ "Binds": ["/var/lib/data/../../..:/host"] }))
+            )
+            .is_err());
+        assert!(policy
+            .check(
+                "alpine",
+                &create_options(
+                    json!({ "Mounts": [{ "Type": "bind", "Source": "/etc", "Target": "/etc" }] })
+                )
+            )
+            .is_err());
+    }
+
+    #[test]
+    fn volume_driver_options_match_binds() {
+        let policy = CreateOptionsPolicy::default()
+            .with_binds(AllowDeny::new(Some(list(&["/var/lib/data"])), vec![]));
+        let volume = |driver: &str, options: serde_json::Value| {
+            create_options(json!({
+                "Mounts": [{
+                    "Type": "volume",
+                    "Source": "data",
+                    "Target": "/data",
+                    "VolumeOptions": { "DriverConfig": { "Name": driver, "Options": options } },
+                }]
+            }))
+        };
+
+        policy
+            .check(
+                "alpine",
+                &volume(
+                    "local",
+                    json!({ "type": "none", "o": "bind", "device": "/var/lib/data/x" }),
+                ),
+            )
+            .unwrap();
+        policy
+            .check(
+                "alpine",
+                &volume("local", json!({ "type": "tmpfs", "device": "tmpfs" })),
+            )
+            .unwrap();
+        assert_eq!(
+            "Create options are not allowed by the local policy: binds of host path /",
+            message(policy.check(
+                "alpine",
+                &volume(
+                    "local",
+                    json!({ "type": "none", "o": "bind", "device": "/" })
+                )
+            ))
+        );
+        assert_eq!(
+            "Create options are not allowed by the local policy: options of volume driver local-persist",
+            message(policy.check(
+                "alpine",
+                &volume("local-persist", json!({ "mountpoint": "/" }))
+            ))
+        );
+        CreateOptionsPolicy::default()
+            .check(
+                "alpine",
+                &volume("local-persist", json!({ "mountpoint": "/" })),
+            )
+            .unwrap();
+    }
+
+    #[test]
+    fn npipe_mounts_match_binds() {
+        let policy = CreateOptionsPolicy::default().with_binds(AllowDeny::new(
+            Some(list(&[r"\\.\pipe\iotedge_mgmt"])),
+            vec![],
+        ));
+        let npipe = |source: &str| {
+            create_options(json!({
+                "Mounts": [{ "Type": "npipe", "Source": source, "Target": source }]
+            }))
+        };
+
+        policy
+            .check("alpine", &npipe(r"\\.\pipe\iotedge_mgmt"))
+            .unwrap();
+        assert_eq!(
+            r"Create options are not allowed by the local policy: binds of host path \\.\pipe\docker_engine",
+            message(policy.check("alpine", &npipe(r"\\.\pipe\docker_engine")))
+        );
+    }
+
+    #[test]
+    fn volumes_from_is_denied_with_restricted_binds() {
+        let create_options = create_options(json!({ "VolumesFrom": ["hostAgent:ro"] }));
+
+        let policy = CreateOptionsPolicy::default()
+            .with_binds(AllowDeny::new(Some(list(&["/var/lib/data"])), vec![]));
+        assert_eq!(
+            "Create options are not allowed by the local policy: volumes from container hostAgent:ro",
+            message(policy.check("alpine", &create_options))
+        );
+
+        CreateOptionsPolicy::default()
+            .check("alpine", &create_options)
+            .unwrap();
+    }
+
+    #[test]
+    fn pid_mode_host_is_privileged() {
+        let create_options = create_options(json!({ "PidMode": "host" }));
+
+        let policy = CreateOptionsPolicy::default();
+        assert_eq!(
+            "Create options are not allowed by the local policy: PID mode host",
+            message(policy.check("alpine", &create_options))
+        );
+        policy
+            .check(
+                "alpine",
+                &self::create_options(json!({ "PidMode": "container:edgeHub" })),
+            )
+            .unwrap();
+
+        let policy = policy.with_allow_privileged(true);
+        policy.check("alpine", &create_options).unwrap();
+    }
+
+    #[test]
+    fn ipc_mode_host_is_privileged() {
+        let create_options = create_options(json!({ "IpcMode": "host" }));
+
+        let policy = CreateOptionsPolicy::default();
+        assert_eq!(
+            "Create options are not allowed by the local policy: IPC mode host",
+            message(policy.check("alpine", &create_options))
+        );
+        policy
+            .check(
+                "alpine",
+                &self::create_options(json!({ "IpcMode": "shareable" })),
+            )
+            .unwrap();
+
+        let policy = policy.with_allow_privileged(true);
+        policy.check("alpine", &create_options).unwrap();
+    }
+
+    #[test]
+    fn userns_mode_host_is_privileged() {
+        let create_options = create_options(json!({ "UsernsMode": "host" }));
+
+        let policy = CreateOptionsPolicy::default();
+        assert_eq!(
+            "Create options are not allowed by the local policy: user namespace mode host",
+            message(policy.check("alpine", &create_options))
+        );
+
+        let policy = policy.with_allow_privileged(true);
+        policy.check("alpine", &create_options).unwrap();
+    }
+
+    #[test]
+    fn security_options_other_than_no_new_privileges_are_privileged() {
+        let policy = CreateOptionsPolicy::default();
+
+        policy
+            .check(
+                "alpine",
+                &create_options(json!({ "SecurityOpt": ["no-new-privileges:true"] })),
+            )
+            .unwrap();
+        for option in &["seccomp=unconfined", "apparmor=unconfined", "label=disable"] {
+            assert_eq!(
+                format!(
+                    "Create options are not allowed by the local policy: security option {}",
+                    option
+                ),
+                message(policy.check(
+                    "alpine",
+                    &create_options(json!({ "SecurityOpt": [option] }))
+                ))
+            );
+        }
+
+        let policy = policy.with_allow_privileged(true);
+        policy
+            .check(
+                "alpine",
+                &create_options(json!({ "SecurityOpt": ["seccomp=unconfined"] })),
+            )
+            .unwrap();
+    }
+
+    #[test]
+    fn device_cgroup_rules_are_denied_with_restricted_devices() {
+        let create_options = create_options(json!({ "DeviceCgroupRules": ["c *:* rwm"] }));
+
+        let policy = CreateOptionsPolicy::default()
+            .with_devices(AllowDeny::new(Some(list(&["/dev/ttyUSB0"])), vec![]));
+        assert_eq!(
+            "Create options are not allowed by the local policy: device cgroup rule c *:* rwm",
+            message(policy.check("alpine", &create_options))
+        );
+
+        CreateOptionsPolicy::default()
+            .check("alpine", &create_options)
+            .unwrap();
+    }
+
+    #[test]
+    fn going_up_is_denied_by_denied_binds() {
+        let policy =
+            CreateOptionsPolicy::default().with_binds(AllowDeny::new(None, list(&["/etc"])));
+
+        assert!(policy
+            .check(
+                "alpine",
+                &create_options(json!({ "Binds": ["/var/../etc:/etc"] }))
+            )
+            .is_err());
+        CreateOptionsPolicy::default()
+            .check(
+                "alpine",
+                &create_options(json!({ "Binds": ["/var/../etc:/etc"] })),
+            )
+            .unwrap();
+    }
+
+    #[test]
+    fn denied_entries_win() {
+        let policy = CreateOptionsPolicy::default()
+            .with_devices(AllowDeny::new(Some(list(&["/dev"])), list(&["/dev/mem"])));
+
+        policy
+            .check(
+                "alpine",
+                &create_options(json!({ "Devices": [{ "PathOnHost": "/dev/ttyUSB0" }] })),
+            )
+            .unwrap();
+        assert_eq!(
+            "Create options are not allowed by the local policy: device /dev/mem",
+            message(policy.check(
+                "alpine",
+                &create_options(json!({ "Devices": [{ "PathOnHost": "/dev/mem" }] }))
+            ))
+        );
+    }
+
+    #[test]
+    fn all_capabilities_match_denied_capabilities() {
+        let policy = CreateOptionsPolicy::default()
+            .with_capabilities(AllowDeny::new(None, list(&["SYS_ADMIN"])));
+
+        policy
+            .check(
+                "alpine",
+                &create_options(json!({ "CapAdd": ["NET_ADMIN"] })),
+            )
+            .unwrap();
+        assert!(policy
+            .check(
+                "alpine",
+                &create_options(json!({ "CapAdd": ["CAP_SYS_ADMIN"] }))
+            )
+            .is_err());
+        assert!(policy
+            .check("alpine", &create_options(json!({ "CapAdd": ["all"] })))
+            .is_err());
+    }
+
+    #[test]
+    fn network_modes() {
+        let policy = CreateOptionsPolicy::default()
+            .with_network_modes(AllowDeny::new(None, list(&["host", "container"])));
+
+        policy
+            .check(
+                "alpine",
+                &create_options(json!({ "NetworkMode": "azure-iot-edge" })),
+            )
+            .unwrap();
+        assert!(policy
+            .check("alpine", &create_options(json!({ "NetworkMode": "host" })))
+            .is_err());
+        assert!(policy
+            .check(
+                "alpine",
+                &create_options(json!({ "NetworkMode": "container:edgeHub" }))
+            )
+            .is_err());
+    }
+
+    #[test]
+    fn registries() {
+        let policy = CreateOptionsPolicy::default().with_registries(AllowDeny::new(
+            Some(list(&["mcr.microsoft.com", "localhost:5000"])),
+            vec![],
+        ));
+        let create_options = ContainerCreateBody::new();
+
+        policy
+            .check("mcr.microsoft.com/azureiotedge-agent:1.0", &create_options)
+            .unwrap();
+        policy
+            .check("localhost:5000/module:latest", &create_options)
+            .unwrap();
+        assert_eq!(
+            "Create options are not allowed by the local policy: images from registry docker.io",
+            message(policy.check("microsoft/azureiotedge-agent:1.0", &create_options))
+        );
+    }
+
+    #[test]
+    fn bind_sources() {
+        assert_eq!("/src", bind_source("/src:/dst:ro"));
+        assert_eq!("volume", bind_source("volume:/dst"));
+        assert_eq!(r"C:\src", bind_source(r"C:\src:C:\dst"));
+    }
+}
@@ -1,27 +1,40 @@
 // Copyright (c) Microsoft. All rights reserved.
 
 use std::collections::HashMap;
+use std::convert::TryInto;
+use std::iter;
 use std::ops::Deref;
-use std::time::Duration;
+use std::process;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
 
 use base64;
+use chrono::{DateTime, Utc};
 use failure::{Fail, ResultExt};
 use futures::future::Either;
 use futures::prelude::*;
 use futures::{future, stream, Async, Stream};
+use hyper::upgrade::Upgraded;
 use hyper::{Body, Chunk as HyperChunk, Client, Request};
 use lazy_static::lazy_static;
 use log::{debug, info, Level};
 use serde_json;
+use sysinfo::{DiskExt, ProcessExt, ProcessorExt, SystemExt};
 use url::Url;
 
 use docker::apis::client::APIClient;
 use docker::apis::configuration::Configuration;
-use docker::models::{ContainerCreateBody, InlineResponse200, Ipam, NetworkConfig};
+use docker::models::{
+    AuthConfig, ContainerCreateBody, ExecConfig, ExecStartConfig, InlineResponse200,
+    InlineResponse20014,
+};
+use edgelet_core::crypto::{Decrypt, Encrypt, MakeRandom};
 use edgelet_core::{
-    AuthId, Authenticator, GetTrustBundle, Ipam as CoreIpam, LogOptions, MakeModuleRuntime,
-    MobyNetwork, Module, ModuleId, ModuleRegistry, ModuleRuntime, ModuleRuntimeState, ModuleSpec,
-    RegistryOperation, RuntimeOperation, SystemInfo as CoreSystemInfo, SystemResources, UrlExt,
+    metrics, AuthId, Authenticator, DiskInfo, ExecOptions, GetTrustBundle, LogOptions,
+    MakeModuleRuntime, Module, ModuleEvent, ModuleId, ModuleRegistry, ModuleResourceUsage,
+    ModuleRuntime, ModuleRuntimeErrorReason, ModuleRuntimeState, ModuleSpec, Network,
+    RegistryOperation, RuntimeOperation, RuntimeSettings, SystemInfo as CoreSystemInfo,
+    SystemResources, UrlExt,
 };
 use edgelet_http::{Pid, UrlConnector};
 use edgelet_utils::{ensure_not_empty_with_context, log_failure};
@@ -29,32 +42,35 @@ use provisioning::ProvisioningResult;
 
 use crate::client::DockerClient;
 use crate::config::DockerConfig;
+use crate::container_stats::ContainerStats;
+use crate::credentials::{RegistryCredentialStore, RegistryCredentials};
 use crate::error::{Error, ErrorKind, Result};
+use crate::events::{self, Lines};
 use crate::module::{
     runtime_state, DockerModule, DockerModuleTop, MODULE_TYPE as DOCKER_MODULE_TYPE,
 };
+use crate::network;
+use crate::policy::CreateOptionsPolicy;
 use crate::settings::Settings;
+use crate::verification::{verify_image, ImageVerifier};
 
-#[cfg(not(windows))]
-use edgelet_core::DiskInfo;
-#[cfg(not(windows))]
-use std::convert::TryInto;
 #[cfg(target_os = "linux")]
 use std::mem;
-#[cfg(not(windows))]
-use std::process;
-#[cfg(not(windows))]
-use std::time::{SystemTime, UNIX_EPOCH};
-#[cfg(not(windows))]
-use sysinfo::{DiskExt, ProcessExt, ProcessorExt, SystemExt};
 
 type Deserializer = &'static mut serde_json::Deserializer<serde_json::de::IoRead<std::io::Empty>>;
 
-static LABEL_KEY: &str = "net.azure-devices.edge.owner";
-static LABEL_VALUE: &str = "Microsoft.Azure.Devices.Edge.Agent";
+/// This is the name of the file in the home directory that the credentials stored by
+/// `iotedge registry login` are kept in.
+const REGISTRY_CREDENTIALS_FILENAME: &str = "registry_credentials";
+
+/// The environment variable that modules get the payload DPS returned at provisioning in.
+const DPS_PAYLOAD_KEY: &str = "IOTEDGE_DPS_PAYLOAD";
+
+pub(crate) static LABEL_KEY: &str = "net.azure-devices.edge.owner";
+pub(crate) static LABEL_VALUE: &str = "Microsoft.Azure.Devices.Edge.Agent";
 
 lazy_static! {
-    static ref LABELS: Vec<&'static str> = {
+    pub(crate) static ref LABELS: Vec<&'static str> = {
         let mut labels = vec![];
         labels.push("net.azure-devices.edge.owner=Microsoft.Azure.Devices.Edge.Agent");
         labels
@@ -64,9 +80,45 @@ lazy_static! {
 #[derive(Clone)]
 pub struct DockerModuleRuntime {
     client: DockerClient<UrlConnector>,
+    create_options_policy: Option<Arc<CreateOptionsPolicy>>,
+    image_verifier: Option<Arc<ImageVerifier>>,
+    networks: Arc<Vec<Network>>,
+    registry_credentials: Arc<RegistryCredentialStore>,
+    dps_payload: Option<Arc<String>>,
 }
 
 impl DockerModuleRuntime {
+    /// The credentials to pull the image of `config` with: its `auth`, or else the stored
+    /// credentials for its registry.
+    fn registry_auth(
+        &self,
+        config: &DockerConfig,
+    ) -> impl Future<Item = Option<AuthConfig>, Error = Error> + Send {
+        match config.auth() {
+            Some(auth) => Either::A(future::ok(Some(auth.clone()))),
+            None => Either::B(self.registry_credentials.auth(config.image())),
+        }
+    }
+
+    /// Modules can only be attached to and detached from the networks that are managed by the
+    /// runtime, and not to the host's other networks.
+    fn check_network(&self, id: &str, network: &str, operation: RuntimeOperation) -> Result<()> {
+        ensure_not_empty_with_context(id, || ErrorKind::RuntimeOperation(operation.clone()))?;
+
+        if self
+            .networks
+            .iter()
+            .any(|managed| managed.name() == network)
+        {
+            Ok(())
+        } else {
+            Err(Error::from(
+                ErrorKind::NotFound(format!("No such network: {}", network))
+                    .context(ErrorKind::RuntimeOperation(operation)),
+            ))
+        }
+    }
+
     fn merge_env(cur_env: Option<&[String]>, new_env: &HashMap<String, String>) -> Vec<String> {
         // build a new merged hashmap containing string slices for keys and values
         // pointing into String instances in new_env
@@ -100,6 +152,8 @@ impl ModuleRegistry for DockerModuleRuntime {
     type Error = Error;
     type PullFuture = Box<dyn Future<Item = (), Error = Self::Error> + Send>;
     type RemoveFuture = Box<dyn Future<Item = (), Error = Self::Error>>;
+    type LoginFuture = Box<dyn Future<Item = (), Error = Self::Error> + Send>;
+    type LogoutFuture = Box<dyn Future<Item = (), Error = Self::Error> + Send>;
     type Config = DockerConfig;
 
     fn pull(&self, config: &Self::Config) -> Self::PullFuture {
@@ -107,19 +161,26 @@ impl ModuleRegistry for DockerModuleRuntime {
 
         info!("Pulling image {}...", image);
 
-        let creds: Result<String> = config.auth().map_or_else(
-            || Ok("".to_string()),
-            |a| {
-                let json = serde_json::to_string(a).with_context(|_| {
-                    ErrorKind::RegistryOperation(RegistryOperation::PullImage(image.clone()))
-                })?;
-                Ok(base64::encode(&json))
-            },
-        );
-
-        let response = creds
-            .map(|creds| {
-                self.client
+        let client = self.client.clone();
+        let response = self
+            .registry_auth(config)
+            .then({
+                let image = image.clone();
+                move |auth| -> Result<String> {
+                    let context = || {
+                        ErrorKind::RegistryOperation(RegistryOperation::PullImage(image.clone()))
+                    };
+                    auth.context(context())?.map_or_else(
+                        || Ok("".to_string()),
+                        |a| {
+                            let json = serde_json::to_string(&a).with_context(|_| context())?;
+                            Ok(base64::encode(&json))
+                        },
+                    )
+                }
+            })
+            .and_then(move |creds| {
+                client
                     .image_api()
                     .image_create(&image, "", "", "", "", &creds, "")
                     .then(|result| match result {
@@ -130,8 +191,6 @@ impl ModuleRegistry for DockerModuleRuntime {
                         )),
                     })
             })
-            .into_future()
-            .flatten()
             .then(move |result| match result {
                 Ok(image) => {
                     info!("Successfully pulled image {}", image);
@@ -177,6 +236,52 @@ impl ModuleRegistry for DockerModuleRuntime {
                 }),
         )
     }
+
+    fn login(&self, registry: &str, username: &str, password: &str) -> Self::LoginFuture {
+        info!("Storing credentials for registry {}...", registry);
+
+        let context =
+            || ErrorKind::RegistryOperation(RegistryOperation::Login(registry.to_string()));
+        let result = ensure_not_empty_with_context(registry, context)
+            .and_then(|()| ensure_not_empty_with_context(username, context))
+            .and_then(|()| ensure_not_empty_with_context(password, context))
+            .map_err(Error::from)
+            .and_then(|()| {
+                self.registry_credentials
+                    .login(
+                        registry,
+                        RegistryCredentials::new(username.to_string(), password.to_string()),
+                    )
+                    .map_err(|err| Error::from(err.context(context())))
+            });
+
+        match &result {
+            Ok(()) => info!("Successfully stored credentials for registry {}", registry),
+            Err(err) => log_failure(Level::Warn, err),
+        }
+        Box::new(result.into_future())
+    }
+
+    fn logout(&self, registry: &str) -> Self::LogoutFuture {
+        info!("Removing stored credentials for registry {}...", registry);
+
+        let result = self.registry_credentials.logout(registry).map_err(|err| {
+            Error::from(
+                err.context(ErrorKind::RegistryOperation(RegistryOperation::Logout(
+                    registry.to_string(),
+                ))),
+            )
+        });
+
+        match &result {
+            Ok(()) => info!(
+                "Successfully removed stored credentials for registry {}",
+                registry
+            ),
+            Err(err) => log_failure(Level::Warn, err),
+        }
+        Box::new(result.into_future())
+    }
 }
 
 fn parse_get_response<'de, D>(resp: &InlineResponse200) -> std::result::Result<String, D::Error>
@@ -200,11 +305,39 @@ impl MakeModuleRuntime for DockerModuleRuntime {
 
     fn make_runtime(
         settings: Settings,
-        _: ProvisioningResult,
-        _: impl GetTrustBundle,
+        provisioning_result: ProvisioningResult,
+        crypto: impl GetTrustBundle + Encrypt + Decrypt + MakeRandom + Send + Sync + 'static,
     ) -> Self::Future {
         info!("Initializing module runtime...");
 
+        let dps_payload = provisioning_result
+            .payload()
+            .map(|payload| Arc::new(payload.to_string()));
+
+        let registry_credentials = Arc::new(RegistryCredentialStore::new(
+            settings.homedir().join(REGISTRY_CREDENTIALS_FILENAME),
+            settings.moby_runtime().registry_credentials(),
+            settings.moby_runtime().credential_helpers(),
+            crypto,
+        ));
+
+        let image_verifier = match settings
+            .moby_runtime()
+            .image_verification()
+            .map(ImageVerifier::new)
+            .transpose()
+        {
+            Ok(image_verifier) => image_verifier.map(Arc::new),
+            Err(err) => {
+                let err = Error::from(err.context(ErrorKind::Initialization));
+                log_failure(Level::Warn, &err);
+                return Box::new(future::err(err));
+            }
+        };
+        if image_verifier.is_some() {
+            info!("Verifying images before creating modules");
+        }
+
         // Clippy incorrectly flags the use of `.map(..).unwrap_or_else(..)` code as being replaceable
         // with `.ok().map_or_else`. This is incorrect because `.ok()` will result in the error being dropped.
         // So we suppress this lint. There's an open issue for this on the Clippy repo:
@@ -213,43 +346,42 @@ impl MakeModuleRuntime for DockerModuleRuntime {
         let created = init_client(settings.moby_runtime().uri())
             .map(|client| {
                 let network_id = settings.moby_runtime().network().name().to_string();
-                let (enable_i_pv6, ipam) = get_ipv6_settings(settings.moby_runtime().network());
                 info!("Using runtime network id {}", network_id);
 
-                let filter = format!(r#"{{"name":{{"{}":true}}}}"#, network_id);
+                // The network that the edge agent attaches the modules to comes first.
+                let networks: Vec<Network> =
+                    iter::once(settings.moby_runtime().network().network())
+                        .chain(settings.moby_runtime().networks().iter().cloned())
+                        .collect();
+
+                let create_options_policy = settings
+                    .moby_runtime()
+                    .create_options_policy()
+                    .cloned()
+                    .map(Arc::new);
+                if create_options_policy.is_some() {
+                    info!("Enforcing the create options policy");
+                }
+
                 let client_copy = client.clone();
-                let fut = client
-                    .network_api()
-                    .network_list(&filter)
-                    .and_then(move |existing_networks| {
-                        if existing_networks.is_empty() {
-                            let mut network_config =
-                                NetworkConfig::new(network_id).with_enable_i_pv6(enable_i_pv6);
-
-                            if let Some(ipam_config) = ipam {
-                                network_config.set_IPAM(ipam_config);
-                            };
-
-                            let fut = client_copy
-                                .network_api()
-                                .network_create(network_config)
-                                .map(move |_| client_copy);
-                            future::Either::A(fut)
-                        } else {
-                            future::Either::B(future::ok(client_copy))
-                        }
-                    })
+                let fut = network::reconcile_networks(client, networks.clone())
                     .map_err(|err| {
-                        let e = Error::from_docker_error(
-                            err,
-                            ErrorKind::RuntimeOperation(RuntimeOperation::Init),
+                        let e = Error::from(
+                            err.context(ErrorKind::RuntimeOperation(RuntimeOperation::Init)),
                         );
                         log_failure(Level::Warn, &e);
                         e
                     })
-                    .map(|client| {
+                    .map(move |()| {
                         info!("Successfully initialized module runtime");
-                        DockerModuleRuntime { client }
+                        DockerModuleRuntime {
+                            client: client_copy,
+                            create_options_policy,
+                            image_verifier,
+                            networks: Arc::new(networks),
+                            registry_credentials,
+                            dps_payload,
+                        }
                     });
 
                 future::Either::A(fut)
@@ -263,40 +395,6 @@ impl MakeModuleRuntime for DockerModuleRuntime {
     }
 }
 
-fn get_ipv6_settings(network_configuration: &MobyNetwork) -> (bool, Option<Ipam>) {
-    if let MobyNetwork::Network(network) = network_configuration {
-        let ipv6 = network.ipv6().unwrap_or_default();
-        network.ipam().and_then(CoreIpam::config).map_or_else(
-            || (ipv6, None),
-            |ipam_config| {
-                let config = ipam_config
-                    .iter()
-                    .map(|ipam_config| {
-                        let mut config_map = HashMap::new();
-                        if let Some(gateway_config) = ipam_config.gateway() {
-                            config_map.insert("Gateway".to_string(), gateway_config.to_string());
-                        };
-
-                        if let Some(subnet_config) = ipam_config.subnet() {
-                            config_map.insert("Subnet".to_string(), subnet_config.to_string());
-                        };
-
-                        if let Some(ip_range_config) = ipam_config.ip_range() {
-                            config_map.insert("IPRange".to_string(), ip_range_config.to_string());
-                        };
-
-                        config_map
-                    })
-                    .collect();
-
-                (ipv6, Some(Ipam::new().with_config(config)))
-            },
-        )
-    } else {
-        (false, None)
-    }
-}
-
 impl ModuleRuntime for DockerModuleRuntime {
     type Error = Error;
     type Config = DockerConfig;
@@ -304,6 +402,8 @@ impl ModuleRuntime for DockerModuleRuntime {
     type ModuleRegistry = Self;
     type Chunk = Chunk;
     type Logs = Logs;
+    type Events = Box<dyn Stream<Item = ModuleEvent, Error = Self::Error> + Send>;
+    type ExecStream = Upgraded;
 
     type CreateFuture = Box<dyn Future<Item = (), Error = Self::Error> + Send>;
     type GetFuture =
@@ -312,6 +412,11 @@ impl ModuleRuntime for DockerModuleRuntime {
     type ListWithDetailsStream =
         Box<dyn Stream<Item = (Self::Module, ModuleRuntimeState), Error = Self::Error> + Send>;
     type LogsFuture = Box<dyn Future<Item = Self::Logs, Error = Self::Error> + Send>;
+    type EventsFuture = Box<dyn Future<Item = Self::Events, Error = Self::Error> + Send>;
+    type ExecFuture =
+        Box<dyn Future<Item = (String, Self::ExecStream), Error = Self::Error> + Send>;
+    type ExecResizeFuture = Box<dyn Future<Item = (), Error = Self::Error> + Send>;
+    type ExecExitCodeFuture = Box<dyn Future<Item = Option<i64>, Error = Self::Error> + Send>;
     type RemoveFuture = Box<dyn Future<Item = (), Error = Self::Error> + Send>;
     type RestartFuture = Box<dyn Future<Item = (), Error = Self::Error> + Send>;
     type StartFuture = Box<dyn Future<Item = (), Error = Self::Error> + Send>;
@@ -320,6 +425,9 @@ impl ModuleRuntime for DockerModuleRuntime {
     type SystemResourcesFuture =
         Box<dyn Future<Item = SystemResources, Error = Self::Error> + Send>;
     type RemoveAllFuture = Box<dyn Future<Item = (), Error = Self::Error> + Send>;
+    type ListNetworksFuture = Box<dyn Future<Item = Vec<Network>, Error = Self::Error> + Send>;
+    type AttachNetworkFuture = Box<dyn Future<Item = (), Error = Self::Error> + Send>;
+    type DetachNetworkFuture = Box<dyn Future<Item = (), Error = Self::Error> + Send>;
 
     fn create(&self, module: ModuleSpec<Self::Config>) -> Self::CreateFuture {
         info!("Creating module {}...", module.name());
@@ -331,12 +439,20 @@ impl ModuleRuntime for DockerModuleRuntime {
             ))));
         }
 
+        let name = module.name().to_string();
         let result = module
             .config()
             .clone_create_options()
             .and_then(|create_options| {
+                // Every module gets the DPS payload, unless its spec sets the variable itself.
+                let mut env = module.env().clone();
+                if let Some(dps_payload) = &self.dps_payload {
+                    env.entry(DPS_PAYLOAD_KEY.to_string())
+                        .or_insert_with(|| dps_payload.to_string());
+                }
+
                 // merge environment variables
-                let merged_env = DockerModuleRuntime::merge_env(create_options.env(), module.env());
+                let merged_env = DockerModuleRuntime::merge_env(create_options.env(), &env);
 
                 let mut labels = create_options
                     .labels()
@@ -355,25 +471,70 @@ impl ModuleRuntime for DockerModuleRuntime {
                     .with_env(merged_env)
                     .with_labels(labels);
 
+                if let Some(policy) = &self.create_options_policy {
+                    policy
+                        .check(module.config().image(), &create_options)
+                        .map_err(|violation| {
+                            violation.context(ErrorKind::RuntimeOperation(
+                                RuntimeOperation::CreateModule(module.name().to_string()),
+                            ))
+                        })?;
+                }
+
                 // Here we don't add the container to the iot edge docker network as the edge-agent is expected to do that.
                 // It contains the logic to add a container to the iot edge network only if a network is not already specified.
-
-                Ok(self
-                    .client
-                    .container_api()
-                    .container_create(create_options, module.name())
-                    .then(|result| match result {
-                        Ok(_) => Ok(module),
-                        Err(err) => Err(Error::from_docker_error(
-                            err,
-                            ErrorKind::RuntimeOperation(RuntimeOperation::CreateModule(
-                                module.name().to_string(),
+                // The container is attached to the other networks that it's configured for once it's created.
+
+                let verified = match &self.image_verifier {
+                    Some(verifier) => {
+                        let verifier = verifier.clone();
+                        let client = self.client.clone();
+                        let name = module.name().to_string();
+                        let config = module.config().clone();
+                        Either::A(self.registry_auth(module.config()).and_then(move |auth| {
+                            verify_image(verifier, &client, &name, &config, auth)
+                        }))
+                    }
+                    None => Either::B(future::ok(())),
+                };
+
+                let client = self.client.clone();
+                let networks = network::configured_networks(&self.networks, module.name());
+                Ok(verified.and_then(move |()| {
+                    let attach_client = client.clone();
+                    client
+                        .container_api()
+                        .container_create(create_options, module.name())
+                        .then(|result| match result {
+                            Ok(_) => Ok(module),
+                            Err(err) => Err(Error::from_docker_error(
+                                err,
+                                ErrorKind::RuntimeOperation(RuntimeOperation::CreateModule(
+                                    module.name().to_string(),
+                                )),
                             )),
-                        )),
-                    }))
+                        })
+                        .and_then(move |module| {
+                            // The module is removed again if it can't be attached to its
+                            // networks, so that creating it can be retried.
+                            let name = module.name().to_string();
+                            network::attach_module(attach_client.clone(), name.clone(), networks)
+                                .or_else(move |err| {
+                                    attach_client
+                                        .container_api()
+                                        .container_delete(&name, false, true, false)
+                                        .then(move |_| Err(err))
+                                })
+                                .map(move |()| module)
+                        })
+                }))
             })
             .into_future()
             .flatten()
+            .then(move |result| {
+                observe_module_operation("create", &name, &result);
+                result
+            })
             .then(|result| match result {
                 Ok(module) => {
                     info!("Successfully created module {}", module.name());
@@ -421,7 +582,11 @@ impl ModuleRuntime for DockerModuleRuntime {
                             DockerModule::new(client_copy, name, config).with_context(|_| {
                                 ErrorKind::RuntimeOperation(RuntimeOperation::GetModule(id.clone()))
                             })?;
-                        let state = runtime_state(container.id(), container.state());
+                        let state = runtime_state(
+                            container.id(),
+                            container.restart_count(),
+                            container.state(),
+                        );
                         Ok((module, state))
                     }
                     Err(err) => {
@@ -445,6 +610,7 @@ impl ModuleRuntime for DockerModuleRuntime {
         }) {
             return Box::new(future::err(Error::from(err)));
         }
+        let name = id.clone();
 
         Box::new(
             self.client
@@ -463,6 +629,10 @@ impl ModuleRuntime for DockerModuleRuntime {
                         log_failure(Level::Warn, &err);
                         Err(err)
                     }
+                })
+                .then(move |result| {
+                    observe_module_operation("start", &name, &result);
+                    result
                 }),
         )
     }
@@ -476,6 +646,7 @@ impl ModuleRuntime for DockerModuleRuntime {
         }) {
             return Box::new(future::err(Error::from(err)));
         }
+        let name = id.clone();
 
         #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
         let wait_timeout = wait_before_kill.and_then(|s| match s.as_secs() {
@@ -500,6 +671,10 @@ impl ModuleRuntime for DockerModuleRuntime {
                         log_failure(Level::Warn, &err);
                         Err(err)
                     }
+                })
+                .then(move |result| {
+                    observe_module_operation("stop", &name, &result);
+                    result
                 }),
         )
     }
@@ -513,6 +688,7 @@ impl ModuleRuntime for DockerModuleRuntime {
         }) {
             return Box::new(future::err(Error::from(err)));
         }
+        let name = id.clone();
 
         Box::new(
             self.client
@@ -531,6 +707,10 @@ impl ModuleRuntime for DockerModuleRuntime {
                         log_failure(Level::Warn, &err);
                         Err(err)
                     }
+                })
+                .then(move |result| {
+                    observe_module_operation("restart", &name, &result);
+                    result
                 }),
         )
     }
@@ -545,6 +725,7 @@ impl ModuleRuntime for DockerModuleRuntime {
         }) {
             return Box::new(future::err(Error::from(err)));
         }
+        let name = id.clone();
 
         Box::new(
             self.client
@@ -566,6 +747,10 @@ impl ModuleRuntime for DockerModuleRuntime {
                         log_failure(Level::Warn, &err);
                         Err(err)
                     }
+                })
+                .then(move |result| {
+                    observe_module_operation("remove", &name, &result);
+                    result
                 }),
         )
     }
@@ -626,95 +811,99 @@ impl ModuleRuntime for DockerModuleRuntime {
                 )
                 .collect()
             })
-            .map(serde_json::Value::Array) // Condense into single json value
-            .and_then(|stats| {
-                // convert to string
-                serde_json::to_string(&stats).map_err(|_| {
+            .and_then(|stats: Vec<serde_json::Value>| {
+                // Keep the raw stats around for clients of older API versions
+                let docker_stats = serde_json::to_string(&stats).map_err(|_| {
                     Error::from(ErrorKind::RuntimeOperation(
                         RuntimeOperation::SystemResources,
                     ))
-                })
-            });
+                })?;
 
-        #[cfg(not(windows))]
-        {
-            #[cfg(target_os = "linux")]
-            let uptime = {
-                let mut info: libc::sysinfo = unsafe { mem::zeroed() };
-                let ret = unsafe { libc::sysinfo(&mut info) };
-                if ret == 0 {
-                    info.uptime.try_into().unwrap_or_default()
-                } else {
-                    0
-                }
-            };
-            #[cfg(not(target_os = "linux"))]
-            let uptime = 0;
-
-            let mut system_info = sysinfo::System::new();
-            system_info.refresh_all();
-            let current_time = SystemTime::now()
-                .duration_since(UNIX_EPOCH)
-                .unwrap_or_default()
-                .as_secs();
-            let start_time = process::id()
-                .try_into()
-                .map(|id| {
-                    system_info
-                        .get_process_list()
-                        .get(&id)
-                        .map(|p| p.start_time())
-                        .unwrap_or_default()
-                })
-                .unwrap_or_default();
-
-            let used_cpu = system_info
-                .get_processor_list()
-                .iter()
-                .find(|p| p.get_name() == "Total CPU")
-                .map_or_else(|| -1.0, |p| p.get_cpu_usage());
-
-            let total_memory = system_info.get_total_memory();
-            let used_memory = system_info.get_used_memory();
-
-            let disks = system_info
-                .get_disks()
-                .iter()
-                .map(|disk| {
-                    DiskInfo::new(
-                        disk.get_name().to_string_lossy().into_owned(),
-                        disk.get_available_space(),
-                        disk.get_total_space(),
-                        String::from_utf8_lossy(disk.get_file_system()).into_owned(),
-                        format!("{:?}", disk.get_type()),
-                    )
-                })
-                .collect();
-
-            let result = docker_stats.map(move |stats: String| {
-                SystemResources::new(
-                    uptime,
-                    current_time - start_time,
-                    used_cpu.into(),
-                    total_memory,
-                    used_memory,
-                    disks,
-                    stats,
-                )
-            });
+                let modules = stats
+                    .into_iter()
+                    .map(|stats| {
+                        let stats: ContainerStats =
+                            serde_json::from_value(stats).map_err(|_| {
+                                Error::from(ErrorKind::RuntimeOperation(
+                                    RuntimeOperation::SystemResources,
+                                ))
+                            })?;
+                        Ok(ModuleResourceUsage::from(&stats))
+                    })
+                    .collect::<Result<Vec<_>>>()?;
 
-            Box::new(result)
-        }
+                Ok((modules, docker_stats))
+            });
 
+        #[cfg(target_os = "linux")]
+        let uptime = {
+            let mut info: libc::sysinfo = unsafe { mem::zeroed() };
+            let ret = unsafe { libc::sysinfo(&mut info) };
+            if ret == 0 {
+                info.uptime.try_into().unwrap_or_default()
+            } else {
+                0
+            }
+        };
         #[cfg(windows)]
-        {
-            let uptime = unsafe { winapi::um::sysinfoapi::GetTickCount() };
-            let result = docker_stats.map(move |stats: String| {
-                SystemResources::new(uptime.into(), 0, 0.0, 0, 0, vec![], stats)
-            });
+        let uptime = unsafe { winapi::um::sysinfoapi::GetTickCount64() } / 1000;
+        #[cfg(not(any(target_os = "linux", windows)))]
+        let uptime = 0;
+
+        let mut system_info = sysinfo::System::new();
+        system_info.refresh_all();
+        let current_time = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+        let start_time = process::id()
+            .try_into()
+            .map(|id| {
+                system_info
+                    .get_process_list()
+                    .get(&id)
+                    .map(|p| p.start_time())
+                    .unwrap_or_default()
+            })
+            .unwrap_or_default();
 
-            Box::new(result)
-        }
+        let used_cpu = system_info
+            .get_processor_list()
+            .iter()
+            .find(|p| p.get_name() == "Total CPU")
+            .map_or_else(|| -1.0, |p| p.get_cpu_usage());
+
+        let total_memory = system_info.get_total_memory();
+        let used_memory = system_info.get_used_memory();
+
+        let disks = system_info
+            .get_disks()
+            .iter()
+            .map(|disk| {
+                DiskInfo::new(
+                    disk.get_name().to_string_lossy().into_owned(),
+                    disk.get_available_space(),
+                    disk.get_total_space(),
+                    String::from_utf8_lossy(disk.get_file_system()).into_owned(),
+                    format!("{:?}", disk.get_type()),
+                )
+            })
+            .collect();
+
+        let result = docker_stats.map(move |(modules, docker_stats)| {
+            SystemResources::new(
+                uptime,
+                current_time.saturating_sub(start_time),
+                used_cpu.into(),
+                used_memory,
+                total_memory,
+                disks,
+                modules,
+            )
+            .with_docker_stats(Some(docker_stats))
+        });
+
+        Box::new(result)
     }
 
     fn list(&self) -> Self::ListFuture {
@@ -821,6 +1010,158 @@ impl ModuleRuntime for DockerModuleRuntime {
         Box::new(result)
     }
 
+    fn events(&self, since: Option<DateTime<Utc>>) -> Self::EventsFuture {
+        debug!("Getting module events...");
+
+        let mut filters: HashMap<&str, &[&str]> = HashMap::new();
+        filters.insert("type", &["container"]);
+        filters.insert("label", LABELS.as_slice());
+        filters.insert("event", events::ACTIONS);
+
+        // Docker replays the events it still remembers from `since`, which it takes as
+        // seconds with an optional fraction, and only streams new events when it's empty.
+        let since = since.map_or_else(String::new, |since| {
+            format!(
+                "{}.{:09}",
+                since.timestamp(),
+                since.timestamp_subsec_nanos()
+            )
+        });
+
+        let result = serde_json::to_string(&filters)
+            .context(ErrorKind::RuntimeOperation(
+                RuntimeOperation::GetModuleEvents,
+            ))
+            .map_err(Error::from)
+            .map(|filters| {
+                self.client
+                    .system_api()
+                    .system_events(&since, "", &filters)
+                    .then(|result| match result {
+                        Ok(body) => {
+                            let events = Lines::new(body)
+                                .map_err(|err| {
+                                    Error::from(err.context(ErrorKind::RuntimeOperation(
+                                        RuntimeOperation::GetModuleEvents,
+                                    )))
+                                })
+                                .filter_map(|line| events::module_event(&line));
+                            Ok(Box::new(events) as Self::Events)
+                        }
+                        Err(err) => {
+                            let err = Error::from_docker_error(
+                                err,
+                                ErrorKind::RuntimeOperation(RuntimeOperation::GetModuleEvents),
+                            );
+                            log_failure(Level::Warn, &err);
+                            Err(err)
+                        }
+                    })
+            })
+            .into_future()
+            .flatten();
+
+        Box::new(result)
+    }
+
+    fn exec(&self, id: &str, options: &ExecOptions) -> Self::ExecFuture {
+        info!("Running command in module {}...", id);
+        let id = id.to_string();
+
+        if let Err(err) = ensure_not_empty_with_context(&id, || {
+            ErrorKind::RuntimeOperation(RuntimeOperation::ExecModule(id.clone()))
+        }) {
+            return Box::new(future::err(Error::from(err)));
+        }
+
+        let exec_config = ExecConfig::new()
+            .with_attach_stdin(true)
+            .with_attach_stdout(true)
+            .with_attach_stderr(true)
+            .with_tty(options.tty())
+            .with_cmd(options.command().to_vec());
+        let exec_start_config = ExecStartConfig::new()
+            .with_detach(false)
+            .with_tty(options.tty());
+        let tty_size = options.tty_size();
+        let client = self.client.clone();
+        let context = RuntimeOperation::ExecModule(id.clone());
+
+        Box::new(
+            module_container_id(&self.client, &id, context.clone())
+                .and_then(move |container_id| {
+                    let start_client = client.clone();
+                    client
+                        .exec_api()
+                        .container_exec(exec_config, &container_id)
+                        .and_then(move |exec| {
+                            let exec_id = exec.id().to_string();
+                            let start = start_client
+                                .exec_api()
+                                .exec_start(&exec_id, exec_start_config);
+                            start.and_then(move |stream| match tty_size {
+                                // Docker only resizes the TTY of exec instances that have started.
+                                Some((height, width)) => Either::A(
+                                    start_client
+                                        .exec_api()
+                                        .exec_resize(&exec_id, height.into(), width.into())
+                                        .map(|_| (exec_id, stream)),
+                                ),
+                                None => Either::B(future::ok((exec_id, stream))),
+                            })
+                        })
+                        .map_err(|err| {
+                            Error::from_docker_error(err, ErrorKind::RuntimeOperation(context))
+                        })
+                })
+                .then(move |result| match result {
+                    Ok(exec) => {
+                        info!("Successfully started command in module {}", id);
+                        Ok(exec)
+                    }
+                    Err(err) => {
+                        log_failure(Level::Warn, &err);
+                        Err(err)
+                    }
+                }),
+        )
+    }
+
+    fn exec_resize(
+        &self,
+        id: &str,
+        exec_id: &str,
+        height: u16,
+        width: u16,
+    ) -> Self::ExecResizeFuture {
+        let client = self.client.clone();
+        let context = RuntimeOperation::ExecModuleResize(id.to_string());
+        let exec_id = exec_id.to_string();
+
+        Box::new(
+            module_exec(&self.client, id, &exec_id, context.clone()).and_then(move |_| {
+                client
+                    .exec_api()
+                    .exec_resize(&exec_id, height.into(), width.into())
+                    .map_err(|err| {
+                        Error::from_docker_error(err, ErrorKind::RuntimeOperation(context))
+                    })
+            }),
+        )
+    }
+
+    fn exec_exit_code(&self, id: &str, exec_id: &str) -> Self::ExecExitCodeFuture {
+        let context = RuntimeOperation::ExecModuleExitCode(id.to_string());
+
+        Box::new(module_exec(&self.client, id, exec_id, context).map(|exec| {
+            if exec.running() == Some(&true) {
+                None
+            } else {
+                exec.exit_code().map(i64::from)
+            }
+        }))
+    }
+
     fn registry(&self) -> &Self::ModuleRegistry {
         self
     }
@@ -834,6 +1175,49 @@ impl ModuleRuntime for DockerModuleRuntime {
             future::join_all(n).map(|_| ())
         }))
     }
+
+    fn list_networks(&self) -> Self::ListNetworksFuture {
+        debug!("Listing networks...");
+
+        Box::new(
+            network::list_networks(&self.client, &self.networks).then(|result| {
+                match result {
+                    Ok(_) => debug!("Successfully listed networks"),
+                    Err(ref err) => log_failure(Level::Warn, err),
+                }
+
+                result
+            }),
+        )
+    }
+
+    fn attach_network(&self, id: &str, network: &str) -> Self::AttachNetworkFuture {
+        let operation = RuntimeOperation::AttachNetwork(id.to_string(), network.to_string());
+        if let Err(err) = self.check_network(id, network, operation) {
+            log_failure(Level::Warn, &err);
+            return Box::new(future::err(err));
+        }
+
+        Box::new(network::connect(&self.client, id, network).map_err(|err| {
+            log_failure(Level::Warn, &err);
+            err
+        }))
+    }
+
+    fn detach_network(&self, id: &str, network: &str) -> Self::DetachNetworkFuture {
+        let operation = RuntimeOperation::DetachNetwork(id.to_string(), network.to_string());
+        if let Err(err) = self.check_network(id, network, operation) {
+            log_failure(Level::Warn, &err);
+            return Box::new(future::err(err));
+        }
+
+        Box::new(
+            network::disconnect(&self.client, id, network).map_err(|err| {
+                log_failure(Level::Warn, &err);
+                err
+            }),
+        )
+    }
 }
 
 impl Authenticator for DockerModuleRuntime {
@@ -846,7 +1230,75 @@ impl Authenticator for DockerModuleRuntime {
     }
 }
 
-fn init_client(docker_url: &Url) -> Result<DockerClient<UrlConnector>> {
+/// Gets the id of the container of a module. Containers that iotedged didn't create aren't
+/// modules, so they're reported as not found rather than letting commands run in them.
+fn module_container_id(
+    client: &DockerClient<UrlConnector>,
+    id: &str,
+    context: RuntimeOperation,
+) -> impl Future<Item = String, Error = Error> + Send {
+    let name = id.to_string();
+
+    client
+        .container_api()
+        .container_inspect(id, false)
+        .then(move |result| match result {
+            Ok(container) => {
+                let is_module = container
+                    .config()
+                    .and_then(|config| config.labels())
+                    .and_then(|labels| labels.get(LABEL_KEY))
+                    .map_or(false, |value| value == LABEL_VALUE);
+                match container.id() {
+                    Some(container_id) if is_module => Ok(container_id.to_string()),
+                    _ => Err(Error::from(
+                        ErrorKind::NotFound(format!("No such module: {}", name))
+                            .context(ErrorKind::RuntimeOperation(context)),
+                    )),
+                }
+            }
+            Err(err) => Err(Error::from_docker_error(
+                err,
+                ErrorKind::RuntimeOperation(context),
+            )),
+        })
+}
+
+/// Inspects a command started in a module. Commands started in other containers are reported
+/// as not found.
+fn module_exec(
+    client: &DockerClient<UrlConnector>,
+    id: &str,
+    exec_id: &str,
+    context: RuntimeOperation,
+) -> impl Future<Item = InlineResponse20014, Error = Error> + Send {
+    let client = client.clone();
+    let exec_id = exec_id.to_string();
+
+    module_container_id(&client, id, context.clone()).and_then(move |container_id| {
+        client
+            .exec_api()
+            .exec_inspect(&exec_id)
+            .then(move |result| match result {
+                Ok(exec) => {
+                    if exec.container_id() == Some(container_id.as_str()) {
+                        Ok(exec)
+                    } else {
+                        Err(Error::from(
+                            ErrorKind::NotFound(format!("No such exec instance: {}", exec_id))
+                                .context(ErrorKind::RuntimeOperation(context)),
+                        ))
+                    }
+                }
+                Err(err) => Err(Error::from_docker_error(
+                    err,
+                    ErrorKind::RuntimeOperation(context),
+                )),
+            })
+    })
+}
+
+pub(crate) fn init_client(docker_url: &Url) -> Result<DockerClient<UrlConnector>> {
     // build the hyper client
     let client =
         Client::builder().build(UrlConnector::new(docker_url).context(ErrorKind::Initialization)?);
@@ -870,6 +1322,17 @@ fn init_client(docker_url: &Url) -> Result<DockerClient<UrlConnector>> {
     Ok(DockerClient::new(APIClient::new(configuration)))
 }
 
+fn observe_module_operation<T>(operation: &str, name: &str, result: &Result<T>) {
+    metrics::observe_module_operation(
+        operation,
+        name,
+        result
+            .as_ref()
+            .map(|_| ())
+            .map_err(ModuleRuntimeErrorReason::from),
+    );
+}
+
 #[derive(Debug)]
 pub struct Logs(String, Body);
 
@@ -1062,7 +1525,7 @@ mod tests {
         )
     }
 
-    fn crypto() -> impl GetTrustBundle {
+    fn crypto() -> TestHsm {
         TestHsm::default()
     }
 
@@ -1345,6 +1808,10 @@ mod tests {
             unimplemented!()
         }
 
+        fn parent_hostname(&self) -> Option<&str> {
+            unimplemented!()
+        }
+
         fn connect(&self) -> &Connect {
             unimplemented!()
         }
@@ -1418,6 +1885,8 @@ mod tests {
         type Error = Error;
         type PullFuture = FutureResult<(), Self::Error>;
         type RemoveFuture = FutureResult<(), Self::Error>;
+        type LoginFuture = FutureResult<(), Self::Error>;
+        type LogoutFuture = FutureResult<(), Self::Error>;
         type Config = TestConfig;
 
         fn pull(&self, _config: &Self::Config) -> Self::PullFuture {
@@ -1427,6 +1896,14 @@ mod tests {
         fn remove(&self, _name: &str) -> Self::RemoveFuture {
             unimplemented!()
         }
+
+        fn login(&self, _registry: &str, _username: &str, _password: &str) -> Self::LoginFuture {
+            unimplemented!()
+        }
+
+        fn logout(&self, _registry: &str) -> Self::LogoutFuture {
+            unimplemented!()
+        }
     }
 
     impl DockerModuleTop for TestModule {
@@ -1469,6 +1946,8 @@ mod tests {
         type ModuleRegistry = Self;
         type Chunk = String;
         type Logs = Empty<Self::Chunk, Self::Error>;
+        type Events = Empty<ModuleEvent, Self::Error>;
+        type ExecStream = Upgraded;
 
         type CreateFuture = FutureResult<(), Self::Error>;
         type GetFuture = FutureResult<(Self::Module, ModuleRuntimeState), Self::Error>;
@@ -1476,6 +1955,10 @@ mod tests {
         type ListWithDetailsStream =
             Box<dyn Stream<Item = (Self::Module, ModuleRuntimeState), Error = Self::Error> + Send>;
         type LogsFuture = FutureResult<Self::Logs, Self::Error>;
+        type EventsFuture = FutureResult<Self::Events, Self::Error>;
+        type ExecFuture = FutureResult<(String, Self::ExecStream), Self::Error>;
+        type ExecResizeFuture = FutureResult<(), Self::Error>;
+        type ExecExitCodeFuture = FutureResult<Option<i64>, Self::Error>;
         type RemoveFuture = FutureResult<(), Self::Error>;
         type RestartFuture = FutureResult<(), Self::Error>;
         type StartFuture = FutureResult<(), Self::Error>;
@@ -1484,6 +1967,9 @@ mod tests {
         type SystemResourcesFuture =
             Box<dyn Future<Item = SystemResources, Error = Self::Error> + Send>;
         type RemoveAllFuture = FutureResult<(), Self::Error>;
+        type ListNetworksFuture = FutureResult<Vec<Network>, Self::Error>;
+        type AttachNetworkFuture = FutureResult<(), Self::Error>;
+        type DetachNetworkFuture = FutureResult<(), Self::Error>;
 
         fn create(&self, _module: ModuleSpec<Self::Config>) -> Self::CreateFuture {
             unimplemented!()
@@ -1529,6 +2015,28 @@ mod tests {
             unimplemented!()
         }
 
+        fn events(&self, _since: Option<DateTime<Utc>>) -> Self::EventsFuture {
+            unimplemented!()
+        }
+
+        fn exec(&self, _id: &str, _options: &ExecOptions) -> Self::ExecFuture {
+            unimplemented!()
+        }
+
+        fn exec_resize(
+            &self,
+            _id: &str,
+            _exec_id: &str,
+            _height: u16,
+            _width: u16,
+        ) -> Self::ExecResizeFuture {
+            unimplemented!()
+        }
+
+        fn exec_exit_code(&self, _id: &str, _exec_id: &str) -> Self::ExecExitCodeFuture {
+            unimplemented!()
+        }
+
         fn registry(&self) -> &Self::ModuleRegistry {
             self
         }
@@ -1536,6 +2044,18 @@ mod tests {
         fn remove_all(&self) -> Self::RemoveAllFuture {
             unimplemented!()
         }
+
+        fn list_networks(&self) -> Self::ListNetworksFuture {
+            unimplemented!()
+        }
+
+        fn attach_network(&self, _id: &str, _network: &str) -> Self::AttachNetworkFuture {
+            unimplemented!()
+        }
+
+        fn detach_network(&self, _id: &str, _network: &str) -> Self::DetachNetworkFuture {
+            unimplemented!()
+        }
     }
 
     impl Authenticator for TestModuleList {
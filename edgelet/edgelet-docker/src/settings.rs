@@ -1,11 +1,12 @@
 // Copyright (c) Microsoft. All rights reserved.
 
+use std::collections::BTreeMap;
 use std::path::Path;
 
 use config::{Config, Environment};
 use docker::models::HostConfig;
 use edgelet_core::{
-    Certificates, Connect, Listen, MobyNetwork, ModuleSpec, Provisioning, RuntimeSettings,
+    Certificates, Connect, Listen, MobyNetwork, ModuleSpec, Network, Provisioning, RuntimeSettings,
     Settings as BaseSettings, UrlExt, WatchdogSettings,
 };
 use edgelet_utils::YamlFileSource;
@@ -14,7 +15,10 @@ use failure::{Context, Fail, ResultExt};
 use url::Url;
 
 use crate::config::DockerConfig;
+use crate::credentials::RegistryCredentials;
 use crate::error::{Error, ErrorKind};
+use crate::policy::CreateOptionsPolicy;
+use crate::verification::ImageVerificationSettings;
 
 #[cfg(unix)]
 pub const DEFAULTS: &str = include_str!("../config/unix/default.yaml");
@@ -27,11 +31,25 @@ const EDGE_NETWORKID_KEY: &str = "NetworkId";
 
 const UNIX_SCHEME: &str = "unix";
 
+/// Images whose name starts with this are pulled through the parent device's
+/// registry, e.g. `$upstream:443/azureiotedge-agent:1.0`.
+const UPSTREAM_PARENT_KEYWORD: &str = "$upstream";
+
 #[derive(Clone, Debug, serde_derive::Deserialize, serde_derive::Serialize)]
 pub struct MobyRuntime {
     #[serde(with = "url_serde")]
     uri: Url,
     network: MobyNetwork,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    networks: Vec<Network>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    create_options_policy: Option<CreateOptionsPolicy>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    image_verification: Option<ImageVerificationSettings>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    registry_credentials: BTreeMap<String, RegistryCredentials>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    credential_helpers: BTreeMap<String, String>,
 }
 
 impl MobyRuntime {
@@ -42,6 +60,31 @@ impl MobyRuntime {
     pub fn network(&self) -> &MobyNetwork {
         &self.network
     }
+
+    /// Networks besides `network` that are created for the modules, with the modules to attach
+    /// to each of them.
+    pub fn networks(&self) -> &[Network] {
+        &self.networks
+    }
+
+    pub fn create_options_policy(&self) -> Option<&CreateOptionsPolicy> {
+        self.create_options_policy.as_ref()
+    }
+
+    pub fn image_verification(&self) -> Option<&ImageVerificationSettings> {
+        self.image_verification.as_ref()
+    }
+
+    /// Credentials for pulling images whose config has no `auth`, keyed by registry host.
+    pub fn registry_credentials(&self) -> &BTreeMap<String, RegistryCredentials> {
+        &self.registry_credentials
+    }
+
+    /// Docker credential helpers to get credentials from for pulling images whose config has no
+    /// `auth`, keyed by registry host. The helper `acr-env` is the binary `docker-credential-acr-env`.
+    pub fn credential_helpers(&self) -> &BTreeMap<String, String> {
+        &self.credential_helpers
+    }
 }
 
 /// This struct is the same as the Settings type from the `edgelet_core` crate
@@ -93,6 +136,10 @@ impl RuntimeSettings for Settings {
         self.base.hostname()
     }
 
+    fn parent_hostname(&self) -> Option<&str> {
+        self.base.parent_hostname()
+    }
+
     fn connect(&self) -> &Connect {
         self.base.connect()
     }
@@ -121,6 +168,9 @@ fn init_agent_spec(settings: &mut Settings) -> Result<(), LoadSettingsError> {
     // setup environment variables that are moby/docker specific
     agent_env(settings);
 
+    // pull the agent image through the parent device in a nested network
+    agent_image(settings);
+
     Ok(())
 }
 
@@ -179,6 +229,17 @@ fn agent_env(settings: &mut Settings) {
         .insert(EDGE_NETWORKID_KEY.to_string(), network_id);
 }
 
+fn agent_image(settings: &mut Settings) {
+    if let Some(parent_hostname) = settings.parent_hostname().map(ToString::to_string) {
+        let image = settings.agent().config().image();
+        if image.starts_with(UPSTREAM_PARENT_KEYWORD) {
+            let image = image.replacen(UPSTREAM_PARENT_KEYWORD, &parent_hostname, 1);
+            let config = settings.agent().config().clone().with_image(image);
+            *settings.agent_mut().config_mut() = config;
+        }
+    }
+}
+
 #[derive(Debug, Fail)]
 #[fail(display = "Could not load settings")]
 pub struct LoadSettingsError(#[cause] Context<Box<dyn std::fmt::Display + Send + Sync>>);
@@ -297,6 +358,14 @@ mod tests {
         "test/linux/bad_sample_settings.dyn.repro.yaml";
     #[cfg(unix)]
     static GOOD_SETTINGS_TLS: &str = "test/linux/sample_settings.tls.yaml";
+    #[cfg(unix)]
+    static GOOD_SETTINGS_PARENT_HOSTNAME: &str = "test/linux/sample_settings.parent.yaml";
+    #[cfg(unix)]
+    static GOOD_SETTINGS_MANUAL_FALLBACK: &str = "test/linux/sample_settings.manual.fallback.yaml";
+    #[cfg(unix)]
+    static BAD_SETTINGS_MANUAL_FALLBACK: &str = "test/linux/bad_settings.manual.fallback.yaml";
+    #[cfg(unix)]
+    static BAD_SETTINGS_RATE_LIMITS: &str = "test/linux/bad_settings.rate_limits.yaml";
 
     #[cfg(windows)]
     static GOOD_SETTINGS: &str = "test/windows/sample_settings.yaml";
@@ -362,6 +431,15 @@ mod tests {
         "test/windows/bad_sample_settings.dyn.repro.yaml";
     #[cfg(windows)]
     static GOOD_SETTINGS_TLS: &str = "test/windows/sample_settings.tls.yaml";
+    #[cfg(windows)]
+    static GOOD_SETTINGS_PARENT_HOSTNAME: &str = "test/windows/sample_settings.parent.yaml";
+    #[cfg(windows)]
+    static GOOD_SETTINGS_MANUAL_FALLBACK: &str =
+        "test/windows/sample_settings.manual.fallback.yaml";
+    #[cfg(windows)]
+    static BAD_SETTINGS_MANUAL_FALLBACK: &str = "test/windows/bad_settings.manual.fallback.yaml";
+    #[cfg(windows)]
+    static BAD_SETTINGS_RATE_LIMITS: &str = "test/windows/bad_settings.rate_limits.yaml";
 
     fn unwrap_manual_provisioning(p: &ProvisioningType) -> String {
         match p {
@@ -382,12 +460,22 @@ mod tests {
         let moby1 = MobyRuntime {
             uri: Url::parse("http://test").unwrap(),
             network: MobyNetwork::Name("".to_string()),
+            networks: vec![],
+            create_options_policy: None,
+            image_verification: None,
+            registry_credentials: BTreeMap::new(),
+            credential_helpers: BTreeMap::new(),
         };
         assert_eq!(DEFAULT_NETWORKID, moby1.network().name());
 
         let moby2 = MobyRuntime {
             uri: Url::parse("http://test").unwrap(),
             network: MobyNetwork::Name("some-network".to_string()),
+            networks: vec![],
+            create_options_policy: None,
+            image_verification: None,
+            registry_credentials: BTreeMap::new(),
+            credential_helpers: BTreeMap::new(),
         };
         assert_eq!("some-network", moby2.network().name());
     }
@@ -426,6 +514,34 @@ mod tests {
             }
             MobyNetwork::Name(_name) => panic!("Unexpected network configuration."),
         };
+
+        let networks = moby_runtime.networks();
+        assert_eq!(2, networks.len());
+
+        let fieldbus = &networks[0];
+        assert_eq!("fieldbus", fieldbus.name());
+        assert_eq!(Some("macvlan"), fieldbus.driver());
+        assert_eq!(Some(&"eth1".to_string()), fieldbus.options().get("parent"));
+        let ipam_config = fieldbus
+            .ipam()
+            .and_then(|ipam| ipam.config())
+            .expect("Expected IPAM configuration.");
+        assert_eq!(
+            &[IpamConfig::default()
+                .with_gateway("192.168.50.1".to_string())
+                .with_subnet("192.168.50.0/24".to_string())],
+            ipam_config
+        );
+        assert_eq!(&["opcpublisher".to_string()], fieldbus.modules());
+
+        let analytics = &networks[1];
+        assert_eq!("analytics", analytics.name());
+        assert_eq!(None, analytics.driver());
+        assert_eq!(Some(true), analytics.internal());
+        assert_eq!(
+            &["filter".to_string(), "store".to_string()],
+            analytics.modules()
+        );
     }
 
     #[test]
@@ -520,6 +636,41 @@ mod tests {
         );
     }
 
+    #[test]
+    fn manual_authentication_fallback_hubs() {
+        let settings = Settings::new(Path::new(GOOD_SETTINGS_MANUAL_FALLBACK)).unwrap();
+        match settings.provisioning().provisioning_type() {
+            ProvisioningType::Manual(manual) => {
+                let hubs: Vec<_> = manual
+                    .authentication_methods()
+                    .map(|auth| match auth {
+                        ManualAuthMethod::DeviceConnectionString(cs) => {
+                            cs.parse_device_connection_string().unwrap().2
+                        }
+                        ManualAuthMethod::X509(_) => unreachable!(),
+                    })
+                    .collect();
+                assert_eq!(
+                    vec!["something.something.com", "secondary.something.com"],
+                    hubs
+                );
+                assert_eq!(1, manual.fallback_authentication_methods().len());
+            }
+            _ => unreachable!(),
+        }
+
+        let settings = Settings::new(Path::new(GOOD_SETTINGS_MANUAL_CS_AUTH)).unwrap();
+        match settings.provisioning().provisioning_type() {
+            ProvisioningType::Manual(manual) => {
+                assert!(manual.fallback_authentication_methods().is_empty());
+            }
+            _ => unreachable!(),
+        }
+
+        let settings = Settings::new(Path::new(BAD_SETTINGS_MANUAL_FALLBACK));
+        assert!(settings.is_err());
+    }
+
     #[test]
     fn manual_empty_connection_string_fails() {
         let settings = Settings::new(Path::new(BAD_SETTINGS_MANUAL_CS3));
@@ -830,6 +981,10 @@ mod tests {
                     AttestationMethod::SymmetricKey(ref key) => {
                         assert_eq!(key.symmetric_key(), "key");
                         assert_eq!(key.registration_id(), "register me fool");
+                        assert_eq!(
+                            key.payload(),
+                            Some(&json!({ "siteId": "site 7", "skus": ["a", "b"] }))
+                        );
                     }
                     _ => unreachable!(),
                 }
@@ -1035,6 +1190,7 @@ mod tests {
             settings.listen().min_tls_version(),
             edgelet_core::Protocol::Tls12
         );
+        assert!(settings.listen().management_client_ca().is_some());
     }
 
     #[test]
@@ -1044,5 +1200,36 @@ mod tests {
             settings.listen().min_tls_version(),
             edgelet_core::Protocol::Tls10
         );
+        assert_eq!(None, settings.listen().management_client_ca());
+    }
+
+    #[test]
+    fn invalid_rate_limits_fail_to_load() {
+        let err = Settings::new(Path::new(BAD_SETTINGS_RATE_LIMITS)).unwrap_err();
+        let message = Fail::iter_chain(&err)
+            .map(ToString::to_string)
+            .collect::<Vec<_>>()
+            .join(": ");
+        assert!(
+            message.contains("requests_per_second must be a number greater than 0"),
+            "{}",
+            message
+        );
+    }
+
+    #[test]
+    fn parent_hostname_is_none_by_default() {
+        let settings = Settings::new(Path::new(GOOD_SETTINGS)).unwrap();
+        assert_eq!(None, settings.parent_hostname());
+    }
+
+    #[test]
+    fn agent_image_is_pulled_through_parent() {
+        let settings = Settings::new(Path::new(GOOD_SETTINGS_PARENT_HOSTNAME)).unwrap();
+        assert_eq!(Some("parent.factory.local"), settings.parent_hostname());
+        assert_eq!(
+            "parent.factory.local:443/azureiotedge-agent:1.0",
+            settings.agent().config().image()
+        );
     }
 }
@@ -0,0 +1,466 @@
+// Copyright (c) Microsoft. All rights reserved.
+
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::thread;
+
+use failure::{Fail, ResultExt};
+use futures::future::{self, Either};
+use futures::sync::oneshot;
+use futures::Future;
+use openssl::hash::MessageDigest;
+use openssl::pkey::{PKey, Public};
+use openssl::sign::Verifier;
+use serde_json::Value;
+
+use containrs::oci_image::v1::Manifest;
+use containrs::{Client, Credentials, Digest, Reference};
+use docker::models::AuthConfig;
+use edgelet_core::RuntimeOperation;
+use edgelet_http::UrlConnector;
+
+use crate::client::DockerClient;
+use crate::config::DockerConfig;
+use crate::error::{Error, ErrorKind, Result};
+
+/// The annotation of the layers of a cosign signature manifest that holds the base64-encoded
+/// signature of the layer.
+const SIGNATURE_ANNOTATION: &str = "dev.cosignproject.cosign/signature";
+
+/// Settings for verifying images before modules are created from them.
+#[derive(Clone, Debug, Default, serde_derive::Deserialize, serde_derive::Serialize)]
+pub struct ImageVerificationSettings {
+    #[serde(default)]
+    allowed_digests: Vec<String>,
+    #[serde(default)]
+    trusted_keys: Vec<PathBuf>,
+}
+
+impl ImageVerificationSettings {
+    pub fn allowed_digests(&self) -> &[String] {
+        &self.allowed_digests
+    }
+
+    pub fn trusted_keys(&self) -> &[PathBuf] {
+        &self.trusted_keys
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum ImageVerificationReason {
+    DigestMismatch(String),
+    DigestNotAllowed(String),
+    FetchSignatures,
+    InspectImage,
+    NoDigest,
+    NoTrustedSignature,
+}
+
+impl fmt::Display for ImageVerificationReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ImageVerificationReason::DigestMismatch(digest) => {
+                write!(f, "the image does not match the pinned digest {}", digest)
+            }
+            ImageVerificationReason::DigestNotAllowed(digest) => write!(
+                f,
+                "the image is not pinned to a digest and its digest {} is not allowed",
+                digest
+            ),
+            ImageVerificationReason::FetchSignatures => {
+                write!(f, "could not fetch the signatures of the image")
+            }
+            ImageVerificationReason::InspectImage => write!(f, "could not inspect the image"),
+            ImageVerificationReason::NoDigest => {
+                write!(
+                    f,
+                    "the image was not pulled from a registry, so it has no digest"
+                )
+            }
+            ImageVerificationReason::NoTrustedSignature => {
+                write!(f, "the image is not signed by a trusted key")
+            }
+        }
+    }
+}
+
+/// A signature of an image and the payload that it signs.
+#[derive(Debug)]
+struct Signature {
+    payload: Vec<u8>,
+    signature: Vec<u8>,
+}
+
+/// Verifies that images are pinned or allowed by digest, and signed by a trusted key if there
+/// are any.
+#[derive(Clone)]
+pub struct ImageVerifier {
+    allowed_digests: Vec<String>,
+    trusted_keys: Vec<PKey<Public>>,
+}
+
+impl ImageVerifier {
+    pub fn new(settings: &ImageVerificationSettings) -> Result<Self> {
+        let trusted_keys = settings
+            .trusted_keys()
+            .iter()
+            .map(|path| load_key(path))
+            .collect::<Result<_>>()?;
+
+        Ok(ImageVerifier {
+            allowed_digests: settings.allowed_digests().to_vec(),
+            trusted_keys,
+        })
+    }
+
+    /// Gets the digest of a pulled image from its repo digests, like
+    /// `mcr.microsoft.com/azureiotedge-agent@sha256:...`. An image that is pinned to a digest
+    /// must have that digest. Any other image must have a digest that is allowed, either by
+    /// itself or with the name of the image.
+    fn check_digest(
+        &self,
+        image: &str,
+        repo_digests: &[String],
+    ) -> std::result::Result<String, ImageVerificationReason> {
+        let digests: Vec<(&str, &str)> = repo_digests
+            .iter()
+            .filter_map(|repo_digest| {
+                let index = repo_digest.find('@')?;
+                Some((repo_digest.as_str(), &repo_digest[index + 1..]))
+            })
+            .collect();
+
+        if let Some(index) = image.find('@') {
+            let pinned = &image[index + 1..];
+            return if digests.iter().any(|&(_, digest)| digest == pinned) {
+                Ok(pinned.to_string())
+            } else {
+                Err(ImageVerificationReason::DigestMismatch(pinned.to_string()))
+            };
+        }
+
+        let allowed = digests.iter().find(|&&(repo_digest, digest)| {
+            self.allowed_digests
+                .iter()
+                .any(|allowed| allowed == repo_digest || allowed == digest)
+        });
+        match (allowed, digests.first()) {
+            (Some(&(_, digest)), _) => Ok(digest.to_string()),
+            (None, Some(&(_, digest))) => Err(ImageVerificationReason::DigestNotAllowed(
+                digest.to_string(),
+            )),
+            (None, None) => Err(ImageVerificationReason::NoDigest),
+        }
+    }
+
+    /// An image is signed if a trusted key made a signature of a payload for its digest.
+    fn check_signatures(&self, digest: &str, signatures: &[Signature]) -> bool {
+        signatures.iter().any(|signature| {
+            signed_digest(&signature.payload).map_or(false, |signed| signed == digest)
+                && self.trusted_keys.iter().any(|key| {
+                    Verifier::new(MessageDigest::sha256(), key)
+                        .and_then(|mut verifier| {
+                            verifier.update(&signature.payload)?;
+                            verifier.verify(&signature.signature)
+                        })
+                        .unwrap_or(false)
+                })
+        })
+    }
+}
+
+impl fmt::Debug for ImageVerifier {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ImageVerifier")
+            .field("allowed_digests", &self.allowed_digests)
+            .finish()
+    }
+}
+
+/// Verifies the pulled image of a module before the module is created. Its signatures are
+/// fetched with `auth`, the credentials that the image was pulled with.
+pub fn verify_image(
+    verifier: Arc<ImageVerifier>,
+    client: &DockerClient<UrlConnector>,
+    name: &str,
+    config: &DockerConfig,
+    auth: Option<AuthConfig>,
+) -> impl Future<Item = (), Error = Error> + Send {
+    let name = name.to_string();
+    let image = config.image().to_string();
+
+    client
+        .image_api()
+        .image_inspect(&image)
+        .then({
+            let image = image.clone();
+            move |inspect| {
+                inspect.map_err(|err| {
+                    Error::from_docker_error(
+                        err,
+                        ErrorKind::UnverifiedImage(image, ImageVerificationReason::InspectImage),
+                    )
+                })
+            }
+        })
+        .and_then(move |inspect| {
+            let digest = verifier
+                .check_digest(&image, inspect.repo_digests().unwrap_or_default())
+                .map_err(|reason| refused(&name, &image, reason))?;
+
+            if verifier.trusted_keys.is_empty() {
+                return Ok(Either::A(future::ok(())));
+            }
+
+            let signatures =
+                fetch_signatures(&image, &digest, auth.as_ref()).and_then(move |signatures| {
+                    if verifier.check_signatures(&digest, &signatures) {
+                        Ok(())
+                    } else {
+                        Err(refused(
+                            &name,
+                            &image,
+                            ImageVerificationReason::NoTrustedSignature,
+                        ))
+                    }
+                });
+            Ok(Either::B(signatures))
+        })
+        .flatten()
+}
+
+/// The reason is the root cause of the error, so that it is reported as a policy violation.
+fn refused(name: &str, image: &str, reason: ImageVerificationReason) -> Error {
+    Error::from(
+        ErrorKind::UnverifiedImage(image.to_string(), reason).context(ErrorKind::RuntimeOperation(
+            RuntimeOperation::CreateModule(name.to_string()),
+        )),
+    )
+}
+
+fn load_key(path: &Path) -> Result<PKey<Public>> {
+    let pem =
+        fs::read(path).with_context(|_| ErrorKind::LoadTrustedKey(path.display().to_string()))?;
+    let key = PKey::public_key_from_pem(&pem)
+        .with_context(|_| ErrorKind::LoadTrustedKey(path.display().to_string()))?;
+    Ok(key)
+}
+
+/// Gets the image digest that a signature payload is for. Payloads are in the simple signing
+/// format: `{"critical":{"image":{"docker-manifest-digest":"sha256:..."}, ...}, ...}`
+fn signed_digest(payload: &[u8]) -> Option<String> {
+    let payload: Value = serde_json::from_slice(payload).ok()?;
+    payload
+        .pointer("/critical/image/docker-manifest-digest")
+        .and_then(Value::as_str)
+        .map(ToString::to_string)
+}
+
+/// Fetches the cosign signatures of an image. They are stored in the same repository, in a
+/// manifest tagged `sha256-<digest>.sig` whose layers are the signed payloads.
+///
+/// The registry client runs on its own thread and runtime, since it uses newer futures than
+/// the rest of the module runtime.
+fn fetch_signatures(
+    image: &str,
+    digest: &str,
+    auth: Option<&AuthConfig>,
+) -> impl Future<Item = Vec<Signature>, Error = Error> + Send {
+    let context =
+        ErrorKind::UnverifiedImage(image.to_string(), ImageVerificationReason::FetchSignatures);
+
+    let reference = image
+        .parse::<Reference>()
+        .map_err(failure::Error::from)
+        .and_then(|image| {
+            let digest = Digest::new(digest)?;
+            let tag = format!(
+                "{}/{}:{}-{}.sig",
+                image.registry(),
+                image.repo(),
+                digest.algorithm(),
+                digest.encoded()
+            );
+            Ok(tag.parse::<Reference>()?)
+        });
+    let reference = match reference {
+        Ok(reference) => reference,
+        Err(err) => return Either::A(future::err(Error::from(err.context(context)))),
+    };
+
+    let credentials = match auth.map(|auth| (auth.username(), auth.password())) {
+        Some((Some(username), Some(password))) => {
+            Credentials::UserPass(username.to_string(), password.to_string())
+        }
+        _ => Credentials::Anonymous,
+    };
+
+    let (sender, receiver) = oneshot::channel();
+    thread::spawn(move || {
+        let signatures = tokio02::runtime::Builder::new()
+            .basic_scheduler()
+            .enable_all()
+            .build()
+            .map_err(failure::Error::from)
+            .and_then(|mut runtime| runtime.block_on(get_signatures(reference, credentials)));
+        let _ = sender.send(signatures);
+    });
+
+    Either::B(receiver.then(move |signatures| match signatures {
+        Ok(Ok(signatures)) => Ok(signatures),
+        Ok(Err(err)) => Err(Error::from(err.context(context))),
+        Err(_) => Err(Error::from(context)),
+    }))
+}
+
+async fn get_signatures(
+    reference: Reference,
+    credentials: Credentials,
+) -> std::result::Result<Vec<Signature>, failure::Error> {
+    let client = Client::new("https", reference.registry(), credentials)?;
+    let manifest = client.get_raw_manifest(&reference).await?.bytes().await?;
+    let manifest: Manifest = serde_json::from_slice(&manifest)?;
+
+    let mut signatures = vec![];
+    for layer in &manifest.layers {
+        let signature = match layer
+            .annotations
+            .as_ref()
+            .and_then(|annotations| annotations.get(SIGNATURE_ANNOTATION))
+        {
+            Some(signature) => base64::decode(signature)?,
+            None => continue,
+        };
+
+        let payload = client
+            .get_blob(reference.repo(), layer)
+            .await?
+            .bytes()
+            .await?;
+        if layer.digest.validate(&payload) {
+            signatures.push(Signature {
+                payload: payload.to_vec(),
+                signature,
+            });
+        }
+    }
+
+    Ok(signatures)
+}
+
+#[cfg(test)]
+mod tests {
+    use openssl::ec::{EcGroup, EcKey};
+    use openssl::nid::Nid;
+    use openssl::pkey::Private;
+    use openssl::sign::Signer;
+
+    use super::*;
+
+    const DIGEST: &str = "sha256:6c3c624b58dbbcd3c0dd82b4c53f04194d1247c6eebdaab7c610cf7d66709b3b";
+    const OTHER_DIGEST: &str =
+        "sha256:0000000000000000000000000000000000000000000000000000000000000000";
+
+    fn image_verifier(allowed_digests: &[&str], trusted_keys: Vec<PKey<Public>>) -> ImageVerifier {
+        ImageVerifier {
+            allowed_digests: allowed_digests.iter().map(ToString::to_string).collect(),
+            trusted_keys,
+        }
+    }
+
+    fn repo_digests(digest: &str) -> Vec<String> {
+        vec![format!("mcr.microsoft.com/azureiotedge-agent@{}", digest)]
+    }
+
+    fn key_pair() -> (PKey<Private>, PKey<Public>) {
+        let group = EcGroup::from_curve_name(Nid::X9_62_PRIME256V1).unwrap();
+        let private = EcKey::generate(&group).unwrap();
+        let public = EcKey::from_public_key(&group, private.public_key()).unwrap();
+        (
+            PKey::from_ec_key(private).unwrap(),
+            PKey::from_ec_key(public).unwrap(),
+        )
+    }
+
+    fn sign(key: &PKey<Private>, digest: &str) -> Signature {
+        let payload = format!(
+            r#"{{"critical":{{"identity":{{"docker-reference":"mcr.microsoft.com/azureiotedge-agent"}},"image":{{"docker-manifest-digest":"{}"}},"type":"cosign container image signature"}},"optional":null}}"#,
+            digest
+        )
+        .into_bytes();
+        let mut signer = Signer::new(MessageDigest::sha256(), key).unwrap();
+        signer.update(&payload).unwrap();
+        let signature = signer.sign_to_vec().unwrap();
+        Signature { payload, signature }
+    }
+
+    #[test]
+    fn pinned_image_must_match() {
+        let verifier = image_verifier(&[], vec![]);
+        let image = format!("mcr.microsoft.com/azureiotedge-agent@{}", DIGEST);
+
+        assert_eq!(
+            DIGEST,
+            verifier
+                .check_digest(&image, &repo_digests(DIGEST))
+                .unwrap()
+        );
+        assert_eq!(
+            ImageVerificationReason::DigestMismatch(DIGEST.to_string()),
+            verifier
+                .check_digest(&image, &repo_digests(OTHER_DIGEST))
+                .unwrap_err()
+        );
+    }
+
+    #[test]
+    fn unpinned_image_must_be_allowed() {
+        let image = "mcr.microsoft.com/azureiotedge-agent:1.0";
+
+        let verifier = image_verifier(&[DIGEST], vec![]);
+        assert_eq!(
+            DIGEST,
+            verifier.check_digest(image, &repo_digests(DIGEST)).unwrap()
+        );
+        assert_eq!(
+            ImageVerificationReason::DigestNotAllowed(OTHER_DIGEST.to_string()),
+            verifier
+                .check_digest(image, &repo_digests(OTHER_DIGEST))
+                .unwrap_err()
+        );
+
+        let verifier = image_verifier(&[repo_digests(DIGEST)[0].as_str()], vec![]);
+        assert_eq!(
+            DIGEST,
+            verifier.check_digest(image, &repo_digests(DIGEST)).unwrap()
+        );
+    }
+
+    #[test]
+    fn local_image_has_no_digest() {
+        let verifier = image_verifier(&[DIGEST], vec![]);
+
+        assert_eq!(
+            ImageVerificationReason::NoDigest,
+            verifier.check_digest("local-image", &[]).unwrap_err()
+        );
+    }
+
+    #[test]
+    fn signature_must_be_trusted_and_for_the_digest() {
+        let (private, public) = key_pair();
+        let (other_private, _) = key_pair();
+        let verifier = image_verifier(&[], vec![public]);
+
+        assert!(verifier.check_signatures(DIGEST, &[sign(&private, DIGEST)]));
+        assert!(!verifier.check_signatures(DIGEST, &[sign(&other_private, DIGEST)]));
+        assert!(!verifier.check_signatures(DIGEST, &[sign(&private, OTHER_DIGEST)]));
+        assert!(verifier.check_signatures(
+            DIGEST,
+            &[sign(&other_private, DIGEST), sign(&private, DIGEST)]
+        ));
+        assert!(!verifier.check_signatures(DIGEST, &[]));
+    }
+}
@@ -0,0 +1,113 @@
+// Copyright (c) Microsoft. All rights reserved.
+
+use std::collections::{HashMap, HashSet};
+use std::ops::Deref;
+use std::path::{Path, PathBuf};
+
+use failure::Fail;
+use futures::future::{self, Either};
+use futures::Future;
+use url::Url;
+
+use docker::models::{Volume, VolumeConfig};
+
+use crate::error::{Error, ErrorKind};
+use crate::runtime::{init_client, LABELS};
+
+/// A Docker volume that holds data of a module.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ModuleVolume {
+    name: String,
+    mountpoint: PathBuf,
+}
+
+impl ModuleVolume {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Where the data of the volume is kept on the host.
+    pub fn mountpoint(&self) -> &Path {
+        &self.mountpoint
+    }
+}
+
+impl From<&Volume> for ModuleVolume {
+    fn from(volume: &Volume) -> Self {
+        ModuleVolume {
+            name: volume.name().clone(),
+            mountpoint: PathBuf::from(volume.mountpoint()),
+        }
+    }
+}
+
+/// Lists the volumes that are mounted into the modules started by the edge agent.
+pub fn list_module_volumes(
+    docker_url: &Url,
+) -> impl Future<Item = Vec<ModuleVolume>, Error = Error> + Send {
+    let mut filters = HashMap::new();
+    filters.insert("label", LABELS.deref());
+
+    let filters = match serde_json::to_string(&filters) {
+        Ok(filters) => filters,
+        Err(err) => {
+            return Either::A(future::err(Error::from(
+                err.context(ErrorKind::ListVolumes),
+            )))
+        }
+    };
+    let client = match init_client(docker_url) {
+        Ok(client) => client,
+        Err(err) => return Either::A(future::err(err)),
+    };
+
+    let volumes = client
+        .container_api()
+        .container_list(true, 0, false, &filters)
+        .map_err(|err| Error::from_docker_error(err, ErrorKind::ListVolumes))
+        .and_then(move |containers| {
+            // Docker reports the host path that a volume is mounted from, which is the volume's
+            // mountpoint.
+            let sources: HashSet<String> = containers
+                .iter()
+                .flat_map(|container| container.mounts())
+                .filter(|mount| mount._type() == Some("volume"))
+                .filter_map(|mount| mount.source().map(ToOwned::to_owned))
+                .collect();
+
+            client
+                .volume_api()
+                .volume_list("")
+                .map_err(|err| Error::from_docker_error(err, ErrorKind::ListVolumes))
+                .map(move |response| {
+                    response
+                        .volumes()
+                        .iter()
+                        .filter(|volume| sources.contains(volume.mountpoint()))
+                        .map(ModuleVolume::from)
+                        .collect()
+                })
+        });
+
+    Either::B(volumes)
+}
+
+/// Creates the named volume, or returns it if it already exists.
+pub fn create_module_volume(
+    docker_url: &Url,
+    name: &str,
+) -> impl Future<Item = ModuleVolume, Error = Error> + Send {
+    let client = match init_client(docker_url) {
+        Ok(client) => client,
+        Err(err) => return Either::A(future::err(err)),
+    };
+
+    let name = name.to_string();
+    let volume = client
+        .volume_api()
+        .volume_create(VolumeConfig::new().with_name(name.clone()))
+        .map_err(move |err| Error::from_docker_error(err, ErrorKind::CreateVolume(name)))
+        .map(|volume| ModuleVolume::from(&volume));
+
+    Either::B(volume)
+}
@@ -26,8 +26,8 @@ use docker::models::{
 };
 
 use edgelet_core::{
-    GetTrustBundle, ImagePullPolicy, LogOptions, LogTail, MakeModuleRuntime, Module,
-    ModuleRegistry, ModuleRuntime, ModuleSpec, RegistryOperation, RuntimeOperation,
+    ExecOptions, ImagePullPolicy, LogOptions, LogTail, MakeModuleRuntime, Module, ModuleRegistry,
+    ModuleRuntime, ModuleSpec, RegistryOperation, RuntimeOperation,
 };
 use edgelet_docker::{DockerConfig, DockerModuleRuntime, Settings};
 use edgelet_docker::{Error, ErrorKind};
@@ -97,7 +97,7 @@ fn provisioning_result() -> ProvisioningResult {
     )
 }
 
-fn crypto() -> impl GetTrustBundle {
+fn crypto() -> TestHsm {
     TestHsm::default()
 }
 
@@ -871,6 +871,86 @@ fn container_create_succeeds() {
     runtime.block_on(task).unwrap();
 }
 
+#[allow(clippy::needless_pass_by_value)]
+fn container_create_dps_payload_handler(req: Request<Body>) -> ResponseFuture {
+    let response = json!({
+        "Id": "12345",
+        "Warnings": []
+    })
+    .to_string();
+    let response_len = response.len();
+
+    Box::new(
+        req.into_body()
+            .concat2()
+            .and_then(|body| {
+                let create_options: ContainerCreateBody =
+                    serde_json::from_slice(body.as_ref()).unwrap();
+
+                let env = create_options.env().unwrap();
+                assert!(env.contains(&"k1=v1".to_string()));
+                assert!(env.contains(&r#"IOTEDGE_DPS_PAYLOAD={"site":"s1"}"#.to_string()));
+
+                Ok(())
+            })
+            .map(move |_| {
+                let mut response = Response::new(response.into());
+                response
+                    .headers_mut()
+                    .typed_insert(&ContentLength(response_len as u64));
+                response
+                    .headers_mut()
+                    .typed_insert(&ContentType(mime::APPLICATION_JSON));
+                response
+            }),
+    )
+}
+
+#[test]
+fn container_create_adds_dps_payload_to_env() {
+    let dispatch_table = routes!(
+        GET "/networks" => default_get_networks_handler(),
+        POST "/networks/create" => default_create_network_handler(),
+        POST "/containers/create" => container_create_dps_payload_handler,
+    );
+
+    let (server, port) = run_tcp_server(
+        "127.0.0.1",
+        make_req_dispatcher(dispatch_table, Box::new(not_found_handler)),
+    );
+    let server = server.map_err(|err| panic!(err));
+
+    let settings = make_settings(Some(json!({
+        "moby_runtime": {
+            "uri": &format!("http://localhost:{}", port)
+        }
+    })));
+
+    let provisioning_result = provisioning_result().with_payload(Some(json!({ "site": "s1" })));
+    let task = DockerModuleRuntime::make_runtime(settings, provisioning_result, crypto()).and_then(
+        |runtime| {
+            let mut env = HashMap::new();
+            env.insert("k1".to_string(), "v1".to_string());
+
+            let module_config = ModuleSpec::new(
+                "m1".to_string(),
+                "docker".to_string(),
+                DockerConfig::new("nginx:latest".to_string(), ContainerCreateBody::new(), None)
+                    .unwrap(),
+                env,
+                ImagePullPolicy::default(),
+            )
+            .unwrap();
+
+            runtime.create(module_config)
+        },
+    );
+
+    let mut runtime = tokio::runtime::current_thread::Runtime::new().unwrap();
+    runtime.spawn(server);
+    runtime.block_on(task).unwrap();
+}
+
 #[allow(clippy::needless_pass_by_value)]
 fn container_start_handler(req: Request<Body>) -> ResponseFuture {
     assert_eq!(req.method(), &Method::POST);
@@ -1235,6 +1315,176 @@ fn container_logs_succeeds() {
     runtime.block_on(assert).unwrap();
 }
 
+fn container_inspect_handler(
+    labels: JsonValue,
+) -> impl Fn(Request<Body>) -> ResponseFuture + Clone {
+    move |req| {
+        assert_eq!(req.method(), &Method::GET);
+        assert_eq!(req.uri().path(), "/containers/m1/json");
+
+        let response = json!({
+            "Id": "container1",
+            "Name": "/m1",
+            "Config": {
+                "Labels": labels.clone(),
+            },
+        })
+        .to_string();
+        let response_len = response.len();
+
+        let mut response = Response::new(response.into());
+        response
+            .headers_mut()
+            .typed_insert(&ContentLength(response_len as u64));
+        response
+            .headers_mut()
+            .typed_insert(&ContentType(mime::APPLICATION_JSON));
+        Box::new(future::ok(response))
+    }
+}
+
+fn exec_inspect_handler(
+    container_id: &'static str,
+) -> impl Fn(Request<Body>) -> ResponseFuture + Clone {
+    move |req| {
+        assert_eq!(req.method(), &Method::GET);
+        assert_eq!(req.uri().path(), "/exec/exec1/json");
+
+        let response = json!({
+            "ID": "exec1",
+            "ContainerID": container_id,
+            "Running": false,
+            "ExitCode": 3,
+        })
+        .to_string();
+        let response_len = response.len();
+
+        let mut response = Response::new(response.into());
+        response
+            .headers_mut()
+            .typed_insert(&ContentLength(response_len as u64));
+        response
+            .headers_mut()
+            .typed_insert(&ContentType(mime::APPLICATION_JSON));
+        Box::new(future::ok(response))
+    }
+}
+
+#[test]
+fn exec_fails_for_container_that_is_not_a_module() {
+    let dispatch_table = routes!(
+        GET "/networks" => default_get_networks_handler(),
+        POST "/networks/create" => default_create_network_handler(),
+        GET "/containers/m1/json" => container_inspect_handler(json!({})),
+    );
+
+    let (server, port) = run_tcp_server(
+        "127.0.0.1",
+        make_req_dispatcher(dispatch_table, Box::new(not_found_handler)),
+    );
+    let server = server.map_err(|err| panic!(err));
+
+    let settings = make_settings(Some(json!({
+        "moby_runtime": {
+            "uri": &format!("http://localhost:{}", port)
+        }
+    })));
+
+    let task = DockerModuleRuntime::make_runtime(settings, provisioning_result(), crypto())
+        .and_then(|runtime| runtime.exec("m1", &ExecOptions::new(vec!["sh".to_string()])))
+        .then(|result| match result {
+            Ok(_) => panic!("Expected test to fail but it didn't!"),
+            Err(err) => match err.kind() {
+                ErrorKind::RuntimeOperation(RuntimeOperation::ExecModule(name)) if name == "m1" => {
+                    match Fail::find_root_cause(&err).downcast_ref::<ErrorKind>() {
+                        Some(ErrorKind::NotFound(message)) => {
+                            assert_eq!("No such module: m1", message);
+                            Ok::<_, Error>(())
+                        }
+                        _ => panic!("Specific error kind not found: {}", err),
+                    }
+                }
+                _ => panic!("Specific error kind not found: {}", err),
+            },
+        });
+
+    let mut runtime = tokio::runtime::current_thread::Runtime::new().unwrap();
+    runtime.spawn(server);
+    runtime.block_on(task).unwrap();
+}
+
+#[test]
+fn exec_exit_code_succeeds() {
+    let dispatch_table = routes!(
+        GET "/networks" => default_get_networks_handler(),
+        POST "/networks/create" => default_create_network_handler(),
+        GET "/containers/m1/json" => container_inspect_handler(json!({
+            "net.azure-devices.edge.owner": "Microsoft.Azure.Devices.Edge.Agent",
+        })),
+        GET "/exec/exec1/json" => exec_inspect_handler("container1"),
+    );
+
+    let (server, port) = run_tcp_server(
+        "127.0.0.1",
+        make_req_dispatcher(dispatch_table, Box::new(not_found_handler)),
+    );
+    let server = server.map_err(|err| panic!(err));
+
+    let settings = make_settings(Some(json!({
+        "moby_runtime": {
+            "uri": &format!("http://localhost:{}", port)
+        }
+    })));
+
+    let task = DockerModuleRuntime::make_runtime(settings, provisioning_result(), crypto())
+        .and_then(|runtime| runtime.exec_exit_code("m1", "exec1"));
+
+    let mut runtime = tokio::runtime::current_thread::Runtime::new().unwrap();
+    runtime.spawn(server);
+    assert_eq!(Some(3), runtime.block_on(task).unwrap());
+}
+
+#[test]
+fn exec_exit_code_fails_for_exec_in_other_container() {
+    let dispatch_table = routes!(
+        GET "/networks" => default_get_networks_handler(),
+        POST "/networks/create" => default_create_network_handler(),
+        GET "/containers/m1/json" => container_inspect_handler(json!({
+            "net.azure-devices.edge.owner": "Microsoft.Azure.Devices.Edge.Agent",
+        })),
+        GET "/exec/exec1/json" => exec_inspect_handler("container2"),
+    );
+
+    let (server, port) = run_tcp_server(
+        "127.0.0.1",
+        make_req_dispatcher(dispatch_table, Box::new(not_found_handler)),
+    );
+    let server = server.map_err(|err| panic!(err));
+
+    let settings = make_settings(Some(json!({
+        "moby_runtime": {
+            "uri": &format!("http://localhost:{}", port)
+        }
+    })));
+
+    let task = DockerModuleRuntime::make_runtime(settings, provisioning_result(), crypto())
+        .and_then(|runtime| runtime.exec_resize("m1", "exec1", 24, 80))
+        .then(|result| match result {
+            Ok(_) => panic!("Expected test to fail but it didn't!"),
+            Err(err) => match Fail::find_root_cause(&err).downcast_ref::<ErrorKind>() {
+                Some(ErrorKind::NotFound(message)) => {
+                    assert_eq!("No such exec instance: exec1", message);
+                    Ok::<_, Error>(())
+                }
+                _ => panic!("Specific error kind not found: {}", err),
+            },
+        });
+
+    let mut runtime = tokio::runtime::current_thread::Runtime::new().unwrap();
+    runtime.spawn(server);
+    runtime.block_on(task).unwrap();
+}
+
 #[test]
 fn image_remove_with_white_space_name_fails() {
     let (server, port) = run_tcp_server("127.0.0.1", default_network_handler());
@@ -1813,6 +2063,107 @@ fn runtime_init_network_exist_do_not_create() {
     assert_eq!(false, *create_got_called_lock_cloned.read().unwrap());
 }
 
+#[test]
+fn network_reconcile_creates_configured_and_removes_stale() {
+    let create_got_called_lock = Arc::new(RwLock::new(false));
+    let create_got_called_lock_cloned = create_got_called_lock.clone();
+
+    let delete_got_called_lock = Arc::new(RwLock::new(false));
+    let delete_got_called_lock_cloned = delete_got_called_lock.clone();
+
+    let on_get = || {
+        json!([
+            {
+                "Name": "azure-iot-edge",
+                "Driver": "bridge",
+                "Labels": {}
+            },
+            {
+                "Name": "stale",
+                "Driver": "bridge",
+                "Labels": {
+                    "net.azure-devices.edge.owner": "Microsoft.Azure.Devices.Edge.Agent"
+                }
+            }
+        ])
+        .to_string()
+    };
+
+    let on_post = move |req: Request<Body>| {
+        let mut create_got_called_w = create_got_called_lock.write().unwrap();
+        *create_got_called_w = true;
+
+        let task = req
+            .into_body()
+            .concat2()
+            .map(|body| {
+                let network: NetworkConfig = serde_json::from_slice(&body).unwrap();
+                assert_eq!("fieldbus", network.name().as_str());
+                assert_eq!(Some("macvlan"), network.driver());
+                assert_eq!("eth1", network.options().unwrap()["parent"]);
+                assert_eq!(
+                    "Microsoft.Azure.Devices.Edge.Agent",
+                    network.labels().unwrap()["net.azure-devices.edge.owner"]
+                );
+            })
+            .map_err(|err| panic!("{:?}", err));
+
+        tokio::spawn(task).into_future().wait().unwrap();
+    };
+
+    let on_delete = move |req: Request<Body>| -> ResponseFuture {
+        assert_eq!(req.uri().path(), "/networks/stale");
+
+        let mut delete_got_called_w = delete_got_called_lock.write().unwrap();
+        *delete_got_called_w = true;
+
+        Box::new(future::ok(
+            Response::builder()
+                .status(StatusCode::NO_CONTENT)
+                .body(Body::empty())
+                .unwrap(),
+        ))
+    };
+
+    let dispatch_table = routes!(
+        GET "/networks" => make_get_networks_handler(on_get),
+        POST "/networks/create" => make_create_network_handler(on_post),
+        DELETE "/networks/stale" => on_delete,
+    );
+
+    let (server, port) = run_tcp_server(
+        "127.0.0.1",
+        make_req_dispatcher(dispatch_table, Box::new(not_found_handler)),
+    );
+    let server = server.map_err(|err| panic!(err));
+
+    let settings = make_settings(Some(json!({
+        "moby_runtime": {
+            "uri": &format!("http://localhost:{}", port),
+            "networks": [
+                {
+                    "name": "fieldbus",
+                    "driver": "macvlan",
+                    "options": {
+                        "parent": "eth1"
+                    }
+                }
+            ]
+        }
+    })));
+
+    //act
+    let task = DockerModuleRuntime::make_runtime(settings, provisioning_result(), crypto());
+
+    let mut runtime = tokio::runtime::current_thread::Runtime::new().unwrap();
+    runtime.spawn(server);
+    runtime.block_on(task).unwrap();
+
+    //assert
+    assert_eq!(true, *create_got_called_lock_cloned.read().unwrap());
+    assert_eq!(true, *delete_got_called_lock_cloned.read().unwrap());
+}
+
 #[test]
 fn runtime_system_info_succeeds() {
     let system_info_got_called_lock = Arc::new(RwLock::new(false));
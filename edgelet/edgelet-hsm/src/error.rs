@@ -23,6 +23,12 @@ pub enum ErrorKind {
     EmptyStrings,
     #[fail(display = "Only Device keys are allowed to be activated")]
     NoModuleActivation,
+    #[fail(display = "Invalid PKCS#11 URI: {}", _0)]
+    InvalidPkcs11Uri(&'static str),
+    #[fail(display = "Could not initialize the OpenSSL pkcs11 engine")]
+    Pkcs11Engine,
+    #[fail(display = "Could not load the private key from the PKCS#11 token")]
+    Pkcs11Key,
 }
 
 impl Fail for Error {
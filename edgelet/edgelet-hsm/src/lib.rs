@@ -13,11 +13,13 @@ use std::sync::{Arc, Mutex};
 mod certificate_properties;
 mod crypto;
 mod error;
+pub mod pkcs11;
 pub mod tpm;
 pub mod x509;
 
 pub use crypto::{Certificate, Crypto};
 pub use error::{Error, ErrorKind};
+pub use pkcs11::Pkcs11Uri;
 pub use tpm::{TpmKey, TpmKeyStore};
 pub use x509::X509;
 
@@ -0,0 +1,336 @@
+// Copyright (c) Microsoft. All rights reserved.
+
+//! Private keys kept on a PKCS#11 token (a smartcard, a secure element, SoftHSM, ...).
+//!
+//! Keys are identified by [RFC 7512](https://tools.ietf.org/html/rfc7512) `pkcs11:` URIs, e.g.
+//!
+//! ```text
+//! pkcs11:token=edge;object=device-id?pin-value=1234&module-path=/usr/lib/softhsm/libsofthsm2.so
+//! ```
+//!
+//! The HSM library and [`load_private_key`] both go through the OpenSSL pkcs11 engine (libp11),
+//! so the key never leaves the token.
+
+use std::collections::BTreeMap;
+use std::ffi::CString;
+use std::fmt;
+use std::os::raw::{c_char, c_int, c_void};
+use std::path::{Path, PathBuf};
+use std::ptr;
+use std::str::FromStr;
+
+use foreign_types::ForeignType;
+use openssl::pkey::{PKey, Private};
+use percent_encoding::percent_decode;
+
+use edgelet_core::PKCS11_URI_SCHEME;
+
+use crate::error::{Error, ErrorKind};
+
+/// The parts of a `pkcs11:` URI that iotedged needs to know about.
+/// Everything else is passed through to the engine untouched.
+///
+/// The URI can hold the token PIN as `pin-value`, so it is masked when the URI is displayed.
+/// Use [`Pkcs11Uri::as_str`] for the URI as it was given.
+#[derive(Clone, PartialEq)]
+pub struct Pkcs11Uri {
+    uri: String,
+    token: Option<String>,
+    object: Option<String>,
+    id: Option<Vec<u8>>,
+    module_path: Option<PathBuf>,
+}
+
+impl Pkcs11Uri {
+    pub fn is_pkcs11_uri(uri: &str) -> bool {
+        uri.starts_with(&format!("{}:", PKCS11_URI_SCHEME))
+    }
+
+    pub fn token(&self) -> Option<&str> {
+        self.token.as_ref().map(AsRef::as_ref)
+    }
+
+    pub fn object(&self) -> Option<&str> {
+        self.object.as_ref().map(AsRef::as_ref)
+    }
+
+    pub fn id(&self) -> Option<&[u8]> {
+        self.id.as_ref().map(AsRef::as_ref)
+    }
+
+    /// The PKCS#11 module to load. If it isn't set the engine's default module is used.
+    pub fn module_path(&self) -> Option<&Path> {
+        self.module_path.as_ref().map(AsRef::as_ref)
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.uri
+    }
+}
+
+impl FromStr for Pkcs11Uri {
+    type Err = Error;
+
+    fn from_str(uri: &str) -> Result<Self, Self::Err> {
+        if !Pkcs11Uri::is_pkcs11_uri(uri) {
+            return Err(Error::from(ErrorKind::InvalidPkcs11Uri(
+                "scheme must be pkcs11",
+            )));
+        }
+
+        let rest = &uri[PKCS11_URI_SCHEME.len() + 1..];
+        let (path, query) = match rest.find('?') {
+            Some(index) => (&rest[..index], &rest[index + 1..]),
+            None => (rest, ""),
+        };
+
+        let path = parse_attributes(path, ';')?;
+        let query = parse_attributes(query, '&')?;
+
+        if let Some(object_type) = path.get("type") {
+            if &object_type[..] != b"private" {
+                return Err(Error::from(ErrorKind::InvalidPkcs11Uri(
+                    "type must be private",
+                )));
+            }
+        }
+
+        let token = path.get("token").map(|v| to_string(v)).transpose()?;
+        let object = path.get("object").map(|v| to_string(v)).transpose()?;
+        let id = path.get("id").cloned();
+        if object.is_none() && id.is_none() {
+            return Err(Error::from(ErrorKind::InvalidPkcs11Uri(
+                "one of object or id is required",
+            )));
+        }
+
+        let module_path = query
+            .get("module-path")
+            .map(|v| to_string(v).map(PathBuf::from))
+            .transpose()?;
+
+        Ok(Pkcs11Uri {
+            uri: uri.to_string(),
+            token,
+            object,
+            id,
+            module_path,
+        })
+    }
+}
+
+impl fmt::Display for Pkcs11Uri {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (path, query) = match self.uri.find('?') {
+            Some(index) => (&self.uri[..index], Some(&self.uri[index + 1..])),
+            None => (&self.uri[..], None),
+        };
+
+        write!(f, "{}", path)?;
+        if let Some(query) = query {
+            for (i, attribute) in query.split('&').enumerate() {
+                let separator = if i == 0 { '?' } else { '&' };
+                if attribute.starts_with("pin-value=") {
+                    write!(f, "{}pin-value=***", separator)?;
+                } else {
+                    write!(f, "{}{}", separator, attribute)?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Debug for Pkcs11Uri {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Pkcs11Uri")
+            .field("uri", &self.to_string())
+            .field("token", &self.token)
+            .field("object", &self.object)
+            .field("id", &self.id)
+            .field("module_path", &self.module_path)
+            .finish()
+    }
+}
+
+fn parse_attributes(s: &str, separator: char) -> Result<BTreeMap<&str, Vec<u8>>, Error> {
+    let mut attributes = BTreeMap::new();
+    for attribute in s.split(separator).filter(|a| !a.is_empty()) {
+        let mut parts = attribute.splitn(2, '=');
+        let name = parts.next().unwrap_or_default();
+        let value = parts.next().ok_or_else(|| {
+            Error::from(ErrorKind::InvalidPkcs11Uri("attributes must be name=value"))
+        })?;
+        if attributes
+            .insert(name, percent_decode(value.as_bytes()).collect())
+            .is_some()
+        {
+            return Err(Error::from(ErrorKind::InvalidPkcs11Uri(
+                "attributes must not be repeated",
+            )));
+        }
+    }
+    Ok(attributes)
+}
+
+fn to_string(value: &[u8]) -> Result<String, Error> {
+    String::from_utf8(value.to_vec())
+        .map_err(|_| Error::from(ErrorKind::InvalidPkcs11Uri("attributes must be UTF-8")))
+}
+
+#[allow(non_camel_case_types)]
+enum ENGINE {}
+
+extern "C" {
+    fn ENGINE_by_id(id: *const c_char) -> *mut ENGINE;
+    fn ENGINE_ctrl_cmd_string(
+        e: *mut ENGINE,
+        cmd_name: *const c_char,
+        arg: *const c_char,
+        cmd_optional: c_int,
+    ) -> c_int;
+    fn ENGINE_init(e: *mut ENGINE) -> c_int;
+    fn ENGINE_finish(e: *mut ENGINE) -> c_int;
+    fn ENGINE_free(e: *mut ENGINE) -> c_int;
+    fn ENGINE_load_private_key(
+        e: *mut ENGINE,
+        key_id: *const c_char,
+        ui_method: *mut c_void,
+        callback_data: *mut c_void,
+    ) -> *mut openssl_sys::EVP_PKEY;
+}
+
+/// Loads a handle to a private key on a PKCS#11 token.
+///
+/// Every private key operation done with the returned key, such as signing during a TLS handshake,
+/// is forwarded to the token.
+pub fn load_private_key(uri: &Pkcs11Uri) -> Result<PKey<Private>, Error> {
+    openssl_sys::init();
+
+    let engine_id = CString::new("pkcs11").expect("hard-coded string has no NUL");
+    let key_id = CString::new(uri.as_str())
+        .map_err(|_| Error::from(ErrorKind::InvalidPkcs11Uri("URI must not contain NUL")))?;
+    let module_path = uri
+        .module_path()
+        .map(|path| {
+            path.to_str()
+                .and_then(|path| CString::new(path).ok())
+                .ok_or_else(|| Error::from(ErrorKind::InvalidPkcs11Uri("invalid module-path")))
+        })
+        .transpose()?;
+
+    unsafe {
+        let engine = ENGINE_by_id(engine_id.as_ptr());
+        if engine.is_null() {
+            return Err(Error::from(ErrorKind::Pkcs11Engine));
+        }
+
+        let result = load_private_key_with_engine(engine, module_path.as_ref(), &key_id);
+        ENGINE_free(engine);
+        result
+    }
+}
+
+unsafe fn load_private_key_with_engine(
+    engine: *mut ENGINE,
+    module_path: Option<&CString>,
+    key_id: &CString,
+) -> Result<PKey<Private>, Error> {
+    if let Some(module_path) = module_path {
+        let cmd = CString::new("MODULE_PATH").expect("hard-coded string has no NUL");
+        if ENGINE_ctrl_cmd_string(engine, cmd.as_ptr(), module_path.as_ptr(), 0) == 0 {
+            return Err(Error::from(ErrorKind::Pkcs11Engine));
+        }
+    }
+
+    if ENGINE_init(engine) == 0 {
+        return Err(Error::from(ErrorKind::Pkcs11Engine));
+    }
+
+    let key = ENGINE_load_private_key(engine, key_id.as_ptr(), ptr::null_mut(), ptr::null_mut());
+    // the key holds its own reference to the engine
+    ENGINE_finish(engine);
+
+    if key.is_null() {
+        Err(Error::from(ErrorKind::Pkcs11Key))
+    } else {
+        Ok(PKey::from_ptr(key))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_object_and_module_path() {
+        let uri: Pkcs11Uri = "pkcs11:token=edge;object=device%20id;type=private?pin-value=1234&module-path=/usr/lib/softhsm/libsofthsm2.so"
+            .parse()
+            .unwrap();
+        assert_eq!(Some("edge"), uri.token());
+        assert_eq!(Some("device id"), uri.object());
+        assert_eq!(None, uri.id());
+        assert_eq!(
+            Some(Path::new("/usr/lib/softhsm/libsofthsm2.so")),
+            uri.module_path()
+        );
+    }
+
+    #[test]
+    fn parses_binary_id() {
+        let uri: Pkcs11Uri = "pkcs11:id=%01%02%ff".parse().unwrap();
+        assert_eq!(Some(&[1_u8, 2, 255][..]), uri.id());
+        assert_eq!(None, uri.module_path());
+    }
+
+    #[test]
+    fn masks_pin_value() {
+        let uri: Pkcs11Uri = "pkcs11:token=edge;object=device?pin-value=1234&module-path=/usr/lib/softhsm/libsofthsm2.so"
+            .parse()
+            .unwrap();
+        assert_eq!(
+            "pkcs11:token=edge;object=device?pin-value=***&module-path=/usr/lib/softhsm/libsofthsm2.so",
+            uri.to_string()
+        );
+        assert!(!format!("{:?}", uri).contains("1234"));
+        assert!(uri.as_str().contains("pin-value=1234"));
+
+        let uri: Pkcs11Uri = "pkcs11:object=device".parse().unwrap();
+        assert_eq!("pkcs11:object=device", uri.to_string());
+    }
+
+    #[test]
+    fn rejects_invalid_uris() {
+        for uri in &[
+            "file:///var/secrets/device-ca.key.pem",
+            "pkcs11:token=edge",
+            "pkcs11:object=device;type=cert",
+            "pkcs11:object=device;object=other",
+            "pkcs11:object",
+        ] {
+            let err = uri.parse::<Pkcs11Uri>().unwrap_err();
+            match err.kind() {
+                ErrorKind::InvalidPkcs11Uri(_) => (),
+                kind => panic!("unexpected error {:?} for {}", kind, uri),
+            }
+        }
+    }
+
+    // Needs libp11 and a PKCS#11 module such as SoftHSM. Set IOTEDGE_TEST_PKCS11_KEY_URI
+    // to the URI of a private key on an initialized token, then run with --ignored.
+    #[test]
+    #[ignore]
+    fn loads_key_from_softhsm() {
+        use openssl::hash::MessageDigest;
+        use openssl::sign::Signer;
+
+        let uri: Pkcs11Uri = std::env::var("IOTEDGE_TEST_PKCS11_KEY_URI")
+            .unwrap()
+            .parse()
+            .unwrap();
+        let key = load_private_key(&uri).unwrap();
+        let mut signer = Signer::new(MessageDigest::sha256(), &key).unwrap();
+        signer.update(b"data").unwrap();
+        assert!(!signer.sign_to_vec().unwrap().is_empty());
+    }
+}
@@ -1,27 +1,34 @@
 // Copyright (c) Microsoft. All rights reserved.
 
+use std::convert::TryInto;
 use std::fmt;
 use std::str::FromStr;
 use std::sync::Arc;
 use std::time::Duration;
 
+use chrono::{DateTime, Utc};
 use failure::{Fail, ResultExt};
 use futures::future::{self, FutureResult};
 use futures::prelude::*;
 use futures::stream;
+use hyper::upgrade::Upgraded;
 use hyper::{Body, Chunk as HyperChunk, Client};
 use management::apis::client::APIClient;
 use management::apis::configuration::Configuration;
-use management::models::{Config, ModuleDetails as HttpModuleDetails};
+use management::models::{
+    Config, ModuleDetails as HttpModuleDetails, NetworkDetails, RegistryCredentials,
+    RotateDeviceKey,
+};
 use serde_json;
 use url::Url;
 
 use edgelet_core::*;
 use edgelet_core::{
-    ModuleOperation, RuntimeOperation, SystemInfo as CoreSystemInfo, SystemResources, UrlExt,
+    DiskInfo, ModuleOperation, ModuleResourceUsage, RuntimeOperation, SystemInfo as CoreSystemInfo,
+    SystemResources, UrlExt,
 };
 use edgelet_docker::{self, DockerConfig};
-use edgelet_http::{UrlConnector, API_VERSION};
+use edgelet_http::{TlsConfig, UrlConnector, API_VERSION};
 
 use crate::error::{Error, ErrorKind};
 
@@ -31,8 +38,15 @@ pub struct ModuleClient {
 
 impl ModuleClient {
     pub fn new(url: &Url) -> Result<Self, Error> {
-        let client = Client::builder()
-            .build(UrlConnector::new(url).context(ErrorKind::InitializeModuleClient)?);
+        ModuleClient::with_tls_config(url, &TlsConfig::default())
+    }
+
+    /// Like `new`, but uses `tls_config` to connect to a management API listening on `https`.
+    pub fn with_tls_config(url: &Url, tls_config: &TlsConfig) -> Result<Self, Error> {
+        let client = Client::builder().build(
+            UrlConnector::with_tls_config(url, tls_config)
+                .context(ErrorKind::InitializeModuleClient)?,
+        );
 
         let base_path = url
             .to_base_path()
@@ -53,6 +67,17 @@ impl ModuleClient {
         };
         Ok(module_client)
     }
+
+    /// Asks the daemon to replace the device key with `primary_key`, which is base64-encoded.
+    pub fn rotate_device_key(
+        &self,
+        primary_key: String,
+    ) -> impl Future<Item = (), Error = Error> + Send {
+        self.client
+            .device_actions_api()
+            .rotate_device_key(&API_VERSION.to_string(), RotateDeviceKey::new(primary_key))
+            .map_err(|err| Error::from_mgmt_error(err, ErrorKind::RotateDeviceKey))
+    }
 }
 
 impl Clone for ModuleClient {
@@ -119,13 +144,18 @@ fn runtime_status(details: &HttpModuleDetails) -> Result<ModuleRuntimeState, Err
         .exit_status()
         .and_then(|e| e.exit_time().parse().ok());
     let start_time = details.status().start_time().and_then(|s| s.parse().ok());
+    let restart_count = details
+        .status()
+        .restart_count()
+        .and_then(|count| count.try_into().ok());
 
     let state = ModuleRuntimeState::default()
         .with_status(status)
         .with_status_description(description)
         .with_exit_code(exit_code)
         .with_started_at(start_time)
-        .with_finished_at(exit_time);
+        .with_finished_at(exit_time)
+        .with_restart_count(restart_count);
     Ok(state)
 }
 
@@ -133,6 +163,8 @@ impl ModuleRegistry for ModuleClient {
     type Error = Error;
     type PullFuture = FutureResult<(), Self::Error>;
     type RemoveFuture = FutureResult<(), Self::Error>;
+    type LoginFuture = Box<dyn Future<Item = (), Error = Self::Error> + Send>;
+    type LogoutFuture = Box<dyn Future<Item = (), Error = Self::Error> + Send>;
     type Config = ModuleConfig;
 
     fn pull(&self, _config: &Self::Config) -> Self::PullFuture {
@@ -142,6 +174,42 @@ impl ModuleRegistry for ModuleClient {
     fn remove(&self, _name: &str) -> Self::RemoveFuture {
         future::ok(())
     }
+
+    fn login(&self, registry: &str, username: &str, password: &str) -> Self::LoginFuture {
+        let registry = registry.to_string();
+
+        let login = self
+            .client
+            .registry_api()
+            .login_registry(
+                &API_VERSION.to_string(),
+                &registry,
+                RegistryCredentials::new(username.to_string(), password.to_string()),
+            )
+            .map_err(|err| {
+                Error::from_mgmt_error(
+                    err,
+                    ErrorKind::RegistryOperation(RegistryOperation::Login(registry)),
+                )
+            });
+        Box::new(login)
+    }
+
+    fn logout(&self, registry: &str) -> Self::LogoutFuture {
+        let registry = registry.to_string();
+
+        let logout = self
+            .client
+            .registry_api()
+            .logout_registry(&API_VERSION.to_string(), &registry)
+            .map_err(|err| {
+                Error::from_mgmt_error(
+                    err,
+                    ErrorKind::RegistryOperation(RegistryOperation::Logout(registry)),
+                )
+            });
+        Box::new(logout)
+    }
 }
 
 impl ModuleRuntime for ModuleClient {
@@ -151,6 +219,8 @@ impl ModuleRuntime for ModuleClient {
     type ModuleRegistry = Self;
     type Chunk = Chunk;
     type Logs = Logs;
+    type Events = Box<dyn Stream<Item = ModuleEvent, Error = Self::Error> + Send>;
+    type ExecStream = Upgraded;
 
     type CreateFuture = Box<dyn Future<Item = (), Error = Self::Error> + Send>;
     type GetFuture =
@@ -159,6 +229,11 @@ impl ModuleRuntime for ModuleClient {
     type ListWithDetailsStream =
         Box<dyn Stream<Item = (Self::Module, ModuleRuntimeState), Error = Self::Error> + Send>;
     type LogsFuture = Box<dyn Future<Item = Self::Logs, Error = Self::Error> + Send>;
+    type EventsFuture = Box<dyn Future<Item = Self::Events, Error = Self::Error> + Send>;
+    type ExecFuture =
+        Box<dyn Future<Item = (String, Self::ExecStream), Error = Self::Error> + Send>;
+    type ExecResizeFuture = Box<dyn Future<Item = (), Error = Self::Error> + Send>;
+    type ExecExitCodeFuture = Box<dyn Future<Item = Option<i64>, Error = Self::Error> + Send>;
     type RemoveFuture = Box<dyn Future<Item = (), Error = Self::Error> + Send>;
     type RestartFuture = Box<dyn Future<Item = (), Error = Self::Error> + Send>;
     type StartFuture = Box<dyn Future<Item = (), Error = Self::Error> + Send>;
@@ -167,6 +242,9 @@ impl ModuleRuntime for ModuleClient {
     type SystemResourcesFuture =
         Box<dyn Future<Item = SystemResources, Error = Self::Error> + Send>;
     type RemoveAllFuture = Box<dyn Future<Item = (), Error = Self::Error> + Send>;
+    type ListNetworksFuture = Box<dyn Future<Item = Vec<Network>, Error = Self::Error> + Send>;
+    type AttachNetworkFuture = Box<dyn Future<Item = (), Error = Self::Error> + Send>;
+    type DetachNetworkFuture = Box<dyn Future<Item = (), Error = Self::Error> + Send>;
 
     fn create(&self, _module: ModuleSpec<Self::Config>) -> Self::CreateFuture {
         unimplemented!()
@@ -254,7 +332,74 @@ impl ModuleRuntime for ModuleClient {
     }
 
     fn system_resources(&self) -> Self::SystemResourcesFuture {
-        unimplemented!()
+        let system_resources = self
+            .client
+            .system_information_api()
+            .get_system_resources(&API_VERSION.to_string())
+            .map(|system_resources| {
+                let disks = system_resources
+                    .disks()
+                    .iter()
+                    .map(|disk| {
+                        DiskInfo::new(
+                            disk.name().clone(),
+                            disk.available_space().try_into().unwrap_or_default(),
+                            disk.total_space().try_into().unwrap_or_default(),
+                            disk.file_system().clone(),
+                            disk.file_type().clone(),
+                        )
+                    })
+                    .collect();
+
+                let modules = system_resources
+                    .modules()
+                    .unwrap_or_default()
+                    .iter()
+                    .map(|module| {
+                        ModuleResourceUsage::new(module.name().clone())
+                            .with_cpu_percent(module.cpu_percent().map(Into::into))
+                            .with_memory_usage(
+                                module.memory_usage().and_then(|m| m.try_into().ok()),
+                            )
+                            .with_memory_limit(
+                                module.memory_limit().and_then(|m| m.try_into().ok()),
+                            )
+                            .with_network_io(
+                                module.network_rx_bytes().try_into().unwrap_or_default(),
+                                module.network_tx_bytes().try_into().unwrap_or_default(),
+                            )
+                            .with_block_io(
+                                module.block_read_bytes().try_into().unwrap_or_default(),
+                                module.block_write_bytes().try_into().unwrap_or_default(),
+                            )
+                            .with_pids(module.pids().and_then(|p| p.try_into().ok()))
+                    })
+                    .collect();
+
+                SystemResources::new(
+                    system_resources
+                        .host_uptime()
+                        .try_into()
+                        .unwrap_or_default(),
+                    system_resources
+                        .process_uptime()
+                        .try_into()
+                        .unwrap_or_default(),
+                    system_resources.used_cpu().into(),
+                    system_resources.used_ram().try_into().unwrap_or_default(),
+                    system_resources.total_ram().try_into().unwrap_or_default(),
+                    disks,
+                    modules,
+                )
+                .with_docker_stats(system_resources.docker_stats().map(ToOwned::to_owned))
+            })
+            .map_err(|err| {
+                Error::from_mgmt_error(
+                    err,
+                    ErrorKind::RuntimeOperation(RuntimeOperation::SystemResources),
+                )
+            });
+        Box::new(system_resources)
     }
 
     fn list(&self) -> Self::ListFuture {
@@ -332,6 +477,71 @@ impl ModuleRuntime for ModuleClient {
         Box::new(result)
     }
 
+    fn events(&self, _since: Option<DateTime<Utc>>) -> Self::EventsFuture {
+        unimplemented!()
+    }
+
+    fn exec(&self, id: &str, options: &ExecOptions) -> Self::ExecFuture {
+        let id = id.to_string();
+
+        let exec = self
+            .client
+            .module_api()
+            .exec_module(
+                &API_VERSION.to_string(),
+                &id,
+                options.command(),
+                options.tty(),
+                options.tty_size(),
+            )
+            .map_err(|err| {
+                Error::from_mgmt_error(
+                    err,
+                    ErrorKind::RuntimeOperation(RuntimeOperation::ExecModule(id)),
+                )
+            });
+        Box::new(exec)
+    }
+
+    fn exec_resize(
+        &self,
+        id: &str,
+        exec_id: &str,
+        height: u16,
+        width: u16,
+    ) -> Self::ExecResizeFuture {
+        let id = id.to_string();
+
+        let resize = self
+            .client
+            .module_api()
+            .exec_module_resize(&API_VERSION.to_string(), &id, exec_id, height, width)
+            .map_err(|err| {
+                Error::from_mgmt_error(
+                    err,
+                    ErrorKind::RuntimeOperation(RuntimeOperation::ExecModuleResize(id)),
+                )
+            });
+        Box::new(resize)
+    }
+
+    fn exec_exit_code(&self, id: &str, exec_id: &str) -> Self::ExecExitCodeFuture {
+        let id = id.to_string();
+
+        let exit_code = self
+            .client
+            .module_api()
+            .exec_module_status(&API_VERSION.to_string(), &id, exec_id)
+            .map(|status| status.exit_code())
+            .map_err(|err| {
+                Error::from_mgmt_error(
+                    err,
+                    ErrorKind::RuntimeOperation(RuntimeOperation::ExecModuleExitCode(id)),
+                )
+            });
+        Box::new(exit_code)
+    }
+
     fn registry(&self) -> &Self::ModuleRegistry {
         self
     }
@@ -345,6 +555,70 @@ impl ModuleRuntime for ModuleClient {
             future::join_all(n).map(|_| ())
         }))
     }
+
+    fn list_networks(&self) -> Self::ListNetworksFuture {
+        let networks = self
+            .client
+            .network_api()
+            .list_networks(&API_VERSION.to_string())
+            .map(|list| list.networks().iter().map(core_network).collect())
+            .map_err(|err| {
+                Error::from_mgmt_error(
+                    err,
+                    ErrorKind::RuntimeOperation(RuntimeOperation::ListNetworks),
+                )
+            });
+        Box::new(networks)
+    }
+
+    fn attach_network(&self, id: &str, network: &str) -> Self::AttachNetworkFuture {
+        let id = id.to_string();
+        let network = network.to_string();
+
+        let attach = self
+            .client
+            .network_api()
+            .attach_network(&API_VERSION.to_string(), &network, &id)
+            .map_err(|err| {
+                Error::from_mgmt_error(
+                    err,
+                    ErrorKind::RuntimeOperation(RuntimeOperation::AttachNetwork(id, network)),
+                )
+            });
+        Box::new(attach)
+    }
+
+    fn detach_network(&self, id: &str, network: &str) -> Self::DetachNetworkFuture {
+        let id = id.to_string();
+        let network = network.to_string();
+
+        let detach = self
+            .client
+            .network_api()
+            .detach_network(&API_VERSION.to_string(), &network, &id)
+            .map_err(|err| {
+                Error::from_mgmt_error(
+                    err,
+                    ErrorKind::RuntimeOperation(RuntimeOperation::DetachNetwork(id, network)),
+                )
+            });
+        Box::new(detach)
+    }
+}
+
+fn core_network(details: &NetworkDetails) -> Network {
+    let config = details
+        .subnets()
+        .iter()
+        .map(|subnet| IpamConfig::default().with_subnet(subnet.clone()))
+        .collect();
+
+    Network::new(details.name().clone())
+        .with_driver(details.driver().map(ToOwned::to_owned))
+        .with_internal(details.internal())
+        .with_ipv6(details.ipv6())
+        .with_ipam(Ipam::default().with_config(config))
+        .with_modules(details.modules().to_vec())
 }
 
 pub struct Logs(String, Body);
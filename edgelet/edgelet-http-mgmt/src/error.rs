@@ -2,7 +2,10 @@
 
 use std::fmt::{self, Display};
 
-use edgelet_core::{IdentityOperation, ModuleOperation, RuntimeOperation};
+use edgelet_core::{
+    ErrorKind as CoreErrorKind, IdentityOperation, ModuleOperation, RegistryOperation,
+    RuntimeOperation,
+};
 use edgelet_docker::ErrorKind as DockerErrorKind;
 use edgelet_iothub::Error as IoTHubError;
 use failure::{Backtrace, Context, Fail};
@@ -48,6 +51,9 @@ pub enum ErrorKind {
     #[fail(display = "The request is missing required parameter `{}`", _0)]
     MissingRequiredParameter(&'static str),
 
+    #[fail(display = "The request must ask to upgrade the connection")]
+    MissingUpgrade,
+
     #[fail(display = "{}", _0)]
     ModuleOperation(ModuleOperation),
 
@@ -57,9 +63,15 @@ pub enum ErrorKind {
     #[fail(display = "Could not prepare update for module {:?}", _0)]
     PrepareUpdateModule(String),
 
+    #[fail(display = "{}", _0)]
+    RegistryOperation(RegistryOperation),
+
     #[fail(display = "Could not reprovision device")]
     ReprovisionDevice,
 
+    #[fail(display = "Could not rotate the device key")]
+    RotateDeviceKey,
+
     #[fail(display = "{}", _0)]
     RuntimeOperation(RuntimeOperation),
 
@@ -124,21 +136,29 @@ impl IntoResponse for Error {
             message.push_str(&format!("\n\tcaused by: {}", cause));
         }
 
-        // Specialize status code based on the underlying docker runtime error, if any
+        // Specialize status code based on the underlying module runtime error, if any
         let status_code =
             if let Some(cause) = Fail::find_root_cause(&self).downcast_ref::<DockerErrorKind>() {
                 match cause {
                     DockerErrorKind::NotFound(_) => StatusCode::NOT_FOUND,
                     DockerErrorKind::Conflict => StatusCode::CONFLICT,
                     DockerErrorKind::NotModified => StatusCode::NOT_MODIFIED,
+                    DockerErrorKind::PolicyViolation(_) | DockerErrorKind::UnverifiedImage(..) => {
+                        StatusCode::FORBIDDEN
+                    }
                     _ => StatusCode::INTERNAL_SERVER_ERROR,
                 }
+            } else if let Some(CoreErrorKind::ModuleRuntimeNotSupported) =
+                Fail::find_root_cause(&self).downcast_ref::<CoreErrorKind>()
+            {
+                StatusCode::NOT_IMPLEMENTED
             } else {
                 match self.kind() {
                     ErrorKind::InvalidApiVersion(_)
                     | ErrorKind::MalformedRequestBody
                     | ErrorKind::MalformedRequestParameter(_)
-                    | ErrorKind::MissingRequiredParameter(_) => StatusCode::BAD_REQUEST,
+                    | ErrorKind::MissingRequiredParameter(_)
+                    | ErrorKind::MissingUpgrade => StatusCode::BAD_REQUEST,
                     _ => {
                         error!("Internal server error: {}", message);
                         StatusCode::INTERNAL_SERVER_ERROR
@@ -1,4 +1,6 @@
 // Copyright (c) Microsoft. All rights reserved.
 mod reprovision;
+mod rotate_key;
 
 pub use self::reprovision::ReprovisionDevice;
+pub use self::rotate_key::RotateDeviceKey;
@@ -0,0 +1,174 @@
+// Copyright (c) Microsoft. All rights reserved.
+
+use std::sync::Arc;
+
+use failure::{Fail, ResultExt};
+use futures::{Future, Stream};
+use hyper::{Body, Request, Response, StatusCode};
+use log::debug;
+
+use edgelet_core::DeviceKeyRotation;
+use edgelet_http::route::{Handler, Parameters};
+use edgelet_http::Error as HttpError;
+use management::models::RotateDeviceKey as RotateDeviceKeyRequest;
+
+use crate::error::{Error, ErrorKind};
+use crate::IntoResponse;
+
+pub struct RotateDeviceKey<R> {
+    rotation: Arc<R>,
+}
+
+impl<R> RotateDeviceKey<R> {
+    pub fn new(rotation: R) -> Self {
+        RotateDeviceKey {
+            rotation: Arc::new(rotation),
+        }
+    }
+}
+
+impl<R> Handler<Parameters> for RotateDeviceKey<R>
+where
+    R: 'static + DeviceKeyRotation + Send + Sync,
+{
+    fn handle(
+        &self,
+        req: Request<Body>,
+        _params: Parameters,
+    ) -> Box<dyn Future<Item = Response<Body>, Error = HttpError> + Send> {
+        debug!("Rotate Device Key");
+        let rotation = self.rotation.clone();
+
+        let response = read_request(req)
+            .and_then(move |primary_key| {
+                rotation
+                    .rotate_device_key(primary_key)
+                    .map_err(|err| Error::from(err.context(ErrorKind::RotateDeviceKey)))
+            })
+            .and_then(|()| -> Result<_, Error> {
+                let response = Response::builder()
+                    .status(StatusCode::OK)
+                    .body(Body::default())
+                    .context(ErrorKind::RotateDeviceKey)?;
+
+                Ok(response)
+            })
+            .or_else(|e| Ok(e.into_response()));
+
+        Box::new(response)
+    }
+}
+
+fn read_request(req: Request<Body>) -> impl Future<Item = Vec<u8>, Error = Error> {
+    req.into_body().concat2().then(|b| {
+        let b = b.context(ErrorKind::MalformedRequestBody)?;
+        let rotate_req = serde_json::from_slice::<RotateDeviceKeyRequest>(&b)
+            .context(ErrorKind::MalformedRequestBody)?;
+        let primary_key = base64::decode(rotate_req.primary_key())
+            .context(ErrorKind::MalformedRequestParameter("primaryKey"))?;
+        if primary_key.is_empty() {
+            return Err(Error::from(ErrorKind::MalformedRequestParameter(
+                "primaryKey",
+            )));
+        }
+        Ok(primary_key)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+
+    use futures::future::{self, FutureResult};
+    use management::models::ErrorResponse;
+
+    use super::*;
+
+    #[derive(Debug, Fail)]
+    #[fail(display = "IoT Hub rejected the key")]
+    struct TestError;
+
+    struct TestRotation {
+        result: Result<(), ()>,
+        keys: Mutex<Vec<Vec<u8>>>,
+    }
+
+    impl TestRotation {
+        fn new(result: Result<(), ()>) -> Self {
+            TestRotation {
+                result,
+                keys: Mutex::new(vec![]),
+            }
+        }
+    }
+
+    impl DeviceKeyRotation for TestRotation {
+        type Error = TestError;
+        type RotateFuture = FutureResult<(), Self::Error>;
+
+        fn rotate_device_key(&self, primary_key: Vec<u8>) -> Self::RotateFuture {
+            self.keys.lock().unwrap().push(primary_key);
+            future::result(self.result.map_err(|()| TestError))
+        }
+    }
+
+    fn request(body: &str) -> Request<Body> {
+        Request::post("http://localhost/device/key/rotate")
+            .body(body.to_string().into())
+            .unwrap()
+    }
+
+    fn error_message(response: Response<Body>) -> String {
+        let body = response.into_body().concat2().wait().unwrap();
+        let error: ErrorResponse = serde_json::from_slice(&body).unwrap();
+        error.message().to_string()
+    }
+
+    #[test]
+    fn rotate_device_key_success() {
+        let handler = RotateDeviceKey::new(TestRotation::new(Ok(())));
+        let response = handler
+            .handle(request(r#"{"primaryKey":"a2V5"}"#), Parameters::new())
+            .wait()
+            .unwrap();
+
+        assert_eq!(StatusCode::OK, response.status());
+        assert_eq!(
+            vec![b"key".to_vec()],
+            *handler.rotation.keys.lock().unwrap()
+        );
+    }
+
+    #[test]
+    fn rotate_device_key_failed() {
+        let handler = RotateDeviceKey::new(TestRotation::new(Err(())));
+        let response = handler
+            .handle(request(r#"{"primaryKey":"a2V5"}"#), Parameters::new())
+            .wait()
+            .unwrap();
+
+        assert_eq!(StatusCode::INTERNAL_SERVER_ERROR, response.status());
+        assert_eq!(
+            "Could not rotate the device key\n\tcaused by: IoT Hub rejected the key",
+            error_message(response)
+        );
+    }
+
+    #[test]
+    fn rotate_device_key_rejects_malformed_key() {
+        for body in &[
+            r#"{"primaryKey":"not base64!"}"#,
+            r#"{"primaryKey":""}"#,
+            r#"{"key":"a2V5"}"#,
+        ] {
+            let handler = RotateDeviceKey::new(TestRotation::new(Ok(())));
+            let response = handler
+                .handle(request(body), Parameters::new())
+                .wait()
+                .unwrap();
+
+            assert_eq!(StatusCode::BAD_REQUEST, response.status());
+            assert!(handler.rotation.keys.lock().unwrap().is_empty());
+        }
+    }
+}
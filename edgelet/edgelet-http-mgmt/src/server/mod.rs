@@ -11,7 +11,8 @@ use serde::de::DeserializeOwned;
 use serde::Serialize;
 
 use edgelet_core::{
-    Authenticator, IdentityManager, Module, ModuleRuntime, ModuleRuntimeErrorReason, Policy,
+    Authenticator, DeviceKeyRotation, IdentityManager, Module, ModuleRuntime,
+    ModuleRuntimeErrorReason, Policy,
 };
 use edgelet_http::authentication::Authentication;
 use edgelet_http::authorization::Authorization;
@@ -22,11 +23,15 @@ use edgelet_http::Version;
 mod device_actions;
 mod identity;
 mod module;
+mod network;
+mod registry;
 mod system_info;
 
 use self::device_actions::*;
 use self::identity::*;
 pub use self::module::*;
+use self::network::*;
+use self::registry::*;
 use self::system_info::*;
 use crate::error::{Error, ErrorKind};
 
@@ -40,10 +45,11 @@ pub struct ManagementService {
 }
 
 impl ManagementService {
-    pub fn new<M, I>(
+    pub fn new<M, I, R>(
         runtime: &M,
         identity: &I,
         initiate_shutdown_and_reprovision: UnboundedSender<()>,
+        key_rotation: R,
     ) -> impl Future<Item = Self, Error = Error>
     where
         M: ModuleRuntime + Authenticator<Request = Request<Body>> + Clone + Send + Sync + 'static,
@@ -52,6 +58,7 @@ impl ManagementService {
         M::Logs: Into<Body>,
         I: IdentityManager + Clone + Send + Sync + 'static,
         I::Identity: Serialize,
+        R: DeviceKeyRotation + Send + Sync + 'static,
         <M::AuthenticateFuture as Future>::Error: Fail,
     {
         let router = router!(
@@ -65,6 +72,10 @@ impl ManagementService {
             post    Version2018_06_28 runtime Policy::Anonymous             => "/modules/(?P<name>[^/]+)/stop"      => StopModule::new(runtime.clone()),
             post    Version2018_06_28 runtime Policy::Anonymous             => "/modules/(?P<name>[^/]+)/restart"   => RestartModule::new(runtime.clone()),
             get     Version2018_06_28 runtime Policy::Anonymous             => "/modules/(?P<name>[^/]+)/logs"      => ModuleLogs::new(runtime.clone()),
+            post    Version2020_07_07 runtime Policy::Caller                => "/modules/(?P<name>[^/]+)/exec"      => ExecModule::new(runtime.clone()),
+            get     Version2020_07_07 runtime Policy::Caller                => "/modules/(?P<name>[^/]+)/exec/(?P<id>[^/]+)"            => ExecModuleStatus::new(runtime.clone()),
+            post    Version2020_07_07 runtime Policy::Caller                => "/modules/(?P<name>[^/]+)/exec/(?P<id>[^/]+)/resize"     => ExecModuleResize::new(runtime.clone()),
+            get     Version2020_07_07 runtime Policy::Anonymous             => "/events"                            => ModuleEvents::new(runtime.clone()),
 
             get     Version2018_06_28 runtime Policy::Module(&*AGENT_NAME)  => "/identities"                        => ListIdentities::new(identity.clone()),
             post    Version2018_06_28 runtime Policy::Module(&*AGENT_NAME)  => "/identities"                        => CreateIdentity::new(identity.clone()),
@@ -72,9 +83,18 @@ impl ManagementService {
             delete  Version2018_06_28 runtime Policy::Module(&*AGENT_NAME)  => "/identities/(?P<name>[^/]+)"        => DeleteIdentity::new(identity.clone()),
 
             get     Version2018_06_28 runtime Policy::Anonymous             => "/systeminfo"                        => GetSystemInfo::new(runtime.clone()),
-            get     Version2019_11_05 runtime Policy::Anonymous             => "/systeminfo/resources"              => GetSystemResources::new(runtime.clone()),
+            get     Version2020_07_07 runtime Policy::Anonymous             => "/systeminfo/resources"              => GetSystemResources::new(runtime.clone()),
+            get     Version2019_11_05 runtime Policy::Anonymous             => "/systeminfo/resources"              => GetSystemResources::with_docker_stats(runtime.clone()),
 
             post    Version2019_10_22 runtime Policy::Module(&*AGENT_NAME)  => "/device/reprovision"                => ReprovisionDevice::new(initiate_shutdown_and_reprovision),
+            post    Version2020_07_07 runtime Policy::Anonymous             => "/device/key/rotate"                 => RotateDeviceKey::new(key_rotation),
+
+            post    Version2020_07_07 runtime Policy::Anonymous             => "/registries/(?P<registry>[^/]+)/login"  => LoginRegistry::new(runtime.clone()),
+            post    Version2020_07_07 runtime Policy::Anonymous             => "/registries/(?P<registry>[^/]+)/logout" => LogoutRegistry::new(runtime.clone()),
+
+            get     Version2020_07_07 runtime Policy::Anonymous             => "/networks"                          => ListNetworks::new(runtime.clone()),
+            put     Version2020_07_07 runtime Policy::Anonymous             => "/networks/(?P<network>[^/]+)/modules/(?P<name>[^/]+)"   => AttachNetwork::new(runtime.clone()),
+            delete  Version2020_07_07 runtime Policy::Anonymous             => "/networks/(?P<network>[^/]+)/modules/(?P<name>[^/]+)"   => DetachNetwork::new(runtime.clone()),
         );
 
         router.new_service().then(|inner| {
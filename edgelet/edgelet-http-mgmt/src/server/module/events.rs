@@ -0,0 +1,260 @@
+// Copyright (c) Microsoft. All rights reserved.
+
+use chrono::{DateTime, LocalResult, SecondsFormat, TimeZone, Utc};
+use failure::{Fail, ResultExt};
+use futures::{future, Future, IntoFuture, Stream};
+use hyper::header::{ACCEPT, CONTENT_TYPE};
+use hyper::{Body, Request, Response, StatusCode};
+use log::warn;
+use url::form_urlencoded;
+
+use edgelet_core::{
+    ModuleEvent as CoreModuleEvent, ModuleEventKind, ModuleRuntime, RuntimeOperation,
+};
+use edgelet_http::route::{Handler, Parameters};
+use edgelet_http::Error as HttpError;
+use management::models::ModuleEvent;
+
+use crate::error::{Error, ErrorKind};
+use crate::IntoResponse;
+
+const NDJSON: &str = "application/x-ndjson";
+const EVENT_STREAM: &str = "text/event-stream";
+
+pub struct ModuleEvents<M> {
+    runtime: M,
+}
+
+impl<M> ModuleEvents<M> {
+    pub fn new(runtime: M) -> Self {
+        ModuleEvents { runtime }
+    }
+}
+
+impl<M> Handler<Parameters> for ModuleEvents<M>
+where
+    M: 'static + ModuleRuntime + Clone + Send,
+{
+    fn handle(
+        &self,
+        req: Request<Body>,
+        _params: Parameters,
+    ) -> Box<dyn Future<Item = Response<Body>, Error = HttpError> + Send> {
+        let runtime = self.runtime.clone();
+
+        // Server-sent events are sent only to clients that ask for them, like browsers do.
+        let event_stream = req
+            .headers()
+            .get_all(ACCEPT)
+            .iter()
+            .filter_map(|value| value.to_str().ok())
+            .any(|value| value.contains(EVENT_STREAM));
+
+        let response = req
+            .uri()
+            .query()
+            .map_or(Ok(None), parse_since)
+            .map(move |since| {
+                runtime
+                    .events(since)
+                    .then(move |events| -> Result<_, Error> {
+                        let events = events.context(ErrorKind::RuntimeOperation(
+                            RuntimeOperation::GetModuleEvents,
+                        ))?;
+
+                        let body = events
+                            .map(move |event| to_chunk(&event, event_stream))
+                            .map_err(|err| {
+                                let err = Error::from(err.context(ErrorKind::RuntimeOperation(
+                                    RuntimeOperation::GetModuleEvents,
+                                )));
+                                warn!("Module event stream ended: {}", err);
+                                err.compat()
+                            });
+
+                        let response = Response::builder()
+                            .status(StatusCode::OK)
+                            .header(
+                                CONTENT_TYPE,
+                                if event_stream { EVENT_STREAM } else { NDJSON },
+                            )
+                            .body(Body::wrap_stream(body))
+                            .context(ErrorKind::RuntimeOperation(
+                                RuntimeOperation::GetModuleEvents,
+                            ))?;
+                        Ok(response)
+                    })
+            })
+            .into_future()
+            .flatten()
+            .or_else(|e| future::ok(e.into_response()));
+
+        Box::new(response)
+    }
+}
+
+fn parse_since(query: &str) -> Result<Option<DateTime<Utc>>, Error> {
+    let since = form_urlencoded::parse(query.as_bytes())
+        .find(|(key, _)| key == "since")
+        .map(|(_, val)| val.parse::<i64>())
+        .transpose()
+        .context(ErrorKind::MalformedRequestParameter("since"))?;
+
+    match since.map(|since| Utc.timestamp_opt(since, 0)) {
+        None => Ok(None),
+        Some(LocalResult::Single(since)) => Ok(Some(since)),
+        Some(_) => Err(Error::from(ErrorKind::MalformedRequestParameter("since"))),
+    }
+}
+
+fn to_chunk(event: &CoreModuleEvent, event_stream: bool) -> String {
+    let type_ = match event.kind() {
+        ModuleEventKind::Created => "created",
+        ModuleEventKind::Started => "started",
+        ModuleEventKind::Died(_) => "died",
+        ModuleEventKind::OomKilled => "oomKilled",
+        ModuleEventKind::Restarted => "restarted",
+        ModuleEventKind::Removed => "removed",
+    };
+    let mut model = ModuleEvent::new(
+        event.name().to_string(),
+        type_.to_string(),
+        event.time().to_rfc3339_opts(SecondsFormat::AutoSi, true),
+    );
+    if let ModuleEventKind::Died(exit_code) = event.kind() {
+        model.set_exit_code(exit_code);
+    }
+
+    let json = serde_json::to_string(&model).expect("could not serialize module event");
+    if event_stream {
+        format!("data: {}\n\n", json)
+    } else {
+        format!("{}\n", json)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use edgelet_core::{MakeModuleRuntime, ModuleRuntimeState};
+    use edgelet_test_utils::crypto::TestHsm;
+    use edgelet_test_utils::module::*;
+    use management::models::ErrorResponse;
+
+    use super::*;
+    use crate::server::module::tests::Error;
+
+    fn runtime(
+        module: Result<TestModule<Error, TestConfig>, Error>,
+    ) -> TestRuntime<Error, TestSettings> {
+        TestRuntime::make_runtime(
+            TestSettings::new(),
+            TestProvisioningResult::new(),
+            TestHsm::default(),
+        )
+        .wait()
+        .unwrap()
+        .with_module(module)
+    }
+
+    fn module() -> TestModule<Error, TestConfig> {
+        TestModule::new(
+            "test-module".to_string(),
+            TestConfig::new("microsoft/test-image".to_string()),
+            Ok(ModuleRuntimeState::default()),
+        )
+    }
+
+    fn body(response: Response<Body>) -> String {
+        let body = response.into_body().concat2().wait().unwrap();
+        String::from_utf8(body.to_vec()).unwrap()
+    }
+
+    #[test]
+    fn events_are_newline_delimited_json() {
+        let handler = ModuleEvents::new(runtime(Ok(module())));
+        let request = Request::get("http://localhost/events?api-version=2020-07-07")
+            .body(Body::default())
+            .unwrap();
+
+        let response = handler
+            .handle(request, Parameters::default())
+            .wait()
+            .unwrap();
+
+        assert_eq!(StatusCode::OK, response.status());
+        assert_eq!(NDJSON, response.headers()[CONTENT_TYPE]);
+        let events: Vec<ModuleEvent> = body(response)
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(3, events.len());
+        assert_eq!("test-module", events[0].name());
+        assert_eq!("created", events[0].type_());
+        assert_eq!(None, events[0].exit_code());
+        assert_eq!("died", events[2].type_());
+        assert_eq!("2019-03-06T15:25:20Z", events[2].time());
+        assert_eq!(Some(1), events[2].exit_code());
+    }
+
+    #[test]
+    fn events_are_server_sent_events_when_accepted() {
+        let handler = ModuleEvents::new(runtime(Ok(module())));
+        let request =
+            Request::get("http://localhost/events?api-version=2020-07-07&since=1551885910")
+                .header(ACCEPT, EVENT_STREAM)
+                .body(Body::default())
+                .unwrap();
+
+        let response = handler
+            .handle(request, Parameters::default())
+            .wait()
+            .unwrap();
+
+        assert_eq!(StatusCode::OK, response.status());
+        assert_eq!(EVENT_STREAM, response.headers()[CONTENT_TYPE]);
+        let body = body(response);
+        let events: Vec<_> = body.split_terminator("\n\n").collect();
+        assert_eq!(2, events.len());
+        assert!(events[0].starts_with("data: {"));
+        assert!(events[0].contains(r#""type":"started""#));
+    }
+
+    #[test]
+    fn bad_since_fails() {
+        let handler = ModuleEvents::new(runtime(Ok(module())));
+        let request = Request::get("http://localhost/events?api-version=2020-07-07&since=abc")
+            .body(Body::default())
+            .unwrap();
+
+        let response = handler
+            .handle(request, Parameters::default())
+            .wait()
+            .unwrap();
+
+        assert_eq!(StatusCode::BAD_REQUEST, response.status());
+        let error: ErrorResponse = serde_json::from_str(&body(response)).unwrap();
+        assert!(error
+            .message()
+            .starts_with("The request parameter `since` is malformed"));
+    }
+
+    #[test]
+    fn runtime_error() {
+        let handler = ModuleEvents::new(runtime(Err(Error::General)));
+        let request = Request::get("http://localhost/events?api-version=2020-07-07")
+            .body(Body::default())
+            .unwrap();
+
+        let response = handler
+            .handle(request, Parameters::default())
+            .wait()
+            .unwrap();
+
+        assert_eq!(StatusCode::INTERNAL_SERVER_ERROR, response.status());
+        let error: ErrorResponse = serde_json::from_str(&body(response)).unwrap();
+        assert_eq!(
+            "Could not get module events\n\tcaused by: General error",
+            error.message()
+        );
+    }
+}
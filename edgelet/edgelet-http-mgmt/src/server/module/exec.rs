@@ -0,0 +1,479 @@
+// Copyright (c) Microsoft. All rights reserved.
+
+use std::borrow::Cow;
+
+use failure::ResultExt;
+use futures::{Future, IntoFuture};
+use hyper::header::{CONNECTION, CONTENT_LENGTH, CONTENT_TYPE, UPGRADE};
+use hyper::{Body, Request, Response, StatusCode};
+use log::{debug, warn};
+use tokio::io::{self, AsyncRead, AsyncWrite};
+use url::form_urlencoded;
+
+use edgelet_core::{ExecOptions, ModuleRuntime, RuntimeOperation};
+use edgelet_http::route::{Handler, Parameters};
+use edgelet_http::Error as HttpError;
+use management::apis::EXEC_ID_HEADER;
+use management::models::ExecStatus;
+
+use crate::error::{Error, ErrorKind};
+use crate::IntoResponse;
+
+pub struct ExecModule<M> {
+    runtime: M,
+}
+
+impl<M> ExecModule<M> {
+    pub fn new(runtime: M) -> Self {
+        ExecModule { runtime }
+    }
+}
+
+impl<M> Handler<Parameters> for ExecModule<M>
+where
+    M: 'static + ModuleRuntime + Send,
+{
+    fn handle(
+        &self,
+        req: Request<Body>,
+        params: Parameters,
+    ) -> Box<dyn Future<Item = Response<Body>, Error = HttpError> + Send> {
+        let response = params
+            .name("name")
+            .ok_or_else(|| Error::from(ErrorKind::MissingRequiredParameter("name")))
+            .and_then(|name| {
+                let name = name.to_string();
+                if !req.headers().contains_key(UPGRADE) {
+                    return Err(Error::from(ErrorKind::MissingUpgrade));
+                }
+                let options = parse_options(req.uri().query().unwrap_or_default())?;
+                Ok((name, options))
+            })
+            .map(|(name, options)| {
+                let connection = req.into_body().on_upgrade();
+
+                self.runtime
+                    .exec(&name, &options)
+                    .then(move |exec| -> Result<_, Error> {
+                        let (exec_id, exec) = exec.with_context(|_| {
+                            ErrorKind::RuntimeOperation(RuntimeOperation::ExecModule(name.clone()))
+                        })?;
+
+                        // The connection is only upgraded once the response is sent.
+                        tokio::spawn(
+                            connection
+                                .map_err(|err| warn!("Could not upgrade exec connection: {}", err))
+                                .and_then(move |connection| session(connection, exec)),
+                        );
+
+                        let response = Response::builder()
+                            .status(StatusCode::SWITCHING_PROTOCOLS)
+                            .header(CONNECTION, "Upgrade")
+                            .header(UPGRADE, "tcp")
+                            .header(EXEC_ID_HEADER, exec_id.as_str())
+                            .body(Body::empty())
+                            .context(ErrorKind::RuntimeOperation(RuntimeOperation::ExecModule(
+                                name,
+                            )))?;
+                        Ok(response)
+                    })
+            })
+            .into_future()
+            .flatten()
+            .or_else(|e| Ok(e.into_response()));
+
+        Box::new(response)
+    }
+}
+
+pub struct ExecModuleResize<M> {
+    runtime: M,
+}
+
+impl<M> ExecModuleResize<M> {
+    pub fn new(runtime: M) -> Self {
+        ExecModuleResize { runtime }
+    }
+}
+
+impl<M> Handler<Parameters> for ExecModuleResize<M>
+where
+    M: 'static + ModuleRuntime + Send,
+{
+    fn handle(
+        &self,
+        req: Request<Body>,
+        params: Parameters,
+    ) -> Box<dyn Future<Item = Response<Body>, Error = HttpError> + Send> {
+        let response = exec_params(&params)
+            .and_then(|(name, exec_id)| {
+                let (height, width) = parse_tty_size(req.uri().query().unwrap_or_default())?;
+                Ok((name, exec_id, height, width))
+            })
+            .map(|(name, exec_id, height, width)| {
+                self.runtime
+                    .exec_resize(&name, &exec_id, height, width)
+                    .then(|result| -> Result<_, Error> {
+                        let context = RuntimeOperation::ExecModuleResize(name);
+                        result.with_context(|_| ErrorKind::RuntimeOperation(context.clone()))?;
+
+                        let response = Response::builder()
+                            .status(StatusCode::NO_CONTENT)
+                            .body(Body::default())
+                            .context(ErrorKind::RuntimeOperation(context))?;
+                        Ok(response)
+                    })
+            })
+            .into_future()
+            .flatten()
+            .or_else(|e| Ok(e.into_response()));
+
+        Box::new(response)
+    }
+}
+
+pub struct ExecModuleStatus<M> {
+    runtime: M,
+}
+
+impl<M> ExecModuleStatus<M> {
+    pub fn new(runtime: M) -> Self {
+        ExecModuleStatus { runtime }
+    }
+}
+
+impl<M> Handler<Parameters> for ExecModuleStatus<M>
+where
+    M: 'static + ModuleRuntime + Send,
+{
+    fn handle(
+        &self,
+        _req: Request<Body>,
+        params: Parameters,
+    ) -> Box<dyn Future<Item = Response<Body>, Error = HttpError> + Send> {
+        let response = exec_params(&params)
+            .map(|(name, exec_id)| {
+                self.runtime
+                    .exec_exit_code(&name, &exec_id)
+                    .then(|exit_code| -> Result<_, Error> {
+                        let context = RuntimeOperation::ExecModuleExitCode(name);
+                        let exit_code = exit_code
+                            .with_context(|_| ErrorKind::RuntimeOperation(context.clone()))?;
+
+                        let mut body = ExecStatus::new();
+                        if let Some(exit_code) = exit_code {
+                            body.set_exit_code(exit_code);
+                        }
+                        let b = serde_json::to_string(&body)
+                            .with_context(|_| ErrorKind::RuntimeOperation(context.clone()))?;
+
+                        let response = Response::builder()
+                            .status(StatusCode::OK)
+                            .header(CONTENT_TYPE, "application/json")
+                            .header(CONTENT_LENGTH, b.len().to_string().as_str())
+                            .body(b.into())
+                            .context(ErrorKind::RuntimeOperation(context))?;
+                        Ok(response)
+                    })
+            })
+            .into_future()
+            .flatten()
+            .or_else(|e| Ok(e.into_response()));
+
+        Box::new(response)
+    }
+}
+
+fn exec_params(params: &Parameters) -> Result<(String, String), Error> {
+    let name = params
+        .name("name")
+        .ok_or_else(|| Error::from(ErrorKind::MissingRequiredParameter("name")))?;
+    let exec_id = params
+        .name("id")
+        .ok_or_else(|| Error::from(ErrorKind::MissingRequiredParameter("id")))?;
+    Ok((name.to_string(), exec_id.to_string()))
+}
+
+fn parse_options(query: &str) -> Result<ExecOptions, Error> {
+    let parse: Vec<_> = form_urlencoded::parse(query.as_bytes()).collect();
+    let command: Vec<String> = parse
+        .iter()
+        .filter(|&(ref key, _)| key == "cmd")
+        .map(|(_, val)| val.to_string())
+        .collect();
+    if command.is_empty() {
+        return Err(Error::from(ErrorKind::MissingRequiredParameter("cmd")));
+    }
+    let tty = parse
+        .iter()
+        .find(|&(ref key, _)| key == "tty")
+        .map_or_else(|| Ok(false), |(_, val)| val.parse::<bool>())
+        .context(ErrorKind::MalformedRequestParameter("tty"))?;
+    let height = parse_dimension(&parse, "h")?;
+    let width = parse_dimension(&parse, "w")?;
+
+    let options = ExecOptions::new(command).with_tty(tty);
+    let options = match (height, width) {
+        (Some(height), Some(width)) => options.with_tty_size(height, width),
+        _ => options,
+    };
+    Ok(options)
+}
+
+fn parse_tty_size(query: &str) -> Result<(u16, u16), Error> {
+    let parse: Vec<_> = form_urlencoded::parse(query.as_bytes()).collect();
+    let height = parse_dimension(&parse, "h")?
+        .ok_or_else(|| Error::from(ErrorKind::MissingRequiredParameter("h")))?;
+    let width = parse_dimension(&parse, "w")?
+        .ok_or_else(|| Error::from(ErrorKind::MissingRequiredParameter("w")))?;
+    Ok((height, width))
+}
+
+fn parse_dimension(
+    parse: &[(Cow<'_, str>, Cow<'_, str>)],
+    key: &'static str,
+) -> Result<Option<u16>, Error> {
+    let dimension = parse
+        .iter()
+        .find(|&(ref k, _)| k == key)
+        .map(|(_, val)| val.parse::<u16>())
+        .transpose()
+        .context(ErrorKind::MalformedRequestParameter(key))?;
+    Ok(dimension)
+}
+
+/// Copies the input of the command from the connection, and its output to the connection,
+/// until the command exits.
+fn session<C, E>(connection: C, exec: E) -> impl Future<Item = (), Error = ()>
+where
+    C: AsyncRead + AsyncWrite + Send + 'static,
+    E: AsyncRead + AsyncWrite + Send + 'static,
+{
+    let (connection_read, connection_write) = connection.split();
+    let (exec_read, exec_write) = exec.split();
+
+    // The client shuts down its side of the connection at the end of its input, which doesn't
+    // end the session since the command can still have output.
+    tokio::spawn(
+        io::copy(connection_read, exec_write)
+            .and_then(|(_, _, exec_write)| io::shutdown(exec_write))
+            .map(drop)
+            .map_err(|err| debug!("Exec input ended: {}", err)),
+    );
+
+    io::copy(exec_read, connection_write)
+        .and_then(|(_, _, connection_write)| io::shutdown(connection_write))
+        .map(drop)
+        .map_err(|err| warn!("Exec output ended: {}", err))
+}
+
+#[cfg(test)]
+mod tests {
+    use edgelet_core::{MakeModuleRuntime, ModuleRuntimeState};
+    use edgelet_test_utils::crypto::TestHsm;
+    use edgelet_test_utils::module::*;
+    use futures::Stream;
+    use management::models::ErrorResponse;
+    use tokio::runtime::current_thread::Runtime;
+
+    use super::*;
+    use crate::server::module::tests::Error;
+
+    fn handler(
+        module: Result<TestModule<Error, TestConfig>, Error>,
+    ) -> ExecModule<TestRuntime<Error, TestSettings>> {
+        ExecModule::new(runtime(module))
+    }
+
+    fn module() -> TestModule<Error, TestConfig> {
+        TestModule::new(
+            "test-module".to_string(),
+            TestConfig::new("microsoft/test-image".to_string()),
+            Ok(ModuleRuntimeState::default()),
+        )
+    }
+
+    fn request(uri: &str) -> Request<Body> {
+        Request::post(uri)
+            .header(CONNECTION, "Upgrade")
+            .header(UPGRADE, "tcp")
+            .body(Body::default())
+            .unwrap()
+    }
+
+    fn parameters() -> Parameters {
+        Parameters::with_captures(vec![(Some("name".to_string()), "mod1".to_string())])
+    }
+
+    fn exec_parameters() -> Parameters {
+        Parameters::with_captures(vec![
+            (Some("name".to_string()), "mod1".to_string()),
+            (Some("id".to_string()), "exec1".to_string()),
+        ])
+    }
+
+    fn runtime(
+        module: Result<TestModule<Error, TestConfig>, Error>,
+    ) -> TestRuntime<Error, TestSettings> {
+        TestRuntime::make_runtime(
+            TestSettings::new(),
+            TestProvisioningResult::new(),
+            TestHsm::default(),
+        )
+        .wait()
+        .unwrap()
+        .with_module(module)
+    }
+
+    fn error_message(response: Response<Body>) -> String {
+        let body = response.into_body().concat2().wait().unwrap();
+        let error: ErrorResponse = serde_json::from_slice(&body).unwrap();
+        error.message().to_string()
+    }
+
+    #[test]
+    fn correct_options() {
+        let options = parse_options("cmd=sh&cmd=-c&cmd=echo%20hi&tty=true&h=24&w=80").unwrap();
+        assert_eq!(&["sh", "-c", "echo hi"], options.command());
+        assert!(options.tty());
+        assert_eq!(Some((24, 80)), options.tty_size());
+    }
+
+    #[test]
+    fn command_is_required() {
+        let err = parse_options("tty=true").unwrap_err();
+        assert_eq!(
+            "The request is missing required parameter `cmd`",
+            err.to_string()
+        );
+    }
+
+    #[test]
+    fn success_switches_protocols() {
+        let handler = handler(Ok(module()));
+        let request = request("http://localhost/modules/mod1/exec?api-version=2020-07-07&cmd=ls");
+
+        let response = Runtime::new()
+            .unwrap()
+            .block_on(handler.handle(request, parameters()))
+            .unwrap();
+
+        assert_eq!(StatusCode::SWITCHING_PROTOCOLS, response.status());
+        assert_eq!("tcp", response.headers()[UPGRADE]);
+        assert_eq!("exec1", response.headers()[EXEC_ID_HEADER]);
+    }
+
+    #[test]
+    fn upgrade_is_required() {
+        let handler = handler(Ok(module()));
+        let request =
+            Request::post("http://localhost/modules/mod1/exec?api-version=2020-07-07&cmd=ls")
+                .body(Body::default())
+                .unwrap();
+
+        let response = handler.handle(request, parameters()).wait().unwrap();
+
+        assert_eq!(StatusCode::BAD_REQUEST, response.status());
+        assert_eq!(
+            "The request must ask to upgrade the connection",
+            error_message(response)
+        );
+    }
+
+    #[test]
+    fn runtime_error() {
+        let handler = handler(Err(Error::General));
+        let request = request("http://localhost/modules/mod1/exec?api-version=2020-07-07&cmd=ls");
+
+        let response = handler.handle(request, parameters()).wait().unwrap();
+
+        assert_eq!(StatusCode::INTERNAL_SERVER_ERROR, response.status());
+        assert_eq!(
+            "Could not run command in module mod1\n\tcaused by: General error",
+            error_message(response)
+        );
+    }
+
+    #[test]
+    fn resize_succeeds() {
+        let handler = ExecModuleResize::new(runtime(Ok(module())));
+        let request = Request::post(
+            "http://localhost/modules/mod1/exec/exec1/resize?api-version=2020-07-07&h=24&w=80",
+        )
+        .body(Body::default())
+        .unwrap();
+
+        let response = handler.handle(request, exec_parameters()).wait().unwrap();
+
+        assert_eq!(StatusCode::NO_CONTENT, response.status());
+    }
+
+    #[test]
+    fn resize_requires_size() {
+        let handler = ExecModuleResize::new(runtime(Ok(module())));
+        let request = Request::post(
+            "http://localhost/modules/mod1/exec/exec1/resize?api-version=2020-07-07&h=24",
+        )
+        .body(Body::default())
+        .unwrap();
+
+        let response = handler.handle(request, exec_parameters()).wait().unwrap();
+
+        assert_eq!(StatusCode::BAD_REQUEST, response.status());
+        assert_eq!(
+            "The request is missing required parameter `w`",
+            error_message(response)
+        );
+    }
+
+    #[test]
+    fn resize_runtime_error() {
+        let handler = ExecModuleResize::new(runtime(Err(Error::General)));
+        let request = Request::post(
+            "http://localhost/modules/mod1/exec/exec1/resize?api-version=2020-07-07&h=24&w=80",
+        )
+        .body(Body::default())
+        .unwrap();
+
+        let response = handler.handle(request, exec_parameters()).wait().unwrap();
+
+        assert_eq!(StatusCode::INTERNAL_SERVER_ERROR, response.status());
+        assert_eq!(
+            "Could not resize the terminal of the command in module mod1\n\tcaused by: General error",
+            error_message(response)
+        );
+    }
+
+    #[test]
+    fn status_has_exit_code() {
+        let handler = ExecModuleStatus::new(runtime(Ok(module())));
+        let request =
+            Request::get("http://localhost/modules/mod1/exec/exec1?api-version=2020-07-07")
+                .body(Body::default())
+                .unwrap();
+
+        let response = handler.handle(request, exec_parameters()).wait().unwrap();
+
+        assert_eq!(StatusCode::OK, response.status());
+        let body = response.into_body().concat2().wait().unwrap();
+        let status: ExecStatus = serde_json::from_slice(&body).unwrap();
+        assert_eq!(Some(0), status.exit_code());
+    }
+
+    #[test]
+    fn status_runtime_error() {
+        let handler = ExecModuleStatus::new(runtime(Err(Error::General)));
+        let request =
+            Request::get("http://localhost/modules/mod1/exec/exec1?api-version=2020-07-07")
+                .body(Body::default())
+                .unwrap();
+
+        let response = handler.handle(request, exec_parameters()).wait().unwrap();
+
+        assert_eq!(StatusCode::INTERNAL_SERVER_ERROR, response.status());
+        assert_eq!(
+            "Could not get the exit code of the command in module mod1\n\tcaused by: General error",
+            error_message(response)
+        );
+    }
+}
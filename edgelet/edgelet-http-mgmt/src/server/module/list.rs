@@ -86,6 +86,9 @@ where
             status.set_exit_status(ExitStatus::new(finished_at.to_rfc3339(), code.to_string()));
         }
     }
+    if let Some(restart_count) = state.restart_count() {
+        status.set_restart_count(restart_count.into());
+    }
 
     Ok(ModuleDetails::new(
         "id".to_string(),
@@ -118,7 +121,8 @@ mod tests {
             .with_status_description(Some("description".to_string()))
             .with_started_at(Some(Utc.ymd(2018, 4, 13).and_hms_milli(14, 20, 0, 1)))
             .with_finished_at(Some(Utc.ymd(2018, 4, 13).and_hms_milli(15, 20, 0, 1)))
-            .with_image_id(Some("image-id".to_string()));
+            .with_image_id(Some("image-id".to_string()))
+            .with_restart_count(Some(2));
         let config = TestConfig::new("microsoft/test-image".to_string());
         let module: TestModule<Error, _> =
             TestModule::new("test-module".to_string(), config, Ok(state));
@@ -169,6 +173,7 @@ mod tests {
                     "description",
                     module.status().runtime_status().description().unwrap()
                 );
+                assert_eq!(Some(2), module.status().restart_count());
                 Ok(())
             })
             .wait()
@@ -16,6 +16,8 @@ use crate::error::{Error, ErrorKind};
 
 mod create;
 mod delete;
+mod events;
+mod exec;
 mod get;
 mod list;
 mod logs;
@@ -27,6 +29,8 @@ mod update;
 
 pub use self::create::CreateModule;
 pub use self::delete::DeleteModule;
+pub use self::events::ModuleEvents;
+pub use self::exec::{ExecModule, ExecModuleResize, ExecModuleStatus};
 pub use self::get::GetModule;
 pub use self::list::ListModules;
 pub use self::logs::ModuleLogs;
@@ -0,0 +1,162 @@
+// Copyright (c) Microsoft. All rights reserved.
+
+use failure::{Fail, ResultExt};
+use futures::{Future, IntoFuture};
+use hyper::{Body, Request, Response, StatusCode};
+use log::debug;
+
+use edgelet_core::{ModuleRuntime, RuntimeOperation};
+use edgelet_http::route::{Handler, Parameters};
+use edgelet_http::Error as HttpError;
+
+use crate::error::{Error, ErrorKind};
+use crate::IntoResponse;
+
+pub struct DetachNetwork<M> {
+    runtime: M,
+}
+
+impl<M> DetachNetwork<M> {
+    pub fn new(runtime: M) -> Self {
+        DetachNetwork { runtime }
+    }
+}
+
+impl<M> Handler<Parameters> for DetachNetwork<M>
+where
+    M: 'static + ModuleRuntime + Send,
+{
+    fn handle(
+        &self,
+        _req: Request<Body>,
+        params: Parameters,
+    ) -> Box<dyn Future<Item = Response<Body>, Error = HttpError> + Send> {
+        debug!("Detach network");
+
+        let response = params
+            .name("network")
+            .ok_or_else(|| Error::from(ErrorKind::MissingRequiredParameter("network")))
+            .and_then(|network| {
+                let name = params
+                    .name("name")
+                    .ok_or_else(|| Error::from(ErrorKind::MissingRequiredParameter("name")))?;
+                Ok((name.to_string(), network.to_string()))
+            })
+            .map(|(name, network)| {
+                self.runtime
+                    .detach_network(&name, &network)
+                    .then(|result| match result {
+                        Ok(()) => Ok((name, network)),
+                        Err(err) => Err(Error::from(err.context(ErrorKind::RuntimeOperation(
+                            RuntimeOperation::DetachNetwork(name, network),
+                        )))),
+                    })
+            })
+            .into_future()
+            .flatten()
+            .and_then(|(name, network)| {
+                Ok(Response::builder()
+                    .status(StatusCode::NO_CONTENT)
+                    .body(Body::default())
+                    .context(ErrorKind::RuntimeOperation(
+                        RuntimeOperation::DetachNetwork(name, network),
+                    ))?)
+            })
+            .or_else(|e| Ok(e.into_response()));
+
+        Box::new(response)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use edgelet_core::{MakeModuleRuntime, ModuleRuntimeState};
+    use edgelet_test_utils::crypto::TestHsm;
+    use edgelet_test_utils::module::*;
+    use futures::Stream;
+    use management::models::ErrorResponse;
+
+    use super::*;
+    use crate::server::module::tests::Error;
+
+    fn handler(
+        module: Result<TestModule<Error, TestConfig>, Error>,
+    ) -> DetachNetwork<TestRuntime<Error, TestSettings>> {
+        let runtime = TestRuntime::make_runtime(
+            TestSettings::new(),
+            TestProvisioningResult::new(),
+            TestHsm::default(),
+        )
+        .wait()
+        .unwrap()
+        .with_module(module);
+        DetachNetwork::new(runtime)
+    }
+
+    fn request() -> Request<Body> {
+        Request::delete("http://localhost/networks/fieldbus/modules/test")
+            .body(Body::default())
+            .unwrap()
+    }
+
+    fn parameters() -> Parameters {
+        Parameters::with_captures(vec![
+            (Some("network".to_string()), "fieldbus".to_string()),
+            (Some("name".to_string()), "test".to_string()),
+        ])
+    }
+
+    #[test]
+    fn success() {
+        // arrange
+        let config = TestConfig::new("microsoft/test-image".to_string());
+        let module = TestModule::new(
+            "test".to_string(),
+            config,
+            Ok(ModuleRuntimeState::default()),
+        );
+
+        // act
+        let response = handler(Ok(module))
+            .handle(request(), parameters())
+            .wait()
+            .unwrap();
+
+        // assert
+        assert_eq!(StatusCode::NO_CONTENT, response.status());
+    }
+
+    #[test]
+    fn detach_failed() {
+        // act
+        let response = handler(Err(Error::General))
+            .handle(request(), parameters())
+            .wait()
+            .unwrap();
+
+        // assert
+        assert_eq!(StatusCode::INTERNAL_SERVER_ERROR, response.status());
+        let body = response.into_body().concat2().wait().unwrap();
+        let error: ErrorResponse = serde_json::from_slice(&body).unwrap();
+        assert_eq!(
+            "Could not detach module test from network fieldbus\n\tcaused by: General error",
+            error.message()
+        );
+    }
+
+    #[test]
+    fn detach_bad_params() {
+        // arrange
+        let parameters =
+            Parameters::with_captures(vec![(Some("network".to_string()), "fieldbus".to_string())]);
+
+        // act
+        let response = handler(Err(Error::General))
+            .handle(request(), parameters)
+            .wait()
+            .unwrap();
+
+        // assert
+        assert_eq!(StatusCode::BAD_REQUEST, response.status());
+    }
+}
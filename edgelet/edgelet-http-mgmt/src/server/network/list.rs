@@ -0,0 +1,168 @@
+// Copyright (c) Microsoft. All rights reserved.
+
+use failure::ResultExt;
+use futures::Future;
+use hyper::header::{CONTENT_LENGTH, CONTENT_TYPE};
+use hyper::{Body, Request, Response, StatusCode};
+use log::debug;
+use serde_json;
+
+use edgelet_core::{ModuleRuntime, Network, RuntimeOperation};
+use edgelet_http::route::{Handler, Parameters};
+use edgelet_http::Error as HttpError;
+use management::models::{NetworkDetails, NetworkList};
+
+use crate::error::{Error, ErrorKind};
+use crate::IntoResponse;
+
+pub struct ListNetworks<M> {
+    runtime: M,
+}
+
+impl<M> ListNetworks<M> {
+    pub fn new(runtime: M) -> Self {
+        ListNetworks { runtime }
+    }
+}
+
+impl<M> Handler<Parameters> for ListNetworks<M>
+where
+    M: 'static + ModuleRuntime + Send,
+{
+    fn handle(
+        &self,
+        _req: Request<Body>,
+        _params: Parameters,
+    ) -> Box<dyn Future<Item = Response<Body>, Error = HttpError> + Send> {
+        debug!("List networks");
+
+        let response = self
+            .runtime
+            .list_networks()
+            .then(|result| -> Result<_, Error> {
+                let networks = result
+                    .context(ErrorKind::RuntimeOperation(RuntimeOperation::ListNetworks))?
+                    .iter()
+                    .map(core_to_details)
+                    .collect();
+                let body = NetworkList::new(networks);
+                let b = serde_json::to_string(&body)
+                    .context(ErrorKind::RuntimeOperation(RuntimeOperation::ListNetworks))?;
+                let response = Response::builder()
+                    .status(StatusCode::OK)
+                    .header(CONTENT_TYPE, "application/json")
+                    .header(CONTENT_LENGTH, b.len().to_string().as_str())
+                    .body(b.into())
+                    .context(ErrorKind::RuntimeOperation(RuntimeOperation::ListNetworks))?;
+                Ok(response)
+            })
+            .or_else(|e| Ok(e.into_response()));
+
+        Box::new(response)
+    }
+}
+
+fn core_to_details(network: &Network) -> NetworkDetails {
+    let subnets = network
+        .ipam()
+        .and_then(|ipam| ipam.config())
+        .unwrap_or_default()
+        .iter()
+        .filter_map(|config| config.subnet().map(ToOwned::to_owned))
+        .collect();
+
+    let mut details = NetworkDetails::new(
+        network.name().to_string(),
+        subnets,
+        network.modules().to_vec(),
+    );
+    if let Some(driver) = network.driver() {
+        details.set_driver(driver.to_string());
+    }
+    if let Some(internal) = network.internal() {
+        details.set_internal(internal);
+    }
+    if let Some(ipv6) = network.ipv6() {
+        details.set_ipv6(ipv6);
+    }
+    details
+}
+
+#[cfg(test)]
+mod tests {
+    use edgelet_core::{MakeModuleRuntime, ModuleRuntimeState, DEFAULT_NETWORKID};
+    use edgelet_test_utils::crypto::TestHsm;
+    use edgelet_test_utils::module::*;
+    use futures::Stream;
+    use management::models::ErrorResponse;
+
+    use super::*;
+    use crate::server::module::tests::Error;
+
+    fn handler(
+        module: Result<TestModule<Error, TestConfig>, Error>,
+    ) -> ListNetworks<TestRuntime<Error, TestSettings>> {
+        let runtime = TestRuntime::make_runtime(
+            TestSettings::new(),
+            TestProvisioningResult::new(),
+            TestHsm::default(),
+        )
+        .wait()
+        .unwrap()
+        .with_module(module);
+        ListNetworks::new(runtime)
+    }
+
+    #[test]
+    fn success() {
+        // arrange
+        let config = TestConfig::new("microsoft/test-image".to_string());
+        let module = TestModule::new(
+            "test-module".to_string(),
+            config,
+            Ok(ModuleRuntimeState::default()),
+        );
+        let request = Request::get("http://localhost/networks")
+            .body(Body::default())
+            .unwrap();
+
+        // act
+        let response = handler(Ok(module))
+            .handle(request, Parameters::new())
+            .wait()
+            .unwrap();
+
+        // assert
+        assert_eq!(StatusCode::OK, response.status());
+        let body = response.into_body().concat2().wait().unwrap();
+        let list: NetworkList = serde_json::from_slice(&body).unwrap();
+        assert_eq!(1, list.networks().len());
+        assert_eq!(DEFAULT_NETWORKID, list.networks()[0].name());
+        assert_eq!(&["test-module".to_string()], list.networks()[0].modules());
+        assert!(list.networks()[0].subnets().is_empty());
+        assert_eq!(None, list.networks()[0].driver());
+    }
+
+    #[test]
+    fn list_failed() {
+        // arrange
+        let request = Request::get("http://localhost/networks")
+            .body(Body::default())
+            .unwrap();
+
+        // act
+        let response = handler(Err(Error::General))
+            .handle(request, Parameters::new())
+            .wait()
+            .unwrap();
+
+        // assert
+        assert_eq!(StatusCode::INTERNAL_SERVER_ERROR, response.status());
+        let body = response.into_body().concat2().wait().unwrap();
+        let error: ErrorResponse = serde_json::from_slice(&body).unwrap();
+        assert_eq!(
+            "Could not list networks\n\tcaused by: General error",
+            error.message()
+        );
+    }
+}
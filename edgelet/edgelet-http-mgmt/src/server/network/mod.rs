@@ -0,0 +1,8 @@
+// Copyright (c) Microsoft. All rights reserved.
+mod attach;
+mod detach;
+mod list;
+
+pub use self::attach::AttachNetwork;
+pub use self::detach::DetachNetwork;
+pub use self::list::ListNetworks;
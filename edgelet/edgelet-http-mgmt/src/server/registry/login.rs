@@ -0,0 +1,177 @@
+// Copyright (c) Microsoft. All rights reserved.
+
+use failure::{Fail, ResultExt};
+use futures::{Future, IntoFuture, Stream};
+use hyper::{Body, Request, Response, StatusCode};
+use log::debug;
+
+use edgelet_core::{ModuleRegistry, ModuleRuntime, RegistryOperation};
+use edgelet_http::route::{Handler, Parameters};
+use edgelet_http::Error as HttpError;
+use management::models::RegistryCredentials;
+
+use crate::error::{Error, ErrorKind};
+use crate::IntoResponse;
+
+pub struct LoginRegistry<M> {
+    runtime: M,
+}
+
+impl<M> LoginRegistry<M> {
+    pub fn new(runtime: M) -> Self {
+        LoginRegistry { runtime }
+    }
+}
+
+impl<M> Handler<Parameters> for LoginRegistry<M>
+where
+    M: 'static + ModuleRuntime + Clone + Send + Sync,
+{
+    fn handle(
+        &self,
+        req: Request<Body>,
+        params: Parameters,
+    ) -> Box<dyn Future<Item = Response<Body>, Error = HttpError> + Send> {
+        debug!("Login Registry");
+        let runtime = self.runtime.clone();
+
+        let response = params
+            .name("registry")
+            .ok_or_else(|| Error::from(ErrorKind::MissingRequiredParameter("registry")))
+            .map(|registry| {
+                let registry = registry.to_string();
+
+                read_request(req)
+                    .and_then(move |credentials| {
+                        runtime
+                            .registry()
+                            .login(&registry, credentials.username(), credentials.password())
+                            .then(|result| match result {
+                                Ok(()) => Ok(registry),
+                                Err(err) => {
+                                    Err(Error::from(err.context(ErrorKind::RegistryOperation(
+                                        RegistryOperation::Login(registry),
+                                    ))))
+                                }
+                            })
+                    })
+                    .and_then(|registry| -> Result<_, Error> {
+                        let response = Response::builder()
+                            .status(StatusCode::OK)
+                            .body(Body::default())
+                            .context(ErrorKind::RegistryOperation(RegistryOperation::Login(
+                                registry,
+                            )))?;
+                        Ok(response)
+                    })
+            })
+            .into_future()
+            .flatten()
+            .or_else(|e| Ok(e.into_response()));
+
+        Box::new(response)
+    }
+}
+
+fn read_request(req: Request<Body>) -> impl Future<Item = RegistryCredentials, Error = Error> {
+    req.into_body().concat2().then(|b| {
+        let b = b.context(ErrorKind::MalformedRequestBody)?;
+        let credentials = serde_json::from_slice::<RegistryCredentials>(&b)
+            .context(ErrorKind::MalformedRequestBody)?;
+        if credentials.username().is_empty() {
+            return Err(Error::from(ErrorKind::MalformedRequestParameter(
+                "username",
+            )));
+        }
+        if credentials.password().is_empty() {
+            return Err(Error::from(ErrorKind::MalformedRequestParameter(
+                "password",
+            )));
+        }
+        Ok(credentials)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use edgelet_core::MakeModuleRuntime;
+    use edgelet_test_utils::crypto::TestHsm;
+    use edgelet_test_utils::module::*;
+    use management::models::ErrorResponse;
+
+    use super::*;
+    use crate::server::module::tests::Error;
+
+    fn handler(err: Option<Error>) -> LoginRegistry<TestRuntime<Error, TestSettings>> {
+        let runtime = TestRuntime::make_runtime(
+            TestSettings::new(),
+            TestProvisioningResult::new(),
+            TestHsm::default(),
+        )
+        .wait()
+        .unwrap()
+        .with_registry(TestRegistry::new(err));
+        LoginRegistry::new(runtime)
+    }
+
+    fn request(body: &str) -> Request<Body> {
+        Request::post("http://localhost/registries/myregistry.azurecr.io/login")
+            .body(body.to_string().into())
+            .unwrap()
+    }
+
+    fn parameters() -> Parameters {
+        Parameters::with_captures(vec![(
+            Some("registry".to_string()),
+            "myregistry.azurecr.io".to_string(),
+        )])
+    }
+
+    #[test]
+    fn login_success() {
+        let response = handler(None)
+            .handle(
+                request(r#"{"username":"user","password":"pass"}"#),
+                parameters(),
+            )
+            .wait()
+            .unwrap();
+
+        assert_eq!(StatusCode::OK, response.status());
+    }
+
+    #[test]
+    fn login_failed() {
+        let response = handler(Some(Error::General))
+            .handle(
+                request(r#"{"username":"user","password":"pass"}"#),
+                parameters(),
+            )
+            .wait()
+            .unwrap();
+
+        assert_eq!(StatusCode::INTERNAL_SERVER_ERROR, response.status());
+        let body = response.into_body().concat2().wait().unwrap();
+        let error: ErrorResponse = serde_json::from_slice(&body).unwrap();
+        assert_eq!(
+            "Could not log in to registry myregistry.azurecr.io\n\tcaused by: General error",
+            error.message()
+        );
+    }
+
+    #[test]
+    fn login_rejects_malformed_credentials() {
+        for body in &[
+            r#"{"username":"","password":"pass"}"#,
+            r#"{"username":"user","password":""}"#,
+            r#"{"username":"user"}"#,
+        ] {
+            let response = handler(None)
+                .handle(request(body), parameters())
+                .wait()
+                .unwrap();
+
+            assert_eq!(StatusCode::BAD_REQUEST, response.status());
+        }
+    }
+}
@@ -0,0 +1,66 @@
+// Copyright (c) Microsoft. All rights reserved.
+
+use failure::{Fail, ResultExt};
+use futures::{Future, IntoFuture};
+use hyper::{Body, Request, Response, StatusCode};
+use log::debug;
+
+use edgelet_core::{ModuleRegistry, ModuleRuntime, RegistryOperation};
+use edgelet_http::route::{Handler, Parameters};
+use edgelet_http::Error as HttpError;
+
+use crate::error::{Error, ErrorKind};
+use crate::IntoResponse;
+
+pub struct LogoutRegistry<M> {
+    runtime: M,
+}
+
+impl<M> LogoutRegistry<M> {
+    pub fn new(runtime: M) -> Self {
+        LogoutRegistry { runtime }
+    }
+}
+
+impl<M> Handler<Parameters> for LogoutRegistry<M>
+where
+    M: 'static + ModuleRuntime + Send,
+{
+    fn handle(
+        &self,
+        _req: Request<Body>,
+        params: Parameters,
+    ) -> Box<dyn Future<Item = Response<Body>, Error = HttpError> + Send> {
+        debug!("Logout Registry");
+
+        let response = params
+            .name("registry")
+            .ok_or_else(|| Error::from(ErrorKind::MissingRequiredParameter("registry")))
+            .map(|registry| {
+                let registry = registry.to_string();
+
+                self.runtime
+                    .registry()
+                    .logout(&registry)
+                    .then(|result| match result {
+                        Ok(()) => Ok(registry),
+                        Err(err) => Err(Error::from(err.context(ErrorKind::RegistryOperation(
+                            RegistryOperation::Logout(registry),
+                        )))),
+                    })
+            })
+            .into_future()
+            .flatten()
+            .and_then(|registry| {
+                Ok(Response::builder()
+                    .status(StatusCode::OK)
+                    .body(Body::default())
+                    .context(ErrorKind::RegistryOperation(RegistryOperation::Logout(
+                        registry,
+                    )))?)
+            })
+            .or_else(|e| Ok(e.into_response()));
+
+        Box::new(response)
+    }
+}
@@ -0,0 +1,6 @@
+// Copyright (c) Microsoft. All rights reserved.
+mod login;
+mod logout;
+
+pub use self::login::LoginRegistry;
+pub use self::logout::LogoutRegistry;
@@ -1,5 +1,7 @@
 // Copyright (c) Microsoft. All rights reserved.
 
+use std::convert::TryInto;
+
 use failure::ResultExt;
 use futures::Future;
 use hyper::header::{CONTENT_LENGTH, CONTENT_TYPE};
@@ -8,20 +10,36 @@ use log::debug;
 use serde::Serialize;
 use serde_json;
 
-use edgelet_core::{Module, ModuleRuntime, RuntimeOperation};
+use edgelet_core::{
+    Module, ModuleRuntime, RuntimeOperation, SystemResources as CoreSystemResources,
+};
 use edgelet_http::route::{Handler, Parameters};
 use edgelet_http::Error as HttpError;
+use management::models::*;
 
 use crate::error::{Error, ErrorKind};
 use crate::IntoResponse;
 
 pub struct GetSystemResources<M> {
     runtime: M,
+    docker_stats: bool,
 }
 
 impl<M> GetSystemResources<M> {
     pub fn new(runtime: M) -> Self {
-        GetSystemResources { runtime }
+        GetSystemResources {
+            runtime,
+            docker_stats: false,
+        }
+    }
+
+    /// Serves the raw `docker_stats` string that API versions before 2020-07-07 return
+    /// instead of the per-module `modules` list.
+    pub fn with_docker_stats(runtime: M) -> Self {
+        GetSystemResources {
+            runtime,
+            docker_stats: true,
+        }
     }
 }
 
@@ -37,17 +55,21 @@ where
     ) -> Box<dyn Future<Item = Response<Body>, Error = HttpError> + Send> {
         debug!("Get System Resources");
 
+        let docker_stats = self.docker_stats;
+
         let response = self
             .runtime
             .system_resources()
-            .then(|system_resources| -> Result<_, Error> {
+            .then(move |system_resources| -> Result<_, Error> {
                 let system_resources = system_resources.context(ErrorKind::RuntimeOperation(
                     RuntimeOperation::SystemResources,
                 ))?;
 
-                let body = serde_json::to_string(&system_resources).context(
-                    ErrorKind::RuntimeOperation(RuntimeOperation::SystemResources),
-                )?;
+                let body = core_into_body(&system_resources, docker_stats);
+
+                let body = serde_json::to_string(&body).context(ErrorKind::RuntimeOperation(
+                    RuntimeOperation::SystemResources,
+                ))?;
 
                 let response = Response::builder()
                     .status(StatusCode::OK)
@@ -64,3 +86,198 @@ where
         Box::new(response)
     }
 }
+
+fn to_i64(value: u64) -> i64 {
+    value.try_into().unwrap_or(i64::max_value())
+}
+
+#[allow(clippy::cast_possible_truncation)]
+fn core_into_body(system_resources: &CoreSystemResources, docker_stats: bool) -> SystemResources {
+    let disks = system_resources
+        .disks()
+        .iter()
+        .map(|disk| {
+            Disk::new(
+                disk.name().to_string(),
+                to_i64(disk.available_space()),
+                to_i64(disk.total_space()),
+                disk.file_system().to_string(),
+                disk.file_type().to_string(),
+            )
+        })
+        .collect();
+
+    let body = SystemResources::new(
+        to_i64(system_resources.host_uptime()),
+        to_i64(system_resources.process_uptime()),
+        system_resources.used_cpu() as f32,
+        to_i64(system_resources.used_ram()),
+        to_i64(system_resources.total_ram()),
+        disks,
+    );
+
+    if docker_stats {
+        body.with_docker_stats(
+            system_resources
+                .docker_stats()
+                .unwrap_or_default()
+                .to_string(),
+        )
+    } else {
+        let modules = system_resources
+            .modules()
+            .iter()
+            .map(|module| {
+                let mut usage = ModuleResourceUsage::new(
+                    module.name().to_string(),
+                    to_i64(module.network_rx_bytes()),
+                    to_i64(module.network_tx_bytes()),
+                    to_i64(module.block_read_bytes()),
+                    to_i64(module.block_write_bytes()),
+                );
+                if let Some(cpu_percent) = module.cpu_percent() {
+                    usage.set_cpu_percent(cpu_percent as f32);
+                }
+                if let Some(memory_usage) = module.memory_usage() {
+                    usage.set_memory_usage(to_i64(memory_usage));
+                }
+                if let Some(memory_limit) = module.memory_limit() {
+                    usage.set_memory_limit(to_i64(memory_limit));
+                }
+                if let Some(pids) = module.pids() {
+                    usage.set_pids(to_i64(pids));
+                }
+                usage
+            })
+            .collect();
+
+        body.with_modules(modules)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use edgelet_core::{MakeModuleRuntime, ModuleRuntimeState};
+    use edgelet_http::route::Parameters;
+    use edgelet_test_utils::crypto::TestHsm;
+    use edgelet_test_utils::module::*;
+    use futures::Stream;
+
+    use super::*;
+    use crate::server::module::tests::Error;
+
+    fn runtime() -> TestRuntime<Error, TestSettings> {
+        let state = ModuleRuntimeState::default();
+        let config = TestConfig::new("microsoft/test-image".to_string());
+        let module: TestModule<Error, _> =
+            TestModule::new("test-module".to_string(), config, Ok(state));
+        TestRuntime::make_runtime(
+            TestSettings::new(),
+            TestProvisioningResult::new(),
+            TestHsm::default(),
+        )
+        .wait()
+        .unwrap()
+        .with_module(Ok(module))
+    }
+
+    #[test]
+    fn system_resources_success() {
+        // arrange
+        let handler = GetSystemResources::new(runtime());
+        let request = Request::get("http://localhost/systeminfo/resources")
+            .body(Body::default())
+            .unwrap();
+
+        // act
+        let response = handler.handle(request, Parameters::new()).wait().unwrap();
+
+        // assert
+        response
+            .into_body()
+            .concat2()
+            .and_then(|b| {
+                let system_resources: SystemResources = serde_json::from_slice(&b).unwrap();
+
+                assert_eq!(595_023, system_resources.host_uptime());
+                assert_eq!(5000, system_resources.used_ram());
+                assert_eq!(8000, system_resources.total_ram());
+                assert_eq!(1, system_resources.disks().len());
+                assert_eq!(None, system_resources.docker_stats());
+
+                let modules = system_resources.modules().unwrap();
+                assert_eq!(1, modules.len());
+                assert_eq!("test-module", modules[0].name());
+                assert_eq!(Some(1.5), modules[0].cpu_percent());
+                assert_eq!(Some(1024), modules[0].memory_usage());
+                assert_eq!(None, modules[0].memory_limit());
+                assert_eq!(Some(2), modules[0].pids());
+
+                Ok(())
+            })
+            .wait()
+            .unwrap();
+    }
+
+    #[test]
+    fn system_resources_with_docker_stats() {
+        // arrange
+        let handler = GetSystemResources::with_docker_stats(runtime());
+        let request = Request::get("http://localhost/systeminfo/resources")
+            .body(Body::default())
+            .unwrap();
+
+        // act
+        let response = handler.handle(request, Parameters::new()).wait().unwrap();
+
+        // assert
+        response
+            .into_body()
+            .concat2()
+            .and_then(|b| {
+                let system_resources: SystemResources = serde_json::from_slice(&b).unwrap();
+
+                assert_eq!(Some("fake docker stats"), system_resources.docker_stats());
+                assert!(system_resources.modules().is_none());
+
+                Ok(())
+            })
+            .wait()
+            .unwrap();
+    }
+
+    #[test]
+    fn system_resources_failed() {
+        // arrange
+        let runtime = TestRuntime::make_runtime(
+            TestSettings::new(),
+            TestProvisioningResult::new(),
+            TestHsm::default(),
+        )
+        .wait()
+        .unwrap()
+        .with_module(Err(Error::General));
+        let handler = GetSystemResources::new(runtime);
+        let request = Request::get("http://localhost/systeminfo/resources")
+            .body(Body::default())
+            .unwrap();
+
+        // act
+        let response = handler.handle(request, Parameters::new()).wait().unwrap();
+
+        // assert
+        response
+            .into_body()
+            .concat2()
+            .and_then(|b| {
+                let error: ErrorResponse = serde_json::from_slice(&b).unwrap();
+                assert_eq!(
+                    "Could not query system resources\n\tcaused by: General error",
+                    error.message()
+                );
+                Ok(())
+            })
+            .wait()
+            .unwrap();
+    }
+}
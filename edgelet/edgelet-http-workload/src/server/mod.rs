@@ -8,10 +8,11 @@ mod trust_bundle;
 
 use edgelet_core::{
     Authenticator, CreateCertificate, Decrypt, Encrypt, GetTrustBundle, KeyStore, Module,
-    ModuleRuntime, ModuleRuntimeErrorReason, Policy, WorkloadConfig,
+    ModuleRuntime, ModuleRuntimeErrorReason, Policy, WorkloadConfig, WorkloadRateLimits,
 };
 use edgelet_http::authentication::Authentication;
 use edgelet_http::authorization::Authorization;
+use edgelet_http::rate_limit::{RateLimit, RateLimiter};
 use edgelet_http::route::*;
 use edgelet_http::{router, Version};
 use edgelet_http_mgmt::ListModules;
@@ -39,6 +40,7 @@ impl WorkloadService {
         hsm: H,
         runtime: &M,
         config: W,
+        rate_limits: &WorkloadRateLimits,
     ) -> impl Future<Item = Self, Error = Error>
     where
         K: KeyStore + Clone + Send + Sync + 'static,
@@ -50,13 +52,18 @@ impl WorkloadService {
         W: WorkloadConfig + Clone + Send + Sync + 'static,
         <M::AuthenticateFuture as Future>::Error: Fail,
     {
+        // Signing and issuing certificates take the HSM lock, so limit how often each module can
+        // ask for them to keep one module from starving the others.
+        let crypto = RateLimiter::new(rate_limits.crypto().clone());
+        let certificates = RateLimiter::new(rate_limits.certificates().clone());
+
         let router = router!(
             get   Version2018_06_28 runtime Policy::Anonymous => "/modules" => ListModules::new(runtime.clone()),
-            post  Version2018_06_28 runtime Policy::Caller =>    "/modules/(?P<name>[^/]+)/genid/(?P<genid>[^/]+)/sign"     => SignHandler::new(key_store.clone()),
-            post  Version2018_06_28 runtime Policy::Caller =>    "/modules/(?P<name>[^/]+)/genid/(?P<genid>[^/]+)/decrypt"  => DecryptHandler::new(hsm.clone()),
-            post  Version2018_06_28 runtime Policy::Caller =>    "/modules/(?P<name>[^/]+)/genid/(?P<genid>[^/]+)/encrypt"  => EncryptHandler::new(hsm.clone()),
-            post  Version2018_06_28 runtime Policy::Caller =>    "/modules/(?P<name>[^/]+)/certificate/identity"            => IdentityCertHandler::new(hsm.clone(), config.clone()),
-            post  Version2018_06_28 runtime Policy::Caller =>    "/modules/(?P<name>[^/]+)/genid/(?P<genid>[^/]+)/certificate/server" => ServerCertHandler::new(hsm.clone(), config),
+            post  Version2018_06_28 runtime Policy::Caller =>    "/modules/(?P<name>[^/]+)/genid/(?P<genid>[^/]+)/sign"     => RateLimit::new(SignHandler::new(key_store.clone()), crypto.clone()),
+            post  Version2018_06_28 runtime Policy::Caller =>    "/modules/(?P<name>[^/]+)/genid/(?P<genid>[^/]+)/decrypt"  => RateLimit::new(DecryptHandler::new(hsm.clone()), crypto.clone()),
+            post  Version2018_06_28 runtime Policy::Caller =>    "/modules/(?P<name>[^/]+)/genid/(?P<genid>[^/]+)/encrypt"  => RateLimit::new(EncryptHandler::new(hsm.clone()), crypto),
+            post  Version2018_06_28 runtime Policy::Caller =>    "/modules/(?P<name>[^/]+)/certificate/identity"            => RateLimit::new(IdentityCertHandler::new(hsm.clone(), config.clone()), certificates.clone()),
+            post  Version2018_06_28 runtime Policy::Caller =>    "/modules/(?P<name>[^/]+)/genid/(?P<genid>[^/]+)/certificate/server" => RateLimit::new(ServerCertHandler::new(hsm.clone(), config), certificates),
 
             get   Version2018_06_28 runtime Policy::Anonymous => "/trust-bundle" => TrustBundleHandler::new(hsm),
         );
@@ -29,7 +29,7 @@ use edgelet_core::crypto::MemoryKeyStore;
 use edgelet_core::{
     AuthId, Certificate, CertificateIssuer, CertificateProperties, CertificateType,
     CreateCertificate, MakeModuleRuntime, ModuleRuntimeErrorReason, ModuleRuntimeState,
-    ModuleStatus, WorkloadConfig, IOTEDGED_CA_ALIAS,
+    ModuleStatus, WorkloadConfig, WorkloadRateLimits, IOTEDGED_CA_ALIAS,
 };
 use edgelet_hsm::{Crypto, HsmLock};
 use edgelet_http_workload::WorkloadService;
@@ -180,9 +180,15 @@ fn create_workload_service(module_id: &str) -> (WorkloadService, Crypto) {
     };
 
     (
-        WorkloadService::new(&key_store, crypto.clone(), &runtime, config)
-            .wait()
-            .unwrap(),
+        WorkloadService::new(
+            &key_store,
+            crypto.clone(),
+            &runtime,
+            config,
+            &WorkloadRateLimits::default(),
+        )
+        .wait()
+        .unwrap(),
         crypto,
     )
 }
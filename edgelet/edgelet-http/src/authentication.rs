@@ -52,7 +52,13 @@ where
                 if let Some(name) = name {
                     req.extensions_mut().insert(ModuleId::from(name));
                 }
-                Either::A(self.runtime.authenticate(&req))
+
+                // Callers that presented a client certificate were already authenticated when
+                // their connection was accepted.
+                match req.extensions().get::<AuthId>().cloned() {
+                    Some(auth_id) => Either::B(future::ok(auth_id)),
+                    None => Either::A(self.runtime.authenticate(&req)),
+                }
             }
             (false, _) => Either::B(future::ok(AuthId::Any)),
         };
@@ -61,8 +67,13 @@ where
 
         let response = authenticate.then(move |auth_id| match auth_id {
             Ok(auth_id) => {
-                req.extensions_mut().insert(auth_id);
-                future::Either::A(inner.handle(req, params))
+                req.extensions_mut().insert(auth_id.clone());
+
+                // Also on the response, so that the caller can be logged.
+                future::Either::A(inner.handle(req, params).map(|mut response| {
+                    response.extensions_mut().insert(auth_id);
+                    response
+                }))
             }
             Err(err) => future::Either::B(future::ok(
                 Error::from(err.context(ErrorKind::Authorization)).into_response(),
@@ -144,6 +155,27 @@ mod tests {
         assert_eq!("auth = none", body);
     }
 
+    #[test]
+    fn handler_calls_inner_with_connection_auth_id_when_caller_presented_certificate() {
+        let policy = Policy::Caller;
+        let mut req = Request::default();
+        req.extensions_mut()
+            .insert(AuthId::Value("technician".into()));
+        let runtime = TestAuthenticator::error();
+        let inner = TestHandler::new();
+        let auth = Authentication::new(inner, policy, runtime);
+
+        let response = auth.handle(req, Parameters::new()).wait().unwrap();
+
+        let body = response
+            .into_body()
+            .concat2()
+            .and_then(|body| Ok(String::from_utf8(body.to_vec()).unwrap()))
+            .wait()
+            .unwrap();
+        assert_eq!("auth = technician", body);
+    }
+
     #[test]
     fn handler_responds_with_not_found_when_error() {
         let policy = Policy::Caller;
@@ -19,7 +19,7 @@ use tokio::timer::Delay;
 use edgelet_core::crypto::{
     Certificate as CryptoCertificate, CreateCertificate, KeyBytes, PrivateKey, Signature,
 };
-use edgelet_core::CertificateProperties;
+use edgelet_core::{metrics, CertificateProperties};
 use failure::ResultExt;
 
 pub use crate::error::{Error, ErrorKind};
@@ -156,6 +156,10 @@ impl<C: CreateCertificate + Clone> CertificateManager<C> {
                 .with_context(|_| ErrorKind::CertificateCreationError)?
         };
 
+        if let Ok(valid_to) = cert.get_valid_to() {
+            metrics::set_certificate_expiration(self.props.alias(), valid_to);
+        }
+
         let cert_pem = cert
             .pem()
             .with_context(|_| ErrorKind::CertificateCreationError)?;
@@ -2,6 +2,7 @@
 
 use std::collections::HashMap;
 use std::sync::Arc;
+use std::time::Duration as StdDuration;
 
 use chrono::{DateTime, Duration, Utc};
 use failure::{Fail, ResultExt};
@@ -205,17 +206,24 @@ where
                     .call(req)
                     .then(|resp| resp.context(ErrorKind::Http).map_err(Error::from))
                     .and_then(|resp| {
-                        let (http::response::Parts { status, .. }, body) = resp.into_parts();
+                        let (
+                            http::response::Parts {
+                                status, headers, ..
+                            },
+                            body,
+                        ) = resp.into_parts();
+                        let retry_after = retry_after(&headers);
                         body.concat2().then(move |res| {
                             let body = res.context(ErrorKind::Http)?;
-                            Ok((status, body))
+                            Ok((status, retry_after, body))
                         })
                     })
-                    .and_then(|(status, body)| {
+                    .and_then(|(status, retry_after, body)| {
                         if status.is_success() {
                             Ok(body)
                         } else {
-                            Err(Error::http_with_error_response(status, &*body))
+                            Err(Error::http_with_error_response(status, &*body)
+                                .with_retry_after(retry_after))
                         }
                     })
                     .and_then(|body| {
@@ -234,6 +242,16 @@ where
     }
 }
 
+/// Parses a `Retry-After` header given in seconds. The HTTP-date form isn't used by the Azure
+/// services and is ignored.
+fn retry_after(headers: &http::HeaderMap) -> Option<StdDuration> {
+    headers
+        .get(http::header::RETRY_AFTER)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.trim().parse().ok())
+        .map(StdDuration::from_secs)
+}
+
 impl<C, T> Clone for Client<C, T>
 where
     T: TokenSource + Clone,
@@ -604,4 +622,28 @@ mod tests {
             .unwrap();
         assert_eq!(result, "response");
     }
+
+    #[test]
+    fn request_error_has_retry_after() {
+        let api_version = "2018-04-10".to_string();
+        let host_name = Url::parse("http://localhost").unwrap();
+        let token_source: Option<StaticTokenSource> = None;
+
+        let handler = |_req: Request<Body>| {
+            Ok(Response::builder()
+                .status(hyper::StatusCode::TOO_MANY_REQUESTS)
+                .header(hyper::header::RETRY_AFTER, "3")
+                .body(Body::empty())
+                .unwrap())
+        };
+        let client = Client::new(handler, token_source, api_version, host_name).unwrap();
+
+        let task = client.request::<String, String>(Method::GET, "/boo", None, None, false);
+
+        let err = tokio::runtime::current_thread::Runtime::new()
+            .unwrap()
+            .block_on(task)
+            .unwrap_err();
+        assert_eq!(Some(StdDuration::from_secs(3)), err.retry_after());
+    }
 }
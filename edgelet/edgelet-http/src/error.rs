@@ -3,9 +3,10 @@
 use std::fmt::{self, Display};
 use std::net::SocketAddr;
 use std::str;
+use std::time::Duration;
 
 use failure::{Backtrace, Compat, Context, Fail};
-use hyper::header::{CONTENT_LENGTH, CONTENT_TYPE};
+use hyper::header::{CONTENT_LENGTH, CONTENT_TYPE, RETRY_AFTER};
 use hyper::{Body, Response, StatusCode, Uri};
 use serde_json::json;
 use systemd::Fd;
@@ -16,6 +17,7 @@ use crate::IntoResponse;
 #[derive(Debug)]
 pub struct Error {
     inner: Context<ErrorKind>,
+    retry_after: Option<Duration>,
 }
 
 #[derive(Debug, Fail, PartialEq)]
@@ -65,6 +67,11 @@ pub enum ErrorKind {
     #[fail(display = "Invalid API version {:?}", _0)]
     InvalidApiVersion(String),
 
+    #[fail(
+        display = "The client CA must not include the CAs of the server certificate, like the device CA and the workload CA"
+    )]
+    InvalidClientCa,
+
     #[fail(display = "Invalid URL {:?}", _0)]
     InvalidUrl(String),
 
@@ -108,6 +115,9 @@ pub enum ErrorKind {
     #[fail(display = "Token source error")]
     TokenSource,
 
+    #[fail(display = "Too many requests from {}", _0)]
+    TooManyRequests(String),
+
     #[fail(display = "Could not parse trust bundle")]
     TrustBundle,
 
@@ -150,19 +160,34 @@ impl Error {
 
         kind.into()
     }
+
+    /// How long the server asked the client to wait before retrying, from the `Retry-After`
+    /// header of an error response.
+    pub fn retry_after(&self) -> Option<Duration> {
+        self.retry_after
+    }
+
+    pub(crate) fn with_retry_after(mut self, retry_after: Option<Duration>) -> Self {
+        self.retry_after = retry_after;
+        self
+    }
 }
 
 impl From<ErrorKind> for Error {
     fn from(kind: ErrorKind) -> Self {
         Error {
             inner: Context::new(kind),
+            retry_after: None,
         }
     }
 }
 
 impl From<Context<ErrorKind>> for Error {
     fn from(inner: Context<ErrorKind>) -> Self {
-        Error { inner }
+        Error {
+            inner,
+            retry_after: None,
+        }
     }
 }
 
@@ -178,6 +203,7 @@ impl IntoResponse for Error {
         let status_code = match *self.kind() {
             ErrorKind::Authorization | ErrorKind::ModuleNotFound(_) => StatusCode::NOT_FOUND,
             ErrorKind::InvalidApiVersion(_) => StatusCode::BAD_REQUEST,
+            ErrorKind::TooManyRequests(_) => StatusCode::TOO_MANY_REQUESTS,
             _ => StatusCode::INTERNAL_SERVER_ERROR,
         };
 
@@ -186,10 +212,18 @@ impl IntoResponse for Error {
         })
         .to_string();
 
-        Response::builder()
+        let mut response = Response::builder();
+        response
             .status(status_code)
             .header(CONTENT_TYPE, "application/json")
-            .header(CONTENT_LENGTH, body.len().to_string().as_str())
+            .header(CONTENT_LENGTH, body.len().to_string().as_str());
+        if let Some(retry_after) = self.retry_after {
+            // Retry-After is in whole seconds, so round up to not invite retries that are
+            // rejected again.
+            let seconds = retry_after.as_secs() + u64::from(retry_after.subsec_nanos() > 0);
+            response.header(RETRY_AFTER, seconds.to_string().as_str());
+        }
+        response
             .body(body.into())
             .expect("response builder failure")
     }
@@ -30,10 +30,10 @@ use hyper::service::{NewService, Service};
 use hyper::{Body, Response};
 use log::{debug, error, Level};
 use native_tls::Identity;
-#[cfg(unix)]
-use native_tls::TlsAcceptor;
 use openssl::pkcs12::Pkcs12;
 use openssl::pkey::PKey;
+#[cfg(unix)]
+use openssl::ssl::{SslAcceptor, SslMethod, SslOptions, SslVerifyMode};
 use openssl::stack::Stack;
 use openssl::x509::X509;
 #[cfg(target_os = "linux")]
@@ -45,6 +45,7 @@ use url::Url;
 
 use edgelet_core::crypto::{Certificate, CreateCertificate, KeyBytes, PrivateKey};
 use edgelet_core::{Protocol, UrlExt, UNIX_SCHEME};
+use edgelet_hsm::Pkcs11Uri;
 use edgelet_utils::log_failure;
 
 pub mod authentication;
@@ -53,7 +54,9 @@ pub mod certificate_manager;
 pub mod client;
 pub mod error;
 pub mod logging;
+pub mod metrics;
 mod pid;
+pub mod rate_limit;
 pub mod route;
 mod unix;
 mod util;
@@ -63,14 +66,13 @@ pub use certificate_manager::CertificateManager;
 pub use error::{BindListenerType, Error, ErrorKind, InvalidUrlReason};
 pub use pid::Pid;
 pub use util::proxy::MaybeProxyClient;
-pub use util::UrlConnector;
+pub use util::{TlsConfig, UrlConnector};
 pub use version::{Version, API_VERSION};
 
 use crate::pid::PidService;
 use crate::util::incoming::Incoming;
 
 const HTTP_SCHEME: &str = "http";
-#[cfg(unix)]
 const HTTPS_SCHEME: &str = "https";
 #[cfg(windows)]
 const PIPE_SCHEME: &str = "npipe";
@@ -124,6 +126,15 @@ impl PemCertificate {
         Ok(PemCertificate::new(cert, key, None, None))
     }
 
+    /// The `pkcs11:` URI of the private key, if it lives on a PKCS#11 token
+    /// rather than being held in memory.
+    pub fn pkcs11_key_uri(&self) -> Option<&str> {
+        self.key
+            .as_ref()
+            .and_then(|key| std::str::from_utf8(key).ok())
+            .filter(|key| Pkcs11Uri::is_pkcs11_uri(key))
+    }
+
     pub fn get_identity(&self) -> Result<Identity, Error> {
         let mut certs = X509::stack_from_pem(&self.cert).context(ErrorKind::IdentityCertificate)?;
 
@@ -230,6 +241,7 @@ where
 
             debug!("accepted new connection ({})", addr);
             let pid = socket.pid()?;
+            let auth_id = socket.auth_id();
             let fut = new_service
                 .new_service()
                 .then(move |srv| match srv {
@@ -241,9 +253,11 @@ where
                     }
                 })
                 .and_then(move |(srv, addr)| {
-                    let service = PidService::new(pid, srv);
+                    let service = PidService::new(pid, srv).with_auth_id(auth_id);
                     protocol
                         .serve_connection(socket, service)
+                        // Allow services to take over the connection, like module exec does.
+                        .with_upgrades()
                         .then(move |result| match result {
                             Ok(_) => Ok(()),
                             Err(err) => {
@@ -338,30 +352,10 @@ impl HyperExt for Http {
                         )
                     })?;
 
-                let cert = tls_params
+                let params = tls_params
                     .as_ref()
-                    .map(|params| params.cert_manager.get_pkcs12_certificate())
                     .ok_or(ErrorKind::CertificateCreationError)?;
-
-                let cert = cert.context(ErrorKind::TlsBootstrapError)?;
-
-                let cert_identity = Identity::from_pkcs12(&cert, "")
-                    .context(ErrorKind::TlsIdentityCreationError)?;
-
-                let min_protocol_version =
-                    tls_params
-                        .as_ref()
-                        .map(|params| match params.min_protocol_version {
-                            Protocol::Tls10 => native_tls::Protocol::Tlsv10,
-                            Protocol::Tls11 => native_tls::Protocol::Tlsv11,
-                            Protocol::Tls12 => native_tls::Protocol::Tlsv12,
-                        });
-
-                let tls_acceptor = TlsAcceptor::builder(cert_identity)
-                    .min_protocol_version(min_protocol_version)
-                    .build()
-                    .context(ErrorKind::TlsBootstrapError)?;
-                let tls_acceptor = tokio_tls::TlsAcceptor::from(tls_acceptor);
+                let tls_acceptor = params.tls_acceptor()?;
 
                 let listener = TcpListener::bind(&addr)
                     .with_context(|_| ErrorKind::BindListener(BindListenerType::Address(addr)))?;
@@ -441,6 +435,7 @@ where
 {
     cert_manager: &'a CertificateManager<C>,
     min_protocol_version: Protocol,
+    client_ca: Option<PemCertificate>,
 }
 
 impl<'a, C> TlsAcceptorParams<'a, C>
@@ -451,6 +446,82 @@ where
         Self {
             cert_manager,
             min_protocol_version,
+            client_ca: None,
+        }
+    }
+
+    /// Requires clients to present a certificate issued by one of the CAs in `client_ca`.
+    /// Requests on such connections are authenticated as the module named by the common
+    /// name of the certificate, if it has the client authentication extended key usage.
+    /// `client_ca` must not include any CA of the server certificate.
+    pub fn with_client_ca(mut self, client_ca: PemCertificate) -> Self {
+        self.client_ca = Some(client_ca);
+        self
+    }
+
+    #[cfg(unix)]
+    fn tls_acceptor(&self) -> Result<SslAcceptor, Error> {
+        let cert = self
+            .cert_manager
+            .get_pkcs12_certificate()
+            .context(ErrorKind::TlsBootstrapError)?;
+        let identity = Pkcs12::from_der(&cert)
+            .and_then(|pkcs12| pkcs12.parse(""))
+            .context(ErrorKind::TlsIdentityCreationError)?;
+
+        let mut builder = SslAcceptor::mozilla_intermediate(SslMethod::tls())
+            .context(ErrorKind::TlsBootstrapError)?;
+        builder
+            .set_private_key(&identity.pkey)
+            .context(ErrorKind::TlsIdentityCreationError)?;
+        builder
+            .set_certificate(&identity.cert)
+            .context(ErrorKind::TlsIdentityCreationError)?;
+        let chain: Vec<X509> = identity.chain.into_iter().flatten().collect();
+        for cert in &chain {
+            builder
+                .add_extra_chain_cert(cert.clone())
+                .context(ErrorKind::TlsIdentityCreationError)?;
         }
+
+        // Set the protocol options explicitly, so the minimum version doesn't depend on what
+        // the acceptor profile happens to disable.
+        builder.clear_options(SslOptions::NO_TLSV1 | SslOptions::NO_TLSV1_1);
+        builder.set_options(match self.min_protocol_version {
+            Protocol::Tls10 => SslOptions::empty(),
+            Protocol::Tls11 => SslOptions::NO_TLSV1,
+            Protocol::Tls12 => SslOptions::NO_TLSV1 | SslOptions::NO_TLSV1_1,
+        });
+
+        if let Some(client_ca) = &self.client_ca {
+            let certs = X509::stack_from_pem(client_ca.get_certificate())
+                .context(ErrorKind::TrustBundle)
+                .context(ErrorKind::TlsBootstrapError)?;
+
+            // Modules get certificates issued by the workload CA, so trusting it or the CAs above
+            // it would let any module call in as any other module.
+            let chain_der = chain
+                .iter()
+                .map(|cert| cert.to_der())
+                .collect::<Result<Vec<_>, _>>()
+                .context(ErrorKind::TlsIdentityCreationError)?;
+            for cert in &certs {
+                let der = cert.to_der().context(ErrorKind::TrustBundle)?;
+                if chain_der.contains(&der) {
+                    return Err(Error::from(ErrorKind::InvalidClientCa));
+                }
+            }
+
+            for cert in certs {
+                builder
+                    .cert_store_mut()
+                    .add_cert(cert)
+                    .context(ErrorKind::TrustBundle)
+                    .context(ErrorKind::TlsBootstrapError)?;
+            }
+            builder.set_verify(SslVerifyMode::PEER | SslVerifyMode::FAIL_IF_NO_PEER_CERT);
+        }
+
+        Ok(builder.build())
     }
 }
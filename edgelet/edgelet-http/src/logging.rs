@@ -1,6 +1,8 @@
 // Copyright (c) Microsoft. All rights reserved.
 #![allow(deprecated)]
 
+use std::time::Instant;
+
 use chrono::prelude::*;
 use futures::prelude::*;
 use hyper::header::{CONTENT_LENGTH, USER_AGENT};
@@ -8,7 +10,12 @@ use hyper::service::{NewService, Service};
 use hyper::Request;
 use log::info;
 
-use edgelet_core::AuthId;
+use edgelet_core::{metrics, AuthId};
+
+use crate::route::MatchedRoute;
+
+/// The route label of requests that matched no route.
+const OTHER_ROUTE: &str = "other";
 
 #[derive(Clone)]
 pub struct LoggingService<T> {
@@ -44,20 +51,38 @@ where
             |q| format!("{}?{}", req.uri().path(), q),
         );
         let request = format!("{} {} {:?}", req.method(), uri, req.version());
+        let method = req.method().to_string();
+        let start = Instant::now();
         let user_agent = req
             .headers()
             .get(USER_AGENT)
             .and_then(|ua| ua.to_str().ok())
             .unwrap_or_else(|| "-")
             .to_string();
-        let auth_id = req
-            .extensions()
-            .get::<AuthId>()
-            .map_or_else(|| "-".to_string(), ToString::to_string);
+        let connection_auth_id = req.extensions().get::<AuthId>().cloned();
 
         let inner = self.inner.call(req);
 
         Box::new(inner.map(move |response| {
+            // Handlers of routes add the route and the authenticated caller to their responses.
+            // Requests that matched no route are all counted as one, so that arbitrary paths
+            // don't each get their own metric.
+            let route = response
+                .extensions()
+                .get::<MatchedRoute>()
+                .map_or(OTHER_ROUTE, |route| &route.0)
+                .to_string();
+            let auth_id = response
+                .extensions()
+                .get::<AuthId>()
+                .cloned()
+                .or(connection_auth_id);
+            let module = match &auth_id {
+                Some(AuthId::Value(module)) => module.to_string(),
+                _ => String::new(),
+            };
+            let auth_id = auth_id.map_or_else(|| "-".to_string(), |auth_id| auth_id.to_string());
+
             let body_length = response
                 .headers()
                 .get(CONTENT_LENGTH)
@@ -75,6 +100,15 @@ where
                 auth_id,
             );
 
+            metrics::observe_http_request(
+                &label,
+                &route,
+                &method,
+                response.status().as_u16(),
+                &module,
+                start.elapsed(),
+            );
+
             response
         }))
     }
@@ -103,3 +137,74 @@ where
         )
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use futures::future;
+    use hyper::service::service_fn;
+    use hyper::{Body, Error as HyperError, Response, StatusCode};
+
+    use super::*;
+
+    fn observed(api: &str) -> Vec<String> {
+        String::from_utf8(metrics::encode().unwrap())
+            .unwrap()
+            .lines()
+            .filter(|line| {
+                line.starts_with("edgelet_http_requests_total{")
+                    && line.contains(&format!("api=\"{}\"", api))
+            })
+            .map(ToString::to_string)
+            .collect()
+    }
+
+    #[test]
+    fn observes_matched_route_and_authenticated_module() {
+        let mut service = LoggingService::new(
+            "logging-matched".to_string(),
+            service_fn(|_| {
+                let mut response = Response::new(Body::empty());
+                response
+                    .extensions_mut()
+                    .insert(MatchedRoute("/modules/{name}/logs".to_string()));
+                response
+                    .extensions_mut()
+                    .insert(AuthId::Value("tempSensor".into()));
+                future::ok::<_, HyperError>(response)
+            }),
+        );
+
+        let request = Request::get("/modules/edgeHub/logs")
+            .body(Body::empty())
+            .unwrap();
+        service.call(request).wait().unwrap();
+
+        let observed = observed("logging-matched");
+        assert_eq!(1, observed.len());
+        assert!(observed[0].contains("route=\"/modules/{name}/logs\""));
+        assert!(observed[0].contains("module=\"tempSensor\""));
+    }
+
+    #[test]
+    fn observes_unmatched_requests_as_other() {
+        let mut service = LoggingService::new(
+            "logging-unmatched".to_string(),
+            service_fn(|_| {
+                let mut response = Response::new(Body::empty());
+                *response.status_mut() = StatusCode::NOT_FOUND;
+                future::ok::<_, HyperError>(response)
+            }),
+        );
+
+        for path in &["/modules/a/unknown", "/modules/b/unknown"] {
+            let request = Request::get(*path).body(Body::empty()).unwrap();
+            service.call(request).wait().unwrap();
+        }
+
+        let observed = observed("logging-unmatched");
+        assert_eq!(1, observed.len());
+        assert!(observed[0].contains("route=\"other\""));
+        assert!(observed[0].contains("module=\"\""));
+        assert!(observed[0].ends_with(" 2"));
+    }
+}
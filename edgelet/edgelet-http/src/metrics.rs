@@ -0,0 +1,89 @@
+// Copyright (c) Microsoft. All rights reserved.
+
+use futures::future::{self, FutureResult};
+use hyper::header::CONTENT_TYPE;
+use hyper::service::{NewService, Service};
+use hyper::{Body, Error as HyperError, Method, Request, Response, StatusCode};
+use log::Level;
+
+use edgelet_core::metrics;
+use edgelet_utils::log_failure;
+
+/// Serves the metrics recorded in `edgelet_core::metrics` at `/metrics`, in the Prometheus text
+/// format.
+#[derive(Clone, Default)]
+pub struct MetricsService;
+
+impl Service for MetricsService {
+    type ReqBody = Body;
+    type ResBody = Body;
+    type Error = HyperError;
+    type Future = FutureResult<Response<Self::ResBody>, Self::Error>;
+
+    fn call(&mut self, req: Request<Self::ReqBody>) -> Self::Future {
+        let response = if req.method() != Method::GET || req.uri().path() != "/metrics" {
+            Response::builder()
+                .status(StatusCode::NOT_FOUND)
+                .body(Body::empty())
+        } else {
+            match metrics::encode() {
+                Ok(body) => Response::builder()
+                    .status(StatusCode::OK)
+                    .header(CONTENT_TYPE, metrics::CONTENT_TYPE)
+                    .body(body.into()),
+                Err(err) => {
+                    log_failure(Level::Warn, &err);
+                    Response::builder()
+                        .status(StatusCode::INTERNAL_SERVER_ERROR)
+                        .body(Body::empty())
+                }
+            }
+        };
+
+        future::ok(response.expect("response builder failure"))
+    }
+}
+
+impl NewService for MetricsService {
+    type ReqBody = <Self::Service as Service>::ReqBody;
+    type ResBody = <Self::Service as Service>::ResBody;
+    type Error = <Self::Service as Service>::Error;
+    type Service = Self;
+    type Future = FutureResult<Self::Service, Self::InitError>;
+    type InitError = <Self::Service as Service>::Error;
+
+    fn new_service(&self) -> Self::Future {
+        future::ok(self.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures::{Future, Stream};
+
+    use super::*;
+
+    #[test]
+    fn serves_metrics() {
+        metrics::observe_provisioning_failure();
+
+        let request = Request::get("/metrics").body(Body::empty()).unwrap();
+        let response = MetricsService.call(request).wait().unwrap();
+        assert_eq!(StatusCode::OK, response.status());
+        assert_eq!(
+            metrics::CONTENT_TYPE,
+            response.headers().get(CONTENT_TYPE).unwrap()
+        );
+
+        let body = response.into_body().concat2().wait().unwrap();
+        let body = String::from_utf8(body.to_vec()).unwrap();
+        assert!(body.contains("edgelet_provisioning_failures_total"));
+    }
+
+    #[test]
+    fn other_paths_are_not_found() {
+        let request = Request::get("/modules").body(Body::empty()).unwrap();
+        let response = MetricsService.call(request).wait().unwrap();
+        assert_eq!(StatusCode::NOT_FOUND, response.status());
+    }
+}
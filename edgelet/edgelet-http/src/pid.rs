@@ -10,6 +10,8 @@ use tokio_uds::UnixStream;
 #[cfg(windows)]
 use tokio_uds_windows::UnixStream;
 
+use edgelet_core::AuthId;
+
 #[derive(Clone, Copy, Debug, serde_derive::Deserialize, serde_derive::Serialize)]
 pub enum Pid {
     None,
@@ -69,12 +71,24 @@ mod tests {
 #[derive(Clone)]
 pub struct PidService<T> {
     pid: Pid,
+    auth_id: Option<AuthId>,
     inner: T,
 }
 
 impl<T> PidService<T> {
     pub fn new(pid: Pid, inner: T) -> Self {
-        PidService { pid, inner }
+        PidService {
+            pid,
+            auth_id: None,
+            inner,
+        }
+    }
+
+    /// Marks requests as coming from a caller that was already authenticated when the
+    /// connection was established, for example with a TLS client certificate.
+    pub fn with_auth_id(mut self, auth_id: Option<AuthId>) -> Self {
+        self.auth_id = auth_id;
+        self
     }
 }
 
@@ -92,6 +106,9 @@ where
     fn call(&mut self, req: Request<Self::ReqBody>) -> Self::Future {
         let mut req = req;
         req.extensions_mut().insert(self.pid);
+        if let Some(auth_id) = &self.auth_id {
+            req.extensions_mut().insert(auth_id.clone());
+        }
         self.inner.call(req)
     }
 }
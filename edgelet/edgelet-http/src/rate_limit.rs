@@ -0,0 +1,226 @@
+// Copyright (c) Microsoft. All rights reserved.
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use futures::{future, Future};
+use hyper::{Body, Request, Response};
+
+use edgelet_core::{AuthId, RateLimit as RateLimitSettings};
+
+use crate::route::{Handler, Parameters};
+use crate::{Error, ErrorKind, IntoResponse};
+
+/// The longest a caller is asked to wait before retrying.
+const MAX_RETRY_AFTER_SECS: f64 = 60.0;
+
+/// Tracks, for each caller of a group of routes, a token bucket that is refilled at
+/// `requests_per_second` up to `burst` tokens, and the number of the caller's requests that are
+/// in progress. Clones share the same state, so one limiter can be used for several routes.
+#[derive(Clone)]
+pub struct RateLimiter {
+    settings: RateLimitSettings,
+    callers: Arc<Mutex<HashMap<String, Caller>>>,
+}
+
+struct Caller {
+    tokens: f64,
+    updated: Instant,
+    in_flight: u32,
+}
+
+impl RateLimiter {
+    pub fn new(settings: RateLimitSettings) -> Self {
+        RateLimiter {
+            settings,
+            callers: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    /// Takes a token and a concurrency slot for `caller`, or returns how long the caller should
+    /// wait before trying again.
+    fn acquire(&self, caller: &str, now: Instant) -> Result<Permit, Duration> {
+        let mut callers = self.callers.lock().expect("rate limiter lock poisoned");
+
+        let burst = f64::from(self.settings.burst());
+        let rate = self.settings.requests_per_second();
+        let state = callers.entry(caller.to_string()).or_insert_with(|| Caller {
+            tokens: burst,
+            updated: now,
+            in_flight: 0,
+        });
+
+        if now > state.updated {
+            let elapsed = now.duration_since(state.updated).as_secs_f64();
+            state.tokens = (state.tokens + elapsed * rate).min(burst);
+            state.updated = now;
+        }
+
+        if state.in_flight >= self.settings.max_concurrent_requests() {
+            return Err(Duration::from_secs(1));
+        }
+
+        if state.tokens < 1.0 {
+            let wait = ((1.0 - state.tokens) / rate).min(MAX_RETRY_AFTER_SECS);
+            return Err(Duration::from_secs_f64(wait));
+        }
+
+        state.tokens -= 1.0;
+        state.in_flight += 1;
+
+        Ok(Permit {
+            callers: self.callers.clone(),
+            caller: caller.to_string(),
+        })
+    }
+}
+
+/// A request in progress. Frees the caller's concurrency slot when dropped.
+struct Permit {
+    callers: Arc<Mutex<HashMap<String, Caller>>>,
+    caller: String,
+}
+
+impl Drop for Permit {
+    fn drop(&mut self) {
+        let mut callers = self.callers.lock().expect("rate limiter lock poisoned");
+        if let Some(state) = callers.get_mut(&self.caller) {
+            state.in_flight -= 1;
+        }
+    }
+}
+
+/// Rejects requests with `429 Too Many Requests` when the authenticated caller has exceeded the
+/// limits of `limiter`.
+pub struct RateLimit<H> {
+    limiter: RateLimiter,
+    inner: Arc<H>,
+}
+
+impl<H> RateLimit<H> {
+    pub fn new(inner: H, limiter: RateLimiter) -> Self {
+        RateLimit {
+            limiter,
+            inner: Arc::new(inner),
+        }
+    }
+}
+
+impl<H> Handler<Parameters> for RateLimit<H>
+where
+    H: Handler<Parameters> + Sync,
+{
+    fn handle(
+        &self,
+        req: Request<Body>,
+        params: Parameters,
+    ) -> Box<dyn Future<Item = Response<Body>, Error = Error> + Send> {
+        let caller = req
+            .extensions()
+            .get::<AuthId>()
+            .cloned()
+            .unwrap_or_else(|| AuthId::None)
+            .to_string();
+
+        match self.limiter.acquire(&caller, Instant::now()) {
+            Ok(permit) => Box::new(self.inner.handle(req, params).then(move |response| {
+                drop(permit);
+                response
+            })),
+            Err(retry_after) => Box::new(future::ok(
+                Error::from(ErrorKind::TooManyRequests(caller))
+                    .with_retry_after(Some(retry_after))
+                    .into_response(),
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use hyper::header::RETRY_AFTER;
+    use hyper::StatusCode;
+
+    use super::*;
+
+    fn request(caller: &str) -> Request<Body> {
+        let mut request = Request::default();
+        request
+            .extensions_mut()
+            .insert(AuthId::Value(caller.into()));
+        request
+    }
+
+    #[test]
+    fn callers_are_limited_once_burst_is_spent() {
+        let limiter = RateLimiter::new(RateLimitSettings::new(1.0, 2, 10));
+        let now = Instant::now();
+
+        let _first = limiter.acquire("m1", now).unwrap();
+        let _second = limiter.acquire("m1", now).unwrap();
+        assert_eq!(
+            Duration::from_secs(1),
+            limiter.acquire("m1", now).err().unwrap()
+        );
+
+        // Other callers have their own bucket.
+        let _other = limiter.acquire("m2", now).unwrap();
+
+        // The bucket is refilled over time.
+        let later = now + Duration::from_millis(500);
+        assert_eq!(
+            Duration::from_millis(500),
+            limiter.acquire("m1", later).err().unwrap()
+        );
+        let _third = limiter.acquire("m1", now + Duration::from_secs(1)).unwrap();
+    }
+
+    #[test]
+    fn callers_are_limited_to_max_concurrent_requests() {
+        let limiter = RateLimiter::new(RateLimitSettings::new(100.0, 100, 1));
+        let now = Instant::now();
+
+        let first = limiter.acquire("m1", now).unwrap();
+        assert!(limiter.acquire("m1", now).is_err());
+
+        drop(first);
+        assert!(limiter.acquire("m1", now).is_ok());
+    }
+
+    #[test]
+    fn handler_responds_too_many_requests_with_retry_after() {
+        let limiter = RateLimiter::new(RateLimitSettings::new(0.5, 1, 10));
+        let handler = RateLimit::new(TestHandler, limiter);
+
+        let response = handler
+            .handle(request("m1"), Parameters::new())
+            .wait()
+            .unwrap();
+        assert_eq!(StatusCode::OK, response.status());
+
+        let response = handler
+            .handle(request("m1"), Parameters::new())
+            .wait()
+            .unwrap();
+        assert_eq!(StatusCode::TOO_MANY_REQUESTS, response.status());
+        let retry_after: u64 = response.headers()[RETRY_AFTER]
+            .to_str()
+            .unwrap()
+            .parse()
+            .unwrap();
+        assert!(retry_after >= 1 && retry_after <= 2);
+    }
+
+    struct TestHandler;
+
+    impl Handler<Parameters> for TestHandler {
+        fn handle(
+            &self,
+            _req: Request<Body>,
+            _params: Parameters,
+        ) -> Box<dyn Future<Item = Response<Body>, Error = Error> + Send> {
+            Box::new(future::ok(Response::new(Body::empty())))
+        }
+    }
+}
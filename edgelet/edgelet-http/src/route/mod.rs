@@ -46,6 +46,11 @@ where
 
 pub type HandlerParamsPair<'a, P> = (&'a dyn Handler<P>, P);
 
+/// The route a request matched, like `/modules/{name}/logs`, with the names of the captures in
+/// place of their patterns. Routers add it to the extensions of the responses of their handlers.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct MatchedRoute(pub(crate) String);
+
 pub trait Recognizer {
     type Parameters: 'static;
 
@@ -4,11 +4,13 @@ use std::borrow::Cow;
 use std::collections::HashMap;
 use std::default::Default;
 
-use hyper::{Method, StatusCode};
+use futures::Future;
+use hyper::{Body, Method, Request, Response, StatusCode};
 use percent_encoding::percent_decode;
-use regex::Regex;
+use regex::{Captures, Regex};
 
-use super::{Builder, Handler, HandlerParamsPair, Recognizer};
+use super::{Builder, Handler, HandlerParamsPair, MatchedRoute, Recognizer};
+use crate::error::Error;
 use crate::version::Version;
 
 pub trait IntoCaptures {
@@ -83,9 +85,10 @@ impl Builder for RegexRoutesBuilder {
         S: AsRef<str>,
         H: Handler<<Self::Recognizer as Recognizer>::Parameters> + Sync,
     {
+        let route = MatchedRoute(route_label(pattern.as_ref()));
         let pattern = normalize_pattern(pattern.as_ref());
         let pattern = Regex::new(&pattern).expect("failed to compile regex");
-        let handler = Box::new(handler);
+        let handler = Box::new(RouteHandler { route, handler });
         self.routes
             .entry(method)
             .or_insert_with(Vec::new)
@@ -104,6 +107,29 @@ impl Builder for RegexRoutesBuilder {
     }
 }
 
+/// Adds the route to the responses of the handler.
+struct RouteHandler<H> {
+    route: MatchedRoute,
+    handler: H,
+}
+
+impl<H> Handler<Parameters> for RouteHandler<H>
+where
+    H: Handler<Parameters>,
+{
+    fn handle(
+        &self,
+        req: Request<Body>,
+        params: Parameters,
+    ) -> Box<dyn Future<Item = Response<Body>, Error = Error> + Send> {
+        let route = self.route.clone();
+        Box::new(self.handler.handle(req, params).map(|mut response| {
+            response.extensions_mut().insert(route);
+            response
+        }))
+    }
+}
+
 pub struct RegexRecognizer {
     routes: HashMap<Method, Vec<RegexRoute>>,
 }
@@ -159,6 +185,17 @@ fn normalize_pattern(pattern: &str) -> Cow<'_, str> {
     }
 }
 
+/// Replaces the named captures of a pattern with their names, so that it can be used as a metric
+/// label.
+fn route_label(pattern: &str) -> String {
+    let named_capture = Regex::new(r"\(\?P<(\w+)>[^)]*\)").expect("hard-coded regex is valid");
+    named_capture
+        .replace_all(pattern, |captures: &Captures<'_>| {
+            format!("{{{}}}", &captures[1])
+        })
+        .into_owned()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -190,4 +227,13 @@ mod tests {
         let params = match_route(&pattern, "/test/mi%2fke").expect("failed to get params");
         assert_eq!("mi/ke", params.name("name").unwrap());
     }
+
+    #[test]
+    fn route_label_names_captures() {
+        assert_eq!(
+            "/modules/{name}/genid/{genid}/sign",
+            route_label("/modules/(?P<name>[^/]+)/genid/(?P<genid>[^/]+)/sign")
+        );
+        assert_eq!("/modules", route_label("/modules"));
+    }
 }
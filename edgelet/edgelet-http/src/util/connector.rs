@@ -9,9 +9,9 @@
 //! hyper's `Service` trait so it can be used directly with its `Client` type.
 //! The `Service` trait's `Response` associated type is a struct named
 //! `StreamSelector` which is also defined in this module. `StreamSelector` is
-//! an enumeration that switches between a `TcpStream` or a `UnixStream` (or
-//! other kinds of streams in the future when we support more protocols) for
-//! HTTP and Unix sockets respectively.
+//! an enumeration that switches between a `TcpStream`, a TLS stream or a
+//! `UnixStream` (or other kinds of streams in the future when we support more
+//! protocols) for HTTP, HTTPS and Unix sockets respectively.
 
 use std::io;
 
@@ -26,6 +26,10 @@ use hyper_named_pipe::{PipeConnector, Uri as PipeUri};
 use hyperlocal::{UnixConnector, Uri as HyperlocalUri};
 #[cfg(windows)]
 use hyperlocal_windows::{UnixConnector, Uri as HyperlocalUri};
+use openssl::pkey::PKey;
+use openssl::ssl::{SslConnector, SslMethod};
+use openssl::x509::X509;
+use tokio_openssl::SslConnectorExt;
 use url::{ParseError, Url};
 
 use edgelet_core::UrlExt;
@@ -34,11 +38,91 @@ use crate::error::{Error, ErrorKind, InvalidUrlReason};
 use crate::util::{socket_file_exists, StreamSelector};
 #[cfg(windows)]
 use crate::PIPE_SCHEME;
-use crate::{HTTP_SCHEME, UNIX_SCHEME};
+use crate::{PemCertificate, HTTPS_SCHEME, HTTP_SCHEME, UNIX_SCHEME};
+
+/// TLS settings used when connecting to an `https` URL. Without a trust bundle the system's
+/// trust roots are used, and without an identity certificate no client certificate is sent.
+#[derive(Clone, Debug, Default)]
+pub struct TlsConfig {
+    trust_bundle: Option<PemCertificate>,
+    identity_certificate: Option<PemCertificate>,
+}
+
+impl TlsConfig {
+    pub fn new() -> Self {
+        TlsConfig::default()
+    }
+
+    pub fn with_trust_bundle(mut self, trust_bundle: PemCertificate) -> Self {
+        self.trust_bundle = Some(trust_bundle);
+        self
+    }
+
+    pub fn with_identity_certificate(mut self, identity_certificate: PemCertificate) -> Self {
+        self.identity_certificate = Some(identity_certificate);
+        self
+    }
+
+    fn connector(&self) -> Result<SslConnector, Error> {
+        let mut builder =
+            SslConnector::builder(SslMethod::tls()).context(ErrorKind::Initialization)?;
+
+        if let Some(bundle) = &self.trust_bundle {
+            let certs = X509::stack_from_pem(bundle.get_certificate())
+                .context(ErrorKind::TrustBundle)
+                .context(ErrorKind::Initialization)?;
+            for cert in certs {
+                builder
+                    .cert_store_mut()
+                    .add_cert(cert)
+                    .context(ErrorKind::TrustBundle)
+                    .context(ErrorKind::Initialization)?;
+            }
+        }
+
+        if let Some(id) = &self.identity_certificate {
+            // the first cert is the identity cert and the rest are its CA chain
+            let mut certs = X509::stack_from_pem(id.get_certificate())
+                .context(ErrorKind::IdentityCertificate)
+                .context(ErrorKind::Initialization)?
+                .into_iter();
+            let identity_cert = certs
+                .next()
+                .ok_or(ErrorKind::IdentityCertificate)
+                .context(ErrorKind::Initialization)?;
+            builder
+                .set_certificate(&identity_cert)
+                .context(ErrorKind::IdentityCertificate)
+                .context(ErrorKind::Initialization)?;
+            for cert in certs {
+                builder
+                    .add_extra_chain_cert(cert)
+                    .context(ErrorKind::IdentityCertificate)
+                    .context(ErrorKind::Initialization)?;
+            }
+
+            let key = id
+                .key
+                .as_ref()
+                .ok_or(ErrorKind::IdentityPrivateKey)
+                .context(ErrorKind::Initialization)?;
+            let key = PKey::private_key_from_pem(key)
+                .with_context(|err| ErrorKind::IdentityPrivateKeyRead(err.to_string()))
+                .context(ErrorKind::Initialization)?;
+            builder
+                .set_private_key(&key)
+                .context(ErrorKind::IdentityPrivateKey)
+                .context(ErrorKind::Initialization)?;
+        }
+
+        Ok(builder.build())
+    }
+}
 
 #[derive(Clone)]
 pub enum UrlConnector {
     Http(HttpConnector),
+    Https(HttpConnector, SslConnector),
     #[cfg(windows)]
     Pipe(PipeConnector),
     Unix(UnixConnector),
@@ -46,6 +130,11 @@ pub enum UrlConnector {
 
 impl UrlConnector {
     pub fn new(url: &Url) -> Result<Self, Error> {
+        UrlConnector::with_tls_config(url, &TlsConfig::default())
+    }
+
+    /// Like `new`, but uses `tls_config` to set up connections to `https` URLs.
+    pub fn with_tls_config(url: &Url, tls_config: &TlsConfig) -> Result<Self, Error> {
         match url.scheme() {
             #[cfg(windows)]
             PIPE_SCHEME => Ok(UrlConnector::Pipe(PipeConnector)),
@@ -71,6 +160,12 @@ impl UrlConnector {
                 //       this time.
                 Ok(UrlConnector::Http(HttpConnector::new(4)))
             }
+
+            HTTPS_SCHEME => {
+                let mut http = HttpConnector::new(4);
+                http.enforce_http(false);
+                Ok(UrlConnector::Https(http, tls_config.connector()?))
+            }
             _ => Err(ErrorKind::InvalidUrlWithReason(
                 url.to_string(),
                 InvalidUrlReason::InvalidScheme,
@@ -90,7 +185,7 @@ impl UrlConnector {
                 })?
                 .into()),
             UNIX_SCHEME => Ok(HyperlocalUri::new(base_path, &path).into()),
-            HTTP_SCHEME => Ok(Url::parse(base_path)
+            HTTP_SCHEME | HTTPS_SCHEME => Ok(Url::parse(base_path)
                 .and_then(|base| base.join(path))
                 .and_then(|url| url.as_str().parse().map_err(|_| ParseError::IdnaError))
                 .with_context(|_| ErrorKind::MalformedUrl {
@@ -112,6 +207,7 @@ impl std::fmt::Debug for UrlConnector {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             UrlConnector::Http(_) => f.debug_struct("Http").finish(),
+            UrlConnector::Https(..) => f.debug_struct("Https").finish(),
             #[cfg(windows)]
             UrlConnector::Pipe(_) => f.debug_struct("Pipe").finish(),
             UrlConnector::Unix(_) => f.debug_struct("UnixConnector").finish(),
@@ -129,6 +225,8 @@ impl Connect for UrlConnector {
         match (self, dst.scheme()) {
             (UrlConnector::Http(_), HTTP_SCHEME) => (),
 
+            (UrlConnector::Https(..), HTTPS_SCHEME) => (),
+
             #[cfg(windows)]
             (UrlConnector::Pipe(_), PIPE_SCHEME) => (),
 
@@ -149,6 +247,27 @@ impl Connect for UrlConnector {
                 })) as Self::Future
             }
 
+            UrlConnector::Https(connector, tls_connector) => {
+                let host = dst.host().to_string();
+                let tls_connector = tls_connector.clone();
+                Box::new(
+                    connector
+                        .connect(dst)
+                        .and_then(move |(tcp_stream, connected)| {
+                            tls_connector
+                                .connect_async(&host, tcp_stream)
+                                .map(move |tls_stream| {
+                                    #[cfg(windows)]
+                                    let tls_stream = Box::new(tls_stream);
+                                    (StreamSelector::Tls(tls_stream), connected)
+                                })
+                                .map_err(|err| {
+                                    io::Error::new(io::ErrorKind::Other, err.to_string())
+                                })
+                        }),
+                ) as Self::Future
+            }
+
             #[cfg(windows)]
             UrlConnector::Pipe(connector) => {
                 Box::new(connector.connect(dst).and_then(|(pipe_stream, connected)| {
@@ -207,6 +326,30 @@ mod tests {
         let _connector = UrlConnector::new(&Url::parse("http://localhost:2375").unwrap()).unwrap();
     }
 
+    #[test]
+    fn create_https_succeeds() {
+        let _connector =
+            UrlConnector::new(&Url::parse("https://localhost:15580").unwrap()).unwrap();
+    }
+
+    #[test]
+    fn create_https_with_invalid_trust_bundle_fails() {
+        let tls_config = TlsConfig::new().with_trust_bundle(PemCertificate::new(
+            b"-----BEGIN CERTIFICATE-----\nbm90IGEgY2VydGlmaWNhdGU=\n-----END CERTIFICATE-----\n"
+                .to_vec(),
+            None,
+            None,
+            None,
+        ));
+        let err = UrlConnector::with_tls_config(
+            &Url::parse("https://localhost:15580").unwrap(),
+            &tls_config,
+        )
+        .unwrap_err();
+        assert!(failure::Fail::iter_chain(&err)
+            .any(|err| err.to_string().contains("Could not parse trust bundle")));
+    }
+
     #[cfg(windows)]
     #[test]
     fn create_pipe_succeeds() {
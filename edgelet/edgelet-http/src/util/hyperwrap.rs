@@ -4,14 +4,18 @@ use failure::ResultExt;
 use futures::future;
 use hyper::client::HttpConnector;
 use hyper::{Body, Client as HyperClient, Error as HyperError, Request, Response, StatusCode, Uri};
+use hyper_openssl::HttpsConnector as OpenSslHttpsConnector;
 use hyper_proxy::{Intercept, Proxy, ProxyConnector};
 use hyper_tls::HttpsConnector;
 use native_tls::{Certificate as TlsCertificate, TlsConnector};
+use openssl::ssl::{SslConnector, SslMethod};
 use openssl::x509::X509;
 use typed_headers::Credentials;
 use url::percent_encoding::percent_decode;
 use url::Url;
 
+use edgelet_hsm::pkcs11::{load_private_key, Pkcs11Uri};
+
 use crate::client::ClientImpl;
 use crate::error::{Error, ErrorKind, InvalidUrlReason};
 use crate::PemCertificate;
@@ -51,6 +55,14 @@ impl Config {
         if self.null {
             Ok(Client::Null)
         } else {
+            if let Some(key_uri) = self
+                .identity_certificate
+                .as_ref()
+                .and_then(PemCertificate::pkcs11_key_uri)
+            {
+                return self.build_openssl(key_uri);
+            }
+
             let mut builder = TlsConnector::builder();
             if let Some(bundle) = &self.trust_bundle {
                 let certs = X509::stack_from_pem(&bundle.cert)
@@ -91,6 +103,77 @@ impl Config {
             }
         }
     }
+
+    /// native-tls can only use private keys it holds in memory, so a client whose identity key
+    /// is on a PKCS#11 token talks TLS through OpenSSL directly instead.
+    fn build_openssl(&self, key_uri: &str) -> Result<Client, Error> {
+        let mut builder =
+            SslConnector::builder(SslMethod::tls()).context(ErrorKind::Initialization)?;
+
+        if let Some(bundle) = &self.trust_bundle {
+            let certs = X509::stack_from_pem(&bundle.cert)
+                .context(ErrorKind::TrustBundle)
+                .context(ErrorKind::Initialization)?;
+            for cert in certs {
+                builder
+                    .cert_store_mut()
+                    .add_cert(cert)
+                    .context(ErrorKind::TrustBundle)
+                    .context(ErrorKind::Initialization)?;
+            }
+        }
+
+        if let Some(id) = &self.identity_certificate {
+            // the first cert is the identity cert and the rest are its CA chain
+            let mut certs = X509::stack_from_pem(id.get_certificate())
+                .context(ErrorKind::IdentityCertificate)
+                .context(ErrorKind::Initialization)?
+                .into_iter();
+            let identity_cert = certs
+                .next()
+                .ok_or(ErrorKind::IdentityCertificate)
+                .context(ErrorKind::Initialization)?;
+            builder
+                .set_certificate(&identity_cert)
+                .context(ErrorKind::IdentityCertificate)
+                .context(ErrorKind::Initialization)?;
+            for cert in certs {
+                builder
+                    .add_extra_chain_cert(cert)
+                    .context(ErrorKind::IdentityCertificate)
+                    .context(ErrorKind::Initialization)?;
+            }
+
+            let key = key_uri
+                .parse::<Pkcs11Uri>()
+                .and_then(|uri| load_private_key(&uri))
+                .context(ErrorKind::IdentityPrivateKey)
+                .context(ErrorKind::Initialization)?;
+            builder
+                .set_private_key(&key)
+                .and_then(|()| builder.check_private_key())
+                .context(ErrorKind::IdentityPrivateKey)
+                .context(ErrorKind::Initialization)?;
+        }
+
+        let mut http = HttpConnector::new(DNS_WORKER_THREADS);
+        http.enforce_http(false);
+        let https_connector = OpenSslHttpsConnector::with_connector(http, builder)
+            .context(ErrorKind::Initialization)?;
+
+        match &self.proxy_uri {
+            None => Ok(Client::OpenSslNoProxy(
+                HyperClient::builder().build(https_connector),
+            )),
+            Some(uri) => {
+                let proxy = uri_to_proxy(uri.clone())?;
+                let conn = ProxyConnector::from_proxy(https_connector, proxy)
+                    .context(ErrorKind::Proxy(uri.clone()))
+                    .context(ErrorKind::Initialization)?;
+                Ok(Client::OpenSslProxy(HyperClient::builder().build(conn)))
+            }
+        }
+    }
 }
 
 fn uri_to_proxy(uri: Uri) -> Result<Proxy, Error> {
@@ -151,6 +234,8 @@ fn uri_to_proxy(uri: Uri) -> Result<Proxy, Error> {
 pub enum Client {
     NoProxy(HyperClient<HttpsConnector<HttpConnector>>),
     Proxy(HyperClient<ProxyConnector<HttpsConnector<HttpConnector>>>),
+    OpenSslNoProxy(HyperClient<OpenSslHttpsConnector<HttpConnector>>),
+    OpenSslProxy(HyperClient<ProxyConnector<OpenSslHttpsConnector<HttpConnector>>>),
     Null,
 }
 
@@ -175,7 +260,7 @@ impl Client {
     #[cfg(test)]
     pub fn has_proxy(&self) -> bool {
         match *self {
-            Client::Proxy(_) => true,
+            Client::Proxy(_) | Client::OpenSslProxy(_) => true,
             _ => false,
         }
     }
@@ -188,6 +273,8 @@ impl ClientImpl for Client {
         match *self {
             Client::NoProxy(ref client) => Box::new(client.request(req)) as Self::Response,
             Client::Proxy(ref client) => Box::new(client.request(req)) as Self::Response,
+            Client::OpenSslNoProxy(ref client) => Box::new(client.request(req)) as Self::Response,
+            Client::OpenSslProxy(ref client) => Box::new(client.request(req)) as Self::Response,
             Client::Null => Box::new(future::ok(
                 Response::builder()
                     .status(
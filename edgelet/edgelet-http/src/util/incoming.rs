@@ -7,6 +7,8 @@ use std::sync::Mutex;
 use futures::{Poll, Stream};
 #[cfg(unix)]
 use log::Level;
+#[cfg(unix)]
+use openssl::ssl::SslAcceptor;
 #[cfg(windows)]
 use tokio::net::TcpListener;
 #[cfg(unix)]
@@ -14,7 +16,7 @@ use tokio::net::{TcpListener, TcpStream};
 #[cfg(unix)]
 use tokio::prelude::*;
 #[cfg(unix)]
-use tokio_tls::{Accept, TlsAcceptor};
+use tokio_openssl::{AcceptAsync, SslAcceptorExt};
 #[cfg(unix)]
 use tokio_uds::UnixListener;
 #[cfg(windows)]
@@ -30,8 +32,8 @@ pub enum Incoming {
     #[cfg(unix)]
     Tls(
         TcpListener,
-        TlsAcceptor,
-        Mutex<Vec<(Accept<TcpStream>, IncomingSocketAddr)>>,
+        SslAcceptor,
+        Mutex<Vec<(AcceptAsync<TcpStream>, IncomingSocketAddr)>>,
     ),
     Unix(UnixListener),
 }
@@ -63,7 +65,10 @@ impl Stream for Incoming {
                         connections
                             .lock()
                             .expect("Unable to lock the connections mutex")
-                            .push((acceptor.accept(tcp_stream), IncomingSocketAddr::Tcp(addr)));
+                            .push((
+                                acceptor.accept_async(tcp_stream),
+                                IncomingSocketAddr::Tcp(addr),
+                            ));
                     }
                 }) {
                     return Err(err);
@@ -101,7 +106,9 @@ impl Stream for Incoming {
                                 // so this line is unreachable.
                                 Ok(_) => unreachable!(),
                                 Err(err) => {
-                                    // Ignore TLS handshake errors
+                                    // Ignore TLS handshake errors, including clients that
+                                    // did not present a trusted certificate
+                                    let err = io::Error::new(io::ErrorKind::Other, err.to_string());
                                     log_failure(Level::Warn, &err);
                                 }
                             }
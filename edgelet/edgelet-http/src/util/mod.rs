@@ -6,21 +6,29 @@ use std::net::SocketAddr;
 #[cfg(unix)]
 use std::os::unix::net::SocketAddr as UnixSocketAddr;
 use std::path::Path;
+use std::ptr;
 
 use bytes::{Buf, BufMut};
+use foreign_types::{ForeignType, ForeignTypeRef};
 use futures::Poll;
 #[cfg(windows)]
 use mio_uds_windows::net::SocketAddr as UnixSocketAddr;
+use openssl::asn1::Asn1ObjectRef;
+use openssl::nid::Nid;
+use openssl::stack::Stack;
+use openssl::x509::X509Ref;
 use tokio::io::{AsyncRead, AsyncWrite};
 use tokio::net::TcpStream;
 #[cfg(windows)]
 use tokio_named_pipe::PipeStream;
-use tokio_tls::TlsStream;
+use tokio_openssl::SslStream;
 #[cfg(unix)]
 use tokio_uds::UnixStream;
 #[cfg(windows)]
 use tokio_uds_windows::UnixStream;
 
+use edgelet_core::AuthId;
+
 use crate::pid::{Pid, UnixStreamExt};
 
 pub mod connector;
@@ -28,15 +36,18 @@ mod hyperwrap;
 pub mod incoming;
 pub mod proxy;
 
-pub use connector::UrlConnector;
+pub use connector::{TlsConfig, UrlConnector};
 pub use incoming::Incoming;
 
+/// The edge agent, which can't be named by client certificates.
+const EDGE_AGENT: &str = "edgeAgent";
+
 pub enum StreamSelector {
     Tcp(TcpStream),
     #[cfg(not(windows))]
-    Tls(TlsStream<TcpStream>),
+    Tls(SslStream<TcpStream>),
     #[cfg(windows)]
-    Tls(Box<TlsStream<TcpStream>>),
+    Tls(Box<SslStream<TcpStream>>),
     #[cfg(windows)]
     Pipe(PipeStream),
     Unix(UnixStream),
@@ -53,6 +64,87 @@ impl StreamSelector {
             StreamSelector::Unix(ref stream) => stream.pid(),
         }
     }
+
+    /// The caller identified by the certificate it presented during the TLS handshake, if any.
+    /// The certificate has already been validated against the client CA by then.
+    pub fn auth_id(&self) -> Option<AuthId> {
+        if let StreamSelector::Tls(ref stream) = *self {
+            let cert = stream.get_ref().ssl().peer_certificate()?;
+            Some(cert_auth_id(&cert))
+        } else {
+            None
+        }
+    }
+}
+
+/// Gets the module named by the common name of a client certificate.
+///
+/// Certificates that aren't meant for client authentication, such as the server certificates of
+/// modules, or that name the edge agent, identify no module. The edge agent only calls over the
+/// management socket. These get `AuthId::None` rather than no id at all, since connections over
+/// TCP would otherwise be authenticated as any caller.
+fn cert_auth_id(cert: &X509Ref) -> AuthId {
+    if !is_client_auth_cert(cert) {
+        return AuthId::None;
+    }
+
+    let common_name = cert
+        .subject_name()
+        .entries_by_nid(Nid::COMMONNAME)
+        .next()
+        .and_then(|entry| entry.data().as_utf8().ok());
+    match common_name {
+        Some(ref name)
+            if !name
+                .trim_start_matches('$')
+                .eq_ignore_ascii_case(EDGE_AGENT) =>
+        {
+            AuthId::Value(name.to_string().into())
+        }
+        _ => AuthId::None,
+    }
+}
+
+// The openssl crate can't hold the key purposes of an extended key usage in a stack.
+mod key_purpose {
+    use foreign_types::foreign_type;
+    use openssl::stack::Stackable;
+
+    foreign_type! {
+        type CType = openssl_sys::ASN1_OBJECT;
+        fn drop = openssl_sys::ASN1_OBJECT_free;
+
+        /// A key purpose in an extended key usage.
+        pub struct KeyPurpose;
+        /// A borrowed key purpose.
+        pub struct KeyPurposeRef;
+    }
+
+    impl Stackable for KeyPurpose {
+        type StackType = openssl_sys::stack_st_ASN1_OBJECT;
+    }
+}
+
+/// Whether the certificate has an extended key usage, and it includes client authentication.
+/// Certificates without one are valid for any purpose, so they don't count.
+fn is_client_auth_cert(cert: &X509Ref) -> bool {
+    let usage = unsafe {
+        openssl_sys::X509_get_ext_d2i(
+            cert.as_ptr(),
+            Nid::EXT_KEY_USAGE.as_raw(),
+            ptr::null_mut(),
+            ptr::null_mut(),
+        )
+    };
+    if usage.is_null() {
+        return false;
+    }
+
+    let usage: Stack<key_purpose::KeyPurpose> = unsafe { Stack::from_ptr(usage as *mut _) };
+    usage.iter().any(|purpose| {
+        let purpose = unsafe { Asn1ObjectRef::from_ptr(purpose.as_ptr()) };
+        purpose.nid() == Nid::CLIENT_AUTH
+    })
 }
 
 impl Read for StreamSelector {
@@ -117,7 +209,7 @@ impl AsyncWrite for StreamSelector {
     fn shutdown(&mut self) -> Poll<(), io::Error> {
         match self {
             StreamSelector::Tcp(stream) => AsyncWrite::shutdown(stream),
-            StreamSelector::Tls(stream) => TlsStream::shutdown(stream),
+            StreamSelector::Tls(stream) => AsyncWrite::shutdown(stream),
             #[cfg(windows)]
             StreamSelector::Pipe(stream) => PipeStream::shutdown(stream),
             StreamSelector::Unix(stream) => AsyncWrite::shutdown(stream),
@@ -179,6 +271,13 @@ mod tests {
     #[cfg(windows)]
     use tokio_uds_windows::{UnixListener, UnixStream};
 
+    use openssl::asn1::Asn1Time;
+    use openssl::ec::{EcGroup, EcKey};
+    use openssl::hash::MessageDigest;
+    use openssl::pkey::PKey;
+    use openssl::x509::extension::ExtendedKeyUsage;
+    use openssl::x509::{X509Name, X509};
+
     use super::*;
 
     struct Pair {
@@ -244,4 +343,56 @@ mod tests {
             Pid::Value(_) => (),
         }
     }
+
+    fn client_cert(common_name: &str, ext_key_usage: Option<&ExtendedKeyUsage>) -> X509 {
+        let group = EcGroup::from_curve_name(Nid::X9_62_PRIME256V1).unwrap();
+        let key = PKey::from_ec_key(EcKey::generate(&group).unwrap()).unwrap();
+
+        let mut name = X509Name::builder().unwrap();
+        name.append_entry_by_nid(Nid::COMMONNAME, common_name)
+            .unwrap();
+        let name = name.build();
+
+        let mut builder = X509::builder().unwrap();
+        builder.set_version(2).unwrap();
+        builder.set_subject_name(&name).unwrap();
+        builder.set_issuer_name(&name).unwrap();
+        builder.set_pubkey(&key).unwrap();
+        builder
+            .set_not_before(&Asn1Time::days_from_now(0).unwrap())
+            .unwrap();
+        builder
+            .set_not_after(&Asn1Time::days_from_now(1).unwrap())
+            .unwrap();
+        if let Some(ext_key_usage) = ext_key_usage {
+            builder
+                .append_extension(ext_key_usage.build().unwrap())
+                .unwrap();
+        }
+        builder.sign(&key, MessageDigest::sha256()).unwrap();
+        builder.build()
+    }
+
+    #[test]
+    fn client_cert_names_module() {
+        let cert = client_cert("technician", Some(ExtendedKeyUsage::new().client_auth()));
+        assert_eq!(AuthId::Value("technician".into()), cert_auth_id(&cert));
+    }
+
+    #[test]
+    fn cert_without_client_auth_names_no_module() {
+        let cert = client_cert("technician", None);
+        assert_eq!(AuthId::None, cert_auth_id(&cert));
+
+        let cert = client_cert("technician", Some(ExtendedKeyUsage::new().server_auth()));
+        assert_eq!(AuthId::None, cert_auth_id(&cert));
+    }
+
+    #[test]
+    fn client_cert_cannot_name_edge_agent() {
+        for name in &["edgeAgent", "$edgeAgent", "EdgeAgent"] {
+            let cert = client_cert(name, Some(ExtendedKeyUsage::new().client_auth()));
+            assert_eq!(AuthId::None, cert_auth_id(&cert));
+        }
+    }
 }
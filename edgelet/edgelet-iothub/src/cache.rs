@@ -0,0 +1,340 @@
+// Copyright (c) Microsoft. All rights reserved.
+
+use std::collections::BTreeMap;
+use std::io;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+
+use failure::{Fail, ResultExt};
+use log::{warn, Level};
+
+use edgelet_core::crypto::{Decrypt, Encrypt, MakeRandom};
+use edgelet_core::SealedFile;
+use edgelet_utils::log_failure;
+use iothubservice::{Error as HubError, Module};
+
+use crate::error::ErrorKind;
+
+/// Client ID the cache is encrypted under with the master encryption key
+const IDENTITY_CACHE_CRYPTO_ID: &str = "$iotedge-identity-cache";
+
+/// An encrypted copy of the module identities last returned by IoT Hub.
+///
+/// `HubIdentityManager` answers from the cache when IoT Hub can't be reached, so that
+/// edgeAgent and the modules it manages can start on a device that comes up without
+/// connectivity. The file is the IV followed by the module list, encrypted with the
+/// device's master encryption key.
+pub struct IdentityCache {
+    file: SealedFile,
+    modules: Mutex<BTreeMap<String, Module>>,
+    stale: AtomicBool,
+}
+
+impl IdentityCache {
+    /// Opens the cache at `path`. A cache that can't be read or decrypted (for example because
+    /// the master encryption key was regenerated) is discarded rather than treated as an error.
+    pub fn new<C>(path: PathBuf, crypto: C) -> Self
+    where
+        C: 'static + Encrypt + Decrypt + MakeRandom + Send + Sync,
+    {
+        let file = SealedFile::new(path, IDENTITY_CACHE_CRYPTO_ID, crypto);
+        let modules = match file.load().context(ErrorKind::IdentityCache) {
+            Ok(modules) => modules.unwrap_or_default(),
+            Err(err) => {
+                warn!("Discarding the identity cache at {}", file.path().display());
+                log_failure(Level::Warn, &err);
+                BTreeMap::new()
+            }
+        };
+
+        IdentityCache {
+            file,
+            modules: Mutex::new(modules),
+            stale: AtomicBool::new(false),
+        }
+    }
+
+    /// Whether an identity was served from the cache since the last time it was refreshed
+    /// from IoT Hub.
+    pub fn is_stale(&self) -> bool {
+        self.stale.load(Ordering::SeqCst)
+    }
+
+    pub(crate) fn get(&self, module_id: &str) -> Option<Module> {
+        self.stale.store(true, Ordering::SeqCst);
+        self.modules
+            .lock()
+            .expect("identity cache lock poisoned")
+            .get(module_id)
+            .cloned()
+    }
+
+    pub(crate) fn list(&self) -> Vec<Module> {
+        self.stale.store(true, Ordering::SeqCst);
+        self.modules
+            .lock()
+            .expect("identity cache lock poisoned")
+            .values()
+            .cloned()
+            .collect()
+    }
+
+    pub(crate) fn insert(&self, module: &Module) {
+        if let Some(module_id) = module.module_id() {
+            self.update(|modules| {
+                modules.insert(module_id.to_string(), module.clone());
+            });
+        }
+    }
+
+    pub(crate) fn remove(&self, module_id: &str) {
+        self.update(|modules| {
+            modules.remove(module_id);
+        });
+    }
+
+    pub(crate) fn replace_all(&self, all: &[Module]) {
+        self.update(|modules| {
+            *modules = all
+                .iter()
+                .filter_map(|module| {
+                    module
+                        .module_id()
+                        .map(|module_id| (module_id.to_string(), module.clone()))
+                })
+                .collect();
+        });
+        self.stale.store(false, Ordering::SeqCst);
+    }
+
+    // Failing to persist the cache only costs the ability to start offline,
+    // so it's logged instead of failing the identity operation.
+    fn update<F>(&self, f: F)
+    where
+        F: FnOnce(&mut BTreeMap<String, Module>),
+    {
+        let mut modules = self.modules.lock().expect("identity cache lock poisoned");
+        f(&mut modules);
+        if let Err(err) = self.file.save(&*modules).context(ErrorKind::IdentityCache) {
+            warn!(
+                "Could not save the identity cache to {}",
+                self.file.path().display()
+            );
+            log_failure(Level::Warn, &err);
+        }
+    }
+}
+
+/// Whether `err` means IoT Hub couldn't be reached: connecting to it failed, or the connection
+/// broke or timed out. Only that is a reason to fall back to the cache, not IoT Hub rejecting the
+/// request or the request failing before it was sent.
+pub(crate) fn is_hub_unreachable(err: &HubError) -> bool {
+    Fail::iter_chain(err).any(|cause| {
+        if let Some(err) = cause.downcast_ref::<hyper::Error>() {
+            err.is_connect() || err.is_closed() || err.is_incomplete_message()
+        } else {
+            cause.downcast_ref::<io::Error>().is_some()
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::fs;
+
+    use hyper::{Body, Request, Response, StatusCode};
+    use tempdir::TempDir;
+    use typed_headers::{mime, ContentType, HeaderMapExt};
+    use url::Url;
+
+    use edgelet_core::crypto::{KeyIdentity, MemoryKey, MemoryKeyStore};
+    use edgelet_core::Error as CoreError;
+    use edgelet_core::{Identity, IdentityManager, IdentitySpec};
+    use edgelet_http::client::{Client, ClientImpl};
+    use iothubservice::{AuthMechanism, AuthType as HubAuthType, DeviceClient};
+
+    use crate::{HubIdentityManager, SasTokenSource, KEY_PRIMARY, KEY_SECONDARY};
+
+    // XORs the data with the first byte of the IV, which is enough to tell
+    // whether the cache went through the cipher.
+    struct TestCrypto;
+
+    impl MakeRandom for TestCrypto {
+        fn get_random_bytes(&self, buffer: &mut [u8]) -> Result<(), CoreError> {
+            for b in buffer.iter_mut() {
+                *b = 0x5a;
+            }
+            Ok(())
+        }
+    }
+
+    impl Encrypt for TestCrypto {
+        type Buffer = Vec<u8>;
+
+        fn encrypt(
+            &self,
+            _client_id: &[u8],
+            plaintext: &[u8],
+            initialization_vector: &[u8],
+        ) -> Result<Self::Buffer, CoreError> {
+            Ok(plaintext
+                .iter()
+                .map(|b| b ^ initialization_vector[0])
+                .collect())
+        }
+    }
+
+    impl Decrypt for TestCrypto {
+        type Buffer = Vec<u8>;
+
+        fn decrypt(
+            &self,
+            client_id: &[u8],
+            ciphertext: &[u8],
+            initialization_vector: &[u8],
+        ) -> Result<Self::Buffer, CoreError> {
+            self.encrypt(client_id, ciphertext, initialization_vector)
+        }
+    }
+
+    fn module(module_id: &str, generation_id: &str) -> Module {
+        Module::default()
+            .with_device_id("d1".to_string())
+            .with_module_id(module_id.to_string())
+            .with_generation_id(generation_id.to_string())
+            .with_authentication(AuthMechanism::default().with_type(HubAuthType::Sas))
+    }
+
+    #[test]
+    fn identities_survive_restart() {
+        let dir = TempDir::new("identity-cache").unwrap();
+        let path = dir.path().join("identities");
+
+        let cache = IdentityCache::new(path.clone(), TestCrypto);
+        cache.replace_all(&[module("m1", "g1"), module("m2", "g2")]);
+        cache.insert(&module("m3", "g3"));
+        cache.remove("m2");
+        assert!(!cache.is_stale());
+
+        let sealed = fs::read(&path).unwrap();
+        assert!(!String::from_utf8_lossy(&sealed).contains("m1"));
+
+        let cache = IdentityCache::new(path, TestCrypto);
+        assert_eq!(Some(module("m1", "g1")), cache.get("m1"));
+        assert_eq!(None, cache.get("m2"));
+        assert_eq!(vec![module("m1", "g1"), module("m3", "g3")], cache.list());
+        assert!(cache.is_stale());
+    }
+
+    #[test]
+    fn unreadable_cache_is_discarded() {
+        let dir = TempDir::new("identity-cache").unwrap();
+        let path = dir.path().join("identities");
+        fs::write(&path, b"garbage").unwrap();
+
+        let cache = IdentityCache::new(path, TestCrypto);
+        assert!(cache.list().is_empty());
+    }
+
+    fn identity_manager<C>(
+        client: C,
+        host_name: &str,
+        cache: IdentityCache,
+    ) -> HubIdentityManager<MemoryKeyStore, C, MemoryKey>
+    where
+        C: ClientImpl,
+    {
+        let token_source = SasTokenSource::new(
+            "hub".to_string(),
+            "device".to_string(),
+            MemoryKey::new("device"),
+        );
+        let client = Client::new(
+            client,
+            Some(token_source),
+            "2018-04-10".to_string(),
+            Url::parse(host_name).unwrap(),
+        )
+        .unwrap();
+        let device_client = DeviceClient::new(client, "d1".to_string()).unwrap();
+        let mut key_store = MemoryKeyStore::new();
+        for key_name in &[KEY_PRIMARY, KEY_SECONDARY] {
+            key_store.insert(
+                &KeyIdentity::Module("m1".to_string()),
+                &format!("{}g1", key_name),
+                MemoryKey::new(*key_name),
+            );
+        }
+        HubIdentityManager::new(key_store, device_client).with_identity_cache(cache)
+    }
+
+    #[test]
+    fn falls_back_to_cache_when_hub_is_unreachable() {
+        let dir = TempDir::new("identity-cache").unwrap();
+        let path = dir.path().join("identities");
+        let mut runtime = tokio::runtime::current_thread::Runtime::new().unwrap();
+
+        let handler = |_: Request<Body>| {
+            let mut response =
+                Response::new(serde_json::to_string(&module("m1", "g1")).unwrap().into());
+            response
+                .headers_mut()
+                .typed_insert(&ContentType(mime::APPLICATION_JSON));
+            Ok(response)
+        };
+        let online = identity_manager(
+            handler,
+            "http://localhost",
+            IdentityCache::new(path.clone(), TestCrypto),
+        );
+        runtime
+            .block_on(online.get(IdentitySpec::new("m1".to_string())))
+            .unwrap()
+            .unwrap();
+
+        // nothing listens on port 1, so every request fails to connect
+        let offline = identity_manager(
+            hyper::Client::new(),
+            "http://127.0.0.1:1",
+            IdentityCache::new(path.clone(), TestCrypto),
+        );
+        let identity = runtime
+            .block_on(offline.get(IdentitySpec::new("m1".to_string())))
+            .unwrap()
+            .unwrap();
+        assert_eq!("g1", identity.generation_id());
+
+        let identity =
+            runtime
+                .block_on(offline.clone().update(
+                    IdentitySpec::new("m1".to_string()).with_generation_id("g1".to_string()),
+                ))
+                .unwrap();
+        assert_eq!("m1", identity.module_id());
+
+        assert!(runtime
+            .block_on(offline.get(IdentitySpec::new("m2".to_string())))
+            .is_err());
+        assert_eq!(1, runtime.block_on(offline.list()).unwrap().len());
+        assert!(offline.cache.as_ref().unwrap().is_stale());
+
+        // a hub that answers but rejects the request is not a reason to use the cache
+        let handler = |_: Request<Body>| {
+            Ok(Response::builder()
+                .status(StatusCode::UNAUTHORIZED)
+                .body(Body::empty())
+                .unwrap())
+        };
+        let rejected = identity_manager(
+            handler,
+            "http://localhost",
+            IdentityCache::new(path, TestCrypto),
+        );
+        assert!(runtime
+            .block_on(rejected.get(IdentitySpec::new("m1".to_string())))
+            .is_err());
+    }
+}
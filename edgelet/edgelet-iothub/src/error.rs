@@ -26,6 +26,9 @@ pub enum ErrorKind {
     #[fail(display = "{}", _0)]
     IdentityOperation(IdentityOperation),
 
+    #[fail(display = "Could not load or save the identity cache")]
+    IdentityCache,
+
     #[fail(display = "Could not update identity {}: {}", _0, _1)]
     UpdateIdentityWithReason(String, IdentityOperationReason),
 }
@@ -0,0 +1,129 @@
+// Copyright (c) Microsoft. All rights reserved.
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use futures::sync::mpsc::UnboundedSender;
+use log::warn;
+
+use iothubservice::{Error as HubError, ErrorKind as HubErrorKind};
+
+use crate::cache;
+
+/// Counts the identity operations in a row that couldn't reach IoT Hub.
+///
+/// Once `max_failures` operations in a row have failed, `unreachable` is signaled so that
+/// the device can fail over to another IoT hub. Any answer from IoT Hub, including an
+/// error response, resets the count.
+pub struct ConnectivityMonitor {
+    max_failures: usize,
+    failures: AtomicUsize,
+    unreachable: UnboundedSender<()>,
+}
+
+impl ConnectivityMonitor {
+    pub fn new(max_failures: usize, unreachable: UnboundedSender<()>) -> Self {
+        ConnectivityMonitor {
+            max_failures,
+            failures: AtomicUsize::new(0),
+            unreachable,
+        }
+    }
+
+    pub fn failures(&self) -> usize {
+        self.failures.load(Ordering::SeqCst)
+    }
+
+    pub(crate) fn observe<T>(&self, result: &Result<T, HubError>) {
+        match result {
+            Err(err) if is_hub_unreachable(err) => {
+                let failures = self.failures.fetch_add(1, Ordering::SeqCst) + 1;
+                if failures == self.max_failures {
+                    warn!(
+                        "IoT Hub could not be reached by {} identity operations in a row",
+                        failures
+                    );
+                    self.unreachable.unbounded_send(()).unwrap_or(());
+                }
+            }
+            _ => self.failures.store(0, Ordering::SeqCst),
+        }
+    }
+}
+
+// Errors that say what IoT Hub answered, like a module that was not found, came from a
+// hub that could be reached even though they don't carry its response.
+fn is_hub_unreachable(err: &HubError) -> bool {
+    match err.kind() {
+        HubErrorKind::DeleteModuleWithReason(_, _)
+        | HubErrorKind::GetModuleWithReason(_, _)
+        | HubErrorKind::HubService(_, _)
+        | HubErrorKind::ListModulesWithReason(_)
+        | HubErrorKind::UpsertModuleWithReason(_, _) => false,
+        _ => cache::is_hub_unreachable(err),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io;
+
+    use failure::Fail;
+    use futures::sync::mpsc;
+    use futures::{Future, Stream};
+
+    use edgelet_http::{Error as HttpError, ErrorKind as HttpErrorKind};
+    use iothubservice::ModuleOperationReason;
+
+    use super::*;
+
+    fn hub_error(err: HttpError) -> HubError {
+        HubError::from(err.context(HubErrorKind::ListModules))
+    }
+
+    fn unreachable() -> Result<(), HubError> {
+        let err = io::Error::from(io::ErrorKind::ConnectionRefused);
+        Err(hub_error(HttpError::from(err.context(HttpErrorKind::Http))))
+    }
+
+    fn error_response() -> Result<(), HubError> {
+        Err(hub_error(HttpError::from(
+            HttpErrorKind::HttpWithErrorResponse(hyper::StatusCode::NOT_FOUND, String::new()),
+        )))
+    }
+
+    #[test]
+    fn signals_once_after_max_failures_in_a_row() {
+        let (tx, rx) = mpsc::unbounded();
+        let monitor = ConnectivityMonitor::new(3, tx);
+
+        monitor.observe(&unreachable());
+        monitor.observe(&unreachable());
+        monitor.observe(&Ok(()));
+        assert_eq!(0, monitor.failures());
+
+        monitor.observe(&unreachable());
+        monitor.observe(&unreachable());
+        monitor.observe(&error_response());
+        assert_eq!(0, monitor.failures());
+
+        monitor.observe(&unreachable());
+        monitor.observe::<()>(&Err(hub_error(HttpError::from(HttpErrorKind::TokenSource))));
+        assert_eq!(0, monitor.failures());
+
+        monitor.observe(&unreachable());
+        monitor.observe::<()>(&Err(HubError::from(HubErrorKind::GetModuleWithReason(
+            "m1".to_string(),
+            ModuleOperationReason::ModuleNotFound,
+        ))));
+        assert_eq!(0, monitor.failures());
+
+        for _ in 0..5 {
+            monitor.observe(&unreachable());
+        }
+        assert_eq!(5, monitor.failures());
+
+        drop(monitor);
+        let signals = rx.collect().wait().unwrap();
+        assert_eq!(1, signals.len());
+    }
+}
@@ -8,7 +8,9 @@
     clippy::use_self
 )]
 
+mod cache;
 mod error;
+mod failover;
 
 use std::convert::AsRef;
 use std::marker::PhantomData;
@@ -18,6 +20,7 @@ use chrono::{DateTime, Utc};
 use failure::{Fail, ResultExt};
 use futures::future::{self, Either};
 use futures::Future;
+use log::{info, warn};
 use percent_encoding::{define_encode_set, percent_encode, PATH_SEGMENT_ENCODE_SET};
 use url::form_urlencoded::Serializer as UrlSerializer;
 
@@ -25,11 +28,13 @@ use edgelet_core::crypto::{KeyIdentity, KeyStore, Sign, Signature, SignatureAlgo
 use edgelet_core::{AuthType, Identity, IdentityManager, IdentityOperation, IdentitySpec};
 use edgelet_http::client::{ClientImpl, TokenSource};
 use iothubservice::{
-    AuthMechanism, AuthType as HubAuthType, DeviceClient, ErrorKind as HubErrorKind, Module,
-    ModuleOperationReason as HubReason, SymmetricKey,
+    AuthMechanism, AuthType as HubAuthType, DeviceClient, Error as HubError,
+    ErrorKind as HubErrorKind, Module, ModuleOperationReason as HubReason, SymmetricKey,
 };
 
+pub use crate::cache::IdentityCache;
 pub use crate::error::{Error, ErrorKind, IdentityOperationReason};
+pub use crate::failover::ConnectivityMonitor;
 
 const KEY_PRIMARY: &str = "primary";
 const KEY_SECONDARY: &str = "secondary";
@@ -78,7 +83,7 @@ fn convert_auth_type(hub_auth_type: HubAuthType) -> AuthType {
     match hub_auth_type {
         HubAuthType::None => AuthType::None,
         HubAuthType::Sas => AuthType::Sas,
-        HubAuthType::X509 => AuthType::X509,
+        HubAuthType::X509 | HubAuthType::CertificateAuthority => AuthType::X509,
     }
 }
 
@@ -91,6 +96,7 @@ where
 {
     key_store: K,
     client: DeviceClient<C, SasTokenSource<D>>,
+    module_auth_type: AuthType,
 }
 
 pub struct SasTokenSource<K>
@@ -164,6 +170,8 @@ where
     D: 'static + Sign + Clone,
 {
     state: Arc<State<K, C, D>>,
+    cache: Option<Arc<IdentityCache>>,
+    monitor: Option<Arc<ConnectivityMonitor>>,
     phantom: PhantomData<D>,
 }
 
@@ -175,12 +183,139 @@ where
     D: 'static + Sign + Clone,
 {
     pub fn new(key_store: K, client: DeviceClient<C, SasTokenSource<D>>) -> Self {
+        Self::with_module_auth_type(key_store, client, AuthType::Sas)
+    }
+
+    /// Creates module identities that authenticate with X.509 certificates issued by the
+    /// device CA instead of SAS keys derived from the device key. This is what devices that
+    /// were themselves provisioned with X.509 use.
+    pub fn with_x509_module_auth(key_store: K, client: DeviceClient<C, SasTokenSource<D>>) -> Self {
+        Self::with_module_auth_type(key_store, client, AuthType::X509)
+    }
+
+    pub fn module_auth_type(&self) -> AuthType {
+        self.state.module_auth_type
+    }
+
+    fn with_module_auth_type(
+        key_store: K,
+        client: DeviceClient<C, SasTokenSource<D>>,
+        module_auth_type: AuthType,
+    ) -> Self {
         HubIdentityManager {
-            state: Arc::new(State { key_store, client }),
+            state: Arc::new(State {
+                key_store,
+                client,
+                module_auth_type,
+            }),
+            cache: None,
+            monitor: None,
             phantom: PhantomData,
         }
     }
 
+    /// Keeps a copy of the identities returned by IoT Hub in `cache` and answers from it
+    /// when IoT Hub can't be reached.
+    pub fn with_identity_cache(mut self, cache: IdentityCache) -> Self {
+        self.cache = Some(Arc::new(cache));
+        self
+    }
+
+    /// Reports to `monitor` whether the requests to IoT Hub reach it.
+    pub fn with_connectivity_monitor(mut self, monitor: ConnectivityMonitor) -> Self {
+        self.monitor = Some(Arc::new(monitor));
+        self
+    }
+
+    /// Refreshes the identity cache from IoT Hub if identities were served from it
+    /// since it was last refreshed.
+    pub fn sync_identity_cache(&self) -> impl Future<Item = (), Error = Error> {
+        match &self.cache {
+            Some(cache) if cache.is_stale() => {
+                let cache = cache.clone();
+                Either::A(
+                    self.monitored(self.state.client.list_modules())
+                        .map_err(|err| {
+                            Error::from(err.context(ErrorKind::IdentityOperation(
+                                IdentityOperation::ListIdentities,
+                            )))
+                        })
+                        .map(move |modules| {
+                            info!("IoT Hub is reachable again, refreshed the identity cache");
+                            cache.replace_all(&modules);
+                        }),
+                )
+            }
+            _ => Either::B(future::ok(())),
+        }
+    }
+
+    fn monitored<F>(&self, request: F) -> impl Future<Item = F::Item, Error = HubError>
+    where
+        F: Future<Error = HubError>,
+    {
+        let monitor = self.monitor.clone();
+        request.then(move |result| {
+            if let Some(monitor) = monitor {
+                monitor.observe(&result);
+            }
+            result
+        })
+    }
+
+    fn cache_module(&self, module: Module) -> HubIdentity {
+        if let Some(cache) = &self.cache {
+            cache.insert(&module);
+        }
+        HubIdentity::new(module)
+    }
+
+    // An update only changes the identity's authentication, which a module that was
+    // already given this identity has locally, so the cached identity is as good as
+    // the one IoT Hub would have returned as long as the generation matches.
+    fn update_or_cached<F>(
+        &self,
+        module_id: String,
+        generation_id: Option<String>,
+        update: F,
+    ) -> impl Future<Item = HubIdentity, Error = Error>
+    where
+        F: Future<Item = Module, Error = HubError>,
+    {
+        let idman = self.clone();
+        self.monitored(update).then(move |module| match module {
+            Ok(module) => Ok(idman.cache_module(module)),
+            Err(err) => {
+                let cached = idman.cached(&err, &module_id).filter(|module| {
+                    generation_id.is_none()
+                        || module.generation_id() == generation_id.as_ref().map(String::as_str)
+                });
+                match cached {
+                    Some(module) => Ok(HubIdentity::new(module)),
+                    None => Err(Error::from(err.context(ErrorKind::IdentityOperation(
+                        IdentityOperation::UpdateIdentity(module_id),
+                    )))),
+                }
+            }
+        })
+    }
+
+    fn cached(&self, err: &HubError, module_id: &str) -> Option<Module> {
+        let cache = self.cache.as_ref()?;
+        if cache::is_hub_unreachable(err) {
+            let module = cache.get(module_id);
+            if module.is_some() {
+                warn!(
+                    "IoT Hub is unreachable, using the cached identity of module {}",
+                    module_id
+                );
+            }
+            module
+        } else {
+            None
+        }
+    }
+
     fn get_key_pair(&self, id: &str, generation_id: &str) -> Result<(K::Key, K::Key), Error> {
         self.state
             .key_store
@@ -216,6 +351,8 @@ where
     fn clone(&self) -> Self {
         HubIdentityManager {
             state: self.state.clone(),
+            cache: self.cache.clone(),
+            monitor: self.monitor.clone(),
             phantom: PhantomData,
         }
     }
@@ -237,6 +374,26 @@ where
     type DeleteFuture = Box<dyn Future<Item = (), Error = Self::Error> + Send>;
 
     fn create(&mut self, id: IdentitySpec) -> Self::CreateFuture {
+        if self.state.module_auth_type == AuthType::X509 {
+            // The module authenticates with a certificate from the workload API,
+            // so there are no keys to derive and a single call is enough.
+            let idman = self.clone();
+            let module_id = id.module_id().to_string();
+            return Box::new(
+                self.monitored(self.state.client.create_module(
+                    module_id.clone(),
+                    Some(AuthMechanism::default().with_type(HubAuthType::CertificateAuthority)),
+                    id.managed_by(),
+                ))
+                .map_err(|err| {
+                    Error::from(err.context(ErrorKind::IdentityOperation(
+                        IdentityOperation::CreateIdentity(module_id),
+                    )))
+                })
+                .map(move |module| idman.cache_module(module)),
+            );
+        }
+
         // This code first creates a module in the hub with the auth type
         // set as "None" in order to have a generation identifier generated for
         // the module by the hub. Once we have a generation ID we use it to
@@ -245,62 +402,74 @@ where
         let idman = self.clone();
         let module_id = id.module_id().to_string();
         Box::new(
-            self.state
-                .client
-                .create_module(
-                    module_id.clone(),
-                    Some(AuthMechanism::default().with_type(HubAuthType::None)),
-                    id.managed_by(),
-                )
-                .then(|module| {
-                    let module = module.with_context(|_| {
-                        ErrorKind::IdentityOperation(IdentityOperation::CreateIdentity(
-                            module_id.clone(),
-                        ))
-                    })?;
-
-                    if let (Some(module_id2), Some(generation_id)) =
-                        (module.module_id(), module.generation_id())
-                    {
-                        idman.get_key_pair(module_id2, generation_id).map(
-                            |(primary_key, secondary_key)| {
-                                (primary_key, secondary_key, idman, module_id)
-                            },
-                        )
-                    } else {
-                        Err(Error::from(ErrorKind::CreateIdentityWithReason(
+            self.monitored(self.state.client.create_module(
+                module_id.clone(),
+                Some(AuthMechanism::default().with_type(HubAuthType::None)),
+                id.managed_by(),
+            ))
+            .then(|module| {
+                let module = module.with_context(|_| {
+                    ErrorKind::IdentityOperation(IdentityOperation::CreateIdentity(
+                        module_id.clone(),
+                    ))
+                })?;
+
+                if let (Some(module_id2), Some(generation_id)) =
+                    (module.module_id(), module.generation_id())
+                {
+                    idman.get_key_pair(module_id2, generation_id).map(
+                        |(primary_key, secondary_key)| {
+                            (primary_key, secondary_key, idman, module_id)
+                        },
+                    )
+                } else {
+                    Err(Error::from(ErrorKind::CreateIdentityWithReason(
+                        module_id,
+                        IdentityOperationReason::InvalidHubResponse,
+                    )))
+                }
+            })
+            .and_then(move |(primary_key, secondary_key, idman, module_id)| {
+                let auth = AuthMechanism::default()
+                    .with_type(HubAuthType::Sas)
+                    .with_symmetric_key(
+                        SymmetricKey::default()
+                            .with_primary_key(base64::encode(primary_key.as_ref()))
+                            .with_secondary_key(base64::encode(secondary_key.as_ref())),
+                    );
+
+                idman
+                    .monitored(idman.state.client.update_module(
+                        id.module_id().to_string(),
+                        Some(auth),
+                        id.managed_by(),
+                    ))
+                    .map_err(|err| {
+                        Error::from(err.context(ErrorKind::CreateIdentityWithReason(
                             module_id,
                             IdentityOperationReason::InvalidHubResponse,
                         )))
-                    }
-                })
-                .and_then(move |(primary_key, secondary_key, idman, module_id)| {
-                    let auth = AuthMechanism::default()
-                        .with_type(HubAuthType::Sas)
-                        .with_symmetric_key(
-                            SymmetricKey::default()
-                                .with_primary_key(base64::encode(primary_key.as_ref()))
-                                .with_secondary_key(base64::encode(secondary_key.as_ref())),
-                        );
-
-                    idman
-                        .state
-                        .client
-                        .update_module(id.module_id().to_string(), Some(auth), id.managed_by())
-                        .map_err(|err| {
-                            Error::from(err.context(ErrorKind::CreateIdentityWithReason(
-                                module_id,
-                                IdentityOperationReason::InvalidHubResponse,
-                            )))
-                        })
-                        .map(HubIdentity::new)
-                }),
+                    })
+                    .map(move |module| idman.cache_module(module))
+            }),
         )
     }
 
     fn update(&mut self, id: IdentitySpec) -> Self::UpdateFuture {
         let module_id = id.module_id().to_string();
 
+        if self.state.module_auth_type == AuthType::X509 {
+            return Box::new(self.update_or_cached(
+                module_id.clone(),
+                id.generation_id().map(ToString::to_string),
+                self.state.client.update_module(
+                    module_id,
+                    Some(AuthMechanism::default().with_type(HubAuthType::CertificateAuthority)),
+                    id.managed_by(),
+                ),
+            ));
+        }
+
         let result = if let Some(generation_id) = id.generation_id() {
             match self.get_key_pair(&module_id, generation_id) {
                 Ok((primary_key, secondary_key)) => {
@@ -313,15 +482,13 @@ where
                         );
 
                     Either::A(
-                        self.state
-                            .client
-                            .update_module(module_id.clone(), Some(auth), id.managed_by())
-                            .map_err(|err| {
-                                Error::from(err.context(ErrorKind::IdentityOperation(
-                                    IdentityOperation::UpdateIdentity(module_id),
-                                )))
-                            })
-                            .map(HubIdentity::new),
+                        self.update_or_cached(
+                            module_id.clone(),
+                            Some(generation_id.to_string()),
+                            self.state
+                                .client
+                                .update_module(module_id, Some(auth), id.managed_by()),
+                        ),
                     )
                 }
 
@@ -340,48 +507,79 @@ where
     }
 
     fn list(&self) -> Self::ListFuture {
+        let identity_cache = self.cache.clone();
         Box::new(
-            self.state
-                .client
-                .list_modules()
-                .map_err(|err| {
-                    Error::from(err.context(ErrorKind::IdentityOperation(
-                        IdentityOperation::ListIdentities,
-                    )))
-                })
-                .map(|modules| modules.into_iter().map(HubIdentity::new).collect()),
+            self.monitored(self.state.client.list_modules())
+                .then(move |modules| {
+                    let modules = match (modules, identity_cache) {
+                        (Ok(modules), Some(identity_cache)) => {
+                            identity_cache.replace_all(&modules);
+                            modules
+                        }
+                        (Ok(modules), None) => modules,
+                        (Err(ref err), Some(ref identity_cache))
+                            if cache::is_hub_unreachable(err) =>
+                        {
+                            warn!("IoT Hub is unreachable, using the cached module identities");
+                            identity_cache.list()
+                        }
+                        (Err(err), _) => {
+                            return Err(Error::from(err.context(ErrorKind::IdentityOperation(
+                                IdentityOperation::ListIdentities,
+                            ))));
+                        }
+                    };
+                    Ok(modules.into_iter().map(HubIdentity::new).collect())
+                }),
         )
     }
 
     fn get(&self, id: IdentitySpec) -> Self::GetFuture {
         let module_id = id.module_id().to_string();
 
-        Box::new(self.state.client.get_module_by_id(module_id.clone()).then(
-            |module| match module {
-                Ok(module) => Ok(Some(HubIdentity::new(module))),
-                Err(err) => {
-                    if let HubErrorKind::GetModuleWithReason(_, HubReason::ModuleNotFound) =
-                        err.kind()
-                    {
-                        Ok(None)
-                    } else {
-                        Err(Error::from(err.context(ErrorKind::IdentityOperation(
-                            IdentityOperation::GetIdentity(module_id),
-                        ))))
+        let idman = self.clone();
+        Box::new(
+            self.monitored(self.state.client.get_module_by_id(module_id.clone()))
+                .then(move |module| match module {
+                    Ok(module) => Ok(Some(idman.cache_module(module))),
+                    Err(err) => {
+                        if let HubErrorKind::GetModuleWithReason(_, HubReason::ModuleNotFound) =
+                            err.kind()
+                        {
+                            if let Some(cache) = &idman.cache {
+                                cache.remove(&module_id);
+                            }
+                            Ok(None)
+                        } else if let Some(module) = idman.cached(&err, &module_id) {
+                            Ok(Some(HubIdentity::new(module)))
+                        } else {
+                            Err(Error::from(err.context(ErrorKind::IdentityOperation(
+                                IdentityOperation::GetIdentity(module_id),
+                            ))))
+                        }
                     }
-                }
-            },
-        ))
+                }),
+        )
     }
 
     fn delete(&mut self, id: IdentitySpec) -> Self::DeleteFuture {
         let module_id = id.module_id().to_string();
 
-        Box::new(self.state.client.delete_module(&module_id).map_err(|err| {
-            Error::from(err.context(ErrorKind::IdentityOperation(
-                IdentityOperation::DeleteIdentity(module_id),
-            )))
-        }))
+        let cache = self.cache.clone();
+        Box::new(
+            self.monitored(self.state.client.delete_module(&module_id))
+                .then(move |result| match result {
+                    Ok(()) => {
+                        if let Some(cache) = cache {
+                            cache.remove(&module_id);
+                        }
+                        Ok(())
+                    }
+                    Err(err) => Err(Error::from(err.context(ErrorKind::IdentityOperation(
+                        IdentityOperation::DeleteIdentity(module_id),
+                    )))),
+                }),
+        )
     }
 }
 
@@ -606,6 +804,71 @@ mod tests {
         assert_eq!(hub_identity.hub_module(), &expected_module_result);
     }
 
+    #[test]
+    fn create_with_x509_module_auth_succeeds() {
+        let api_version = "2018-04-10".to_string();
+        let host_name = Url::parse("http://localhost").unwrap();
+        let expected_module = Module::default()
+            .with_device_id("d1".to_string())
+            .with_module_id("m1".to_string())
+            .with_authentication(
+                AuthMechanism::default().with_type(HubAuthType::CertificateAuthority),
+            );
+        let expected_module_result = expected_module
+            .clone()
+            .with_generation_id("g1".to_string())
+            .with_managed_by("iotedge".to_string());
+
+        let handler = move |req: Request<Body>| {
+            assert_eq!(req.method(), &Method::PUT);
+            assert_eq!(req.uri().path(), "/devices/d1/modules/m1");
+
+            // there are no keys to derive so the module is never updated
+            assert!(req.headers().get(hyper::header::IF_MATCH).is_none());
+
+            let expected_module_copy = expected_module.clone();
+            req.into_body()
+                .concat2()
+                .and_then(|req_body| Ok(serde_json::from_slice::<Module>(&req_body).unwrap()))
+                .and_then(move |module| {
+                    assert_eq!(module, expected_module_copy);
+
+                    let mut response = Response::new(
+                        serde_json::to_string(
+                            &module
+                                .with_generation_id("g1".to_string())
+                                .with_managed_by("iotedge".to_string()),
+                        )
+                        .unwrap()
+                        .into(),
+                    );
+                    response
+                        .headers_mut()
+                        .typed_insert(&ContentType(mime::APPLICATION_JSON));
+                    Ok(response)
+                })
+        };
+        let token_source = SasTokenSource::new(
+            "hub".to_string(),
+            "device".to_string(),
+            MemoryKey::new("device"),
+        );
+        let client = Client::new(handler, Some(token_source), api_version, host_name).unwrap();
+        let device_client = DeviceClient::new(client, "d1".to_string()).unwrap();
+
+        let mut identity_manager =
+            HubIdentityManager::with_x509_module_auth(MemoryKeyStore::new(), device_client);
+        let task = identity_manager.create(IdentitySpec::new("m1".to_string()));
+
+        let hub_identity = tokio::runtime::current_thread::Runtime::new()
+            .unwrap()
+            .block_on(task)
+            .unwrap();
+
+        assert_eq!(hub_identity.hub_module(), &expected_module_result);
+        assert_eq!(AuthType::X509, hub_identity.auth_type());
+    }
+
     #[test]
     fn list_succeeds() {
         let m1pkey = "m1pkey";
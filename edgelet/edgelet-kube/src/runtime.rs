@@ -5,18 +5,21 @@ use std::collections::HashMap;
 use std::sync::{Arc, Mutex};
 use std::time::Duration;
 
+use chrono::{DateTime, Utc};
 use failure::Fail;
 use futures::prelude::*;
 use futures::{future, stream, Async, Future, Stream};
 use hyper::client::HttpConnector;
 use hyper::service::Service;
+use hyper::upgrade::Upgraded;
 use hyper::{Body, Chunk as HyperChunk, Request};
 use hyper_tls::HttpsConnector;
 
 use edgelet_core::{
-    AuthId, Authenticator, GetTrustBundle, LogOptions, MakeModuleRuntime, ModuleRegistry,
-    ModuleRuntime, ModuleRuntimeState, ModuleSpec, ProvisioningResult as CoreProvisioningResult,
-    RuntimeOperation, SystemInfo, SystemResources,
+    AuthId, Authenticator, ErrorKind as CoreErrorKind, ExecOptions, GetTrustBundle, LogOptions,
+    MakeModuleRuntime, ModuleEvent, ModuleRegistry, ModuleRuntime, ModuleRuntimeState, ModuleSpec,
+    Network, ProvisioningResult as CoreProvisioningResult, RuntimeOperation, SystemInfo,
+    SystemResources,
 };
 use edgelet_docker::DockerConfig;
 use kube_client::{get_config, Client as KubeClient, HttpClient, TokenSource, ValueToken};
@@ -81,6 +84,8 @@ where
     type Error = Error;
     type PullFuture = Box<dyn Future<Item = (), Error = Self::Error> + Send>;
     type RemoveFuture = Box<dyn Future<Item = (), Error = Self::Error>>;
+    type LoginFuture = Box<dyn Future<Item = (), Error = Self::Error> + Send>;
+    type LogoutFuture = Box<dyn Future<Item = (), Error = Self::Error> + Send>;
     type Config = DockerConfig;
 
     fn pull(&self, config: &Self::Config) -> Self::PullFuture {
@@ -90,6 +95,16 @@ where
     fn remove(&self, _: &str) -> Self::RemoveFuture {
         Box::new(future::ok(()))
     }
+
+    fn login(&self, _registry: &str, _username: &str, _password: &str) -> Self::LoginFuture {
+        // TODO: add support for stored registry credentials on k8s, as image pull secrets
+        // that modules without auth in their config are created with
+        Box::new(future::err(not_supported(ErrorKind::RegistryOperation)))
+    }
+
+    fn logout(&self, _registry: &str) -> Self::LogoutFuture {
+        Box::new(future::err(not_supported(ErrorKind::RegistryOperation)))
+    }
 }
 
 impl MakeModuleRuntime
@@ -147,6 +162,8 @@ where
     type ModuleRegistry = Self;
     type Chunk = Chunk;
     type Logs = Logs;
+    type Events = Box<dyn Stream<Item = ModuleEvent, Error = Self::Error> + Send>;
+    type ExecStream = Upgraded;
 
     type CreateFuture = Box<dyn Future<Item = (), Error = Self::Error> + Send>;
     type GetFuture =
@@ -155,6 +172,11 @@ where
     type ListWithDetailsStream =
         Box<dyn Stream<Item = (Self::Module, ModuleRuntimeState), Error = Self::Error> + Send>;
     type LogsFuture = Box<dyn Future<Item = Self::Logs, Error = Self::Error> + Send>;
+    type EventsFuture = Box<dyn Future<Item = Self::Events, Error = Self::Error> + Send>;
+    type ExecFuture =
+        Box<dyn Future<Item = (String, Self::ExecStream), Error = Self::Error> + Send>;
+    type ExecResizeFuture = Box<dyn Future<Item = (), Error = Self::Error> + Send>;
+    type ExecExitCodeFuture = Box<dyn Future<Item = Option<i64>, Error = Self::Error> + Send>;
     type RemoveFuture = Box<dyn Future<Item = (), Error = Self::Error> + Send>;
     type RestartFuture = Box<dyn Future<Item = (), Error = Self::Error> + Send>;
     type StartFuture = Box<dyn Future<Item = (), Error = Self::Error> + Send>;
@@ -163,6 +185,9 @@ where
     type SystemResourcesFuture =
         Box<dyn Future<Item = SystemResources, Error = Self::Error> + Send>;
     type RemoveAllFuture = Box<dyn Future<Item = (), Error = Self::Error> + Send>;
+    type ListNetworksFuture = Box<dyn Future<Item = Vec<Network>, Error = Self::Error> + Send>;
+    type AttachNetworkFuture = Box<dyn Future<Item = (), Error = Self::Error> + Send>;
+    type DetachNetworkFuture = Box<dyn Future<Item = (), Error = Self::Error> + Send>;
 
     fn create(&self, module: ModuleSpec<Self::Config>) -> Self::CreateFuture {
         Box::new(create_module(self, module))
@@ -247,16 +272,10 @@ where
     }
 
     fn system_resources(&self) -> Self::SystemResourcesFuture {
-        // TODO: add support for system resources on k8s
-        Box::new(future::ok(SystemResources::new(
-            0,
-            0,
-            0.0,
-            0,
-            0,
-            vec![],
-            "".to_owned(),
-        )))
+        // TODO: add support for system resources on k8s, eg module usage from the metrics API
+        Box::new(future::err(not_supported(ErrorKind::RuntimeOperation(
+            RuntimeOperation::SystemResources,
+        ))))
     }
 
     fn list(&self) -> Self::ListFuture {
@@ -296,6 +315,37 @@ where
         Box::new(future::ok(Logs("".to_string(), Body::empty())))
     }
 
+    fn events(&self, _since: Option<DateTime<Utc>>) -> Self::EventsFuture {
+        Box::new(future::err(not_supported(ErrorKind::RuntimeOperation(
+            RuntimeOperation::GetModuleEvents,
+        ))))
+    }
+
+    fn exec(&self, id: &str, _options: &ExecOptions) -> Self::ExecFuture {
+        // TODO: add support for exec on k8s, through the exec subresource of the module's pod
+        Box::new(future::err(not_supported(ErrorKind::RuntimeOperation(
+            RuntimeOperation::ExecModule(id.to_string()),
+        ))))
+    }
+
+    fn exec_resize(
+        &self,
+        id: &str,
+        _exec_id: &str,
+        _height: u16,
+        _width: u16,
+    ) -> Self::ExecResizeFuture {
+        Box::new(future::err(not_supported(ErrorKind::RuntimeOperation(
+            RuntimeOperation::ExecModuleResize(id.to_string()),
+        ))))
+    }
+
+    fn exec_exit_code(&self, id: &str, _exec_id: &str) -> Self::ExecExitCodeFuture {
+        Box::new(future::err(not_supported(ErrorKind::RuntimeOperation(
+            RuntimeOperation::ExecModuleExitCode(id.to_string()),
+        ))))
+    }
+
     fn registry(&self) -> &Self::ModuleRegistry {
         self
     }
@@ -303,6 +353,25 @@ where
     fn remove_all(&self) -> Self::RemoveAllFuture {
         Box::new(future::ok(()))
     }
+
+    // Pod networking on k8s is owned by the cluster's CNI plugin.
+    fn list_networks(&self) -> Self::ListNetworksFuture {
+        Box::new(future::err(not_supported(ErrorKind::RuntimeOperation(
+            RuntimeOperation::ListNetworks,
+        ))))
+    }
+
+    fn attach_network(&self, id: &str, network: &str) -> Self::AttachNetworkFuture {
+        Box::new(future::err(not_supported(ErrorKind::RuntimeOperation(
+            RuntimeOperation::AttachNetwork(id.to_string(), network.to_string()),
+        ))))
+    }
+
+    fn detach_network(&self, id: &str, network: &str) -> Self::DetachNetworkFuture {
+        Box::new(future::err(not_supported(ErrorKind::RuntimeOperation(
+            RuntimeOperation::DetachNetwork(id.to_string(), network.to_string()),
+        ))))
+    }
 }
 
 impl<T, S> Authenticator for KubeModuleRuntime<T, S>
@@ -375,20 +444,28 @@ impl AsRef<[u8]> for Chunk {
     }
 }
 
+// Operations that have no Kubernetes counterpart fail with a core `ModuleRuntimeNotSupported` root
+// cause, which the management API reports as 501 Not Implemented.
+fn not_supported(context: ErrorKind) -> Error {
+    Error::from(CoreErrorKind::ModuleRuntimeNotSupported.context(context))
+}
+
 #[cfg(test)]
 mod tests {
+    use failure::Fail;
     use hyper::service::service_fn;
     use hyper::{Body, Method, Request, StatusCode};
     use maplit::btreemap;
     use serde_json::json;
     use tokio::runtime::Runtime;
 
-    use edgelet_core::ModuleRuntime;
+    use edgelet_core::{ErrorKind as CoreErrorKind, ExecOptions, ModuleRegistry, ModuleRuntime};
     use edgelet_test_utils::routes;
     use edgelet_test_utils::web::{
         make_req_dispatcher, HttpMethod, RequestHandler, RequestPath, ResponseFuture,
     };
 
+    use crate::error::Error;
     use crate::tests::{create_runtime, make_settings, not_found_handler, response};
 
     #[test]
@@ -459,6 +536,84 @@ mod tests {
         );
     }
 
+    #[test]
+    fn runtime_system_resources_not_supported() {
+        let settings = make_settings(None);
+        let runtime = create_runtime(settings, service_fn(not_found_handler));
+
+        let task = runtime.system_resources();
+
+        let mut runtime = Runtime::new().unwrap();
+        let err = runtime.block_on(task).unwrap_err();
+
+        assert_not_supported(&err);
+    }
+
+    #[test]
+    fn runtime_exec_not_supported() {
+        let settings = make_settings(None);
+        let runtime = create_runtime(settings, service_fn(not_found_handler));
+
+        let exec = runtime.exec("edgeHub", &ExecOptions::new(vec!["ls".to_string()]));
+        let resize = runtime.exec_resize("edgeHub", "exec1", 24, 80);
+        let exit_code = runtime.exec_exit_code("edgeHub", "exec1");
+
+        let mut runtime = Runtime::new().unwrap();
+        assert_not_supported(&runtime.block_on(exec).map(|_| ()).unwrap_err());
+        assert_not_supported(&runtime.block_on(resize).unwrap_err());
+        assert_not_supported(&runtime.block_on(exit_code).map(|_| ()).unwrap_err());
+    }
+
+    #[test]
+    fn runtime_events_not_supported() {
+        let settings = make_settings(None);
+        let runtime = create_runtime(settings, service_fn(not_found_handler));
+
+        let task = runtime.events(None);
+
+        let mut runtime = Runtime::new().unwrap();
+        let err = runtime.block_on(task).map(|_| ()).unwrap_err();
+
+        assert_not_supported(&err);
+    }
+
+    #[test]
+    fn runtime_registry_login_not_supported() {
+        let settings = make_settings(None);
+        let runtime = create_runtime(settings, service_fn(not_found_handler));
+
+        let login = runtime
+            .registry()
+            .login("example.azurecr.io", "user", "pass");
+        let logout = runtime.registry().logout("example.azurecr.io");
+
+        let mut runtime = Runtime::new().unwrap();
+        assert_not_supported(&runtime.block_on(login).unwrap_err());
+        assert_not_supported(&runtime.block_on(logout).unwrap_err());
+    }
+
+    #[test]
+    fn runtime_networks_not_supported() {
+        let settings = make_settings(None);
+        let runtime = create_runtime(settings, service_fn(not_found_handler));
+
+        let list = runtime.list_networks();
+        let attach = runtime.attach_network("edgeHub", "fieldbus");
+        let detach = runtime.detach_network("edgeHub", "fieldbus");
+
+        let mut runtime = Runtime::new().unwrap();
+        assert_not_supported(&runtime.block_on(list).unwrap_err());
+        assert_not_supported(&runtime.block_on(attach).unwrap_err());
+        assert_not_supported(&runtime.block_on(detach).unwrap_err());
+    }
+
+    fn assert_not_supported(err: &Error) {
+        match Fail::find_root_cause(err).downcast_ref::<CoreErrorKind>() {
+            Some(CoreErrorKind::ModuleRuntimeNotSupported) => (),
+            _ => panic!("expected ModuleRuntimeNotSupported but got {:?}", err),
+        }
+    }
+
     fn list_node_handler() -> impl Fn(Request<Body>) -> ResponseFuture + Clone {
         move |_| {
             response(StatusCode::OK, || {
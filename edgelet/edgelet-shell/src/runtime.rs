@@ -2,12 +2,15 @@
 
 use std::time::Duration;
 
+use chrono::{DateTime, Utc};
 use futures::prelude::*;
 use futures::Stream;
+use hyper::upgrade::Upgraded;
 
 use edgelet_core::{
-    AuthId, Authenticator, GetTrustBundle, LogOptions, MakeModuleRuntime, ModuleRegistry,
-    ModuleRuntime, ModuleRuntimeState, ModuleSpec, SystemInfo as CoreSystemInfo,
+    AuthId, Authenticator, ExecOptions, GetTrustBundle, LogOptions, MakeModuleRuntime, ModuleEvent,
+    ModuleRegistry, ModuleRuntime, ModuleRuntimeState, ModuleSpec, Network,
+    SystemInfo as CoreSystemInfo, SystemResources,
 };
 use provisioning::ProvisioningResult;
 
@@ -29,6 +32,8 @@ impl ModuleRegistry for ShellModuleRuntime {
     type Error = Error;
     type PullFuture = Box<dyn Future<Item = (), Error = Self::Error> + Send>;
     type RemoveFuture = Box<dyn Future<Item = (), Error = Self::Error>>;
+    type LoginFuture = Box<dyn Future<Item = (), Error = Self::Error> + Send>;
+    type LogoutFuture = Box<dyn Future<Item = (), Error = Self::Error> + Send>;
     type Config = ShellConfig;
 
     fn pull(&self, _config: &Self::Config) -> Self::PullFuture {
@@ -53,6 +58,17 @@ impl ModuleRegistry for ShellModuleRuntime {
         //   - containerd handles pruning automatically
         unimplemented!()
     }
+
+    fn login(&self, _registry: &str, _username: &str, _password: &str) -> Self::LoginFuture {
+        // edgelet:
+        // - store { registry, username, password }, and pass them as the auth of pulls
+        //   whose config has none
+        unimplemented!()
+    }
+
+    fn logout(&self, _registry: &str) -> Self::LogoutFuture {
+        unimplemented!()
+    }
 }
 
 impl MakeModuleRuntime for ShellModuleRuntime {
@@ -85,6 +101,8 @@ impl ModuleRuntime for ShellModuleRuntime {
     type ModuleRegistry = Self;
     type Chunk = Chunk;
     type Logs = Logs;
+    type Events = Box<dyn Stream<Item = ModuleEvent, Error = Self::Error> + Send>;
+    type ExecStream = Upgraded;
 
     type CreateFuture = Box<dyn Future<Item = (), Error = Self::Error> + Send>;
     type GetFuture =
@@ -93,12 +111,22 @@ impl ModuleRuntime for ShellModuleRuntime {
     type ListWithDetailsStream =
         Box<dyn Stream<Item = (Self::Module, ModuleRuntimeState), Error = Self::Error> + Send>;
     type LogsFuture = Box<dyn Future<Item = Self::Logs, Error = Self::Error> + Send>;
+    type EventsFuture = Box<dyn Future<Item = Self::Events, Error = Self::Error> + Send>;
+    type ExecFuture =
+        Box<dyn Future<Item = (String, Self::ExecStream), Error = Self::Error> + Send>;
+    type ExecResizeFuture = Box<dyn Future<Item = (), Error = Self::Error> + Send>;
+    type ExecExitCodeFuture = Box<dyn Future<Item = Option<i64>, Error = Self::Error> + Send>;
     type RemoveFuture = Box<dyn Future<Item = (), Error = Self::Error> + Send>;
     type RestartFuture = Box<dyn Future<Item = (), Error = Self::Error> + Send>;
     type StartFuture = Box<dyn Future<Item = (), Error = Self::Error> + Send>;
     type StopFuture = Box<dyn Future<Item = (), Error = Self::Error> + Send>;
     type SystemInfoFuture = Box<dyn Future<Item = CoreSystemInfo, Error = Self::Error> + Send>;
+    type SystemResourcesFuture =
+        Box<dyn Future<Item = SystemResources, Error = Self::Error> + Send>;
     type RemoveAllFuture = Box<dyn Future<Item = (), Error = Self::Error> + Send>;
+    type ListNetworksFuture = Box<dyn Future<Item = Vec<Network>, Error = Self::Error> + Send>;
+    type AttachNetworkFuture = Box<dyn Future<Item = (), Error = Self::Error> + Send>;
+    type DetachNetworkFuture = Box<dyn Future<Item = (), Error = Self::Error> + Send>;
 
     fn create(&self, _module: ModuleSpec<Self::Config>) -> Self::CreateFuture {
         // edgelet:
@@ -166,6 +194,16 @@ impl ModuleRuntime for ShellModuleRuntime {
         unimplemented!()
     }
 
+    fn system_resources(&self) -> Self::SystemResourcesFuture {
+        // edgelet:
+        // - shell out { }
+        // shellrt-containerd
+        // - for each running id:
+        //   - (containerd) (tasks.proto) MetricsRequest, and map the cgroup
+        //     metrics onto a ModuleResourceUsage
+        unimplemented!()
+    }
+
     fn list(&self) -> Self::ListFuture {
         unimplemented!()
     }
@@ -185,6 +223,39 @@ impl ModuleRuntime for ShellModuleRuntime {
         unimplemented!()
     }
 
+    fn events(&self, _since: Option<DateTime<Utc>>) -> Self::EventsFuture {
+        // shellrt-containerd
+        // - (containerd) (events.proto) SubscribeRequest, filtered to task events, and map
+        //   TaskCreate/TaskStart/TaskExit/TaskOOM/TaskDelete onto a ModuleEvent
+        unimplemented!()
+    }
+
+    fn exec(&self, _id: &str, _options: &ExecOptions) -> Self::ExecFuture {
+        // shellrt-containerd
+        // - (containerd) (tasks.proto) ExecProcessRequest with stdio FIFOs, then StartRequest
+        //   for the exec ID, and proxy the FIFOs to the returned stream
+        unimplemented!()
+    }
+
+    fn exec_resize(
+        &self,
+        _id: &str,
+        _exec_id: &str,
+        _height: u16,
+        _width: u16,
+    ) -> Self::ExecResizeFuture {
+        // shellrt-containerd
+        // - (containerd) (tasks.proto) ResizePtyRequest for the exec ID
+        unimplemented!()
+    }
+
+    fn exec_exit_code(&self, _id: &str, _exec_id: &str) -> Self::ExecExitCodeFuture {
+        // shellrt-containerd
+        // - (containerd) (tasks.proto) WaitRequest for the exec ID, or DeleteProcessRequest once
+        //   it has exited
+        unimplemented!()
+    }
+
     fn registry(&self) -> &Self::ModuleRegistry {
         self
     }
@@ -197,6 +268,24 @@ impl ModuleRuntime for ShellModuleRuntime {
         //   - (containerd) (tasks.proto) DeleteTaskRequest
         unimplemented!()
     }
+
+    fn list_networks(&self) -> Self::ListNetworksFuture {
+        // shellrt-containerd
+        // - list the CNI network configurations the shim was configured with
+        unimplemented!()
+    }
+
+    fn attach_network(&self, _id: &str, _network: &str) -> Self::AttachNetworkFuture {
+        // shellrt-containerd
+        // - CNI ADD for the network against the task's network namespace
+        unimplemented!()
+    }
+
+    fn detach_network(&self, _id: &str, _network: &str) -> Self::DetachNetworkFuture {
+        // shellrt-containerd
+        // - CNI DEL for the network against the task's network namespace
+        unimplemented!()
+    }
 }
 
 impl Authenticator for ShellModuleRuntime {
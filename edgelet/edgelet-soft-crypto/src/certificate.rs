@@ -0,0 +1,99 @@
+// Copyright (c) Microsoft. All rights reserved.
+
+use chrono::{DateTime, NaiveDateTime, Utc};
+use failure::{Fail, ResultExt};
+use openssl::asn1::Asn1TimeRef;
+use openssl::nid::Nid;
+use openssl::x509::X509;
+
+use edgelet_core::{
+    Certificate as CoreCertificate, Error as CoreError, ErrorKind as CoreErrorKind,
+    KeyBytes as CoreKeyBytes, PrivateKey as CorePrivateKey,
+};
+
+use crate::error::{Error, ErrorKind};
+
+#[derive(Clone, Debug)]
+pub struct Certificate {
+    alias: String,
+    /// The certificate followed by the chain of its issuers
+    pem: String,
+    private_key: Option<Vec<u8>>,
+}
+
+impl Certificate {
+    pub(crate) fn new(alias: String, pem: String, private_key: Option<Vec<u8>>) -> Self {
+        Certificate {
+            alias,
+            pem,
+            private_key,
+        }
+    }
+
+    pub(crate) fn alias(&self) -> &str {
+        &self.alias
+    }
+
+    pub(crate) fn pem_str(&self) -> &str {
+        &self.pem
+    }
+
+    pub(crate) fn private_key_pem(&self) -> Option<&[u8]> {
+        self.private_key.as_ref().map(AsRef::as_ref)
+    }
+
+    /// The leaf certificate, which is the first one in the PEM
+    pub(crate) fn x509(&self) -> Result<X509, Error> {
+        X509::stack_from_pem(self.pem.as_bytes())
+            .ok()
+            .and_then(|certs| certs.into_iter().next())
+            .ok_or_else(|| Error::from(ErrorKind::LoadCertificate(self.alias.clone())))
+    }
+
+    pub(crate) fn not_after(&self) -> Result<DateTime<Utc>, Error> {
+        let x509 = self.x509()?;
+        let not_after = parse_openssl_time(x509.not_after())
+            .context(ErrorKind::LoadCertificate(self.alias.clone()))?;
+        Ok(not_after)
+    }
+}
+
+impl CoreCertificate for Certificate {
+    type Buffer = String;
+    type KeyBuffer = Vec<u8>;
+
+    fn pem(&self) -> Result<Self::Buffer, CoreError> {
+        Ok(self.pem.clone())
+    }
+
+    fn get_private_key(&self) -> Result<Option<CorePrivateKey<Self::KeyBuffer>>, CoreError> {
+        Ok(self
+            .private_key
+            .clone()
+            .map(|key| CorePrivateKey::Key(CoreKeyBytes::Pem(key))))
+    }
+
+    fn get_valid_to(&self) -> Result<DateTime<Utc>, CoreError> {
+        self.not_after()
+            .map_err(|err| CoreError::from(err.context(CoreErrorKind::CertificateDetail)))
+    }
+
+    fn get_common_name(&self) -> Result<String, CoreError> {
+        let common_name = self.x509().and_then(|x509| {
+            x509.subject_name()
+                .entries_by_nid(Nid::COMMONNAME)
+                .next()
+                .and_then(|entry| entry.data().as_utf8().ok())
+                .map(|common_name| common_name.to_string())
+                .ok_or_else(|| Error::from(ErrorKind::LoadCertificate(self.alias.clone())))
+        });
+        common_name.map_err(|err| CoreError::from(err.context(CoreErrorKind::CertificateDetail)))
+    }
+}
+
+/// `Asn1TimeRef` doesn't expose the time as a Rust-friendly type, but its Display impl uses
+/// `ASN1_TIME_print`, so print it and parse it back.
+pub(crate) fn parse_openssl_time(time: &Asn1TimeRef) -> chrono::ParseResult<DateTime<Utc>> {
+    let time = NaiveDateTime::parse_from_str(&time.to_string(), "%b %e %H:%M:%S %Y GMT")?;
+    Ok(DateTime::<Utc>::from_utc(time, Utc))
+}
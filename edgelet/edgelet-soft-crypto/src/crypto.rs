@@ -0,0 +1,699 @@
+// Copyright (c) Microsoft. All rights reserved.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use chrono::Utc;
+use failure::{Fail, ResultExt};
+use openssl::asn1::Asn1Time;
+use openssl::bn::{BigNum, MsbOption};
+use openssl::ec::{EcGroup, EcKey};
+use openssl::hash::MessageDigest;
+use openssl::nid::Nid;
+use openssl::pkey::PKey;
+use openssl::rand::rand_bytes;
+use openssl::symm::{decrypt_aead, encrypt_aead, Cipher};
+use openssl::x509::extension::{
+    AuthorityKeyIdentifier, BasicConstraints, ExtendedKeyUsage, KeyUsage, SubjectAlternativeName,
+    SubjectKeyIdentifier,
+};
+use openssl::x509::{X509Name, X509};
+
+use edgelet_core::{
+    CertificateIssuer as CoreCertificateIssuer, CertificateProperties as CoreCertificateProperties,
+    CertificateType as CoreCertificateType, CreateCertificate as CoreCreateCertificate,
+    Decrypt as CoreDecrypt, Encrypt as CoreEncrypt, Error as CoreError, ErrorKind as CoreErrorKind,
+    GetHsmVersion as CoreGetHsmVersion, GetIssuerAlias as CoreGetIssuerAlias,
+    GetTrustBundle as CoreGetTrustBundle, MakeRandom as CoreMakeRandom,
+    MasterEncryptionKey as CoreMasterEncryptionKey, IOTEDGED_CA_ALIAS,
+};
+
+use crate::certificate::{parse_openssl_time, Certificate};
+use crate::error::{Error, ErrorKind};
+use crate::store::{self, Store};
+
+/// Alias of the device CA certificate, which issues the workload CA
+pub const DEVICE_CA_ALIAS: &str = "iotedge-device-ca";
+
+/// Common name of the device CA that is generated when the config doesn't provide one
+const QUICKSTART_DEVICE_CA_COMMON_NAME: &str = "iotedged device ca quickstart";
+
+const MASTER_KEY_LEN: usize = 32;
+
+/// Ciphertexts are `version || tag || AES-256-GCM(plaintext)`, so that the format can change later.
+const CIPHERTEXT_VERSION: u8 = 1;
+const TAG_LEN: usize = 16;
+
+/// Files of a device CA certificate provided in the config
+#[derive(Clone, Debug)]
+struct DeviceCaFiles {
+    cert: PathBuf,
+    private_key: PathBuf,
+    trusted_ca_certs: PathBuf,
+}
+
+#[derive(Clone)]
+pub struct Crypto {
+    store: Arc<Store>,
+    device_ca: Option<DeviceCaFiles>,
+    auto_generated_ca_lifetime_seconds: u64,
+    lock: Arc<Mutex<()>>,
+}
+
+impl Crypto {
+    /// Creates a crypto store under `homedir`.
+    ///
+    /// Without a device CA from `with_device_ca`, a self-signed device CA valid for
+    /// `auto_generated_ca_lifetime_seconds` is generated on first use, and is regenerated when it expires.
+    pub fn new(homedir: &Path, auto_generated_ca_lifetime_seconds: u64) -> Result<Self, Error> {
+        let store =
+            Store::new(homedir).context(ErrorKind::Initialize(homedir.display().to_string()))?;
+        Ok(Crypto {
+            store: Arc::new(store),
+            device_ca: None,
+            auto_generated_ca_lifetime_seconds,
+            lock: Arc::new(Mutex::new(())),
+        })
+    }
+
+    /// Uses the given device CA certificate and private key to issue the workload CA,
+    /// and the given trusted CA certificates as the trust bundle.
+    pub fn with_device_ca(
+        mut self,
+        cert: PathBuf,
+        private_key: PathBuf,
+        trusted_ca_certs: PathBuf,
+    ) -> Self {
+        self.device_ca = Some(DeviceCaFiles {
+            cert,
+            private_key,
+            trusted_ca_certs,
+        });
+        self
+    }
+
+    fn load(&self, alias: &str) -> Result<Option<Certificate>, Error> {
+        if alias == DEVICE_CA_ALIAS {
+            return self.device_ca().map(Some);
+        }
+
+        let pem = store::read(&self.store.cert_path(alias))
+            .context(ErrorKind::LoadCertificate(alias.to_string()))?;
+        let private_key = store::read(&self.store.private_key_path(alias))
+            .context(ErrorKind::LoadCertificate(alias.to_string()))?;
+        match pem {
+            Some(pem) => {
+                let pem = String::from_utf8(pem)
+                    .context(ErrorKind::LoadCertificate(alias.to_string()))?;
+                Ok(Some(Certificate::new(alias.to_string(), pem, private_key)))
+            }
+            None => Ok(None),
+        }
+    }
+
+    fn save(&self, cert: &Certificate, alias: &str) -> Result<(), Error> {
+        if let Some(private_key) = cert.private_key_pem() {
+            store::write_private(&self.store.private_key_path(alias), private_key)
+                .context(ErrorKind::CreateCertificate(alias.to_string()))?;
+        }
+        store::write_private(&self.store.cert_path(alias), cert.pem_str().as_bytes())
+            .context(ErrorKind::CreateCertificate(alias.to_string()))?;
+        Ok(())
+    }
+
+    fn device_ca(&self) -> Result<Certificate, Error> {
+        if let Some(device_ca) = &self.device_ca {
+            let pem = fs::read_to_string(&device_ca.cert).context(ErrorKind::DeviceCa)?;
+            let private_key = fs::read(&device_ca.private_key).context(ErrorKind::DeviceCa)?;
+            return Ok(Certificate::new(
+                DEVICE_CA_ALIAS.to_string(),
+                pem,
+                Some(private_key),
+            ));
+        }
+
+        let pem =
+            store::read(&self.store.cert_path(DEVICE_CA_ALIAS)).context(ErrorKind::DeviceCa)?;
+        let private_key = store::read(&self.store.private_key_path(DEVICE_CA_ALIAS))
+            .context(ErrorKind::DeviceCa)?;
+        if let (Some(pem), Some(private_key)) = (pem, private_key) {
+            let pem = String::from_utf8(pem).context(ErrorKind::DeviceCa)?;
+            let cert = Certificate::new(DEVICE_CA_ALIAS.to_string(), pem, Some(private_key));
+            if cert.not_after()? > Utc::now() {
+                return Ok(cert);
+            }
+        }
+
+        let properties = CoreCertificateProperties::new(
+            self.auto_generated_ca_lifetime_seconds,
+            QUICKSTART_DEVICE_CA_COMMON_NAME.to_string(),
+            CoreCertificateType::Ca,
+            DEVICE_CA_ALIAS.to_string(),
+        );
+        let cert = issue(&properties, None).context(ErrorKind::DeviceCa)?;
+        self.save(&cert, DEVICE_CA_ALIAS)
+            .context(ErrorKind::DeviceCa)?;
+        Ok(cert)
+    }
+
+    fn trust_bundle(&self) -> Result<Certificate, Error> {
+        match &self.device_ca {
+            Some(device_ca) => {
+                let pem = fs::read_to_string(&device_ca.trusted_ca_certs)
+                    .context(ErrorKind::TrustBundle)?;
+                Ok(Certificate::new("trust-bundle".to_string(), pem, None))
+            }
+            None => {
+                // The quickstart device CA is self-signed, so it is its own root.
+                let device_ca = self.device_ca().context(ErrorKind::TrustBundle)?;
+                Ok(Certificate::new(
+                    "trust-bundle".to_string(),
+                    device_ca.pem_str().to_string(),
+                    None,
+                ))
+            }
+        }
+    }
+
+    fn master_key(&self) -> Result<Vec<u8>, Error> {
+        store::read(&self.store.master_key_path())
+            .context(ErrorKind::MasterEncryptionKey)?
+            .filter(|key| key.len() == MASTER_KEY_LEN)
+            .ok_or_else(|| Error::from(ErrorKind::MasterEncryptionKeyNotFound))
+    }
+}
+
+/// Issues a certificate for `properties` with a new P-256 key. The certificate is self-signed if `issuer` is `None`.
+fn issue(
+    properties: &CoreCertificateProperties,
+    issuer: Option<&Certificate>,
+) -> Result<Certificate, Error> {
+    let alias = properties.alias();
+    let context = || ErrorKind::CreateCertificate(alias.to_string());
+
+    let group = EcGroup::from_curve_name(Nid::X9_62_PRIME256V1).with_context(|_| context())?;
+    let private_key = EcKey::generate(&group)
+        .and_then(PKey::from_ec_key)
+        .with_context(|_| context())?;
+
+    let mut name = X509Name::builder().with_context(|_| context())?;
+    name.append_entry_by_nid(Nid::COMMONNAME, properties.common_name())
+        .with_context(|_| context())?;
+    let name = name.build();
+
+    let (issuer_x509, issuer_private_key) = match issuer {
+        Some(issuer) => {
+            let x509 = issuer.x509()?;
+            let private_key = issuer
+                .private_key_pem()
+                .ok_or_else(|| ErrorKind::LoadCertificate(issuer.alias().to_string()))
+                .and_then(|pem| {
+                    PKey::private_key_from_pem(pem)
+                        .map_err(|_| ErrorKind::LoadCertificate(issuer.alias().to_string()))
+                })?;
+            (Some(x509), Some(private_key))
+        }
+        None => (None, None),
+    };
+
+    // A certificate can't outlive its issuer.
+    let now = Utc::now().timestamp();
+    #[allow(clippy::cast_possible_wrap)]
+    let mut not_after = now.saturating_add(*properties.validity_in_secs() as i64);
+    if let Some(issuer_x509) = &issuer_x509 {
+        let issuer_not_after = parse_openssl_time(issuer_x509.not_after())
+            .with_context(|_| context())?
+            .timestamp();
+        not_after = not_after.min(issuer_not_after);
+    }
+
+    let mut serial_number = BigNum::new().with_context(|_| context())?;
+    serial_number
+        .rand(127, MsbOption::MAYBE_ZERO, false)
+        .with_context(|_| context())?;
+    let serial_number = serial_number
+        .to_asn1_integer()
+        .with_context(|_| context())?;
+
+    let mut builder = X509::builder().with_context(|_| context())?;
+    builder.set_version(2).with_context(|_| context())?;
+    builder
+        .set_serial_number(&serial_number)
+        .with_context(|_| context())?;
+    builder
+        .set_subject_name(&name)
+        .with_context(|_| context())?;
+    builder
+        .set_issuer_name(
+            issuer_x509
+                .as_ref()
+                .map_or(&*name, |x509| x509.subject_name()),
+        )
+        .with_context(|_| context())?;
+    let not_before = asn1_time(now).with_context(|_| context())?;
+    builder
+        .set_not_before(&not_before)
+        .with_context(|_| context())?;
+    let not_after = asn1_time(not_after).with_context(|_| context())?;
+    builder
+        .set_not_after(&not_after)
+        .with_context(|_| context())?;
+    builder
+        .set_pubkey(&private_key)
+        .with_context(|_| context())?;
+
+    let extensions = match properties.certificate_type() {
+        CoreCertificateType::Ca => vec![
+            BasicConstraints::new().critical().ca().build(),
+            KeyUsage::new()
+                .critical()
+                .digital_signature()
+                .key_cert_sign()
+                .crl_sign()
+                .build(),
+        ],
+        CoreCertificateType::Server => vec![
+            BasicConstraints::new().critical().build(),
+            KeyUsage::new()
+                .critical()
+                .digital_signature()
+                .key_agreement()
+                .build(),
+            ExtendedKeyUsage::new().server_auth().build(),
+        ],
+        CoreCertificateType::Client => vec![
+            BasicConstraints::new().critical().build(),
+            KeyUsage::new()
+                .critical()
+                .digital_signature()
+                .key_agreement()
+                .build(),
+            ExtendedKeyUsage::new().client_auth().build(),
+        ],
+        CoreCertificateType::Unknown => vec![BasicConstraints::new().critical().build()],
+    };
+    for extension in extensions {
+        builder
+            .append_extension(extension.with_context(|_| context())?)
+            .with_context(|_| context())?;
+    }
+
+    if let Some(san_entries) = properties.san_entries() {
+        let mut san = SubjectAlternativeName::new();
+        let mut any = false;
+        for entry in san_entries
+            .iter()
+            .flat_map(|entries| entries.split(','))
+            .map(str::trim)
+            .filter(|entry| !entry.is_empty())
+        {
+            let mut parts = entry.splitn(2, ':');
+            match (parts.next(), parts.next()) {
+                (Some("DNS"), Some(value)) => san.dns(value),
+                (Some("URI"), Some(value)) => san.uri(value),
+                (Some("IP"), Some(value)) => san.ip(value),
+                (Some("email"), Some(value)) => san.email(value),
+                _ => {
+                    return Err(Error::from(ErrorKind::InvalidSubjectAltName(
+                        entry.to_string(),
+                    )))
+                }
+            };
+            any = true;
+        }
+        if any {
+            let san = san
+                .build(&builder.x509v3_context(issuer_x509.as_ref().map(|x509| &**x509), None))
+                .with_context(|_| context())?;
+            builder.append_extension(san).with_context(|_| context())?;
+        }
+    }
+
+    let subject_key_identifier = SubjectKeyIdentifier::new()
+        .build(&builder.x509v3_context(issuer_x509.as_ref().map(|x509| &**x509), None))
+        .with_context(|_| context())?;
+    builder
+        .append_extension(subject_key_identifier)
+        .with_context(|_| context())?;
+    if issuer_x509.is_some() {
+        let authority_key_identifier = AuthorityKeyIdentifier::new()
+            .keyid(false)
+            .build(&builder.x509v3_context(issuer_x509.as_ref().map(|x509| &**x509), None))
+            .with_context(|_| context())?;
+        builder
+            .append_extension(authority_key_identifier)
+            .with_context(|_| context())?;
+    }
+
+    builder
+        .sign(
+            issuer_private_key.as_ref().unwrap_or(&private_key),
+            MessageDigest::sha256(),
+        )
+        .with_context(|_| context())?;
+    let x509 = builder.build();
+
+    let mut pem = String::from_utf8(x509.to_pem().with_context(|_| context())?)
+        .with_context(|_| context())?;
+    if let Some(issuer) = issuer {
+        pem.push_str(issuer.pem_str());
+    }
+    let private_key = private_key
+        .private_key_to_pem_pkcs8()
+        .with_context(|_| context())?;
+
+    Ok(Certificate::new(alias.to_string(), pem, Some(private_key)))
+}
+
+#[allow(clippy::cast_possible_truncation)]
+fn asn1_time(timestamp: i64) -> Result<Asn1Time, openssl::error::ErrorStack> {
+    // `time_t` is 32 bits on some of the platforms we build for.
+    Asn1Time::from_unix(timestamp as _)
+}
+
+fn issuer_alias(issuer: CoreCertificateIssuer) -> &'static str {
+    match issuer {
+        CoreCertificateIssuer::DeviceCa => DEVICE_CA_ALIAS,
+        CoreCertificateIssuer::DefaultCa => IOTEDGED_CA_ALIAS,
+    }
+}
+
+impl CoreGetHsmVersion for Crypto {
+    fn get_version(&self) -> Result<String, CoreError> {
+        Ok(env!("CARGO_PKG_VERSION").to_string())
+    }
+}
+
+impl CoreMasterEncryptionKey for Crypto {
+    fn create_key(&self) -> Result<(), CoreError> {
+        let _lock = self
+            .lock
+            .lock()
+            .expect("Acquiring crypto store lock failed");
+        match self.master_key() {
+            Ok(_) => Ok(()),
+            Err(ref err) if *err.kind() == ErrorKind::MasterEncryptionKeyNotFound => {
+                let mut key = [0_u8; MASTER_KEY_LEN];
+                rand_bytes(&mut key)
+                    .context(ErrorKind::MasterEncryptionKey)
+                    .map_err(Error::from)
+                    .and_then(|()| {
+                        store::write_private(&self.store.master_key_path(), &key)
+                            .context(ErrorKind::MasterEncryptionKey)
+                            .map_err(Error::from)
+                    })
+                    .map_err(|err| CoreError::from(err.context(CoreErrorKind::KeyStore)))
+            }
+            Err(err) => Err(CoreError::from(err.context(CoreErrorKind::KeyStore))),
+        }
+    }
+
+    fn destroy_key(&self) -> Result<(), CoreError> {
+        let _lock = self
+            .lock
+            .lock()
+            .expect("Acquiring crypto store lock failed");
+        store::remove(&self.store.master_key_path())
+            .context(ErrorKind::MasterEncryptionKey)
+            .map_err(|err| CoreError::from(Error::from(err).context(CoreErrorKind::KeyStore)))
+    }
+}
+
+impl CoreCreateCertificate for Crypto {
+    type Certificate = Certificate;
+
+    fn create_certificate(
+        &self,
+        properties: &CoreCertificateProperties,
+    ) -> Result<Self::Certificate, CoreError> {
+        let _lock = self
+            .lock
+            .lock()
+            .expect("Acquiring crypto store lock failed");
+        let alias = properties.alias();
+        let result = self
+            .load(issuer_alias(*properties.issuer()))
+            .and_then(|issuer| {
+                issuer.ok_or_else(|| {
+                    Error::from(ErrorKind::CertificateNotFound(
+                        issuer_alias(*properties.issuer()).to_string(),
+                    ))
+                })
+            })
+            .and_then(|issuer| issue(properties, Some(&issuer)))
+            .and_then(|cert| {
+                self.save(&cert, alias)?;
+                Ok(cert)
+            });
+        result.map_err(|err| CoreError::from(err.context(CoreErrorKind::CertificateCreate)))
+    }
+
+    fn destroy_certificate(&self, alias: String) -> Result<(), CoreError> {
+        let _lock = self
+            .lock
+            .lock()
+            .expect("Acquiring crypto store lock failed");
+        store::remove(&self.store.cert_path(&alias))
+            .and_then(|()| store::remove(&self.store.private_key_path(&alias)))
+            .context(ErrorKind::DestroyCertificate(alias))
+            .map_err(|err| {
+                CoreError::from(Error::from(err).context(CoreErrorKind::CertificateDestroy))
+            })
+    }
+
+    fn get_certificate(&self, alias: String) -> Result<Self::Certificate, CoreError> {
+        let _lock = self
+            .lock
+            .lock()
+            .expect("Acquiring crypto store lock failed");
+        self.load(&alias)
+            .and_then(|cert| cert.ok_or_else(|| Error::from(ErrorKind::CertificateNotFound(alias))))
+            .map_err(|err| CoreError::from(err.context(CoreErrorKind::CertificateGet)))
+    }
+}
+
+impl CoreEncrypt for Crypto {
+    type Buffer = Vec<u8>;
+
+    fn encrypt(
+        &self,
+        client_id: &[u8],
+        plaintext: &[u8],
+        initialization_vector: &[u8],
+    ) -> Result<Self::Buffer, CoreError> {
+        let _lock = self
+            .lock
+            .lock()
+            .expect("Acquiring crypto store lock failed");
+        let result = self.master_key().and_then(|key| {
+            let mut tag = [0_u8; TAG_LEN];
+            let ciphertext = encrypt_aead(
+                Cipher::aes_256_gcm(),
+                &key,
+                Some(initialization_vector),
+                client_id,
+                plaintext,
+                &mut tag,
+            )
+            .context(ErrorKind::Encrypt)?;
+
+            let mut result = Vec::with_capacity(1 + TAG_LEN + ciphertext.len());
+            result.push(CIPHERTEXT_VERSION);
+            result.extend_from_slice(&tag);
+            result.extend_from_slice(&ciphertext);
+            Ok(result)
+        });
+        result.map_err(|err| CoreError::from(err.context(CoreErrorKind::KeyStore)))
+    }
+}
+
+impl CoreDecrypt for Crypto {
+    type Buffer = Vec<u8>;
+
+    fn decrypt(
+        &self,
+        client_id: &[u8],
+        ciphertext: &[u8],
+        initialization_vector: &[u8],
+    ) -> Result<Self::Buffer, CoreError> {
+        let _lock = self
+            .lock
+            .lock()
+            .expect("Acquiring crypto store lock failed");
+        let result = self.master_key().and_then(|key| {
+            if ciphertext.len() < 1 + TAG_LEN || ciphertext[0] != CIPHERTEXT_VERSION {
+                return Err(Error::from(ErrorKind::Decrypt));
+            }
+            let (tag, ciphertext) = ciphertext[1..].split_at(TAG_LEN);
+            let plaintext = decrypt_aead(
+                Cipher::aes_256_gcm(),
+                &key,
+                Some(initialization_vector),
+                client_id,
+                ciphertext,
+                tag,
+            )
+            .context(ErrorKind::Decrypt)?;
+            Ok(plaintext)
+        });
+        result.map_err(|err| CoreError::from(err.context(CoreErrorKind::KeyStore)))
+    }
+}
+
+impl CoreGetIssuerAlias for Crypto {
+    fn get_issuer_alias(&self, issuer: CoreCertificateIssuer) -> Result<String, CoreError> {
+        if issuer == CoreCertificateIssuer::DeviceCa {
+            Ok(DEVICE_CA_ALIAS.to_string())
+        } else {
+            Err(CoreError::from(CoreErrorKind::InvalidIssuer))
+        }
+    }
+}
+
+impl CoreGetTrustBundle for Crypto {
+    type Certificate = Certificate;
+
+    fn get_trust_bundle(&self) -> Result<Self::Certificate, CoreError> {
+        let _lock = self
+            .lock
+            .lock()
+            .expect("Acquiring crypto store lock failed");
+        self.trust_bundle()
+            .map_err(|err| CoreError::from(err.context(CoreErrorKind::CertificateGet)))
+    }
+}
+
+impl CoreMakeRandom for Crypto {
+    fn get_random_bytes(&self, buffer: &mut [u8]) -> Result<(), CoreError> {
+        rand_bytes(buffer)
+            .context(ErrorKind::MakeRandom)
+            .map_err(|err| CoreError::from(Error::from(err).context(CoreErrorKind::MakeRandom)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use edgelet_core::Certificate as CoreCertificate;
+    use tempdir::TempDir;
+
+    use super::*;
+
+    fn workload_ca(crypto: &Crypto) -> Certificate {
+        let properties = CoreCertificateProperties::new(
+            3600,
+            "iotedged workload ca".to_string(),
+            CoreCertificateType::Ca,
+            IOTEDGED_CA_ALIAS.to_string(),
+        )
+        .with_issuer(CoreCertificateIssuer::DeviceCa);
+        crypto.create_certificate(&properties).unwrap()
+    }
+
+    #[test]
+    fn issues_chain_from_quickstart_device_ca() {
+        let dir = TempDir::new("soft-crypto").unwrap();
+        let crypto = Crypto::new(dir.path(), 86_400).unwrap();
+        let workload_ca = workload_ca(&crypto);
+        assert_eq!(
+            "iotedged workload ca",
+            workload_ca.get_common_name().unwrap()
+        );
+
+        let properties = CoreCertificateProperties::new(
+            365 * 86_400,
+            "edgehub".to_string(),
+            CoreCertificateType::Server,
+            "edgeHubserver".to_string(),
+        )
+        .with_san_entries(vec!["DNS:edgehub, DNS:edgeHub".to_string()]);
+        let server = crypto.create_certificate(&properties).unwrap();
+
+        assert_eq!("edgehub", server.get_common_name().unwrap());
+        assert!(server.get_private_key().unwrap().is_some());
+
+        // leaf, workload CA, device CA
+        let chain = X509::stack_from_pem(server.pem().unwrap().as_bytes()).unwrap();
+        assert_eq!(3, chain.len());
+        assert_eq!(
+            chain[1].subject_name().to_der().unwrap(),
+            chain[0].issuer_name().to_der().unwrap()
+        );
+
+        // The requested year is capped to the lifetime of the issuers.
+        assert!(server.get_valid_to().unwrap() <= workload_ca.get_valid_to().unwrap());
+
+        let trust_bundle = crypto.get_trust_bundle().unwrap();
+        let root = X509::stack_from_pem(trust_bundle.pem().unwrap().as_bytes()).unwrap();
+        assert_eq!(
+            root[0].subject_name().to_der().unwrap(),
+            chain[2].subject_name().to_der().unwrap()
+        );
+    }
+
+    #[test]
+    fn certificates_persist_until_destroyed() {
+        let dir = TempDir::new("soft-crypto").unwrap();
+        let crypto = Crypto::new(dir.path(), 86_400).unwrap();
+        let created = workload_ca(&crypto);
+
+        let crypto = Crypto::new(dir.path(), 86_400).unwrap();
+        let loaded = crypto
+            .get_certificate(IOTEDGED_CA_ALIAS.to_string())
+            .unwrap();
+        assert_eq!(created.pem().unwrap(), loaded.pem().unwrap());
+
+        crypto
+            .destroy_certificate(IOTEDGED_CA_ALIAS.to_string())
+            .unwrap();
+        assert!(crypto
+            .get_certificate(IOTEDGED_CA_ALIAS.to_string())
+            .is_err());
+    }
+
+    #[test]
+    fn rejects_unknown_san_entries() {
+        let dir = TempDir::new("soft-crypto").unwrap();
+        let crypto = Crypto::new(dir.path(), 86_400).unwrap();
+        workload_ca(&crypto);
+
+        let properties = CoreCertificateProperties::new(
+            3600,
+            "module".to_string(),
+            CoreCertificateType::Client,
+            "module".to_string(),
+        )
+        .with_san_entries(vec!["otherName:1.2.3.4;UTF8:x".to_string()]);
+        assert!(crypto.create_certificate(&properties).is_err());
+    }
+
+    #[test]
+    fn encrypt_decrypt() {
+        let dir = TempDir::new("soft-crypto").unwrap();
+        let crypto = Crypto::new(dir.path(), 86_400).unwrap();
+        let iv = b"initialization vector";
+
+        assert!(crypto.encrypt(b"module1", b"plaintext", iv).is_err());
+
+        crypto.create_key().unwrap();
+        let ciphertext = crypto.encrypt(b"module1", b"plaintext", iv).unwrap();
+        assert_ne!(b"plaintext".as_ref(), &ciphertext[1 + TAG_LEN..]);
+        assert_eq!(
+            b"plaintext".to_vec(),
+            crypto.decrypt(b"module1", &ciphertext, iv).unwrap()
+        );
+
+        // The client ID is authenticated, and so is the ciphertext.
+        assert!(crypto.decrypt(b"module2", &ciphertext, iv).is_err());
+        let mut tampered = ciphertext.clone();
+        *tampered.last_mut().unwrap() ^= 1;
+        assert!(crypto.decrypt(b"module1", &tampered, iv).is_err());
+
+        // Creating the key again keeps the existing one.
+        crypto.create_key().unwrap();
+        assert!(crypto.decrypt(b"module1", &ciphertext, iv).is_ok());
+
+        crypto.destroy_key().unwrap();
+        assert!(crypto.decrypt(b"module1", &ciphertext, iv).is_err());
+    }
+}
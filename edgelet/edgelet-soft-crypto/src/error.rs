@@ -0,0 +1,96 @@
+// Copyright (c) Microsoft. All rights reserved.
+
+use std::fmt;
+use std::fmt::Display;
+
+use failure::{Backtrace, Context, Fail};
+
+#[derive(Debug)]
+pub struct Error {
+    inner: Context<ErrorKind>,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq, Fail)]
+pub enum ErrorKind {
+    #[fail(display = "Certificate {:?} was not found", _0)]
+    CertificateNotFound(String),
+
+    #[fail(display = "Could not create certificate {:?}", _0)]
+    CreateCertificate(String),
+
+    #[fail(display = "Could not decrypt the ciphertext")]
+    Decrypt,
+
+    #[fail(display = "Could not destroy certificate {:?}", _0)]
+    DestroyCertificate(String),
+
+    #[fail(display = "Could not load the device CA certificate")]
+    DeviceCa,
+
+    #[fail(display = "Could not encrypt the plaintext")]
+    Encrypt,
+
+    #[fail(display = "Could not initialize the crypto store at {:?}", _0)]
+    Initialize(String),
+
+    #[fail(display = "Invalid subject alternative name {:?}", _0)]
+    InvalidSubjectAltName(String),
+
+    #[fail(display = "Could not access the key store")]
+    KeyStore,
+
+    #[fail(display = "Could not load certificate {:?}", _0)]
+    LoadCertificate(String),
+
+    #[fail(display = "Could not generate random bytes")]
+    MakeRandom,
+
+    #[fail(display = "Could not access the master encryption key")]
+    MasterEncryptionKey,
+
+    #[fail(display = "The master encryption key has not been created")]
+    MasterEncryptionKeyNotFound,
+
+    #[fail(display = "Could not load the trust bundle")]
+    TrustBundle,
+}
+
+impl Fail for Error {
+    fn cause(&self) -> Option<&dyn Fail> {
+        self.inner.cause()
+    }
+
+    fn backtrace(&self) -> Option<&Backtrace> {
+        self.inner.backtrace()
+    }
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        Display::fmt(&self.inner, f)
+    }
+}
+
+impl Error {
+    pub fn new(inner: Context<ErrorKind>) -> Self {
+        Error { inner }
+    }
+
+    pub fn kind(&self) -> &ErrorKind {
+        self.inner.get_context()
+    }
+}
+
+impl From<ErrorKind> for Error {
+    fn from(kind: ErrorKind) -> Self {
+        Error {
+            inner: Context::new(kind),
+        }
+    }
+}
+
+impl From<Context<ErrorKind>> for Error {
+    fn from(inner: Context<ErrorKind>) -> Self {
+        Error { inner }
+    }
+}
@@ -0,0 +1,121 @@
+// Copyright (c) Microsoft. All rights reserved.
+
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use failure::{Fail, ResultExt};
+
+use edgelet_core::crypto::{Activate, KeyIdentity, KeyStore, MemoryKey};
+use edgelet_core::{Error as CoreError, ErrorKind as CoreErrorKind};
+
+use crate::error::{Error, ErrorKind};
+use crate::store::{self, Store};
+
+/// A key store that keeps activated identity keys in files, so that they survive restarts.
+#[derive(Clone, Debug)]
+pub struct FileKeyStore {
+    store: Arc<Store>,
+}
+
+impl FileKeyStore {
+    pub fn new(homedir: &Path) -> Result<Self, Error> {
+        let store =
+            Store::new(homedir).context(ErrorKind::Initialize(homedir.display().to_string()))?;
+        Ok(FileKeyStore {
+            store: Arc::new(store),
+        })
+    }
+
+    fn key_path(&self, identity: &KeyIdentity, key_name: &str) -> PathBuf {
+        match identity {
+            KeyIdentity::Device => self.store.key_path(&format!("device.{}", key_name)),
+            KeyIdentity::Module(module) => self
+                .store
+                .key_path(&format!("module.{}.{}", module, key_name)),
+        }
+    }
+}
+
+impl Activate for FileKeyStore {
+    type Key = MemoryKey;
+
+    fn activate_identity_key<B: AsRef<[u8]>>(
+        &mut self,
+        identity: KeyIdentity,
+        key_name: String,
+        key: B,
+    ) -> Result<(), CoreError> {
+        store::write_private(&self.key_path(&identity, &key_name), key.as_ref())
+            .context(ErrorKind::KeyStore)
+            .map_err(|err| CoreError::from(Error::from(err).context(CoreErrorKind::KeyStore)))
+    }
+}
+
+impl KeyStore for FileKeyStore {
+    type Key = MemoryKey;
+
+    fn get(&self, identity: &KeyIdentity, key_name: &str) -> Result<Self::Key, CoreError> {
+        match store::read(&self.key_path(identity, key_name)) {
+            Ok(Some(key)) => Ok(MemoryKey::new(key)),
+            Ok(None) => Err(CoreError::from(CoreErrorKind::KeyStoreItemNotFound)),
+            Err(err) => Err(CoreError::from(
+                Error::from(err.context(ErrorKind::KeyStore)).context(CoreErrorKind::KeyStore),
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use edgelet_core::crypto::{Sign, Signature, SignatureAlgorithm};
+    use tempdir::TempDir;
+
+    use super::*;
+
+    #[test]
+    fn activated_keys_survive_restart() {
+        let dir = TempDir::new("soft-crypto").unwrap();
+        let mut key_store = FileKeyStore::new(dir.path()).unwrap();
+
+        let err = key_store
+            .get(&KeyIdentity::Device, "primary")
+            .unwrap_err();
+        if let CoreErrorKind::KeyStoreItemNotFound = err.kind() {
+        } else {
+            panic!("Expected a KeyStoreItemNotFound error, but got {:?}", err);
+        }
+
+        key_store
+            .activate_identity_key(KeyIdentity::Device, "primary".to_string(), b"device key")
+            .unwrap();
+        key_store
+            .activate_identity_key(
+                KeyIdentity::Module("m1".to_string()),
+                "primary".to_string(),
+                b"module key",
+            )
+            .unwrap();
+
+        let key_store = FileKeyStore::new(dir.path()).unwrap();
+        let key = key_store.get(&KeyIdentity::Device, "primary").unwrap();
+        assert_eq!(
+            MemoryKey::new(b"device key")
+                .sign(SignatureAlgorithm::HMACSHA256, b"data")
+                .unwrap()
+                .as_bytes(),
+            key.sign(SignatureAlgorithm::HMACSHA256, b"data")
+                .unwrap()
+                .as_bytes()
+        );
+        assert_eq!(
+            b"module key".as_ref(),
+            key_store
+                .get(&KeyIdentity::Module("m1".to_string()), "primary")
+                .unwrap()
+                .as_ref()
+        );
+        assert!(key_store
+            .get(&KeyIdentity::Module("m2".to_string()), "primary")
+            .is_err());
+    }
+}
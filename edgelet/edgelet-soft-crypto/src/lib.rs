@@ -0,0 +1,23 @@
+// Copyright (c) Microsoft. All rights reserved.
+
+//! A pure software implementation of the `edgelet_core` crypto traits.
+//!
+//! Certificates, private keys and the master encryption key are kept in files under the
+//! daemon's home directory, readable only by the user that iotedged runs as. This is an
+//! alternative to `edgelet-hsm` for devices that don't need an HSM and for builds that can't
+//! link the native HSM library.
+
+#![deny(rust_2018_idioms, warnings)]
+#![deny(clippy::all, clippy::pedantic)]
+#![allow(clippy::module_name_repetitions, clippy::use_self)]
+
+mod certificate;
+mod crypto;
+mod error;
+mod key_store;
+mod store;
+
+pub use certificate::Certificate;
+pub use crypto::{Crypto, DEVICE_CA_ALIAS};
+pub use error::{Error, ErrorKind};
+pub use key_store::FileKeyStore;
@@ -0,0 +1,241 @@
+// Copyright (c) Microsoft. All rights reserved.
+
+use std::fmt::Write as FmtWrite;
+use std::fs::{self, DirBuilder, OpenOptions};
+use std::io::{self, Write};
+#[cfg(unix)]
+use std::os::unix::fs::{DirBuilderExt, OpenOptionsExt, PermissionsExt};
+use std::path::{Path, PathBuf};
+
+/// Name of the directory under the home directory that holds everything this crate persists
+const STORE_DIR: &str = "soft_crypto";
+
+/// Layout of the files this crate keeps under the home directory:
+///
+/// ```text
+/// <homedir>/soft_crypto/certs/<alias>.pem       certificate chains
+/// <homedir>/soft_crypto/private/<alias>.pem     certificate private keys
+/// <homedir>/soft_crypto/keys/<name>.key         identity keys
+/// <homedir>/soft_crypto/master.key              master encryption key
+/// ```
+///
+/// Directories are created with mode 0700 and files with mode 0600. Existing directories are
+/// reset to mode 0700. On Windows, directories get a protected ACL that only grants access to
+/// their owner, and files inherit it.
+#[derive(Debug)]
+pub(crate) struct Store {
+    root: PathBuf,
+}
+
+impl Store {
+    pub(crate) fn new(homedir: &Path) -> io::Result<Self> {
+        let root = homedir.join(STORE_DIR);
+        create_dir(&root)?;
+        for dir in &["certs", "private", "keys"] {
+            create_dir(&root.join(dir))?;
+        }
+        Ok(Store { root })
+    }
+
+    pub(crate) fn cert_path(&self, alias: &str) -> PathBuf {
+        self.root
+            .join("certs")
+            .join(format!("{}.pem", file_name(alias)))
+    }
+
+    pub(crate) fn private_key_path(&self, alias: &str) -> PathBuf {
+        self.root
+            .join("private")
+            .join(format!("{}.pem", file_name(alias)))
+    }
+
+    pub(crate) fn key_path(&self, name: &str) -> PathBuf {
+        self.root
+            .join("keys")
+            .join(format!("{}.key", file_name(name)))
+    }
+
+    pub(crate) fn master_key_path(&self) -> PathBuf {
+        self.root.join("master.key")
+    }
+}
+
+/// Reads a file, returning `None` if it doesn't exist.
+pub(crate) fn read(path: &Path) -> io::Result<Option<Vec<u8>>> {
+    match fs::read(path) {
+        Ok(contents) => Ok(Some(contents)),
+        Err(ref err) if err.kind() == io::ErrorKind::NotFound => Ok(None),
+        Err(err) => Err(err),
+    }
+}
+
+/// Writes a file that only the current user can read.
+///
+/// The contents are written to a temporary file first and then renamed over `path`,
+/// so a crash never leaves a partially written key behind.
+pub(crate) fn write_private(path: &Path, contents: &[u8]) -> io::Result<()> {
+    let mut temp_path = path.as_os_str().to_owned();
+    temp_path.push(".tmp");
+    let temp_path = PathBuf::from(temp_path);
+
+    let mut options = OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    options.mode(0o600);
+
+    let mut file = options.open(&temp_path)?;
+    file.write_all(contents)?;
+    file.sync_all()?;
+    fs::rename(&temp_path, path)
+}
+
+/// Removes a file, ignoring it if it doesn't exist.
+pub(crate) fn remove(path: &Path) -> io::Result<()> {
+    match fs::remove_file(path) {
+        Err(ref err) if err.kind() == io::ErrorKind::NotFound => Ok(()),
+        result => result,
+    }
+}
+
+/// Creates a directory that only the current user can access.
+///
+/// The permissions are also applied if the directory already exists, so a directory that was
+/// created some other way can't leave the keys in it readable by other users.
+fn create_dir(path: &Path) -> io::Result<()> {
+    let mut builder = DirBuilder::new();
+    builder.recursive(true);
+    #[cfg(unix)]
+    builder.mode(0o700);
+    builder.create(path)?;
+
+    #[cfg(unix)]
+    fs::set_permissions(path, fs::Permissions::from_mode(0o700))?;
+    #[cfg(windows)]
+    restrict_to_owner(path)?;
+
+    Ok(())
+}
+
+/// Replaces the ACL of a directory with one that only grants its owner access, and that files
+/// and directories created in it inherit.
+#[cfg(windows)]
+fn restrict_to_owner(path: &Path) -> io::Result<()> {
+    use std::ffi::OsStr;
+    use std::iter;
+    use std::os::windows::ffi::OsStrExt;
+    use std::ptr;
+
+    use winapi::shared::sddl::{
+        ConvertStringSecurityDescriptorToSecurityDescriptorW, SDDL_REVISION_1,
+    };
+    use winapi::um::securitybaseapi::SetFileSecurityW;
+    use winapi::um::winbase::LocalFree;
+    use winapi::um::winnt::{DACL_SECURITY_INFORMATION, PROTECTED_DACL_SECURITY_INFORMATION};
+
+    fn to_wide(s: &OsStr) -> Vec<u16> {
+        s.encode_wide().chain(iter::once(0)).collect()
+    }
+
+    // Full access for the owner, inherited by files and directories, and nothing inherited from
+    // the parent directory.
+    let sddl = to_wide(OsStr::new("D:P(A;OICI;FA;;;OW)"));
+    let path = to_wide(path.as_os_str());
+
+    unsafe {
+        let mut descriptor = ptr::null_mut();
+        if ConvertStringSecurityDescriptorToSecurityDescriptorW(
+            sddl.as_ptr(),
+            SDDL_REVISION_1.into(),
+            &mut descriptor,
+            ptr::null_mut(),
+        ) == 0
+        {
+            return Err(io::Error::last_os_error());
+        }
+
+        let result = if SetFileSecurityW(
+            path.as_ptr(),
+            DACL_SECURITY_INFORMATION | PROTECTED_DACL_SECURITY_INFORMATION,
+            descriptor,
+        ) == 0
+        {
+            Err(io::Error::last_os_error())
+        } else {
+            Ok(())
+        };
+        LocalFree(descriptor);
+        result
+    }
+}
+
+/// Aliases come from module names and so can contain characters that aren't valid in file names.
+/// Everything other than ASCII letters, digits and `-` is escaped as `_xx`.
+fn file_name(alias: &str) -> String {
+    let mut result = String::with_capacity(alias.len());
+    for b in alias.bytes() {
+        if b.is_ascii_alphanumeric() || b == b'-' {
+            result.push(char::from(b));
+        } else {
+            write!(result, "_{:02x}", b).expect("writing to a String cannot fail");
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use tempdir::TempDir;
+
+    use super::*;
+
+    #[test]
+    fn file_name_escapes_reserved_characters() {
+        assert_eq!("edgeHub-server", file_name("edgeHub-server"));
+        assert_eq!("_2e_2e_2fetc_2fpasswd", file_name("../etc/passwd"));
+        assert_eq!("a_5fb", file_name("a_b"));
+        assert_ne!(file_name("a_b"), file_name("a_5fb"));
+    }
+
+    #[test]
+    fn write_private_replaces_file() {
+        let dir = TempDir::new("soft-crypto").unwrap();
+        let store = Store::new(dir.path()).unwrap();
+        let path = store.key_path("device");
+
+        assert_eq!(None, read(&path).unwrap());
+
+        write_private(&path, b"first").unwrap();
+        write_private(&path, b"second").unwrap();
+        assert_eq!(Some(b"second".to_vec()), read(&path).unwrap());
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+
+            let mode = fs::metadata(&path).unwrap().permissions().mode();
+            assert_eq!(0o600, mode & 0o777);
+        }
+
+        remove(&path).unwrap();
+        remove(&path).unwrap();
+        assert_eq!(None, read(&path).unwrap());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn existing_dirs_are_restricted_to_owner() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = TempDir::new("soft-crypto").unwrap();
+        let keys = dir.path().join(STORE_DIR).join("keys");
+        fs::create_dir_all(&keys).unwrap();
+        fs::set_permissions(&keys, fs::Permissions::from_mode(0o755)).unwrap();
+
+        Store::new(dir.path()).unwrap();
+
+        for path in &[dir.path().join(STORE_DIR), keys] {
+            let mode = fs::metadata(path).unwrap().permissions().mode();
+            assert_eq!(0o700, mode & 0o777);
+        }
+    }
+}
@@ -1,5 +1,6 @@
 // Copyright (c) Microsoft. All rights reserved.
 
+use edgelet_core::crypto::{Decrypt, Encrypt, MakeRandom};
 use edgelet_core::{Error as CoreError, ErrorKind as CoreErrorKind, GetTrustBundle};
 
 use crate::cert::TestCert;
@@ -33,3 +34,51 @@ impl GetTrustBundle for TestHsm {
         }
     }
 }
+
+// Encryption is the identity, so that tests can check what was encrypted.
+impl Encrypt for TestHsm {
+    type Buffer = Vec<u8>;
+
+    fn encrypt(
+        &self,
+        _client_id: &[u8],
+        plaintext: &[u8],
+        _initialization_vector: &[u8],
+    ) -> Result<Self::Buffer, CoreError> {
+        if self.fail_call {
+            Err(CoreError::from(CoreErrorKind::KeyStore))
+        } else {
+            Ok(plaintext.to_vec())
+        }
+    }
+}
+
+impl Decrypt for TestHsm {
+    type Buffer = Vec<u8>;
+
+    fn decrypt(
+        &self,
+        _client_id: &[u8],
+        ciphertext: &[u8],
+        _initialization_vector: &[u8],
+    ) -> Result<Self::Buffer, CoreError> {
+        if self.fail_call {
+            Err(CoreError::from(CoreErrorKind::KeyStore))
+        } else {
+            Ok(ciphertext.to_vec())
+        }
+    }
+}
+
+impl MakeRandom for TestHsm {
+    fn get_random_bytes(&self, buffer: &mut [u8]) -> Result<(), CoreError> {
+        if self.fail_call {
+            Err(CoreError::from(CoreErrorKind::KeyStore))
+        } else {
+            for b in buffer {
+                *b = 0;
+            }
+            Ok(())
+        }
+    }
+}
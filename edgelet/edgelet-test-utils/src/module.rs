@@ -1,9 +1,11 @@
 // Copyright (c) Microsoft. All rights reserved.
 
+use std::io;
 use std::marker::PhantomData;
 use std::path::Path;
 use std::time::Duration;
 
+use chrono::{DateTime, TimeZone, Utc};
 use edgelet_core::*;
 use failure::Fail;
 use futures::future::{self, FutureResult};
@@ -11,6 +13,7 @@ use futures::prelude::*;
 use futures::stream;
 use futures::IntoFuture;
 use hyper::{Body, Request};
+use tokio::io::{AsyncRead, AsyncWrite};
 
 #[derive(Clone, Debug)]
 pub struct TestRegistry<E, C> {
@@ -34,6 +37,8 @@ where
     type Error = E;
     type PullFuture = FutureResult<(), Self::Error>;
     type RemoveFuture = FutureResult<(), Self::Error>;
+    type LoginFuture = FutureResult<(), Self::Error>;
+    type LogoutFuture = FutureResult<(), Self::Error>;
     type Config = C;
 
     fn pull(&self, _config: &Self::Config) -> Self::PullFuture {
@@ -49,6 +54,20 @@ where
             None => future::ok(()),
         }
     }
+
+    fn login(&self, _registry: &str, _username: &str, _password: &str) -> Self::LoginFuture {
+        match self.err {
+            Some(ref e) => future::err(e.clone()),
+            None => future::ok(()),
+        }
+    }
+
+    fn logout(&self, _registry: &str) -> Self::LogoutFuture {
+        match self.err {
+            Some(ref e) => future::err(e.clone()),
+            None => future::ok(()),
+        }
+    }
 }
 
 #[derive(Clone, Debug, serde_derive::Serialize, serde_derive::Deserialize)]
@@ -94,6 +113,10 @@ impl RuntimeSettings for TestSettings {
         unimplemented!()
     }
 
+    fn parent_hostname(&self) -> Option<&str> {
+        unimplemented!()
+    }
+
     fn connect(&self) -> &Connect {
         unimplemented!()
     }
@@ -268,6 +291,44 @@ impl<E> From<TestBody<E>> for Body {
     }
 }
 
+/// The stream of a command run by [`TestRuntime::exec`]. The output of the command is the
+/// command itself, like `echo` would print it, and its input is discarded.
+pub struct TestExec {
+    output: io::Cursor<Vec<u8>>,
+}
+
+impl TestExec {
+    pub fn new(command: &[String]) -> Self {
+        TestExec {
+            output: io::Cursor::new(format!("{}\n", command.join(" ")).into_bytes()),
+        }
+    }
+}
+
+impl io::Read for TestExec {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        io::Read::read(&mut self.output, buf)
+    }
+}
+
+impl io::Write for TestExec {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl AsyncRead for TestExec {}
+
+impl AsyncWrite for TestExec {
+    fn shutdown(&mut self) -> Poll<(), io::Error> {
+        Ok(Async::Ready(()))
+    }
+}
+
 #[derive(Default)]
 pub struct TestProvisioningResult;
 
@@ -325,6 +386,8 @@ where
     type ModuleRegistry = TestRegistry<E, S::Config>;
     type Chunk = &'static [u8];
     type Logs = TestBody<E>;
+    type Events = Box<dyn Stream<Item = ModuleEvent, Error = Self::Error> + Send>;
+    type ExecStream = TestExec;
 
     type CreateFuture = FutureResult<(), Self::Error>;
     type GetFuture = FutureResult<(Self::Module, ModuleRuntimeState), Self::Error>;
@@ -332,6 +395,10 @@ where
     type ListWithDetailsStream =
         Box<dyn Stream<Item = (Self::Module, ModuleRuntimeState), Error = Self::Error> + Send>;
     type LogsFuture = FutureResult<Self::Logs, Self::Error>;
+    type EventsFuture = FutureResult<Self::Events, Self::Error>;
+    type ExecFuture = FutureResult<(String, Self::ExecStream), Self::Error>;
+    type ExecResizeFuture = FutureResult<(), Self::Error>;
+    type ExecExitCodeFuture = FutureResult<Option<i64>, Self::Error>;
     type RemoveFuture = FutureResult<(), Self::Error>;
     type RestartFuture = FutureResult<(), Self::Error>;
     type StartFuture = FutureResult<(), Self::Error>;
@@ -339,6 +406,9 @@ where
     type SystemInfoFuture = FutureResult<SystemInfo, Self::Error>;
     type SystemResourcesFuture = FutureResult<SystemResources, Self::Error>;
     type RemoveAllFuture = FutureResult<(), Self::Error>;
+    type ListNetworksFuture = FutureResult<Vec<Network>, Self::Error>;
+    type AttachNetworkFuture = FutureResult<(), Self::Error>;
+    type DetachNetworkFuture = FutureResult<(), Self::Error>;
 
     fn create(&self, _module: ModuleSpec<Self::Config>) -> Self::CreateFuture {
         match self.module.as_ref().unwrap() {
@@ -394,21 +464,27 @@ where
 
     fn system_resources(&self) -> Self::SystemResourcesFuture {
         match self.module.as_ref().unwrap() {
-            Ok(_) => future::ok(SystemResources::new(
-                595_023,
-                200,
-                0.25,
-                5000,
-                8000,
-                vec![DiskInfo::new(
-                    "test disk".to_owned(),
-                    10000,
-                    20000,
-                    "test system".to_owned(),
-                    "test type".to_owned(),
-                )],
-                "fake docker stats".to_owned(),
-            )),
+            Ok(m) => future::ok(
+                SystemResources::new(
+                    595_023,
+                    200,
+                    0.25,
+                    5000,
+                    8000,
+                    vec![DiskInfo::new(
+                        "test disk".to_owned(),
+                        10000,
+                        20000,
+                        "test system".to_owned(),
+                        "test type".to_owned(),
+                    )],
+                    vec![ModuleResourceUsage::new(m.name().to_string())
+                        .with_cpu_percent(Some(1.5))
+                        .with_memory_usage(Some(1024))
+                        .with_pids(Some(2))],
+                )
+                .with_docker_stats(Some("fake docker stats".to_owned())),
+            ),
             Err(ref e) => future::err(e.clone()),
         }
     }
@@ -437,6 +513,51 @@ where
         }
     }
 
+    fn events(&self, since: Option<DateTime<Utc>>) -> Self::EventsFuture {
+        match self.module.as_ref().unwrap() {
+            Ok(ref m) => {
+                let events: Vec<_> = vec![
+                    (ModuleEventKind::Created, Utc.timestamp(1_551_885_900, 0)),
+                    (ModuleEventKind::Started, Utc.timestamp(1_551_885_910, 0)),
+                    (ModuleEventKind::Died(1), Utc.timestamp(1_551_885_920, 0)),
+                ]
+                .into_iter()
+                .filter(|(_, time)| since.map_or(true, |since| *time >= since))
+                .map(|(kind, time)| ModuleEvent::new(m.name().to_string(), kind, time))
+                .collect();
+                future::ok(Box::new(stream::iter_ok(events)))
+            }
+            Err(ref e) => future::err(e.clone()),
+        }
+    }
+
+    fn exec(&self, _id: &str, options: &ExecOptions) -> Self::ExecFuture {
+        match self.module.as_ref().unwrap() {
+            Ok(_) => future::ok(("exec1".to_string(), TestExec::new(options.command()))),
+            Err(ref e) => future::err(e.clone()),
+        }
+    }
+
+    fn exec_resize(
+        &self,
+        _id: &str,
+        _exec_id: &str,
+        _height: u16,
+        _width: u16,
+    ) -> Self::ExecResizeFuture {
+        match self.module.as_ref().unwrap() {
+            Ok(_) => future::ok(()),
+            Err(ref e) => future::err(e.clone()),
+        }
+    }
+
+    fn exec_exit_code(&self, _id: &str, _exec_id: &str) -> Self::ExecExitCodeFuture {
+        match self.module.as_ref().unwrap() {
+            Ok(_) => future::ok(Some(0)),
+            Err(ref e) => future::err(e.clone()),
+        }
+    }
+
     fn registry(&self) -> &Self::ModuleRegistry {
         &self.registry
     }
@@ -444,4 +565,26 @@ where
     fn remove_all(&self) -> Self::RemoveAllFuture {
         future::ok(())
     }
+
+    fn list_networks(&self) -> Self::ListNetworksFuture {
+        match self.module.as_ref().unwrap() {
+            Ok(ref m) => future::ok(vec![Network::new(DEFAULT_NETWORKID.to_string())
+                .with_modules(vec![m.name().to_string()])]),
+            Err(ref e) => future::err(e.clone()),
+        }
+    }
+
+    fn attach_network(&self, _id: &str, _network: &str) -> Self::AttachNetworkFuture {
+        match self.module.as_ref().unwrap() {
+            Ok(_) => future::ok(()),
+            Err(ref e) => future::err(e.clone()),
+        }
+    }
+
+    fn detach_network(&self, _id: &str, _network: &str) -> Self::DetachNetworkFuture {
+        match self.module.as_ref().unwrap() {
+            Ok(_) => future::ok(()),
+            Err(ref e) => future::err(e.clone()),
+        }
+    }
 }
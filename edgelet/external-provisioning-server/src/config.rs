@@ -0,0 +1,291 @@
+// Copyright (c) Microsoft. All rights reserved.
+
+use std::fmt;
+use std::fs;
+use std::path::Path;
+
+use external_provisioning::models::{
+    Credentials as ProvisioningCredentials, DeviceProvisioningInfo,
+};
+use failure::{Fail, ResultExt};
+use serde_derive::Deserialize;
+
+use crate::error::{Error, ErrorKind};
+
+/// The configuration file of the server.
+///
+/// The device is served as described at the top level of the file. Once the device has asked
+/// to be reprovisioned, it's served as described by `reprovisioned` instead, if present.
+/// `faults` are error responses that are returned instead of the regular ones.
+#[derive(Clone, Debug, Deserialize)]
+pub(crate) struct Config {
+    #[serde(flatten)]
+    device: Device,
+
+    reprovisioned: Option<Device>,
+
+    #[serde(default)]
+    faults: Vec<Fault>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct Device {
+    hub_name: String,
+    device_id: String,
+    credentials: Credentials,
+    status: Option<String>,
+    substatus: Option<String>,
+}
+
+/// X.509 payload credentials are paths to PEM files, relative to the configuration file.
+/// X.509 HSM credentials are references that are passed on to the device as they are.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(tag = "authType")]
+pub(crate) enum Credentials {
+    #[serde(rename = "symmetric-key")]
+    SymmetricKey { source: Source, key: Option<String> },
+
+    #[serde(rename = "x509")]
+    X509 {
+        source: Source,
+
+        #[serde(rename = "identityCert")]
+        identity_cert: String,
+
+        #[serde(rename = "identityPrivateKey")]
+        identity_private_key: String,
+    },
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum Source {
+    Payload,
+    Hsm,
+}
+
+/// An error response returned for `operation`, the first `count` times it's called or every
+/// time if `count` is not set.
+#[derive(Clone, Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub(crate) struct Fault {
+    operation: Operation,
+    status_code: u16,
+    message: Option<String>,
+    count: Option<usize>,
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub enum Operation {
+    GetDeviceProvisioningInformation,
+    ReprovisionDevice,
+}
+
+impl Config {
+    pub(crate) fn load(path: &Path) -> Result<Self, Error> {
+        let context = || ErrorKind::LoadConfig(path.display().to_string());
+
+        let contents = fs::read(path).with_context(|_| context())?;
+        let config: Config = serde_json::from_slice(&contents).with_context(|_| context())?;
+
+        for fault in &config.faults {
+            if !(400..600).contains(&fault.status_code) {
+                return Err(Error::from(
+                    ErrorKind::InvalidFaultStatusCode(fault.status_code).context(context()),
+                ));
+            }
+        }
+
+        Ok(config)
+    }
+
+    pub(crate) fn device(&self, reprovisioned: bool) -> &Device {
+        if reprovisioned {
+            self.reprovisioned.as_ref().unwrap_or(&self.device)
+        } else {
+            &self.device
+        }
+    }
+
+    pub(crate) fn faults(&self) -> &[Fault] {
+        &self.faults
+    }
+}
+
+impl Device {
+    pub(crate) fn provisioning_info(&self, dir: &Path) -> Result<DeviceProvisioningInfo, Error> {
+        let credentials = match &self.credentials {
+            Credentials::SymmetricKey { source, key } => {
+                let credentials =
+                    ProvisioningCredentials::new("symmetric-key".to_string(), source.to_string());
+                match key {
+                    Some(key) => credentials.with_key(key.clone()),
+                    None => credentials,
+                }
+            }
+            Credentials::X509 {
+                source,
+                identity_cert,
+                identity_private_key,
+            } => {
+                let (identity_cert, identity_private_key) = match source {
+                    Source::Payload => (
+                        read_pem(dir, identity_cert)?,
+                        read_pem(dir, identity_private_key)?,
+                    ),
+                    Source::Hsm => (identity_cert.clone(), identity_private_key.clone()),
+                };
+                ProvisioningCredentials::new("x509".to_string(), source.to_string())
+                    .with_identity_cert(identity_cert)
+                    .with_identity_private_key(identity_private_key)
+            }
+        };
+
+        let mut info =
+            DeviceProvisioningInfo::new(self.hub_name.clone(), self.device_id.clone(), credentials);
+        if let Some(status) = &self.status {
+            info.set_status(status.clone());
+        }
+        if let Some(substatus) = &self.substatus {
+            info.set_substatus(substatus.clone());
+        }
+
+        Ok(info)
+    }
+}
+
+impl fmt::Display for Source {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Source::Payload => write!(f, "payload"),
+            Source::Hsm => write!(f, "hsm"),
+        }
+    }
+}
+
+impl Fault {
+    pub(crate) fn operation(&self) -> Operation {
+        self.operation
+    }
+
+    pub(crate) fn status_code(&self) -> u16 {
+        self.status_code
+    }
+
+    pub(crate) fn message(&self) -> &str {
+        self.message
+            .as_ref()
+            .map_or("Simulated error response", String::as_str)
+    }
+
+    pub(crate) fn count(&self) -> Option<usize> {
+        self.count
+    }
+}
+
+// Payload certificates and keys are sent as base64-encoded PEM files.
+fn read_pem(dir: &Path, path: &str) -> Result<String, Error> {
+    let path = dir.join(path);
+    let pem =
+        fs::read(&path).with_context(|_| ErrorKind::ReadCredential(path.display().to_string()))?;
+    Ok(base64::encode(&pem))
+}
+
+#[cfg(test)]
+mod tests {
+    use tempdir::TempDir;
+
+    use super::*;
+
+    fn write_config(dir: &TempDir, config: &serde_json::Value) -> std::path::PathBuf {
+        let path = dir.path().join("device.json");
+        fs::write(&path, config.to_string()).unwrap();
+        path
+    }
+
+    #[test]
+    fn x509_payload_is_read_from_files_next_to_config() {
+        let dir = TempDir::new("external-provisioning-server").unwrap();
+        fs::write(dir.path().join("device.cert.pem"), "CERT").unwrap();
+        fs::write(dir.path().join("device.key.pem"), "KEY").unwrap();
+        let path = write_config(
+            &dir,
+            &serde_json::json!({
+                "hubName": "hub.azure-devices.net",
+                "deviceId": "device01",
+                "credentials": {
+                    "authType": "x509",
+                    "source": "payload",
+                    "identityCert": "device.cert.pem",
+                    "identityPrivateKey": "device.key.pem",
+                },
+            }),
+        );
+
+        let config = Config::load(&path).unwrap();
+        let info = config.device(false).provisioning_info(dir.path()).unwrap();
+        assert_eq!("hub.azure-devices.net", info.hub_name());
+        assert_eq!("x509", info.credentials().auth_type());
+        assert_eq!("payload", info.credentials().source());
+        assert_eq!(
+            Some(base64::encode("CERT").as_ref()),
+            info.credentials().identity_cert()
+        );
+        assert_eq!(
+            Some(base64::encode("KEY").as_ref()),
+            info.credentials().identity_private_key()
+        );
+    }
+
+    #[test]
+    fn reprovisioned_device_falls_back_to_device() {
+        let dir = TempDir::new("external-provisioning-server").unwrap();
+        let path = write_config(
+            &dir,
+            &serde_json::json!({
+                "hubName": "hub.azure-devices.net",
+                "deviceId": "device01",
+                "credentials": {
+                    "authType": "symmetric-key",
+                    "source": "hsm",
+                },
+            }),
+        );
+
+        let config = Config::load(&path).unwrap();
+        let info = config.device(true).provisioning_info(dir.path()).unwrap();
+        assert_eq!("hub.azure-devices.net", info.hub_name());
+        assert_eq!(None, info.credentials().key());
+    }
+
+    #[test]
+    fn fault_must_be_error_status_code() {
+        let dir = TempDir::new("external-provisioning-server").unwrap();
+        let path = write_config(
+            &dir,
+            &serde_json::json!({
+                "hubName": "hub.azure-devices.net",
+                "deviceId": "device01",
+                "credentials": {
+                    "authType": "symmetric-key",
+                    "source": "payload",
+                    "key": "QXp1cmUgSW9UIEVkZ2U=",
+                },
+                "faults": [
+                    {
+                        "operation": "reprovisionDevice",
+                        "statusCode": 200,
+                    },
+                ],
+            }),
+        );
+
+        let err = Config::load(&path).unwrap_err();
+        assert_eq!(
+            &ErrorKind::LoadConfig(path.display().to_string()),
+            err.kind()
+        );
+    }
+}
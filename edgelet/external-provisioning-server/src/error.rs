@@ -0,0 +1,67 @@
+// Copyright (c) Microsoft. All rights reserved.
+
+use std::fmt::{self, Display};
+
+use failure::{Backtrace, Context, Fail};
+
+#[derive(Debug)]
+pub struct Error {
+    inner: Context<ErrorKind>,
+}
+
+#[derive(Clone, Debug, Fail, PartialEq)]
+pub enum ErrorKind {
+    #[fail(display = "Fault status code {} is not an error status code", _0)]
+    InvalidFaultStatusCode(u16),
+
+    #[fail(display = "Invalid listen URL {}", _0)]
+    InvalidListenUrl(String),
+
+    #[fail(display = "Could not load the configuration file {}", _0)]
+    LoadConfig(String),
+
+    #[fail(display = "Could not read the credential file {}", _0)]
+    ReadCredential(String),
+
+    #[fail(display = "The server could not start up successfully")]
+    StartServer,
+
+    #[fail(display = "The server stopped unexpectedly")]
+    Server,
+}
+
+impl Fail for Error {
+    fn cause(&self) -> Option<&dyn Fail> {
+        self.inner.cause()
+    }
+
+    fn backtrace(&self) -> Option<&Backtrace> {
+        self.inner.backtrace()
+    }
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        Display::fmt(&self.inner, f)
+    }
+}
+
+impl Error {
+    pub fn kind(&self) -> &ErrorKind {
+        self.inner.get_context()
+    }
+}
+
+impl From<ErrorKind> for Error {
+    fn from(kind: ErrorKind) -> Self {
+        Error {
+            inner: Context::new(kind),
+        }
+    }
+}
+
+impl From<Context<ErrorKind>> for Error {
+    fn from(inner: Context<ErrorKind>) -> Self {
+        Error { inner }
+    }
+}
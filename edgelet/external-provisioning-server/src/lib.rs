@@ -0,0 +1,23 @@
+// Copyright (c) Microsoft. All rights reserved.
+
+//! A local stand-in for the external provisioning environment of a device.
+//!
+//! It implements the external provisioning API that the daemon calls when the device is
+//! provisioned with the `external` source, so that the daemon can be run and tested without a
+//! real external provisioning environment. The device is described by a JSON configuration file
+//! that can serve symmetric-key or X.509 credentials, change once the device asks to be
+//! reprovisioned, and simulate error responses. See `config/` for examples.
+
+#![deny(rust_2018_idioms, warnings)]
+#![deny(clippy::all, clippy::pedantic)]
+#![allow(clippy::module_name_repetitions, clippy::use_self)]
+
+mod config;
+mod error;
+mod server;
+
+pub use crate::config::Operation;
+pub use crate::error::{Error, ErrorKind};
+pub use crate::server::ExternalProvisioningServer;
+
+pub const API_VERSION: &str = "2019-04-10";
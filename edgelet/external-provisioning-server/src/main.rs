@@ -0,0 +1,84 @@
+// Copyright (c) Microsoft. All rights reserved.
+
+#![deny(rust_2018_idioms, warnings)]
+#![deny(clippy::all, clippy::pedantic)]
+
+use std::env;
+use std::path::PathBuf;
+
+use clap::{crate_authors, crate_description, crate_name, crate_version, App, Arg};
+use failure::{Fail, ResultExt};
+use hyper::server::conn::Http;
+use log::{error, info, LevelFilter};
+use url::Url;
+
+use edgelet_hsm::Crypto;
+use edgelet_http::{HyperExt, TlsAcceptorParams};
+use external_provisioning_server::{Error, ErrorKind, ExternalProvisioningServer};
+
+fn main() {
+    env_logger::Builder::new()
+        .filter_level(LevelFilter::Info)
+        .default_format_module_path(false)
+        .parse(&env::var("EXTERNAL_PROVISIONING_SERVER_LOG").unwrap_or_default())
+        .init();
+
+    if let Err(err) = run() {
+        error!("{}", err);
+        let fail: &dyn Fail = &err;
+        for cause in fail.iter_causes() {
+            error!("\tcaused by: {}", cause);
+        }
+        std::process::exit(1);
+    }
+}
+
+fn run() -> Result<(), Error> {
+    let matches = create_app().get_matches();
+    let config = matches
+        .value_of_os("config")
+        .map(PathBuf::from)
+        .expect("config is a required argument");
+    let listen = matches
+        .value_of("listen")
+        .expect("listen has a default value");
+    let url = Url::parse(listen).context(ErrorKind::InvalidListenUrl(listen.to_string()))?;
+
+    let server = ExternalProvisioningServer::new(config)?;
+    let run = Http::new()
+        .bind_url(url, server, None::<TlsAcceptorParams<'_, Crypto>>)
+        .context(ErrorKind::StartServer)?
+        .run();
+
+    info!("Listening on {}", listen);
+    tokio::runtime::current_thread::Runtime::new()
+        .context(ErrorKind::StartServer)?
+        .block_on(run)
+        .context(ErrorKind::Server)?;
+
+    Ok(())
+}
+
+fn create_app() -> App<'static, 'static> {
+    App::new(crate_name!())
+        .author(crate_authors!("\n"))
+        .version(crate_version!())
+        .about(crate_description!())
+        .arg(
+            Arg::with_name("config")
+                .short("c")
+                .long("config")
+                .value_name("FILE")
+                .help("Sets the file that describes the device")
+                .required(true)
+                .takes_value(true),
+        )
+        .arg(
+            Arg::with_name("listen")
+                .short("l")
+                .long("listen")
+                .value_name("URL")
+                .help("Sets the URL to listen on, like http://localhost:9999 or unix:///var/run/provisioning.sock")
+                .default_value("http://localhost:9999"),
+        )
+}
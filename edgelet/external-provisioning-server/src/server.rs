@@ -0,0 +1,277 @@
+// Copyright (c) Microsoft. All rights reserved.
+
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use external_provisioning::models::ErrorResponse;
+use futures::future::{self, FutureResult};
+use hyper::header::{CONTENT_LENGTH, CONTENT_TYPE};
+use hyper::service::{NewService, Service};
+use hyper::{Body, Method, Request, Response, StatusCode};
+use log::{error, info};
+use serde::Serialize;
+
+use crate::config::{Config, Operation};
+use crate::error::Error;
+use crate::API_VERSION;
+
+/// Serves the external provisioning API for the device described by a configuration file.
+///
+/// The file is read again for every request, so that editing it while the server runs changes
+/// what the device is told, like the external provisioning environment of a real device would.
+#[derive(Clone)]
+pub struct ExternalProvisioningServer {
+    config_path: Arc<PathBuf>,
+    state: Arc<Mutex<State>>,
+}
+
+#[derive(Default)]
+struct State {
+    reprovisioned: bool,
+    faults_returned: Vec<usize>,
+}
+
+impl ExternalProvisioningServer {
+    pub fn new(config_path: PathBuf) -> Result<Self, Error> {
+        // Reject a bad configuration file up front rather than on the first request.
+        Config::load(&config_path)?;
+
+        Ok(ExternalProvisioningServer {
+            config_path: Arc::new(config_path),
+            state: Arc::new(Mutex::new(State::default())),
+        })
+    }
+
+    /// Whether the device has asked to be reprovisioned.
+    pub fn reprovisioned(&self) -> bool {
+        self.state
+            .lock()
+            .expect("external provisioning server state lock poisoned")
+            .reprovisioned
+    }
+
+    fn handle(&self, req: &Request<Body>) -> Response<Body> {
+        let operation = match (req.method(), req.uri().path()) {
+            (&Method::GET, "/device/provisioninginformation") => {
+                Operation::GetDeviceProvisioningInformation
+            }
+            // iotedged asks to be reprovisioned with a GET, the API spec says POST
+            (&Method::GET, "/device/reprovision") | (&Method::POST, "/device/reprovision") => {
+                Operation::ReprovisionDevice
+            }
+            (method, path) => {
+                return error_response(
+                    StatusCode::NOT_FOUND,
+                    &format!("No route for {} {}", method, path),
+                );
+            }
+        };
+
+        let api_version = req.uri().query().and_then(|query| {
+            url::form_urlencoded::parse(query.as_bytes())
+                .find(|(key, _)| key == "api-version")
+                .map(|(_, value)| value.into_owned())
+        });
+        if api_version.as_ref().map(String::as_str) != Some(API_VERSION) {
+            return error_response(
+                StatusCode::BAD_REQUEST,
+                &format!("The api-version query parameter must be {}", API_VERSION),
+            );
+        }
+
+        self.respond(operation).unwrap_or_else(|err| {
+            error!("Could not respond to {:?}: {}", operation, err);
+            error_response(StatusCode::INTERNAL_SERVER_ERROR, &err.to_string())
+        })
+    }
+
+    fn respond(&self, operation: Operation) -> Result<Response<Body>, Error> {
+        let config = Config::load(&self.config_path)?;
+        let mut state = self
+            .state
+            .lock()
+            .expect("external provisioning server state lock poisoned");
+
+        if state.faults_returned.len() < config.faults().len() {
+            state.faults_returned.resize(config.faults().len(), 0);
+        }
+        let fault = config
+            .faults()
+            .iter()
+            .zip(state.faults_returned.iter_mut())
+            .find(|(fault, returned)| {
+                fault.operation() == operation && fault.count().map_or(true, |c| **returned < c)
+            });
+        if let Some((fault, returned)) = fault {
+            *returned += 1;
+            info!(
+                "Returning simulated error {} for {:?}",
+                fault.status_code(),
+                operation
+            );
+            let status = StatusCode::from_u16(fault.status_code())
+                .unwrap_or(StatusCode::INTERNAL_SERVER_ERROR);
+            return Ok(error_response(status, fault.message()));
+        }
+
+        match operation {
+            Operation::GetDeviceProvisioningInformation => {
+                let dir = self.config_path.parent().unwrap_or_else(|| Path::new(""));
+                let info = config.device(state.reprovisioned).provisioning_info(dir)?;
+                info!(
+                    "Returning device {} in IoT hub {}",
+                    info.device_id(),
+                    info.hub_name()
+                );
+                Ok(json_response(StatusCode::OK, &info))
+            }
+            Operation::ReprovisionDevice => {
+                info!("Device asked to be reprovisioned");
+                state.reprovisioned = true;
+                Ok(Response::builder()
+                    .status(StatusCode::OK)
+                    .header(CONTENT_LENGTH, "0")
+                    .body(Body::empty())
+                    .expect("response builder failure"))
+            }
+        }
+    }
+}
+
+impl Service for ExternalProvisioningServer {
+    type ReqBody = Body;
+    type ResBody = Body;
+    type Error = hyper::Error;
+    type Future = FutureResult<Response<Body>, hyper::Error>;
+
+    fn call(&mut self, req: Request<Body>) -> Self::Future {
+        future::ok(self.handle(&req))
+    }
+}
+
+impl NewService for ExternalProvisioningServer {
+    type ReqBody = Body;
+    type ResBody = Body;
+    type Error = hyper::Error;
+    type Service = Self;
+    type Future = FutureResult<Self, io::Error>;
+    type InitError = io::Error;
+
+    fn new_service(&self) -> Self::Future {
+        future::ok(self.clone())
+    }
+}
+
+fn error_response(status: StatusCode, message: &str) -> Response<Body> {
+    json_response(status, &ErrorResponse::new(message.to_string()))
+}
+
+fn json_response<T: Serialize>(status: StatusCode, body: &T) -> Response<Body> {
+    let body = serde_json::to_string(body).expect("could not serialize response");
+    Response::builder()
+        .status(status)
+        .header(CONTENT_TYPE, "application/json")
+        .header(CONTENT_LENGTH, body.len().to_string().as_str())
+        .body(body.into())
+        .expect("response builder failure")
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use futures::{Future, Stream};
+    use tempdir::TempDir;
+
+    use super::*;
+
+    fn server(dir: &TempDir, config: &serde_json::Value) -> ExternalProvisioningServer {
+        let path = dir.path().join("device.json");
+        fs::write(&path, config.to_string()).unwrap();
+        ExternalProvisioningServer::new(path).unwrap()
+    }
+
+    fn request(method: Method, uri: &str) -> Request<Body> {
+        Request::builder()
+            .method(method)
+            .uri(uri)
+            .body(Body::empty())
+            .unwrap()
+    }
+
+    fn message(response: Response<Body>) -> String {
+        let body = response.into_body().concat2().wait().unwrap();
+        let error: ErrorResponse = serde_json::from_slice(&body).unwrap();
+        error.message().to_string()
+    }
+
+    #[test]
+    fn fault_is_returned_count_times() {
+        let dir = TempDir::new("external-provisioning-server").unwrap();
+        let mut server = server(
+            &dir,
+            &serde_json::json!({
+                "hubName": "hub.azure-devices.net",
+                "deviceId": "device01",
+                "credentials": {
+                    "authType": "symmetric-key",
+                    "source": "hsm",
+                },
+                "faults": [
+                    {
+                        "operation": "getDeviceProvisioningInformation",
+                        "statusCode": 503,
+                        "message": "Busy",
+                        "count": 2,
+                    },
+                ],
+            }),
+        );
+        let uri = "http://localhost/device/provisioninginformation?api-version=2019-04-10";
+
+        for _ in 0..2 {
+            let response = server.call(request(Method::GET, uri)).wait().unwrap();
+            assert_eq!(StatusCode::SERVICE_UNAVAILABLE, response.status());
+            assert_eq!("Busy", message(response));
+        }
+
+        let response = server.call(request(Method::GET, uri)).wait().unwrap();
+        assert_eq!(StatusCode::OK, response.status());
+    }
+
+    #[test]
+    fn api_version_is_required() {
+        let dir = TempDir::new("external-provisioning-server").unwrap();
+        let mut server = server(
+            &dir,
+            &serde_json::json!({
+                "hubName": "hub.azure-devices.net",
+                "deviceId": "device01",
+                "credentials": {
+                    "authType": "symmetric-key",
+                    "source": "hsm",
+                },
+            }),
+        );
+
+        let response = server
+            .call(request(
+                Method::POST,
+                "http://localhost/device/reprovision?api-version=2018-06-28",
+            ))
+            .wait()
+            .unwrap();
+        assert_eq!(StatusCode::BAD_REQUEST, response.status());
+        assert!(!server.reprovisioned());
+
+        let response = server
+            .call(request(
+                Method::DELETE,
+                "http://localhost/device/reprovision",
+            ))
+            .wait()
+            .unwrap();
+        assert_eq!(StatusCode::NOT_FOUND, response.status());
+    }
+}
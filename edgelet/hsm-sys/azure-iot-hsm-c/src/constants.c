@@ -9,6 +9,7 @@ const char* const ENV_TRUSTED_CA_CERTS_PATH = "IOTEDGE_TRUSTED_CA_CERTS";
 const char* const ENV_REGISTRATION_ID = "IOTEDGE_REGISTRATION_ID";
 const char* const ENV_DEVICE_ID_CERTIFICATE_PATH = "IOTEDGE_DEVICE_IDENTITY_CERT";
 const char* const ENV_DEVICE_ID_PRIVATE_KEY_PATH = "IOTEDGE_DEVICE_IDENTITY_PK";
+const char* const ENV_PKCS11_MODULE_PATH = "IOTEDGE_PKCS11_MODULE_PATH";
 
 /* HSM directory name under IOTEDGE_HOMEDIR */
 const char* const DEFAULT_EDGE_HOME_DIR_UNIX = "/var/lib/iotedge"; // note MacOS is included
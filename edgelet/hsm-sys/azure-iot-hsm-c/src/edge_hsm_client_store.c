@@ -886,6 +886,27 @@ static CERT_INFO_HANDLE prepare_cert_info_handle
         LOG_ERROR("Private key file path is NULL");
         result = NULL;
     }
+    else if (is_pkcs11_uri(pk_file))
+    {
+        // the key stays on the token, hand out the URI as a reference to it
+        if ((cert_file = STRING_c_str(cert_entry->cert_file)) == NULL)
+        {
+            LOG_ERROR("Certificate file path NULL");
+            result = NULL;
+        }
+        else if ((cert_contents = read_file_into_cstring(cert_file, NULL)) == NULL)
+        {
+            LOG_ERROR("Could not read certificate into buffer %s", cert_file);
+            result = NULL;
+        }
+        else
+        {
+            result = certificate_info_create(cert_contents,
+                                             pk_file,
+                                             strlen(pk_file),
+                                             PRIVATE_KEY_REFERENCE);
+        }
+    }
     else if ((private_key_contents = read_file_into_buffer(pk_file, &private_key_size)) == NULL)
     {
         LOG_ERROR("Could not load private key into buffer %s", pk_file);
@@ -1780,7 +1801,8 @@ static int hsm_provision_edge_id_certificate(void)
 
         if (device_id_pk_path != NULL)
         {
-            if ((strlen(device_id_pk_path) != 0) && is_file_valid(device_id_pk_path))
+            if ((strlen(device_id_pk_path) != 0) &&
+                (is_file_valid(device_id_pk_path) || is_pkcs11_uri(device_id_pk_path)))
             {
                 mask |= 1 << i; i++;
             }
@@ -1791,7 +1813,8 @@ static int hsm_provision_edge_id_certificate(void)
 
             }
             env_set = true;
-            LOG_DEBUG("Env %s set to %s", ENV_DEVICE_ID_PRIVATE_KEY_PATH, device_id_pk_path);
+            LOG_DEBUG("Env %s set to %.*s", ENV_DEVICE_ID_PRIVATE_KEY_PATH,
+                      loggable_key_length(device_id_pk_path), device_id_pk_path);
         }
         else
         {
@@ -1884,7 +1907,8 @@ static int hsm_provision_edge_ca_certificates(uint64_t auto_generated_ca_lifetim
 
         if (device_ca_pk_path != NULL)
         {
-            if ((strlen(device_ca_pk_path) != 0) && is_file_valid(device_ca_pk_path))
+            if ((strlen(device_ca_pk_path) != 0) &&
+                (is_file_valid(device_ca_pk_path) || is_pkcs11_uri(device_ca_pk_path)))
             {
                 mask |= 1 << i; i++;
             }
@@ -1895,7 +1919,8 @@ static int hsm_provision_edge_ca_certificates(uint64_t auto_generated_ca_lifetim
 
             }
             env_set = true;
-            LOG_DEBUG("Env %s set to %s", ENV_DEVICE_PK_PATH, device_ca_pk_path);
+            LOG_DEBUG("Env %s set to %.*s", ENV_DEVICE_PK_PATH,
+                      loggable_key_length(device_ca_pk_path), device_ca_pk_path);
         }
         else
         {
@@ -18,6 +18,7 @@
 #include <openssl/bio.h>
 #include <openssl/err.h>
 #include <openssl/ec.h>
+#include <openssl/engine.h>
 #include <openssl/pem.h>
 #include <openssl/rand.h>
 #include <openssl/x509.h>
@@ -28,6 +29,7 @@
 #include "azure_c_shared_utility/hmacsha256.h"
 #include "edge_openssl_common.h"
 
+#include "hsm_constants.h"
 #include "hsm_err.h"
 #include "hsm_key.h"
 #include "hsm_log.h"
@@ -645,11 +647,73 @@ static int write_certificate_file
     return result;
 }
 
+/**
+ * Loads a private key that lives on a PKCS#11 token through the OpenSSL pkcs11 engine
+ * (libp11). The key never leaves the token; the returned EVP_PKEY forwards all
+ * private key operations to it.
+ *
+ * If the env variable IOTEDGE_PKCS11_MODULE_PATH is set, the engine loads that
+ * PKCS#11 module, otherwise the engine's own default module is used.
+ */
+static EVP_PKEY* load_pkcs11_private_key(const char* key_uri)
+{
+    EVP_PKEY* evp_key = NULL;
+#ifndef OPENSSL_NO_ENGINE
+    ENGINE* engine;
+    char* module_path = NULL;
+
+    ENGINE_load_dynamic();
+    if ((engine = ENGINE_by_id("pkcs11")) == NULL)
+    {
+        LOG_ERROR("Could not load the OpenSSL pkcs11 engine");
+    }
+    else
+    {
+        if (hsm_get_env(ENV_PKCS11_MODULE_PATH, &module_path) != 0)
+        {
+            LOG_ERROR("Failed to read env variable %s", ENV_PKCS11_MODULE_PATH);
+        }
+        else if ((module_path != NULL) &&
+                 !ENGINE_ctrl_cmd_string(engine, "MODULE_PATH", module_path, 0))
+        {
+            LOG_ERROR("Could not set the PKCS#11 module path %s", module_path);
+        }
+        else if (!ENGINE_init(engine))
+        {
+            LOG_ERROR("Could not initialize the OpenSSL pkcs11 engine");
+        }
+        else
+        {
+            evp_key = ENGINE_load_private_key(engine, key_uri, NULL, NULL);
+            if (evp_key == NULL)
+            {
+                LOG_ERROR("Failure ENGINE_load_private_key for %.*s",
+                          loggable_key_length(key_uri), key_uri);
+            }
+            // the key holds its own reference to the engine
+            ENGINE_finish(engine);
+        }
+        FREEIF(module_path);
+        ENGINE_free(engine);
+    }
+#else
+    LOG_ERROR("OpenSSL was built without engine support, cannot load %.*s",
+              loggable_key_length(key_uri), key_uri);
+#endif
+
+    return evp_key;
+}
+
 static EVP_PKEY* load_private_key_file(const char* key_file_name)
 {
     EVP_PKEY* evp_key;
-    BIO* key_file = BIO_new_file(key_file_name, "r");
-    if (key_file == NULL)
+    BIO* key_file;
+
+    if (is_pkcs11_uri(key_file_name))
+    {
+        evp_key = load_pkcs11_private_key(key_file_name);
+    }
+    else if ((key_file = BIO_new_file(key_file_name, "r")) == NULL)
     {
         LOG_ERROR("Failure to open key file %s", key_file_name);
         evp_key = NULL;
@@ -1627,7 +1691,8 @@ KEY_HANDLE create_cert_key(const char* key_file_name)
     }
     else if ((evp_key = load_private_key_file(key_file_name)) == NULL)
     {
-        LOG_ERROR("Could not load private key file %s", key_file_name);
+        LOG_ERROR("Could not load private key file %.*s",
+                  loggable_key_length(key_file_name), key_file_name);
         result = NULL;
     }
     else if ((cert_key = (CERT_KEY*)malloc(sizeof(CERT_KEY))) == NULL)
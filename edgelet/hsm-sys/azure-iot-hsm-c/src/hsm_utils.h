@@ -1,7 +1,9 @@
 #ifndef HSM_UTILS_H
 #define HSM_UTILS_H
 
+#include <stdbool.h>
 #include <stddef.h>
+#include <string.h>
 #include "umock_c/umock_c_prod.h"
 
 //##############################################################################
@@ -14,6 +16,27 @@
         (x) = NULL; \
     } while(0)
 
+/**
+ * Private keys on a PKCS#11 token are referred to by an RFC 7512 "pkcs11:" URI
+ * wherever a private key file path is otherwise expected.
+ */
+static inline bool is_pkcs11_uri(const char* uri)
+{
+    static const char PKCS11_SCHEME[] = "pkcs11:";
+
+    return (uri != NULL) && (strncmp(uri, PKCS11_SCHEME, sizeof(PKCS11_SCHEME) - 1) == 0);
+}
+
+/**
+ * The number of characters of a private key file path or URI that may be logged,
+ * for use with "%.*s". The query of a "pkcs11:" URI can hold the token PIN as
+ * pin-value, so it is left out.
+ */
+static inline int loggable_key_length(const char* key)
+{
+    return (int)(is_pkcs11_uri(key) ? strcspn(key, "?") : strlen(key));
+}
+
 MOCKABLE_FUNCTION(, char*, concat_files_to_cstring, const char **, file_names, int, num_files);
 MOCKABLE_FUNCTION(, char*, read_file_into_cstring, const char*, file_name, size_t*, output_buffer_size);
 MOCKABLE_FUNCTION(, void*, read_file_into_buffer, const char*, file_name, size_t*, output_buffer_size);
@@ -0,0 +1,876 @@
+// Copyright (c) Microsoft. All rights reserved.
+
+use std::collections::HashMap;
+use std::convert::TryInto;
+use std::fmt;
+use std::fs::{self, File};
+use std::io::{self, BufReader, BufWriter, Read, Seek, SeekFrom, Write};
+use std::path::{Component, Path, PathBuf};
+
+use chrono::{DateTime, Utc};
+use failure::{Fail, ResultExt};
+use futures::{future, Future};
+use openssl::error::ErrorStack;
+use openssl::hash::MessageDigest;
+use openssl::pkcs5::pbkdf2_hmac;
+use openssl::rand::rand_bytes;
+use openssl::symm::{Cipher, Crypter, Mode};
+use serde_derive::{Deserialize, Serialize};
+
+use edgelet_core::{ManualAuthMethod, ProvisioningType, RuntimeSettings};
+use edgelet_docker::{create_module_volume, list_module_volumes, ModuleVolume, Settings};
+
+use crate::error::{Error, ErrorKind};
+use crate::Command;
+
+/// Identifies an encrypted backup archive and the version of its format.
+const MAGIC: &[u8] = b"IOTEDGE-BACKUP-1";
+const SALT_LEN: usize = 16;
+const IV_LEN: usize = 12;
+const TAG_LEN: usize = 16;
+const HEADER_LEN: usize = MAGIC.len() + SALT_LEN + IV_LEN;
+const KEY_LEN: usize = 32;
+const PBKDF2_ITERATIONS: usize = 100_000;
+
+// The archive holds the manifest, then a nested archive of the daemon's home directory, then a
+// nested archive of each volume. The paths in the nested archives are relative to where they are
+// restored to.
+const MANIFEST_PATH: &str = "manifest.json";
+const HOMEDIR_ARCHIVE_PATH: &str = "homedir.tar";
+const VOLUMES_DIR: &str = "volumes";
+const ARCHIVE_EXTENSION: &str = "tar";
+
+const CACHE_DIR: &str = "cache";
+const PROVISIONING_BACKUP_FILENAME: &str = "provisioning_backup.json";
+
+/// The parts of the daemon's home directory that are backed up. `cache` holds the provisioning
+/// backup, and `active_hub` which of the configured IoT hubs the device failed over to. `hsm`
+/// holds the keys that the hybrid identity key, the certificates and the stored registry
+/// credentials are protected with, without which the rest can't be used on another device.
+const HOMEDIR_ENTRIES: &[&str] = &[
+    "active_hub",
+    CACHE_DIR,
+    "external_prov",
+    "hsm",
+    "hybrid_id",
+    "registry_credentials",
+];
+
+/// The first entry of every archive. Restoring reads it to know which volumes to create and to
+/// check that the backup is of this device.
+#[derive(Debug, Deserialize, Serialize)]
+struct Manifest {
+    created: DateTime<Utc>,
+    device: Option<DeviceIdentity>,
+    volumes: Vec<String>,
+}
+
+/// The fields of the provisioning backup that identify a device.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+struct DeviceIdentity {
+    hub_name: String,
+    device_id: String,
+}
+
+impl fmt::Display for DeviceIdentity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} on {}", self.device_id, self.hub_name)
+    }
+}
+
+/// Writes the daemon's state and the volumes of the modules to an archive encrypted with a
+/// passphrase.
+pub struct BackupCreate {
+    config_file: PathBuf,
+    output: PathBuf,
+    passphrase: String,
+}
+
+impl BackupCreate {
+    pub fn new(config_file: PathBuf, output: PathBuf, passphrase: String) -> Self {
+        BackupCreate {
+            config_file,
+            output,
+            passphrase,
+        }
+    }
+}
+
+impl Command for BackupCreate {
+    type Future = Box<dyn Future<Item = (), Error = Error> + Send>;
+
+    fn execute(self) -> Self::Future {
+        let BackupCreate {
+            config_file,
+            output,
+            passphrase,
+        } = self;
+
+        let settings = match Settings::new(&config_file).context(ErrorKind::InvalidConfig) {
+            Ok(settings) => settings,
+            Err(err) => return Box::new(future::err(Error::from(err))),
+        };
+        if passphrase.is_empty() {
+            return Box::new(future::err(Error::from(ErrorKind::BadPassphraseParameter)));
+        }
+
+        let result = list_module_volumes(settings.moby_runtime().uri())
+            .map_err(|err| Error::from(err.context(ErrorKind::CreateBackup)))
+            .and_then(move |volumes| {
+                let result = write_backup(&output, &passphrase, settings.homedir(), &volumes);
+                if result.is_err() {
+                    let _ = fs::remove_file(&output);
+                }
+                result.context(ErrorKind::CreateBackup)?;
+
+                println!(
+                    "Backed up the IoT Edge state and {} module volume(s) to {}",
+                    volumes.len(),
+                    output.display()
+                );
+                Ok(())
+            });
+
+        Box::new(result)
+    }
+}
+
+/// Restores an archive written by `BackupCreate`. Unless `reprovision` is set, the archive must
+/// be of the device that this device is configured as.
+pub struct BackupRestore {
+    config_file: PathBuf,
+    input: PathBuf,
+    passphrase: String,
+    reprovision: bool,
+}
+
+impl BackupRestore {
+    pub fn new(
+        config_file: PathBuf,
+        input: PathBuf,
+        passphrase: String,
+        reprovision: bool,
+    ) -> Self {
+        BackupRestore {
+            config_file,
+            input,
+            passphrase,
+            reprovision,
+        }
+    }
+}
+
+impl Command for BackupRestore {
+    type Future = Box<dyn Future<Item = (), Error = Error> + Send>;
+
+    fn execute(self) -> Self::Future {
+        let BackupRestore {
+            config_file,
+            input,
+            passphrase,
+            reprovision,
+        } = self;
+
+        let prepare = || -> Result<_, Error> {
+            let settings = Settings::new(&config_file).context(ErrorKind::InvalidConfig)?;
+
+            // Reading the whole archive once checks the passphrase, and that the archive is
+            // intact, before anything is overwritten.
+            let manifest = read_manifest(&input, &passphrase)?;
+
+            if !reprovision {
+                let target = target_identity(&settings);
+                if manifest.device.is_none() || manifest.device != target {
+                    return Err(Error::from(ErrorKind::BackupIdentityMismatch(
+                        manifest
+                            .device
+                            .as_ref()
+                            .map(ToString::to_string)
+                            .unwrap_or_else(|| "an unprovisioned device".to_string()),
+                        target
+                            .as_ref()
+                            .map(ToString::to_string)
+                            .unwrap_or_else(|| "unknown".to_string()),
+                    )));
+                }
+            }
+
+            Ok((settings, manifest))
+        };
+        let (settings, manifest) = match prepare() {
+            Ok(prepared) => prepared,
+            Err(err) => return Box::new(future::err(err)),
+        };
+
+        let docker_url = settings.moby_runtime().uri();
+        let volumes: Vec<_> = manifest
+            .volumes
+            .iter()
+            .map(|name| create_module_volume(docker_url, name))
+            .collect();
+
+        let result = future::join_all(volumes)
+            .map_err(|err| Error::from(err.context(ErrorKind::RestoreBackup)))
+            .and_then(move |volumes| {
+                let volumes: HashMap<_, _> = volumes
+                    .iter()
+                    .map(|volume| (volume.name().to_string(), volume.mountpoint().to_path_buf()))
+                    .collect();
+                unpack_backup(
+                    &input,
+                    &passphrase,
+                    settings.homedir(),
+                    &volumes,
+                    reprovision,
+                )?;
+
+                println!(
+                    "Restored the IoT Edge state and {} module volume(s) from {}",
+                    volumes.len(),
+                    input.display()
+                );
+                if reprovision {
+                    println!("The device provisions again when the IoT Edge service starts");
+                }
+                Ok(())
+            });
+
+        Box::new(result)
+    }
+}
+
+fn write_backup(
+    output: &Path,
+    passphrase: &str,
+    homedir: &Path,
+    volumes: &[ModuleVolume],
+) -> io::Result<()> {
+    let created = Utc::now();
+    let manifest = Manifest {
+        created,
+        device: read_device_identity(homedir),
+        volumes: volumes
+            .iter()
+            .map(|volume| volume.name().to_string())
+            .collect(),
+    };
+    let manifest = serde_json::to_vec(&manifest)?;
+
+    // The nested archives are staged next to the output, since their size has to be known before
+    // they can be added to it.
+    let staging_dir = match output.parent() {
+        Some(parent) if parent != Path::new("") => parent,
+        _ => Path::new("."),
+    };
+    let mtime = created.timestamp().try_into().unwrap_or_default();
+
+    let file = File::create(output)?;
+    let mut builder = tar::Builder::new(EncryptWriter::new(BufWriter::new(file), passphrase)?);
+
+    let mut header = tar::Header::new_gnu();
+    header.set_size(manifest.len() as u64);
+    header.set_mode(0o600);
+    header.set_mtime(mtime);
+    builder.append_data(&mut header, MANIFEST_PATH, &*manifest)?;
+
+    append_archive(
+        &mut builder,
+        Path::new(HOMEDIR_ARCHIVE_PATH),
+        staging_dir,
+        mtime,
+        |archive| {
+            for entry in HOMEDIR_ENTRIES {
+                let path = homedir.join(entry);
+                if path.is_dir() {
+                    archive.append_dir_all(entry, &path)?;
+                } else if path.is_file() {
+                    archive.append_path_with_name(&path, entry)?;
+                }
+            }
+            Ok(())
+        },
+    )?;
+
+    for volume in volumes {
+        append_archive(
+            &mut builder,
+            &volume_archive_path(volume.name()),
+            staging_dir,
+            mtime,
+            |archive| archive.append_dir_all("", volume.mountpoint()),
+        )?;
+    }
+
+    let file = builder
+        .into_inner()?
+        .finish()?
+        .into_inner()
+        .map_err(io::Error::from)?;
+    file.sync_all()
+}
+
+/// Adds a nested archive with the entries that `append` adds to it.
+fn append_archive<W>(
+    builder: &mut tar::Builder<W>,
+    path: &Path,
+    staging_dir: &Path,
+    mtime: u64,
+    append: impl FnOnce(&mut tar::Builder<File>) -> io::Result<()>,
+) -> io::Result<()>
+where
+    W: Write,
+{
+    let mut archive = tar::Builder::new(tempfile::tempfile_in(staging_dir)?);
+    archive.follow_symlinks(false);
+    append(&mut archive)?;
+
+    let mut file = archive.into_inner()?;
+    let len = file.seek(SeekFrom::End(0))?;
+    file.seek(SeekFrom::Start(0))?;
+
+    let mut header = tar::Header::new_gnu();
+    header.set_size(len);
+    header.set_mode(0o600);
+    header.set_mtime(mtime);
+    builder.append_data(&mut header, path, file)
+}
+
+fn read_manifest(input: &Path, passphrase: &str) -> Result<Manifest, Error> {
+    let mut reader = open_backup(input, passphrase)?;
+
+    let manifest = {
+        let mut archive = tar::Archive::new(&mut reader);
+        let mut entries = archive.entries().context(ErrorKind::InvalidBackup)?;
+        let mut entry = entries
+            .next()
+            .ok_or(ErrorKind::InvalidBackup)?
+            .context(ErrorKind::InvalidBackup)?;
+        if entry.path().context(ErrorKind::InvalidBackup)? != Path::new(MANIFEST_PATH) {
+            return Err(Error::from(ErrorKind::InvalidBackup));
+        }
+        serde_json::from_reader(&mut entry).context(ErrorKind::InvalidBackup)?
+    };
+
+    io::copy(&mut reader, &mut io::sink()).context(ErrorKind::InvalidBackup)?;
+    reader.finish()?;
+
+    Ok(manifest)
+}
+
+fn unpack_backup(
+    input: &Path,
+    passphrase: &str,
+    homedir: &Path,
+    volumes: &HashMap<String, PathBuf>,
+    reprovision: bool,
+) -> Result<(), Error> {
+    let mut reader = open_backup(input, passphrase)?;
+
+    {
+        let mut archive = tar::Archive::new(&mut reader);
+
+        for entry in archive.entries().context(ErrorKind::RestoreBackup)? {
+            let entry = entry.context(ErrorKind::RestoreBackup)?;
+            let path = entry.path().context(ErrorKind::RestoreBackup)?.into_owned();
+
+            if path == Path::new(HOMEDIR_ARCHIVE_PATH) {
+                // Without the provisioning backup, the daemon provisions the device again.
+                let provisioning_backup = provisioning_backup_path(Path::new(""));
+                unpack_archive(entry, homedir, |path| {
+                    !reprovision || path != provisioning_backup
+                })?;
+            } else if let Some(mountpoint) = volume_name(&path).and_then(|name| volumes.get(name)) {
+                unpack_archive(entry, mountpoint, |_| true)?;
+            }
+        }
+    }
+
+    reader.finish()
+}
+
+/// Restores the entries of a nested archive that `filter` accepts to `dest`. The entries can't
+/// be written outside of `dest`, whether through `..` or through symlinks in the archive.
+fn unpack_archive(
+    archive: impl Read,
+    dest: &Path,
+    filter: impl Fn(&Path) -> bool,
+) -> Result<(), Error> {
+    let mut archive = tar::Archive::new(archive);
+    archive.set_preserve_permissions(true);
+
+    for entry in archive.entries().context(ErrorKind::RestoreBackup)? {
+        let mut entry = entry.context(ErrorKind::RestoreBackup)?;
+        let path = entry.path().context(ErrorKind::RestoreBackup)?.into_owned();
+        if !filter(&path) {
+            continue;
+        }
+
+        if !entry.unpack_in(dest).context(ErrorKind::RestoreBackup)? {
+            return Err(Error::from(ErrorKind::InvalidBackup));
+        }
+
+        // Modules that don't run as root need to keep owning the files in their volumes.
+        // `unpack_in` has checked that the path stays within `dest`.
+        #[cfg(unix)]
+        set_owner(&dest.join(&path), entry.header()).context(ErrorKind::RestoreBackup)?;
+    }
+
+    Ok(())
+}
+
+fn volume_archive_path(name: &str) -> PathBuf {
+    Path::new(VOLUMES_DIR)
+        .join(name)
+        .with_extension(ARCHIVE_EXTENSION)
+}
+
+/// The name of the volume that a nested archive is of, if it is of one.
+fn volume_name(path: &Path) -> Option<&str> {
+    let mut components = path.components();
+    match (components.next(), components.next(), components.next()) {
+        (Some(Component::Normal(dir)), Some(Component::Normal(_)), None) if dir == VOLUMES_DIR => {
+            if path.extension()? == ARCHIVE_EXTENSION {
+                path.file_stem()?.to_str()
+            } else {
+                None
+            }
+        }
+        _ => None,
+    }
+}
+
+/// Where iotedged keeps the provisioning backup, relative to `homedir`.
+fn provisioning_backup_path(homedir: &Path) -> PathBuf {
+    homedir.join(CACHE_DIR).join(PROVISIONING_BACKUP_FILENAME)
+}
+
+fn read_device_identity(homedir: &Path) -> Option<DeviceIdentity> {
+    fs::read(provisioning_backup_path(homedir))
+        .ok()
+        .and_then(|backup| serde_json::from_slice(&backup).ok())
+}
+
+/// The identity that the daemon uses for this device: the one it was last provisioned with, or
+/// the one in the config file for manually provisioned devices.
+fn target_identity(settings: &Settings) -> Option<DeviceIdentity> {
+    if let Some(identity) = read_device_identity(settings.homedir()) {
+        return Some(identity);
+    }
+
+    match settings.provisioning().provisioning_type() {
+        ProvisioningType::Manual(manual) => match manual.authentication_method() {
+            ManualAuthMethod::DeviceConnectionString(connection_string) => connection_string
+                .parse_device_connection_string()
+                .ok()
+                .map(|(_, device_id, hub_name)| DeviceIdentity {
+                    hub_name,
+                    device_id,
+                }),
+            ManualAuthMethod::X509(x509) => Some(DeviceIdentity {
+                hub_name: x509.iothub_hostname().to_string(),
+                device_id: x509.device_id().to_string(),
+            }),
+        },
+        _ => None,
+    }
+}
+
+#[cfg(unix)]
+fn set_owner(path: &Path, header: &tar::Header) -> io::Result<()> {
+    use std::ffi::CString;
+    use std::os::unix::ffi::OsStrExt;
+
+    let invalid = |_| io::Error::new(io::ErrorKind::InvalidData, "invalid owner");
+    let uid = header.uid()?.try_into().map_err(invalid)?;
+    let gid = header.gid()?.try_into().map_err(invalid)?;
+
+    let path = CString::new(path.as_os_str().as_bytes())?;
+    if unsafe { libc::lchown(path.as_ptr(), uid, gid) } == 0 {
+        Ok(())
+    } else {
+        Err(io::Error::last_os_error())
+    }
+}
+
+fn derive_key(passphrase: &str, salt: &[u8]) -> Result<[u8; KEY_LEN], ErrorStack> {
+    let mut key = [0; KEY_LEN];
+    pbkdf2_hmac(
+        passphrase.as_bytes(),
+        salt,
+        PBKDF2_ITERATIONS,
+        MessageDigest::sha256(),
+        &mut key,
+    )?;
+    Ok(key)
+}
+
+fn open_backup(input: &Path, passphrase: &str) -> Result<DecryptReader<BufReader<File>>, Error> {
+    let file = File::open(input).context(ErrorKind::RestoreBackup)?;
+    let len = file.metadata().context(ErrorKind::RestoreBackup)?.len();
+    DecryptReader::new(BufReader::new(file), len, passphrase)
+}
+
+/// Encrypts everything written to it with AES-256-GCM, using a key derived from a passphrase.
+/// The output is a header with the salt and IV, the ciphertext, and the authentication tag.
+struct EncryptWriter<W> {
+    inner: W,
+    crypter: Crypter,
+    buf: Vec<u8>,
+}
+
+impl<W> EncryptWriter<W>
+where
+    W: Write,
+{
+    fn new(mut inner: W, passphrase: &str) -> io::Result<Self> {
+        let cipher = Cipher::aes_256_gcm();
+
+        let mut header = MAGIC.to_vec();
+        let mut salt = [0; SALT_LEN];
+        let mut iv = [0; IV_LEN];
+        rand_bytes(&mut salt).map_err(to_io_error)?;
+        rand_bytes(&mut iv).map_err(to_io_error)?;
+        header.extend_from_slice(&salt);
+        header.extend_from_slice(&iv);
+
+        let key = derive_key(passphrase, &salt).map_err(to_io_error)?;
+        let mut crypter =
+            Crypter::new(cipher, Mode::Encrypt, &key, Some(&iv)).map_err(to_io_error)?;
+        crypter.aad_update(&header).map_err(to_io_error)?;
+
+        inner.write_all(&header)?;
+
+        Ok(EncryptWriter {
+            inner,
+            crypter,
+            buf: vec![],
+        })
+    }
+
+    fn finish(mut self) -> io::Result<W> {
+        self.buf.resize(Cipher::aes_256_gcm().block_size(), 0);
+        let len = self.crypter.finalize(&mut self.buf).map_err(to_io_error)?;
+        self.inner.write_all(&self.buf[..len])?;
+
+        let mut tag = [0; TAG_LEN];
+        self.crypter.get_tag(&mut tag).map_err(to_io_error)?;
+        self.inner.write_all(&tag)?;
+        self.inner.flush()?;
+
+        Ok(self.inner)
+    }
+}
+
+impl<W> Write for EncryptWriter<W>
+where
+    W: Write,
+{
+    fn write(&mut self, data: &[u8]) -> io::Result<usize> {
+        self.buf
+            .resize(data.len() + Cipher::aes_256_gcm().block_size(), 0);
+        let len = self
+            .crypter
+            .update(data, &mut self.buf)
+            .map_err(to_io_error)?;
+        self.inner.write_all(&self.buf[..len])?;
+        Ok(data.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// Decrypts what `EncryptWriter` wrote. The plaintext is only authentic once `finish` succeeds.
+struct DecryptReader<R> {
+    inner: io::Take<R>,
+    crypter: Crypter,
+    tag: [u8; TAG_LEN],
+    input: Vec<u8>,
+    output: Vec<u8>,
+    pos: usize,
+}
+
+impl<R> DecryptReader<R>
+where
+    R: Read + Seek,
+{
+    fn new(mut inner: R, len: u64, passphrase: &str) -> Result<Self, Error> {
+        let ciphertext_len = len
+            .checked_sub((HEADER_LEN + TAG_LEN) as u64)
+            .ok_or(ErrorKind::InvalidBackup)?;
+
+        let mut header = [0; HEADER_LEN];
+        inner
+            .read_exact(&mut header)
+            .context(ErrorKind::InvalidBackup)?;
+        if !header.starts_with(MAGIC) {
+            return Err(Error::from(ErrorKind::InvalidBackup));
+        }
+        let (salt, iv) = header[MAGIC.len()..].split_at(SALT_LEN);
+
+        let mut tag = [0; TAG_LEN];
+        inner
+            .seek(SeekFrom::Start(HEADER_LEN as u64 + ciphertext_len))
+            .and_then(|_| inner.read_exact(&mut tag))
+            .and_then(|()| inner.seek(SeekFrom::Start(HEADER_LEN as u64)))
+            .context(ErrorKind::InvalidBackup)?;
+
+        let key = derive_key(passphrase, salt).context(ErrorKind::RestoreBackup)?;
+        let mut crypter = Crypter::new(Cipher::aes_256_gcm(), Mode::Decrypt, &key, Some(iv))
+            .context(ErrorKind::RestoreBackup)?;
+        crypter
+            .aad_update(&header)
+            .context(ErrorKind::RestoreBackup)?;
+
+        Ok(DecryptReader {
+            inner: inner.take(ciphertext_len),
+            crypter,
+            tag,
+            input: vec![0; 64 * 1024],
+            output: vec![],
+            pos: 0,
+        })
+    }
+
+    /// Checks the authentication tag once all of the ciphertext has been read.
+    fn finish(mut self) -> Result<(), Error> {
+        if self.inner.limit() > 0 {
+            return Err(Error::from(ErrorKind::InvalidBackup));
+        }
+
+        self.crypter
+            .set_tag(&self.tag)
+            .context(ErrorKind::RestoreBackup)?;
+        let mut buf = vec![0; Cipher::aes_256_gcm().block_size()];
+        self.crypter
+            .finalize(&mut buf)
+            .context(ErrorKind::BadBackupPassphrase)?;
+        Ok(())
+    }
+}
+
+impl<R> Read for DecryptReader<R>
+where
+    R: Read,
+{
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        while self.pos == self.output.len() {
+            let len = self.inner.read(&mut self.input)?;
+            if len == 0 {
+                return Ok(0);
+            }
+
+            self.output
+                .resize(len + Cipher::aes_256_gcm().block_size(), 0);
+            let len = self
+                .crypter
+                .update(&self.input[..len], &mut self.output)
+                .map_err(to_io_error)?;
+            self.output.truncate(len);
+            self.pos = 0;
+        }
+
+        let len = buf.len().min(self.output.len() - self.pos);
+        buf[..len].copy_from_slice(&self.output[self.pos..self.pos + len]);
+        self.pos += len;
+        Ok(len)
+    }
+}
+
+fn to_io_error(err: ErrorStack) -> io::Error {
+    io::Error::new(io::ErrorKind::Other, err)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+
+    fn encrypt(plaintext: &[u8], passphrase: &str) -> Vec<u8> {
+        let mut writer = EncryptWriter::new(vec![], passphrase).unwrap();
+        writer.write_all(plaintext).unwrap();
+        writer.finish().unwrap()
+    }
+
+    fn decrypt(ciphertext: Vec<u8>, passphrase: &str) -> Result<Vec<u8>, Error> {
+        let len = ciphertext.len() as u64;
+        let mut reader = DecryptReader::new(Cursor::new(ciphertext), len, passphrase)?;
+        let mut plaintext = vec![];
+        reader.read_to_end(&mut plaintext).unwrap();
+        reader.finish()?;
+        Ok(plaintext)
+    }
+
+    #[test]
+    fn archive_round_trips_with_the_passphrase() {
+        let plaintext = vec![42; 100 * 1024];
+        let ciphertext = encrypt(&plaintext, "correct horse");
+        assert!(ciphertext.starts_with(MAGIC));
+        assert_eq!(plaintext, decrypt(ciphertext, "correct horse").unwrap());
+    }
+
+    #[test]
+    fn archive_is_rejected_with_another_passphrase() {
+        let ciphertext = encrypt(b"state", "correct horse");
+        let err = decrypt(ciphertext, "battery staple").unwrap_err();
+        match err.kind() {
+            ErrorKind::BadBackupPassphrase => (),
+            kind => panic!("expected BadBackupPassphrase but got {:?}", kind),
+        }
+    }
+
+    #[test]
+    fn archive_is_rejected_when_modified() {
+        let mut ciphertext = encrypt(b"state", "correct horse");
+        ciphertext[HEADER_LEN] ^= 1;
+        assert!(decrypt(ciphertext, "correct horse").is_err());
+    }
+
+    #[test]
+    fn volume_archives_are_named_after_their_volume() {
+        assert_eq!(
+            Some("edgehub"),
+            volume_name(&volume_archive_path("edgehub"))
+        );
+
+        assert_eq!(None, volume_name(Path::new(HOMEDIR_ARCHIVE_PATH)));
+        assert_eq!(None, volume_name(Path::new("volumes/edgehub")));
+        assert_eq!(None, volume_name(Path::new("volumes/edgehub/db.tar")));
+        assert_eq!(None, volume_name(Path::new("other/edgehub.tar")));
+    }
+
+    #[test]
+    fn homedir_state_round_trips() {
+        let homedir = tempfile::tempdir().unwrap();
+        let provisioning_backup = provisioning_backup_path(homedir.path());
+        fs::create_dir_all(provisioning_backup.parent().unwrap()).unwrap();
+        fs::write(
+            &provisioning_backup,
+            r#"{"device_id":"device1","hub_name":"hub1.azure-devices.net","reconfigure":"None"}"#,
+        )
+        .unwrap();
+        fs::write(homedir.path().join("cache/identity_cache"), "identities").unwrap();
+        fs::write(homedir.path().join("active_hub"), "hub2.azure-devices.net").unwrap();
+        fs::write(homedir.path().join("settings_state"), "not backed up").unwrap();
+
+        let output_dir = tempfile::tempdir().unwrap();
+        let output = output_dir.path().join("backup");
+        write_backup(&output, "correct horse", homedir.path(), &[]).unwrap();
+
+        let manifest = read_manifest(&output, "correct horse").unwrap();
+        assert_eq!(
+            Some(DeviceIdentity {
+                hub_name: "hub1.azure-devices.net".to_string(),
+                device_id: "device1".to_string(),
+            }),
+            manifest.device
+        );
+
+        let restored = tempfile::tempdir().unwrap();
+        unpack_backup(
+            &output,
+            "correct horse",
+            restored.path(),
+            &HashMap::new(),
+            false,
+        )
+        .unwrap();
+        assert_eq!(
+            fs::read(&provisioning_backup).unwrap(),
+            fs::read(provisioning_backup_path(restored.path())).unwrap()
+        );
+        assert_eq!(
+            "hub2.azure-devices.net",
+            fs::read_to_string(restored.path().join("active_hub")).unwrap()
+        );
+        assert!(!restored.path().join("settings_state").exists());
+
+        let reprovisioned = tempfile::tempdir().unwrap();
+        unpack_backup(
+            &output,
+            "correct horse",
+            reprovisioned.path(),
+            &HashMap::new(),
+            true,
+        )
+        .unwrap();
+        assert!(!provisioning_backup_path(reprovisioned.path()).exists());
+        assert!(reprovisioned.path().join("cache/identity_cache").exists());
+        assert!(reprovisioned.path().join("active_hub").exists());
+    }
+
+    /// Writes a backup whose nested homedir archive has the entries that `append` adds.
+    fn write_crafted_backup(
+        output: &Path,
+        append: impl FnOnce(&mut tar::Builder<File>) -> io::Result<()>,
+    ) {
+        let file = File::create(output).unwrap();
+        let mut builder =
+            tar::Builder::new(EncryptWriter::new(BufWriter::new(file), "correct horse").unwrap());
+        append_archive(
+            &mut builder,
+            Path::new(HOMEDIR_ARCHIVE_PATH),
+            output.parent().unwrap(),
+            0,
+            append,
+        )
+        .unwrap();
+        builder
+            .into_inner()
+            .unwrap()
+            .finish()
+            .unwrap()
+            .flush()
+            .unwrap();
+    }
+
+    #[test]
+    fn restore_does_not_follow_symlinks_out_of_homedir() {
+        let dir = tempfile::tempdir().unwrap();
+        let outside = dir.path().join("outside");
+        let homedir = dir.path().join("homedir");
+        fs::create_dir(&outside).unwrap();
+        fs::create_dir(&homedir).unwrap();
+
+        let output = dir.path().join("backup");
+        write_crafted_backup(&output, |archive| {
+            let mut header = tar::Header::new_gnu();
+            header.set_entry_type(tar::EntryType::Symlink);
+            header.set_size(0);
+            header.set_link_name(&outside)?;
+            archive.append_data(&mut header, "cache", io::empty())?;
+
+            let mut header = tar::Header::new_gnu();
+            header.set_size(4);
+            header.set_mode(0o600);
+            archive.append_data(&mut header, "cache/evil", &b"evil"[..])
+        });
+
+        assert!(unpack_backup(&output, "correct horse", &homedir, &HashMap::new(), false).is_err());
+        assert!(!outside.join("evil").exists());
+    }
+
+    #[test]
+    fn restore_does_not_write_parent_paths() {
+        let dir = tempfile::tempdir().unwrap();
+        let homedir = dir.path().join("homedir");
+        fs::create_dir(&homedir).unwrap();
+
+        let output = dir.path().join("backup");
+        write_crafted_backup(&output, |archive| {
+            let mut header = tar::Header::new_gnu();
+            header.set_path("xxxxxxx")?;
+            header.set_size(4);
+            header.set_mode(0o600);
+            // `set_path` refuses `..`, so write the name directly.
+            header.as_old_mut().name[..7].copy_from_slice(b"../evil");
+            header.set_cksum();
+            archive.append(&header, &b"evil"[..])
+        });
+
+        assert!(unpack_backup(&output, "correct horse", &homedir, &HashMap::new(), false).is_err());
+        assert!(!dir.path().join("evil").exists());
+    }
+}
@@ -0,0 +1,242 @@
+use std::iter;
+use std::net::IpAddr;
+use std::str::FromStr;
+
+use failure::{self, Context};
+
+use edgelet_core::IpamConfig;
+
+use crate::check::{checker::Checker, Check, CheckResult};
+
+#[derive(Default, serde_derive::Serialize)]
+pub(crate) struct ContainerEngineNetworkSubnets {
+    subnets: Vec<(String, String)>,
+}
+
+impl Checker for ContainerEngineNetworkSubnets {
+    fn id(&self) -> &'static str {
+        "container-engine-network-subnets"
+    }
+    fn description(&self) -> &'static str {
+        "container network subnets are valid and do not overlap"
+    }
+    fn execute(&mut self, check: &mut Check) -> CheckResult {
+        self.inner_execute(check)
+            .unwrap_or_else(CheckResult::Failed)
+    }
+    fn get_json(&self) -> serde_json::Value {
+        serde_json::to_value(self).unwrap()
+    }
+}
+
+impl ContainerEngineNetworkSubnets {
+    fn inner_execute(&mut self, check: &mut Check) -> Result<CheckResult, failure::Error> {
+        let settings = if let Some(settings) = &check.settings {
+            settings
+        } else {
+            return Ok(CheckResult::Skipped);
+        };
+
+        let moby_runtime = settings.moby_runtime();
+        let networks = iter::once(moby_runtime.network().network())
+            .chain(moby_runtime.networks().iter().cloned());
+
+        let mut subnets: Vec<(String, Cidr)> = vec![];
+        for network in networks {
+            let configs = network
+                .ipam()
+                .and_then(|ipam| ipam.config())
+                .unwrap_or_default();
+            for config in configs {
+                if let Some(subnet) = validate(config).map_err(|message| {
+                    Context::new(format!(
+                        "Network {} in config.yaml has an invalid IPAM config: {}",
+                        network.name(),
+                        message,
+                    ))
+                })? {
+                    self.subnets
+                        .push((network.name().to_string(), subnet.to_string()));
+                    subnets.push((network.name().to_string(), subnet));
+                }
+            }
+        }
+
+        if subnets.is_empty() {
+            return Ok(CheckResult::Ignored);
+        }
+
+        for (i, (name1, subnet1)) in subnets.iter().enumerate() {
+            for (name2, subnet2) in &subnets[i + 1..] {
+                if subnet1.overlaps(*subnet2) {
+                    return Err(Context::new(format!(
+                        "Subnet {} of network {} overlaps subnet {} of network {} in config.yaml.\n\
+                         Please give each network a distinct subnet.",
+                        subnet1, name1, subnet2, name2,
+                    ))
+                    .into());
+                }
+            }
+        }
+
+        Ok(CheckResult::Ok)
+    }
+}
+
+/// Checks that the gateway and IP range of an IPAM config fall within its subnet, and returns the
+/// subnet if one is set.
+fn validate(config: &IpamConfig) -> Result<Option<Cidr>, String> {
+    let subnet = match config.subnet() {
+        Some(subnet) => subnet.parse::<Cidr>()?,
+        None => return Ok(None),
+    };
+
+    if let Some(gateway) = config.gateway() {
+        let gateway: IpAddr = gateway
+            .parse()
+            .map_err(|_| format!("gateway {} is not an IP address", gateway))?;
+        if !subnet.contains(gateway) {
+            return Err(format!("gateway {} is not in subnet {}", gateway, subnet));
+        }
+    }
+
+    if let Some(ip_range) = config.ip_range() {
+        let ip_range: Cidr = ip_range.parse()?;
+        if ip_range.prefix < subnet.prefix || !subnet.contains(ip_range.addr) {
+            return Err(format!("IP range {} is not in subnet {}", ip_range, subnet));
+        }
+    }
+
+    Ok(Some(subnet))
+}
+
+/// An IPv4 or IPv6 subnet in CIDR notation, eg 172.18.0.0/16.
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct Cidr {
+    addr: IpAddr,
+    prefix: u8,
+}
+
+impl Cidr {
+    fn contains(self, addr: IpAddr) -> bool {
+        same_family(self.addr, addr) && mask(self.addr, self.prefix) == mask(addr, self.prefix)
+    }
+
+    fn overlaps(self, other: Self) -> bool {
+        let prefix = std::cmp::min(self.prefix, other.prefix);
+        same_family(self.addr, other.addr) && mask(self.addr, prefix) == mask(other.addr, prefix)
+    }
+}
+
+impl FromStr for Cidr {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let err = || format!("{} is not a subnet in CIDR notation", s);
+
+        let mut parts = s.splitn(2, '/');
+        let addr: IpAddr = parts
+            .next()
+            .and_then(|addr| addr.parse().ok())
+            .ok_or_else(err)?;
+        let prefix: u8 = parts
+            .next()
+            .and_then(|prefix| prefix.parse().ok())
+            .ok_or_else(err)?;
+        let max_prefix = if addr.is_ipv4() { 32 } else { 128 };
+        if prefix > max_prefix {
+            return Err(err());
+        }
+
+        Ok(Cidr { addr, prefix })
+    }
+}
+
+impl std::fmt::Display for Cidr {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}/{}", self.addr, self.prefix)
+    }
+}
+
+fn same_family(addr1: IpAddr, addr2: IpAddr) -> bool {
+    addr1.is_ipv4() == addr2.is_ipv4()
+}
+
+/// Returns the first `prefix` bits of the address.
+fn mask(addr: IpAddr, prefix: u8) -> u128 {
+    let (bits, width) = match addr {
+        IpAddr::V4(addr) => (u128::from(u32::from(addr)), 32),
+        IpAddr::V6(addr) => (u128::from(addr), 128),
+    };
+    if prefix == 0 {
+        0
+    } else {
+        bits >> (width - u32::from(prefix))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cidr(s: &str) -> Cidr {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn parse_cidr() {
+        assert_eq!(
+            Cidr {
+                addr: "172.18.0.0".parse().unwrap(),
+                prefix: 16,
+            },
+            cidr("172.18.0.0/16")
+        );
+        assert_eq!(128, cidr("2001:db8::/128").prefix);
+
+        assert!("172.18.0.0".parse::<Cidr>().is_err());
+        assert!("172.18.0.0/33".parse::<Cidr>().is_err());
+        assert!("2001:db8::/129".parse::<Cidr>().is_err());
+        assert!("172.18.0/16".parse::<Cidr>().is_err());
+        assert!("fieldbus/24".parse::<Cidr>().is_err());
+    }
+
+    #[test]
+    fn overlapping_subnets() {
+        assert!(cidr("172.18.0.0/16").overlaps(cidr("172.18.5.0/24")));
+        assert!(cidr("172.18.5.0/24").overlaps(cidr("172.18.0.0/16")));
+        assert!(cidr("10.0.0.0/8").overlaps(cidr("10.0.0.0/8")));
+        assert!(cidr("0.0.0.0/0").overlaps(cidr("192.168.50.0/24")));
+        assert!(cidr("2001:db8:1::/64").overlaps(cidr("2001:db8:1::/48")));
+
+        assert!(!cidr("172.18.0.0/16").overlaps(cidr("172.19.0.0/16")));
+        assert!(!cidr("192.168.50.0/25").overlaps(cidr("192.168.50.128/25")));
+        assert!(!cidr("0.0.0.0/0").overlaps(cidr("::/0")));
+    }
+
+    #[test]
+    fn validate_ipam_config() {
+        let config = IpamConfig::default()
+            .with_subnet("192.168.50.0/24".to_string())
+            .with_gateway("192.168.50.1".to_string())
+            .with_ip_range("192.168.50.128/25".to_string());
+        assert_eq!(Ok(Some(cidr("192.168.50.0/24"))), validate(&config));
+
+        assert_eq!(Ok(None), validate(&IpamConfig::default()));
+
+        let config = IpamConfig::default()
+            .with_subnet("192.168.50.0/24".to_string())
+            .with_gateway("192.168.51.1".to_string());
+        assert!(validate(&config).is_err());
+
+        let config = IpamConfig::default()
+            .with_subnet("192.168.50.0/24".to_string())
+            .with_ip_range("192.168.0.0/16".to_string());
+        assert!(validate(&config).is_err());
+
+        let config = IpamConfig::default()
+            .with_subnet("2001:db8:1::/64".to_string())
+            .with_gateway("192.168.50.1".to_string());
+        assert!(validate(&config).is_err());
+    }
+}
@@ -0,0 +1,85 @@
+use failure::{self, Context, ResultExt};
+
+use dps::{RegistrationState, DPS_REGISTRATION_STATE_FILENAME};
+use edgelet_core::{self, ProvisioningType, RuntimeSettings};
+
+use crate::check::{checker::Checker, Check, CheckResult};
+
+#[derive(Default, serde_derive::Serialize)]
+pub(crate) struct DpsRegistration {
+    registration_state: Option<RegistrationState>,
+}
+
+impl Checker for DpsRegistration {
+    fn id(&self) -> &'static str {
+        "dps-registration"
+    }
+    fn description(&self) -> &'static str {
+        "DPS has not rejected the registration of the device"
+    }
+    fn execute(&mut self, check: &mut Check) -> CheckResult {
+        self.inner_execute(check)
+            .unwrap_or_else(CheckResult::Failed)
+    }
+    fn get_json(&self) -> serde_json::Value {
+        serde_json::to_value(self).unwrap()
+    }
+}
+
+impl DpsRegistration {
+    fn inner_execute(&mut self, check: &mut Check) -> Result<CheckResult, failure::Error> {
+        let settings = if let Some(settings) = &check.settings {
+            settings
+        } else {
+            return Ok(CheckResult::Skipped);
+        };
+
+        match settings.provisioning().provisioning_type() {
+            ProvisioningType::Dps(_) => (),
+            _ => return Ok(CheckResult::Ignored),
+        }
+
+        // iotedged keeps the state of the registration in its cache directory
+        let path = settings
+            .homedir()
+            .join("cache")
+            .join(DPS_REGISTRATION_STATE_FILENAME);
+        let state = RegistrationState::load(&path).with_context(|_| {
+            format!("Could not read DPS registration state {}", path.display())
+        })?;
+        let state = if let Some(state) = state {
+            state
+        } else {
+            return Ok(CheckResult::Ok);
+        };
+        self.registration_state = Some(state.clone());
+
+        if let Some(failure) = state.failure() {
+            let mut message = format!(
+                "DPS rejected the registration {:?} in scope {:?} with {}.",
+                state.registration_id(),
+                state.scope_id(),
+                failure,
+            );
+            if failure.is_disabled() {
+                message.push_str(" The enrollment of this device is disabled in DPS.");
+            }
+            return Ok(CheckResult::Failed(Context::new(message).into()));
+        }
+
+        if let Some(operation_id) = state.operation_id() {
+            return Ok(CheckResult::Warning(
+                Context::new(format!(
+                    "DPS has not finished assigning the registration {:?} in scope {:?} (operation {:?}). \
+                     The IoT Edge daemon resumes the registration when it restarts.",
+                    state.registration_id(),
+                    state.scope_id(),
+                    operation_id,
+                ))
+                .into(),
+            ));
+        }
+
+        Ok(CheckResult::Ok)
+    }
+}
@@ -2,9 +2,25 @@ use failure::{self, Context, Fail};
 
 use crate::check::{checker::Checker, Check, CheckResult};
 
+/// The number of samples taken from each NTP server address to compute its delay and jitter.
+const NUM_SAMPLES_PER_SERVER: usize = 3;
+
 #[derive(Default, serde_derive::Serialize)]
 pub(crate) struct HostLocalTime {
     offset: Option<i64>,
+    confidence: Option<f64>,
+    servers: Vec<NtpServer>,
+}
+
+#[derive(serde_derive::Serialize)]
+struct NtpServer {
+    address: String,
+    offset_ms: Option<i64>,
+    delay_ms: Option<i64>,
+    jitter_ms: Option<i64>,
+    stratum: Option<u8>,
+    truechimer: bool,
+    error: Option<String>,
 }
 
 impl Checker for HostLocalTime {
@@ -37,27 +53,87 @@ impl HostLocalTime {
             }
         }
 
-        let mini_sntp::SntpTimeQueryResult {
-            local_clock_offset, ..
-        } = match mini_sntp::query(&check.ntp_server) {
-            Ok(result) => result,
-            Err(err) => {
-                if is_server_unreachable_error(&err) {
-                    return Ok(CheckResult::Warning(
-                        err.context("Could not query NTP server").into(),
-                    ));
-                } else {
-                    return Err(err.context("Could not query NTP server").into());
+        let mini_sntp::MultiServerQueryResult {
+            servers,
+            resolve_errors,
+            selection,
+        } = mini_sntp::query_servers(
+            &check.ntp_servers,
+            mini_sntp::NtpVersion::V4,
+            NUM_SAMPLES_PER_SERVER,
+        );
+
+        self.servers = servers
+            .iter()
+            .map(|server| match &server.result {
+                Ok(statistics) => NtpServer {
+                    address: server.addr.to_string(),
+                    offset_ms: Some(statistics.local_clock_offset.num_milliseconds()),
+                    delay_ms: Some(statistics.round_trip_delay.num_milliseconds()),
+                    jitter_ms: Some(statistics.jitter.num_milliseconds()),
+                    stratum: Some(statistics.stratum),
+                    truechimer: selection.as_ref().map_or(false, |selection| {
+                        selection.truechimers.contains(&server.addr)
+                    }),
+                    error: None,
+                },
+                Err(err) => NtpServer {
+                    address: server.addr.to_string(),
+                    offset_ms: None,
+                    delay_ms: None,
+                    jitter_ms: None,
+                    stratum: None,
+                    truechimer: false,
+                    error: Some(err.to_string()),
+                },
+            })
+            .collect();
+
+        let selection = if let Some(selection) = selection {
+            selection
+        } else {
+            // If some servers responded, they just didn't agree with each other. Otherwise report the first error,
+            // preferring query errors over resolution errors since they're more specific.
+            let err = if servers.iter().any(|server| server.result.is_ok()) {
+                None
+            } else {
+                servers
+                    .into_iter()
+                    .filter_map(|server| server.result.err())
+                    .chain(resolve_errors)
+                    .next()
+            };
+
+            return match err {
+                Some(err) => {
+                    if is_server_unreachable_error(&err) {
+                        Ok(CheckResult::Warning(
+                            err.context("Could not query NTP server").into(),
+                        ))
+                    } else {
+                        Err(err.context("Could not query NTP server").into())
+                    }
                 }
-            }
+
+                None => Ok(CheckResult::Warning(
+                    Context::new(
+                        "The NTP servers do not agree on the current time, so the time on the device could not be verified.",
+                    )
+                    .into(),
+                )),
+            };
         };
 
-        let offset = local_clock_offset.num_seconds().abs();
+        let offset = selection.local_clock_offset.num_seconds().abs();
         self.offset = Some(offset);
+        self.confidence = Some(selection.confidence);
         if offset >= 10 {
             return Ok(CheckResult::Warning(Context::new(format!(
-            "Time on the device is out of sync with the NTP server. This may cause problems connecting to IoT Hub.\n\
+            "Time on the device is out of sync with the NTP servers by {} seconds ({} of {} servers agree). This may cause problems connecting to IoT Hub.\n\
              Please ensure time on device is accurate, for example by {}.",
+            offset,
+            selection.truechimers.len(),
+            selection.truechimers.len() + selection.falsetickers.len(),
             if cfg!(windows) {
                 "setting up the Windows Time service to automatically sync with a time server"
             } else {
@@ -6,7 +6,9 @@ mod container_engine_installed;
 mod container_engine_ipv6;
 mod container_engine_is_moby;
 mod container_engine_logrotate;
+mod container_engine_network_subnets;
 mod container_local_time;
+mod dps_registration;
 mod host_connect_dps_endpoint;
 mod host_connect_iothub;
 mod host_local_time;
@@ -26,7 +28,9 @@ pub(crate) use self::container_engine_installed::ContainerEngineInstalled;
 pub(crate) use self::container_engine_ipv6::ContainerEngineIPv6;
 pub(crate) use self::container_engine_is_moby::ContainerEngineIsMoby;
 pub(crate) use self::container_engine_logrotate::ContainerEngineLogrotate;
+pub(crate) use self::container_engine_network_subnets::ContainerEngineNetworkSubnets;
 pub(crate) use self::container_local_time::ContainerLocalTime;
+pub(crate) use self::dps_registration::DpsRegistration;
 pub(crate) use self::host_connect_dps_endpoint::HostConnectDpsEndpoint;
 pub(crate) use self::host_connect_iothub::get_host_connect_iothub_tests;
 pub(crate) use self::host_local_time::HostLocalTime;
@@ -21,6 +21,9 @@ use crate::LatestVersions;
 mod additional_info;
 use self::additional_info::AdditionalInfo;
 
+mod report;
+use self::report::CheckReport;
+
 mod stdout;
 use self::stdout::Stdout;
 
@@ -39,7 +42,7 @@ pub struct Check {
     dont_run: BTreeSet<String>,
     iotedged: PathBuf,
     latest_versions: Result<super::LatestVersions, Option<Error>>,
-    ntp_server: String,
+    ntp_servers: Vec<String>,
     output_format: OutputFormat,
     verbose: bool,
     warnings_as_errors: bool,
@@ -58,6 +61,8 @@ pub struct Check {
 #[derive(Clone, Copy, Debug, PartialEq)]
 pub enum OutputFormat {
     Json,
+    Junit,
+    Sarif,
     Text,
 }
 
@@ -94,7 +99,7 @@ impl Check {
         expected_iotedged_version: Option<String>,
         iotedged: PathBuf,
         iothub_hostname: Option<String>,
-        ntp_server: String,
+        ntp_servers: Vec<String>,
         output_format: OutputFormat,
         verbose: bool,
         warnings_as_errors: bool,
@@ -200,7 +205,7 @@ impl Check {
                 dont_run,
                 iotedged,
                 latest_versions: latest_versions.map_err(Some),
-                ntp_server,
+                ntp_servers,
                 output_format,
                 verbose,
                 warnings_as_errors,
@@ -233,6 +238,7 @@ impl Check {
                     Box::new(ContainerLocalTime::default()),
                     Box::new(ContainerEngineDns::default()),
                     Box::new(ContainerEngineIPv6::default()),
+                    Box::new(ContainerEngineNetworkSubnets::default()),
                     Box::new(IdentityCertificateExpiry::default()),
                     Box::new(CertificatesQuickstart::default()),
                     Box::new(ContainerEngineIsMoby::default()),
@@ -244,6 +250,7 @@ impl Check {
             ("Connectivity checks", {
                 let mut tests: Vec<Box<dyn Checker>> = Vec::new();
                 tests.push(Box::new(HostConnectDpsEndpoint::default()));
+                tests.push(Box::new(DpsRegistration::default()));
                 tests.extend(get_host_connect_iothub_tests());
                 tests.extend(get_host_container_iothub_tests());
                 tests
@@ -305,8 +312,28 @@ impl Check {
         Ok(())
     }
 
+    pub fn print_list_json() -> Result<(), Error> {
+        let checks = Check::checks();
+        let checks: Vec<_> = checks
+            .iter()
+            .flat_map(|(section_name, section_checks)| {
+                section_checks.iter().map(move |check| CheckListEntry {
+                    id: check.id(),
+                    description: check.description(),
+                    category: *section_name,
+                })
+            })
+            .collect();
+
+        serde_json::to_writer(std::io::stdout(), &checks).context(ErrorKind::WriteToStdout)?;
+        println!();
+
+        Ok(())
+    }
+
     fn execute_inner(&mut self) -> Result<(), Error> {
         let mut checks: BTreeMap<&str, CheckOutputSerializable> = Default::default();
+        let mut check_order: Vec<(&'static str, &'static str, &'static str)> = vec![];
         let mut check_data = Check::checks();
 
         let mut stdout = Stdout::new(self.output_format);
@@ -341,6 +368,8 @@ impl Check {
                     check.execute(self)
                 };
 
+                check_order.push((*section_name, check_id, check_name));
+
                 match check_result {
                     CheckResult::Ok => {
                         num_successful += 1;
@@ -555,18 +584,57 @@ impl Check {
             Ok(())
         };
 
-        if self.output_format == OutputFormat::Json {
-            let check_results = CheckResultsSerializable {
-                additional_info: &self.additional_info,
-                checks,
-            };
+        let reports = || {
+            check_order
+                .iter()
+                .map(|&(section, id, description)| CheckReport {
+                    section,
+                    id,
+                    description,
+                    result: &checks[id].result,
+                })
+                .collect::<Vec<_>>()
+        };
+
+        match self.output_format {
+            OutputFormat::Json => {
+                let check_results = CheckResultsSerializable {
+                    additional_info: &self.additional_info,
+                    checks: &checks,
+                };
 
-            if let Err(err) = serde_json::to_writer(std::io::stdout(), &check_results) {
-                eprintln!("Could not write JSON output: {}", err,);
-                return Err(ErrorKind::Diagnostics.into());
+                if let Err(err) = serde_json::to_writer(std::io::stdout(), &check_results) {
+                    eprintln!("Could not write JSON output: {}", err,);
+                    return Err(ErrorKind::Diagnostics.into());
+                }
+
+                println!();
             }
 
-            println!();
+            OutputFormat::Junit => {
+                if let Err(err) =
+                    report::write_junit(&mut std::io::stdout(), &reports(), self.warnings_as_errors)
+                {
+                    eprintln!("Could not write JUnit output: {}", err,);
+                    return Err(ErrorKind::Diagnostics.into());
+                }
+            }
+
+            OutputFormat::Sarif => {
+                if let Err(err) = report::write_sarif(
+                    &mut std::io::stdout(),
+                    &reports(),
+                    edgelet_core::version(),
+                    self.warnings_as_errors,
+                ) {
+                    eprintln!("Could not write SARIF output: {}", err,);
+                    return Err(ErrorKind::Diagnostics.into());
+                }
+
+                println!();
+            }
+
+            OutputFormat::Text => (),
         }
 
         result
@@ -601,7 +669,14 @@ fn write_lines<'a>(
 #[derive(Debug, serde_derive::Serialize)]
 struct CheckResultsSerializable<'a> {
     additional_info: &'a AdditionalInfo,
-    checks: BTreeMap<&'static str, CheckOutputSerializable>,
+    checks: &'a BTreeMap<&'static str, CheckOutputSerializable>,
+}
+
+#[derive(Debug, serde_derive::Serialize)]
+struct CheckListEntry {
+    id: &'static str,
+    description: &'static str,
+    category: &'static str,
 }
 
 #[derive(Debug, serde_derive::Serialize)]
@@ -644,11 +719,11 @@ mod tests {
                     "daemon.json".into(), // unused for this test
                     "mcr.microsoft.com/azureiotedge-diagnostics:1.0.0".to_owned(), // unused for this test
                     Default::default(),
-                    Some("1.0.0".to_owned()),      // unused for this test
-                    "iotedged".into(),             // unused for this test
-                    None,                          // unused for this test
-                    "pool.ntp.org:123".to_owned(), // unused for this test
-                    super::OutputFormat::Text,     // unused for this test
+                    Some("1.0.0".to_owned()), // unused for this test
+                    "iotedged".into(),        // unused for this test
+                    None,                     // unused for this test
+                    vec!["pool.ntp.org:123".to_owned()], // unused for this test
+                    super::OutputFormat::Text, // unused for this test
                     false,
                     false,
                 ))
@@ -715,11 +790,11 @@ mod tests {
                 "daemon.json".into(), // unused for this test
                 "mcr.microsoft.com/azureiotedge-diagnostics:1.0.0".to_owned(), // unused for this test
                 Default::default(),
-                Some("1.0.0".to_owned()),      // unused for this test
-                "iotedged".into(),             // unused for this test
-                None,                          // unused for this test
-                "pool.ntp.org:123".to_owned(), // unused for this test
-                super::OutputFormat::Text,     // unused for this test
+                Some("1.0.0".to_owned()),            // unused for this test
+                "iotedged".into(),                   // unused for this test
+                None,                                // unused for this test
+                vec!["pool.ntp.org:123".to_owned()], // unused for this test
+                super::OutputFormat::Text,           // unused for this test
                 false,
                 false,
             ))
@@ -765,7 +840,7 @@ mod tests {
                 Some("1.0.0".to_owned()), // unused for this test
                 "iotedged".into(),        // unused for this test
                 Some("something.something.com".to_owned()), // pretend user specified --iothub-hostname
-                "pool.ntp.org:123".to_owned(),              // unused for this test
+                vec!["pool.ntp.org:123".to_owned()],        // unused for this test
                 super::OutputFormat::Text,                  // unused for this test
                 false,
                 false,
@@ -801,11 +876,11 @@ mod tests {
                 "daemon.json".into(), // unused for this test
                 "mcr.microsoft.com/azureiotedge-diagnostics:1.0.0".to_owned(), // unused for this test
                 Default::default(),
-                Some("1.0.0".to_owned()),      // unused for this test
-                "iotedged".into(),             // unused for this test
-                None,                          // pretend user did not specify --iothub-hostname
-                "pool.ntp.org:123".to_owned(), // unused for this test
-                super::OutputFormat::Text,     // unused for this test
+                Some("1.0.0".to_owned()),            // unused for this test
+                "iotedged".into(),                   // unused for this test
+                None, // pretend user did not specify --iothub-hostname
+                vec!["pool.ntp.org:123".to_owned()], // unused for this test
+                super::OutputFormat::Text, // unused for this test
                 false,
                 false,
             ))
@@ -844,11 +919,11 @@ mod tests {
                 "daemon.json".into(), // unused for this test
                 "mcr.microsoft.com/azureiotedge-diagnostics:1.0.0".to_owned(), // unused for this test
                 Default::default(),
-                Some("1.0.0".to_owned()),      // unused for this test
-                "iotedged".into(),             // unused for this test
-                None,                          // unused for this test
-                "pool.ntp.org:123".to_owned(), // unused for this test
-                super::OutputFormat::Text,     // unused for this test
+                Some("1.0.0".to_owned()),            // unused for this test
+                "iotedged".into(),                   // unused for this test
+                None,                                // unused for this test
+                vec!["pool.ntp.org:123".to_owned()], // unused for this test
+                super::OutputFormat::Text,           // unused for this test
                 false,
                 false,
             ))
@@ -897,11 +972,11 @@ mod tests {
                 "daemon.json".into(), // unused for this test
                 "mcr.microsoft.com/azureiotedge-diagnostics:1.0.0".to_owned(), // unused for this test
                 Default::default(),
-                Some("1.0.0".to_owned()),      // unused for this test
-                "iotedged".into(),             // unused for this test
-                None,                          // unused for this test
-                "pool.ntp.org:123".to_owned(), // unused for this test
-                super::OutputFormat::Text,     // unused for this test
+                Some("1.0.0".to_owned()),            // unused for this test
+                "iotedged".into(),                   // unused for this test
+                None,                                // unused for this test
+                vec!["pool.ntp.org:123".to_owned()], // unused for this test
+                super::OutputFormat::Text,           // unused for this test
                 false,
                 false,
             ))
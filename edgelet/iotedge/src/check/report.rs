@@ -0,0 +1,521 @@
+// Copyright (c) Microsoft. All rights reserved.
+
+//! `JUnit` XML and SARIF renderings of the results of `iotedge check`, for CI systems and fleet tooling.
+
+use std::io::Write;
+
+use super::CheckResultSerializable;
+
+/// The outcome of a single check, along with the catalog information of the check.
+#[derive(Debug)]
+pub(super) struct CheckReport<'a> {
+    pub(super) section: &'static str,
+    pub(super) id: &'static str,
+    pub(super) description: &'static str,
+    pub(super) result: &'a CheckResultSerializable,
+}
+
+const SKIPPED_MESSAGE: &str = "skipped because of errors from previous checks";
+const IGNORED_MESSAGE: &str = "ignored because of --dont-run";
+
+/// Writes the reports as a `JUnit` XML document, with one `<testsuite>` per section and one `<testcase>` per check.
+///
+/// - `Ok` and `Warning` are passing test cases. Warnings are written to the test case's `<system-out>`.
+/// - `Ignored` and `Skipped` are skipped test cases.
+/// - `Error` is a `<failure>`, and `Fatal` is an `<error>`.
+///
+/// With `warnings_as_errors`, a `Warning` is a `<failure>` too, like `iotedge check --warnings-as-errors` prints it.
+pub(super) fn write_junit(
+    w: &mut (impl Write + ?Sized),
+    reports: &[CheckReport<'_>],
+    warnings_as_errors: bool,
+) -> std::io::Result<()> {
+    let mut sections: Vec<(&str, Vec<&CheckReport<'_>>)> = vec![];
+    for report in reports {
+        match sections.last_mut() {
+            Some((section, section_reports)) if *section == report.section => {
+                section_reports.push(report);
+            }
+            _ => sections.push((report.section, vec![report])),
+        }
+    }
+
+    let counts = Counts::new(reports.iter(), warnings_as_errors);
+
+    writeln!(w, r#"<?xml version="1.0" encoding="UTF-8"?>"#)?;
+    writeln!(
+        w,
+        r#"<testsuites name="iotedge check" tests="{}" failures="{}" errors="{}" skipped="{}">"#,
+        reports.len(),
+        counts.failures,
+        counts.errors,
+        counts.skipped,
+    )?;
+
+    for (section, section_reports) in sections {
+        let counts = Counts::new(section_reports.iter().copied(), warnings_as_errors);
+
+        writeln!(
+            w,
+            r#"  <testsuite name="{}" tests="{}" failures="{}" errors="{}" skipped="{}">"#,
+            xml_escape(section),
+            section_reports.len(),
+            counts.failures,
+            counts.errors,
+            counts.skipped,
+        )?;
+
+        for report in section_reports {
+            write!(
+                w,
+                r#"    <testcase name="{}" classname="{}""#,
+                xml_escape(report.id),
+                xml_escape(report.section),
+            )?;
+
+            match report.result {
+                CheckResultSerializable::Ok => writeln!(w, " />")?,
+
+                CheckResultSerializable::Warning { details } if !warnings_as_errors => {
+                    writeln!(w, ">")?;
+                    writeln!(
+                        w,
+                        "      <system-out>{}</system-out>",
+                        xml_escape(&format!("Warning: {}", cause_chain(details)))
+                    )?;
+                    writeln!(w, "    </testcase>")?;
+                }
+
+                CheckResultSerializable::Ignored => {
+                    writeln!(w, ">")?;
+                    writeln!(
+                        w,
+                        r#"      <skipped message="{}" />"#,
+                        xml_escape(IGNORED_MESSAGE)
+                    )?;
+                    writeln!(w, "    </testcase>")?;
+                }
+
+                CheckResultSerializable::Skipped => {
+                    writeln!(w, ">")?;
+                    writeln!(
+                        w,
+                        r#"      <skipped message="{}" />"#,
+                        xml_escape(SKIPPED_MESSAGE)
+                    )?;
+                    writeln!(w, "    </testcase>")?;
+                }
+
+                CheckResultSerializable::Warning { details }
+                | CheckResultSerializable::Error { details }
+                | CheckResultSerializable::Fatal { details } => {
+                    let element = if let CheckResultSerializable::Fatal { .. } = report.result {
+                        "error"
+                    } else {
+                        "failure"
+                    };
+
+                    writeln!(w, ">")?;
+                    writeln!(
+                        w,
+                        r#"      <{} message="{}">{}</{}>"#,
+                        element,
+                        xml_escape(details.first().map_or("", String::as_str)),
+                        xml_escape(&cause_chain(details)),
+                        element,
+                    )?;
+                    writeln!(w, "    </testcase>")?;
+                }
+            }
+        }
+
+        writeln!(w, "  </testsuite>")?;
+    }
+
+    writeln!(w, "</testsuites>")?;
+
+    Ok(())
+}
+
+/// Writes the reports as a SARIF 2.1.0 log, with one rule per check and one result per check that was run.
+///
+/// Checks that passed get a result too, with a `kind` of `pass`. Following the SARIF spec, only results with a `kind`
+/// of `fail` have a `level` other than `none`. With `warnings_as_errors`, warnings have a `level` of `error`.
+pub(super) fn write_sarif(
+    w: &mut (impl Write + ?Sized),
+    reports: &[CheckReport<'_>],
+    version: &str,
+    warnings_as_errors: bool,
+) -> serde_json::Result<()> {
+    let rules = reports
+        .iter()
+        .map(|report| SarifRule {
+            id: report.id,
+            short_description: SarifMessage {
+                text: report.description.to_owned(),
+            },
+            properties: SarifRuleProperties {
+                category: report.section,
+            },
+        })
+        .collect();
+
+    let results = reports
+        .iter()
+        .enumerate()
+        .map(|(rule_index, report)| {
+            let (kind, level, message, details) = match report.result {
+                CheckResultSerializable::Ok => ("pass", "none", "OK".to_owned(), None),
+                CheckResultSerializable::Warning { details } if !warnings_as_errors => {
+                    ("fail", "warning", cause_chain(details), Some(details))
+                }
+                CheckResultSerializable::Ignored => {
+                    ("notApplicable", "none", IGNORED_MESSAGE.to_owned(), None)
+                }
+                CheckResultSerializable::Skipped => {
+                    ("open", "none", SKIPPED_MESSAGE.to_owned(), None)
+                }
+                CheckResultSerializable::Warning { details }
+                | CheckResultSerializable::Error { details }
+                | CheckResultSerializable::Fatal { details } => {
+                    ("fail", "error", cause_chain(details), Some(details))
+                }
+            };
+
+            SarifResult {
+                rule_id: report.id,
+                rule_index,
+                kind,
+                level,
+                message: SarifMessage { text: message },
+                properties: details.map(|details| SarifResultProperties {
+                    details,
+                    fatal: if let CheckResultSerializable::Fatal { .. } = report.result {
+                        Some(true)
+                    } else {
+                        None
+                    },
+                }),
+            }
+        })
+        .collect();
+
+    let counts = Counts::new(reports.iter(), warnings_as_errors);
+
+    let log = SarifLog {
+        schema: "https://json.schemastore.org/sarif-2.1.0.json",
+        version: "2.1.0",
+        runs: vec![SarifRun {
+            tool: SarifTool {
+                driver: SarifDriver {
+                    name: "iotedge check",
+                    version,
+                    rules,
+                },
+            },
+            invocations: vec![SarifInvocation {
+                execution_successful: counts.failures + counts.errors == 0,
+            }],
+            results,
+        }],
+    };
+
+    serde_json::to_writer(w, &log)
+}
+
+struct Counts {
+    failures: usize,
+    errors: usize,
+    skipped: usize,
+}
+
+impl Counts {
+    fn new<'a, 'b: 'a>(
+        reports: impl Iterator<Item = &'a CheckReport<'b>>,
+        warnings_as_errors: bool,
+    ) -> Self {
+        let mut counts = Counts {
+            failures: 0,
+            errors: 0,
+            skipped: 0,
+        };
+
+        for report in reports {
+            match report.result {
+                CheckResultSerializable::Warning { .. } if warnings_as_errors => {
+                    counts.failures += 1;
+                }
+                CheckResultSerializable::Ok | CheckResultSerializable::Warning { .. } => (),
+                CheckResultSerializable::Ignored | CheckResultSerializable::Skipped => {
+                    counts.skipped += 1;
+                }
+                CheckResultSerializable::Error { .. } => counts.failures += 1,
+                CheckResultSerializable::Fatal { .. } => counts.errors += 1,
+            }
+        }
+
+        counts
+    }
+}
+
+/// Joins an error and its causes the same way `iotedge check --verbose` prints them
+fn cause_chain(details: &[String]) -> String {
+    details.join("\n    caused by: ")
+}
+
+fn xml_escape(s: &str) -> String {
+    let mut result = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '&' => result.push_str("&amp;"),
+            '<' => result.push_str("&lt;"),
+            '>' => result.push_str("&gt;"),
+            '"' => result.push_str("&quot;"),
+            '\'' => result.push_str("&apos;"),
+            '\n' | '\r' | '\t' => result.push(c),
+            // Other control characters aren't allowed in XML 1.0 documents, even escaped
+            c if c.is_control() => (),
+            c => result.push(c),
+        }
+    }
+    result
+}
+
+#[derive(Debug, serde_derive::Serialize)]
+struct SarifLog<'a> {
+    #[serde(rename = "$schema")]
+    schema: &'static str,
+    version: &'static str,
+    runs: Vec<SarifRun<'a>>,
+}
+
+#[derive(Debug, serde_derive::Serialize)]
+struct SarifRun<'a> {
+    tool: SarifTool<'a>,
+    invocations: Vec<SarifInvocation>,
+    results: Vec<SarifResult<'a>>,
+}
+
+#[derive(Debug, serde_derive::Serialize)]
+struct SarifTool<'a> {
+    driver: SarifDriver<'a>,
+}
+
+#[derive(Debug, serde_derive::Serialize)]
+struct SarifDriver<'a> {
+    name: &'static str,
+    version: &'a str,
+    rules: Vec<SarifRule>,
+}
+
+#[derive(Debug, serde_derive::Serialize)]
+#[serde(rename_all = "camelCase")]
+struct SarifRule {
+    id: &'static str,
+    short_description: SarifMessage,
+    properties: SarifRuleProperties,
+}
+
+#[derive(Debug, serde_derive::Serialize)]
+struct SarifRuleProperties {
+    category: &'static str,
+}
+
+#[derive(Debug, serde_derive::Serialize)]
+#[serde(rename_all = "camelCase")]
+struct SarifInvocation {
+    execution_successful: bool,
+}
+
+#[derive(Debug, serde_derive::Serialize)]
+#[serde(rename_all = "camelCase")]
+struct SarifResult<'a> {
+    rule_id: &'static str,
+    rule_index: usize,
+    kind: &'static str,
+    level: &'static str,
+    message: SarifMessage,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    properties: Option<SarifResultProperties<'a>>,
+}
+
+#[derive(Debug, serde_derive::Serialize)]
+struct SarifResultProperties<'a> {
+    details: &'a [String],
+    #[serde(skip_serializing_if = "Option::is_none")]
+    fatal: Option<bool>,
+}
+
+#[derive(Debug, serde_derive::Serialize)]
+struct SarifMessage {
+    text: String,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn results() -> Vec<(&'static str, &'static str, CheckResultSerializable)> {
+        vec![
+            (
+                "Configuration checks",
+                "config-yaml-well-formed",
+                CheckResultSerializable::Ok,
+            ),
+            (
+                "Configuration checks",
+                "container-engine-logrotate",
+                CheckResultSerializable::Warning {
+                    details: vec![
+                        "Container engine is not configured to rotate module logs".to_owned()
+                    ],
+                },
+            ),
+            (
+                "Configuration checks",
+                "windows-host-version",
+                CheckResultSerializable::Ignored,
+            ),
+            (
+                "Connectivity checks",
+                "host-connect-iothub-amqp",
+                CheckResultSerializable::Error {
+                    details: vec![
+                        "Could not connect to <hub> & friends".to_owned(),
+                        "connection refused".to_owned(),
+                    ],
+                },
+            ),
+            (
+                "Connectivity checks",
+                "host-connect-iothub-https",
+                CheckResultSerializable::Fatal {
+                    details: vec!["DNS failure".to_owned()],
+                },
+            ),
+            (
+                "Connectivity checks",
+                "container-connect-iothub-amqp",
+                CheckResultSerializable::Skipped,
+            ),
+        ]
+    }
+
+    fn reports<'a>(
+        results: &'a [(&'static str, &'static str, CheckResultSerializable)],
+    ) -> Vec<CheckReport<'a>> {
+        results
+            .iter()
+            .map(|(section, id, result)| CheckReport {
+                section,
+                id,
+                description: "description",
+                result,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn junit() {
+        let results = results();
+        let mut output = vec![];
+        write_junit(&mut output, &reports(&results), false).unwrap();
+        let output = String::from_utf8(output).unwrap();
+
+        assert!(output.contains(
+            r#"<testsuites name="iotedge check" tests="6" failures="1" errors="1" skipped="2">"#
+        ));
+        assert!(output.contains(
+            r#"<testsuite name="Configuration checks" tests="3" failures="0" errors="0" skipped="1">"#
+        ));
+        assert!(output.contains(
+            r#"<testsuite name="Connectivity checks" tests="3" failures="1" errors="1" skipped="1">"#
+        ));
+        assert!(output.contains(
+            r#"<testcase name="config-yaml-well-formed" classname="Configuration checks" />"#
+        ));
+        assert!(output.contains(
+            "<system-out>Warning: Container engine is not configured to rotate module logs</system-out>"
+        ));
+        assert!(output.contains(
+            "<failure message=\"Could not connect to &lt;hub&gt; &amp; friends\">Could not connect to &lt;hub&gt; &amp; friends\n    caused by: connection refused</failure>"
+        ));
+        assert!(output.contains(r#"<error message="DNS failure">DNS failure</error>"#));
+        assert!(output.contains(&format!(r#"<skipped message="{}" />"#, SKIPPED_MESSAGE)));
+        assert!(output.ends_with("</testsuites>\n"));
+    }
+
+    #[test]
+    fn sarif() {
+        let results = results();
+        let mut output = vec![];
+        write_sarif(&mut output, &reports(&results), "1.0.9", false).unwrap();
+        let output: serde_json::Value = serde_json::from_slice(&output).unwrap();
+
+        assert_eq!("2.1.0", output["version"]);
+
+        let run = &output["runs"][0];
+        assert_eq!("1.0.9", run["tool"]["driver"]["version"]);
+        assert_eq!(6, run["tool"]["driver"]["rules"].as_array().unwrap().len());
+        assert_eq!(
+            "Connectivity checks",
+            run["tool"]["driver"]["rules"][3]["properties"]["category"]
+        );
+        assert_eq!(false, run["invocations"][0]["executionSuccessful"]);
+
+        let levels: Vec<_> = run["results"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|result| {
+                (
+                    result["kind"].as_str().unwrap(),
+                    result["level"].as_str().unwrap(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            vec![
+                ("pass", "none"),
+                ("fail", "warning"),
+                ("notApplicable", "none"),
+                ("fail", "error"),
+                ("fail", "error"),
+                ("open", "none"),
+            ],
+            levels
+        );
+
+        let failed = &run["results"][3];
+        assert_eq!("host-connect-iothub-amqp", failed["ruleId"]);
+        assert_eq!(3, failed["ruleIndex"]);
+        assert_eq!("connection refused", failed["properties"]["details"][1]);
+        assert_eq!(true, run["results"][4]["properties"]["fatal"]);
+    }
+
+    #[test]
+    fn warnings_as_errors() {
+        let results = results();
+
+        let mut output = vec![];
+        write_junit(&mut output, &reports(&results), true).unwrap();
+        let output = String::from_utf8(output).unwrap();
+
+        assert!(output.contains(
+            r#"<testsuites name="iotedge check" tests="6" failures="2" errors="1" skipped="2">"#
+        ));
+        assert!(output.contains(
+            r#"<failure message="Container engine is not configured to rotate module logs">"#
+        ));
+        assert!(!output.contains("<system-out>"));
+
+        let mut output = vec![];
+        write_sarif(&mut output, &reports(&results), "1.0.9", true).unwrap();
+        let output: serde_json::Value = serde_json::from_slice(&output).unwrap();
+
+        let warning = &output["runs"][0]["results"][1];
+        assert_eq!("container-engine-logrotate", warning["ruleId"]);
+        assert_eq!("fail", warning["kind"]);
+        assert_eq!("error", warning["level"]);
+    }
+}
@@ -10,7 +10,8 @@ pub(super) enum Stdout {
         error_color_spec: termcolor::ColorSpec,
     },
 
-    DefaultJson,
+    /// Used by the structured output formats, which are written in one go after all checks have run.
+    Silent,
 
     DefaultText,
 }
@@ -70,7 +71,9 @@ impl Stdout {
             }
         } else {
             match output_format {
-                super::OutputFormat::Json => Stdout::DefaultJson,
+                super::OutputFormat::Json
+                | super::OutputFormat::Junit
+                | super::OutputFormat::Sarif => Stdout::Silent,
                 super::OutputFormat::Text => Stdout::DefaultText,
             }
         }
@@ -86,7 +89,7 @@ impl Stdout {
                 success_color_spec,
                 ..
             } => write_colored(stdout, success_color_spec, f),
-            Stdout::DefaultJson => Ok(()),
+            Stdout::Silent => Ok(()),
             Stdout::DefaultText => f(&mut std::io::stdout()),
         };
         result.expect("could not write to stdout");
@@ -102,7 +105,7 @@ impl Stdout {
                 warning_color_spec,
                 ..
             } => write_colored(stdout, warning_color_spec, f),
-            Stdout::DefaultJson => Ok(()),
+            Stdout::Silent => Ok(()),
             Stdout::DefaultText => f(&mut std::io::stdout()),
         };
         result.expect("could not write to stdout");
@@ -118,7 +121,7 @@ impl Stdout {
                 error_color_spec,
                 ..
             } => write_colored(stdout, error_color_spec, f),
-            Stdout::DefaultJson => Ok(()),
+            Stdout::Silent => Ok(()),
             Stdout::DefaultText => f(&mut std::io::stdout()),
         };
         result.expect("could not write to stdout");
@@ -0,0 +1,117 @@
+// Copyright (c) Microsoft. All rights reserved.
+
+use std::ffi::OsString;
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::process::Command as ShellCommand;
+
+use chrono::Local;
+use failure::ResultExt;
+use futures::future::{self, FutureResult};
+
+use crate::config::validate::validate;
+use crate::error::{Error, ErrorKind};
+use crate::Command;
+
+pub struct ConfigApply {
+    config_file: PathBuf,
+    new_config_file: Option<PathBuf>,
+}
+
+impl ConfigApply {
+    pub fn new(config_file: PathBuf, new_config_file: Option<PathBuf>) -> Self {
+        ConfigApply {
+            config_file,
+            new_config_file,
+        }
+    }
+}
+
+impl Command for ConfigApply {
+    type Future = FutureResult<(), Error>;
+
+    fn execute(self) -> Self::Future {
+        let ConfigApply {
+            config_file,
+            new_config_file,
+        } = self;
+
+        let result = (|| -> Result<(), Error> {
+            validate(new_config_file.as_ref().unwrap_or(&config_file))?;
+
+            let backup_file = backup_file_name(&config_file);
+            fs::copy(&config_file, &backup_file).context(ErrorKind::BackupConfig)?;
+            println!(
+                "Backed up {} to {}",
+                config_file.display(),
+                backup_file.display()
+            );
+
+            if let Some(new_config_file) = &new_config_file {
+                fs::copy(new_config_file, &config_file).context(ErrorKind::WriteConfig)?;
+                println!(
+                    "Copied {} to {}",
+                    new_config_file.display(),
+                    config_file.display()
+                );
+            }
+
+            restart_service()?;
+            println!("Restarted the IoT Edge service");
+
+            Ok(())
+        })();
+
+        future::result(result)
+    }
+}
+
+/// Returns a path like `/etc/iotedge/config.yaml.20200707T120000.bak` next to `config_file`
+fn backup_file_name(config_file: &Path) -> PathBuf {
+    let mut file_name = config_file
+        .file_name()
+        .map_or_else(|| OsString::from("config.yaml"), ToOwned::to_owned);
+    file_name.push(format!(".{}.bak", Local::now().format("%Y%m%dT%H%M%S")));
+    config_file.with_file_name(file_name)
+}
+
+fn restart_service() -> Result<(), Error> {
+    #[cfg(unix)]
+    let output = ShellCommand::new("systemctl")
+        .args(&["restart", "iotedge"])
+        .output();
+
+    #[cfg(windows)]
+    let output = ShellCommand::new("powershell.exe")
+        .arg("-NoProfile")
+        .arg("-Command")
+        .arg("Restart-Service iotedge")
+        .output();
+
+    let output = output.context(ErrorKind::RestartService)?;
+    if !output.status.success() {
+        let _ = io::stderr().write_all(&output.stderr);
+        return Err(Error::from(
+            failure::err_msg(format!("the service manager exited with {}", output.status))
+                .context(ErrorKind::RestartService),
+        ));
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn backup_file_is_next_to_config_file() {
+        let backup_file = backup_file_name(Path::new("/etc/iotedge/config.yaml"));
+        assert_eq!(Some(Path::new("/etc/iotedge")), backup_file.parent());
+
+        let file_name = backup_file.file_name().unwrap().to_str().unwrap();
+        assert!(file_name.starts_with("config.yaml."));
+        assert!(file_name.ends_with(".bak"));
+    }
+}
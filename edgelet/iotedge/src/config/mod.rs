@@ -0,0 +1,12 @@
+// Copyright (c) Microsoft. All rights reserved.
+
+mod apply;
+mod set;
+mod show;
+mod validate;
+mod yaml;
+
+pub use self::apply::ConfigApply;
+pub use self::set::ConfigSet;
+pub use self::show::ConfigShow;
+pub use self::validate::ConfigValidate;
@@ -0,0 +1,381 @@
+// Copyright (c) Microsoft. All rights reserved.
+
+use std::fs;
+use std::path::PathBuf;
+
+use failure::ResultExt;
+use futures::future::{self, FutureResult};
+use yaml_rust::scanner::TScalarStyle;
+
+use crate::config::yaml::{self, Node, NodeKind};
+use crate::error::{Error, ErrorKind, SetConfigReason};
+use crate::Command;
+
+/// The indentation used for keys that are added under a mapping that has no other keys
+const INDENT: usize = 2;
+
+pub struct ConfigSet {
+    config_file: PathBuf,
+    path: String,
+    value: String,
+}
+
+impl ConfigSet {
+    pub fn new(config_file: PathBuf, path: String, value: String) -> Self {
+        ConfigSet {
+            config_file,
+            path,
+            value,
+        }
+    }
+}
+
+impl Command for ConfigSet {
+    type Future = FutureResult<(), Error>;
+
+    fn execute(self) -> Self::Future {
+        let ConfigSet {
+            config_file,
+            path,
+            value,
+        } = self;
+
+        let result = fs::read_to_string(&config_file)
+            .context(ErrorKind::ReadConfig)
+            .map_err(Error::from)
+            .and_then(|contents| {
+                let contents = set(&contents, &path, &value)
+                    .map_err(|reason| ErrorKind::SetConfig(path.clone(), reason))?;
+                fs::write(&config_file, contents).context(ErrorKind::WriteConfig)?;
+                println!("Set {} in {}", path, config_file.display());
+                Ok(())
+            });
+
+        future::result(result)
+    }
+}
+
+/// Sets the value at the dotted `path` in the YAML document `contents` to the YAML scalar `value`, and returns the
+/// new document.
+///
+/// Only the text of the old value is replaced, so comments and formatting elsewhere in the document are preserved.
+/// If the path doesn't exist, it's added under its closest existing ancestor, which must be a block mapping.
+pub(crate) fn set(contents: &str, path: &str, value: &str) -> Result<String, SetConfigReason> {
+    let nodes =
+        yaml::index(contents).map_err(|err| SetConfigReason::InvalidYaml(err.to_string()))?;
+
+    let result = if let Some(node) = nodes.get(path) {
+        replace_scalar(contents, node, value)?
+    } else {
+        let segments: Vec<&str> = path.split('.').collect();
+        let (existing, parent_path, parent) = (0..segments.len())
+            .rev()
+            .find_map(|i| {
+                let parent_path = segments[..i].join(".");
+                nodes
+                    .get(&parent_path)
+                    .map(|parent| (i, parent_path, parent))
+            })
+            .ok_or_else(|| SetConfigReason::NotAMapping(String::new()))?;
+
+        insert(
+            contents,
+            &nodes,
+            &parent_path,
+            parent,
+            &segments[existing..],
+            value,
+        )?
+    };
+
+    // Make sure the value didn't change the structure of the document, like a value of `a: b` would
+    match yaml::index(&result)
+        .ok()
+        .and_then(|nodes| nodes.get(path).cloned())
+    {
+        Some(Node {
+            kind: NodeKind::Scalar { empty: false, .. },
+            ..
+        }) => Ok(result),
+        _ => Err(SetConfigReason::InvalidValue),
+    }
+}
+
+fn replace_scalar(contents: &str, node: &Node, value: &str) -> Result<String, SetConfigReason> {
+    let (style, empty) = match &node.kind {
+        NodeKind::Scalar { style, empty, .. } => (*style, *empty),
+        _ => return Err(SetConfigReason::NotAScalar),
+    };
+
+    let key_line = node
+        .key_mark
+        .map_or_else(|| node.value_mark.line(), |key_mark| key_mark.line());
+
+    let (start, end) = if empty {
+        // There's no value text to replace, so write the value after the key's colon
+        let key_mark = node.key_mark.ok_or(SetConfigReason::NotAScalar)?;
+        let key_start = yaml::byte_offset(contents, key_mark.index());
+        let line_end = line_end(contents, key_start);
+        let colon = contents[key_start..line_end]
+            .find(':')
+            .map(|i| key_start + i + 1)
+            .ok_or(SetConfigReason::NotAScalar)?;
+        (colon, comment_start(contents, colon, line_end))
+    } else {
+        if node.value_mark.line() != key_line {
+            return Err(SetConfigReason::MultiLineScalar);
+        }
+
+        let start = yaml::byte_offset(contents, node.value_mark.index());
+        let line_end = line_end(contents, start);
+        let end = match style {
+            TScalarStyle::Plain => comment_start(contents, start, line_end),
+            TScalarStyle::SingleQuoted => closing_quote(&contents[start..line_end], '\'')
+                .map(|i| start + i)
+                .ok_or(SetConfigReason::MultiLineScalar)?,
+            TScalarStyle::DoubleQuoted => closing_quote(&contents[start..line_end], '"')
+                .map(|i| start + i)
+                .ok_or(SetConfigReason::MultiLineScalar)?,
+            _ => return Err(SetConfigReason::MultiLineScalar),
+        };
+
+        // A plain scalar may continue on the next line
+        let next_line = &contents[line_end..];
+        if style == TScalarStyle::Plain
+            && node.end_line != key_line
+            && !next_line.trim_start().is_empty()
+        {
+            return Err(SetConfigReason::MultiLineScalar);
+        }
+
+        (start, end)
+    };
+
+    let mut result = String::with_capacity(contents.len() + value.len());
+    result.push_str(&contents[..start]);
+    if empty {
+        result.push(' ');
+    }
+    result.push_str(value);
+    if empty && end != start && !contents[start..end].trim().is_empty() {
+        // Keep the space before an inline comment
+        result.push(' ');
+    }
+    result.push_str(&contents[end..]);
+    Ok(result)
+}
+
+fn insert(
+    contents: &str,
+    nodes: &std::collections::BTreeMap<String, Node>,
+    parent_path: &str,
+    parent: &Node,
+    missing: &[&str],
+    value: &str,
+) -> Result<String, SetConfigReason> {
+    let not_a_mapping = || SetConfigReason::NotAMapping(parent_path.to_owned());
+
+    let (indent, after_line) = match &parent.kind {
+        NodeKind::Mapping => {
+            if contents[yaml::byte_offset(contents, parent.value_mark.index())..].starts_with('{') {
+                // Flow mappings like `env: {}` would have to be rewritten as block mappings
+                return Err(not_a_mapping());
+            }
+
+            // Line up with an existing key of the mapping
+            let prefix = yaml::join(parent_path, "");
+            let indent = nodes
+                .iter()
+                .filter(|(path, _)| {
+                    path.len() > prefix.len()
+                        && path.starts_with(&prefix)
+                        && !path[prefix.len()..].contains('.')
+                })
+                .find_map(|(_, node)| node.key_mark)
+                .map_or(0, |key_mark| key_mark.col());
+            (indent, parent.end_line)
+        }
+
+        NodeKind::Scalar { empty: true, .. } => {
+            let key_mark = parent.key_mark.ok_or_else(not_a_mapping)?;
+            (key_mark.col() + INDENT, parent.end_line)
+        }
+
+        _ => return Err(not_a_mapping()),
+    };
+
+    let mut lines = String::new();
+    for (depth, segment) in missing.iter().enumerate() {
+        lines.push_str(&" ".repeat(indent + depth * INDENT));
+        lines.push_str(segment);
+        lines.push(':');
+        if depth == missing.len() - 1 {
+            lines.push(' ');
+            lines.push_str(value);
+        }
+        lines.push('\n');
+    }
+
+    // Lines are 1-based, so the new lines go at the start of line `after_line + 1`
+    let offset = contents
+        .match_indices('\n')
+        .nth(after_line - 1)
+        .map(|(i, _)| i + 1);
+
+    let mut result = String::with_capacity(contents.len() + lines.len() + 1);
+    if let Some(offset) = offset {
+        result.push_str(&contents[..offset]);
+        result.push_str(&lines);
+        result.push_str(&contents[offset..]);
+    } else {
+        result.push_str(contents);
+        if !contents.is_empty() && !contents.ends_with('\n') {
+            result.push('\n');
+        }
+        result.push_str(&lines);
+    }
+    Ok(result)
+}
+
+/// Returns the byte offset of the end of the line containing `offset`, excluding the line break
+fn line_end(contents: &str, offset: usize) -> usize {
+    contents[offset..]
+        .find(|c| c == '\r' || c == '\n')
+        .map_or_else(|| contents.len(), |i| offset + i)
+}
+
+/// Returns the byte offset where the text between `start` and `end` ends, excluding an inline comment and trailing
+/// whitespace
+fn comment_start(contents: &str, start: usize, end: usize) -> usize {
+    let text = &contents[start..end];
+    let text = text
+        .match_indices(" #")
+        .next()
+        .map_or(text, |(i, _)| &text[..i]);
+    start + text.trim_end().len()
+}
+
+/// Returns the byte offset just after the quote that closes the quoted scalar at the start of `text`
+fn closing_quote(text: &str, quote: char) -> Option<usize> {
+    let mut chars = text.char_indices().skip(1).peekable();
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '\\' if quote == '"' => {
+                let _ = chars.next();
+            }
+            '\'' if quote == '\'' && chars.peek().map(|&(_, c)| c) == Some('\'') => {
+                let _ = chars.next();
+            }
+            c if c == quote => return Some(i + c.len_utf8()),
+            _ => (),
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CONFIG: &str = r#"# Provisioning mode
+provisioning:
+  source: "manual"
+  device_connection_string: "<ADD DEVICE CONNECTION STRING HERE>"
+
+# dynamic_reprovisioning: false
+
+agent:
+  name: edgeAgent # the agent
+  env: {}
+  config:
+    image: 'mcr.microsoft.com/azureiotedge-agent:1.0'
+    auth:
+
+hostname: "my-device"
+"#;
+
+    #[test]
+    fn replaces_double_quoted() {
+        let result = set(
+            CONFIG,
+            "provisioning.device_connection_string",
+            "\"HostName=a;DeviceId=b\"",
+        )
+        .unwrap();
+        assert_eq!(
+            CONFIG.replace(
+                "\"<ADD DEVICE CONNECTION STRING HERE>\"",
+                "\"HostName=a;DeviceId=b\""
+            ),
+            result
+        );
+    }
+
+    #[test]
+    fn replaces_single_quoted() {
+        let result = set(CONFIG, "agent.config.image", "'image:1.1'").unwrap();
+        assert_eq!(
+            CONFIG.replace("'mcr.microsoft.com/azureiotedge-agent:1.0'", "'image:1.1'"),
+            result
+        );
+    }
+
+    #[test]
+    fn replaces_plain_and_keeps_comment() {
+        let result = set(CONFIG, "agent.name", "myAgent").unwrap();
+        assert_eq!(
+            CONFIG.replace("name: edgeAgent # the agent", "name: myAgent # the agent"),
+            result
+        );
+    }
+
+    #[test]
+    fn fills_empty_value() {
+        let result = set(CONFIG, "agent.config.auth", "\"\"").unwrap();
+        assert_eq!(CONFIG.replace("    auth:\n", "    auth: \"\"\n"), result);
+    }
+
+    #[test]
+    fn adds_key_to_mapping() {
+        let result = set(CONFIG, "provisioning.dynamic_reprovisioning", "true").unwrap();
+        assert_eq!(
+            CONFIG.replace("HERE>\"\n", "HERE>\"\n  dynamic_reprovisioning: true\n"),
+            result
+        );
+    }
+
+    #[test]
+    fn adds_nested_keys() {
+        let result = set(CONFIG, "agent.config.auth.username", "user").unwrap();
+        assert_eq!(
+            CONFIG.replace("    auth:\n", "    auth:\n      username: user\n"),
+            result
+        );
+
+        let result = set(CONFIG, "watchdog.max_retries", "3").unwrap();
+        assert_eq!(format!("{}watchdog:\n  max_retries: 3\n", CONFIG), result);
+    }
+
+    #[test]
+    fn rejects_non_scalars() {
+        match set(CONFIG, "agent.config", "a").unwrap_err() {
+            SetConfigReason::NotAScalar => (),
+            reason => panic!("expected NotAScalar but got {:?}", reason),
+        }
+
+        match set(CONFIG, "agent.env.FOO", "a").unwrap_err() {
+            SetConfigReason::NotAMapping(path) => assert_eq!("agent.env", path),
+            reason => panic!("expected NotAMapping but got {:?}", reason),
+        }
+
+        match set(CONFIG, "hostname.foo", "a").unwrap_err() {
+            SetConfigReason::NotAMapping(path) => assert_eq!("hostname", path),
+            reason => panic!("expected NotAMapping but got {:?}", reason),
+        }
+
+        match set(CONFIG, "hostname", "a: b").unwrap_err() {
+            SetConfigReason::InvalidValue => (),
+            reason => panic!("expected InvalidValue but got {:?}", reason),
+        }
+    }
+}
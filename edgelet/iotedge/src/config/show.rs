@@ -0,0 +1,159 @@
+// Copyright (c) Microsoft. All rights reserved.
+
+use std::path::PathBuf;
+
+use failure::ResultExt;
+use futures::future::{self, FutureResult};
+use serde_json::Value;
+
+use edgelet_core::PKCS11_URI_SCHEME;
+use edgelet_docker::Settings;
+
+use crate::error::{Error, ErrorKind};
+use crate::Command;
+
+/// Keys whose values are credentials, and so are never printed
+const SECRET_KEYS: &[&str] = &[
+    "device_connection_string",
+    "identitytoken",
+    "password",
+    "registrytoken",
+    "symmetric_key",
+];
+
+/// Keys whose values can be `pkcs11:` URIs, which can hold the token PIN as `pin-value`
+const PRIVATE_KEY_KEYS: &[&str] = &["device_ca_pk", "identity_pk"];
+
+const MASK: &str = "***";
+
+pub struct ConfigShow {
+    config_file: PathBuf,
+}
+
+impl ConfigShow {
+    pub fn new(config_file: PathBuf) -> Self {
+        ConfigShow { config_file }
+    }
+}
+
+impl Command for ConfigShow {
+    type Future = FutureResult<(), Error>;
+
+    fn execute(self) -> Self::Future {
+        let result = Settings::new(&self.config_file)
+            .context(ErrorKind::InvalidConfig)
+            .map_err(Error::from)
+            .and_then(|settings| {
+                let mut settings =
+                    serde_json::to_value(&settings).context(ErrorKind::ReadConfig)?;
+                mask_secrets(&mut settings);
+                let settings = serde_yaml::to_string(&settings).context(ErrorKind::ReadConfig)?;
+                println!("{}", settings);
+                Ok(())
+            });
+
+        future::result(result)
+    }
+}
+
+fn mask_secrets(value: &mut Value) {
+    match value {
+        Value::Object(map) => {
+            for (key, value) in map {
+                if SECRET_KEYS.contains(&key.as_str()) {
+                    if !value.is_null() {
+                        *value = Value::String(MASK.to_owned());
+                    }
+                } else if PRIVATE_KEY_KEYS.contains(&key.as_str()) {
+                    if let Value::String(uri) = value {
+                        *uri = mask_pin_value(uri);
+                    }
+                } else {
+                    mask_secrets(value);
+                }
+            }
+        }
+        Value::Array(values) => {
+            for value in values {
+                mask_secrets(value);
+            }
+        }
+        Value::Null | Value::Bool(_) | Value::Number(_) | Value::String(_) => (),
+    }
+}
+
+fn mask_pin_value(uri: &str) -> String {
+    match uri.find('?') {
+        Some(index) if uri.starts_with(&format!("{}:", PKCS11_URI_SCHEME)) => {
+            let query: Vec<_> = uri[index + 1..]
+                .split('&')
+                .map(|attribute| {
+                    if attribute.starts_with("pin-value=") {
+                        format!("pin-value={}", MASK)
+                    } else {
+                        attribute.to_owned()
+                    }
+                })
+                .collect();
+            format!("{}?{}", &uri[..index], query.join("&"))
+        }
+        _ => uri.to_owned(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn masks_secrets() {
+        let mut settings = json!({
+            "provisioning": {
+                "source": "manual",
+                "device_connection_string": "HostName=hub;DeviceId=device;SharedAccessKey=key",
+            },
+            "agent": {
+                "config": {
+                    "auth": {
+                        "username": "user",
+                        "password": "pass",
+                        "identitytoken": null,
+                    },
+                },
+            },
+            "listen": [{ "symmetric_key": "key" }],
+            "certificates": {
+                "device_ca_cert": "file:///var/secrets/device-ca.pem",
+                "device_ca_pk": "pkcs11:object=device-ca?pin-value=1234&module-path=/usr/lib/p11.so",
+            },
+        });
+
+        mask_secrets(&mut settings);
+
+        assert_eq!(
+            json!({
+                "provisioning": {
+                    "source": "manual",
+                    "device_connection_string": "***",
+                },
+                "agent": {
+                    "config": {
+                        "auth": {
+                            "username": "user",
+                            "password": "***",
+                            "identitytoken": null,
+                        },
+                    },
+                },
+                "listen": [{ "symmetric_key": "***" }],
+                "certificates": {
+                    "device_ca_cert": "file:///var/secrets/device-ca.pem",
+                    "device_ca_pk": "pkcs11:object=device-ca?pin-value=***&module-path=/usr/lib/p11.so",
+                },
+            }),
+            settings
+        );
+    }
+}
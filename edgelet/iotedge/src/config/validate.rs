@@ -0,0 +1,172 @@
+// Copyright (c) Microsoft. All rights reserved.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use failure::Fail;
+use futures::future::{self, FutureResult};
+use regex::Regex;
+
+use edgelet_docker::Settings;
+
+use crate::config::yaml;
+use crate::error::{Error, ErrorKind};
+use crate::Command;
+
+pub struct ConfigValidate {
+    config_file: PathBuf,
+}
+
+impl ConfigValidate {
+    pub fn new(config_file: PathBuf) -> Self {
+        ConfigValidate { config_file }
+    }
+}
+
+impl Command for ConfigValidate {
+    type Future = FutureResult<(), Error>;
+
+    fn execute(self) -> Self::Future {
+        let result = validate(&self.config_file).map(|()| {
+            println!("{} is valid", self.config_file.display());
+        });
+        future::result(result)
+    }
+}
+
+/// Checks that `config_file` can be loaded by iotedged. Each problem is printed to stderr as
+/// `file:line:column: message`, so that editors can jump to it.
+pub(crate) fn validate(config_file: &Path) -> Result<(), Error> {
+    let contents = match fs::read_to_string(config_file) {
+        Ok(contents) => contents,
+        Err(err) => {
+            if err.kind() == std::io::ErrorKind::PermissionDenied {
+                eprintln!(
+                    "Could not open file {}. You might need to run this command as {}.",
+                    config_file.display(),
+                    if cfg!(windows) {
+                        "Administrator"
+                    } else {
+                        "root"
+                    },
+                );
+            }
+            return Err(Error::from(err.context(ErrorKind::ReadConfig)));
+        }
+    };
+
+    // The config crate doesn't report positions for syntax errors, so parse the file ourselves first.
+    let nodes = match yaml::index(&contents) {
+        Ok(nodes) => nodes,
+        Err(err) => {
+            let marker = err.marker();
+            eprintln!(
+                "{}:{}:{}: {}",
+                config_file.display(),
+                marker.line(),
+                marker.col() + 1,
+                err,
+            );
+            return Err(Error::from(ErrorKind::InvalidConfig));
+        }
+    };
+
+    if let Err(err) = Settings::new(config_file) {
+        let fail: &dyn Fail = &err;
+        let message = fail
+            .iter_causes()
+            .map(ToString::to_string)
+            .collect::<Vec<_>>()
+            .join(": ");
+        let message = if message.is_empty() {
+            err.to_string()
+        } else {
+            message
+        };
+
+        match locate(&nodes, &message) {
+            Some((line, col)) => {
+                eprintln!("{}:{}:{}: {}", config_file.display(), line, col, message)
+            }
+            None => eprintln!("{}: {}", config_file.display(), message),
+        }
+        return Err(Error::from(ErrorKind::InvalidConfig));
+    }
+
+    Ok(())
+}
+
+/// Finds the 1-based line and column of the key that a settings error refers to.
+///
+/// Errors from the config crate name the key they're about, like "missing field `source` for key `provisioning`".
+/// If that key isn't in the file, because it was expected but missing, the position of its closest ancestor is used.
+fn locate(
+    nodes: &std::collections::BTreeMap<String, yaml::Node>,
+    message: &str,
+) -> Option<(usize, usize)> {
+    lazy_static::lazy_static! {
+        static ref KEY_REGEX: Regex =
+            Regex::new(r"for key `([^`]*)`").expect("This hard-coded regex is expected to be valid.");
+    }
+
+    let key = KEY_REGEX
+        .captures_iter(message)
+        .last()
+        .and_then(|captures| captures.get(1))?
+        .as_str();
+
+    let mut path = key;
+    loop {
+        if let Some(mark) = nodes.get(path).and_then(|node| node.key_mark) {
+            return Some((mark.line(), mark.col() + 1));
+        }
+
+        match path.rfind(|c| c == '.' || c == '[') {
+            Some(i) => path = &path[..i],
+            None => return None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CONFIG: &str = r#"provisioning:
+  source: "manual"
+  device_connection_string: 5
+
+agent:
+  name: "edgeAgent"
+  config:
+    image: "mcr.microsoft.com/azureiotedge-agent:1.0"
+"#;
+
+    #[test]
+    fn locates_key() {
+        let nodes = yaml::index(CONFIG).unwrap();
+
+        assert_eq!(
+            Some((3, 3)),
+            locate(
+                &nodes,
+                "invalid type: integer `5`, expected a string for key `provisioning.device_connection_string`"
+            )
+        );
+    }
+
+    #[test]
+    fn locates_closest_ancestor() {
+        let nodes = yaml::index(CONFIG).unwrap();
+
+        assert_eq!(
+            Some((7, 3)),
+            locate(
+                &nodes,
+                "missing field `type` for key `agent.config.auth.type`"
+            )
+        );
+        assert_eq!(None, locate(&nodes, "missing field `hostname` for key ``"));
+        assert_eq!(None, locate(&nodes, "Could not load settings"));
+    }
+}
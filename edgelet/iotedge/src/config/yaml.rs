@@ -0,0 +1,280 @@
+// Copyright (c) Microsoft. All rights reserved.
+
+//! Locates the nodes of a YAML document in its source text, so that errors can be reported with their position and
+//! values can be edited in place without disturbing comments or formatting.
+
+use std::collections::BTreeMap;
+
+use yaml_rust::parser::{Event, MarkedEventReceiver, Parser};
+use yaml_rust::scanner::{Marker, ScanError, TScalarStyle};
+
+#[derive(Clone, Debug)]
+pub(crate) struct Node {
+    /// The position of the node's key, if the node is the value of a mapping entry
+    pub(crate) key_mark: Option<Marker>,
+
+    /// The position of the node's value. For empty scalars, this is the position of the token after the value.
+    pub(crate) value_mark: Marker,
+
+    /// The last line that contains the node or any of its descendants
+    pub(crate) end_line: usize,
+
+    pub(crate) kind: NodeKind,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) enum NodeKind {
+    Scalar {
+        value: String,
+        style: TScalarStyle,
+        empty: bool,
+    },
+    Mapping,
+    Sequence,
+    Alias,
+}
+
+/// Indexes every node of the first document in `contents` by its dotted path, eg `provisioning.source` or
+/// `agent.env.FOO`. Sequence elements are indexed as `path[0]`, `path[1]`, etc. The root node has the empty path.
+pub(crate) fn index(contents: &str) -> Result<BTreeMap<String, Node>, ScanError> {
+    let mut indexer = Indexer {
+        source: contents.chars().collect(),
+        stack: vec![],
+        nodes: BTreeMap::new(),
+    };
+    Parser::new(contents.chars()).load(&mut indexer, false)?;
+    Ok(indexer.nodes)
+}
+
+/// Returns the byte offset of the given char index in `contents`
+pub(crate) fn byte_offset(contents: &str, char_index: usize) -> usize {
+    contents
+        .char_indices()
+        .nth(char_index)
+        .map_or_else(|| contents.len(), |(offset, _)| offset)
+}
+
+pub(crate) fn join(parent: &str, key: &str) -> String {
+    if parent.is_empty() {
+        key.to_owned()
+    } else {
+        format!("{}.{}", parent, key)
+    }
+}
+
+enum Frame {
+    Mapping {
+        path: String,
+        key: Option<(String, Marker)>,
+    },
+    Sequence {
+        path: String,
+        next_index: usize,
+    },
+}
+
+struct Indexer {
+    source: Vec<char>,
+    stack: Vec<Frame>,
+    nodes: BTreeMap<String, Node>,
+}
+
+impl Indexer {
+    fn extend_ancestors(&mut self, line: usize) {
+        for frame in &self.stack {
+            let path = match frame {
+                Frame::Mapping { path, .. } | Frame::Sequence { path, .. } => path,
+            };
+            if let Some(node) = self.nodes.get_mut(path) {
+                node.end_line = std::cmp::max(node.end_line, line);
+            }
+        }
+    }
+
+    fn on_node(&mut self, kind: NodeKind, mark: Marker) {
+        if let Some(Frame::Mapping { key, .. }) = self.stack.last_mut() {
+            if key.is_none() {
+                // This node is a key. Complex keys aren't used in config.yaml, so they're indexed as the empty key.
+                let name = match &kind {
+                    NodeKind::Scalar { value, .. } => value.clone(),
+                    _ => String::new(),
+                };
+                *key = Some((name, mark));
+                self.extend_ancestors(mark.line());
+                return;
+            }
+        }
+
+        let (path, key_mark) = match self.stack.last_mut() {
+            Some(Frame::Mapping { path, key }) => {
+                let (key, key_mark) = key.take().expect("key was set by the previous node");
+                (join(path, &key), Some(key_mark))
+            }
+
+            Some(Frame::Sequence { path, next_index }) => {
+                let path = format!("{}[{}]", path, next_index);
+                *next_index += 1;
+                (path, None)
+            }
+
+            None => (String::new(), None),
+        };
+
+        let empty = match &kind {
+            NodeKind::Scalar { empty, .. } => *empty,
+            _ => false,
+        };
+        if !empty {
+            self.extend_ancestors(mark.line());
+        }
+
+        let end_line = key_mark.map_or_else(|| mark.line(), |key_mark| key_mark.line());
+        let end_line = if empty {
+            end_line
+        } else {
+            std::cmp::max(end_line, mark.line())
+        };
+
+        match kind {
+            NodeKind::Mapping => self.stack.push(Frame::Mapping {
+                path: path.clone(),
+                key: None,
+            }),
+            NodeKind::Sequence => self.stack.push(Frame::Sequence {
+                path: path.clone(),
+                next_index: 0,
+            }),
+            NodeKind::Scalar { .. } | NodeKind::Alias => (),
+        }
+
+        self.nodes.insert(
+            path,
+            Node {
+                key_mark,
+                value_mark: mark,
+                end_line,
+                kind,
+            },
+        );
+    }
+}
+
+impl MarkedEventReceiver for Indexer {
+    fn on_event(&mut self, ev: Event, mark: Marker) {
+        match ev {
+            Event::Scalar(value, style, _, _) => {
+                // The parser reports an empty value (like `key:` with nothing after it) as `~`,
+                // positioned at the token after it.
+                let empty = style == TScalarStyle::Plain
+                    && value == "~"
+                    && self.source.get(mark.index()) != Some(&'~');
+                self.on_node(
+                    NodeKind::Scalar {
+                        value,
+                        style,
+                        empty,
+                    },
+                    mark,
+                );
+            }
+            Event::MappingStart(_) => self.on_node(NodeKind::Mapping, mark),
+            Event::SequenceStart(_) => self.on_node(NodeKind::Sequence, mark),
+            Event::Alias(_) => self.on_node(NodeKind::Alias, mark),
+            Event::MappingEnd | Event::SequenceEnd => {
+                self.stack.pop();
+            }
+            Event::Nothing
+            | Event::StreamStart
+            | Event::StreamEnd
+            | Event::DocumentStart
+            | Event::DocumentEnd => (),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CONFIG: &str = r#"# Provisioning mode
+provisioning:
+  source: "manual"
+  device_connection_string: "<ADD DEVICE CONNECTION STRING HERE>"
+
+agent:
+  name: "edgeAgent"
+  env: {}
+  config:
+    image: "mcr.microsoft.com/azureiotedge-agent:1.0"
+    auth:
+
+hostname: "my-device" # the device's hostname
+listen:
+  - a
+  - b
+"#;
+
+    #[test]
+    fn indexes_paths() {
+        let nodes = index(CONFIG).unwrap();
+
+        assert_eq!(NodeKind::Mapping, nodes[""].kind);
+        assert_eq!(NodeKind::Mapping, nodes["provisioning"].kind);
+        assert_eq!(NodeKind::Sequence, nodes["listen"].kind);
+
+        let source = &nodes["provisioning.source"];
+        assert_eq!(3, source.key_mark.unwrap().line());
+        assert_eq!(2, source.key_mark.unwrap().col());
+        assert_eq!(3, source.value_mark.line());
+        assert_eq!(10, source.value_mark.col());
+        assert_eq!(
+            NodeKind::Scalar {
+                value: "manual".to_owned(),
+                style: TScalarStyle::DoubleQuoted,
+                empty: false,
+            },
+            source.kind,
+        );
+
+        assert_eq!(
+            NodeKind::Scalar {
+                value: "b".to_owned(),
+                style: TScalarStyle::Plain,
+                empty: false,
+            },
+            nodes["listen[1]"].kind,
+        );
+    }
+
+    #[test]
+    fn empty_values() {
+        let nodes = index(CONFIG).unwrap();
+
+        let auth = &nodes["agent.config.auth"];
+        assert_eq!(
+            NodeKind::Scalar {
+                value: "~".to_owned(),
+                style: TScalarStyle::Plain,
+                empty: true,
+            },
+            auth.kind,
+        );
+        assert_eq!(11, auth.end_line);
+    }
+
+    #[test]
+    fn end_lines() {
+        let nodes = index(CONFIG).unwrap();
+
+        assert_eq!(4, nodes["provisioning"].end_line);
+        assert_eq!(11, nodes["agent"].end_line);
+        assert_eq!(11, nodes["agent.config"].end_line);
+        assert_eq!(16, nodes[""].end_line);
+    }
+
+    #[test]
+    fn reports_syntax_errors() {
+        let err = index("a:\n  b: [1, 2\n").unwrap_err();
+        assert_eq!(3, err.marker().line());
+    }
+}
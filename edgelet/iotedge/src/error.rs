@@ -15,12 +15,30 @@ pub enum ErrorKind {
     #[fail(display = "Invalid value for --host parameter")]
     BadHostParameter,
 
+    #[fail(display = "Invalid value for --interval parameter")]
+    BadIntervalParameter,
+
+    #[fail(display = "Invalid value for --key parameter. The key must be base64-encoded")]
+    BadKeyParameter,
+
+    #[fail(display = "Invalid value for --passphrase parameter. The passphrase must not be empty")]
+    BadPassphraseParameter,
+
+    #[fail(display = "Invalid value for --password parameter. The password must not be empty")]
+    BadPasswordParameter,
+
     #[fail(display = "Invalid value for --since parameter")]
     BadSinceParameter,
 
     #[fail(display = "Invalid value for --tail parameter")]
     BadTailParameter,
 
+    #[fail(display = "Invalid value for --{} parameter", _0)]
+    BadTlsParameter(&'static str),
+
+    #[fail(display = "The command exited with code {}", _0)]
+    CommandExited(i32),
+
     #[fail(display = "")]
     Diagnostics,
 
@@ -30,24 +48,86 @@ pub enum ErrorKind {
     )]
     FetchLatestVersions(FetchLatestVersionsReason),
 
+    #[fail(display = "Could not attach the module to the network")]
+    AttachNetwork,
+
+    #[fail(display = "Could not back up the config file")]
+    BackupConfig,
+
+    #[fail(
+        display = "The backup is of device {}, but this device is {}. Use --reprovision to restore it and provision the device again",
+        _0, _1
+    )]
+    BackupIdentityMismatch(String, String),
+
+    #[fail(
+        display = "The backup could not be decrypted. The passphrase is wrong or the backup was modified"
+    )]
+    BadBackupPassphrase,
+
+    #[fail(display = "Could not create the backup")]
+    CreateBackup,
+
+    #[fail(display = "Could not detach the module from the network")]
+    DetachNetwork,
+
     #[fail(display = "Could not initialize tokio runtime")]
     InitializeTokio,
 
+    #[fail(display = "The file is not an IoT Edge backup, or it is damaged")]
+    InvalidBackup,
+
+    #[fail(display = "The config file is not valid")]
+    InvalidConfig,
+
+    #[fail(display = "Could not list the networks")]
+    ListNetworks,
+
     #[fail(display = "Missing --host parameter")]
     MissingHostParameter,
 
     #[fail(display = "A module runtime error occurred")]
     ModuleRuntime,
 
+    #[fail(display = "Could not read the config file")]
+    ReadConfig,
+
+    #[fail(display = "Could not store the registry credentials")]
+    RegistryLogin,
+
+    #[fail(display = "Could not remove the registry credentials")]
+    RegistryLogout,
+
+    #[fail(display = "Could not restart the IoT Edge service")]
+    RestartService,
+
+    #[fail(display = "Could not restore the backup")]
+    RestoreBackup,
+
+    #[fail(display = "Could not rotate the device key")]
+    RotateKey,
+
+    #[fail(display = "Could not set {}: {}", _0, _1)]
+    SetConfig(String, SetConfigReason),
+
     #[fail(display = "Could not generate support bundle")]
     SupportBundle,
 
+    #[fail(display = "Could not configure the terminal")]
+    Terminal,
+
+    #[fail(display = "Could not refresh module resource usage")]
+    Top,
+
     #[fail(display = "Could not write to stdout")]
     WriteToStdout,
 
     #[fail(display = "Could not write to file")]
     WriteToFile,
 
+    #[fail(display = "Could not write the config file")]
+    WriteConfig,
+
     #[fail(display = "Unable to bundle iotedge check")]
     BundleCheck,
 
@@ -71,6 +151,12 @@ impl Display for Error {
     }
 }
 
+impl Error {
+    pub fn kind(&self) -> &ErrorKind {
+        self.inner.get_context()
+    }
+}
+
 impl From<ErrorKind> for Error {
     fn from(kind: ErrorKind) -> Self {
         Error {
@@ -108,3 +194,34 @@ impl Display for FetchLatestVersionsReason {
         }
     }
 }
+
+#[derive(Clone, Debug)]
+pub enum SetConfigReason {
+    InvalidValue,
+    InvalidYaml(String),
+    MultiLineScalar,
+    NotAMapping(String),
+    NotAScalar,
+}
+
+impl Display for SetConfigReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SetConfigReason::InvalidValue => write!(f, "the value is not a single YAML scalar"),
+            SetConfigReason::InvalidYaml(message) => {
+                write!(f, "the config file is not valid YAML: {}", message)
+            }
+            SetConfigReason::MultiLineScalar => write!(
+                f,
+                "the existing value spans multiple lines and must be edited by hand"
+            ),
+            SetConfigReason::NotAMapping(path) if path.is_empty() => {
+                write!(f, "the document is not a mapping")
+            }
+            SetConfigReason::NotAMapping(path) => {
+                write!(f, "{} is not a block mapping", path)
+            }
+            SetConfigReason::NotAScalar => write!(f, "the existing value is not a scalar"),
+        }
+    }
+}
@@ -0,0 +1,241 @@
+// Copyright (c) Microsoft. All rights reserved.
+
+use std::convert::TryInto;
+use std::io::{self, Read, Write};
+use std::thread;
+
+use bytes::Bytes;
+use failure::Fail;
+use futures::future::{self, Either};
+use futures::prelude::*;
+use futures::sync::{mpsc, oneshot};
+use tokio::codec::{BytesCodec, FramedRead};
+use tokio::io::AsyncRead;
+
+use edgelet_core::{ExecOptions, LogChunk, LogDecode, ModuleRuntime};
+
+use crate::error::{Error, ErrorKind};
+use crate::Command;
+
+pub struct Exec<M> {
+    id: String,
+    options: ExecOptions,
+    runtime: M,
+}
+
+impl<M> Exec<M> {
+    pub fn new(id: String, options: ExecOptions, runtime: M) -> Self {
+        Exec {
+            id,
+            options,
+            runtime,
+        }
+    }
+}
+
+impl<M> Command for Exec<M>
+where
+    M: 'static + ModuleRuntime + Clone + Send,
+{
+    type Future = Box<dyn Future<Item = (), Error = Error> + Send>;
+
+    fn execute(self) -> Self::Future {
+        let tty = self.options.tty();
+        let options = match terminal_size() {
+            Some((height, width)) if tty => self.options.with_tty_size(height, width),
+            _ => self.options,
+        };
+
+        let raw_mode = if tty && atty::is(atty::Stream::Stdin) {
+            match RawMode::enable() {
+                Ok(raw_mode) => Some(raw_mode),
+                Err(err) => {
+                    return Box::new(future::err(Error::from(err.context(ErrorKind::Terminal))))
+                }
+            }
+        } else {
+            None
+        };
+
+        let id = self.id;
+        let runtime = self.runtime;
+        let result = runtime
+            .exec(&id, &options)
+            .map_err(|err| Error::from(err.context(ErrorKind::ModuleRuntime)))
+            .and_then(move |(exec_id, stream)| {
+                let (read, write) = stream.split();
+                tokio::spawn(forward_stdin(write));
+
+                // The terminal is resized until the command exits, when `resized` is dropped.
+                let (resized, done) = oneshot::channel::<()>();
+                if tty {
+                    tokio::spawn(
+                        forward_resizes(runtime.clone(), id.clone(), exec_id.clone())
+                            .select2(done)
+                            .then(|_| Ok(())),
+                    );
+                }
+
+                // With a TTY the output is sent as is, otherwise stdout and stderr are multiplexed
+                // like logs are.
+                let output = if tty {
+                    Either::A(
+                        FramedRead::new(read, BytesCodec::new())
+                            .map(|b| LogChunk::Stdout(b.freeze())),
+                    )
+                } else {
+                    Either::B(LogDecode::new(read))
+                };
+
+                output
+                    .for_each(|chunk| match chunk {
+                        LogChunk::Stderr(b) => write_flush(&mut io::stderr(), &b),
+                        LogChunk::Stdin(b) | LogChunk::Stdout(b) | LogChunk::Unknown(b) => {
+                            write_flush(&mut io::stdout(), &b)
+                        }
+                    })
+                    .map_err(|err| Error::from(err.context(ErrorKind::WriteToStdout)))
+                    // The terminal is restored once the command exits.
+                    .then(move |result| {
+                        drop(resized);
+                        drop(raw_mode);
+                        result
+                    })
+                    .and_then(move |()| {
+                        runtime
+                            .exec_exit_code(&id, &exec_id)
+                            .map_err(|err| Error::from(err.context(ErrorKind::ModuleRuntime)))
+                    })
+                    .and_then(|exit_code| match exit_code {
+                        Some(0) | None => Ok(()),
+                        Some(code) => Err(Error::from(ErrorKind::CommandExited(
+                            code.try_into().unwrap_or(1),
+                        ))),
+                    })
+            });
+        Box::new(result)
+    }
+}
+
+fn write_flush<W: Write>(w: &mut W, buf: &[u8]) -> io::Result<()> {
+    w.write_all(buf)?;
+    w.flush()
+}
+
+/// Sends stdin to the command, and closes its input at the end of stdin.
+///
+/// Stdin is read on its own thread, since a blocking read would otherwise keep the tokio runtime
+/// from shutting down after the command exits.
+fn forward_stdin<W>(write: W) -> impl Future<Item = (), Error = ()>
+where
+    W: tokio::io::AsyncWrite + Send + 'static,
+{
+    let (sender, receiver) = mpsc::channel::<Bytes>(1);
+    thread::spawn(move || {
+        let mut sender = sender;
+        let mut buf = [0; 4096];
+        loop {
+            match io::stdin().read(&mut buf) {
+                Ok(0) | Err(_) => break,
+                Ok(n) => match sender.send(Bytes::from(&buf[..n])).wait() {
+                    Ok(s) => sender = s,
+                    Err(_) => break,
+                },
+            }
+        }
+    });
+
+    receiver
+        .fold(write, |write, chunk| {
+            tokio::io::write_all(write, chunk)
+                .map(|(write, _)| write)
+                .map_err(drop)
+        })
+        .and_then(|write| tokio::io::shutdown(write).map(drop).map_err(drop))
+}
+
+/// Resizes the TTY of the command whenever the terminal is resized.
+#[cfg(unix)]
+fn forward_resizes<M>(runtime: M, id: String, exec_id: String) -> impl Future<Item = (), Error = ()>
+where
+    M: 'static + ModuleRuntime,
+{
+    use tokio_signal::unix::Signal;
+
+    Signal::new(libc::SIGWINCH)
+        .flatten_stream()
+        .map_err(drop)
+        .for_each(move |_| match terminal_size() {
+            Some((height, width)) => Either::A(
+                runtime
+                    .exec_resize(&id, &exec_id, height, width)
+                    // A failed resize only leaves the command with the previous size.
+                    .or_else(|_| Ok(())),
+            ),
+            None => Either::B(future::ok(())),
+        })
+}
+
+/// Terminal sizes aren't known on Windows, so there are no resizes to forward.
+#[cfg(windows)]
+fn forward_resizes<M>(
+    _runtime: M,
+    _id: String,
+    _exec_id: String,
+) -> impl Future<Item = (), Error = ()> {
+    future::empty()
+}
+
+#[cfg(unix)]
+fn terminal_size() -> Option<(u16, u16)> {
+    let mut size: libc::winsize = unsafe { std::mem::zeroed() };
+    if unsafe { libc::ioctl(libc::STDOUT_FILENO, libc::TIOCGWINSZ, &mut size) } == 0 {
+        Some((size.ws_row, size.ws_col))
+    } else {
+        None
+    }
+}
+
+#[cfg(windows)]
+fn terminal_size() -> Option<(u16, u16)> {
+    None
+}
+
+/// Puts the terminal in raw mode so that keystrokes, including control characters, go to the
+/// command as they are typed. The original mode is restored on drop.
+#[cfg(unix)]
+struct RawMode(libc::termios);
+
+#[cfg(unix)]
+impl RawMode {
+    fn enable() -> io::Result<Self> {
+        let mut termios: libc::termios = unsafe { std::mem::zeroed() };
+        if unsafe { libc::tcgetattr(libc::STDIN_FILENO, &mut termios) } != 0 {
+            return Err(io::Error::last_os_error());
+        }
+        let original = termios;
+
+        unsafe { libc::cfmakeraw(&mut termios) };
+        if unsafe { libc::tcsetattr(libc::STDIN_FILENO, libc::TCSANOW, &termios) } != 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(RawMode(original))
+    }
+}
+
+#[cfg(unix)]
+impl Drop for RawMode {
+    fn drop(&mut self) {
+        unsafe { libc::tcsetattr(libc::STDIN_FILENO, libc::TCSANOW, &self.0) };
+    }
+}
+
+#[cfg(windows)]
+struct RawMode;
+
+#[cfg(windows)]
+impl RawMode {
+    fn enable() -> io::Result<Self> {
+        Ok(RawMode)
+    }
+}
@@ -14,21 +14,35 @@
 use futures::Future;
 use serde_derive::Deserialize;
 
+mod backup;
 mod check;
+mod config;
 mod error;
+mod exec;
 mod list;
 mod logs;
+mod network;
+mod registry;
 mod restart;
+mod rotate_key;
 mod support_bundle;
+mod top;
 mod unknown;
 mod version;
 
+pub use crate::backup::{BackupCreate, BackupRestore};
 pub use crate::check::{Check, OutputFormat};
-pub use crate::error::{Error, ErrorKind, FetchLatestVersionsReason};
+pub use crate::config::{ConfigApply, ConfigSet, ConfigShow, ConfigValidate};
+pub use crate::error::{Error, ErrorKind, FetchLatestVersionsReason, SetConfigReason};
+pub use crate::exec::Exec;
 pub use crate::list::List;
 pub use crate::logs::Logs;
+pub use crate::network::{NetworkAttach, NetworkDetach, NetworkList};
+pub use crate::registry::{RegistryLogin, RegistryLogout};
 pub use crate::restart::Restart;
+pub use crate::rotate_key::RotateKey;
 pub use crate::support_bundle::SupportBundle;
+pub use crate::top::Top;
 pub use crate::unknown::Unknown;
 pub use crate::version::Version;
 
@@ -6,23 +6,30 @@
 
 use std::borrow::Cow;
 use std::convert::TryInto;
-use std::io;
+use std::fs;
+use std::io::{self, BufRead};
 use std::path::{Path, PathBuf};
 use std::process;
 
 use chrono::{DateTime, Duration, Local};
-use clap::{crate_description, crate_name, App, AppSettings, Arg, SubCommand};
+use clap::{crate_description, crate_name, App, AppSettings, Arg, ArgMatches, SubCommand};
 use failure::{Fail, ResultExt};
 use futures::Future;
 use url::Url;
 
-use edgelet_core::{LogOptions, LogTail};
+use edgelet_core::{ExecOptions, LogOptions, LogTail};
+use edgelet_http::{PemCertificate, TlsConfig};
 use edgelet_http_mgmt::ModuleClient;
 
 use iotedge::*;
 
 fn main() {
     if let Err(ref error) = run() {
+        // `iotedge exec` exits with the exit code of the command it ran.
+        if let ErrorKind::CommandExited(code) = error.kind() {
+            process::exit(*code);
+        }
+
         let fail: &dyn Fail = error;
 
         eprintln!("{}", error.to_string());
@@ -83,6 +90,26 @@ fn run() -> Result<(), Error> {
         edgelet_core::version().replace("~", "-")
     );
 
+    let config_file_arg = || {
+        Arg::with_name("config-file")
+            .short("c")
+            .long("config-file")
+            .value_name("FILE")
+            .help("Sets daemon configuration file")
+            .takes_value(true)
+            .default_value_os(default_config_path.as_os_str())
+    };
+
+    let passphrase_arg = || {
+        Arg::with_name("passphrase")
+            .help("Sets the passphrase that the archive is encrypted with. Use - to read it from stdin so it does not end up in the shell history")
+            .long("passphrase")
+            .takes_value(true)
+            .value_name("PASSPHRASE")
+            .env("IOTEDGE_BACKUP_PASSPHRASE")
+            .required(true)
+    };
+
     let mut possible_check_id_values: Vec<_> = Check::possible_ids().collect();
     possible_check_id_values.sort();
 
@@ -101,6 +128,78 @@ fn run() -> Result<(), Error> {
                 .env("IOTEDGE_HOST")
                 .default_value(default_mgmt_uri),
         )
+        .arg(
+            Arg::with_name("tls-ca-cert")
+                .help("CA certificates to trust when HOST is an https URL, instead of the system's")
+                .long("tls-ca-cert")
+                .takes_value(true)
+                .value_name("FILE")
+                .global(true)
+                .env("IOTEDGE_TLS_CA_CERT"),
+        )
+        .arg(
+            Arg::with_name("tls-cert")
+                .help("Client certificate to present when HOST is an https URL")
+                .long("tls-cert")
+                .takes_value(true)
+                .value_name("FILE")
+                .global(true)
+                .env("IOTEDGE_TLS_CERT")
+                .requires("tls-key"),
+        )
+        .arg(
+            Arg::with_name("tls-key")
+                .help("Private key of the client certificate")
+                .long("tls-key")
+                .takes_value(true)
+                .value_name("FILE")
+                .global(true)
+                .env("IOTEDGE_TLS_KEY")
+                .requires("tls-cert"),
+        )
+        .subcommand(
+            SubCommand::with_name("backup")
+                .about("Back up and restore the state of the device, to replace its hardware")
+                .setting(AppSettings::SubcommandRequiredElseHelp)
+                .subcommand(
+                    SubCommand::with_name("create")
+                        .about("Write the IoT Edge state and the volumes of the modules to an encrypted archive")
+                        .long_about("Write the IoT Edge state and the volumes of the modules to an encrypted archive.\n\nThe archive holds the device's keys and certificates, its provisioning information, and the Docker volumes mounted into modules. Stop the IoT Edge service and the modules first so that their state doesn't change while it is copied.")
+                        .arg(config_file_arg())
+                        .arg(
+                            Arg::with_name("output")
+                                .help("Path of the archive to write")
+                                .long("output")
+                                .short("o")
+                                .takes_value(true)
+                                .value_name("FILENAME")
+                                .required(true),
+                        )
+                        .arg(passphrase_arg()),
+                )
+                .subcommand(
+                    SubCommand::with_name("restore")
+                        .about("Restore an archive written by 'iotedge backup create'")
+                        .long_about("Restore an archive written by 'iotedge backup create'.\n\nThe archive must be of the device that the configuration file provisions, unless --reprovision is set. Stop the IoT Edge service first, and start it once the archive is restored.")
+                        .arg(config_file_arg())
+                        .arg(
+                            Arg::with_name("input")
+                                .help("Path of the archive to restore")
+                                .long("input")
+                                .short("i")
+                                .takes_value(true)
+                                .value_name("FILENAME")
+                                .required(true),
+                        )
+                        .arg(passphrase_arg())
+                        .arg(
+                            Arg::with_name("reprovision")
+                                .help("Restore the archive to a device with another identity. The device provisions again with the configuration file when the IoT Edge service starts")
+                                .long("reprovision")
+                                .takes_value(false),
+                        ),
+                ),
+        )
         .subcommand(
             SubCommand::with_name("check")
                 .about("Check for common config and deployment issues")
@@ -166,8 +265,10 @@ fn run() -> Result<(), Error> {
                     Arg::with_name("ntp-server")
                         .long("ntp-server")
                         .value_name("NTP_SERVER")
-                        .help("Sets the NTP server to use when checking host local time.")
+                        .help("Sets the NTP servers to use when checking host local time. Every address of each server is queried, and servers that disagree with the majority are discarded.")
                         .takes_value(true)
+                        .multiple(true)
+                        .number_of_values(1)
                         .default_value("pool.ntp.org:123"),
                 )
                 .arg(
@@ -175,9 +276,9 @@ fn run() -> Result<(), Error> {
                         .long("output")
                         .short("o")
                         .value_name("FORMAT")
-                        .help("Output format. Note that JSON output contains some additional information like OS name, OS version, disk space, etc.")
+                        .help("Output format. Note that JSON output contains some additional information like OS name, OS version, disk space, etc. JUnit and SARIF output are meant for CI systems and contain one test case or result per check.")
                         .takes_value(true)
-                        .possible_values(&["json", "text"])
+                        .possible_values(&["json", "junit", "sarif", "text"])
                         .default_value("text"),
                 )
                 .arg(
@@ -195,7 +296,61 @@ fn run() -> Result<(), Error> {
                         .takes_value(false),
                 ),
         )
-        .subcommand(SubCommand::with_name("check-list").about("List the checks that are run for 'iotedge check'"))
+        .subcommand(
+            SubCommand::with_name("check-list")
+                .about("List the checks that are run for 'iotedge check'")
+                .arg(
+                    Arg::with_name("json")
+                        .help("Print the checks as JSON")
+                        .long("json")
+                        .takes_value(false),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("config")
+                .about("Validate, show and edit the daemon configuration file")
+                .setting(AppSettings::SubcommandRequiredElseHelp)
+                .subcommand(
+                    SubCommand::with_name("apply")
+                        .about("Validate the configuration file, back it up, and restart the IoT Edge service")
+                        .arg(config_file_arg())
+                        .arg(
+                            Arg::with_name("file")
+                                .short("f")
+                                .long("file")
+                                .value_name("FILE")
+                                .help("Sets a new configuration file to copy over the daemon configuration file. Defaults to applying the daemon configuration file as it is.")
+                                .takes_value(true),
+                        ),
+                )
+                .subcommand(
+                    SubCommand::with_name("set")
+                        .about("Set a value in the configuration file, keeping its comments and formatting")
+                        .arg(config_file_arg())
+                        .arg(
+                            Arg::with_name("PATH")
+                                .help("Sets the dotted path of the value, like provisioning.device_connection_string")
+                                .required(true)
+                                .index(1),
+                        )
+                        .arg(
+                            Arg::with_name("VALUE")
+                                .help("Sets the new value, as a YAML scalar")
+                                .required(true)
+                                .index(2),
+                        ),
+                )
+                .subcommand(
+                    SubCommand::with_name("show")
+                        .about("Show the effective configuration after defaults are applied, with secrets masked")
+                        .arg(config_file_arg()),
+                )
+                .subcommand(
+                    SubCommand::with_name("validate")
+                        .about("Check that the configuration file can be loaded by the daemon")
+                        .arg(config_file_arg()),
+                ),
+        )
         .subcommand(SubCommand::with_name("list").about("List modules"))
         .subcommand(
             SubCommand::with_name("restart")
@@ -207,6 +362,106 @@ fn run() -> Result<(), Error> {
                         .index(1),
                 ),
         )
+        .subcommand(
+            SubCommand::with_name("rotate-key")
+                .about("Replace the device key of a device provisioned with a connection string or a DPS symmetric key")
+                .long_about("Replace the device key of a device provisioned with a connection string or a DPS symmetric key.\n\nIoT Hub (or, for DPS, the enrollment) must already accept the new key. The module identities are updated with keys derived from it, the configuration file is updated, and the IoT Edge service restarts. If any step fails, the device keeps its current key.")
+                .arg(
+                    Arg::with_name("key")
+                        .help("Sets the new base64-encoded device key. Use - to read it from stdin so it does not end up in the shell history")
+                        .long("key")
+                        .takes_value(true)
+                        .value_name("KEY")
+                        .required(true),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("registry")
+                .about("Manage the credentials that images are pulled with when a module's config has none")
+                .setting(AppSettings::SubcommandRequiredElseHelp)
+                .subcommand(
+                    SubCommand::with_name("login")
+                        .about("Store credentials for a registry")
+                        .long_about("Store credentials for a registry.\n\nThe IoT Edge service encrypts them with the device's master encryption key, and pulls images from the registry with them for modules whose config has no registry credentials. They take precedence over the registry credentials and credential helpers in the configuration file.")
+                        .arg(
+                            Arg::with_name("REGISTRY")
+                                .help("Sets the host of the registry, with its port if it has one")
+                                .required(true)
+                                .index(1),
+                        )
+                        .arg(
+                            Arg::with_name("username")
+                                .help("Sets the username")
+                                .long("username")
+                                .short("u")
+                                .takes_value(true)
+                                .value_name("USERNAME")
+                                .required(true),
+                        )
+                        .arg(
+                            Arg::with_name("password")
+                                .help("Sets the password. Use - to read it from stdin so it does not end up in the shell history")
+                                .long("password")
+                                .short("p")
+                                .takes_value(true)
+                                .value_name("PASSWORD")
+                                .env("IOTEDGE_REGISTRY_PASSWORD")
+                                .required(true),
+                        ),
+                )
+                .subcommand(
+                    SubCommand::with_name("logout")
+                        .about("Remove the credentials stored for a registry by 'iotedge registry login'")
+                        .arg(
+                            Arg::with_name("REGISTRY")
+                                .help("Sets the host of the registry, with its port if it has one")
+                                .required(true)
+                                .index(1),
+                        ),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("network")
+                .about("Manage the container networks declared in the configuration file")
+                .setting(AppSettings::SubcommandRequiredElseHelp)
+                .subcommand(
+                    SubCommand::with_name("list")
+                        .about("List the networks and the modules attached to them"),
+                )
+                .subcommand(
+                    SubCommand::with_name("attach")
+                        .about("Attach a module to a network")
+                        .long_about("Attach a module to a network.\n\nThe module stays attached until it is recreated. To attach it permanently, add it to the network's modules in the configuration file.")
+                        .arg(
+                            Arg::with_name("NETWORK")
+                                .help("Sets the name of the network")
+                                .required(true)
+                                .index(1),
+                        )
+                        .arg(
+                            Arg::with_name("MODULE")
+                                .help("Sets the module to attach")
+                                .required(true)
+                                .index(2),
+                        ),
+                )
+                .subcommand(
+                    SubCommand::with_name("detach")
+                        .about("Detach a module from a network")
+                        .arg(
+                            Arg::with_name("NETWORK")
+                                .help("Sets the name of the network")
+                                .required(true)
+                                .index(1),
+                        )
+                        .arg(
+                            Arg::with_name("MODULE")
+                                .help("Sets the module to detach")
+                                .required(true)
+                                .index(2),
+                        ),
+                ),
+        )
         .subcommand(
             SubCommand::with_name("logs")
                 .about("Fetch the logs of a module")
@@ -239,6 +494,29 @@ fn run() -> Result<(), Error> {
                         .long("follow"),
                 ),
         )
+        .subcommand(
+            SubCommand::with_name("exec")
+                .about("Run a command in a running module")
+                .arg(
+                    Arg::with_name("MODULE")
+                        .help("Sets the module identity to run the command in")
+                        .required(true)
+                        .index(1),
+                )
+                .arg(
+                    Arg::with_name("tty")
+                        .help("Allocate a TTY for the command, for interactive shells")
+                        .short("t")
+                        .long("tty"),
+                )
+                .arg(
+                    Arg::with_name("COMMAND")
+                        .help("Sets the command to run, after --")
+                        .required(true)
+                        .multiple(true)
+                        .last(true),
+                ),
+        )
         .subcommand(
             SubCommand::with_name("support-bundle")
                 .about("Bundles troubleshooting information")
@@ -279,6 +557,25 @@ fn run() -> Result<(), Error> {
                         .takes_value(false),
                 ),
         )
+        .subcommand(
+            SubCommand::with_name("top")
+                .about("Display a live view of the resource usage of modules")
+                .arg(
+                    Arg::with_name("interval")
+                        .help("Number of seconds between refreshes")
+                        .long("interval")
+                        .short("n")
+                        .takes_value(true)
+                        .value_name("SECONDS")
+                        .default_value("2"),
+                )
+                .arg(
+                    Arg::with_name("json")
+                        .help("Print the resource usage once as JSON and exit")
+                        .long("json")
+                        .takes_value(false),
+                ),
+        )
         .subcommand(SubCommand::with_name("version").about("Show the version information"))
         .get_matches();
 
@@ -291,13 +588,57 @@ fn run() -> Result<(), Error> {
                     .map_err(Error::from)
             },
         )?;
-        let runtime = ModuleClient::new(&url).context(ErrorKind::ModuleRuntime)?;
+        let tls_config = tls_config(&matches)?;
+        let runtime =
+            ModuleClient::with_tls_config(&url, &tls_config).context(ErrorKind::ModuleRuntime)?;
         Ok(runtime)
     };
 
     let mut tokio_runtime = tokio::runtime::Runtime::new().context(ErrorKind::InitializeTokio)?;
 
     match matches.subcommand() {
+        ("backup", Some(args)) => {
+            let config_file = |args: &clap::ArgMatches<'_>| -> PathBuf {
+                args.value_of_os("config-file")
+                    .expect("arg has a default value")
+                    .to_os_string()
+                    .into()
+            };
+            let passphrase = |args: &clap::ArgMatches<'_>| -> Result<String, Error> {
+                match args.value_of("passphrase").expect("arg is required") {
+                    "-" => {
+                        let mut passphrase = String::new();
+                        io::stdin()
+                            .lock()
+                            .read_line(&mut passphrase)
+                            .context(ErrorKind::BadPassphraseParameter)?;
+                        Ok(passphrase.trim_end_matches(&['\r', '\n'][..]).to_string())
+                    }
+                    passphrase => Ok(passphrase.to_string()),
+                }
+            };
+
+            match args.subcommand() {
+                ("create", Some(args)) => tokio_runtime.block_on(
+                    BackupCreate::new(
+                        config_file(args),
+                        args.value_of_os("output").expect("arg is required").into(),
+                        passphrase(args)?,
+                    )
+                    .execute(),
+                ),
+                ("restore", Some(args)) => tokio_runtime.block_on(
+                    BackupRestore::new(
+                        config_file(args),
+                        args.value_of_os("input").expect("arg is required").into(),
+                        passphrase(args)?,
+                        args.is_present("reprovision"),
+                    )
+                    .execute(),
+                ),
+                (command, _) => tokio_runtime.block_on(Unknown::new(command.to_string()).execute()),
+            }
+        }
         ("check", Some(args)) => tokio_runtime.block_on(
             Check::new(
                 args.value_of_os("config-file")
@@ -323,12 +664,15 @@ fn run() -> Result<(), Error> {
                     .to_os_string()
                     .into(),
                 args.value_of("iothub-hostname").map(ToOwned::to_owned),
-                args.value_of("ntp-server")
+                args.values_of("ntp-server")
                     .expect("arg has a default value")
-                    .to_string(),
+                    .map(ToOwned::to_owned)
+                    .collect(),
                 args.value_of("output")
                     .map(|arg| match arg {
                         "json" => OutputFormat::Json,
+                        "junit" => OutputFormat::Junit,
+                        "sarif" => OutputFormat::Sarif,
                         "text" => OutputFormat::Text,
                         _ => unreachable!(),
                     })
@@ -338,7 +682,43 @@ fn run() -> Result<(), Error> {
             )
             .and_then(Command::execute),
         ),
-        ("check-list", _) => Check::print_list(),
+        ("check-list", Some(args)) => {
+            if args.is_present("json") {
+                Check::print_list_json()
+            } else {
+                Check::print_list()
+            }
+        }
+        ("config", Some(args)) => {
+            let config_file = |args: &clap::ArgMatches<'_>| -> PathBuf {
+                args.value_of_os("config-file")
+                    .expect("arg has a default value")
+                    .to_os_string()
+                    .into()
+            };
+
+            match args.subcommand() {
+                ("apply", Some(args)) => tokio_runtime.block_on(
+                    ConfigApply::new(config_file(args), args.value_of_os("file").map(Into::into))
+                        .execute(),
+                ),
+                ("set", Some(args)) => tokio_runtime.block_on(
+                    ConfigSet::new(
+                        config_file(args),
+                        args.value_of("PATH").unwrap().to_string(),
+                        args.value_of("VALUE").unwrap().to_string(),
+                    )
+                    .execute(),
+                ),
+                ("show", Some(args)) => {
+                    tokio_runtime.block_on(ConfigShow::new(config_file(args)).execute())
+                }
+                ("validate", Some(args)) => {
+                    tokio_runtime.block_on(ConfigValidate::new(config_file(args)).execute())
+                }
+                (command, _) => tokio_runtime.block_on(Unknown::new(command.to_string()).execute()),
+            }
+        }
         ("list", _) => tokio_runtime.block_on(List::new(runtime()?, io::stdout()).execute()),
         ("restart", Some(args)) => tokio_runtime.block_on(
             Restart::new(
@@ -348,6 +728,92 @@ fn run() -> Result<(), Error> {
             )
             .execute(),
         ),
+        ("rotate-key", Some(args)) => {
+            let key = match args.value_of("key").expect("arg is required") {
+                "-" => {
+                    let mut key = String::new();
+                    io::stdin()
+                        .lock()
+                        .read_line(&mut key)
+                        .context(ErrorKind::BadKeyParameter)?;
+                    key
+                }
+                key => key.to_string(),
+            };
+            tokio_runtime.block_on(RotateKey::new(key, runtime()?, io::stdout()).execute())
+        }
+        ("registry", Some(args)) => match args.subcommand() {
+            ("login", Some(args)) => {
+                let password = match args.value_of("password").expect("arg is required") {
+                    "-" => {
+                        let mut password = String::new();
+                        io::stdin()
+                            .lock()
+                            .read_line(&mut password)
+                            .context(ErrorKind::BadPasswordParameter)?;
+                        password.trim_end_matches(&['\r', '\n'][..]).to_string()
+                    }
+                    password => password.to_string(),
+                };
+                tokio_runtime.block_on(
+                    RegistryLogin::new(
+                        args.value_of("REGISTRY")
+                            .expect("arg is required")
+                            .to_string(),
+                        args.value_of("username")
+                            .expect("arg is required")
+                            .to_string(),
+                        password,
+                        runtime()?,
+                        io::stdout(),
+                    )
+                    .execute(),
+                )
+            }
+            ("logout", Some(args)) => tokio_runtime.block_on(
+                RegistryLogout::new(
+                    args.value_of("REGISTRY")
+                        .expect("arg is required")
+                        .to_string(),
+                    runtime()?,
+                    io::stdout(),
+                )
+                .execute(),
+            ),
+            (command, _) => tokio_runtime.block_on(Unknown::new(command.to_string()).execute()),
+        },
+        ("network", Some(args)) => match args.subcommand() {
+            ("list", Some(_)) => {
+                tokio_runtime.block_on(NetworkList::new(runtime()?, io::stdout()).execute())
+            }
+            ("attach", Some(args)) => tokio_runtime.block_on(
+                NetworkAttach::new(
+                    args.value_of("NETWORK")
+                        .expect("arg is required")
+                        .to_string(),
+                    args.value_of("MODULE")
+                        .expect("arg is required")
+                        .to_string(),
+                    runtime()?,
+                    io::stdout(),
+                )
+                .execute(),
+            ),
+            ("detach", Some(args)) => tokio_runtime.block_on(
+                NetworkDetach::new(
+                    args.value_of("NETWORK")
+                        .expect("arg is required")
+                        .to_string(),
+                    args.value_of("MODULE")
+                        .expect("arg is required")
+                        .to_string(),
+                    runtime()?,
+                    io::stdout(),
+                )
+                .execute(),
+            ),
+            (command, _) => tokio_runtime.block_on(Unknown::new(command.to_string()).execute()),
+        },
         ("logs", Some(args)) => {
             let id = args.value_of("MODULE").unwrap().to_string();
             let follow = args.is_present("follow");
@@ -370,6 +836,16 @@ fn run() -> Result<(), Error> {
                 .with_since(since);
             tokio_runtime.block_on(Logs::new(id, options, runtime()?).execute())
         }
+        ("exec", Some(args)) => {
+            let id = args.value_of("MODULE").unwrap().to_string();
+            let command = args
+                .values_of("COMMAND")
+                .unwrap()
+                .map(ToString::to_string)
+                .collect();
+            let options = ExecOptions::new(command).with_tty(args.is_present("tty"));
+            tokio_runtime.block_on(Exec::new(id, options, runtime()?).execute())
+        }
         ("support-bundle", Some(args)) => {
             let location = args.value_of_os("output").expect("arg has a default value");
             let since = args
@@ -396,6 +872,18 @@ fn run() -> Result<(), Error> {
                 .execute(),
             )
         }
+        ("top", Some(args)) => {
+            let interval = args
+                .value_of("interval")
+                .expect("arg has a default value")
+                .parse::<u64>()
+                .ok()
+                .filter(|interval| *interval > 0)
+                .map(std::time::Duration::from_secs)
+                .ok_or(ErrorKind::BadIntervalParameter)?;
+            let json = args.is_present("json");
+            tokio_runtime.block_on(Top::new(runtime()?, io::stdout(), interval, json).execute())
+        }
         ("version", _) => tokio_runtime.block_on(Version::new().execute()),
         (command, _) => tokio_runtime.block_on(Unknown::new(command.to_string()).execute()),
     }
@@ -420,6 +908,27 @@ fn parse_since(since: &str) -> Result<i32, Error> {
     }
 }
 
+fn tls_config(matches: &ArgMatches<'_>) -> Result<TlsConfig, Error> {
+    let read = |name: &'static str| -> Result<Option<Vec<u8>>, Error> {
+        match matches.value_of_os(name) {
+            Some(path) => Ok(Some(
+                fs::read(path).context(ErrorKind::BadTlsParameter(name))?,
+            )),
+            None => Ok(None),
+        }
+    };
+
+    let mut tls_config = TlsConfig::new();
+    if let Some(ca_cert) = read("tls-ca-cert")? {
+        tls_config = tls_config.with_trust_bundle(PemCertificate::new(ca_cert, None, None, None));
+    }
+    if let (Some(cert), Some(key)) = (read("tls-cert")?, read("tls-key")?) {
+        tls_config =
+            tls_config.with_identity_certificate(PemCertificate::new(cert, Some(key), None, None));
+    }
+    Ok(tls_config)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
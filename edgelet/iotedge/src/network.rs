@@ -0,0 +1,169 @@
+// Copyright (c) Microsoft. All rights reserved.
+
+use std::io::Write;
+use std::sync::{Arc, Mutex};
+
+use failure::{Fail, ResultExt};
+use futures::Future;
+use tabwriter::TabWriter;
+
+use edgelet_core::{ModuleRuntime, Network};
+use edgelet_http_mgmt::ModuleClient;
+
+use crate::error::{Error, ErrorKind};
+use crate::Command;
+
+/// Lists the networks declared in the config file, with the modules attached to each.
+pub struct NetworkList<W> {
+    client: ModuleClient,
+    output: Arc<Mutex<TabWriter<W>>>,
+}
+
+impl<W> NetworkList<W>
+where
+    W: Write,
+{
+    pub fn new(client: ModuleClient, output: W) -> Self {
+        let tab = TabWriter::new(output).minwidth(15);
+        NetworkList {
+            client,
+            output: Arc::new(Mutex::new(tab)),
+        }
+    }
+}
+
+impl<W> Command for NetworkList<W>
+where
+    W: 'static + Write + Send,
+{
+    type Future = Box<dyn Future<Item = (), Error = Error> + Send>;
+
+    fn execute(self) -> Self::Future {
+        let NetworkList { client, output } = self;
+
+        let result = client
+            .list_networks()
+            .map_err(|err| Error::from(err.context(ErrorKind::ListNetworks)))
+            .and_then(move |networks| {
+                let mut w = output.lock().unwrap();
+                writeln!(w, "NAME\tDRIVER\tSUBNETS\tMODULES").context(ErrorKind::WriteToStdout)?;
+                for network in networks {
+                    writeln!(
+                        w,
+                        "{}\t{}\t{}\t{}",
+                        network.name(),
+                        network.driver().unwrap_or("bridge"),
+                        subnets(&network),
+                        network.modules().join(","),
+                    )
+                    .context(ErrorKind::WriteToStdout)?;
+                }
+                w.flush().context(ErrorKind::WriteToStdout)?;
+                Ok(())
+            });
+        Box::new(result)
+    }
+}
+
+fn subnets(network: &Network) -> String {
+    network
+        .ipam()
+        .and_then(|ipam| ipam.config())
+        .unwrap_or_default()
+        .iter()
+        .filter_map(|config| config.subnet())
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+/// Connects a module's container to one of the networks declared in the config file.
+pub struct NetworkAttach<W> {
+    network: String,
+    module: String,
+    client: ModuleClient,
+    output: Arc<Mutex<W>>,
+}
+
+impl<W> NetworkAttach<W> {
+    pub fn new(network: String, module: String, client: ModuleClient, output: W) -> Self {
+        NetworkAttach {
+            network,
+            module,
+            client,
+            output: Arc::new(Mutex::new(output)),
+        }
+    }
+}
+
+impl<W> Command for NetworkAttach<W>
+where
+    W: 'static + Write + Send,
+{
+    type Future = Box<dyn Future<Item = (), Error = Error> + Send>;
+
+    fn execute(self) -> Self::Future {
+        let NetworkAttach {
+            network,
+            module,
+            client,
+            output,
+        } = self;
+
+        let result = client
+            .attach_network(&module, &network)
+            .map_err(|err| Error::from(err.context(ErrorKind::AttachNetwork)))
+            .and_then(move |()| {
+                let mut w = output.lock().unwrap();
+                writeln!(w, "Attached module {} to network {}.", module, network)
+                    .context(ErrorKind::WriteToStdout)?;
+                Ok(())
+            });
+        Box::new(result)
+    }
+}
+
+/// Disconnects a module's container from one of the networks declared in the config file.
+pub struct NetworkDetach<W> {
+    network: String,
+    module: String,
+    client: ModuleClient,
+    output: Arc<Mutex<W>>,
+}
+
+impl<W> NetworkDetach<W> {
+    pub fn new(network: String, module: String, client: ModuleClient, output: W) -> Self {
+        NetworkDetach {
+            network,
+            module,
+            client,
+            output: Arc::new(Mutex::new(output)),
+        }
+    }
+}
+
+impl<W> Command for NetworkDetach<W>
+where
+    W: 'static + Write + Send,
+{
+    type Future = Box<dyn Future<Item = (), Error = Error> + Send>;
+
+    fn execute(self) -> Self::Future {
+        let NetworkDetach {
+            network,
+            module,
+            client,
+            output,
+        } = self;
+
+        let result = client
+            .detach_network(&module, &network)
+            .map_err(|err| Error::from(err.context(ErrorKind::DetachNetwork)))
+            .and_then(move |()| {
+                let mut w = output.lock().unwrap();
+                writeln!(w, "Detached module {} from network {}.", module, network)
+                    .context(ErrorKind::WriteToStdout)?;
+                Ok(())
+            });
+        Box::new(result)
+    }
+}
@@ -0,0 +1,116 @@
+// Copyright (c) Microsoft. All rights reserved.
+
+use std::io::Write;
+use std::sync::{Arc, Mutex};
+
+use failure::{Fail, ResultExt};
+use futures::{future, Future};
+
+use edgelet_core::ModuleRegistry;
+use edgelet_http_mgmt::ModuleClient;
+
+use crate::error::{Error, ErrorKind};
+use crate::Command;
+
+/// Stores the credentials that the daemon pulls images from a registry with, for modules whose
+/// config has no auth.
+pub struct RegistryLogin<W> {
+    registry: String,
+    username: String,
+    password: String,
+    client: ModuleClient,
+    output: Arc<Mutex<W>>,
+}
+
+impl<W> RegistryLogin<W> {
+    pub fn new(
+        registry: String,
+        username: String,
+        password: String,
+        client: ModuleClient,
+        output: W,
+    ) -> Self {
+        RegistryLogin {
+            registry,
+            username,
+            password,
+            client,
+            output: Arc::new(Mutex::new(output)),
+        }
+    }
+}
+
+impl<W> Command for RegistryLogin<W>
+where
+    W: 'static + Write + Send,
+{
+    type Future = Box<dyn Future<Item = (), Error = Error> + Send>;
+
+    fn execute(self) -> Self::Future {
+        let RegistryLogin {
+            registry,
+            username,
+            password,
+            client,
+            output,
+        } = self;
+
+        if password.is_empty() {
+            return Box::new(future::err(Error::from(ErrorKind::BadPasswordParameter)));
+        }
+
+        let result = client
+            .login(&registry, &username, &password)
+            .map_err(|err| Error::from(err.context(ErrorKind::RegistryLogin)))
+            .and_then(move |()| {
+                let mut w = output.lock().unwrap();
+                writeln!(w, "Stored the credentials for registry {}.", registry)
+                    .context(ErrorKind::WriteToStdout)?;
+                Ok(())
+            });
+        Box::new(result)
+    }
+}
+
+/// Removes the credentials that were stored for a registry by `iotedge registry login`.
+pub struct RegistryLogout<W> {
+    registry: String,
+    client: ModuleClient,
+    output: Arc<Mutex<W>>,
+}
+
+impl<W> RegistryLogout<W> {
+    pub fn new(registry: String, client: ModuleClient, output: W) -> Self {
+        RegistryLogout {
+            registry,
+            client,
+            output: Arc::new(Mutex::new(output)),
+        }
+    }
+}
+
+impl<W> Command for RegistryLogout<W>
+where
+    W: 'static + Write + Send,
+{
+    type Future = Box<dyn Future<Item = (), Error = Error> + Send>;
+
+    fn execute(self) -> Self::Future {
+        let RegistryLogout {
+            registry,
+            client,
+            output,
+        } = self;
+
+        let result = client
+            .logout(&registry)
+            .map_err(|err| Error::from(err.context(ErrorKind::RegistryLogout)))
+            .and_then(move |()| {
+                let mut w = output.lock().unwrap();
+                writeln!(w, "Removed the credentials for registry {}.", registry)
+                    .context(ErrorKind::WriteToStdout)?;
+                Ok(())
+            });
+        Box::new(result)
+    }
+}
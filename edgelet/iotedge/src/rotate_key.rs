@@ -0,0 +1,65 @@
+// Copyright (c) Microsoft. All rights reserved.
+
+use std::io::Write;
+use std::sync::{Arc, Mutex};
+
+use failure::{Fail, ResultExt};
+use futures::{future, Future};
+
+use edgelet_http_mgmt::ModuleClient;
+
+use crate::error::{Error, ErrorKind};
+use crate::Command;
+
+/// Replaces the device key that the daemon was provisioned with, for devices that use a connection
+/// string or a DPS symmetric key.
+pub struct RotateKey<W> {
+    primary_key: String,
+    client: ModuleClient,
+    output: Arc<Mutex<W>>,
+}
+
+impl<W> RotateKey<W> {
+    pub fn new(primary_key: String, client: ModuleClient, output: W) -> Self {
+        RotateKey {
+            primary_key,
+            client,
+            output: Arc::new(Mutex::new(output)),
+        }
+    }
+}
+
+impl<W> Command for RotateKey<W>
+where
+    W: 'static + Write + Send,
+{
+    type Future = Box<dyn Future<Item = (), Error = Error> + Send>;
+
+    fn execute(self) -> Self::Future {
+        let RotateKey {
+            primary_key,
+            client,
+            output,
+        } = self;
+
+        let primary_key = primary_key.trim().to_string();
+        match base64::decode(&primary_key) {
+            Ok(ref key) if !key.is_empty() => (),
+            _ => return Box::new(future::err(Error::from(ErrorKind::BadKeyParameter))),
+        }
+
+        let result = client
+            .rotate_device_key(primary_key)
+            .map_err(|err| Error::from(err.context(ErrorKind::RotateKey)))
+            .and_then(move |()| {
+                let mut w = output.lock().unwrap();
+                writeln!(
+                    w,
+                    "The device key was rotated. The IoT Edge service restarts to start using it."
+                )
+                .context(ErrorKind::WriteToStdout)?;
+                Ok(())
+            });
+        Box::new(result)
+    }
+}
@@ -0,0 +1,331 @@
+// Copyright (c) Microsoft. All rights reserved.
+
+use std::collections::BTreeMap;
+use std::io::Write;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use chrono::{DateTime, Utc};
+use failure::{Fail, ResultExt};
+use futures::{Future, Stream};
+use tabwriter::TabWriter;
+use tokio::timer::Interval;
+
+use edgelet_core::{Module, ModuleRuntime, ModuleRuntimeState, SystemResources};
+
+use crate::error::{Error, ErrorKind};
+use crate::Command;
+
+pub struct Top<M, W> {
+    runtime: M,
+    output: Arc<Mutex<W>>,
+    interval: Duration,
+    json: bool,
+}
+
+impl<M, W> Top<M, W> {
+    pub fn new(runtime: M, output: W, interval: Duration, json: bool) -> Self {
+        Top {
+            runtime,
+            output: Arc::new(Mutex::new(output)),
+            interval,
+            json,
+        }
+    }
+}
+
+impl<M, W> Command for Top<M, W>
+where
+    M: 'static + ModuleRuntime + Clone + Send,
+    W: 'static + Write + Send,
+{
+    type Future = Box<dyn Future<Item = (), Error = Error> + Send>;
+
+    fn execute(self) -> Self::Future {
+        let Top {
+            runtime,
+            output,
+            interval,
+            json,
+        } = self;
+
+        if json {
+            let result = snapshot(&runtime).and_then(move |snapshot| {
+                let mut w = output.lock().unwrap();
+                serde_json::to_writer(&mut *w, &snapshot).context(ErrorKind::WriteToStdout)?;
+                writeln!(w).context(ErrorKind::WriteToStdout)?;
+                Ok(())
+            });
+            return Box::new(result);
+        }
+
+        let result = Interval::new(Instant::now(), interval)
+            .map_err(|err| Error::from(err.context(ErrorKind::Top)))
+            .for_each(move |_| {
+                let output = output.clone();
+
+                snapshot(&runtime).and_then(move |snapshot| {
+                    let mut w = output.lock().unwrap();
+                    render(&mut *w, &snapshot).context(ErrorKind::WriteToStdout)?;
+                    Ok(())
+                })
+            });
+        Box::new(result)
+    }
+}
+
+#[derive(Debug, serde_derive::Serialize)]
+struct Snapshot {
+    host: HostUsage,
+    modules: Vec<ModuleUsage>,
+}
+
+#[derive(Debug, serde_derive::Serialize)]
+struct HostUsage {
+    uptime: u64,
+    cpu_percent: f64,
+    memory_usage: u64,
+    memory_total: u64,
+}
+
+#[derive(Debug, serde_derive::Serialize)]
+struct ModuleUsage {
+    name: String,
+    status: String,
+    started_at: Option<DateTime<Utc>>,
+    cpu_percent: Option<f64>,
+    memory_usage: Option<u64>,
+    memory_limit: Option<u64>,
+    network_rx: u64,
+    network_tx: u64,
+    block_read: u64,
+    block_write: u64,
+    pids: Option<u64>,
+    restarts: Option<u32>,
+}
+
+fn snapshot<M>(runtime: &M) -> impl Future<Item = Snapshot, Error = Error> + Send
+where
+    M: 'static + ModuleRuntime,
+{
+    let modules = runtime
+        .list_with_details()
+        .map_err(|err| Error::from(err.context(ErrorKind::ModuleRuntime)))
+        .collect();
+    let system_resources = runtime
+        .system_resources()
+        .map_err(|err| Error::from(err.context(ErrorKind::ModuleRuntime)));
+
+    modules
+        .join(system_resources)
+        .map(|(modules, system_resources)| make_snapshot(&modules, &system_resources))
+}
+
+fn make_snapshot<Mod>(
+    modules: &[(Mod, ModuleRuntimeState)],
+    system_resources: &SystemResources,
+) -> Snapshot
+where
+    Mod: Module,
+{
+    let usages: BTreeMap<_, _> = system_resources
+        .modules()
+        .iter()
+        .map(|usage| (usage.name(), usage))
+        .collect();
+
+    let mut modules: Vec<_> = modules
+        .iter()
+        .map(|(module, state)| {
+            let usage = usages.get(module.name());
+
+            ModuleUsage {
+                name: module.name().to_owned(),
+                status: state.status().to_string(),
+                started_at: state.started_at().copied(),
+                cpu_percent: usage.and_then(|usage| usage.cpu_percent()),
+                memory_usage: usage.and_then(|usage| usage.memory_usage()),
+                memory_limit: usage.and_then(|usage| usage.memory_limit()),
+                network_rx: usage.map_or(0, |usage| usage.network_rx_bytes()),
+                network_tx: usage.map_or(0, |usage| usage.network_tx_bytes()),
+                block_read: usage.map_or(0, |usage| usage.block_read_bytes()),
+                block_write: usage.map_or(0, |usage| usage.block_write_bytes()),
+                pids: usage.and_then(|usage| usage.pids()),
+                restarts: state.restart_count(),
+            }
+        })
+        .collect();
+    modules.sort_by(|module1, module2| module1.name.cmp(&module2.name));
+
+    Snapshot {
+        host: HostUsage {
+            uptime: system_resources.host_uptime(),
+            cpu_percent: system_resources.used_cpu(),
+            memory_usage: system_resources.used_ram(),
+            memory_total: system_resources.total_ram(),
+        },
+        modules,
+    }
+}
+
+fn render(w: &mut impl Write, snapshot: &Snapshot) -> std::io::Result<()> {
+    // Clear the screen and move the cursor to the top-left corner
+    write!(w, "\x1b[2J\x1b[H")?;
+
+    writeln!(
+        w,
+        "CPU: {:.2}%  MEM: {} / {}  UPTIME: {}s",
+        snapshot.host.cpu_percent,
+        human_bytes(snapshot.host.memory_usage),
+        human_bytes(snapshot.host.memory_total),
+        snapshot.host.uptime,
+    )?;
+    writeln!(w)?;
+
+    let mut tw = TabWriter::new(&mut *w).minwidth(10);
+    writeln!(
+        tw,
+        "NAME\tSTATUS\tCPU %\tMEM USAGE / LIMIT\tNET I/O\tBLOCK I/O\tPIDS\tRESTARTS"
+    )?;
+    for module in &snapshot.modules {
+        writeln!(
+            tw,
+            "{}\t{}\t{}\t{} / {}\t{} / {}\t{} / {}\t{}\t{}",
+            module.name,
+            module.status,
+            module
+                .cpu_percent
+                .map_or_else(|| "--".to_owned(), |cpu| format!("{:.2}%", cpu)),
+            module
+                .memory_usage
+                .map_or_else(|| "--".to_owned(), human_bytes),
+            module
+                .memory_limit
+                .map_or_else(|| "--".to_owned(), human_bytes),
+            human_bytes(module.network_rx),
+            human_bytes(module.network_tx),
+            human_bytes(module.block_read),
+            human_bytes(module.block_write),
+            module
+                .pids
+                .map_or_else(|| "--".to_owned(), |pids| pids.to_string()),
+            module
+                .restarts
+                .map_or_else(|| "--".to_owned(), |restarts| restarts.to_string()),
+        )?;
+    }
+    tw.flush()?;
+
+    Ok(())
+}
+
+#[allow(clippy::cast_precision_loss)]
+fn human_bytes(bytes: u64) -> String {
+    const UNITS: &[&str] = &["KiB", "MiB", "GiB", "TiB"];
+
+    if bytes < 1024 {
+        return format!("{}B", bytes);
+    }
+
+    let mut value = bytes as f64 / 1024.0;
+    let mut unit = UNITS[0];
+    for next_unit in &UNITS[1..] {
+        if value < 1024.0 {
+            break;
+        }
+        value /= 1024.0;
+        unit = next_unit;
+    }
+
+    format!("{:.1}{}", value, unit)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use edgelet_core::{DiskInfo, ModuleResourceUsage, ModuleStatus};
+    use edgelet_test_utils::module::{TestConfig, TestModule};
+
+    #[allow(dead_code)]
+    #[derive(Clone, Copy, Debug, Fail)]
+    pub enum Error {
+        #[fail(display = "General error")]
+        General,
+    }
+
+    #[test]
+    fn human_bytes_formats_units() {
+        assert_eq!("0B", human_bytes(0));
+        assert_eq!("1023B", human_bytes(1023));
+        assert_eq!("1.0KiB", human_bytes(1024));
+        assert_eq!("1.5MiB", human_bytes(1024 * 1024 * 3 / 2));
+        assert_eq!("2.0GiB", human_bytes(2 * 1024 * 1024 * 1024));
+    }
+
+    #[test]
+    fn snapshot_joins_stats_by_module_name() {
+        let modules = vec![
+            (
+                TestModule::<Error, _>::new(
+                    "tempSensor".to_owned(),
+                    TestConfig::new("microsoft/test-image".to_owned()),
+                    Ok(ModuleRuntimeState::default().with_status(ModuleStatus::Running)),
+                ),
+                ModuleRuntimeState::default()
+                    .with_status(ModuleStatus::Running)
+                    .with_restart_count(Some(3)),
+            ),
+            (
+                TestModule::new(
+                    "edgeAgent".to_owned(),
+                    TestConfig::new("microsoft/test-image".to_owned()),
+                    Ok(ModuleRuntimeState::default().with_status(ModuleStatus::Stopped)),
+                ),
+                ModuleRuntimeState::default().with_status(ModuleStatus::Stopped),
+            ),
+        ];
+        let system_resources = SystemResources::new(
+            100,
+            10,
+            12.5,
+            512,
+            1024,
+            vec![DiskInfo::new(
+                "sda".to_owned(),
+                1,
+                2,
+                "ext4".to_owned(),
+                "SSD".to_owned(),
+            )],
+            vec![ModuleResourceUsage::new("tempSensor".to_owned())
+                .with_memory_usage(Some(2048))
+                .with_memory_limit(Some(4096))
+                .with_network_io(10, 20)
+                .with_pids(Some(4))],
+        );
+
+        let snapshot = make_snapshot(&modules, &system_resources);
+
+        assert_eq!(512, snapshot.host.memory_usage);
+        assert_eq!(1024, snapshot.host.memory_total);
+
+        assert_eq!(2, snapshot.modules.len());
+
+        let edge_agent = &snapshot.modules[0];
+        assert_eq!("edgeAgent", edge_agent.name);
+        assert_eq!("stopped", edge_agent.status);
+        assert_eq!(None, edge_agent.memory_usage);
+        assert_eq!(None, edge_agent.pids);
+
+        let temp_sensor = &snapshot.modules[1];
+        assert_eq!("tempSensor", temp_sensor.name);
+        assert_eq!("running", temp_sensor.status);
+        assert_eq!(Some(2048), temp_sensor.memory_usage);
+        assert_eq!(Some(4096), temp_sensor.memory_limit);
+        assert_eq!((10, 20), (temp_sensor.network_rx, temp_sensor.network_tx));
+        assert_eq!(Some(4), temp_sensor.pids);
+        assert_eq!(Some(3), temp_sensor.restarts);
+        assert_eq!(None, edge_agent.restarts);
+    }
+}
@@ -49,7 +49,7 @@ fn create_app(default_config_file: &OsStr) -> App<'_, '_> {
     }
 }
 
-fn init_common(running_as_windows_service: bool) -> Result<Settings, Error> {
+fn init_common(running_as_windows_service: bool) -> Result<(Settings, PathBuf), Error> {
     let default_config_file = if cfg!(windows) {
         let program_data: PathBuf =
             std::env::var_os("PROGRAMDATA").map_or_else(|| r"C:\ProgramData".into(), Into::into);
@@ -92,15 +92,15 @@ fn init_common(running_as_windows_service: bool) -> Result<Settings, Error> {
     let settings = Settings::new(&config_file)
         .context(ErrorKind::Initialize(InitializeErrorReason::LoadSettings))?;
 
-    Ok(settings)
+    Ok((settings, config_file))
 }
 
-pub fn init() -> Result<Settings, Error> {
+pub fn init() -> Result<(Settings, PathBuf), Error> {
     init_common(false)
 }
 
 #[cfg(windows)]
-pub fn init_win_svc() -> Result<Settings, Error> {
+pub fn init_win_svc() -> Result<(Settings, PathBuf), Error> {
     init_common(true)
 }
 
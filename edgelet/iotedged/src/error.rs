@@ -31,6 +31,12 @@ pub enum ErrorKind {
     #[fail(display = "The device has been de-provisioned")]
     DeviceDeprovisioned,
 
+    #[fail(display = "The device key was rotated")]
+    DeviceKeyRotated,
+
+    #[fail(display = "The device failed over to IoT hub {}", _0)]
+    HubFailover(String),
+
     #[fail(display = "The daemon could not start up successfully: {}", _0)]
     Initialize(InitializeErrorReason),
 
@@ -43,6 +49,12 @@ pub enum ErrorKind {
     #[fail(display = "The reprovisioning operation failed")]
     ReprovisionFailure,
 
+    #[fail(display = "Could not rotate the device key: {}", _0)]
+    RotateDeviceKey(RotateDeviceKeyReason),
+
+    #[fail(display = "Could not record the active IoT hub")]
+    SaveActiveHub,
+
     #[fail(display = "The symmetric key string is malformed")]
     SymmetricKeyMalformed,
 
@@ -152,6 +164,8 @@ impl From<&ErrorKind> for i32 {
             ErrorKind::InvalidSignedToken => 152,
             ErrorKind::Initialize(InitializeErrorReason::LoadSettings) => 153,
             ErrorKind::DeviceDeprovisioned => 154,
+            ErrorKind::DeviceKeyRotated => 155,
+            ErrorKind::HubFailover(_) => 156,
             _ => 1,
         }
     }
@@ -186,9 +200,13 @@ pub enum InitializeErrorReason {
     InvalidProxyUri,
     IssuerCAExpiration,
     LoadSettings,
+    ManagementClientCaMissing,
     ManagementService,
     ManualProvisioningClient,
+    MetricsService,
     ModuleRuntime,
+    ParentTrustBundle,
+    Pkcs11ModulePath,
     PrepareWorkloadCa,
     #[cfg(windows)]
     RegisterWindowsService,
@@ -316,6 +334,11 @@ impl fmt::Display for InitializeErrorReason {
 
             InitializeErrorReason::LoadSettings => write!(f, "Could not load settings"),
 
+            InitializeErrorReason::ManagementClientCaMissing => write!(
+                f,
+                "The management API is served over HTTPS, but listen.management_client_ca is not set"
+            ),
+
             InitializeErrorReason::ManagementService => {
                 write!(f, "Could not start management service")
             }
@@ -324,10 +347,23 @@ impl fmt::Display for InitializeErrorReason {
                 write!(f, "Could not initialize manual provisioning client")
             }
 
+            InitializeErrorReason::MetricsService => write!(f, "Could not start metrics service"),
+
             InitializeErrorReason::ModuleRuntime => {
                 write!(f, "Could not initialize module runtime")
             }
 
+            InitializeErrorReason::ParentTrustBundle => write!(
+                f,
+                "Could not load the trusted CA certificates for the connection to the parent device"
+            ),
+
+            InitializeErrorReason::Pkcs11ModulePath => write!(
+                f,
+                "The private keys on PKCS#11 tokens have different module-path values. \
+                 All of them must use the same PKCS#11 module."
+            ),
+
             InitializeErrorReason::PrepareWorkloadCa => {
                 write!(f, "Could not prepare workload CA certificate")
             }
@@ -404,6 +440,68 @@ impl fmt::Display for ExternalProvisioningErrorReason {
     }
 }
 
+#[derive(Clone, Debug, PartialEq)]
+pub enum RotateDeviceKeyReason {
+    ConfigFile,
+    DeviceClient,
+    DpsAssignmentChanged,
+    DpsProvisioning,
+    InProgress,
+    ListIdentities,
+    SameKey,
+    SaveSettings,
+    UnsupportedProvisioning,
+    UpdateIdentity(String),
+}
+
+impl fmt::Display for RotateDeviceKeyReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RotateDeviceKeyReason::ConfigFile => {
+                write!(f, "Could not update the key in the configuration file.")
+            }
+
+            RotateDeviceKeyReason::DeviceClient => {
+                write!(f, "Could not create an IoT Hub client with the new key.")
+            }
+
+            RotateDeviceKeyReason::DpsAssignmentChanged => write!(
+                f,
+                "DPS assigned the device to a different IoT hub or device ID. Restart the daemon to reprovision the device."
+            ),
+
+            RotateDeviceKeyReason::DpsProvisioning => {
+                write!(f, "Could not register with DPS using the new key.")
+            }
+
+            RotateDeviceKeyReason::InProgress => {
+                write!(f, "The device key is already being rotated.")
+            }
+
+            RotateDeviceKeyReason::ListIdentities => {
+                write!(f, "Could not list the module identities.")
+            }
+
+            RotateDeviceKeyReason::SameKey => {
+                write!(f, "The new key is the same as the current key.")
+            }
+
+            RotateDeviceKeyReason::SaveSettings => {
+                write!(f, "Could not save the settings state.")
+            }
+
+            RotateDeviceKeyReason::UnsupportedProvisioning => write!(
+                f,
+                "Only devices provisioned with a connection string or a DPS symmetric key can rotate their key."
+            ),
+
+            RotateDeviceKeyReason::UpdateIdentity(module_id) => {
+                write!(f, "Could not update the identity of module {}.", module_id)
+            }
+        }
+    }
+}
+
 // The use of the Mutex below is an artifact of trying to unify 2 different error
 // handling crates. `windows_service` uses `error_chain` and we use `failure`.
 // `error_chain`'s error type does not implement `Sync` unfortunately (they have
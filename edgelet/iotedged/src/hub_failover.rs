@@ -0,0 +1,156 @@
+// Copyright (c) Microsoft. All rights reserved.
+
+//! Failover between the IoT hubs of a device that is provisioned manually with fallback hubs.
+//!
+//! The device is registered in every hub of the list. The daemon connects to the hub that is
+//! recorded as active, or to the primary hub if none is. When identity operations can't reach
+//! that hub several times in a row, the next hub of the list is recorded as active, edgeAgent is
+//! removed so that it is created again with the hostname of that hub, and the daemon restarts.
+
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
+use failure::ResultExt;
+use log::{info, warn};
+
+use edgelet_core::{Manual, ManualAuthMethod};
+
+use crate::error::{Error, ErrorKind, InitializeErrorReason};
+
+pub(crate) struct HubFailover {
+    path: PathBuf,
+    hubs: Vec<(String, ManualAuthMethod)>,
+    active: usize,
+}
+
+impl HubFailover {
+    /// `path` is the file that records the hostname of the active hub.
+    pub(crate) fn new(path: PathBuf, manual: &Manual) -> Result<Self, Error> {
+        let hubs = manual
+            .authentication_methods()
+            .map(|auth| Ok((hub_hostname(auth)?, auth.clone())))
+            .collect::<Result<Vec<_>, Error>>()?;
+
+        let active = match fs::read_to_string(&path) {
+            Ok(hostname) => hubs
+                .iter()
+                .position(|(hub, _)| hub == hostname.trim())
+                .unwrap_or(0),
+            Err(ref err) if err.kind() == io::ErrorKind::NotFound => 0,
+            Err(err) => {
+                warn!(
+                    "Could not read the active IoT hub, connecting to the primary hub: {}",
+                    err
+                );
+                0
+            }
+        };
+
+        Ok(HubFailover { path, hubs, active })
+    }
+
+    pub(crate) fn has_fallbacks(&self) -> bool {
+        self.hubs.len() > 1
+    }
+
+    pub(crate) fn active_hostname(&self) -> &str {
+        &self.hubs[self.active].0
+    }
+
+    pub(crate) fn active_authentication_method(&self) -> &ManualAuthMethod {
+        &self.hubs[self.active].1
+    }
+
+    /// Records the hub after the active one as active, wrapping around to the primary hub,
+    /// and returns its hostname.
+    pub(crate) fn fail_over(&self) -> Result<&str, Error> {
+        let next = &self.hubs[(self.active + 1) % self.hubs.len()].0;
+        fs::write(&self.path, next).context(ErrorKind::SaveActiveHub)?;
+        info!(
+            "Failing over from IoT hub {} to IoT hub {}",
+            self.active_hostname(),
+            next
+        );
+        Ok(next)
+    }
+}
+
+fn hub_hostname(auth: &ManualAuthMethod) -> Result<String, Error> {
+    match auth {
+        ManualAuthMethod::DeviceConnectionString(cs) => {
+            let (_, _, hub) = cs
+                .parse_device_connection_string()
+                .context(ErrorKind::Initialize(InitializeErrorReason::LoadSettings))?;
+            Ok(hub)
+        }
+        ManualAuthMethod::X509(x509) => Ok(x509.iothub_hostname().to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tempdir::TempDir;
+
+    use edgelet_core::ManualDeviceConnectionString;
+
+    use super::*;
+
+    fn manual() -> Manual {
+        serde_json::from_value(serde_json::json!({
+            "device_connection_string": "HostName=primary.azure-devices.net;DeviceId=d1;SharedAccessKey=QXp1cmUgSW9UIEVkZ2U=",
+            "fallback_authentication": [
+                {
+                    "method": "device_connection_string",
+                    "device_connection_string": "HostName=secondary.azure-devices.net;DeviceId=d1;SharedAccessKey=QXp1cmUgSW9UIEVkZ2U=",
+                },
+            ],
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn fails_over_to_next_hub_and_back() {
+        let dir = TempDir::new("hub_failover").unwrap();
+        let path = dir.path().join("active_hub");
+
+        let failover = HubFailover::new(path.clone(), &manual()).unwrap();
+        assert!(failover.has_fallbacks());
+        assert_eq!("primary.azure-devices.net", failover.active_hostname());
+        assert_eq!("secondary.azure-devices.net", failover.fail_over().unwrap());
+
+        let failover = HubFailover::new(path.clone(), &manual()).unwrap();
+        assert_eq!("secondary.azure-devices.net", failover.active_hostname());
+        match failover.active_authentication_method() {
+            ManualAuthMethod::DeviceConnectionString(cs) => assert!(cs
+                .device_connection_string()
+                .starts_with("HostName=secondary.azure-devices.net;")),
+            ManualAuthMethod::X509(_) => panic!("unexpected authentication method"),
+        }
+        assert_eq!("primary.azure-devices.net", failover.fail_over().unwrap());
+    }
+
+    #[test]
+    fn unknown_active_hub_is_primary_hub() {
+        let dir = TempDir::new("hub_failover").unwrap();
+        let path = dir.path().join("active_hub");
+        fs::write(&path, "removed.azure-devices.net").unwrap();
+
+        let failover = HubFailover::new(path, &manual()).unwrap();
+        assert_eq!("primary.azure-devices.net", failover.active_hostname());
+    }
+
+    #[test]
+    fn single_hub_has_no_fallbacks() {
+        let dir = TempDir::new("hub_failover").unwrap();
+        let manual = Manual::new(ManualAuthMethod::DeviceConnectionString(
+            ManualDeviceConnectionString::new(
+                "HostName=primary.azure-devices.net;DeviceId=d1;SharedAccessKey=QXp1cmUgSW9UIEVkZ2U="
+                    .to_string(),
+            ),
+        ));
+
+        let failover = HubFailover::new(dir.path().join("active_hub"), &manual).unwrap();
+        assert!(!failover.has_fallbacks());
+    }
+}
@@ -0,0 +1,605 @@
+// Copyright (c) Microsoft. All rights reserved.
+
+//! Rotation of the device key of devices that are provisioned with a connection string or a DPS
+//! symmetric key.
+//!
+//! Module keys are derived from the device key, so every module identity in IoT Hub is updated with
+//! keys derived from the new one. Then the key is replaced in the configuration file, the settings
+//! state is updated to match, and the daemon restarts to start using the new key. If a step fails,
+//! the steps before it are undone and the device keeps using its current key.
+
+use std::fs::{self, File};
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+use failure::{Fail, ResultExt};
+use futures::future::{self, Either, Loop};
+use futures::sync::mpsc::UnboundedSender;
+use futures::{Future, IntoFuture};
+use log::{info, warn};
+use serde::Serialize;
+use url::Url;
+
+use dps::DPS_API_VERSION;
+use edgelet_core::crypto::{
+    Activate, DerivedKeyStore, KeyIdentity, MemoryKey, MemoryKeyStore, Sign,
+};
+use edgelet_core::{
+    AttestationMethod, AuthType, DeviceKeyRotation, Dps, Identity, IdentityManager, IdentitySpec,
+    ManualAuthMethod, ProvisioningResult as CoreProvisioningResult, ProvisioningType,
+    RuntimeSettings, SymmetricKeyAttestationInfo,
+};
+use edgelet_http::client::{Client as HttpClient, ClientImpl};
+use edgelet_iothub::{HubIdentityManager, SasTokenSource};
+use iothubservice::DeviceClient;
+use provisioning::provisioning::{DpsSymmetricKeyProvisioning, Provision};
+
+use crate::error::{Error, ErrorKind, RotateDeviceKeyReason};
+use crate::{digest_serialized_settings, EDGE_RUNTIME_MODULEID, IOTHUB_API_VERSION};
+
+/// The `managedBy` value of the module identities that edgeAgent creates
+const EDGE_MANAGED_BY: &str = "IotEdge";
+
+pub(crate) struct KeyRotation<HC, K, S> {
+    inner: Arc<Inner<HC, K, S>>,
+}
+
+struct Inner<HC, K, S> {
+    settings: S,
+    config_file: Option<PathBuf>,
+    settings_state_path: PathBuf,
+    hyper_client: HC,
+    hub_name: String,
+    device_id: String,
+    hub_url: Url,
+    key_store: DerivedKeyStore<K>,
+    in_progress: AtomicBool,
+    rotated: UnboundedSender<()>,
+}
+
+/// The files that are rewritten once the module identities use the new key
+struct PendingFiles {
+    config_file: PathBuf,
+    old_config: String,
+    new_config: String,
+    settings_state_path: PathBuf,
+    settings_digest: String,
+}
+
+impl<HC, K, S> KeyRotation<HC, K, S>
+where
+    HC: 'static + ClientImpl + Clone,
+    K: 'static + Sign + Clone + Send + Sync,
+    S: 'static + RuntimeSettings + Serialize + Send + Sync,
+{
+    /// `rotated` is signaled once the rotation is complete and the daemon should restart.
+    #[allow(clippy::too_many_arguments)]
+    pub(crate) fn new(
+        settings: S,
+        config_file: Option<PathBuf>,
+        settings_state_path: PathBuf,
+        hyper_client: HC,
+        hub_name: String,
+        device_id: String,
+        hub_url: Url,
+        key_store: DerivedKeyStore<K>,
+        rotated: UnboundedSender<()>,
+    ) -> Self {
+        KeyRotation {
+            inner: Arc::new(Inner {
+                settings,
+                config_file,
+                settings_state_path,
+                hyper_client,
+                hub_name,
+                device_id,
+                hub_url,
+                key_store,
+                in_progress: AtomicBool::new(false),
+                rotated,
+            }),
+        }
+    }
+}
+
+impl<HC, K, S> Inner<HC, K, S>
+where
+    HC: 'static + ClientImpl + Clone,
+    K: 'static + Sign + Clone + Send + Sync,
+    S: 'static + RuntimeSettings + Serialize + Send + Sync,
+{
+    /// Prepares the new contents of the files up front, so that nothing is changed in IoT Hub if
+    /// they can't be updated.
+    fn prepare_files(&self, primary_key: &[u8]) -> Result<PendingFiles, Error> {
+        let (old_secret, new_secret) = device_secret(&self.settings, &self.hub_name, primary_key)?;
+        if old_secret == new_secret {
+            return Err(Error::from(ErrorKind::RotateDeviceKey(
+                RotateDeviceKeyReason::SameKey,
+            )));
+        }
+
+        let config_file = self.config_file.clone().ok_or_else(|| {
+            ErrorKind::RotateDeviceKey(RotateDeviceKeyReason::UnsupportedProvisioning)
+        })?;
+        let old_config = fs::read_to_string(&config_file).context(ErrorKind::RotateDeviceKey(
+            RotateDeviceKeyReason::ConfigFile,
+        ))?;
+        // The key is swapped in the text of the file so that its comments and formatting are kept.
+        // If the key isn't there exactly once, the file has been edited since the daemon started.
+        let new_config = replace_once(&old_config, &old_secret, &new_secret)
+            .ok_or_else(|| ErrorKind::RotateDeviceKey(RotateDeviceKeyReason::ConfigFile))?;
+
+        let settings_digest = rotated_settings_digest(&self.settings, &old_secret, &new_secret)?;
+
+        Ok(PendingFiles {
+            config_file,
+            old_config,
+            new_config,
+            settings_state_path: self.settings_state_path.clone(),
+            settings_digest,
+        })
+    }
+
+    /// With DPS, IoT Hub only learns the new key when the device registers with it again.
+    fn register_with_dps(
+        &self,
+        primary_key: &[u8],
+    ) -> impl Future<Item = (), Error = Error> + Send {
+        let (dps, symmetric_key) = match self.settings.provisioning().provisioning_type() {
+            ProvisioningType::Dps(dps) => match dps.attestation() {
+                AttestationMethod::SymmetricKey(symmetric_key) => (dps, symmetric_key),
+                _ => return Either::A(future::ok(())),
+            },
+            _ => return Either::A(future::ok(())),
+        };
+
+        let registration =
+            dps_provisioning(self.hyper_client.clone(), dps, symmetric_key, primary_key)
+                .into_future()
+                .and_then(|(memory_hsm, dps)| {
+                    dps.provision(memory_hsm).map_err(|err| {
+                        Error::from(err.context(ErrorKind::RotateDeviceKey(
+                            RotateDeviceKeyReason::DpsProvisioning,
+                        )))
+                    })
+                });
+
+        let hub_name = self.hub_name.clone();
+        let device_id = self.device_id.clone();
+        Either::B(registration.and_then(move |prov_result| {
+            if prov_result.hub_name() == hub_name && prov_result.device_id() == device_id {
+                Ok(())
+            } else {
+                Err(Error::from(ErrorKind::RotateDeviceKey(
+                    RotateDeviceKeyReason::DpsAssignmentChanged,
+                )))
+            }
+        }))
+    }
+
+    fn device_client(
+        &self,
+        primary_key: &[u8],
+    ) -> Result<DeviceClient<HC, SasTokenSource<MemoryKey>>, Error> {
+        let token_source = SasTokenSource::new(
+            self.hub_name.clone(),
+            self.device_id.clone(),
+            MemoryKey::new(primary_key),
+        );
+        let http_client = HttpClient::new(
+            self.hyper_client.clone(),
+            Some(token_source),
+            IOTHUB_API_VERSION.to_string(),
+            self.hub_url.clone(),
+        )
+        .context(ErrorKind::RotateDeviceKey(
+            RotateDeviceKeyReason::DeviceClient,
+        ))?;
+        let device_client = DeviceClient::new(http_client, self.device_id.clone()).context(
+            ErrorKind::RotateDeviceKey(RotateDeviceKeyReason::DeviceClient),
+        )?;
+        Ok(device_client)
+    }
+
+    fn rotate(&self, primary_key: Vec<u8>) -> impl Future<Item = (), Error = Error> + Send {
+        let prepared = self.prepare_files(&primary_key).and_then(|files| {
+            let device_client = self.device_client(&primary_key)?;
+            Ok((files, device_client))
+        });
+        let (files, device_client) = match prepared {
+            Ok(prepared) => prepared,
+            Err(err) => return Either::A(future::err(err)),
+        };
+
+        // Both identity managers authenticate with the new key, since IoT Hub might not accept the
+        // current key anymore. The current one is only used to derive module keys for a rollback.
+        let new_id_man = HubIdentityManager::new(
+            DerivedKeyStore::new(MemoryKey::new(primary_key.clone())),
+            device_client.clone(),
+        );
+        let current_id_man = HubIdentityManager::new(self.key_store.clone(), device_client);
+
+        let rotation = self
+            .register_with_dps(&primary_key)
+            .and_then({
+                let new_id_man = new_id_man.clone();
+                move |()| {
+                    new_id_man.list().map_err(|err| {
+                        Error::from(err.context(ErrorKind::RotateDeviceKey(
+                            RotateDeviceKeyReason::ListIdentities,
+                        )))
+                    })
+                }
+            })
+            .and_then(move |identities| {
+                let identities = identities_to_rotate(&identities);
+                info!(
+                    "Updating {} module identities with keys derived from the new device key...",
+                    identities.len()
+                );
+                let rollback_id_man = current_id_man.clone();
+                update_identities(new_id_man, identities)
+                    .map(move |(_, updated)| (current_id_man, updated))
+                    .or_else(move |(err, updated)| {
+                        roll_back_identities(rollback_id_man, updated).then(|_| Err(err))
+                    })
+            });
+
+        Either::B(
+            rotation.and_then(move |(current_id_man, updated)| match save_files(&files) {
+                Ok(()) => Either::A(future::ok(())),
+                Err(err) => {
+                    Either::B(roll_back_identities(current_id_man, updated).then(|_| Err(err)))
+                }
+            }),
+        )
+    }
+}
+
+impl<HC, K, S> DeviceKeyRotation for KeyRotation<HC, K, S>
+where
+    HC: 'static + ClientImpl + Clone,
+    K: 'static + Sign + Clone + Send + Sync,
+    S: 'static + RuntimeSettings + Serialize + Send + Sync,
+{
+    type Error = Error;
+    type RotateFuture = Box<dyn Future<Item = (), Error = Self::Error> + Send>;
+
+    fn rotate_device_key(&self, primary_key: Vec<u8>) -> Self::RotateFuture {
+        // After a successful rotation the flag stays set, since the daemon is about to restart.
+        if self.inner.in_progress.swap(true, Ordering::SeqCst) {
+            return Box::new(future::err(Error::from(ErrorKind::RotateDeviceKey(
+                RotateDeviceKeyReason::InProgress,
+            ))));
+        }
+
+        info!("Rotating the device key...");
+        let inner = self.inner.clone();
+        Box::new(self.inner.rotate(primary_key).then(move |result| {
+            match result {
+                Ok(()) => {
+                    info!("Rotated the device key. Restarting to start using it.");
+                    inner.rotated.unbounded_send(()).unwrap_or(());
+                }
+                Err(_) => inner.in_progress.store(false, Ordering::SeqCst),
+            }
+            result
+        }))
+    }
+}
+
+/// Returns the current text of the setting that holds the device key, and its text with the new key.
+/// A device with fallback IoT hubs rotates the key of the hub it is connected to, `hub_name`.
+fn device_secret<S>(
+    settings: &S,
+    hub_name: &str,
+    primary_key: &[u8],
+) -> Result<(String, String), Error>
+where
+    S: RuntimeSettings,
+{
+    match settings.provisioning().provisioning_type() {
+        ProvisioningType::Manual(manual) => {
+            let cs = manual
+                .authentication_methods()
+                .filter_map(|auth| match auth {
+                    ManualAuthMethod::DeviceConnectionString(cs) => Some(cs),
+                    ManualAuthMethod::X509(_) => None,
+                })
+                .find(|cs| {
+                    cs.parse_device_connection_string()
+                        .map(|(_, _, hub)| hub == hub_name)
+                        .unwrap_or(false)
+                });
+            if let Some(cs) = cs {
+                return Ok((
+                    cs.device_connection_string().to_string(),
+                    cs.with_shared_access_key(primary_key)
+                        .device_connection_string()
+                        .to_string(),
+                ));
+            }
+        }
+        ProvisioningType::Dps(dps) => {
+            if let AttestationMethod::SymmetricKey(symmetric_key) = dps.attestation() {
+                return Ok((
+                    symmetric_key.symmetric_key().to_string(),
+                    base64::encode(primary_key),
+                ));
+            }
+        }
+        ProvisioningType::External(_) => (),
+    }
+
+    Err(Error::from(ErrorKind::RotateDeviceKey(
+        RotateDeviceKeyReason::UnsupportedProvisioning,
+    )))
+}
+
+fn replace_once(s: &str, from: &str, to: &str) -> Option<String> {
+    if s.matches(from).count() == 1 {
+        Some(s.replacen(from, to, 1))
+    } else {
+        None
+    }
+}
+
+/// Computes the settings digest that the daemon computes at startup once the configuration file has
+/// the new key, so that the key change isn't mistaken for a configuration change that requires the
+/// modules to be removed.
+fn rotated_settings_digest<S>(
+    settings: &S,
+    old_secret: &str,
+    new_secret: &str,
+) -> Result<String, Error>
+where
+    S: Serialize,
+{
+    let serialized = serde_json::to_string(settings).context(ErrorKind::RotateDeviceKey(
+        RotateDeviceKeyReason::SaveSettings,
+    ))?;
+    let old_secret = serde_json::to_string(old_secret).context(ErrorKind::RotateDeviceKey(
+        RotateDeviceKeyReason::SaveSettings,
+    ))?;
+    let new_secret = serde_json::to_string(new_secret).context(ErrorKind::RotateDeviceKey(
+        RotateDeviceKeyReason::SaveSettings,
+    ))?;
+    let serialized = replace_once(&serialized, &old_secret, &new_secret)
+        .ok_or_else(|| ErrorKind::RotateDeviceKey(RotateDeviceKeyReason::SaveSettings))?;
+    Ok(digest_serialized_settings(serialized, None))
+}
+
+fn dps_provisioning<HC>(
+    hyper_client: HC,
+    dps: &Dps,
+    symmetric_key: &SymmetricKeyAttestationInfo,
+    primary_key: &[u8],
+) -> Result<(MemoryKeyStore, DpsSymmetricKeyProvisioning<HC>), Error>
+where
+    HC: 'static + ClientImpl,
+{
+    let mut memory_hsm = MemoryKeyStore::new();
+    memory_hsm
+        .activate_identity_key(KeyIdentity::Device, "primary".to_string(), primary_key)
+        .context(ErrorKind::ActivateSymmetricKey)?;
+
+    let dps = DpsSymmetricKeyProvisioning::new(
+        hyper_client,
+        dps.global_endpoint().clone(),
+        dps.scope_id().to_string(),
+        symmetric_key.registration_id().to_string(),
+        DPS_API_VERSION.to_string(),
+    )
+    .context(ErrorKind::RotateDeviceKey(
+        RotateDeviceKeyReason::DpsProvisioning,
+    ))?
+    .with_payload(symmetric_key.payload().cloned());
+    Ok((memory_hsm, dps))
+}
+
+/// Only SAS identities that the edge runtime created have keys derived from the device key.
+/// Identities created by anyone else are left alone.
+fn identities_to_rotate<I>(identities: &[I]) -> Vec<IdentitySpec>
+where
+    I: Identity,
+{
+    identities
+        .iter()
+        .filter(|identity| {
+            identity.auth_type() == AuthType::Sas
+                && (identity.module_id() == EDGE_RUNTIME_MODULEID
+                    || identity.managed_by() == EDGE_MANAGED_BY)
+        })
+        .map(|identity| {
+            let spec = IdentitySpec::new(identity.module_id().to_string())
+                .with_generation_id(identity.generation_id().to_string());
+            if identity.managed_by().is_empty() {
+                spec
+            } else {
+                spec.with_managed_by(identity.managed_by().to_string())
+            }
+        })
+        .collect()
+}
+
+/// Updates the identities one at a time. If an update fails, the error is returned along with the
+/// identities that were already updated, so that they can be rolled back.
+fn update_identities<I>(
+    id_man: I,
+    identities: Vec<IdentitySpec>,
+) -> impl Future<Item = (I, Vec<IdentitySpec>), Error = (Error, Vec<IdentitySpec>)>
+where
+    I: IdentityManager,
+{
+    future::loop_fn(
+        (id_man, identities.into_iter(), vec![]),
+        |(mut id_man, mut pending, mut updated)| match pending.next() {
+            Some(identity) => {
+                Either::A(
+                    id_man
+                        .update(identity.clone())
+                        .then(move |result| match result {
+                            Ok(_) => {
+                                updated.push(identity);
+                                Ok(Loop::Continue((id_man, pending, updated)))
+                            }
+                            Err(err) => {
+                                let err = Error::from(err.context(ErrorKind::RotateDeviceKey(
+                                    RotateDeviceKeyReason::UpdateIdentity(
+                                        identity.module_id().to_string(),
+                                    ),
+                                )));
+                                Err((err, updated))
+                            }
+                        }),
+                )
+            }
+            None => Either::B(future::ok(Loop::Break((id_man, updated)))),
+        },
+    )
+}
+
+/// Gives the identities keys derived from the current device key again. Failures are logged, since
+/// the error that caused the rollback is the one that gets reported.
+fn roll_back_identities<I>(
+    id_man: I,
+    identities: Vec<IdentitySpec>,
+) -> impl Future<Item = (), Error = ()>
+where
+    I: IdentityManager,
+{
+    if !identities.is_empty() {
+        warn!(
+            "Rolling back the keys of {} module identities...",
+            identities.len()
+        );
+    }
+    update_identities(id_man, identities).then(|result| {
+        if let Err((err, _)) = result {
+            warn!("Could not roll back the module identity keys: {}", err);
+            for cause in Fail::iter_causes(&err) {
+                warn!("\tcaused by: {}", cause);
+            }
+        }
+        Ok(())
+    })
+}
+
+fn save_files(files: &PendingFiles) -> Result<(), Error> {
+    fs::write(&files.config_file, &files.new_config).context(ErrorKind::RotateDeviceKey(
+        RotateDeviceKeyReason::ConfigFile,
+    ))?;
+
+    let saved = File::create(&files.settings_state_path)
+        .and_then(|mut file| file.write_all(files.settings_digest.as_bytes()))
+        .context(ErrorKind::RotateDeviceKey(
+            RotateDeviceKeyReason::SaveSettings,
+        ));
+    if let Err(err) = saved {
+        if let Err(restore_err) = fs::write(&files.config_file, &files.old_config) {
+            warn!(
+                "Could not restore {}: {}",
+                files.config_file.display(),
+                restore_err
+            );
+        }
+        return Err(Error::from(err));
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use edgelet_docker::Settings;
+    use edgelet_test_utils::identity::{TestIdentity, TestIdentityManager};
+    use tempdir::TempDir;
+
+    use super::*;
+    use crate::compute_settings_digest;
+
+    #[cfg(unix)]
+    static CONNECTION_STRING_SETTINGS: &str = "test/linux/sample_settings1.yaml";
+    #[cfg(unix)]
+    static DPS_SYMMETRIC_KEY_SETTINGS: &str = "test/linux/sample_settings.dps.symm.key.yaml";
+
+    #[cfg(windows)]
+    static CONNECTION_STRING_SETTINGS: &str = "test/windows/sample_settings1.yaml";
+    #[cfg(windows)]
+    static DPS_SYMMETRIC_KEY_SETTINGS: &str = "test/windows/sample_settings.dps.symm.key.yaml";
+
+    #[test]
+    fn rotated_settings_digest_matches_rotated_config_file() {
+        for settings_file in &[CONNECTION_STRING_SETTINGS, DPS_SYMMETRIC_KEY_SETTINGS] {
+            let settings = Settings::new(Path::new(settings_file)).unwrap();
+            let (old_secret, new_secret) =
+                device_secret(&settings, "something1.something1.com", b"new key!!").unwrap();
+            assert_ne!(old_secret, new_secret);
+
+            let tmp_dir = TempDir::new("key-rotation").unwrap();
+            let config_file = tmp_dir.path().join("config.yaml");
+            let old_config = fs::read_to_string(settings_file).unwrap();
+            let new_config = replace_once(&old_config, &old_secret, &new_secret).unwrap();
+            fs::write(&config_file, new_config).unwrap();
+
+            let rotated_settings = Settings::new(&config_file).unwrap();
+            assert_eq!(
+                compute_settings_digest(&rotated_settings, None).unwrap(),
+                rotated_settings_digest(&settings, &old_secret, &new_secret).unwrap()
+            );
+        }
+    }
+
+    #[test]
+    fn replace_once_requires_a_single_match() {
+        assert_eq!(
+            Some("key: new".to_string()),
+            replace_once("key: old", "old", "new")
+        );
+        assert_eq!(None, replace_once("key: old # old", "old", "new"));
+        assert_eq!(None, replace_once("key: other", "old", "new"));
+    }
+
+    #[test]
+    fn only_runtime_created_sas_identities_are_rotated() {
+        let identities = vec![
+            TestIdentity::new("$edgeAgent", "", "g1", AuthType::Sas),
+            TestIdentity::new("$edgeHub", EDGE_MANAGED_BY, "g2", AuthType::Sas),
+            TestIdentity::new("m1", EDGE_MANAGED_BY, "g3", AuthType::X509),
+            TestIdentity::new("m2", "someone-else", "g4", AuthType::Sas),
+        ];
+
+        let specs = identities_to_rotate(&identities);
+        assert_eq!(2, specs.len());
+        assert_eq!("$edgeAgent", specs[0].module_id());
+        assert_eq!(Some("g1"), specs[0].generation_id());
+        assert_eq!(None, specs[0].managed_by());
+        assert_eq!("$edgeHub", specs[1].module_id());
+        assert_eq!(Some("g2"), specs[1].generation_id());
+        assert_eq!(Some(EDGE_MANAGED_BY), specs[1].managed_by());
+    }
+
+    #[test]
+    fn update_failure_returns_the_identities_to_roll_back() {
+        let id_man = TestIdentityManager::new(vec![
+            TestIdentity::new("m1", EDGE_MANAGED_BY, "g1", AuthType::Sas),
+            TestIdentity::new("m2", EDGE_MANAGED_BY, "g2", AuthType::Sas),
+        ]);
+        let identities = vec![
+            IdentitySpec::new("m1".to_string()).with_generation_id("g1".to_string()),
+            IdentitySpec::new("m2".to_string()),
+        ];
+
+        let (err, updated) = update_identities(id_man, identities).wait().err().unwrap();
+        assert_eq!(
+            &ErrorKind::RotateDeviceKey(RotateDeviceKeyReason::UpdateIdentity("m2".to_string())),
+            err.kind()
+        );
+        assert_eq!(1, updated.len());
+        assert_eq!("m1", updated[0].module_id());
+    }
+}
@@ -13,6 +13,8 @@
 
 pub mod app;
 mod error;
+mod hub_failover;
+mod key_rotation;
 pub mod logging;
 pub mod signal;
 pub mod workload;
@@ -31,6 +33,7 @@ use std::fs::{DirBuilder, File, OpenOptions};
 use std::io::{Read, Write};
 use std::path::{Path, PathBuf};
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 
 use failure::{Context, Fail, ResultExt};
 use futures::future::{Either, IntoFuture};
@@ -42,9 +45,10 @@ use log::{debug, info, Level};
 use serde::de::DeserializeOwned;
 use serde::Serialize;
 use sha2::{Digest, Sha256};
+use tokio::timer::Interval;
 use url::Url;
 
-use dps::DPS_API_VERSION;
+use dps::{DPS_API_VERSION, DPS_REGISTRATION_STATE_FILENAME};
 use edgelet_core::crypto::{
     Activate, CreateCertificate, Decrypt, DerivedKeyStore, Encrypt, GetDeviceIdentityCertificate,
     GetHsmVersion, GetIssuerAlias, GetTrustBundle, KeyIdentity, KeyStore, MakeRandom,
@@ -53,22 +57,28 @@ use edgelet_core::crypto::{
 };
 use edgelet_core::watchdog::Watchdog;
 use edgelet_core::{
-    AttestationMethod, Authenticator, Certificate, CertificateIssuer, CertificateProperties,
-    CertificateType, Dps, MakeModuleRuntime, ManualAuthMethod, Module, ModuleRuntime,
-    ModuleRuntimeErrorReason, ModuleSpec, ProvisioningResult as CoreProvisioningResult,
-    ProvisioningType, RuntimeSettings, SymmetricKeyAttestationInfo, TpmAttestationInfo,
-    WorkloadConfig, X509AttestationInfo,
+    metrics, AttestationMethod, AuthType as CoreAuthType, Authenticator, Certificate,
+    CertificateIssuer, CertificateProperties, CertificateType, Dps, MakeModuleRuntime,
+    ManualAuthMethod, Module, ModuleRuntime, ModuleRuntimeErrorReason, ModuleSpec,
+    ProvisioningResult as CoreProvisioningResult, ProvisioningType, RuntimeSettings,
+    SymmetricKeyAttestationInfo, TpmAttestationInfo, WorkloadConfig, X509AttestationInfo,
+    PKCS11_URI_SCHEME,
 };
 use edgelet_hsm::tpm::{TpmKey, TpmKeyStore};
-use edgelet_hsm::{Crypto, HsmLock, X509};
+#[cfg(not(feature = "soft-crypto"))]
+use edgelet_hsm::Crypto;
+use edgelet_hsm::{HsmLock, Pkcs11Uri, X509};
 use edgelet_http::certificate_manager::CertificateManager;
 use edgelet_http::client::{Client as HttpClient, ClientImpl};
 use edgelet_http::logging::LoggingService;
+use edgelet_http::metrics::MetricsService;
 use edgelet_http::{HyperExt, MaybeProxyClient, PemCertificate, TlsAcceptorParams, API_VERSION};
 use edgelet_http_external_provisioning::ExternalProvisioningClient;
 use edgelet_http_mgmt::ManagementService;
 use edgelet_http_workload::WorkloadService;
-use edgelet_iothub::{HubIdentityManager, SasTokenSource};
+use edgelet_iothub::{ConnectivityMonitor, HubIdentityManager, IdentityCache, SasTokenSource};
+#[cfg(feature = "soft-crypto")]
+use edgelet_soft_crypto::{Crypto, FileKeyStore};
 use edgelet_utils::log_failure;
 pub use error::{Error, ErrorKind, InitializeErrorReason};
 use hsm::tpm::Tpm;
@@ -81,11 +91,14 @@ use provisioning::provisioning::{
 };
 
 use crate::error::ExternalProvisioningErrorReason;
+use crate::hub_failover::HubFailover;
+use crate::key_rotation::KeyRotation;
 use crate::workload::WorkloadData;
 
 const EDGE_RUNTIME_MODULEID: &str = "$edgeAgent";
 const EDGE_RUNTIME_MODULE_NAME: &str = "edgeAgent";
-const AUTH_SCHEME: &str = "sasToken";
+const SAS_AUTH_SCHEME: &str = "sasToken";
+const X509_AUTH_SCHEME: &str = "x509";
 
 /// The following constants are all environment variables names injected into
 /// the Edge Agent container.
@@ -99,9 +112,17 @@ const HOSTNAME_KEY: &str = "IOTEDGE_IOTHUBHOSTNAME";
 /// network so that TLS cert validation works.
 const GATEWAY_HOSTNAME_KEY: &str = "EDGEDEVICEHOSTNAME";
 
+/// This variable holds the host name of the parent edge device that this device
+/// connects to IoT Hub through. It is not set if the device connects directly.
+const PARENT_HOSTNAME_KEY: &str = "IOTEDGE_GATEWAYHOSTNAME";
+
 /// This variable holds the IoT Hub device identifier.
 const DEVICEID_KEY: &str = "IOTEDGE_DEVICEID";
 
+/// This variable holds the custom allocation payload, as JSON, that DPS returned when the
+/// device was provisioned. It is not set if DPS did not return one.
+const DPS_PAYLOAD_KEY: &str = "IOTEDGE_DPS_PAYLOAD";
+
 /// This variable holds the IoT Hub module identifier.
 const MODULEID_KEY: &str = "IOTEDGE_MODULEID";
 
@@ -191,6 +212,23 @@ const IOTEDGED_CRYPTO_ID: &str = "$iotedge";
 /// This is the name of the cache subdirectory for settings state
 const EDGE_SETTINGS_SUBDIR: &str = "cache";
 
+/// This is the name of the encrypted copy of the module identities kept in the cache
+/// subdirectory, which is used when IoT Hub can't be reached
+const EDGE_IDENTITY_CACHE_FILENAME: &str = "identity_cache";
+
+/// This is how often the identity cache is refreshed from IoT Hub after it has been used
+/// because IoT Hub couldn't be reached
+const IDENTITY_CACHE_SYNC_FREQUENCY_SECS: u64 = 60;
+
+/// This is the name of the file that records the IoT hub a manually provisioned device with
+/// fallback hubs is connected to. It is kept outside the cache subdirectory so that the device
+/// stays on that hub when the configuration changes.
+const EDGE_ACTIVE_HUB_FILENAME: &str = "active_hub";
+
+/// This is how many identity operations in a row have to fail to reach IoT Hub before a device
+/// with fallback hubs fails over to the next hub
+const HUB_FAILOVER_MAX_FAILURES: usize = 5;
+
 /// This is the DPS registration ID env variable key
 const DPS_REGISTRATION_ID_ENV_KEY: &str = "IOTEDGE_REGISTRATION_ID";
 
@@ -200,6 +238,8 @@ const DEVICE_IDENTITY_CERT_PATH_ENV_KEY: &str = "IOTEDGE_DEVICE_IDENTITY_CERT";
 /// This is the edge device identity private key file path env variable key.
 /// This is used for both DPS attestation and manual authentication modes.
 const DEVICE_IDENTITY_KEY_PATH_ENV_KEY: &str = "IOTEDGE_DEVICE_IDENTITY_PK";
+/// This is the PKCS#11 module the HSM lib loads when a private key is given as a `pkcs11:` URI.
+const PKCS11_MODULE_PATH_ENV_KEY: &str = "IOTEDGE_PKCS11_MODULE_PATH";
 
 const IOTEDGED_COMMONNAME: &str = "iotedged workload ca";
 const IOTEDGED_TLS_COMMONNAME: &str = "iotedged";
@@ -217,6 +257,8 @@ const IOTEDGE_COMPAT_HSM_VERSION: &str = "1.0.3";
 enum StartApiReturnStatus {
     Restart,
     Shutdown,
+    DeviceKeyRotated,
+    HubFailover,
 }
 
 pub struct Main<M>
@@ -224,6 +266,7 @@ where
     M: MakeModuleRuntime,
 {
     settings: M::Settings,
+    config_file: Option<PathBuf>,
 }
 
 #[derive(Debug, PartialEq)]
@@ -244,13 +287,23 @@ where
     M::ModuleRuntime: 'static + Authenticator<Request = Request<Body>> + Clone + Send + Sync,
     <<M::ModuleRuntime as ModuleRuntime>::Module as Module>::Config:
         Clone + DeserializeOwned + Serialize,
-    M::Settings: 'static + Clone + Serialize,
+    M::Settings: 'static + Clone + Serialize + Send + Sync,
     <M::ModuleRuntime as ModuleRuntime>::Logs: Into<Body>,
     <M::ModuleRuntime as Authenticator>::Error: Fail + Sync,
     for<'r> &'r <M::ModuleRuntime as ModuleRuntime>::Error: Into<ModuleRuntimeErrorReason>,
 {
     pub fn new(settings: M::Settings) -> Self {
-        Main { settings }
+        Main {
+            settings,
+            config_file: None,
+        }
+    }
+
+    /// The configuration file that `settings` were loaded from. The device key can only be rotated
+    /// when it's known, since the new key is written to it.
+    pub fn with_config_file(mut self, config_file: PathBuf) -> Self {
+        self.config_file = Some(config_file);
+        self
     }
 
     // Allowing cognitive complexity errors for now. TODO: Refactor method later.
@@ -260,12 +313,19 @@ where
         F: Future<Item = (), Error = ()> + Send + 'static,
         G: Fn() -> F,
     {
-        let Main { settings } = self;
+        let Main {
+            settings,
+            config_file,
+        } = self;
         let hsm_lock = HsmLock::new();
 
         let mut tokio_runtime = tokio::runtime::Runtime::new()
             .context(ErrorKind::Initialize(InitializeErrorReason::Tokio))?;
 
+        if let Some(metrics_uri) = settings.listen().metrics_uri() {
+            start_metrics(metrics_uri, &mut tokio_runtime)?;
+        }
+
         let (external_provisioning_info, external_provisioning) =
             get_external_provisioning_info(&settings, &mut tokio_runtime)?;
 
@@ -276,22 +336,29 @@ where
             settings.certificates().auto_generated_ca_lifetime_seconds();
 
         info!("Initializing hsm...");
-        let crypto = Crypto::new(hsm_lock.clone(), auto_generated_ca_lifetime_seconds)
-            .context(ErrorKind::Initialize(InitializeErrorReason::Hsm))?;
-
-        let hsm_version = crypto
-            .get_version()
-            .context(ErrorKind::Initialize(InitializeErrorReason::Hsm))?;
+        #[cfg(not(feature = "soft-crypto"))]
+        let crypto = {
+            let crypto = Crypto::new(hsm_lock.clone(), auto_generated_ca_lifetime_seconds)
+                .context(ErrorKind::Initialize(InitializeErrorReason::Hsm))?;
+
+            let hsm_version = crypto
+                .get_version()
+                .context(ErrorKind::Initialize(InitializeErrorReason::Hsm))?;
+
+            if hsm_version != IOTEDGE_COMPAT_HSM_VERSION {
+                info!(
+                    "Incompatible HSM crypto interface version. Found {}, required {}",
+                    hsm_version, IOTEDGE_COMPAT_HSM_VERSION
+                );
+                return Err(Error::from(ErrorKind::Initialize(
+                    InitializeErrorReason::IncompatibleHsmVersion,
+                )));
+            }
 
-        if hsm_version != IOTEDGE_COMPAT_HSM_VERSION {
-            info!(
-                "Incompatible HSM crypto interface version. Found {}, required {}",
-                hsm_version, IOTEDGE_COMPAT_HSM_VERSION
-            );
-            return Err(Error::from(ErrorKind::Initialize(
-                InitializeErrorReason::IncompatibleHsmVersion,
-            )));
-        }
+            crypto
+        };
+        #[cfg(feature = "soft-crypto")]
+        let crypto = init_soft_crypto(&settings, auto_generated_ca_lifetime_seconds)?;
 
         // ensure a master encryption key is initialized
         crypto.create_key().context(ErrorKind::Initialize(
@@ -316,7 +383,7 @@ where
             ))?;
 
         macro_rules! start_edgelet {
-            ($key_store:ident, $provisioning_result:ident, $root_key:ident, $force_reprovision:ident, $id_cert_thumprint:ident, $provision:ident,) => {{
+            ($key_store:ident, $provisioning_result:ident, $root_key:ident, $force_reprovision:ident, $id_cert_thumprint:ident, $provision:ident, $hub_failover:ident,) => {{
                 info!("Finished provisioning edge device.");
 
                 let runtime = init_runtime::<M>(
@@ -355,6 +422,9 @@ where
                     $id_cert_thumprint,
                 )?;
 
+                let provisioning_auth_method =
+                    get_provisioning_auth_method(&settings, Some(&$provisioning_result))?;
+
                 let cfg = WorkloadData::new(
                     $provisioning_result.hub_name().to_string(),
                     $provisioning_result.device_id().to_string(),
@@ -371,6 +441,10 @@ where
                         &$key_store,
                         cfg.clone(),
                         $root_key.clone(),
+                        $provisioning_result.payload(),
+                        &provisioning_auth_method,
+                        config_file.clone(),
+                        $hub_failover,
                         make_shutdown_signal(),
                         &crypto,
                         &mut tokio_runtime,
@@ -391,6 +465,19 @@ where
                         return Err(Error::from(ErrorKind::DeviceDeprovisioned))
                     }
 
+                    if code == StartApiReturnStatus::DeviceKeyRotated {
+                        // The new key is in the config file now. Exiting with an error makes
+                        // `systemd` restart the daemon, which then authenticates with the new key.
+                        return Err(Error::from(ErrorKind::DeviceKeyRotated))
+                    }
+
+                    if let (StartApiReturnStatus::HubFailover, Some(hub_failover)) = (&code, $hub_failover) {
+                        // edgeAgent is created again with the hostname of the next hub once the
+                        // restarted daemon connects to it.
+                        let hub = fail_over_hub::<M>(hub_failover, &settings, &runtime, &mut tokio_runtime)?;
+                        return Err(Error::from(ErrorKind::HubFailover(hub)))
+                    }
+
                     if code != StartApiReturnStatus::Restart {
                         break;
                     }
@@ -412,7 +499,19 @@ where
 
         match settings.provisioning().provisioning_type() {
             ProvisioningType::Manual(manual) => {
-                match manual.authentication_method() {
+                let hub_failover = HubFailover::new(
+                    Path::new(&settings.homedir()).join(EDGE_ACTIVE_HUB_FILENAME),
+                    manual,
+                )?;
+                let hub_failover_op = Some(&hub_failover).filter(|f| f.has_fallbacks());
+                if hub_failover_op.is_some() {
+                    info!(
+                        "The device has fallback IoT hubs, connecting to IoT hub {}",
+                        hub_failover.active_hostname()
+                    );
+                }
+
+                match hub_failover.active_authentication_method() {
                     ManualAuthMethod::DeviceConnectionString(cs) => {
                         info!("Starting provisioning edge device via manual mode using a device connection string...");
                         let (key, device_id, hub) = cs
@@ -430,6 +529,7 @@ where
                             force_module_reprovision,
                             None,
                             manual,
+                            hub_failover_op,
                         );
                     }
                     ManualAuthMethod::X509(x509) => {
@@ -461,6 +561,7 @@ where
                             force_module_reprovision,
                             thumbprint_op,
                             manual,
+                            hub_failover_op,
                         );
                     }
                 };
@@ -501,17 +602,23 @@ where
                                 force_module_reprovision,
                                 None,
                                 external_provisioning_val,
+                                None,
                             );
                         } else {
-                            let (derived_key_store, tpm_key) =
+                            #[cfg(not(feature = "soft-crypto"))]
+                            let (derived_key_store, device_key) =
                                 external_provision_tpm(hsm_lock.clone())?;
+                            #[cfg(feature = "soft-crypto")]
+                            let (derived_key_store, device_key) =
+                                external_provision_key_store(settings.homedir())?;
                             start_edgelet!(
                                 derived_key_store,
                                 provisioning_result,
-                                tpm_key,
+                                device_key,
                                 force_module_reprovision,
                                 None,
                                 external_provisioning_val,
+                                None,
                             );
                         }
                     }
@@ -538,18 +645,24 @@ where
                             force_module_reprovision,
                             thumbprint_op,
                             external_provisioning_val,
+                            None,
                         );
                     }
                 };
             }
             ProvisioningType::Dps(dps) => {
                 let dps_path = cache_subdir_path.join(EDGE_PROVISIONING_BACKUP_FILENAME);
+                let dps_state_path = cache_subdir_path.join(DPS_REGISTRATION_STATE_FILENAME);
 
                 match dps.attestation() {
                     AttestationMethod::Tpm(ref tpm) => {
                         info!("Starting provisioning edge device via TPM...");
-                        let (tpm_instance, dps_tpm) =
-                            dps_tpm_provision_init(&dps, hyper_client.clone(), tpm)?;
+                        let (tpm_instance, dps_tpm) = dps_tpm_provision_init(
+                            &dps,
+                            hyper_client.clone(),
+                            tpm,
+                            dps_state_path,
+                        )?;
                         let (key_store, provisioning_result, root_key) = dps_tpm_provision(
                             dps_path,
                             &mut tokio_runtime,
@@ -565,6 +678,7 @@ where
                             force_module_reprovision,
                             None,
                             dps_tpm,
+                            None,
                         );
                     }
                     AttestationMethod::SymmetricKey(ref symmetric_key_info) => {
@@ -573,6 +687,7 @@ where
                             &dps,
                             hyper_client.clone(),
                             symmetric_key_info,
+                            dps_state_path,
                         )?;
                         let (key_store, provisioning_result, root_key) =
                             dps_symmetric_key_provision(
@@ -589,6 +704,7 @@ where
                             force_module_reprovision,
                             None,
                             dps_symmetric_key,
+                            None,
                         );
                     }
                     AttestationMethod::X509(ref x509_info) => {
@@ -603,6 +719,7 @@ where
                             x509_info,
                             hybrid_identity_key,
                             &id_data.common_name,
+                            dps_state_path,
                         )?;
 
                         let (key_store, provisioning_result, root_key) = dps_x509_provision(
@@ -620,6 +737,7 @@ where
                             force_module_reprovision,
                             thumbprint_op,
                             dps_x509,
+                            None,
                         );
                     }
                 }
@@ -631,9 +749,18 @@ where
     }
 }
 
+/// The key store that external provisioning activates the device's identity key in.
+///
+/// The soft crypto backend keeps the key in a file under the home directory. That file is also
+/// where the key is read from when the external endpoint says the key is held by the HSM.
+#[cfg(not(feature = "soft-crypto"))]
+type ExternalProvisioningKeyStore = MemoryKeyStore;
+#[cfg(feature = "soft-crypto")]
+type ExternalProvisioningKeyStore = FileKeyStore;
+
 type ExternalProvisioningInfo = (
     Option<ProvisioningResult>,
-    Option<ExternalProvisioning<ExternalProvisioningClient, MemoryKeyStore>>,
+    Option<ExternalProvisioning<ExternalProvisioningClient, ExternalProvisioningKeyStore>>,
 );
 
 fn get_external_provisioning_info<S>(
@@ -660,7 +787,7 @@ where
 
         info!("Retrieving provisioning information from the external endpoint...");
         let provision_fut = external_provisioning
-            .provision(MemoryKeyStore::new())
+            .provision(external_provisioning_key_store(settings.homedir())?)
             .map_err(|err| {
                 Error::from(err.context(ErrorKind::Initialize(
                     InitializeErrorReason::ExternalProvisioningClient(
@@ -677,6 +804,21 @@ where
     }
 }
 
+#[cfg(not(feature = "soft-crypto"))]
+fn external_provisioning_key_store(_homedir: &Path) -> Result<MemoryKeyStore, Error> {
+    Ok(MemoryKeyStore::new())
+}
+
+#[cfg(feature = "soft-crypto")]
+fn external_provisioning_key_store(homedir: &Path) -> Result<FileKeyStore, Error> {
+    let key_store = FileKeyStore::new(homedir).context(ErrorKind::Initialize(
+        InitializeErrorReason::ExternalProvisioningClient(
+            ExternalProvisioningErrorReason::HsmInitialization,
+        ),
+    ))?;
+    Ok(key_store)
+}
+
 fn configure_external_provisioning<S>(
     provisioning_info: &ProvisioningResult,
     settings: &S,
@@ -743,6 +885,41 @@ where
     Ok(())
 }
 
+/// Builds the pure-Rust crypto backend, which keeps its keys and certificates under the home
+/// directory instead of going through the HSM library.
+#[cfg(feature = "soft-crypto")]
+fn init_soft_crypto<S>(
+    settings: &S,
+    auto_generated_ca_lifetime_seconds: u64,
+) -> Result<Crypto, Error>
+where
+    S: RuntimeSettings,
+{
+    let crypto = Crypto::new(settings.homedir(), auto_generated_ca_lifetime_seconds)
+        .context(ErrorKind::Initialize(InitializeErrorReason::Hsm))?;
+
+    let crypto = match settings.certificates().device_cert() {
+        Some(device_cert) => {
+            let cert = device_cert.device_ca_cert().context(ErrorKind::Initialize(
+                InitializeErrorReason::CertificateSettings,
+            ))?;
+            let private_key = device_cert.device_ca_pk().context(ErrorKind::Initialize(
+                InitializeErrorReason::CertificateSettings,
+            ))?;
+            let trusted_ca_certs =
+                device_cert
+                    .trusted_ca_certs()
+                    .context(ErrorKind::Initialize(
+                        InitializeErrorReason::CertificateSettings,
+                    ))?;
+            crypto.with_device_ca(cert, private_key, trusted_ca_certs)
+        }
+        None => crypto,
+    };
+
+    Ok(crypto)
+}
+
 fn set_iot_edge_env_vars<S>(
     settings: &S,
     provisioning_result: &Option<ProvisioningResult>,
@@ -758,6 +935,7 @@ where
 
     info!("Configuring certificates...");
     let certificates = settings.certificates();
+    let mut pkcs11_module_path = None;
 
     match certificates.device_cert().as_ref() {
         None => {
@@ -773,14 +951,16 @@ where
             );
             env::set_var(DEVICE_CA_CERT_KEY, path);
 
-            let path = c.device_ca_pk().context(ErrorKind::Initialize(
+            let uri = c.device_ca_pk_uri().context(ErrorKind::Initialize(
                 InitializeErrorReason::CertificateSettings,
             ))?;
-            info!(
-                "Configuring the Device private key using {:?}.",
-                path.as_os_str()
-            );
-            env::set_var(DEVICE_CA_PK_KEY, path);
+            set_private_key_env_var(
+                DEVICE_CA_PK_KEY,
+                &uri,
+                || c.device_ca_pk(),
+                &mut pkcs11_module_path,
+                InitializeErrorReason::CertificateSettings,
+            )?;
 
             let path = c.trusted_ca_certs().context(ErrorKind::Initialize(
                 InitializeErrorReason::CertificateSettings,
@@ -801,10 +981,16 @@ where
                 ))?;
                 env::set_var(DEVICE_IDENTITY_CERT_PATH_ENV_KEY, path.as_os_str());
 
-                let path = x509.identity_pk().context(ErrorKind::Initialize(
+                let uri = x509.identity_pk_uri().context(ErrorKind::Initialize(
                     InitializeErrorReason::IdentityCertificateSettings,
                 ))?;
-                env::set_var(DEVICE_IDENTITY_KEY_PATH_ENV_KEY, path.as_os_str());
+                set_private_key_env_var(
+                    DEVICE_IDENTITY_KEY_PATH_ENV_KEY,
+                    uri,
+                    || x509.identity_pk(),
+                    &mut pkcs11_module_path,
+                    InitializeErrorReason::IdentityCertificateSettings,
+                )?;
             }
         }
         ProvisioningType::External(_external) => {
@@ -862,10 +1048,16 @@ where
                 ))?;
                 env::set_var(DEVICE_IDENTITY_CERT_PATH_ENV_KEY, path.as_os_str());
 
-                let path = x509_info.identity_pk().context(ErrorKind::Initialize(
+                let uri = x509_info.identity_pk_uri().context(ErrorKind::Initialize(
                     InitializeErrorReason::IdentityCertificateSettings,
                 ))?;
-                env::set_var(DEVICE_IDENTITY_KEY_PATH_ENV_KEY, path.as_os_str());
+                set_private_key_env_var(
+                    DEVICE_IDENTITY_KEY_PATH_ENV_KEY,
+                    uri,
+                    || x509_info.identity_pk(),
+                    &mut pkcs11_module_path,
+                    InitializeErrorReason::IdentityCertificateSettings,
+                )?;
             }
         },
     }
@@ -874,6 +1066,48 @@ where
     Ok(())
 }
 
+/// Private keys on a PKCS#11 token are handed to the HSM lib as their `pkcs11:` URI,
+/// everything else as a file path.
+///
+/// The HSM lib loads one PKCS#11 module for the whole process, so `pkcs11_module_path` holds the
+/// module-path of the keys configured so far and a key with a different one is rejected.
+fn set_private_key_env_var<F>(
+    env_key: &str,
+    uri: &Url,
+    path: F,
+    pkcs11_module_path: &mut Option<PathBuf>,
+    reason: InitializeErrorReason,
+) -> Result<(), Error>
+where
+    F: FnOnce() -> Result<PathBuf, edgelet_core::Error>,
+{
+    if uri.scheme() == PKCS11_URI_SCHEME {
+        let pkcs11_uri = uri
+            .as_str()
+            .parse::<Pkcs11Uri>()
+            .context(ErrorKind::Initialize(reason))?;
+        info!("Configuring {} using {}.", env_key, pkcs11_uri);
+        if let Some(module_path) = pkcs11_uri.module_path() {
+            match pkcs11_module_path {
+                Some(existing) if existing.as_path() != module_path => {
+                    return Err(Error::from(ErrorKind::Initialize(
+                        InitializeErrorReason::Pkcs11ModulePath,
+                    )));
+                }
+                _ => *pkcs11_module_path = Some(module_path.to_path_buf()),
+            }
+            env::set_var(PKCS11_MODULE_PATH_ENV_KEY, module_path);
+        }
+        env::set_var(env_key, uri.as_str());
+    } else {
+        let path = path().context(ErrorKind::Initialize(reason))?;
+        info!("Configuring {} using {:?}.", env_key, path.as_os_str());
+        env::set_var(env_key, path.as_os_str());
+    }
+
+    Ok(())
+}
+
 fn prepare_httpclient_and_identity_data<S>(
     hsm_lock: Arc<HsmLock>,
     settings: &S,
@@ -883,23 +1117,59 @@ fn prepare_httpclient_and_identity_data<S>(
 where
     S: RuntimeSettings,
 {
+    let trust_bundle = get_parent_trust_bundle(settings)?;
+
     if get_provisioning_auth_method(settings, provisioning_result)? == ProvisioningAuthMethod::X509
     {
         prepare_httpclient_and_identity_data_for_x509_provisioning(
             hsm_lock,
             auto_generated_ca_lifetime_seconds,
+            trust_bundle,
         )
     } else {
-        let hyper_client = MaybeProxyClient::new(get_proxy_uri(None)?, None, None)
+        let hyper_client = MaybeProxyClient::new(get_proxy_uri(None)?, None, trust_bundle)
             .context(ErrorKind::Initialize(InitializeErrorReason::HttpClient))?;
 
         Ok((hyper_client, None))
     }
 }
 
+// The parent device's server certificate is issued by its device CA, which has to be in
+// this device's trusted CA certificates for the connection to the parent to be trusted.
+fn get_parent_trust_bundle<S>(settings: &S) -> Result<Option<PemCertificate>, Error>
+where
+    S: RuntimeSettings,
+{
+    let parent_hostname = match settings.parent_hostname() {
+        Some(parent_hostname) => parent_hostname,
+        None => return Ok(None),
+    };
+    info!(
+        "Connecting to IoT Hub through parent device {}",
+        parent_hostname
+    );
+
+    match settings.certificates().device_cert() {
+        Some(device_cert) => {
+            let path = device_cert
+                .trusted_ca_certs()
+                .context(ErrorKind::Initialize(
+                    InitializeErrorReason::ParentTrustBundle,
+                ))?;
+            let cert = fs::read(path).context(ErrorKind::Initialize(
+                InitializeErrorReason::ParentTrustBundle,
+            ))?;
+            Ok(Some(PemCertificate::new(cert, None, None, None)))
+        }
+        // without gateway certificates the parent's CA has to be trusted by the system
+        None => Ok(None),
+    }
+}
+
 fn prepare_httpclient_and_identity_data_for_x509_provisioning(
     hsm_lock: Arc<HsmLock>,
     auto_generated_ca_lifetime_seconds: u64,
+    trust_bundle: Option<PemCertificate>,
 ) -> Result<(MaybeProxyClient, Option<IdentityCertificateData>), Error> {
     info!("Initializing hsm X509 interface...");
     let x509 = X509::new(hsm_lock, auto_generated_ca_lifetime_seconds)
@@ -937,7 +1207,7 @@ fn prepare_httpclient_and_identity_data_for_x509_provisioning(
         InitializeErrorReason::InvalidDeviceCertCredentials,
     ))?;
 
-    let hyper_client = MaybeProxyClient::new(get_proxy_uri(None)?, Some(pem), None)
+    let hyper_client = MaybeProxyClient::new(get_proxy_uri(None)?, Some(pem), trust_bundle)
         .context(ErrorKind::Initialize(InitializeErrorReason::HttpClient))?;
 
     let cert_data = IdentityCertificateData {
@@ -995,7 +1265,7 @@ where
         ))?;
 
     let issuer_ca = crypto
-        .get_certificate(issuer_alias)
+        .get_certificate(issuer_alias.clone())
         .context(ErrorKind::Initialize(
             InitializeErrorReason::PrepareWorkloadCa,
         ))?;
@@ -1005,6 +1275,7 @@ where
     ))?;
 
     info!("Edge issuer CA expiration date: {:?}", issuer_validity);
+    metrics::set_certificate_expiration(&issuer_alias, issuer_validity);
 
     let now = chrono::Utc::now();
 
@@ -1020,11 +1291,15 @@ where
         )
         .with_issuer(CertificateIssuer::DeviceCa);
 
-        crypto
-            .create_certificate(&edgelet_ca_props)
-            .context(ErrorKind::Initialize(
-                InitializeErrorReason::PrepareWorkloadCa,
-            ))?;
+        let workload_ca =
+            crypto
+                .create_certificate(&edgelet_ca_props)
+                .context(ErrorKind::Initialize(
+                    InitializeErrorReason::PrepareWorkloadCa,
+                ))?;
+        if let Ok(valid_to) = workload_ca.get_valid_to() {
+            metrics::set_certificate_expiration(IOTEDGED_CA_ALIAS, valid_to);
+        }
         Ok(())
     } else {
         Err(Error::from(ErrorKind::Initialize(
@@ -1202,11 +1477,15 @@ fn compute_settings_digest<S>(
 where
     S: RuntimeSettings + Serialize,
 {
-    let mut s = serde_json::to_string(settings)?;
+    let s = serde_json::to_string(settings)?;
+    Ok(digest_serialized_settings(s, id_cert_thumbprint))
+}
+
+fn digest_serialized_settings(mut s: String, id_cert_thumbprint: Option<&str>) -> String {
     if let Some(thumbprint) = id_cert_thumbprint {
         s.push_str(thumbprint);
     }
-    Ok(base64::encode(&Sha256::digest_str(&s)))
+    base64::encode(&Sha256::digest_str(&s))
 }
 
 fn diff_with_cached<S>(settings: &S, path: &Path, id_cert_thumbprint: Option<&str>) -> bool
@@ -1399,18 +1678,23 @@ fn start_api<HC, K, F, C, W, M>(
     key_store: &DerivedKeyStore<K>,
     workload_config: W,
     root_key: K,
+    dps_payload: Option<&serde_json::Value>,
+    provisioning_auth_method: &ProvisioningAuthMethod,
+    config_file: Option<PathBuf>,
+    hub_failover: Option<&HubFailover>,
     shutdown_signal: F,
     crypto: &C,
     tokio_runtime: &mut tokio::runtime::Runtime,
 ) -> Result<(StartApiReturnStatus, bool), Error>
 where
     F: Future<Item = (), Error = ()> + Send + 'static,
-    HC: ClientImpl + 'static,
+    HC: ClientImpl + Clone + 'static,
     K: Sign + Clone + Send + Sync + 'static,
     C: CreateCertificate
         + Decrypt
         + Encrypt
         + GetTrustBundle
+        + MakeRandom
         + MasterEncryptionKey
         + Clone
         + Send
@@ -1421,25 +1705,71 @@ where
     M: MakeModuleRuntime + 'static,
     <<M::ModuleRuntime as ModuleRuntime>::Module as Module>::Config:
         Clone + DeserializeOwned + Serialize,
-    M::Settings: 'static,
+    M::Settings: 'static + Clone + Serialize + Send + Sync,
     <M::ModuleRuntime as ModuleRuntime>::Logs: Into<Body>,
     <M::ModuleRuntime as Authenticator>::Error: Fail + Sync,
     for<'r> &'r <M::ModuleRuntime as ModuleRuntime>::Error: Into<ModuleRuntimeErrorReason>,
 {
     let hub_name = workload_config.iot_hub_name().to_string();
     let device_id = workload_config.device_id().to_string();
-    let hostname = format!("https://{}", hub_name);
+    // In a nested network the parent device forwards requests to IoT Hub. The SAS token
+    // is still scoped to IoT Hub, so only the address changes.
+    let hostname = format!(
+        "https://{}",
+        settings.parent_hostname().unwrap_or(&hub_name)
+    );
+    let hub_url =
+        Url::parse(&hostname).context(ErrorKind::Initialize(InitializeErrorReason::HttpClient))?;
+    let (key_rotated_tx, key_rotated_rx) = mpsc::unbounded();
+    let key_rotation = KeyRotation::new(
+        settings.clone(),
+        config_file,
+        Path::new(&settings.homedir())
+            .join(EDGE_SETTINGS_SUBDIR)
+            .join(EDGE_SETTINGS_STATE_FILENAME),
+        hyper_client.clone(),
+        hub_name.clone(),
+        device_id.clone(),
+        hub_url.clone(),
+        key_store.clone(),
+        key_rotated_tx,
+    );
     let token_source = SasTokenSource::new(hub_name.clone(), device_id.clone(), root_key);
     let http_client = HttpClient::new(
         hyper_client,
         Some(token_source),
         IOTHUB_API_VERSION.to_string(),
-        Url::parse(&hostname).context(ErrorKind::Initialize(InitializeErrorReason::HttpClient))?,
+        hub_url,
     )
     .context(ErrorKind::Initialize(InitializeErrorReason::HttpClient))?;
     let device_client = DeviceClient::new(http_client, device_id.clone())
         .context(ErrorKind::Initialize(InitializeErrorReason::DeviceClient))?;
-    let id_man = HubIdentityManager::new(key_store.clone(), device_client);
+    // Devices that authenticate with X.509 give their modules X.509 identities too, whose
+    // certificates the modules get from the workload API.
+    let id_man = if *provisioning_auth_method == ProvisioningAuthMethod::X509 {
+        HubIdentityManager::with_x509_module_auth(key_store.clone(), device_client)
+    } else {
+        HubIdentityManager::new(key_store.clone(), device_client)
+    };
+    let identity_cache = IdentityCache::new(
+        Path::new(&settings.homedir())
+            .join(EDGE_SETTINGS_SUBDIR)
+            .join(EDGE_IDENTITY_CACHE_FILENAME),
+        crypto.clone(),
+    );
+    let id_man = id_man.with_identity_cache(identity_cache);
+    let (hub_unreachable_tx, hub_unreachable_rx) = mpsc::unbounded();
+    let id_man = if hub_failover.is_some() {
+        id_man.with_connectivity_monitor(ConnectivityMonitor::new(
+            HUB_FAILOVER_MAX_FAILURES,
+            hub_unreachable_tx,
+        ))
+    } else {
+        id_man
+    };
+
+    let (cache_sync_tx, cache_sync_rx) = oneshot::channel();
+    tokio_runtime.spawn(start_identity_cache_sync(id_man.clone(), cache_sync_rx));
 
     let (mgmt_tx, mgmt_rx) = oneshot::channel();
     let (mgmt_stop_and_reprovision_tx, mgmt_stop_and_reprovision_rx) = mpsc::unbounded();
@@ -1483,6 +1813,7 @@ where
         mgmt_rx,
         cert_manager.clone(),
         mgmt_stop_and_reprovision_tx,
+        key_rotation,
     );
 
     let workload = start_workload::<_, _, _, _, M>(
@@ -1501,6 +1832,7 @@ where
         &id_man,
         &hub_name,
         &device_id,
+        dps_payload,
         &settings,
         runt_rx,
     )?;
@@ -1548,6 +1880,44 @@ where
         },
     );
 
+    // The key rotation handler signals once the new device key is saved, and the daemon has to
+    // restart to use it.
+    let key_rotated = key_rotated_rx.into_future().then(|res| match res {
+        Ok((Some(()), _)) => {
+            debug!("Shutdown after device key rotation.");
+            Either::A(future::ok((StartApiReturnStatus::DeviceKeyRotated, false)))
+        }
+        _ => Either::B(future::empty()),
+    });
+    let edge_rt_with_mgmt_signal = edge_rt_with_mgmt_signal.select2(key_rotated).then(|res| {
+        // A -> EdgeRt + Mgmt Stop and Reprovision Signal Future
+        // B -> Device Key Rotated Signal Future
+        match res {
+            Ok(Either::A((x, _))) | Ok(Either::B((x, _))) => Ok(x),
+            Err(Either::A((err, _))) | Err(Either::B((err, _))) => Err(err),
+        }
+    });
+
+    // The connectivity monitor signals once IoT Hub couldn't be reached too many times in a row,
+    // and the device has to fail over to the next IoT hub.
+    let hub_unreachable = hub_unreachable_rx.into_future().then(|res| match res {
+        Ok((Some(()), _)) => {
+            debug!("Shutdown to fail over to the next IoT hub.");
+            Either::A(future::ok((StartApiReturnStatus::HubFailover, false)))
+        }
+        _ => Either::B(future::empty()),
+    });
+    let edge_rt_with_mgmt_signal = edge_rt_with_mgmt_signal
+        .select2(hub_unreachable)
+        .then(|res| {
+            // A -> EdgeRt + Mgmt Stop and Reprovision + Device Key Rotated Signal Future
+            // B -> IoT Hub Unreachable Signal Future
+            match res {
+                Ok(Either::A((x, _))) | Ok(Either::B((x, _))) => Ok(x),
+                Err(Either::A((err, _))) | Err(Either::B((err, _))) => Err(err),
+            }
+        });
+
     // Wait for the watchdog to finish, and then send signal to the workload and management services.
     // This way the edgeAgent can finish shutting down all modules.
     let edge_rt_with_cleanup = edge_rt_with_mgmt_signal
@@ -1555,11 +1925,12 @@ where
         .then(move |res| {
             mgmt_tx.send(()).unwrap_or(());
             work_tx.send(()).unwrap_or(());
+            cache_sync_tx.send(()).unwrap_or(());
 
             // A -> EdgeRt + Mgmt Stop and Reprovision Signal Future
             // B -> Restart Signal Future
             match res {
-                Ok(Either::A((x, _))) => Ok((StartApiReturnStatus::Shutdown, x.1)).into_future(),
+                Ok(Either::A((x, _))) => Ok(x).into_future(),
                 Ok(Either::B(_)) => Ok((StartApiReturnStatus::Restart, false)).into_future(),
                 Err(Either::A((err, _))) => Err(err).into_future(),
                 Err(Either::B(_)) => {
@@ -1586,6 +1957,53 @@ where
     Ok((restart_code, should_reprovision))
 }
 
+// Records the next IoT hub as active and removes edgeAgent, so that it is created again with the
+// hostname of that hub. The identity cache holds the module identities of the hub the device is
+// leaving, so it is removed too.
+fn fail_over_hub<M>(
+    hub_failover: &HubFailover,
+    settings: &M::Settings,
+    runtime: &M::ModuleRuntime,
+    tokio_runtime: &mut tokio::runtime::Runtime,
+) -> Result<String, Error>
+where
+    M: MakeModuleRuntime + 'static,
+    for<'r> &'r <M::ModuleRuntime as ModuleRuntime>::Error: Into<ModuleRuntimeErrorReason>,
+{
+    let hub = hub_failover.fail_over()?.to_string();
+
+    info!(
+        "Removing edge runtime module {}...",
+        EDGE_RUNTIME_MODULE_NAME
+    );
+    let remove = runtime
+        .remove(EDGE_RUNTIME_MODULE_NAME)
+        .then(|result| match result {
+            Ok(()) => Ok(()),
+            Err(err) => match (&err).into() {
+                ModuleRuntimeErrorReason::NotFound => Ok(()),
+                ModuleRuntimeErrorReason::PolicyViolation | ModuleRuntimeErrorReason::Other => {
+                    Err(err)
+                }
+            },
+        });
+    tokio_runtime
+        .block_on(remove)
+        .context(ErrorKind::Initialize(
+            InitializeErrorReason::RemoveExistingModules,
+        ))?;
+
+    // Ignore errors from this operation because the cache is only used when IoT Hub can't be
+    // reached, and is replaced once it can
+    let _u = fs::remove_file(
+        Path::new(&settings.homedir())
+            .join(EDGE_SETTINGS_SUBDIR)
+            .join(EDGE_IDENTITY_CACHE_FILENAME),
+    );
+
+    Ok(hub)
+}
+
 fn init_runtime<M>(
     settings: M::Settings,
     tokio_runtime: &mut tokio::runtime::Runtime,
@@ -1665,6 +2083,7 @@ fn dps_x509_provision_init<HC>(
     x509_info: &X509AttestationInfo,
     hybrid_identity_key: Option<Vec<u8>>,
     common_name: &str,
+    state_path: PathBuf,
 ) -> Result<(MemoryKeyStore, DpsX509Provisioning<HC>), Error>
 where
     HC: 'static + ClientImpl,
@@ -1691,7 +2110,9 @@ where
     )
     .context(ErrorKind::Initialize(
         InitializeErrorReason::DpsProvisioningClient,
-    ))?;
+    ))?
+    .with_payload(x509_info.payload().cloned())
+    .with_state_file(state_path);
 
     Ok((memory_hsm, dps_x509))
 }
@@ -1760,6 +2181,7 @@ fn external_provision_payload(key: &[u8]) -> (DerivedKeyStore<MemoryKey>, Memory
     (derived_key_store, memory_key)
 }
 
+#[cfg(not(feature = "soft-crypto"))]
 fn external_provision_tpm(
     hsm_lock: Arc<HsmLock>,
 ) -> Result<(DerivedKeyStore<TpmKey>, TpmKey), Error> {
@@ -1790,6 +2212,21 @@ fn external_provision_tpm(
         })
 }
 
+/// Reads the device's identity key from the soft crypto key store, which stands in for the HSM.
+#[cfg(feature = "soft-crypto")]
+fn external_provision_key_store(
+    homedir: &Path,
+) -> Result<(DerivedKeyStore<MemoryKey>, MemoryKey), Error> {
+    let key = external_provisioning_key_store(homedir)?
+        .get(&KeyIdentity::Device, "primary")
+        .context(ErrorKind::Initialize(
+            InitializeErrorReason::ExternalProvisioningClient(
+                ExternalProvisioningErrorReason::HsmKeyRetrieval,
+            ),
+        ))?;
+    Ok((DerivedKeyStore::new(key.clone()), key))
+}
+
 fn external_provision_x509(
     provisioning_result: &ProvisioningResult,
     hybrid_identity_key: &[u8],
@@ -1818,6 +2255,7 @@ fn dps_symmetric_key_provision_init<HC>(
     provisioning: &Dps,
     hyper_client: HC,
     key: &SymmetricKeyAttestationInfo,
+    state_path: PathBuf,
 ) -> Result<(MemoryKeyStore, DpsSymmetricKeyProvisioning<HC>), Error>
 where
     HC: 'static + ClientImpl,
@@ -1839,7 +2277,9 @@ where
     )
     .context(ErrorKind::Initialize(
         InitializeErrorReason::DpsProvisioningClient,
-    ))?;
+    ))?
+    .with_payload(key.payload().cloned())
+    .with_state_file(state_path);
     Ok((memory_hsm, dps))
 }
 
@@ -1878,6 +2318,7 @@ fn dps_tpm_provision_init<HC>(
     provisioning: &Dps,
     hyper_client: HC,
     tpm_attestation_info: &TpmAttestationInfo,
+    state_path: PathBuf,
 ) -> Result<(Tpm, DpsTpmProvisioning<HC>), Error>
 where
     HC: 'static + ClientImpl,
@@ -1917,7 +2358,9 @@ where
     )
     .context(ErrorKind::Initialize(
         InitializeErrorReason::DpsProvisioningClient,
-    ))?;
+    ))?
+    .with_payload(tpm_attestation_info.payload().cloned())
+    .with_state_file(state_path);
     Ok((tpm, dps))
 }
 
@@ -1960,6 +2403,7 @@ fn start_runtime<K, HC, M>(
     id_man: &HubIdentityManager<DerivedKeyStore<K>, HC, K>,
     hostname: &str,
     device_id: &str,
+    dps_payload: Option<&serde_json::Value>,
     settings: &M::Settings,
     shutdown: Receiver<()>,
 ) -> Result<impl Future<Item = (), Error = Error>, Error>
@@ -1974,7 +2418,18 @@ where
     for<'r> &'r <M::ModuleRuntime as ModuleRuntime>::Error: Into<ModuleRuntimeErrorReason>,
 {
     let spec = settings.agent().clone();
-    let env = build_env(spec.env(), hostname, device_id, settings);
+    let auth_scheme = match id_man.module_auth_type() {
+        CoreAuthType::X509 => X509_AUTH_SCHEME,
+        _ => SAS_AUTH_SCHEME,
+    };
+    let env = build_env(
+        spec.env(),
+        hostname,
+        device_id,
+        dps_payload,
+        auth_scheme,
+        settings,
+    );
     let spec = ModuleSpec::<<M::ModuleRuntime as ModuleRuntime>::Config>::new(
         EDGE_RUNTIME_MODULE_NAME.to_string(),
         spec.type_().to_string(),
@@ -1996,11 +2451,38 @@ where
     Ok(runtime_future)
 }
 
+// Refresh the identity cache once IoT Hub is reachable again, so that the cache is
+// up to date the next time the device starts without connectivity.
+fn start_identity_cache_sync<K, HC>(
+    id_man: HubIdentityManager<DerivedKeyStore<K>, HC, K>,
+    shutdown: Receiver<()>,
+) -> impl Future<Item = (), Error = ()>
+where
+    K: 'static + Sign + Clone + Send + Sync,
+    HC: 'static + ClientImpl,
+{
+    let frequency = Duration::from_secs(IDENTITY_CACHE_SYNC_FREQUENCY_SECS);
+    Interval::new(Instant::now() + frequency, frequency)
+        .map_err(|err| log_failure(Level::Warn, &err))
+        .for_each(move |_| {
+            id_man.sync_identity_cache().then(|result| {
+                if let Err(err) = result {
+                    debug!("Could not refresh the identity cache: {}", err);
+                }
+                Ok(())
+            })
+        })
+        .select(shutdown.then(|_| Ok(())))
+        .then(|_| Ok(()))
+}
+
 // Add the environment variables needed by the EdgeAgent.
 fn build_env<S>(
     spec_env: &HashMap<String, String>,
     hostname: &str,
     device_id: &str,
+    dps_payload: Option<&serde_json::Value>,
+    auth_scheme: &str,
     settings: &S,
 ) -> HashMap<String, String>
 where
@@ -2012,8 +2494,14 @@ where
         GATEWAY_HOSTNAME_KEY.to_string(),
         settings.hostname().to_string().to_lowercase(),
     );
+    if let Some(parent_hostname) = settings.parent_hostname() {
+        env.insert(PARENT_HOSTNAME_KEY.to_string(), parent_hostname.to_string());
+    }
     env.insert(DEVICEID_KEY.to_string(), device_id.to_string());
     env.insert(MODULEID_KEY.to_string(), EDGE_RUNTIME_MODULEID.to_string());
+    if let Some(dps_payload) = dps_payload {
+        env.insert(DPS_PAYLOAD_KEY.to_string(), dps_payload.to_string());
+    }
 
     #[cfg(feature = "runtime-docker")]
     let (workload_uri, management_uri) = (
@@ -2039,7 +2527,7 @@ where
 
     env.insert(WORKLOAD_URI_KEY.to_string(), workload_uri);
     env.insert(MANAGEMENT_URI_KEY.to_string(), management_uri);
-    env.insert(AUTHSCHEME_KEY.to_string(), AUTH_SCHEME.to_string());
+    env.insert(AUTHSCHEME_KEY.to_string(), auth_scheme.to_string());
     env.insert(
         EDGE_RUNTIME_MODE_KEY.to_string(),
         EDGE_RUNTIME_MODE.to_string(),
@@ -2058,11 +2546,13 @@ fn start_management<C, K, HC, M>(
     shutdown: Receiver<()>,
     cert_manager: Arc<CertificateManager<C>>,
     initiate_shutdown_and_reprovision: mpsc::UnboundedSender<()>,
+    key_rotation: KeyRotation<HC, K, M::Settings>,
 ) -> impl Future<Item = (), Error = Error>
 where
     C: CreateCertificate + Clone,
     K: 'static + Sign + Clone + Send + Sync,
-    HC: 'static + ClientImpl + Send + Sync,
+    HC: 'static + ClientImpl + Clone + Send + Sync,
+    M::Settings: 'static + Serialize + Send + Sync,
     M: MakeModuleRuntime,
     M::ModuleRuntime: Authenticator<Request = Request<Body>> + Send + Sync + Clone + 'static,
     <<M::ModuleRuntime as Authenticator>::AuthenticateFuture as Future>::Error: Fail,
@@ -2075,29 +2565,66 @@ where
     let label = "mgmt".to_string();
     let url = settings.listen().management_uri().clone();
     let min_protocol_version = settings.listen().min_tls_version();
+    let client_ca = settings
+        .listen()
+        .management_client_ca()
+        .map(Path::to_path_buf);
 
-    ManagementService::new(runtime, id_man, initiate_shutdown_and_reprovision)
-        .then(move |service| -> Result<_, Error> {
-            let service = service.context(ErrorKind::Initialize(
+    ManagementService::new(
+        runtime,
+        id_man,
+        initiate_shutdown_and_reprovision,
+        key_rotation,
+    )
+    .then(move |service| -> Result<_, Error> {
+        // Without a client CA, TLS connections carry no identity and every request would be
+        // rejected by the management API's authorization policies.
+        if url.scheme() == "https" && client_ca.is_none() {
+            return Err(Error::from(ErrorKind::Initialize(
+                InitializeErrorReason::ManagementClientCaMissing,
+            )));
+        }
+
+        let service = service.context(ErrorKind::Initialize(
+            InitializeErrorReason::ManagementService,
+        ))?;
+        let service = LoggingService::new(label, service);
+
+        let mut tls_params = TlsAcceptorParams::new(&cert_manager, min_protocol_version);
+        if let Some(client_ca) = client_ca {
+            let client_ca = fs::read(client_ca).context(ErrorKind::Initialize(
                 InitializeErrorReason::ManagementService,
             ))?;
-            let service = LoggingService::new(label, service);
+            tls_params =
+                tls_params.with_client_ca(PemCertificate::new(client_ca, None, None, None));
+        }
 
-            let tls_params = TlsAcceptorParams::new(&cert_manager, min_protocol_version);
+        let run = Http::new()
+            .bind_url(url.clone(), service, Some(tls_params))
+            .map_err(|err| {
+                err.context(ErrorKind::Initialize(
+                    InitializeErrorReason::ManagementService,
+                ))
+            })?
+            .run_until(shutdown.map_err(|_| ()))
+            .map_err(|err| Error::from(err.context(ErrorKind::ManagementService)));
+        info!("Listening on {} with 1 thread for management API.", url);
+        Ok(run)
+    })
+    .flatten()
+}
 
-            let run = Http::new()
-                .bind_url(url.clone(), service, Some(tls_params))
-                .map_err(|err| {
-                    err.context(ErrorKind::Initialize(
-                        InitializeErrorReason::ManagementService,
-                    ))
-                })?
-                .run_until(shutdown.map_err(|_| ()))
-                .map_err(|err| Error::from(err.context(ErrorKind::ManagementService)));
-            info!("Listening on {} with 1 thread for management API.", url);
-            Ok(run)
-        })
-        .flatten()
+fn start_metrics(url: &Url, tokio_runtime: &mut tokio::runtime::Runtime) -> Result<(), Error> {
+    let server = Http::new()
+        .bind_url(
+            url.clone(),
+            MetricsService::default(),
+            None::<TlsAcceptorParams<'_, Crypto>>,
+        )
+        .map_err(|err| err.context(ErrorKind::Initialize(InitializeErrorReason::MetricsService)))?;
+    tokio_runtime.spawn(server.run().map_err(|err| log_failure(Level::Warn, &err)));
+    info!("Listening on {} for metrics.", url);
+    Ok(())
 }
 
 fn start_workload<K, C, CE, W, M>(
@@ -2136,8 +2663,9 @@ where
     let label = "work".to_string();
     let url = settings.listen().workload_uri().clone();
     let min_protocol_version = settings.listen().min_tls_version();
+    let rate_limits = settings.listen().workload_rate_limits();
 
-    WorkloadService::new(key_store, crypto.clone(), runtime, config)
+    WorkloadService::new(key_store, crypto.clone(), runtime, config, rate_limits)
         .then(move |service| -> Result<_, Error> {
             let service = service.context(ErrorKind::Initialize(
                 InitializeErrorReason::WorkloadService,
@@ -15,8 +15,8 @@ type ModuleRuntime = edgelet_kube::KubeModuleRuntime<
 type ModuleRuntime = edgelet_shell::ShellModuleRuntime;
 
 pub fn run() -> Result<(), Error> {
-    let settings = app::init()?;
-    let main = super::Main::<ModuleRuntime>::new(settings);
+    let (settings, config_file) = app::init()?;
+    let main = super::Main::<ModuleRuntime>::new(settings).with_config_file(config_file);
 
     main.run_until(signal::shutdown)?;
     Ok(())
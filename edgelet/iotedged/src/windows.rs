@@ -81,8 +81,8 @@ fn run_as_service(_: Vec<OsString>) -> Result<ServiceStatusHandle, Error> {
 
     // initialize iotedged
     info!("Initializing {} service.", IOTEDGED_SERVICE_NAME);
-    let settings = app::init_win_svc()?;
-    let main = super::Main::<ModuleRuntime>::new(settings);
+    let (settings, config_file) = app::init_win_svc()?;
+    let main = super::Main::<ModuleRuntime>::new(settings).with_config_file(config_file);
 
     // tell Windows we're all set
     update_service_state(status_handle, ServiceState::Running)?;
@@ -108,8 +108,8 @@ fn run_as_service(_: Vec<OsString>) -> Result<ServiceStatusHandle, Error> {
 }
 
 pub fn run_as_console() -> Result<(), Error> {
-    let settings = app::init()?;
-    let main = super::Main::<ModuleRuntime>::new(settings);
+    let (settings, config_file) = app::init()?;
+    let main = super::Main::<ModuleRuntime>::new(settings).with_config_file(config_file);
 
     main.run_until(signal::shutdown)?;
 
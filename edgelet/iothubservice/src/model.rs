@@ -11,6 +11,8 @@ pub enum AuthType {
     None,
     Sas,
     X509,
+    /// X.509 authentication with a certificate issued by the device's CA
+    CertificateAuthority,
 }
 
 #[derive(Serialize, Deserialize, Debug, PartialEq)]
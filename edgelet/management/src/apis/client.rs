@@ -7,6 +7,8 @@ pub struct APIClient {
     device_actions_api: Box<dyn crate::apis::DeviceActionsApi>,
     identity_api: Box<dyn crate::apis::IdentityApi>,
     module_api: Box<dyn crate::apis::ModuleApi>,
+    network_api: Box<dyn crate::apis::NetworkApi>,
+    registry_api: Box<dyn crate::apis::RegistryApi>,
     system_information_api: Box<dyn crate::apis::SystemInformationApi>,
 }
 
@@ -23,6 +25,8 @@ impl APIClient {
             )),
             identity_api: Box::new(crate::apis::IdentityApiClient::new(configuration.clone())),
             module_api: Box::new(crate::apis::ModuleApiClient::new(configuration.clone())),
+            network_api: Box::new(crate::apis::NetworkApiClient::new(configuration.clone())),
+            registry_api: Box::new(crate::apis::RegistryApiClient::new(configuration.clone())),
             system_information_api: Box::new(crate::apis::SystemInformationApiClient::new(
                 configuration.clone(),
             )),
@@ -41,6 +45,14 @@ impl APIClient {
         self.module_api.as_ref()
     }
 
+    pub fn network_api(&self) -> &dyn crate::apis::NetworkApi {
+        self.network_api.as_ref()
+    }
+
+    pub fn registry_api(&self) -> &dyn crate::apis::RegistryApi {
+        self.registry_api.as_ref()
+    }
+
     pub fn system_information_api(&self) -> &dyn crate::apis::SystemInformationApi {
         self.system_information_api.as_ref()
     }
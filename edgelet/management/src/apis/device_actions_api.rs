@@ -15,7 +15,7 @@ use futures;
 use futures::{Future, Stream};
 use hyper;
 use serde_json;
-use typed_headers::http;
+use typed_headers::{self, http, mime, HeaderMapExt};
 
 use super::{configuration, Error};
 
@@ -34,6 +34,12 @@ pub trait DeviceActionsApi: Send + Sync {
         &self,
         api_version: &str,
     ) -> Box<dyn Future<Item = (), Error = Error<serde_json::Value>>>;
+
+    fn rotate_device_key(
+        &self,
+        api_version: &str,
+        key: crate::models::RotateDeviceKey,
+    ) -> Box<dyn Future<Item = (), Error = Error<serde_json::Value>> + Send>;
 }
 
 impl<C> DeviceActionsApi for DeviceActionsApiClient<C>
@@ -87,4 +93,58 @@ where
                 .and_then(|_| futures::future::ok(())),
         )
     }
+
+    fn rotate_device_key(
+        &self,
+        api_version: &str,
+        key: crate::models::RotateDeviceKey,
+    ) -> Box<dyn Future<Item = (), Error = Error<serde_json::Value>> + Send> {
+        let configuration: &configuration::Configuration<C> = self.configuration.borrow();
+
+        let method = hyper::Method::POST;
+
+        let query = ::url::form_urlencoded::Serializer::new(String::new())
+            .append_pair("api-version", &api_version.to_string())
+            .finish();
+        let uri_str = format!("/device/key/rotate?{}", query);
+
+        let uri = (configuration.uri_composer)(&configuration.base_path, &uri_str);
+        let serialized = serde_json::to_string(&key).unwrap();
+        let serialized_len = serialized.len();
+
+        let mut req = hyper::Request::builder();
+        req.method(method).uri(uri.unwrap());
+        if let Some(ref user_agent) = configuration.user_agent {
+            req.header(http::header::USER_AGENT, &**user_agent);
+        }
+        let mut req = req
+            .body(hyper::Body::from(serialized))
+            .expect("could not build hyper::Request");
+        req.headers_mut()
+            .typed_insert(&typed_headers::ContentType(mime::APPLICATION_JSON));
+        req.headers_mut()
+            .typed_insert(&typed_headers::ContentLength(serialized_len as u64));
+
+        // send request
+        Box::new(
+            configuration
+                .client
+                .request(req)
+                .map_err(Error::from)
+                .and_then(|resp| {
+                    let (http::response::Parts { status, .. }, body) = resp.into_parts();
+                    body.concat2()
+                        .and_then(move |body| Ok((status, body)))
+                        .map_err(Error::from)
+                })
+                .and_then(|(status, body)| {
+                    if status.is_success() {
+                        Ok(body)
+                    } else {
+                        Err(Error::from((status, &*body)))
+                    }
+                })
+                .and_then(|_| futures::future::ok(())),
+        )
+    }
 }
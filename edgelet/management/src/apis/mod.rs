@@ -53,7 +53,11 @@ pub use self::device_actions_api::{DeviceActionsApi, DeviceActionsApiClient};
 mod identity_api;
 pub use self::identity_api::{IdentityApi, IdentityApiClient};
 mod module_api;
-pub use self::module_api::{ModuleApi, ModuleApiClient};
+pub use self::module_api::{ModuleApi, ModuleApiClient, EXEC_ID_HEADER};
+mod network_api;
+pub use self::network_api::{NetworkApi, NetworkApiClient};
+mod registry_api;
+pub use self::registry_api::{RegistryApi, RegistryApiClient};
 mod system_information_api;
 pub use self::system_information_api::{SystemInformationApi, SystemInformationApiClient};
 
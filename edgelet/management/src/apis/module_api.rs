@@ -12,7 +12,7 @@ use std::borrow::Borrow;
 use std::sync::Arc;
 
 use futures;
-use futures::{Future, Stream};
+use futures::{Future, IntoFuture, Stream};
 use hyper;
 use serde_json;
 use typed_headers::{self, http, mime, HeaderMapExt};
@@ -20,6 +20,9 @@ use url::percent_encoding::{percent_encode, PATH_SEGMENT_ENCODE_SET};
 
 use super::{configuration, Error};
 
+/// The response header that carries the id of a command started by `exec_module`.
+pub const EXEC_ID_HEADER: &str = "exec-id";
+
 pub struct ModuleApiClient<C: hyper::client::connect::Connect> {
     configuration: Arc<configuration::Configuration<C>>,
 }
@@ -41,6 +44,31 @@ pub trait ModuleApi: Send + Sync {
         api_version: &str,
         name: &str,
     ) -> Box<dyn Future<Item = (), Error = Error<serde_json::Value>>>;
+    fn exec_module(
+        &self,
+        api_version: &str,
+        name: &str,
+        cmd: &[String],
+        tty: bool,
+        tty_size: Option<(u16, u16)>,
+    ) -> Box<
+        dyn Future<Item = (String, hyper::upgrade::Upgraded), Error = Error<serde_json::Value>>
+            + Send,
+    >;
+    fn exec_module_resize(
+        &self,
+        api_version: &str,
+        name: &str,
+        exec_id: &str,
+        h: u16,
+        w: u16,
+    ) -> Box<dyn Future<Item = (), Error = Error<serde_json::Value>> + Send>;
+    fn exec_module_status(
+        &self,
+        api_version: &str,
+        name: &str,
+        exec_id: &str,
+    ) -> Box<dyn Future<Item = crate::models::ExecStatus, Error = Error<serde_json::Value>> + Send>;
     fn get_module(
         &self,
         api_version: &str,
@@ -205,6 +233,220 @@ where
         )
     }
 
+    fn exec_module(
+        &self,
+        api_version: &str,
+        name: &str,
+        cmd: &[String],
+        tty: bool,
+        tty_size: Option<(u16, u16)>,
+    ) -> Box<
+        dyn Future<Item = (String, hyper::upgrade::Upgraded), Error = Error<serde_json::Value>>
+            + Send,
+    > {
+        let configuration: &configuration::Configuration<C> = self.configuration.borrow();
+
+        let method = hyper::Method::POST;
+
+        let mut query = ::url::form_urlencoded::Serializer::new(String::new());
+        query.append_pair("api-version", &api_version.to_string());
+        for arg in cmd {
+            query.append_pair("cmd", arg);
+        }
+        query.append_pair("tty", &tty.to_string());
+        if let Some((h, w)) = tty_size {
+            query
+                .append_pair("h", &h.to_string())
+                .append_pair("w", &w.to_string());
+        }
+        let query = query.finish();
+        let uri_str = format!(
+            "/modules/{name}/exec?{}",
+            query,
+            name = percent_encode(name.as_bytes(), PATH_SEGMENT_ENCODE_SET)
+        );
+
+        let uri = (configuration.uri_composer)(&configuration.base_path, &uri_str);
+        // TODO(farcaller): handle error
+        // if let Err(e) = uri {
+        //     return Box::new(futures::future::err(e));
+        // }
+        let mut req = hyper::Request::builder();
+        req.method(method).uri(uri.unwrap());
+        if let Some(ref user_agent) = configuration.user_agent {
+            req.header(http::header::USER_AGENT, &**user_agent);
+        }
+        req.header(http::header::CONNECTION, "Upgrade");
+        req.header(http::header::UPGRADE, "tcp");
+        let req = req
+            .body(hyper::Body::empty())
+            .expect("could not build hyper::Request");
+
+        // send request
+        Box::new(
+            configuration
+                .client
+                .request(req)
+                .map_err(Error::from)
+                .and_then(|resp| {
+                    let status = resp.status();
+                    let exec_id = resp
+                        .headers()
+                        .get(EXEC_ID_HEADER)
+                        .and_then(|value| value.to_str().ok())
+                        .map(ToString::to_string);
+                    if status == hyper::StatusCode::SWITCHING_PROTOCOLS {
+                        futures::future::Either::A(
+                            exec_id
+                                .ok_or_else(|| {
+                                    Error::Api(super::ApiError {
+                                        code: status,
+                                        content: None,
+                                    })
+                                })
+                                .into_future()
+                                .and_then(|exec_id| {
+                                    resp.into_body()
+                                        .on_upgrade()
+                                        .map(|upgraded| (exec_id, upgraded))
+                                        .map_err(Error::from)
+                                }),
+                        )
+                    } else {
+                        futures::future::Either::B(
+                            resp.into_body()
+                                .concat2()
+                                .map_err(Error::from)
+                                .and_then(move |body| Err(Error::from((status, &*body)))),
+                        )
+                    }
+                }),
+        )
+    }
+
+    fn exec_module_resize(
+        &self,
+        api_version: &str,
+        name: &str,
+        exec_id: &str,
+        h: u16,
+        w: u16,
+    ) -> Box<dyn Future<Item = (), Error = Error<serde_json::Value>> + Send> {
+        let configuration: &configuration::Configuration<C> = self.configuration.borrow();
+
+        let method = hyper::Method::POST;
+
+        let query = ::url::form_urlencoded::Serializer::new(String::new())
+            .append_pair("api-version", &api_version.to_string())
+            .append_pair("h", &h.to_string())
+            .append_pair("w", &w.to_string())
+            .finish();
+        let uri_str = format!(
+            "/modules/{name}/exec/{exec_id}/resize?{}",
+            query,
+            name = percent_encode(name.as_bytes(), PATH_SEGMENT_ENCODE_SET),
+            exec_id = percent_encode(exec_id.as_bytes(), PATH_SEGMENT_ENCODE_SET)
+        );
+
+        let uri = (configuration.uri_composer)(&configuration.base_path, &uri_str);
+        // TODO(farcaller): handle error
+        // if let Err(e) = uri {
+        //     return Box::new(futures::future::err(e));
+        // }
+        let mut req = hyper::Request::builder();
+        req.method(method).uri(uri.unwrap());
+        if let Some(ref user_agent) = configuration.user_agent {
+            req.header(http::header::USER_AGENT, &**user_agent);
+        }
+        let req = req
+            .body(hyper::Body::empty())
+            .expect("could not build hyper::Request");
+
+        // send request
+        Box::new(
+            configuration
+                .client
+                .request(req)
+                .map_err(Error::from)
+                .and_then(|resp| {
+                    let (http::response::Parts { status, .. }, body) = resp.into_parts();
+                    body.concat2()
+                        .and_then(move |body| Ok((status, body)))
+                        .map_err(Error::from)
+                })
+                .and_then(|(status, body)| {
+                    if status.is_success() {
+                        Ok(body)
+                    } else {
+                        Err(Error::from((status, &*body)))
+                    }
+                })
+                .and_then(|_| futures::future::ok(())),
+        )
+    }
+
+    fn exec_module_status(
+        &self,
+        api_version: &str,
+        name: &str,
+        exec_id: &str,
+    ) -> Box<dyn Future<Item = crate::models::ExecStatus, Error = Error<serde_json::Value>> + Send>
+    {
+        let configuration: &configuration::Configuration<C> = self.configuration.borrow();
+
+        let method = hyper::Method::GET;
+
+        let query = ::url::form_urlencoded::Serializer::new(String::new())
+            .append_pair("api-version", &api_version.to_string())
+            .finish();
+        let uri_str = format!(
+            "/modules/{name}/exec/{exec_id}?{}",
+            query,
+            name = percent_encode(name.as_bytes(), PATH_SEGMENT_ENCODE_SET),
+            exec_id = percent_encode(exec_id.as_bytes(), PATH_SEGMENT_ENCODE_SET)
+        );
+
+        let uri = (configuration.uri_composer)(&configuration.base_path, &uri_str);
+        // TODO(farcaller): handle error
+        // if let Err(e) = uri {
+        //     return Box::new(futures::future::err(e));
+        // }
+        let mut req = hyper::Request::builder();
+        req.method(method).uri(uri.unwrap());
+        if let Some(ref user_agent) = configuration.user_agent {
+            req.header(http::header::USER_AGENT, &**user_agent);
+        }
+        let req = req
+            .body(hyper::Body::empty())
+            .expect("could not build hyper::Request");
+
+        // send request
+        Box::new(
+            configuration
+                .client
+                .request(req)
+                .map_err(Error::from)
+                .and_then(|resp| {
+                    let (http::response::Parts { status, .. }, body) = resp.into_parts();
+                    body.concat2()
+                        .and_then(move |body| Ok((status, body)))
+                        .map_err(Error::from)
+                })
+                .and_then(|(status, body)| {
+                    if status.is_success() {
+                        Ok(body)
+                    } else {
+                        Err(Error::from((status, &*body)))
+                    }
+                })
+                .and_then(|body| {
+                    let parsed: Result<crate::models::ExecStatus, _> =
+                        serde_json::from_slice(&body);
+                    parsed.map_err(Error::from)
+                }),
+        )
+    }
+
     fn get_module(
         &self,
         api_version: &str,
@@ -0,0 +1,43 @@
+/*
+ * IoT Edge Management API
+ *
+ * No description provided (generated by Swagger Codegen https://github.com/swagger-api/swagger-codegen)
+ *
+ * OpenAPI spec version: 2020-07-07
+ *
+ * Generated by: https://github.com/swagger-api/swagger-codegen.git
+ */
+
+use serde_derive::{Deserialize, Serialize};
+#[allow(unused_imports)]
+use serde_json::Value;
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct ExecStatus {
+    /// The exit code of the command. Not set while the command is running.
+    #[serde(rename = "exitCode", skip_serializing_if = "Option::is_none")]
+    exit_code: Option<i64>,
+}
+
+impl ExecStatus {
+    pub fn new() -> Self {
+        ExecStatus { exit_code: None }
+    }
+
+    pub fn set_exit_code(&mut self, exit_code: i64) {
+        self.exit_code = Some(exit_code);
+    }
+
+    pub fn with_exit_code(mut self, exit_code: i64) -> Self {
+        self.exit_code = Some(exit_code);
+        self
+    }
+
+    pub fn exit_code(&self) -> Option<i64> {
+        self.exit_code
+    }
+
+    pub fn reset_exit_code(&mut self) {
+        self.exit_code = None;
+    }
+}
@@ -1,9 +1,13 @@
 mod config;
 pub use self::config::Config;
+mod disk;
+pub use self::disk::Disk;
 mod env_var;
 pub use self::env_var::EnvVar;
 mod error_response;
 pub use self::error_response::ErrorResponse;
+mod exec_status;
+pub use self::exec_status::ExecStatus;
 mod exit_status;
 pub use self::exit_status::ExitStatus;
 mod identity;
@@ -16,16 +20,30 @@ mod update_identity;
 pub use self::update_identity::UpdateIdentity;
 mod module_details;
 pub use self::module_details::ModuleDetails;
+mod module_event;
+pub use self::module_event::ModuleEvent;
 mod module_list;
 pub use self::module_list::ModuleList;
+mod module_resource_usage;
+pub use self::module_resource_usage::ModuleResourceUsage;
 mod module_spec;
 pub use self::module_spec::ModuleSpec;
+mod network_details;
+pub use self::network_details::NetworkDetails;
+mod network_list;
+pub use self::network_list::NetworkList;
+mod registry_credentials;
+pub use self::registry_credentials::RegistryCredentials;
+mod rotate_device_key;
+pub use self::rotate_device_key::RotateDeviceKey;
 mod runtime_status;
 pub use self::runtime_status::RuntimeStatus;
 mod status;
 pub use self::status::Status;
 mod system_info;
 pub use self::system_info::SystemInfo;
+mod system_resources;
+pub use self::system_resources::SystemResources;
 
 // TODO(farcaller): sort out files
 pub struct File;
@@ -0,0 +1,94 @@
+/*
+ * IoT Edge Management API
+ *
+ * No description provided (generated by Swagger Codegen https://github.com/swagger-api/swagger-codegen)
+ *
+ * OpenAPI spec version: 2020-07-07
+ *
+ * Generated by: https://github.com/swagger-api/swagger-codegen.git
+ */
+
+use serde_derive::{Deserialize, Serialize};
+#[allow(unused_imports)]
+use serde_json::Value;
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ModuleEvent {
+    #[serde(rename = "name")]
+    name: String,
+    /// One of created, started, died, oomKilled, restarted or removed.
+    #[serde(rename = "type")]
+    type_: String,
+    #[serde(rename = "time")]
+    time: String,
+    /// The exit code of the module, for died events.
+    #[serde(rename = "exitCode", skip_serializing_if = "Option::is_none")]
+    exit_code: Option<i64>,
+}
+
+impl ModuleEvent {
+    pub fn new(name: String, type_: String, time: String) -> Self {
+        ModuleEvent {
+            name,
+            type_,
+            time,
+            exit_code: None,
+        }
+    }
+
+    pub fn set_name(&mut self, name: String) {
+        self.name = name;
+    }
+
+    pub fn with_name(mut self, name: String) -> Self {
+        self.name = name;
+        self
+    }
+
+    pub fn name(&self) -> &String {
+        &self.name
+    }
+
+    pub fn set_type(&mut self, type_: String) {
+        self.type_ = type_;
+    }
+
+    pub fn with_type(mut self, type_: String) -> Self {
+        self.type_ = type_;
+        self
+    }
+
+    pub fn type_(&self) -> &String {
+        &self.type_
+    }
+
+    pub fn set_time(&mut self, time: String) {
+        self.time = time;
+    }
+
+    pub fn with_time(mut self, time: String) -> Self {
+        self.time = time;
+        self
+    }
+
+    pub fn time(&self) -> &String {
+        &self.time
+    }
+
+    pub fn set_exit_code(&mut self, exit_code: i64) {
+        self.exit_code = Some(exit_code);
+    }
+
+    pub fn with_exit_code(mut self, exit_code: i64) -> Self {
+        self.exit_code = Some(exit_code);
+        self
+    }
+
+    pub fn exit_code(&self) -> Option<i64> {
+        self.exit_code
+    }
+
+    pub fn reset_exit_code(&mut self) {
+        self.exit_code = None;
+    }
+}
@@ -0,0 +1,190 @@
+/*
+ * IoT Edge Management API
+ *
+ * No description provided (generated by Swagger Codegen https://github.com/swagger-api/swagger-codegen)
+ *
+ * OpenAPI spec version: 2018-06-28
+ *
+ * Generated by: https://github.com/swagger-api/swagger-codegen.git
+ */
+
+use serde_derive::{Deserialize, Serialize};
+#[allow(unused_imports)]
+use serde_json::Value;
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ModuleResourceUsage {
+    #[serde(rename = "name")]
+    name: String,
+    #[serde(rename = "cpu_percent", skip_serializing_if = "Option::is_none")]
+    cpu_percent: Option<f32>,
+    #[serde(rename = "memory_usage", skip_serializing_if = "Option::is_none")]
+    memory_usage: Option<i64>,
+    #[serde(rename = "memory_limit", skip_serializing_if = "Option::is_none")]
+    memory_limit: Option<i64>,
+    #[serde(rename = "network_rx_bytes")]
+    network_rx_bytes: i64,
+    #[serde(rename = "network_tx_bytes")]
+    network_tx_bytes: i64,
+    #[serde(rename = "block_read_bytes")]
+    block_read_bytes: i64,
+    #[serde(rename = "block_write_bytes")]
+    block_write_bytes: i64,
+    #[serde(rename = "pids", skip_serializing_if = "Option::is_none")]
+    pids: Option<i64>,
+}
+
+impl ModuleResourceUsage {
+    pub fn new(
+        name: String,
+        network_rx_bytes: i64,
+        network_tx_bytes: i64,
+        block_read_bytes: i64,
+        block_write_bytes: i64,
+    ) -> Self {
+        ModuleResourceUsage {
+            name,
+            cpu_percent: None,
+            memory_usage: None,
+            memory_limit: None,
+            network_rx_bytes,
+            network_tx_bytes,
+            block_read_bytes,
+            block_write_bytes,
+            pids: None,
+        }
+    }
+
+    pub fn set_name(&mut self, name: String) {
+        self.name = name;
+    }
+
+    pub fn with_name(mut self, name: String) -> Self {
+        self.name = name;
+        self
+    }
+
+    pub fn name(&self) -> &String {
+        &self.name
+    }
+
+    pub fn set_cpu_percent(&mut self, cpu_percent: f32) {
+        self.cpu_percent = Some(cpu_percent);
+    }
+
+    pub fn with_cpu_percent(mut self, cpu_percent: f32) -> Self {
+        self.cpu_percent = Some(cpu_percent);
+        self
+    }
+
+    pub fn cpu_percent(&self) -> Option<f32> {
+        self.cpu_percent
+    }
+
+    pub fn reset_cpu_percent(&mut self) {
+        self.cpu_percent = None;
+    }
+
+    pub fn set_memory_usage(&mut self, memory_usage: i64) {
+        self.memory_usage = Some(memory_usage);
+    }
+
+    pub fn with_memory_usage(mut self, memory_usage: i64) -> Self {
+        self.memory_usage = Some(memory_usage);
+        self
+    }
+
+    pub fn memory_usage(&self) -> Option<i64> {
+        self.memory_usage
+    }
+
+    pub fn reset_memory_usage(&mut self) {
+        self.memory_usage = None;
+    }
+
+    pub fn set_memory_limit(&mut self, memory_limit: i64) {
+        self.memory_limit = Some(memory_limit);
+    }
+
+    pub fn with_memory_limit(mut self, memory_limit: i64) -> Self {
+        self.memory_limit = Some(memory_limit);
+        self
+    }
+
+    pub fn memory_limit(&self) -> Option<i64> {
+        self.memory_limit
+    }
+
+    pub fn reset_memory_limit(&mut self) {
+        self.memory_limit = None;
+    }
+
+    pub fn set_network_rx_bytes(&mut self, network_rx_bytes: i64) {
+        self.network_rx_bytes = network_rx_bytes;
+    }
+
+    pub fn with_network_rx_bytes(mut self, network_rx_bytes: i64) -> Self {
+        self.network_rx_bytes = network_rx_bytes;
+        self
+    }
+
+    pub fn network_rx_bytes(&self) -> i64 {
+        self.network_rx_bytes
+    }
+
+    pub fn set_network_tx_bytes(&mut self, network_tx_bytes: i64) {
+        self.network_tx_bytes = network_tx_bytes;
+    }
+
+    pub fn with_network_tx_bytes(mut self, network_tx_bytes: i64) -> Self {
+        self.network_tx_bytes = network_tx_bytes;
+        self
+    }
+
+    pub fn network_tx_bytes(&self) -> i64 {
+        self.network_tx_bytes
+    }
+
+    pub fn set_block_read_bytes(&mut self, block_read_bytes: i64) {
+        self.block_read_bytes = block_read_bytes;
+    }
+
+    pub fn with_block_read_bytes(mut self, block_read_bytes: i64) -> Self {
+        self.block_read_bytes = block_read_bytes;
+        self
+    }
+
+    pub fn block_read_bytes(&self) -> i64 {
+        self.block_read_bytes
+    }
+
+    pub fn set_block_write_bytes(&mut self, block_write_bytes: i64) {
+        self.block_write_bytes = block_write_bytes;
+    }
+
+    pub fn with_block_write_bytes(mut self, block_write_bytes: i64) -> Self {
+        self.block_write_bytes = block_write_bytes;
+        self
+    }
+
+    pub fn block_write_bytes(&self) -> i64 {
+        self.block_write_bytes
+    }
+
+    pub fn set_pids(&mut self, pids: i64) {
+        self.pids = Some(pids);
+    }
+
+    pub fn with_pids(mut self, pids: i64) -> Self {
+        self.pids = Some(pids);
+        self
+    }
+
+    pub fn pids(&self) -> Option<i64> {
+        self.pids
+    }
+
+    pub fn reset_pids(&mut self) {
+        self.pids = None;
+    }
+}
@@ -0,0 +1,138 @@
+/*
+ * IoT Edge Management API
+ *
+ * No description provided (generated by Swagger Codegen https://github.com/swagger-api/swagger-codegen)
+ *
+ * OpenAPI spec version: 2020-07-07
+ *
+ * Generated by: https://github.com/swagger-api/swagger-codegen.git
+ */
+
+use serde_derive::{Deserialize, Serialize};
+#[allow(unused_imports)]
+use serde_json::Value;
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct NetworkDetails {
+    /// The name of the network.
+    #[serde(rename = "name")]
+    name: String,
+    /// The driver the network was created with.
+    #[serde(rename = "driver", skip_serializing_if = "Option::is_none")]
+    driver: Option<String>,
+    /// Whether the network is restricted from external access.
+    #[serde(rename = "internal", skip_serializing_if = "Option::is_none")]
+    internal: Option<bool>,
+    /// Whether IPv6 is enabled on the network.
+    #[serde(rename = "ipv6", skip_serializing_if = "Option::is_none")]
+    ipv6: Option<bool>,
+    /// The subnets of the network, in CIDR notation.
+    #[serde(rename = "subnets")]
+    subnets: Vec<String>,
+    /// The names of the modules attached to the network.
+    #[serde(rename = "modules")]
+    modules: Vec<String>,
+}
+
+impl NetworkDetails {
+    pub fn new(name: String, subnets: Vec<String>, modules: Vec<String>) -> Self {
+        NetworkDetails {
+            name,
+            driver: None,
+            internal: None,
+            ipv6: None,
+            subnets,
+            modules,
+        }
+    }
+
+    pub fn set_name(&mut self, name: String) {
+        self.name = name;
+    }
+
+    pub fn with_name(mut self, name: String) -> Self {
+        self.name = name;
+        self
+    }
+
+    pub fn name(&self) -> &String {
+        &self.name
+    }
+
+    pub fn set_driver(&mut self, driver: String) {
+        self.driver = Some(driver);
+    }
+
+    pub fn with_driver(mut self, driver: String) -> Self {
+        self.driver = Some(driver);
+        self
+    }
+
+    pub fn driver(&self) -> Option<&str> {
+        self.driver.as_ref().map(AsRef::as_ref)
+    }
+
+    pub fn reset_driver(&mut self) {
+        self.driver = None;
+    }
+
+    pub fn set_internal(&mut self, internal: bool) {
+        self.internal = Some(internal);
+    }
+
+    pub fn with_internal(mut self, internal: bool) -> Self {
+        self.internal = Some(internal);
+        self
+    }
+
+    pub fn internal(&self) -> Option<bool> {
+        self.internal
+    }
+
+    pub fn reset_internal(&mut self) {
+        self.internal = None;
+    }
+
+    pub fn set_ipv6(&mut self, ipv6: bool) {
+        self.ipv6 = Some(ipv6);
+    }
+
+    pub fn with_ipv6(mut self, ipv6: bool) -> Self {
+        self.ipv6 = Some(ipv6);
+        self
+    }
+
+    pub fn ipv6(&self) -> Option<bool> {
+        self.ipv6
+    }
+
+    pub fn reset_ipv6(&mut self) {
+        self.ipv6 = None;
+    }
+
+    pub fn set_subnets(&mut self, subnets: Vec<String>) {
+        self.subnets = subnets;
+    }
+
+    pub fn with_subnets(mut self, subnets: Vec<String>) -> Self {
+        self.subnets = subnets;
+        self
+    }
+
+    pub fn subnets(&self) -> &[String] {
+        &self.subnets
+    }
+
+    pub fn set_modules(&mut self, modules: Vec<String>) {
+        self.modules = modules;
+    }
+
+    pub fn with_modules(mut self, modules: Vec<String>) -> Self {
+        self.modules = modules;
+        self
+    }
+
+    pub fn modules(&self) -> &[String] {
+        &self.modules
+    }
+}
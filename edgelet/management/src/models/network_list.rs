@@ -0,0 +1,38 @@
+/*
+ * IoT Edge Management API
+ *
+ * No description provided (generated by Swagger Codegen https://github.com/swagger-api/swagger-codegen)
+ *
+ * OpenAPI spec version: 2020-07-07
+ *
+ * Generated by: https://github.com/swagger-api/swagger-codegen.git
+ */
+
+use serde_derive::{Deserialize, Serialize};
+#[allow(unused_imports)]
+use serde_json::Value;
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct NetworkList {
+    #[serde(rename = "networks")]
+    networks: Vec<crate::models::NetworkDetails>,
+}
+
+impl NetworkList {
+    pub fn new(networks: Vec<crate::models::NetworkDetails>) -> Self {
+        NetworkList { networks }
+    }
+
+    pub fn set_networks(&mut self, networks: Vec<crate::models::NetworkDetails>) {
+        self.networks = networks;
+    }
+
+    pub fn with_networks(mut self, networks: Vec<crate::models::NetworkDetails>) -> Self {
+        self.networks = networks;
+        self
+    }
+
+    pub fn networks(&self) -> &[crate::models::NetworkDetails] {
+        &self.networks
+    }
+}
@@ -0,0 +1,55 @@
+/*
+ * IoT Edge Management API
+ *
+ * No description provided (generated by Swagger Codegen https://github.com/swagger-api/swagger-codegen)
+ *
+ * OpenAPI spec version: 2020-07-07
+ *
+ * Generated by: https://github.com/swagger-api/swagger-codegen.git
+ */
+
+use serde_derive::{Deserialize, Serialize};
+#[allow(unused_imports)]
+use serde_json::Value;
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RegistryCredentials {
+    /// The username to pull images from the registry with.
+    #[serde(rename = "username")]
+    username: String,
+    /// The password to pull images from the registry with.
+    #[serde(rename = "password")]
+    password: String,
+}
+
+impl RegistryCredentials {
+    pub fn new(username: String, password: String) -> Self {
+        RegistryCredentials { username, password }
+    }
+
+    pub fn set_username(&mut self, username: String) {
+        self.username = username;
+    }
+
+    pub fn with_username(mut self, username: String) -> Self {
+        self.username = username;
+        self
+    }
+
+    pub fn username(&self) -> &String {
+        &self.username
+    }
+
+    pub fn set_password(&mut self, password: String) {
+        self.password = password;
+    }
+
+    pub fn with_password(mut self, password: String) -> Self {
+        self.password = password;
+        self
+    }
+
+    pub fn password(&self) -> &String {
+        &self.password
+    }
+}
@@ -0,0 +1,39 @@
+/*
+ * IoT Edge Management API
+ *
+ * No description provided (generated by Swagger Codegen https://github.com/swagger-api/swagger-codegen)
+ *
+ * OpenAPI spec version: 2020-07-07
+ *
+ * Generated by: https://github.com/swagger-api/swagger-codegen.git
+ */
+
+use serde_derive::{Deserialize, Serialize};
+#[allow(unused_imports)]
+use serde_json::Value;
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RotateDeviceKey {
+    /// The new device key, base64-encoded. IoT Hub must already accept it for the device.
+    #[serde(rename = "primaryKey")]
+    primary_key: String,
+}
+
+impl RotateDeviceKey {
+    pub fn new(primary_key: String) -> Self {
+        RotateDeviceKey { primary_key }
+    }
+
+    pub fn set_primary_key(&mut self, primary_key: String) {
+        self.primary_key = primary_key;
+    }
+
+    pub fn with_primary_key(mut self, primary_key: String) -> Self {
+        self.primary_key = primary_key;
+        self
+    }
+
+    pub fn primary_key(&self) -> &String {
+        &self.primary_key
+    }
+}
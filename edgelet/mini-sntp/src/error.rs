@@ -39,6 +39,7 @@ impl From<failure::Context<ErrorKind>> for Error {
 pub enum ErrorKind {
     BadServerResponse(BadServerResponseReason),
     BindLocalSocket,
+    QueryThreadPanicked,
     ReceiveServerResponse(std::io::Error),
     ResolveNtpPoolHostname(Option<std::io::Error>),
     SendClientRequest(std::io::Error),
@@ -53,6 +54,7 @@ impl std::fmt::Display for ErrorKind {
                 write!(f, "could not parse NTP server response: {}", reason)
             }
             ErrorKind::BindLocalSocket => write!(f, "could not bind local UDP socket"),
+            ErrorKind::QueryThreadPanicked => write!(f, "thread querying NTP server panicked"),
             ErrorKind::ReceiveServerResponse(err) => {
                 write!(f, "could not receive NTP server response: {}", err)
             }
@@ -81,6 +83,7 @@ impl failure::Fail for ErrorKind {
         match self {
             ErrorKind::BadServerResponse(_) => None,
             ErrorKind::BindLocalSocket => None,
+            ErrorKind::QueryThreadPanicked => None,
             ErrorKind::ReceiveServerResponse(err) => Some(err),
             ErrorKind::ResolveNtpPoolHostname(Some(err)) => Some(err),
             ErrorKind::ResolveNtpPoolHostname(None) => None,
@@ -99,6 +102,7 @@ pub enum BadServerResponseReason {
         actual: chrono::DateTime<chrono::Utc>,
     },
     Mode(u8),
+    Stratum(u8),
     VersionNumber(u8),
 }
 
@@ -116,9 +120,15 @@ impl std::fmt::Display for BadServerResponseReason {
             BadServerResponseReason::Mode(mode) => {
                 write!(f, "expected mode to be 4 but it was {}", mode)
             }
+            BadServerResponseReason::Stratum(0) => {
+                write!(f, "server sent a kiss-o'-death packet (stratum 0)")
+            }
+            BadServerResponseReason::Stratum(stratum) => {
+                write!(f, "server is unsynchronized (stratum {})", stratum)
+            }
             BadServerResponseReason::VersionNumber(version_number) => write!(
                 f,
-                "expected version number to be 3 but it was {}",
+                "expected version number to be 3 or 4 but it was {}",
                 version_number
             ),
         }
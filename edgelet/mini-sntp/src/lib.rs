@@ -14,11 +14,76 @@ use failure::ResultExt;
 mod error;
 pub use error::{BadServerResponseReason, Error, ErrorKind};
 
+mod selection;
+pub use selection::ClockSelection;
+
 /// The result of [`query`]
-#[derive(Debug)]
+#[derive(Clone, Copy, Debug)]
 pub struct SntpTimeQueryResult {
     pub local_clock_offset: chrono::Duration,
     pub round_trip_delay: chrono::Duration,
+    pub stratum: u8,
+    pub root_delay: chrono::Duration,
+    pub root_dispersion: chrono::Duration,
+}
+
+/// The NTP protocol version used for client requests.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum NtpVersion {
+    /// NTPv3. Ref: <https://tools.ietf.org/html/rfc2030>
+    V3,
+
+    /// NTPv4. Ref: <https://tools.ietf.org/html/rfc5905>
+    V4,
+}
+
+impl NtpVersion {
+    fn version_number(self) -> u8 {
+        match self {
+            NtpVersion::V3 => 3,
+            NtpVersion::V4 => 4,
+        }
+    }
+}
+
+/// The statistics of a single server computed by [`query_servers`] from several samples.
+#[derive(Clone, Copy, Debug)]
+pub struct ServerStatistics {
+    /// The offset of the sample with the lowest round-trip delay.
+    pub local_clock_offset: chrono::Duration,
+
+    /// The lowest round-trip delay among all samples.
+    pub round_trip_delay: chrono::Duration,
+
+    /// The RMS difference between the offsets of all samples and `local_clock_offset`.
+    pub jitter: chrono::Duration,
+
+    pub stratum: u8,
+
+    /// The maximum error of `local_clock_offset`, ie the half-width of this server's correctness interval.
+    pub root_distance: chrono::Duration,
+}
+
+/// The result of querying a single server address as part of [`query_servers`]
+#[derive(Debug)]
+pub struct ServerQueryResult {
+    pub addr: SocketAddr,
+    pub result: Result<ServerStatistics, Error>,
+}
+
+/// The result of [`query_servers`]
+#[derive(Debug)]
+pub struct MultiServerQueryResult {
+    /// One entry per resolved server address, in the order they were resolved.
+    pub servers: Vec<ServerQueryResult>,
+
+    /// Errors from server names that could not be resolved.
+    pub resolve_errors: Vec<Error>,
+
+    /// The outcome of running NTP's clock selection algorithm over the servers that responded.
+    ///
+    /// This is `None` if no majority of servers agreed on the time.
+    pub selection: Option<ClockSelection>,
 }
 
 /// Executes an SNTP query against the NTPv3 server at the given address.
@@ -34,17 +99,152 @@ where
         .next()
         .ok_or(ErrorKind::ResolveNtpPoolHostname(None))?;
 
-    let socket = UdpSocket::bind("0.0.0.0:0").context(ErrorKind::BindLocalSocket)?;
+    let socket = bind_socket(addr)?;
+    query_with_retries(&socket, addr, NtpVersion::V3)
+}
+
+/// Queries every address of every given server in parallel, taking `num_samples` samples from each,
+/// and runs the NTP clock selection algorithm over the results to discard falsetickers.
+///
+/// Each server may resolve to several addresses, such as every A and AAAA record of a pool hostname.
+///
+/// Ref: <https://tools.ietf.org/html/rfc5905#section-11.2>
+pub fn query_servers<A>(
+    servers: &[A],
+    version: NtpVersion,
+    num_samples: usize,
+) -> MultiServerQueryResult
+where
+    A: ToSocketAddrs,
+{
+    let num_samples = std::cmp::max(num_samples, 1);
+
+    let mut addrs = vec![];
+    let mut resolve_errors = vec![];
+    for server in servers {
+        match server.to_socket_addrs() {
+            Ok(resolved) => {
+                let len_before = addrs.len();
+                for addr in resolved {
+                    if !addrs.contains(&addr) {
+                        addrs.push(addr);
+                    }
+                }
+                if addrs.len() == len_before {
+                    resolve_errors.push(ErrorKind::ResolveNtpPoolHostname(None).into());
+                }
+            }
+            Err(err) => resolve_errors.push(ErrorKind::ResolveNtpPoolHostname(Some(err)).into()),
+        }
+    }
+
+    let handles: Vec<_> = addrs
+        .into_iter()
+        .map(|addr| {
+            let handle = std::thread::spawn(move || query_server(addr, version, num_samples));
+            (addr, handle)
+        })
+        .collect();
+
+    let servers: Vec<_> = handles
+        .into_iter()
+        .map(|(addr, handle)| ServerQueryResult {
+            addr,
+            result: handle
+                .join()
+                .unwrap_or_else(|_| Err(ErrorKind::QueryThreadPanicked.into())),
+        })
+        .collect();
+
+    let selection = selection::select(
+        servers
+            .iter()
+            .filter_map(|server| match &server.result {
+                Ok(statistics) => Some((server.addr, *statistics)),
+                Err(_) => None,
+            })
+            .collect(),
+    );
+
+    MultiServerQueryResult {
+        servers,
+        resolve_errors,
+        selection,
+    }
+}
+
+fn query_server(
+    addr: SocketAddr,
+    version: NtpVersion,
+    num_samples: usize,
+) -> Result<ServerStatistics, Error> {
+    let socket = bind_socket(addr)?;
+
+    let mut samples = Vec::with_capacity(num_samples);
+    for _ in 0..num_samples {
+        samples.push(query_with_retries(&socket, addr, version)?);
+    }
+
+    let best = *samples
+        .iter()
+        .min_by_key(|sample| sample.round_trip_delay)
+        .expect("num_samples is at least 1");
+
+    // Ref: <https://tools.ietf.org/html/rfc5905#section-10>
+    #[allow(clippy::cast_precision_loss)]
+    let jitter = if samples.len() > 1 {
+        let sum_of_squares: f64 = samples
+            .iter()
+            .map(|sample| {
+                let diff = duration_to_secs(sample.local_clock_offset - best.local_clock_offset);
+                diff * diff
+            })
+            .sum();
+        (sum_of_squares / (samples.len() - 1) as f64).sqrt()
+    } else {
+        0.
+    };
+    let jitter = secs_to_duration(jitter);
+
+    let root_distance = std::cmp::max(
+        (best.root_delay + best.round_trip_delay) / 2 + best.root_dispersion + jitter,
+        chrono::Duration::milliseconds(1),
+    );
+
+    Ok(ServerStatistics {
+        local_clock_offset: best.local_clock_offset,
+        round_trip_delay: best.round_trip_delay,
+        jitter,
+        stratum: best.stratum,
+        root_distance,
+    })
+}
+
+fn bind_socket(addr: SocketAddr) -> Result<UdpSocket, Error> {
+    let local_addr = if addr.is_ipv6() {
+        "[::]:0"
+    } else {
+        "0.0.0.0:0"
+    };
+
+    let socket = UdpSocket::bind(local_addr).context(ErrorKind::BindLocalSocket)?;
     socket
         .set_read_timeout(Some(std::time::Duration::from_secs(10)))
         .context(ErrorKind::SetReadTimeoutOnSocket)?;
     socket
         .set_write_timeout(Some(std::time::Duration::from_secs(10)))
         .context(ErrorKind::SetWriteTimeoutOnSocket)?;
+    Ok(socket)
+}
 
+fn query_with_retries(
+    socket: &UdpSocket,
+    addr: SocketAddr,
+    version: NtpVersion,
+) -> Result<SntpTimeQueryResult, Error> {
     let mut num_retries_remaining = 3;
     loop {
-        match query_inner(&socket, addr) {
+        match query_inner(socket, addr, version) {
             Ok(result) => return Ok(result),
             Err(err) => {
                 let is_retriable = match err.kind() {
@@ -68,9 +268,13 @@ where
     }
 }
 
-fn query_inner(socket: &UdpSocket, addr: SocketAddr) -> Result<SntpTimeQueryResult, Error> {
+fn query_inner(
+    socket: &UdpSocket,
+    addr: SocketAddr,
+    version: NtpVersion,
+) -> Result<SntpTimeQueryResult, Error> {
     let request_transmit_timestamp = {
-        let (buf, request_transmit_timestamp) = create_client_request();
+        let (buf, request_transmit_timestamp) = create_client_request(version);
 
         #[cfg(test)]
         std::thread::sleep(std::time::Duration::from_secs(5)); // simulate network delay
@@ -110,11 +314,11 @@ fn query_inner(socket: &UdpSocket, addr: SocketAddr) -> Result<SntpTimeQueryResu
     Ok(result)
 }
 
-fn create_client_request() -> ([u8; 48], chrono::DateTime<chrono::Utc>) {
+fn create_client_request(version: NtpVersion) -> ([u8; 48], chrono::DateTime<chrono::Utc>) {
     let sntp_epoch = sntp_epoch();
 
     let mut buf = [0_u8; 48];
-    buf[0] = 0b00_011_011; // version_number: 3, mode: 3 (client)
+    buf[0] = (version.version_number() << 3) | 0b00_000_011; // version_number, mode: 3 (client)
 
     let transmit_timestamp = chrono::Utc::now();
 
@@ -198,6 +402,16 @@ fn parse_server_response(
         );
     }
 
+    // A stratum of 0 is a "kiss-o'-death" packet telling the client to go away.
+    // Strata above 15 mean the server is unsynchronized.
+    //
+    // Ref: <https://tools.ietf.org/html/rfc5905#section-7.4>
+    if packet.stratum == 0 || packet.stratum > 15 {
+        return Err(
+            ErrorKind::BadServerResponse(BadServerResponseReason::Stratum(packet.stratum)).into(),
+        );
+    }
+
     if packet.mode != 4 {
         return Err(
             ErrorKind::BadServerResponse(BadServerResponseReason::Mode(packet.mode)).into(),
@@ -221,6 +435,10 @@ fn parse_server_response(
 
         round_trip_delay: (destination_timestamp - request_transmit_timestamp)
             - (packet.receive_timestamp - packet.transmit_timestamp),
+
+        stratum: packet.stratum,
+        root_delay: deserialize_short_format(packet.root_delay),
+        root_dispersion: deserialize_short_format(packet.root_dispersion),
     })
 }
 
@@ -315,6 +533,28 @@ fn deserialize_timestamp(
     sntp_epoch + duration_since_sntp_epoch
 }
 
+/// Deserializes an NTP short format value, a 16.16 fixed-point number of seconds.
+fn deserialize_short_format(raw: u32) -> chrono::Duration {
+    let integral_part = i64::from(raw >> 16);
+    let fractional_part = i64::from(raw & 0xffff);
+    chrono::Duration::nanoseconds(
+        integral_part * 1_000_000_000 + ((fractional_part * 1_000_000_000) >> 16),
+    )
+}
+
+#[allow(clippy::cast_precision_loss)]
+fn duration_to_secs(duration: chrono::Duration) -> f64 {
+    duration
+        .num_nanoseconds()
+        .expect("NTP durations can't overflow nanoseconds") as f64
+        / 1_000_000_000.
+}
+
+#[allow(clippy::cast_possible_truncation)]
+fn secs_to_duration(secs: f64) -> chrono::Duration {
+    chrono::Duration::nanoseconds((secs * 1_000_000_000.) as i64)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -324,6 +564,7 @@ mod tests {
         let SntpTimeQueryResult {
             local_clock_offset,
             round_trip_delay,
+            ..
         } = query(&("pool.ntp.org", 123))?;
 
         println!("local clock offset: {}", local_clock_offset);
@@ -344,4 +585,33 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn query_servers_works() {
+        let MultiServerQueryResult {
+            servers, selection, ..
+        } = query_servers(&[("pool.ntp.org", 123)], NtpVersion::V4, 1);
+
+        assert!(!servers.is_empty());
+
+        let selection = selection.expect("pool.ntp.org servers should agree on the time");
+        println!("selection: {:?}", selection);
+
+        assert!(!selection.truechimers.is_empty());
+        assert!(
+            (selection.local_clock_offset - chrono::Duration::seconds(30))
+                .num_seconds()
+                .abs()
+                < 1
+        );
+    }
+
+    #[test]
+    fn deserialize_short_format_works() {
+        assert_eq!(
+            deserialize_short_format(0x0001_8000),
+            chrono::Duration::milliseconds(1500)
+        );
+        assert_eq!(deserialize_short_format(0), chrono::Duration::zero());
+    }
 }
@@ -0,0 +1,217 @@
+use std::net::SocketAddr;
+
+use crate::{duration_to_secs, secs_to_duration, ServerStatistics};
+
+/// The result of NTP's clock selection algorithm over the servers queried by [`query_servers`](crate::query_servers)
+#[derive(Clone, Debug)]
+pub struct ClockSelection {
+    /// Servers whose correctness interval overlaps the intersection agreed upon by a majority of servers.
+    pub truechimers: Vec<SocketAddr>,
+
+    /// Servers that disagreed with the majority and were discarded.
+    pub falsetickers: Vec<SocketAddr>,
+
+    /// The offsets of the truechimers, combined by weighting each by the inverse of its root distance.
+    pub local_clock_offset: chrono::Duration,
+
+    /// Half the width of the intersection interval, ie the maximum error of `local_clock_offset`.
+    pub max_error: chrono::Duration,
+
+    /// The fraction of responding servers that are truechimers, between 0 and 1.
+    pub confidence: f64,
+}
+
+/// Runs the intersection and combine algorithms over the given servers.
+///
+/// Returns `None` if there are no servers, or if no majority of servers have overlapping correctness intervals.
+///
+/// Ref: <https://tools.ietf.org/html/rfc5905#section-11.2.1>
+pub(crate) fn select(candidates: Vec<(SocketAddr, ServerStatistics)>) -> Option<ClockSelection> {
+    let n = candidates.len();
+    if n == 0 {
+        return None;
+    }
+
+    let mut endpoints: Vec<(f64, Endpoint)> = Vec::with_capacity(n * 3);
+    for (_, statistics) in &candidates {
+        let offset = duration_to_secs(statistics.local_clock_offset);
+        let root_distance = duration_to_secs(statistics.root_distance);
+        endpoints.push((offset - root_distance, Endpoint::Lower));
+        endpoints.push((offset, Endpoint::Mid));
+        endpoints.push((offset + root_distance, Endpoint::Upper));
+    }
+    endpoints.sort_by(|(edge1, type1), (edge2, type2)| {
+        edge1
+            .partial_cmp(edge2)
+            .expect("offsets are never NaN")
+            .then(type1.cmp(type2))
+    });
+
+    // Find the smallest number of falsetickers `allow` for which the intervals of the remaining `n - allow` servers
+    // intersect, and which contains the midpoints of at least `n - allow` servers.
+    let (low, high) = (0..).take_while(|allow| 2 * allow < n).find_map(|allow| {
+        #[allow(clippy::cast_possible_wrap)]
+        let majority = (n - allow) as isize;
+
+        let mut found = 0;
+
+        let mut chime = 0;
+        let mut low = None;
+        for &(edge, endpoint) in &endpoints {
+            chime -= endpoint.ty();
+            if chime >= majority {
+                low = Some(edge);
+                break;
+            }
+            if endpoint == Endpoint::Mid {
+                found += 1;
+            }
+        }
+
+        let mut chime = 0;
+        let mut high = None;
+        for &(edge, endpoint) in endpoints.iter().rev() {
+            chime += endpoint.ty();
+            if chime >= majority {
+                high = Some(edge);
+                break;
+            }
+            if endpoint == Endpoint::Mid {
+                found += 1;
+            }
+        }
+
+        match (low, high) {
+            (Some(low), Some(high)) if found <= allow && low <= high => Some((low, high)),
+            _ => None,
+        }
+    })?;
+
+    let mut truechimers = vec![];
+    let mut falsetickers = vec![];
+    let mut weighted_offsets = 0.;
+    let mut weights = 0.;
+    for (addr, statistics) in candidates {
+        let offset = duration_to_secs(statistics.local_clock_offset);
+        let root_distance = duration_to_secs(statistics.root_distance);
+
+        if offset + root_distance < low || offset - root_distance > high {
+            falsetickers.push(addr);
+        } else {
+            truechimers.push(addr);
+
+            let weight = 1. / root_distance;
+            weighted_offsets += offset * weight;
+            weights += weight;
+        }
+    }
+
+    #[allow(clippy::cast_precision_loss)]
+    let confidence = truechimers.len() as f64 / n as f64;
+
+    Some(ClockSelection {
+        truechimers,
+        falsetickers,
+        local_clock_offset: secs_to_duration(weighted_offsets / weights),
+        max_error: secs_to_duration((high - low) / 2.),
+        confidence,
+    })
+}
+
+/// The type of an endpoint of a server's correctness interval. The ordering is used to break ties between
+/// endpoints at the same offset.
+#[derive(Clone, Copy, Debug, Eq, Ord, PartialEq, PartialOrd)]
+enum Endpoint {
+    Lower,
+    Mid,
+    Upper,
+}
+
+impl Endpoint {
+    fn ty(self) -> isize {
+        match self {
+            Endpoint::Lower => -1,
+            Endpoint::Mid => 0,
+            Endpoint::Upper => 1,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn candidate(
+        port: u16,
+        offset_ms: i64,
+        root_distance_ms: i64,
+    ) -> (SocketAddr, ServerStatistics) {
+        (
+            ([127, 0, 0, 1], port).into(),
+            ServerStatistics {
+                local_clock_offset: chrono::Duration::milliseconds(offset_ms),
+                round_trip_delay: chrono::Duration::milliseconds(20),
+                jitter: chrono::Duration::zero(),
+                stratum: 2,
+                root_distance: chrono::Duration::milliseconds(root_distance_ms),
+            },
+        )
+    }
+
+    #[test]
+    fn no_candidates() {
+        assert!(select(vec![]).is_none());
+    }
+
+    #[test]
+    fn single_candidate_is_truechimer() {
+        let selection = select(vec![candidate(1, 500, 50)]).unwrap();
+        assert_eq!(selection.truechimers.len(), 1);
+        assert!(selection.falsetickers.is_empty());
+        assert_eq!(
+            selection.local_clock_offset,
+            chrono::Duration::milliseconds(500)
+        );
+        assert!((selection.confidence - 1.).abs() < std::f64::EPSILON);
+    }
+
+    #[test]
+    fn discards_falseticker() {
+        let selection = select(vec![
+            candidate(1, 100, 50),
+            candidate(2, 120, 50),
+            candidate(3, 90, 50),
+            candidate(4, 30_000, 50),
+        ])
+        .unwrap();
+
+        assert_eq!(selection.truechimers.len(), 3);
+        assert_eq!(selection.falsetickers, vec![([127, 0, 0, 1], 4).into()]);
+        assert!(
+            (selection.local_clock_offset - chrono::Duration::milliseconds(103))
+                .num_milliseconds()
+                .abs()
+                <= 1
+        );
+        assert!((selection.confidence - 0.75).abs() < std::f64::EPSILON);
+    }
+
+    #[test]
+    fn weights_by_root_distance() {
+        let selection = select(vec![candidate(1, 0, 10), candidate(2, 10, 90)]).unwrap();
+
+        assert_eq!(selection.truechimers.len(), 2);
+        assert!(
+            (selection.local_clock_offset - chrono::Duration::milliseconds(1))
+                .num_microseconds()
+                .unwrap()
+                .abs()
+                <= 1
+        );
+    }
+
+    #[test]
+    fn no_majority() {
+        assert!(select(vec![candidate(1, 0, 10), candidate(2, 1_000, 10)]).is_none());
+    }
+}
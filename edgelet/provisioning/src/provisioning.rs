@@ -12,11 +12,12 @@ use futures::future::Either;
 use futures::{future, Future, IntoFuture};
 use log::info;
 use serde_derive::{Deserialize, Serialize};
-use serde_json;
+use serde_json::{self, Value};
 use url::Url;
 
 use dps::registration::{DpsAuthKind, DpsClient, DpsTokenSource};
 use edgelet_core::crypto::{Activate, KeyIdentity, KeyStore, MemoryKey, MemoryKeyStore};
+use edgelet_core::metrics;
 use edgelet_core::ProvisioningResult as CoreProvisioningResult;
 use edgelet_hsm::tpm::{TpmKey, TpmKeyStore};
 use edgelet_http::client::{Client as HttpClient, ClientImpl};
@@ -164,6 +165,8 @@ pub struct ProvisioningResult {
     reconfigure: ReprovisioningStatus,
     #[serde(skip_serializing_if = "Option::is_none")]
     credentials: Option<Credentials>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    payload: Option<Value>,
 }
 
 impl ProvisioningResult {
@@ -180,6 +183,7 @@ impl ProvisioningResult {
             sha256_thumbprint: sha256_thumbprint.map(&str::to_owned),
             reconfigure,
             credentials,
+            payload: None,
         }
     }
 
@@ -190,6 +194,17 @@ impl ProvisioningResult {
     pub fn credentials(&self) -> Option<&Credentials> {
         self.credentials.as_ref()
     }
+
+    /// The custom allocation payload that DPS returned with the device's assignment, if any
+    pub fn payload(&self) -> Option<&Value> {
+        self.payload.as_ref()
+    }
+
+    /// Sets the custom allocation payload that DPS returned with the device's assignment.
+    pub fn with_payload(mut self, payload: Option<Value>) -> Self {
+        self.payload = payload;
+        self
+    }
 }
 
 impl CoreProvisioningResult for ProvisioningResult {
@@ -249,6 +264,7 @@ impl Provision for ManualProvisioning {
                 reconfigure: ReprovisioningStatus::DeviceDataNotUpdated,
                 sha256_thumbprint: None,
                 credentials: None,
+                payload: None,
             })
             .map_err(|err| Error::from(err.context(ErrorKind::Provision)));
         Box::new(result.into_future())
@@ -436,7 +452,8 @@ where
                     hub_name: device_provisioning_info.hub_name().to_string(),
                     reconfigure,
                     sha256_thumbprint: None,
-                    credentials: Some(credentials)
+                    credentials: Some(credentials),
+                    payload: None,
                 })
             });
 
@@ -470,6 +487,8 @@ where
     registration_id: String,
     hsm_tpm_ek: HsmTpmKey,
     hsm_tpm_srk: HsmTpmKey,
+    payload: Option<Value>,
+    state_file: Option<PathBuf>,
 }
 
 impl<C> DpsTpmProvisioning<C>
@@ -499,9 +518,24 @@ where
             registration_id,
             hsm_tpm_ek,
             hsm_tpm_srk,
+            payload: None,
+            state_file: None,
         };
         Ok(result)
     }
+
+    /// Sets the custom allocation payload that is sent to DPS with the registration request.
+    pub fn with_payload(mut self, payload: Option<Value>) -> Self {
+        self.payload = payload;
+        self
+    }
+
+    /// Sets the file that keeps the state of the DPS registration, so that a registration that
+    /// is interrupted is resumed.
+    pub fn with_state_file(mut self, state_file: PathBuf) -> Self {
+        self.state_file = Some(state_file);
+        self
+    }
 }
 
 impl<C> Provision for DpsTpmProvisioning<C>
@@ -522,12 +556,16 @@ where
             self.registration_id.clone(),
             DpsAuthKind::Tpm { ek, srk },
             key_activator,
-        );
+        )
+        .map(|c| {
+            c.with_payload(self.payload.clone())
+                .with_state_file(self.state_file.clone())
+        });
 
         let d = match c {
             Ok(c) => Either::A(
                 c.register()
-                    .map(|(device_id, hub_name, _substatus)| {
+                    .map(|(device_id, hub_name, _substatus, payload)| {
                         info!(
                             "DPS registration assigned device \"{}\" in hub \"{}\"",
                             device_id, hub_name
@@ -542,6 +580,7 @@ where
                             reconfigure: ReprovisioningStatus::InitialAssignment,
                             sha256_thumbprint: None,
                             credentials: None,
+                            payload,
                         }
                     })
                     .map_err(|err| Error::from(err.context(ErrorKind::Provision))),
@@ -565,6 +604,8 @@ where
     client: HttpClient<C, DpsTokenSource<MemoryKey>>,
     scope_id: String,
     registration_id: String,
+    payload: Option<Value>,
+    state_file: Option<PathBuf>,
 }
 
 impl<C> DpsSymmetricKeyProvisioning<C>
@@ -589,9 +630,24 @@ where
             client,
             scope_id,
             registration_id,
+            payload: None,
+            state_file: None,
         };
         Ok(result)
     }
+
+    /// Sets the custom allocation payload that is sent to DPS with the registration request.
+    pub fn with_payload(mut self, payload: Option<Value>) -> Self {
+        self.payload = payload;
+        self
+    }
+
+    /// Sets the file that keeps the state of the DPS registration, so that a registration that
+    /// is interrupted is resumed.
+    pub fn with_state_file(mut self, state_file: PathBuf) -> Self {
+        self.state_file = Some(state_file);
+        self
+    }
 }
 
 impl<C> Provision for DpsSymmetricKeyProvisioning<C>
@@ -610,12 +666,16 @@ where
             self.registration_id.clone(),
             DpsAuthKind::SymmetricKey,
             key_activator,
-        );
+        )
+        .map(|c| {
+            c.with_payload(self.payload.clone())
+                .with_state_file(self.state_file.clone())
+        });
 
         let d = match c {
             Ok(c) => Either::A(
                 c.register()
-                    .map(|(device_id, hub_name, substatus)| {
+                    .map(|(device_id, hub_name, substatus, payload)| {
                         info!(
                             "DPS registration assigned device \"{}\" in hub \"{}\"",
                             device_id, hub_name
@@ -630,6 +690,7 @@ where
                             reconfigure,
                             sha256_thumbprint: None,
                             credentials: None,
+                            payload,
                         }
                     })
                     .map_err(|err| Error::from(err.context(ErrorKind::Provision))),
@@ -652,6 +713,8 @@ where
     client: HttpClient<C, DpsTokenSource<MemoryKey>>,
     scope_id: String,
     registration_id: String,
+    payload: Option<Value>,
+    state_file: Option<PathBuf>,
 }
 
 impl<C> DpsX509Provisioning<C>
@@ -676,9 +739,24 @@ where
             client,
             scope_id,
             registration_id,
+            payload: None,
+            state_file: None,
         };
         Ok(result)
     }
+
+    /// Sets the custom allocation payload that is sent to DPS with the registration request.
+    pub fn with_payload(mut self, payload: Option<Value>) -> Self {
+        self.payload = payload;
+        self
+    }
+
+    /// Sets the file that keeps the state of the DPS registration, so that a registration that
+    /// is interrupted is resumed.
+    pub fn with_state_file(mut self, state_file: PathBuf) -> Self {
+        self.state_file = Some(state_file);
+        self
+    }
 }
 
 impl<C> Provision for DpsX509Provisioning<C>
@@ -697,12 +775,16 @@ where
             self.registration_id.clone(),
             DpsAuthKind::X509,
             key_activator,
-        );
+        )
+        .map(|c| {
+            c.with_payload(self.payload.clone())
+                .with_state_file(self.state_file.clone())
+        });
 
         let d = match c {
             Ok(c) => Either::A(
                 c.register()
-                    .map(|(device_id, hub_name, substatus)| {
+                    .map(|(device_id, hub_name, substatus, payload)| {
                         info!(
                             "DPS registration assigned device \"{}\" in hub \"{}\"",
                             device_id, hub_name
@@ -719,6 +801,7 @@ where
                             reconfigure,
                             sha256_thumbprint: None,
                             credentials: None,
+                            payload,
                         }
                     })
                     .map_err(|err| Error::from(err.context(ErrorKind::Provision))),
@@ -845,6 +928,7 @@ where
                 })
                 .or_else(move |err| {
                     log_failure(Level::Warn, &err);
+                    metrics::observe_provisioning_failure();
                     match Self::restore(path_on_err) {
                         Ok(prov_result) => Either::A(future::ok(prov_result)),
                         Err(err) => Either::B(future::err(err)),
@@ -865,6 +949,7 @@ mod tests {
     use edgelet_core::{Error as CoreError, ManualDeviceConnectionString};
     use external_provisioning::models::{Credentials, DeviceProvisioningInfo};
     use failure::Fail;
+    use serde_json::json;
     use std::fmt::{self, Display};
     use tempdir::TempDir;
     use tokio;
@@ -886,6 +971,7 @@ mod tests {
                 reconfigure: ReprovisioningStatus::DeviceDataUpdated,
                 sha256_thumbprint: None,
                 credentials: None,
+                payload: Some(json!({ "siteId": "site 7" })),
             }))
         }
 
@@ -909,6 +995,7 @@ mod tests {
                 reconfigure: ReprovisioningStatus::DeviceDataUpdated,
                 sha256_thumbprint: None,
                 credentials: None,
+                payload: None,
             }))
         }
 
@@ -1037,6 +1124,7 @@ mod tests {
                     BackupProvisioning::<ManualProvisioning>::restore(file_path_clone).unwrap();
                 assert_eq!(result.device_id(), "TestDevice");
                 assert_eq!(result.hub_name(), "TestHub");
+                assert_eq!(result.payload(), Some(&json!({ "siteId": "site 7" })));
                 Ok::<_, Error>(())
             });
         tokio::runtime::current_thread::Runtime::new()
@@ -1209,6 +1297,7 @@ mod tests {
             reconfigure: ReprovisioningStatus::DeviceDataNotUpdated,
             sha256_thumbprint: None,
             credentials: None,
+            payload: None,
         })
         .unwrap();
         assert_eq!(
@@ -0,0 +1,214 @@
+// Copyright (c) Microsoft. All rights reserved.
+
+#![deny(rust_2018_idioms, warnings)]
+#![deny(clippy::all, clippy::pedantic)]
+
+use std::fs;
+
+use futures::Future;
+use hyper::server::conn::Http;
+use serde_json::json;
+use tempdir::TempDir;
+use tokio::runtime::Runtime;
+use url::Url;
+
+use edgelet_core::crypto::{KeyIdentity, KeyStore, MemoryKeyStore};
+use edgelet_core::ProvisioningResult as CoreProvisioningResult;
+use edgelet_hsm::Crypto;
+use edgelet_http::{HyperExt, TlsAcceptorParams};
+use edgelet_http_external_provisioning::ExternalProvisioningClient;
+use external_provisioning_server::ExternalProvisioningServer;
+use provisioning::error::{ErrorKind, ExternalProvisioningErrorReason};
+use provisioning::provisioning::{
+    AuthType, CredentialSource, ExternalProvisioning, Provision, ReprovisioningStatus,
+};
+
+struct TestServer {
+    server: ExternalProvisioningServer,
+    provisioning: ExternalProvisioning<ExternalProvisioningClient, MemoryKeyStore>,
+    runtime: Runtime,
+    _dir: TempDir,
+}
+
+impl TestServer {
+    fn start(dir: TempDir, config: &serde_json::Value) -> Self {
+        let config_path = dir.path().join("device.json");
+        fs::write(&config_path, config.to_string()).unwrap();
+        let server = ExternalProvisioningServer::new(config_path).unwrap();
+
+        let listener = Http::new()
+            .bind_url(
+                Url::parse("http://127.0.0.1:0").unwrap(),
+                server.clone(),
+                None::<TlsAcceptorParams<'_, Crypto>>,
+            )
+            .unwrap();
+        let url = Url::parse(&format!("http://127.0.0.1:{}", listener.port().unwrap())).unwrap();
+
+        let mut runtime = Runtime::new().unwrap();
+        runtime.spawn(
+            listener
+                .run()
+                .map_err(|err| panic!("external provisioning server failed: {}", err)),
+        );
+
+        let provisioning =
+            ExternalProvisioning::new(ExternalProvisioningClient::new(&url).unwrap());
+
+        TestServer {
+            server,
+            provisioning,
+            runtime,
+            _dir: dir,
+        }
+    }
+}
+
+fn symmetric_key_device(hub_name: &str, substatus: &str) -> serde_json::Value {
+    json!({
+        "hubName": hub_name,
+        "deviceId": "device01",
+        "credentials": {
+            "authType": "symmetric-key",
+            "source": "payload",
+            "key": "cGFzczEyMzQ=",
+        },
+        "status": "assigned",
+        "substatus": substatus,
+    })
+}
+
+#[test]
+fn provisions_symmetric_key_from_payload() {
+    let dir = TempDir::new("external-provisioning").unwrap();
+    let mut test = TestServer::start(
+        dir,
+        &symmetric_key_device("hub.azure-devices.net", "initialAssignment"),
+    );
+
+    let key_store = MemoryKeyStore::new();
+    let result = test
+        .runtime
+        .block_on(test.provisioning.provision(key_store.clone()))
+        .unwrap();
+
+    assert_eq!("hub.azure-devices.net", result.hub_name());
+    assert_eq!("device01", result.device_id());
+    assert_eq!(
+        ReprovisioningStatus::InitialAssignment,
+        result.reconfigure()
+    );
+    let credentials = result.credentials().unwrap();
+    assert_eq!(&CredentialSource::Payload, credentials.source());
+    match credentials.auth_type() {
+        AuthType::SymmetricKey(key) => assert_eq!(Some(&b"pass1234"[..]), key.key()),
+        AuthType::X509(_) => panic!("unexpected authentication type"),
+    }
+    assert!(key_store.get(&KeyIdentity::Device, "primary").is_ok());
+}
+
+#[test]
+fn provisions_x509_from_payload() {
+    let dir = TempDir::new("external-provisioning").unwrap();
+    fs::write(dir.path().join("device01.cert.pem"), "CERTIFICATE").unwrap();
+    fs::write(dir.path().join("device01.key.pem"), "PRIVATE KEY").unwrap();
+    let mut test = TestServer::start(
+        dir,
+        &json!({
+            "hubName": "hub.azure-devices.net",
+            "deviceId": "device01",
+            "credentials": {
+                "authType": "x509",
+                "source": "payload",
+                "identityCert": "device01.cert.pem",
+                "identityPrivateKey": "device01.key.pem",
+            },
+        }),
+    );
+
+    let result = test
+        .runtime
+        .block_on(test.provisioning.provision(MemoryKeyStore::new()))
+        .unwrap();
+
+    match result.credentials().unwrap().auth_type() {
+        AuthType::X509(x509) => {
+            assert_eq!(base64::encode("CERTIFICATE"), x509.identity_cert());
+            assert_eq!(base64::encode("PRIVATE KEY"), x509.identity_private_key());
+        }
+        AuthType::SymmetricKey(_) => panic!("unexpected authentication type"),
+    }
+}
+
+#[test]
+fn reprovisioned_device_moves_to_another_hub() {
+    let dir = TempDir::new("external-provisioning").unwrap();
+    let mut config = symmetric_key_device("hub.azure-devices.net", "initialAssignment");
+    config["reprovisioned"] =
+        symmetric_key_device("otherhub.azure-devices.net", "deviceDataMigrated");
+    let mut test = TestServer::start(dir, &config);
+
+    let result = test
+        .runtime
+        .block_on(test.provisioning.provision(MemoryKeyStore::new()))
+        .unwrap();
+    assert_eq!("hub.azure-devices.net", result.hub_name());
+
+    test.runtime
+        .block_on(test.provisioning.reprovision())
+        .unwrap();
+    assert!(test.server.reprovisioned());
+
+    let result = test
+        .runtime
+        .block_on(test.provisioning.provision(MemoryKeyStore::new()))
+        .unwrap();
+    assert_eq!("otherhub.azure-devices.net", result.hub_name());
+    assert_eq!(
+        ReprovisioningStatus::DeviceDataMigrated,
+        result.reconfigure()
+    );
+}
+
+#[test]
+fn error_responses_fail_provisioning() {
+    let dir = TempDir::new("external-provisioning").unwrap();
+    let mut config = symmetric_key_device("hub.azure-devices.net", "initialAssignment");
+    config["faults"] = json!([
+        {
+            "operation": "getDeviceProvisioningInformation",
+            "statusCode": 503,
+            "count": 1,
+        },
+        {
+            "operation": "reprovisionDevice",
+            "statusCode": 500,
+        },
+    ]);
+    let mut test = TestServer::start(dir, &config);
+
+    let err = test
+        .runtime
+        .block_on(test.provisioning.provision(MemoryKeyStore::new()))
+        .unwrap_err();
+    assert_eq!(
+        &ErrorKind::ExternalProvisioning(ExternalProvisioningErrorReason::ProvisioningFailure),
+        err.kind()
+    );
+
+    let result = test
+        .runtime
+        .block_on(test.provisioning.provision(MemoryKeyStore::new()))
+        .unwrap();
+    assert_eq!("hub.azure-devices.net", result.hub_name());
+
+    let err = test
+        .runtime
+        .block_on(test.provisioning.reprovision())
+        .unwrap_err();
+    assert_eq!(
+        &ErrorKind::ExternalProvisioning(ExternalProvisioningErrorReason::ReprovisioningFailure),
+        err.kind()
+    );
+    assert!(!test.server.reprovisioned());
+}